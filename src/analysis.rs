@@ -0,0 +1,386 @@
+/// Analysis of media files: the `debug` command and its library counterpart
+///
+/// `dissect_file` prints the dissection with the requested checks, as the command line does.
+/// `analyze_file` returns the same facts as a `MediaSummary` without printing anything, and
+/// `report_file` the structures of the file as a `DissectionReport` tree. `dissect_bytes` prints
+/// the dissection of a file held in memory, `dissect_files` the dissection of several files with
+/// a summary of all.
+use crate::batch_errors::BatchErrors;
+use crate::dissection_report::DissectionReport;
+use crate::dissector_builder::DissectorBuilder;
+use crate::file_links::unique_files;
+use crate::finding::Severity;
+use crate::health_score::collect_issues;
+use crate::media_dissector::MediaDissector;
+use crate::media_source::MediaSource;
+use crate::media_summary::MediaSummary;
+use crate::options::{DebugOptions, TimelineFormat};
+use crate::output;
+use crate::path_display::{display_path, long_path};
+use crate::progress_events::{self, FileProgress};
+use crate::sandbox;
+use owo_colors::OwoColorize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::UNIX_EPOCH;
+
+/// Summary of a media file with the share of the file the dissector interpreted
+pub fn analyze_file(file_path: &Path) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(file_path))?;
+    analyze_source(&mut file, file_path)
+}
+
+/// `analyze_file` for a source that is already open, named `file_path` in the summary
+pub fn analyze_source(file: &mut dyn MediaSource, file_path: &Path) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+    let dissector = DissectorBuilder::new().build_for_file(file)?;
+    let mut summary = file_summary(file, file_path, dissector.as_ref())?;
+    summary.coverage = Some(dissector.coverage(file)?);
+    Ok(summary)
+}
+
+/// Structured dissection of a media file, for rendering or inspection without printing
+pub fn report_file(file_path: &Path) -> Result<DissectionReport, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(file_path))?;
+    let dissector = DissectorBuilder::new().build_for_file(&mut file)?;
+    file_report(&mut file, file_path, dissector.as_ref())
+}
+
+/// Print the dissection of a file and run the checks selected in `options`
+pub fn dissect_file(file_path: &Path, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    options.common.apply(|| {
+        if sandbox::enabled() {
+            sandbox::check_options(options)?;
+        }
+        let progress = FileProgress::start(file_path, 1, 1);
+        let result = match &options.forensic {
+            | Some(forensic) => crate::forensic::with_evidence(file_path, options, forensic, || dissect(file_path, options, &progress, false, |_| ())),
+            | None => dissect(file_path, options, &progress, false, |_| ()),
+        };
+        progress.done(&result);
+        result
+    })
+}
+
+/// Print the dissection of several files, each under a header, followed by a summary of all
+///
+/// The files are dissected in parallel on `options.jobs` threads (one per CPU if not set). Each
+/// file's output is collected and printed as a whole in the order of `file_paths`, as soon as the
+/// files before it are done. The summary lists the format and the warnings and errors of every
+/// file, then counts the files analyzed, the detected formats and the issues (findings above info
+/// level, as `triage` counts them). A file that cannot be analyzed does not stop the others; the
+/// error is printed in its section and the call fails after the summary. Paths that lead to a
+/// file that is already part of the batch (hard links, symbolic links) are dissected once, under
+/// the first path, and listed as links in the summary. A single file is dissected exactly as
+/// `dissect_file` does; with `--report` the reports follow one another without headers or summary.
+pub fn dissect_files(file_paths: &[PathBuf], options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    dissect_batch(file_paths, BatchErrors::default(), options)
+}
+
+/// `dissect_files` for files collected from directories, with the paths the walk could not read
+///
+/// Files that cannot be read are added to `errors` rather than counted as failed; all of them are
+/// listed in the summary, and the call fails with the I/O exit status unless `--continue-on-error`
+/// is set.
+pub fn dissect_batch(file_paths: &[PathBuf], errors: BatchErrors, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    options.common.apply(|| dissect_each(file_paths, errors, options))
+}
+
+fn dissect_each(file_paths: &[PathBuf], mut errors: BatchErrors, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if let [file_path] = file_paths
+        && errors.is_empty()
+    {
+        return output::buffered(|| dissect_file(file_path, options));
+    }
+    if file_paths.is_empty() && errors.is_empty() {
+        return Err("no media files to analyze".into());
+    }
+    if options.forensic.is_some() {
+        return Err("--forensic writes one evidence report and takes a single file".into());
+    }
+    if sandbox::enabled() {
+        sandbox::check_options(options)?;
+    }
+
+    let (unique_paths, links) = unique_files(file_paths);
+    let file_paths = unique_paths.as_slice();
+    let total = file_paths.len();
+    let mut formats: Vec<(String, usize)> = Vec::new();
+    let mut rollup: Vec<String> = Vec::new();
+    let mut issues = 0;
+    let mut failed = 0;
+    let mut unreadable = 0;
+    let mut print_outcome = |file_path: &Path, outcome: FileOutcome| {
+        output::buffered(|| out!("{}", outcome.output));
+        if !outcome.unreadable.is_empty() {
+            unreadable += 1;
+            errors.extend(outcome.unreadable);
+            return;
+        }
+        match outcome.result {
+            | Ok(Some(facts)) => {
+                match formats.iter_mut().find(|(format, _)| *format == facts.format) {
+                    | Some((_, count)) => *count += 1,
+                    | None => formats.push((facts.format.clone(), 1)),
+                }
+                issues += facts.warnings + facts.errors;
+                rollup.push(format!("{}: {}, {} warning(s), {} error(s)", display_path(file_path), facts.format, facts.warnings, facts.errors));
+            }
+            | Ok(None) => {}
+            | Err(error) => {
+                rollup.push(format!("{}: failed ({})", display_path(file_path), error));
+                failed += 1;
+            }
+        }
+    };
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(options.jobs.unwrap_or(0)).build()?;
+    let (sender, receiver) = mpsc::channel();
+    pool.in_place_scope(|scope| {
+        for (index, file_path) in file_paths.iter().enumerate() {
+            let sender = sender.clone();
+            scope.spawn(move |_| {
+                // Worker threads do not inherit the options of the calling thread
+                let _ = sender.send((index, options.common.apply(|| dissect_in_batch(file_path, index, total, options))));
+            });
+        }
+        drop(sender);
+
+        // Print in the order of the arguments; outcomes of later files wait for the earlier ones
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (index, outcome) in receiver {
+            pending.insert(index, outcome);
+            while let Some(outcome) = pending.remove(&next) {
+                print_outcome(&file_paths[next], outcome);
+                next += 1;
+            }
+        }
+    });
+
+    output::buffered(|| {
+        if options.report.is_some() {
+            return;
+        }
+        outln!("\n{}", "Summary:".bright_cyan());
+        for line in &rollup {
+            outln!("  {}", line);
+        }
+        outln!("  Files analyzed: {}", total - failed - unreadable);
+        if failed > 0 {
+            outln!("  Files failed: {}", failed);
+        }
+        errors.print_summary("  ");
+        if !links.is_empty() {
+            outln!("  Linked paths: {} (analyzed once, under the first path)", links.len());
+            for link in &links {
+                outln!("    {}", link);
+            }
+        }
+        formats.sort_by(|(a_format, a), (b_format, b)| b.cmp(a).then_with(|| a_format.cmp(b_format)));
+        let formats: Vec<String> = formats.iter().map(|(format, count)| format!("{} ({})", format, count)).collect();
+        outln!("  Formats: {}", formats.join(", "));
+        outln!("  Issues: {}", issues);
+    });
+    if failed > 0 {
+        return Err(format!("{} of {} files could not be analyzed", failed, total).into());
+    }
+    errors.finish()
+}
+
+/// Format and issue counts of one file of `dissect_files`, all that its summary needs
+#[derive(Serialize, Deserialize)]
+struct FileFacts {
+    format: String,
+    warnings: usize,
+    errors: usize,
+}
+
+impl FileFacts {
+    fn of(summary: &MediaSummary) -> Self {
+        let issues = collect_issues(summary);
+        let count = |severity: Severity| issues.iter().filter(|issue| issue.finding.severity == severity).count();
+        FileFacts { format: summary.format.clone(), warnings: count(Severity::Warning), errors: count(Severity::Error) }
+    }
+}
+
+/// Output and result of one file of `dissect_files`, passed from the worker thread that dissected it
+struct FileOutcome {
+    output: String,
+    result: Result<Option<FileFacts>, String>,
+    /// The file, if it could not be read
+    unreadable: BatchErrors,
+}
+
+fn dissect_in_batch(file_path: &Path, index: usize, total: usize, options: &DebugOptions) -> FileOutcome {
+    let mut unreadable = BatchErrors::default();
+    let (result, output) = output::captured(|| {
+        if options.report.is_none() {
+            if index > 0 {
+                outln!();
+            }
+            outln!("{}", format!("==> {} ({}/{}) <==", display_path(file_path), index + 1, total).bright_cyan());
+        }
+        let progress = FileProgress::start(file_path, index + 1, total);
+        let result = dissect(file_path, options, &progress, true, |summary| summary.as_ref().map(FileFacts::of));
+        progress.done(&result);
+        if let Err(error) = &result {
+            outln!("{}", format!("Error: {}", error).bright_red());
+            unreadable.record(file_path, &**error);
+        }
+        result.map_err(|error| error.to_string())
+    });
+    FileOutcome { output, result, unreadable }
+}
+
+/// Print the dissection of a file held in memory, as `dissect_file` does for a file on disk
+///
+/// The data is shown as "<memory>"; files that options write next to the dissected file (sidecar,
+/// HTML timeline) are named after it in the current directory. `options.forensic` is ignored.
+pub fn dissect_bytes(data: &[u8], options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    options.common.apply(|| {
+        let file_path = Path::new("<memory>");
+        let progress = FileProgress::start(file_path, 1, 1);
+        let result = dissect_source(&mut Cursor::new(data), file_path, options, &progress, false).map(|_| ());
+        progress.done(&result);
+        result
+    })
+}
+
+/// Open the file and print the dissection, in a worker process with `--sandbox`; `keep` turns the
+/// summary into the result, which has to cross the process boundary
+fn dissect<T: Serialize + DeserializeOwned>(file_path: &Path, options: &DebugOptions, progress: &FileProgress, summarize: bool, keep: impl FnOnce(Option<MediaSummary>) -> T) -> Result<T, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(file_path))?;
+    if sandbox::enabled() {
+        return sandbox::isolated(&mut file, |file| dissect_source(file, file_path, options, progress, summarize).map(keep));
+    }
+    dissect_source(&mut file, file_path, options, progress, summarize).map(keep)
+}
+
+/// Print the dissection; the summary is returned if `summarize` is set or the options needed it
+fn dissect_source(archive: &mut dyn MediaSource, file_path: &Path, options: &DebugOptions, progress: &FileProgress, summarize: bool) -> Result<Option<MediaSummary>, Box<dyn std::error::Error>> {
+    // With --inner, dissect a member of a ZIP bundle (as "bundle.zip!member.mp3") instead of the file
+    let mut member = match &options.inner {
+        | Some(name) => Some(crate::zip_archive::ZipArchive::read(archive)?.open_member(archive, name)?),
+        | None => None,
+    };
+    let (file, file_path): (&mut dyn MediaSource, _) = match (&mut member, &options.inner) {
+        | (Some(member), Some(name)) => {
+            let mut file_name = file_path.file_name().unwrap_or_default().to_os_string();
+            file_name.push("!");
+            file_name.push(name.rsplit('/').next().unwrap_or(name));
+            (&mut member.file, file_path.with_file_name(file_name))
+        }
+        | _ => (archive, file_path.to_path_buf()),
+    };
+    let file_path = &file_path;
+
+    // Build appropriate dissector based on file content
+    let builder = DissectorBuilder::new();
+    let dissector = builder.build_for_file(file)?;
+
+    // With --report, the structured report replaces the dissection
+    if let Some(format) = options.report {
+        let report = file_report(file, file_path, dissector.as_ref())?;
+        crate::dissection_report::print_report(&report, format)?;
+        return Ok(None);
+    }
+
+    // Print file info
+    outln!("Analyzing file: {}", display_path(file_path));
+    outln!("Detected format: {} ({})", dissector.media_type(), dissector.name());
+
+    // Perform dissection with options
+    dissector.dissect_with_options(file, options)?;
+
+    // Report how much of the file the dissector interpreted
+    let coverage = dissector.coverage(file)?;
+    crate::coverage::print_coverage(&coverage);
+
+    // Check the links in URL frames if requested
+    if options.check_urls {
+        crate::url_checker::check_urls(file)?;
+    }
+
+    // Verify the CRCs of the MPEG audio frames if requested
+    if let Some(report) = options.verify_crc {
+        crate::mpeg_crc::verify_crc(file, report)?;
+    }
+
+    // Analyze the MPEG frame bitrates if requested
+    if options.bitrates {
+        crate::mpeg_bitrate::print_bitrate_report(file)?;
+    }
+
+    // Draw the chapter timeline if requested
+    match options.timeline {
+        | Some(TimelineFormat::Ascii) => crate::chapter_timeline::print_timeline(file)?,
+        | Some(TimelineFormat::Html) => crate::chapter_timeline::write_html_timeline(file, file_path)?,
+        | None => {}
+    }
+
+    // List the MP4 seek points if requested
+    if options.seek_points {
+        crate::isobmff_seek_points::print_seek_points(file)?;
+    }
+
+    // Map the MP4 sample byte ranges over time if requested
+    if let Some(interval_seconds) = options.chunk_map {
+        crate::isobmff_chunk_map::print_chunk_map(file, interval_seconds)?;
+    }
+
+    // Write the GEOB objects if requested
+    if let Some(dir) = &options.extract_geob {
+        crate::id3v2_general_object_frame::extract_general_objects(file, dir)?;
+    }
+
+    // Break the file size down if requested
+    let sizes = if options.sizes {
+        let tree = dissector.size_tree(file)?;
+        crate::size_tree::print_size_tree(&tree);
+        Some(tree)
+    } else {
+        None
+    };
+
+    // The summary feeds the sidecar file and the progress events
+    if options.sidecar.is_none() && !progress_events::enabled() && !summarize {
+        return Ok(None);
+    }
+    let mut summary = file_summary(file, file_path, dissector.as_ref())?;
+    summary.coverage = Some(coverage);
+    summary.sizes = sizes;
+    progress.summary(&summary);
+
+    // Write sidecar summary if requested
+    if let Some(sidecar_options) = &options.sidecar {
+        let sidecar_path = crate::sidecar_writer::write_sidecar(file_path, &summary, sidecar_options)?;
+        outln!("\nSidecar written: {}", display_path(&sidecar_path));
+    }
+
+    Ok(Some(summary))
+}
+
+fn file_report(file: &mut dyn MediaSource, file_path: &Path, dissector: &dyn MediaDissector) -> Result<DissectionReport, Box<dyn std::error::Error>> {
+    Ok(DissectionReport {
+        path: file_path.to_string_lossy().into_owned(),
+        format: dissector.media_type().to_string(),
+        dissector: dissector.name().to_string(),
+        root: dissector.report(file)?,
+    })
+}
+
+/// Summary of the dissector with the path, size and modification time of the file
+fn file_summary(file: &mut dyn MediaSource, file_path: &Path, dissector: &dyn MediaDissector) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut summary = dissector.summarize(file)?;
+    summary.path = file_path.to_string_lossy().into_owned();
+    summary.file_size = file_size;
+    // Members of ZIP bundles and memory buffers have no modification time of their own
+    summary.modified = fs::metadata(long_path(file_path)).and_then(|metadata| metadata.modified()).ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|duration| duration.as_secs());
+    Ok(summary)
+}
@@ -0,0 +1,112 @@
+/// Export of attached pictures (`extract artwork`)
+///
+/// Writes the image of every APIC frame, including the chapter images inside CHAP (and CTOC)
+/// frames, to a directory. Files are named by picture type, chapter images additionally by the
+/// element ID of their chapter ("cover-front.jpg", "chp3-other.png"), with the extension taken
+/// from the MIME type.
+use crate::id3v2_attached_picture_frame::AttachedPictureFrame;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::path_display::{display_path, safe_filename, unused_path};
+use std::fs::{self, File};
+use std::path::Path;
+
+/// Write all attached pictures of the file at `path` into `dir`
+///
+/// Existing files are kept: a number is appended to the name of the new file instead. Pictures
+/// that are only linked ("-->" as MIME type) have no image data and are skipped.
+pub fn extract_artwork(path: &Path, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    let mut pictures = Vec::new();
+    collect_pictures(&tag.frames, None, &mut pictures);
+    if pictures.is_empty() {
+        return Err("no attached pictures (APIC)".into());
+    }
+
+    fs::create_dir_all(dir)?;
+    let mut written = 0;
+    for (element_id, picture) in pictures {
+        let location = match element_id {
+            | Some(element_id) => format!("chapter '{}'", element_id),
+            | None => "tag".to_string(),
+        };
+        if picture.mime_type == "-->" {
+            outln!("Skipped linked picture of {}: {}", location, String::from_utf8_lossy(&picture.picture_data));
+            continue;
+        }
+        let mut name = picture_type_name(picture.picture_type);
+        // Element IDs are free text, a "/" in them does not start a directory
+        if let Some(element_id) = element_id.and_then(|element_id| safe_filename(&element_id.replace(['/', '\\'], "_"))) {
+            name = format!("{}-{}", element_id, name);
+        }
+        let output = unused_path(dir, &format!("{}.{}", name, extension(picture)));
+        fs::write(&output, &picture.picture_data)?;
+        written += 1;
+        outln!("Picture written: {} ({} of {}, {} bytes)", display_path(&output), picture.picture_type_description(), location, picture.picture_data.len());
+    }
+    outln!("{} picture(s) written to {}", written, display_path(dir));
+    Ok(())
+}
+
+/// APIC frames with the element ID of the chapter or table of contents that contains them
+fn collect_pictures<'a>(frames: &'a [Id3v2Frame], element_id: Option<&'a str>, pictures: &mut Vec<(Option<&'a str>, &'a AttachedPictureFrame)>) {
+    for frame in frames {
+        match &frame.content {
+            | Some(Id3v2FrameContent::Picture(picture)) => pictures.push((element_id, picture)),
+            | Some(Id3v2FrameContent::Chapter(chapter)) => collect_pictures(&chapter.sub_frames, Some(&chapter.element_id), pictures),
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => collect_pictures(&toc.sub_frames, Some(&toc.element_id), pictures),
+            | _ => {}
+        }
+    }
+}
+
+/// Short file name for a picture type
+fn picture_type_name(picture_type: u8) -> String {
+    let name = match picture_type {
+        | 0x00 => "other",
+        | 0x01 => "file-icon",
+        | 0x02 => "other-file-icon",
+        | 0x03 => "cover-front",
+        | 0x04 => "cover-back",
+        | 0x05 => "leaflet",
+        | 0x06 => "media",
+        | 0x07 => "lead-artist",
+        | 0x08 => "artist",
+        | 0x09 => "conductor",
+        | 0x0A => "band",
+        | 0x0B => "composer",
+        | 0x0C => "lyricist",
+        | 0x0D => "recording-location",
+        | 0x0E => "during-recording",
+        | 0x0F => "during-performance",
+        | 0x10 => "screen-capture",
+        | 0x11 => "fish",
+        | 0x12 => "illustration",
+        | 0x13 => "band-logo",
+        | 0x14 => "publisher-logo",
+        | other => return format!("type-{}", other),
+    };
+    name.to_string()
+}
+
+/// File extension for the MIME type, or for the image signature if the MIME type is unknown
+fn extension(picture: &AttachedPictureFrame) -> &'static str {
+    let mime_type = picture.mime_type.to_ascii_lowercase();
+    let subtype = mime_type.strip_prefix("image/").unwrap_or(&mime_type);
+    match subtype {
+        | "jpeg" | "jpg" | "pjpeg" => "jpg",
+        | "png" => "png",
+        | "gif" => "gif",
+        | "bmp" | "x-bmp" | "x-ms-bmp" => "bmp",
+        | "webp" => "webp",
+        | "tiff" => "tif",
+        | _ => match picture.picture_data.as_slice() {
+            | [0xFF, 0xD8, 0xFF, ..] => "jpg",
+            | [0x89, b'P', b'N', b'G', ..] => "png",
+            | [b'G', b'I', b'F', b'8', ..] => "gif",
+            | [b'B', b'M', ..] => "bmp",
+            | _ => "bin",
+        },
+    }
+}
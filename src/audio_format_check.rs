@@ -0,0 +1,279 @@
+/// Cross-check of the audio format declared by the container against the codec
+///
+/// Players take the sample rate and channel count either from the container (mp4a sample entry,
+/// WAVE fmt chunk) or from the codec (AudioSpecificConfig in the esds box, MPEG frame headers).
+/// When the two disagree, the audio plays too fast or too slow ("chipmunk audio") or with the
+/// wrong channel layout, depending on the player.
+use crate::finding::{Finding, Severity};
+use crate::isobmff_box::{BoxHeader, find_path, read_boxes, read_children, read_payload};
+use crate::isobmff_demux::{BitReader, decoder_config, find_child, mp4a_children, sample_entry};
+use crate::isobmff_samples::track_samples;
+use crate::isobmff_tracks::track_id;
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::{MpegFrameHeader, find_first_frame};
+use crate::riff_chunk::{RiffFile, read_chunk_payload};
+use std::io::{Read, SeekFrom};
+
+/// Sampling frequencies of the 4-bit index of an AudioSpecificConfig
+const AAC_SAMPLE_RATES: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+/// Audio bytes of a WAVE data chunk searched for an MPEG frame header
+const MPEG_PROBE_SIZE: u64 = 64 * 1024;
+
+/// Sample rate and channel count of an AudioSpecificConfig
+pub struct AudioSpecificConfig {
+    pub object_type: u32,
+    pub sample_rate: u32,
+    /// Output rate of HE-AAC (SBR) signalled in the config
+    pub extension_rate: Option<u32>,
+    /// Channel configuration; 0 means the channels are defined in a program config element
+    pub channel_config: u32,
+}
+
+/// Mismatches between the mp4a sample entries and the codec configuration of every track
+pub fn isobmff_format_findings(file: &mut dyn MediaSource) -> Result<Vec<Finding>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let file_boxes = read_boxes(file, 0, file_size)?;
+    let Some(moov) = file_boxes.iter().find(|box_header| box_header.box_type == "moov") else {
+        return Ok(Vec::new());
+    };
+
+    let mut findings = Vec::new();
+    for trak in read_children(file, moov)?.into_iter().filter(|child| child.box_type == "trak") {
+        let Some(tkhd) = read_children(file, &trak)?.into_iter().find(|child| child.box_type == "tkhd") else {
+            continue;
+        };
+        let Some(track_id) = track_id(&read_payload(file, &tkhd)?) else {
+            continue;
+        };
+        let Some(stsd) = find_path(file, &trak, &["mdia", "minf", "stbl", "stsd"])? else {
+            continue;
+        };
+        let stsd = read_payload(file, &stsd)?;
+        let Some((entry_type, entry)) = sample_entry(&stsd) else {
+            continue;
+        };
+        if entry_type != "mp4a" {
+            continue;
+        }
+        let location = format!("track {}", track_id);
+        let (declared_rate, declared_channels) = mp4a_format(entry);
+        let Some((object_type, config)) = mp4a_children(entry).and_then(|children| find_child(children, b"esds")).and_then(|esds| decoder_config(esds.get(4..)?))
+        else {
+            continue;
+        };
+
+        match object_type {
+            | 0x40 | 0x66..=0x68 => {
+                let Some(config) = config.and_then(parse_audio_specific_config) else {
+                    continue;
+                };
+                findings.extend(check_aac(&location, declared_rate, declared_channels, &config));
+            }
+            | 0x69 | 0x6B => {
+                let Some(header) = first_mp3_header(file, &file_boxes, moov, &trak, track_id)? else {
+                    continue;
+                };
+                findings.extend(check_mpeg(&location, "mp4a sample entry", declared_rate, declared_channels as u32, &header));
+            }
+            | _ => {}
+        }
+    }
+    Ok(findings)
+}
+
+/// Sample rate and channel count of an mp4a sample entry (QuickTime sound description v0-v2)
+pub fn mp4a_format(entry: &[u8]) -> (u32, u16) {
+    let read_u16 = |pos: usize| entry.get(pos..pos + 2).map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+    let read_u32 = |pos: usize| entry.get(pos..pos + 4).map_or(0, |bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    if read_u16(16) == 2 {
+        // Version 2 keeps the real values after the fixed fields
+        let rate = entry.get(40..48).map_or(0.0, |bytes| f64::from_be_bytes(bytes.try_into().unwrap_or_default()));
+        return (rate as u32, read_u32(48) as u16);
+    }
+    // 16.16 fixed point
+    (read_u32(32) >> 16, read_u16(24))
+}
+
+pub fn parse_audio_specific_config(config: &[u8]) -> Option<AudioSpecificConfig> {
+    let mut bits = BitReader::new(config);
+    let read_rate = |bits: &mut BitReader| -> Option<u32> {
+        match bits.read(4)? {
+            | 15 => bits.read(24),
+            | index => AAC_SAMPLE_RATES.get(index as usize).copied(),
+        }
+    };
+    let object_type = bits.object_type()?;
+    let sample_rate = read_rate(&mut bits)?;
+    let channel_config = bits.read(4)?;
+    // HE-AAC (SBR) and HE-AAC v2 (PS) give the output rate before the core object type
+    let extension_rate = if object_type == 5 || object_type == 29 {
+        read_rate(&mut bits)
+    } else {
+        None
+    };
+    Some(AudioSpecificConfig { object_type, sample_rate, extension_rate, channel_config })
+}
+
+/// Channels of an MPEG-4 channel configuration
+pub fn aac_channels(channel_config: u32) -> Option<u32> {
+    match channel_config {
+        | 1..=6 => Some(channel_config),
+        | 7 | 12 | 14 => Some(8),
+        | 11 => Some(7),
+        | 13 => Some(24),
+        | _ => None,
+    }
+}
+
+fn check_aac(location: &str, declared_rate: u32, declared_channels: u16, config: &AudioSpecificConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let rate_matches = declared_rate == config.sample_rate || config.extension_rate == Some(declared_rate);
+    if declared_rate > 0 && !rate_matches {
+        // Implicit SBR: HE-AAC decoders double the rate they find in a config without extension
+        let implicit_sbr = config.extension_rate.is_none() && declared_rate == config.sample_rate * 2;
+        let (severity, consequence) = if implicit_sbr {
+            (Severity::Info, "correct only for HE-AAC with implicit SBR, plain AAC plays at the wrong speed")
+        } else {
+            (Severity::Warning, "players disagree on the playback speed")
+        };
+        findings.push(Finding::new(
+            severity,
+            location,
+            format!("mp4a sample entry declares {} Hz, the AudioSpecificConfig {} Hz; {}", declared_rate, config.sample_rate, consequence),
+        ));
+    }
+    if let Some(channels) = aac_channels(config.channel_config) {
+        // Parametric stereo (HE-AAC v2) decodes a mono core to two channels
+        let parametric_stereo = config.object_type == 29 && channels == 1 && declared_channels == 2;
+        if declared_channels > 0 && declared_channels as u32 != channels && !parametric_stereo {
+            findings.push(Finding::new(
+                Severity::Warning,
+                location,
+                format!("mp4a sample entry declares {} channels, the AudioSpecificConfig {}", declared_channels, channels),
+            ));
+        }
+    }
+    findings
+}
+
+/// Mismatches between a declared format and the header of the first MPEG audio frame
+fn check_mpeg(location: &str, container: &str, declared_rate: u32, declared_channels: u32, header: &MpegFrameHeader) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if declared_rate > 0 && declared_rate != header.sample_rate {
+        findings.push(Finding::new(
+            Severity::Warning,
+            location,
+            format!("{} declares {} Hz, the MPEG frames {} Hz; players disagree on the playback speed", container, declared_rate, header.sample_rate),
+        ));
+    }
+    // Channel mode 3 is single channel
+    let channels = if header.channel_mode == 3 {
+        1
+    } else {
+        2
+    };
+    if declared_channels > 0 && declared_channels != channels {
+        findings.push(Finding::new(
+            Severity::Warning,
+            location,
+            format!("{} declares {} channels, the MPEG frames {}", container, declared_channels, channels),
+        ));
+    }
+    findings
+}
+
+fn first_mp3_header(file: &mut dyn MediaSource, file_boxes: &[BoxHeader], moov: &BoxHeader, trak: &BoxHeader, track_id: u32) -> Result<Option<MpegFrameHeader>, Box<dyn std::error::Error>> {
+    let Some(sample) = track_samples(file, file_boxes, moov, trak, track_id)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let mut bytes = [0u8; 4];
+    file.seek(SeekFrom::Start(sample.offset))?;
+    file.read_exact(&mut bytes)?;
+    Ok(MpegFrameHeader::parse(&bytes))
+}
+
+/// Inconsistencies of the WAVE fmt chunk and mismatches with the MPEG frames of compressed data
+pub fn wave_format_findings(file: &mut dyn MediaSource, riff: &RiffFile) -> Result<Vec<Finding>, Box<dyn std::error::Error>> {
+    let Some(chunk) = riff.chunk("fmt ") else {
+        return Ok(Vec::new());
+    };
+    let payload = read_chunk_payload(file, chunk)?;
+    if payload.len() < 16 {
+        return Ok(Vec::new());
+    }
+    let read_u16 = |pos: usize| u16::from_le_bytes([payload[pos], payload[pos + 1]]);
+    let read_u32 = |pos: usize| u32::from_le_bytes([payload[pos], payload[pos + 1], payload[pos + 2], payload[pos + 3]]);
+    let format_tag = read_u16(0);
+    let channels = read_u16(2);
+    let sample_rate = read_u32(4);
+    let byte_rate = read_u32(8);
+    let block_align = read_u16(12);
+    let bits_per_sample = read_u16(14);
+    let extensible = format_tag == 0xFFFE && payload.len() >= 26;
+    let effective_tag = if extensible {
+        read_u16(24)
+    } else {
+        format_tag
+    };
+
+    let mut findings = Vec::new();
+    if extensible {
+        let channel_mask = read_u32(20);
+        if channel_mask != 0 && channel_mask.count_ones() != channels as u32 {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "fmt",
+                format!("channel mask 0x{:08X} names {} speakers for {} channels", channel_mask, channel_mask.count_ones(), channels),
+            ));
+        }
+    }
+    match effective_tag {
+        | 0x0001 | 0x0003 => {
+            let expected_align = channels as u32 * bits_per_sample.div_ceil(8) as u32;
+            if block_align as u32 != expected_align {
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    "fmt",
+                    format!("block align is {} bytes, {} channels of {} bits need {}", block_align, channels, bits_per_sample, expected_align),
+                ));
+            }
+            if byte_rate as u64 != sample_rate as u64 * block_align as u64 {
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    "fmt",
+                    format!(
+                        "byte rate is {}, {} Hz with {}-byte blocks need {}; players that time by the byte rate play at the wrong speed",
+                        byte_rate,
+                        sample_rate,
+                        block_align,
+                        sample_rate as u64 * block_align as u64
+                    ),
+                ));
+            }
+        }
+        | 0x0050 | 0x0055 => {
+            if let Some(data) = riff.chunk("data") {
+                let mut probe = Vec::new();
+                file.seek(SeekFrom::Start(data.payload_start()))?;
+                (&mut *file).take(data.size.min(MPEG_PROBE_SIZE)).read_to_end(&mut probe)?;
+                if let Some((_, header)) = find_first_frame(&probe) {
+                    findings.extend(check_mpeg("fmt", "fmt chunk", sample_rate, channels as u32, &header));
+                }
+            }
+        }
+        | _ => {}
+    }
+    Ok(findings)
+}
+
+/// Print the container/codec mismatches of the file, if there are any
+pub fn print_format_findings(findings: &[Finding]) {
+    if findings.is_empty() {
+        return;
+    }
+    outln!("\nContainer/Codec Format:");
+    for finding in findings {
+        outln!("  {}", finding);
+    }
+}
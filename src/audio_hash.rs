@@ -0,0 +1,70 @@
+/// Audio-only content hashing
+///
+/// Hashes only the audio payload of a file so retagging does not change the result:
+/// the ID3v2 tag and the APEv2/Lyrics3/ID3v1 trailers of MP3 files and everything but
+/// the `mdat` boxes of ISO BMFF files are skipped. Unknown formats are hashed as a whole.
+use crate::id3v2_tag::parse_tag_header;
+use crate::isobmff_box::read_boxes;
+use crate::media_source::MediaSource;
+use crate::trailer_tags::find_trailers;
+use sha2::{Digest, Sha256};
+use std::io::SeekFrom;
+
+/// SHA-256 of the audio payload as lowercase hex
+pub fn audio_hash(file: &mut dyn MediaSource) -> Result<String, Box<dyn std::error::Error>> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    for (start, end) in audio_ranges(file)? {
+        file.seek(SeekFrom::Start(start))?;
+        let mut remaining = end - start;
+        while remaining > 0 {
+            let chunk = remaining.min(buffer.len() as u64) as usize;
+            file.read_exact(&mut buffer[..chunk])?;
+            hasher.update(&buffer[..chunk]);
+            remaining -= chunk as u64;
+        }
+    }
+
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Byte ranges (start, end) holding the audio payload
+pub fn audio_ranges(file: &mut dyn MediaSource) -> Result<Vec<(u64, u64)>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut header = [0u8; 10];
+    file.seek(SeekFrom::Start(0))?;
+    if file_size < header.len() as u64 {
+        return Ok(vec![(0, file_size)]);
+    }
+    file.read_exact(&mut header)?;
+
+    if &header[4..8] == b"ftyp" {
+        return mdat_ranges(file, file_size);
+    }
+
+    let start = match parse_tag_header(&header) {
+        | Some((major, _minor, flags, size)) => {
+            let footer = if major == 4 && flags & 0x10 != 0 {
+                10
+            } else {
+                0
+            };
+            (10 + size as u64 + footer).min(file_size)
+        }
+        | None => 0,
+    };
+
+    // APEv2, Lyrics3 and ID3v1 blocks after the audio
+    let end = find_trailers(file, start)?.first().map_or(file_size, |block| block.offset);
+    Ok(vec![(start, end)])
+}
+
+/// Payload ranges of all top-level `mdat` boxes
+fn mdat_ranges(file: &mut dyn MediaSource, file_size: u64) -> Result<Vec<(u64, u64)>, Box<dyn std::error::Error>> {
+    Ok(read_boxes(file, 0, file_size)?
+        .iter()
+        .filter(|box_header| box_header.box_type == "mdat")
+        .map(|box_header| (box_header.payload_start(), box_header.end()))
+        .collect())
+}
@@ -0,0 +1,360 @@
+/// Baseline of the tags of a directory (`baseline save`, `baseline check`)
+///
+/// Archives have to notice when files change after ingest. `baseline save` records for every
+/// media file below a directory its tag frames, each with its value and a SHA-256 of its data,
+/// and the SHA-256 of the audio payload. `baseline check` reads the directory again and reports
+/// new and missing files, added and removed frames, changed values and changed hashes.
+///
+/// Frames are keyed by their ID and what tells frames of one ID apart (the TXXX description, the
+/// COMM language and description, the APIC picture type, the UFID and PRIV owner, the CHAP
+/// element ID), so moving frames around changes nothing. MP4 files are recorded with the items
+/// of their `ilst` box; files of other formats with their audio hash only. Paths are stored
+/// relative to the directory, so an archive can be checked after it moved; bytes of file names
+/// that are no UTF-8 are escaped, so every path keeps a key of its own.
+use crate::audio_hash::audio_hash;
+use crate::batch_errors::BatchErrors;
+use crate::dissector_builder::DissectorBuilder;
+use crate::file_collector::collect_files;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::isobmff_box::{find_path, read_boxes, read_children, read_payload};
+use crate::media_source::MediaSource;
+use crate::path_display::display_path;
+use crate::progress_events::FileProgress;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Version of the baseline file format (2 escapes file names, see `path_key`)
+const BASELINE_VERSION: u32 = 2;
+
+/// Recorded state of the media files of a directory
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    /// Records by path relative to the directory, as made by `path_key`
+    pub files: BTreeMap<String, FileRecord>,
+}
+
+/// Recorded state of one media file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRecord {
+    /// Format as detected by the dissectors (e.g. "ID3v2.4", "ISO BMFF")
+    pub format: String,
+    /// SHA-256 of the audio payload (tags excluded)
+    pub audio_sha256: String,
+    /// Frames or `ilst` items by key
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub frames: BTreeMap<String, FrameRecord>,
+}
+
+/// Recorded state of one frame or `ilst` item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameRecord {
+    /// Value of text, URL, comment and user-defined frames and of text items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// SHA-256 of the frame data or item payload
+    pub sha256: String,
+}
+
+/// Record the media files below `dir` and write the baseline to `output`
+pub fn save_baseline(dir: &Path, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut errors = BatchErrors::default();
+    let recorded = record_directory(dir, &mut errors)?;
+    let baseline = Baseline { version: BASELINE_VERSION, files: recorded.files };
+    fs::write(output, serde_json::to_string_pretty(&baseline)? + "\n")?;
+    outln!("Saved the baseline of {} media file(s) in {} to {} ({} other file(s) skipped)", baseline.files.len(), display_path(dir), display_path(output), recorded.skipped);
+    errors.print_summary("");
+    errors.finish()
+}
+
+/// Compare the media files below `dir` with the baseline in `against` and report every change
+///
+/// Fails if anything changed, so scheduled checks can alert on the exit status.
+pub fn check_baseline(dir: &Path, against: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut baseline: Baseline = serde_json::from_str(&fs::read_to_string(against)?).map_err(|e| format!("{} is not a baseline file: {}", display_path(against), e))?;
+    match baseline.version {
+        | BASELINE_VERSION => {}
+        // Version 1 stored file names as they are, so only `%` needs escaping
+        | 1 => baseline.files = baseline.files.into_iter().map(|(path, record)| (path.replace('%', "%25"), record)).collect(),
+        | version => return Err(format!("baseline format version {} is not supported (expected {})", version, BASELINE_VERSION).into()),
+    }
+    let mut errors = BatchErrors::default();
+    let DirectoryRecords { files: current, unreadable, .. } = record_directory(dir, &mut errors)?;
+
+    let mut changed = 0;
+    let mut unchanged = 0;
+    for (path, record) in &baseline.files {
+        let Some(now) = current.get(path) else {
+            // Files that could not be read (or lie in a directory that could not be read) are listed with the unreadable paths
+            if unreadable.contains(path) || fs::metadata(dir.join(key_path(path))).is_err_and(|e| e.kind() != io::ErrorKind::NotFound) {
+                continue;
+            }
+            outln!("{}", format!("{}: missing", display_path(&key_path(path))).bright_red());
+            changed += 1;
+            continue;
+        };
+        let changes = compare_records(record, now);
+        if changes.is_empty() {
+            unchanged += 1;
+            continue;
+        }
+        changed += 1;
+        for change in changes {
+            outln!("{}: {}", display_path(&key_path(path)), change);
+        }
+    }
+    let new_files: Vec<&String> = current.keys().filter(|path| !baseline.files.contains_key(*path)).collect();
+    for path in &new_files {
+        outln!("{}", format!("{}: new file", display_path(&key_path(path))).bright_yellow());
+    }
+
+    outln!("\n{} file(s) unchanged, {} changed or missing, {} new", unchanged, changed, new_files.len());
+    errors.print_summary("");
+    if changed + new_files.len() > 0 {
+        return Err(format!("{} file(s) differ from the baseline", changed + new_files.len()).into());
+    }
+    errors.finish()
+}
+
+/// What `record_directory` found below a directory
+struct DirectoryRecords {
+    /// Records of the media files by relative path
+    files: BTreeMap<String, FileRecord>,
+    /// Number of other files
+    skipped: usize,
+    /// Relative paths of the files that could not be read
+    unreadable: BTreeSet<String>,
+}
+
+/// Record the media files below `dir`; what cannot be read is recorded in `errors`
+fn record_directory(dir: &Path, errors: &mut BatchErrors) -> Result<DirectoryRecords, Box<dyn std::error::Error>> {
+    let files = collect_files(dir, errors)?;
+    let mut records = BTreeMap::new();
+    let mut skipped = 0;
+    let mut unreadable = BTreeSet::new();
+
+    let total = files.len();
+    for (index, path) in files.iter().enumerate() {
+        let progress = FileProgress::start(path, index + 1, total);
+        let result = record_file(path);
+        progress.done(&result);
+        let key = path_key(path.strip_prefix(dir).unwrap_or(path));
+        match result {
+            | Ok(Some(record)) => {
+                records.insert(key, record);
+            }
+            | Ok(None) => skipped += 1,
+            | Err(e) if errors.record(path, &*e) => {
+                unreadable.insert(key);
+            }
+            | Err(e) => return Err(format!("{}: {}", display_path(path), e).into()),
+        }
+    }
+    Ok(DirectoryRecords { files: records, skipped, unreadable })
+}
+
+/// Key of a path relative to the directory: the components joined by `/`, with `%` and the bytes
+/// that are no UTF-8 escaped as `%XX`, so that paths differing only in such bytes stay apart
+pub fn path_key(relative: &Path) -> String {
+    let mut components = Vec::new();
+    for component in relative.components() {
+        let mut key = String::new();
+        for chunk in component.as_os_str().as_encoded_bytes().utf8_chunks() {
+            key.push_str(&chunk.valid().replace('%', "%25"));
+            for byte in chunk.invalid() {
+                key.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        components.push(key);
+    }
+    components.join("/")
+}
+
+/// Relative path of a key made by `path_key`
+pub fn key_path(key: &str) -> PathBuf {
+    key.split('/').map(unescape_component).collect()
+}
+
+fn unescape_component(component: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut rest = component.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok()).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            | Some(escaped) if byte == b'%' => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            | _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    os_path(bytes)
+}
+
+#[cfg(unix)]
+fn os_path(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+// Other platforms cannot build a path from arbitrary bytes; their file names are UTF-16 and are
+// only escaped for unpaired surrogates
+#[cfg(not(unix))]
+fn os_path(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Record one file; `None` for files no dissector recognizes
+fn record_file(path: &Path) -> Result<Option<FileRecord>, Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let builder = DissectorBuilder::new();
+    if !builder.recognizes(&mut file) {
+        return Ok(None);
+    }
+    let format = builder.build_for_file(&mut file)?.media_type();
+
+    let frames = match Id3v2Tag::read(&mut file)? {
+        | Some(tag) => frame_records(&tag.frames),
+        | None => ilst_records(&mut file)?,
+    };
+    Ok(Some(FileRecord { format: format.to_string(), audio_sha256: audio_hash(&mut file)?, frames }))
+}
+
+/// Records of the frames of a tag by key; frames with the same key get "#2", "#3", ...
+fn frame_records(frames: &[Id3v2Frame]) -> BTreeMap<String, FrameRecord> {
+    let mut records = BTreeMap::new();
+    for frame in frames {
+        let record = FrameRecord { value: frame_value(frame), sha256: sha256(&frame.data) };
+        insert_unique(&mut records, frame_key(frame), record);
+    }
+    records
+}
+
+/// Frame ID and what tells frames of that ID apart
+pub fn frame_key(frame: &Id3v2Frame) -> String {
+    match &frame.content {
+        | Some(Id3v2FrameContent::UserText(user_text)) => format!("TXXX:{}", user_text.description),
+        | Some(Id3v2FrameContent::UserUrl(user_url)) => format!("WXXX:{}", user_url.description),
+        | Some(Id3v2FrameContent::Comment(comment)) => format!("{}:{}:{}", frame.id, comment.language, comment.description),
+        | Some(Id3v2FrameContent::SyncedLyrics(lyrics)) => format!("SYLT:{}:{}", lyrics.language, lyrics.description),
+        | Some(Id3v2FrameContent::Picture(picture)) => format!("APIC:{}", picture.picture_type),
+        | Some(Id3v2FrameContent::GeneralObject(object)) => format!("GEOB:{}", object.description),
+        | Some(Id3v2FrameContent::UniqueFileId(ufid)) => format!("UFID:{}", ufid.owner_identifier),
+        | Some(Id3v2FrameContent::Private(private)) => format!("PRIV:{}", private.owner_identifier),
+        | Some(Id3v2FrameContent::Chapter(chapter)) => format!("CHAP:{}", chapter.element_id),
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => format!("CTOC:{}", toc.element_id),
+        | _ => frame.id.clone(),
+    }
+}
+
+/// Value of frames that hold text; `None` for binary frames, which are compared by hash
+pub fn frame_value(frame: &Id3v2Frame) -> Option<String> {
+    match &frame.content {
+        | Some(Id3v2FrameContent::UserText(user_text)) => Some(user_text.value.clone()),
+        | Some(Id3v2FrameContent::UserUrl(user_url)) => Some(user_url.url.clone()),
+        | Some(Id3v2FrameContent::Comment(comment)) => Some(comment.text.clone()),
+        | _ => frame.get_text_values().map(|values| values.join("; ")).or_else(|| frame.get_url().map(str::to_string)),
+    }
+}
+
+/// Records of the items of the `moov/udta/meta/ilst` box of an MP4 file
+fn ilst_records(file: &mut dyn MediaSource) -> Result<BTreeMap<String, FrameRecord>, Box<dyn std::error::Error>> {
+    let mut records = BTreeMap::new();
+    let file_size = file.byte_len()?;
+    let Some(moov) = read_boxes(file, 0, file_size)?.into_iter().find(|box_header| box_header.box_type == "moov") else {
+        return Ok(records);
+    };
+    let Some(ilst) = find_path(file, &moov, &["udta", "meta", "ilst"])? else {
+        return Ok(records);
+    };
+
+    for item in read_children(file, &ilst)? {
+        let mut key = item.box_type.clone();
+        let mut value = None;
+        for child in read_children(file, &item)? {
+            let payload = read_payload(file, &child)?;
+            match child.box_type.as_str() {
+                // Freeform items are named by their mean and name boxes (after version and flags)
+                | "mean" | "name" => key = format!("{}:{}", key, String::from_utf8_lossy(payload.get(4..).unwrap_or_default())),
+                // Type 1 is UTF-8 text; type indicator and locale precede the value
+                | "data" if payload.get(0..4) == Some(&[0, 0, 0, 1]) => value = Some(String::from_utf8_lossy(payload.get(8..).unwrap_or_default()).into_owned()),
+                | _ => {}
+            }
+        }
+        let record = FrameRecord { value, sha256: sha256(&read_payload(file, &item)?) };
+        insert_unique(&mut records, key, record);
+    }
+    Ok(records)
+}
+
+fn insert_unique(records: &mut BTreeMap<String, FrameRecord>, key: String, record: FrameRecord) {
+    let mut unique = key.clone();
+    let mut count = 1;
+    while records.contains_key(&unique) {
+        count += 1;
+        unique = format!("{}#{}", key, count);
+    }
+    records.insert(unique, record);
+}
+
+/// Changes from the recorded to the current state of a file
+fn compare_records(recorded: &FileRecord, current: &FileRecord) -> Vec<String> {
+    let mut changes = Vec::new();
+    if recorded.format != current.format {
+        changes.push(format!("format changed from {} to {}", recorded.format, current.format));
+    }
+    if recorded.audio_sha256 != current.audio_sha256 {
+        changes.push(format!("audio changed (sha256 {} -> {})", short_hash(&recorded.audio_sha256), short_hash(&current.audio_sha256)));
+    }
+    for (key, before) in &recorded.frames {
+        match current.frames.get(key) {
+            | None => changes.push(format!("{} removed{}", key, quoted(&before.value))),
+            | Some(after) if before.value != after.value => changes.push(format!("{} changed:{} ->{}", key, quoted(&before.value), quoted(&after.value))),
+            | Some(after) if before.sha256 != after.sha256 => changes.push(format!("{} data changed (sha256 {} -> {})", key, short_hash(&before.sha256), short_hash(&after.sha256))),
+            | Some(_) => {}
+        }
+    }
+    for (key, after) in &current.frames {
+        if !recorded.frames.contains_key(key) {
+            changes.push(format!("{} added{}", key, quoted(&after.value)));
+        }
+    }
+    changes
+}
+
+fn quoted(value: &Option<String>) -> String {
+    value.as_ref().map(|value| format!(" \"{}\"", value)).unwrap_or_default()
+}
+
+fn short_hash(hash: &str) -> &str {
+    hash.get(..16).unwrap_or(hash)
+}
+
+fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn file_names_that_are_no_utf8_keep_distinct_keys() {
+        use std::os::unix::ffi::OsStrExt;
+        let first = Path::new("album").join(std::ffi::OsStr::from_bytes(b"track\xff%.mp3"));
+        let second = Path::new("album").join(std::ffi::OsStr::from_bytes(b"track\xfe%.mp3"));
+        assert_eq!(path_key(&first), "album/track%FF%25.mp3");
+        assert_ne!(path_key(&first), path_key(&second));
+        assert_eq!(key_path(&path_key(&first)), first);
+        assert_eq!(key_path(&path_key(&second)), second);
+    }
+}
@@ -0,0 +1,189 @@
+/// Files and directories a batch run could not read
+///
+/// Large libraries have files that cannot be read: missing permissions, files removed while the
+/// run goes on, network shares that drop out. Batch commands, and the commands that change files
+/// one by one, do not stop at them: they record the path and the error here, go on with the other
+/// files and list the unreadable paths, counted by cause, in their summary. At the end the run
+/// fails with the I/O exit status, unless `--continue-on-error` accepts unreadable files (for
+/// scheduled runs over libraries where some locked files are expected). Other errors, such as
+/// malformed files, are not affected.
+use crate::error::SupertoolError;
+use crate::options::CommonOptions;
+use crate::path_display::display_path;
+use crate::safe_save::DRY_RUN_NOTICE;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether unreadable files leave the outcome of a batch run alone (`--continue-on-error`)
+pub fn continue_on_error() -> bool {
+    CommonOptions::current().continue_on_error
+}
+
+/// Why a file or directory could not be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadErrorKind {
+    PermissionDenied,
+    NotFound,
+    /// Any other I/O error (device errors, interrupted network shares, ...)
+    Io,
+}
+
+impl fmt::Display for ReadErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | ReadErrorKind::PermissionDenied => write!(f, "permission denied"),
+            | ReadErrorKind::NotFound => write!(f, "not found"),
+            | ReadErrorKind::Io => write!(f, "I/O error"),
+        }
+    }
+}
+
+/// The kind of I/O error behind `error`; `None` for errors that are no I/O errors
+///
+/// A file that ends too early (`UnexpectedEof`) or holds data that cannot be decoded
+/// (`InvalidData`) was read fine; it is malformed, not unreadable.
+pub fn read_error_kind(error: &(dyn std::error::Error + 'static)) -> Option<ReadErrorKind> {
+    let io_error = match error.downcast_ref::<SupertoolError>() {
+        | Some(SupertoolError::Io(io_error)) => io_error,
+        | _ => error.downcast_ref::<io::Error>()?,
+    };
+    match io_error.kind() {
+        | io::ErrorKind::PermissionDenied => Some(ReadErrorKind::PermissionDenied),
+        | io::ErrorKind::NotFound => Some(ReadErrorKind::NotFound),
+        | io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData => None,
+        | _ => Some(ReadErrorKind::Io),
+    }
+}
+
+/// A file or directory that could not be read
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreadablePath {
+    pub path: String,
+    pub kind: ReadErrorKind,
+    pub error: String,
+}
+
+/// The unreadable files and directories of a batch run
+#[derive(Debug, Clone, Default)]
+pub struct BatchErrors {
+    pub entries: Vec<UnreadablePath>,
+}
+
+impl BatchErrors {
+    /// Record `error` if it is an I/O error; false for other errors, which the caller reports itself
+    pub fn record(&mut self, path: &Path, error: &(dyn std::error::Error + 'static)) -> bool {
+        let Some(kind) = read_error_kind(error) else {
+            return false;
+        };
+        self.entries.push(UnreadablePath { path: display_path(path).to_string(), kind, error: error.to_string() });
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Add the entries of another collection (e.g. of a worker thread)
+    pub fn extend(&mut self, other: BatchErrors) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Print the count by cause and every unreadable path, indented by `indent`
+    pub fn print_summary(&self, indent: &str) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let mut kinds: Vec<(ReadErrorKind, usize)> = Vec::new();
+        for entry in &self.entries {
+            match kinds.iter_mut().find(|(kind, _)| *kind == entry.kind) {
+                | Some((_, count)) => *count += 1,
+                | None => kinds.push((entry.kind, 1)),
+            }
+        }
+        kinds.sort();
+        let kinds: Vec<String> = kinds.iter().map(|(kind, count)| format!("{}: {}", kind, count)).collect();
+        outln!("{}Unreadable: {} ({})", indent, self.entries.len(), kinds.join(", "));
+        let mut entries: Vec<&UnreadablePath> = self.entries.iter().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in entries {
+            outln!("{}  {}", indent, format!("{}: {}", entry.path, entry.error).bright_red());
+        }
+    }
+
+    /// Run `change` on every file of a command that changes files one by one and return the
+    /// number of files that failed; unreadable files are recorded, other errors printed
+    pub fn change_files(&mut self, files: &[PathBuf], mut change: impl FnMut(&Path) -> Result<(), Box<dyn std::error::Error>>) -> usize {
+        let mut failed = 0;
+        for path in files {
+            if let Err(e) = change(path)
+                && !self.record(path, &*e)
+            {
+                outln!("{}", format!("{}: {}", display_path(path), e).bright_red());
+                failed += 1;
+            }
+        }
+        failed
+    }
+
+    /// End of a command that handles files one by one: the dry run is noted and the unreadable
+    /// paths are listed, then the `failed` files (other errors) fail the run with "N file(s) could
+    /// not be `action`", and otherwise the outcome is that of `finish`
+    pub fn finish_files(&self, failed: usize, action: &str, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if dry_run {
+            outln!("{}", DRY_RUN_NOTICE);
+        }
+        self.print_summary("");
+        if failed > 0 {
+            return Err(format!("{} file(s) could not be {}", failed, action).into());
+        }
+        self.finish()
+    }
+
+    /// Outcome of the run as far as unreadable files go: an I/O error unless `--continue-on-error`
+    pub fn finish(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.entries.is_empty() || continue_on_error() {
+            return Ok(());
+        }
+        let message = format!("{} file(s) or directories could not be read (see --continue-on-error)", self.entries.len());
+        Err(SupertoolError::Io(io::Error::other(message)).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::analyze_file;
+
+    #[test]
+    fn a_truncated_file_is_malformed_not_unreadable() {
+        // An ID3v2.3 tag that claims 420 bytes, of which the file has 30
+        let mut data = b"ID3\x03\x00\x00\x00\x00\x03\x24".to_vec();
+        data.extend_from_slice(b"TIT2\x00\x00\x00\x0a\x00\x00\x00New Title");
+        let path = std::env::temp_dir().join(format!("supertool-truncated-{}.mp3", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let result = analyze_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let error = result.expect_err("the tag is truncated");
+        let mut errors = BatchErrors::default();
+        assert_eq!(read_error_kind(&*error), None);
+        assert!(!errors.record(&path, &*error));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn missing_files_are_unreadable() {
+        let error = io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(read_error_kind(&error), Some(ReadErrorKind::NotFound));
+        assert_eq!(read_error_kind(&io::Error::from(io::ErrorKind::UnexpectedEof)), None);
+        assert_eq!(read_error_kind(&SupertoolError::Io(io::Error::from(io::ErrorKind::InvalidData))), None);
+    }
+}
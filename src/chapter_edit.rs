@@ -0,0 +1,342 @@
+/// Merging, splitting, shifting and normalizing of ID3v2 chapters (`tag chapters`)
+///
+/// The top-level CHAP frames are edited in start time order and written back where the first of
+/// them was. Element IDs that follow a numbering scheme ("chp0", "chp1", ... or "ch01", ...) are
+/// renumbered in the new order, with the prefix, first number and digit count kept; the CTOC
+/// child lists follow every removal, insertion and rename. Normalizing renames all chapters to
+/// "chp001", "chp002", ... and replaces all CTOC frames, nested ones included, by a single
+/// top-level ordered table of contents of all chapters.
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_chapter_frame::ChapterFrame;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_table_of_contents_frame::TableOfContentsFrame;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_writer::{ensure_writable_version, write_tag};
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use crate::safe_save::DRY_RUN_NOTICE;
+use crate::time_format::format_duration;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Byte offset value of a CHAP frame that does not use byte offsets
+const NO_OFFSET: u32 = 0xFFFFFFFF;
+
+/// Element ID of the table of contents written by `normalize`
+const TOC_ID: &str = "toc";
+
+/// How far `--at` may be from the chapter boundary to merge at
+const MERGE_TOLERANCE_MS: u32 = 1000;
+
+/// Operation of a `tag chapters` command
+#[derive(Debug, Clone, Copy)]
+pub enum ChapterEdit {
+    /// Join the two chapters that meet at this time (milliseconds)
+    Merge { at: u32 },
+    /// Split the chapter with this 1-based number (in start time order) at a time inside it
+    Split { chapter: usize, at: u32 },
+    /// Move all chapters by this many milliseconds
+    Shift { offset: i64 },
+    /// Rename the chapters to chp001, chp002, ... and replace all CTOC frames by one table of contents
+    Normalize,
+}
+
+/// Parse a point in time: "01:02:03.5", "02:03", "3723.5" or "3723.5s"
+pub fn parse_time(text: &str) -> Result<u32, String> {
+    let milliseconds = parse_milliseconds(text.trim()).ok_or_else(|| format!("'{}' is not a time (hh:mm:ss.mmm, mm:ss or seconds)", text))?;
+    u32::try_from(milliseconds).map_err(|_| format!("'{}' is beyond the range of chapter times", text))
+}
+
+/// Parse a signed offset: "-2.5s", "+1500ms", "-00:01.5"
+pub fn parse_offset(text: &str) -> Result<i64, String> {
+    let trimmed = text.trim();
+    let (sign, magnitude) = match trimmed.strip_prefix('-') {
+        | Some(rest) => (-1, rest),
+        | None => (1, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let milliseconds = match magnitude.strip_suffix("ms") {
+        | Some(number) => number.trim().parse::<u64>().ok(),
+        | None => parse_milliseconds(magnitude),
+    };
+    let milliseconds = milliseconds.ok_or_else(|| format!("'{}' is not an offset (e.g. -2.5s, +1500ms or -00:01.5)", text))?;
+    Ok(sign * milliseconds as i64)
+}
+
+/// Milliseconds of "[[hh:]mm:]ss[.fff]" with an optional "s" suffix
+fn parse_milliseconds(text: &str) -> Option<u64> {
+    let text = text.strip_suffix('s').unwrap_or(text);
+    let mut parts = text.rsplit(':');
+    let seconds = parts.next()?;
+    let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    if whole.is_empty() || !whole.chars().all(|c| c.is_ascii_digit()) || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // ".5" means 500 ms; digits beyond milliseconds are dropped
+    let fraction_ms: u64 = format!("{:0<3}", &fraction[..fraction.len().min(3)]).parse().ok()?;
+    let mut milliseconds = whole.parse::<u64>().ok()?.checked_mul(1000)? + fraction_ms;
+    for (unit, part) in [60_000, 3_600_000].into_iter().zip(parts.by_ref()) {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        milliseconds = milliseconds.checked_add(part.parse::<u64>().ok()?.checked_mul(unit)?)?;
+    }
+    // More than hours
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(milliseconds)
+}
+
+/// Apply an edit to the chapters of a file and print the resulting chapter list
+pub fn edit_chapters(path: &Path, edit: ChapterEdit, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    edit_file(path, edit, dry_run, options)?;
+    if dry_run {
+        outln!("{}", DRY_RUN_NOTICE);
+    }
+    Ok(())
+}
+
+fn edit_file(path: &Path, edit: ChapterEdit, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let mut tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    drop(file);
+
+    ensure_writable_version(&tag)?;
+
+    let Some(position) = tag.frames.iter().position(is_chapter) else {
+        return Err("no chapters (CHAP frames)".into());
+    };
+    let mut chapters: Vec<ChapterFrame> = tag
+        .frames
+        .iter()
+        .filter_map(|frame| match &frame.content {
+            | Some(Id3v2FrameContent::Chapter(chapter)) => Some(chapter.clone()),
+            | _ => None,
+        })
+        .collect();
+    if chapters.len() != tag.frames.iter().filter(|frame| frame.id == "CHAP").count() {
+        return Err("a CHAP frame could not be parsed, so the chapters cannot be rewritten".into());
+    }
+    chapters.sort_by_key(|chapter| chapter.start_time);
+    let original_ids: Vec<String> = chapters.iter().map(|chapter| chapter.element_id.clone()).collect();
+    let numbering = match edit {
+        | ChapterEdit::Normalize => Some(Numbering::normalized(chapters.len())),
+        | _ => Numbering::detect(&chapters),
+    };
+
+    // CTOC child list changes: removed element IDs and (existing ID, ID inserted after it)
+    let mut removed = None;
+    let mut inserted = None;
+    let mut description = match edit {
+        | ChapterEdit::Merge { at } => {
+            let index = (1..chapters.len())
+                .min_by_key(|&i| chapters[i].start_time.abs_diff(at))
+                .filter(|&i| chapters[i].start_time.abs_diff(at) <= MERGE_TOLERANCE_MS)
+                .ok_or_else(|| format!("no chapter starts at {} (within one second)", format_duration(at as u64)))?;
+            let next = chapters.remove(index);
+            let previous = &mut chapters[index - 1];
+            previous.end_time = previous.end_time.max(next.end_time);
+            previous.end_offset = next.end_offset;
+            let description = format!("chapters {} and {} merged at {}", index, index + 1, format_duration(next.start_time as u64));
+            removed = Some(next.element_id);
+            description
+        }
+        | ChapterEdit::Split { chapter: number, at } => {
+            let index = number.checked_sub(1).filter(|&index| index < chapters.len()).ok_or_else(|| format!("there is no chapter {} (the file has {})", number, chapters.len()))?;
+            let chapter = &mut chapters[index];
+            if at <= chapter.start_time || at >= chapter.end_time {
+                return Err(format!(
+                    "{} is not inside chapter {} ({} - {})",
+                    format_duration(at as u64),
+                    number,
+                    format_duration(chapter.start_time as u64),
+                    format_duration(chapter.end_time as u64)
+                )
+                .into());
+            }
+            // The byte position of the new boundary is unknown, so both halves drop their byte offsets
+            let mut second = chapter.clone();
+            chapter.end_time = at;
+            second.start_time = at;
+            for half in [&mut *chapter, &mut second] {
+                half.start_offset = NO_OFFSET;
+                half.end_offset = NO_OFFSET;
+            }
+            second.element_id = unique_id(&chapters, &chapters[index].element_id);
+            inserted = Some((chapters[index].element_id.clone(), second.element_id.clone()));
+            chapters.insert(index + 1, second);
+            format!("chapter {} split at {}", number, format_duration(at as u64))
+        }
+        | ChapterEdit::Shift { offset } => {
+            if let Some(chapter) = chapters.iter().find(|chapter| chapter.end_time as i64 + offset <= 0) {
+                return Err(format!("shifting by {} ms would move chapter '{}' before the start of the file", offset, chapter.element_id).into());
+            }
+            // Byte offsets describe the old positions
+            let shift = |time: u32| (time as i64 + offset).clamp(0, u32::MAX as i64) as u32;
+            for chapter in &mut chapters {
+                chapter.start_time = shift(chapter.start_time);
+                chapter.end_time = shift(chapter.end_time);
+                chapter.start_offset = NO_OFFSET;
+                chapter.end_offset = NO_OFFSET;
+            }
+            let sign = if offset < 0 {
+                "-"
+            } else {
+                "+"
+            };
+            format!("chapters shifted by {}{}", sign, format_duration(offset.unsigned_abs()))
+        }
+        | ChapterEdit::Normalize => String::new(),
+    };
+
+    let renames = match &numbering {
+        | Some(numbering) => numbering.renumber(&mut chapters),
+        | None => HashMap::new(),
+    };
+
+    let version_major = tag.version_major;
+    let mut rebuilt = Vec::new();
+    if let ChapterEdit::Normalize = edit {
+        let tocs: Vec<&TableOfContentsFrame> = tag
+            .frames
+            .iter()
+            .filter_map(|frame| match &frame.content {
+                | Some(Id3v2FrameContent::TableOfContents(toc)) => Some(toc),
+                | _ => None,
+            })
+            .collect();
+        // Entries that name neither a chapter nor another CTOC
+        let orphans = tocs
+            .iter()
+            .flat_map(|toc| &toc.child_element_ids)
+            .filter(|child| !original_ids.contains(child) && !tocs.iter().any(|toc| &toc.element_id == *child))
+            .count();
+        // The title and other sub-frames come from the existing top-level CTOC
+        let sub_frames = tocs.iter().find(|toc| toc.top_level).or(tocs.first()).map(|toc| toc.sub_frames.clone()).unwrap_or_default();
+        description = format!(
+            "{} element ID(s) renamed, {} CTOC frame(s) replaced by one ordered table of contents, {} dangling reference(s) dropped",
+            renames.len(),
+            tocs.len(),
+            orphans
+        );
+        let toc = TableOfContentsFrame {
+            element_id: TOC_ID.to_string(),
+            top_level: true,
+            ordered: true,
+            child_element_ids: chapters.iter().map(|chapter| chapter.element_id.clone()).collect(),
+            sub_frames,
+        };
+        rebuilt.push(Id3v2Frame::new_table_of_contents(toc, version_major));
+    } else {
+        // Update the child lists of all CTOC frames in place
+        for frame in &mut tag.frames {
+            let Some(Id3v2FrameContent::TableOfContents(toc)) = &frame.content else {
+                continue;
+            };
+            let mut toc = toc.clone();
+            let mut children = Vec::new();
+            for child in &toc.child_element_ids {
+                if removed.as_ref() == Some(child) {
+                    continue;
+                }
+                children.push(child.clone());
+                if let Some((after, new_id)) = &inserted
+                    && after == child
+                {
+                    children.push(new_id.clone());
+                }
+            }
+            toc.child_element_ids = children.into_iter().map(|child| renames.get(&child).cloned().unwrap_or(child)).collect();
+            *frame = Id3v2Frame::new_table_of_contents(toc, version_major);
+        }
+    }
+    rebuilt.extend(chapters.iter().map(|chapter| Id3v2Frame::new_chapter(chapter.clone(), version_major)));
+
+    // The rebuilt frames go where the first of the replaced frames was
+    let replaced = |frame: &Id3v2Frame| is_chapter(frame) || (matches!(edit, ChapterEdit::Normalize) && frame.id == "CTOC");
+    let position = tag.frames.iter().position(replaced).unwrap_or(position);
+    tag.frames.retain(|frame| !replaced(frame));
+    tag.frames.splice(position..position, rebuilt);
+
+    outln!("{}: {}", display_path(path), description);
+    for chapter in &chapters {
+        outln!(
+            "  {}  {} - {}  \"{}\"",
+            chapter.element_id,
+            format_duration(chapter.start_time as u64),
+            format_duration(chapter.end_time as u64),
+            chapter.title().unwrap_or_default()
+        );
+    }
+
+    if !dry_run {
+        write_tag(path, &tag, options)?;
+    }
+    Ok(())
+}
+
+/// Normalize the chapters of every file
+pub fn normalize_files(files: &[PathBuf], dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unreadable = BatchErrors::default();
+    let failed = unreadable.change_files(files, |path| edit_file(path, ChapterEdit::Normalize, dry_run, options));
+    unreadable.finish_files(failed, "normalized", dry_run)
+}
+
+fn is_chapter(frame: &Id3v2Frame) -> bool {
+    frame.id == "CHAP"
+}
+
+/// An ID not used by any chapter, derived from the ID of the chapter that was split
+fn unique_id(chapters: &[ChapterFrame], base: &str) -> String {
+    (2..).map(|n| format!("{}-{}", base, n)).find(|candidate| chapters.iter().all(|chapter| &chapter.element_id != candidate)).unwrap_or_default()
+}
+
+/// Numbering scheme shared by all element IDs: a common prefix followed by a number
+struct Numbering {
+    prefix: String,
+    first: u64,
+    /// Digits of the number, zero-padded ("chp001")
+    width: usize,
+}
+
+impl Numbering {
+    /// The scheme of the chapter IDs, if all of them follow one
+    fn detect(chapters: &[ChapterFrame]) -> Option<Self> {
+        let mut numbering: Option<Numbering> = None;
+        for chapter in chapters {
+            let digits = chapter.element_id.len() - chapter.element_id.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                return None;
+            }
+            let (prefix, number) = chapter.element_id.split_at(chapter.element_id.len() - digits);
+            let value: u64 = number.parse().ok()?;
+            match &mut numbering {
+                | Some(existing) if existing.prefix == prefix => {
+                    existing.first = existing.first.min(value);
+                    existing.width = existing.width.min(digits);
+                }
+                | Some(_) => return None,
+                | None => numbering = Some(Numbering { prefix: prefix.to_string(), first: value, width: digits }),
+            }
+        }
+        numbering
+    }
+
+    /// The scheme of `normalize`: "chp001", with more digits for a thousand chapters or more
+    fn normalized(count: usize) -> Self {
+        Numbering { prefix: "chp".to_string(), first: 1, width: count.to_string().len().max(3) }
+    }
+
+    /// Give the chapters consecutive IDs in their order and return the renames (old ID to new ID)
+    fn renumber(&self, chapters: &mut [ChapterFrame]) -> HashMap<String, String> {
+        let mut renames = HashMap::new();
+        for (number, chapter) in (self.first..).zip(chapters.iter_mut()) {
+            let id = format!("{}{:0width$}", self.prefix, number, width = self.width);
+            if id != chapter.element_id {
+                renames.insert(std::mem::replace(&mut chapter.element_id, id.clone()), id);
+            }
+        }
+        renames
+    }
+}
@@ -0,0 +1,140 @@
+/// Export of chapters to other formats (`chapters`)
+///
+/// Podcast tools, players and video editors each read chapters in their own format. The CHAP
+/// frames of a tag are written, sorted by start time, as a CUE sheet, an ffmpeg metadata file, a
+/// WebVTT chapter track or Podlove Simple Chapters (JSON). The chapter title is taken from the
+/// TIT2 sub-frame, with the element ID as fallback; the link (WXXX) is kept where the format has a
+/// place for it.
+use crate::id3v2_chapter_frame::ChapterFrame;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::options::ChapterFormat;
+use crate::path_display::display_path;
+use serde::Serialize;
+use std::fs::File;
+use std::path::Path;
+
+/// Write the chapters of a file in `format` to `output` (stdout if not set)
+pub fn export_chapters(path: &Path, format: ChapterFormat, output: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    let mut chapters: Vec<&ChapterFrame> = tag.chapters().collect();
+    if chapters.is_empty() {
+        return Err("no chapters (CHAP)".into());
+    }
+    chapters.sort_by_key(|chapter| (chapter.start_time, chapter.end_time));
+
+    let text_frame = |id: &str| tag.frames.iter().find(|frame| frame.id == id).and_then(|frame| frame.get_text());
+    let exported = match format {
+        | ChapterFormat::Cue => {
+            let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            format_cue(&chapters, &file_name, text_frame("TIT2"), text_frame("TPE1"))
+        }
+        | ChapterFormat::Ffmetadata => format_ffmetadata(&chapters, text_frame("TIT2"), text_frame("TPE1")),
+        | ChapterFormat::Webvtt => format_webvtt(&chapters),
+        | ChapterFormat::Podlove => format_podlove(&chapters)?,
+    };
+
+    match output {
+        | Some(output) => {
+            std::fs::write(output, exported)?;
+            outln!("{} chapter(s) written: {}", chapters.len(), display_path(output));
+        }
+        | None => out!("{}", exported),
+    }
+    Ok(())
+}
+
+fn chapter_title(chapter: &ChapterFrame) -> &str {
+    chapter.title().unwrap_or(&chapter.element_id)
+}
+
+/// CUE sheet: one track per chapter, times as mm:ss:ff with 75 frames per second
+fn format_cue(chapters: &[&ChapterFrame], file_name: &str, title: Option<&str>, performer: Option<&str>) -> String {
+    // CUE sheets have no escaping, so double quotes inside values become single quotes
+    let quoted = |value: &str| format!("\"{}\"", value.replace('"', "'").replace(['\r', '\n'], " "));
+    let mut cue = String::new();
+    if let Some(performer) = performer {
+        cue.push_str(&format!("PERFORMER {}\n", quoted(performer)));
+    }
+    if let Some(title) = title {
+        cue.push_str(&format!("TITLE {}\n", quoted(title)));
+    }
+    let file_type = if file_name.to_ascii_lowercase().ends_with(".mp3") {
+        "MP3"
+    } else {
+        "WAVE"
+    };
+    cue.push_str(&format!("FILE {} {}\n", quoted(file_name), file_type));
+    for (i, chapter) in chapters.iter().enumerate() {
+        let frames = chapter.start_time as u64 * 75 / 1000;
+        cue.push_str(&format!("  TRACK {:02} AUDIO\n", i + 1));
+        cue.push_str(&format!("    TITLE {}\n", quoted(chapter_title(chapter))));
+        cue.push_str(&format!("    INDEX 01 {:02}:{:02}:{:02}\n", frames / 75 / 60, frames / 75 % 60, frames % 75));
+    }
+    cue
+}
+
+/// ffmpeg metadata file with millisecond chapter times
+fn format_ffmetadata(chapters: &[&ChapterFrame], title: Option<&str>, artist: Option<&str>) -> String {
+    // '=', ';', '#', '\' and line breaks are escaped with a backslash
+    let escaped = |value: &str| {
+        value.chars().fold(String::new(), |mut escaped, c| {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+    };
+    let mut metadata = String::from(";FFMETADATA1\n");
+    if let Some(title) = title {
+        metadata.push_str(&format!("title={}\n", escaped(title)));
+    }
+    if let Some(artist) = artist {
+        metadata.push_str(&format!("artist={}\n", escaped(artist)));
+    }
+    for chapter in chapters {
+        metadata.push_str("\n[CHAPTER]\nTIMEBASE=1/1000\n");
+        metadata.push_str(&format!("START={}\nEND={}\n", chapter.start_time, chapter.end_time));
+        metadata.push_str(&format!("title={}\n", escaped(chapter_title(chapter))));
+    }
+    metadata
+}
+
+/// WebVTT chapter track with the element IDs as cue identifiers
+fn format_webvtt(chapters: &[&ChapterFrame]) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    for chapter in chapters {
+        // Identifiers must not contain "-->" or line breaks
+        let identifier = chapter.element_id.replace("-->", "->").replace(['\r', '\n'], " ");
+        let text = chapter_title(chapter).replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        vtt.push_str(&format!("\n{}\n{} --> {}\n{}\n", identifier, normal_play_time(chapter.start_time), normal_play_time(chapter.end_time), text));
+    }
+    vtt
+}
+
+/// Chapter of the Podlove Simple Chapters JSON representation
+#[derive(Serialize)]
+struct PodloveChapter<'a> {
+    start: String,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    href: Option<&'a str>,
+}
+
+fn format_podlove(chapters: &[&ChapterFrame]) -> Result<String, Box<dyn std::error::Error>> {
+    let chapters: Vec<PodloveChapter> = chapters
+        .iter()
+        .map(|chapter| PodloveChapter {
+            start: normal_play_time(chapter.start_time),
+            title: chapter_title(chapter),
+            href: chapter.link().map(|link| link.url.trim_end_matches('\0')).filter(|url| !url.is_empty()),
+        })
+        .collect();
+    Ok(format!("{}\n", serde_json::to_string_pretty(&chapters)?))
+}
+
+/// hh:mm:ss.mmm, as used by WebVTT and Podlove Simple Chapters
+fn normal_play_time(ms: u32) -> String {
+    format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
@@ -0,0 +1,229 @@
+/// Chapter timeline visualization (`debug --timeline`)
+///
+/// Draws the chapters of a file as segments of a bar spanning the audio duration, with
+/// gaps (time no chapter covers) and overlaps marked, followed by a legend. With the
+/// `report` feature the same timeline can be written as an HTML page.
+use crate::id3v2_chapter_frame::ChapterFrame;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::estimate_duration_ms;
+#[cfg(feature = "report")]
+use crate::path_display::display_path;
+use crate::text_truncate::truncate_text;
+use crate::time_format::format_duration;
+use std::path::Path;
+
+/// Width of the ASCII bar in characters
+const BAR_WIDTH: usize = 72;
+
+/// Segment symbols, assigned to the chapters in time order and reused after the last one
+const SYMBOLS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Chapters sorted by start time together with the duration they are drawn against
+struct Timeline {
+    chapters: Vec<ChapterFrame>,
+    /// Audio duration in milliseconds (or the end of the last chapter if unknown)
+    duration: u64,
+    /// Where the duration comes from
+    duration_source: &'static str,
+    gaps: Vec<(u64, u64)>,
+    /// Overlapping chapter pairs (indices into `chapters`) and the overlapping milliseconds
+    overlaps: Vec<(usize, usize, u64)>,
+}
+
+/// Print an ASCII timeline of the file's chapters
+pub fn print_timeline(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    outln!("\nChapter Timeline:");
+    let Some(timeline) = Timeline::read(file)? else {
+        outln!("    No chapters found");
+        return Ok(());
+    };
+
+    outln!("    {} chapter(s), duration {} ({})", timeline.chapters.len(), format_duration(timeline.duration), timeline.duration_source);
+    outln!("    |{}|", timeline.ascii_bar());
+    let end_label = format_duration(timeline.duration);
+    outln!("    {}{:>width$}", format_duration(0), end_label, width = BAR_WIDTH + 2 - end_label.len());
+    outln!("    ('.' = gap, '#' = overlap)");
+
+    outln!();
+    for (i, chapter) in timeline.chapters.iter().enumerate() {
+        let title = chapter.title().map(|title| format!("  \"{}\"", truncate_text(title))).unwrap_or_default();
+        outln!("    {} {}  {} - {}{}", SYMBOLS[i % SYMBOLS.len()] as char, chapter.element_id, format_duration(chapter.start_time as u64), format_duration(chapter.end_time as u64), title);
+    }
+
+    for &(start, end) in &timeline.gaps {
+        outln!("    Gap: {} - {} ({})", format_duration(start), format_duration(end), format_duration(end - start));
+    }
+    for &(first, second, amount) in &timeline.overlaps {
+        outln!("    Overlap: '{}' and '{}' overlap by {}", timeline.chapters[first].element_id, timeline.chapters[second].element_id, format_duration(amount));
+    }
+    for chapter in timeline.chapters.iter().filter(|chapter| chapter.end_time as u64 > timeline.duration) {
+        outln!("    Chapter '{}' ends after the end of the audio", chapter.element_id);
+    }
+    Ok(())
+}
+
+impl Timeline {
+    /// Collect the chapters of the file's ID3v2 tag; `None` if there are none
+    fn read(file: &mut dyn MediaSource) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let Some(tag) = Id3v2Tag::read(file)? else {
+            return Ok(None);
+        };
+        let mut chapters: Vec<ChapterFrame> = tag.chapters().cloned().collect();
+        if chapters.is_empty() {
+            return Ok(None);
+        }
+        chapters.sort_by_key(|chapter| (chapter.start_time, chapter.end_time));
+
+        let last_end = chapters.iter().map(|chapter| chapter.end_time as u64).max().unwrap_or(0);
+        let tlen = tag.frames.iter().find(|frame| frame.id == "TLEN").and_then(|frame| frame.get_text()).and_then(|text| text.trim().parse::<u64>().ok());
+        let (duration, duration_source) = match (estimate_duration_ms(file)?, tlen) {
+            | (Some(duration), _) => (duration, "from the MPEG audio stream"),
+            | (None, Some(tlen)) => (tlen, "from TLEN"),
+            | (None, None) => (last_end, "end of the last chapter, audio length unknown"),
+        };
+
+        let mut gaps = Vec::new();
+        let mut overlaps = Vec::new();
+        let mut covered_until = 0u64;
+        let mut latest: Option<usize> = None;
+        for (i, chapter) in chapters.iter().enumerate() {
+            let (start, end) = (chapter.start_time as u64, chapter.end_time as u64);
+            if start > covered_until {
+                gaps.push((covered_until, start));
+            }
+            if let Some(previous) = latest
+                && start < covered_until
+            {
+                overlaps.push((previous, i, covered_until.min(end) - start));
+            }
+            if end > covered_until {
+                covered_until = end;
+                latest = Some(i);
+            }
+        }
+        if duration > covered_until {
+            gaps.push((covered_until, duration));
+        }
+
+        Ok(Some(Timeline { chapters, duration: duration.max(1), duration_source, gaps, overlaps }))
+    }
+
+    /// One character per time slice: the symbol of the chapter playing at its midpoint
+    fn ascii_bar(&self) -> String {
+        (0..BAR_WIDTH)
+            .map(|column| {
+                let time = (column as u64 * 2 + 1) * self.duration / (BAR_WIDTH as u64 * 2);
+                let mut playing = self.chapters.iter().enumerate().filter(|(_, chapter)| chapter.start_time as u64 <= time && time < chapter.end_time as u64);
+                match (playing.next(), playing.next()) {
+                    | (None, _) => '.',
+                    | (Some((i, _)), None) => SYMBOLS[i % SYMBOLS.len()] as char,
+                    | (Some(_), Some(_)) => '#',
+                }
+            })
+            .collect()
+    }
+}
+
+/// Write the timeline as an HTML page next to the file (`<file>.timeline.html`)
+#[cfg(feature = "report")]
+pub fn write_html_timeline(file: &mut dyn MediaSource, file_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(timeline) = Timeline::read(file)? else {
+        outln!("\nChapter Timeline: no chapters found, no HTML written");
+        return Ok(());
+    };
+
+    let percent = |ms: u64| ms as f64 * 100.0 / timeline.duration as f64;
+    let mut segments = String::new();
+    // Overlapping chapters are stacked on separate lanes
+    let mut lane_ends: Vec<u64> = Vec::new();
+    for (i, chapter) in timeline.chapters.iter().enumerate() {
+        let (start, end) = (chapter.start_time as u64, chapter.end_time as u64);
+        let lane = lane_ends.iter().position(|&lane_end| lane_end <= start).unwrap_or(lane_ends.len());
+        if lane == lane_ends.len() {
+            lane_ends.push(end);
+        } else {
+            lane_ends[lane] = end;
+        }
+        let title = chapter.title().unwrap_or(&chapter.element_id);
+        segments.push_str(&format!(
+            "<div class=\"chapter c{}\" style=\"left:{:.4}%;width:{:.4}%;top:{}em\" title=\"{} ({} - {})\">{}</div>\n",
+            i % 2,
+            percent(start),
+            percent(end.saturating_sub(start)),
+            lane * 2,
+            escape_html(title),
+            format_duration(chapter.start_time as u64),
+            format_duration(chapter.end_time as u64),
+            escape_html(title)
+        ));
+    }
+    for &(start, end) in &timeline.gaps {
+        segments.push_str(&format!(
+            "<div class=\"gap\" style=\"left:{:.4}%;width:{:.4}%;height:{}em\" title=\"Gap {} - {}\"></div>\n",
+            percent(start),
+            percent(end - start),
+            lane_ends.len().max(1) * 2,
+            format_duration(start),
+            format_duration(end)
+        ));
+    }
+
+    let mut rows = String::new();
+    for chapter in &timeline.chapters {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&chapter.element_id),
+            format_duration(chapter.start_time as u64),
+            format_duration(chapter.end_time as u64),
+            escape_html(chapter.title().unwrap_or(""))
+        ));
+    }
+    let mut notes = String::new();
+    for &(first, second, amount) in &timeline.overlaps {
+        notes.push_str(&format!(
+            "<li>Overlap: {} and {} overlap by {}</li>\n",
+            escape_html(&timeline.chapters[first].element_id),
+            escape_html(&timeline.chapters[second].element_id),
+            format_duration(amount)
+        ));
+    }
+    for &(start, end) in &timeline.gaps {
+        notes.push_str(&format!("<li>Gap: {} - {} ({})</li>\n", format_duration(start), format_duration(end), format_duration(end - start)));
+    }
+
+    let name = escape_html(&display_path(file_path).to_string());
+    let html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Chapter timeline: {name}</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         .bar {{ position: relative; height: {height}em; background: #eee; }}\n\
+         .chapter {{ position: absolute; height: 2em; overflow: hidden; white-space: nowrap; line-height: 2em; box-sizing: border-box; border-left: 1px solid #fff; }}\n\
+         .c0 {{ background: #8ab4f8; }}\n.c1 {{ background: #a8dab5; }}\n\
+         .gap {{ position: absolute; top: 0; background: repeating-linear-gradient(45deg, #f28b82, #f28b82 4px, #fff 4px, #fff 8px); }}\n\
+         td, th {{ padding: 0.2em 1em; text-align: left; }}\n</style>\n</head>\n<body>\n\
+         <h1>Chapter timeline</h1>\n<p>{name}: {count} chapter(s), duration {duration} ({source})</p>\n\
+         <div class=\"bar\">\n{segments}</div>\n<ul>\n{notes}</ul>\n\
+         <table>\n<tr><th>Element ID</th><th>Start</th><th>End</th><th>Title</th></tr>\n{rows}</table>\n</body>\n</html>\n",
+        height = lane_ends.len().max(1) * 2,
+        count = timeline.chapters.len(),
+        duration = format_duration(timeline.duration),
+        source = timeline.duration_source,
+    );
+
+    let mut html_path = file_path.as_os_str().to_owned();
+    html_path.push(".timeline.html");
+    std::fs::write(&html_path, html)?;
+    outln!("\nChapter timeline written: {}", display_path(Path::new(&html_path)));
+    Ok(())
+}
+
+/// Without the `report` feature there is no HTML output
+#[cfg(not(feature = "report"))]
+pub fn write_html_timeline(_file: &mut dyn MediaSource, _file_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--timeline=html requires supertool to be built with the report feature (cargo build --features report)".into())
+}
+
+#[cfg(feature = "report")]
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,228 @@
+/// Overflow-safe extents of frames, chunks and boxes
+///
+/// Sizes in headers come from the file and can be anything up to 0xFFFFFFFF (or 2^64 - 1 for
+/// 64-bit box sizes). Computing an end as `pos + 10 + size as usize` overflows on 32-bit
+/// targets and wraps to a small number, and comparing against `(len - pos) as u32` truncates
+/// buffers over 4 GB, so a corrupt size could panic or pass the check. The walkers compute
+/// every extent through these functions instead, which fail with a `SizeError` naming the
+/// structure, where it starts, the size it claims and the bytes that are left.
+use std::fmt;
+use std::ops::Range;
+
+/// A structure claims more bytes than there are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeError {
+    /// What was being read ("frame 'TIT2'", "chunk 'LIST'", "box 'moov'")
+    pub structure: String,
+    /// Offset of the structure's header in the buffer or file
+    pub offset: u64,
+    /// Header and data size the structure claims
+    pub claimed: u64,
+    /// Bytes from the offset to the end of the buffer or parent
+    pub available: u64,
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {} claims {} bytes, but only {} are left", self.structure, self.offset, self.claimed, self.available)
+    }
+}
+
+impl std::error::Error for SizeError {}
+
+/// Range of the data of a structure with a `header`-byte header at `pos`, in a buffer of `len` bytes
+pub fn data_range(structure: impl Into<String>, pos: usize, header: usize, size: u64, len: usize) -> Result<Range<usize>, SizeError> {
+    let end = (header as u64).checked_add(size).and_then(|total| (pos as u64).checked_add(total)).filter(|&end| end <= len as u64);
+    match end {
+        | Some(end) => Ok(pos + header..end as usize),
+        | None => Err(SizeError { structure: structure.into(), offset: pos as u64, claimed: (header as u64).saturating_add(size), available: len.saturating_sub(pos) as u64 }),
+    }
+}
+
+/// End of a structure of `size` bytes (header included) at `offset`, which must not pass `limit`
+pub fn end_offset(structure: impl Into<String>, offset: u64, size: u64, limit: u64) -> Result<u64, SizeError> {
+    match offset.checked_add(size) {
+        | Some(end) if end <= limit => Ok(end),
+        | _ => Err(SizeError { structure: structure.into(), offset, claimed: size, available: limit.saturating_sub(offset) }),
+    }
+}
+
+/// `size` as the length of a buffer to read into; over 4 GB does not fit on 32-bit targets
+pub fn buffer_length(structure: impl Into<String>, offset: u64, size: u64) -> Result<usize, SizeError> {
+    usize::try_from(size).map_err(|_| SizeError { structure: structure.into(), offset, claimed: size, available: usize::MAX as u64 })
+}
+
+/// `data[pos..pos + length]`, if the buffer holds that many bytes at `pos`
+pub fn slice_at(data: &[u8], pos: usize, length: usize) -> Option<&[u8]> {
+    data.get(pos..pos.checked_add(length)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id3v2_3_dissector::parse_id3v2_3_frame;
+    use crate::id3v2_repair::repair_tag;
+    use crate::id3v2_tag::Id3v2Tag;
+    use crate::id3v2_validation::{FRAME_SIZE, validate_tag};
+    use crate::isobmff_atom::parse_atoms;
+    use crate::isobmff_box::read_boxes;
+    use crate::riff_chunk::RiffFile;
+    use crate::zip_archive::ZipArchive;
+    use std::io::Cursor;
+
+    /// The largest 32-bit size, as corrupt or hostile headers claim it
+    const ADVERSARIAL: u32 = 0xFFFF_FFFF;
+
+    fn id3v2_3_frame(id: &[u8; 4], size: u32, data: &[u8]) -> Vec<u8> {
+        [&id[..], &size.to_be_bytes(), &[0, 0], data].concat()
+    }
+
+    fn id3v2_3_tag(body: &[u8]) -> Vec<u8> {
+        let size = body.len() as u32;
+        let synchsafe = [(size >> 21) as u8 & 0x7F, (size >> 14) as u8 & 0x7F, (size >> 7) as u8 & 0x7F, size as u8 & 0x7F];
+        [&b"ID3\x03\x00\x00"[..], &synchsafe, body].concat()
+    }
+
+    fn chunk(id: &[u8; 4], size: u32, data: &[u8]) -> Vec<u8> {
+        [&id[..], &size.to_le_bytes(), data].concat()
+    }
+
+    /// A stored ZIP archive with one member whose sizes in the central directory are `compressed_size`
+    fn zip_archive(name: &str, data: &[u8], compressed_size: u32, directory_size: Option<u32>) -> Vec<u8> {
+        let name = name.as_bytes();
+        let length = data.len() as u32;
+        let mut archive = [&0x04034B50u32.to_le_bytes()[..], &[20, 0, 0, 0, 0, 0, 0, 0, 0, 0], &0u32.to_le_bytes(), &length.to_le_bytes(), &length.to_le_bytes(), &(name.len() as u16).to_le_bytes(), &[0, 0], name, data].concat();
+        let directory_offset = archive.len() as u32;
+        let directory = [&0x02014B50u32.to_le_bytes()[..], &[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0], &0u32.to_le_bytes(), &compressed_size.to_le_bytes(), &length.to_le_bytes(), &(name.len() as u16).to_le_bytes(), &[0; 12], &0u32.to_le_bytes(), name].concat();
+        let directory_size = directory_size.unwrap_or(directory.len() as u32);
+        archive.extend_from_slice(&directory);
+        archive.extend_from_slice(&[&0x06054B50u32.to_le_bytes()[..], &[0, 0, 0, 0, 1, 0, 1, 0], &directory_size.to_le_bytes(), &directory_offset.to_le_bytes(), &[0, 0]].concat());
+        archive
+    }
+
+    #[test]
+    fn data_range_of_a_fitting_structure() {
+        assert_eq!(data_range("frame 'TIT2'", 4, 10, 6, 20), Ok(14..20));
+    }
+
+    #[test]
+    fn data_range_rejects_an_adversarial_size() {
+        let error = data_range("frame 'TIT2'", 4, 10, ADVERSARIAL as u64, 64).unwrap_err();
+        assert_eq!(error, SizeError { structure: "frame 'TIT2'".to_string(), offset: 4, claimed: ADVERSARIAL as u64 + 10, available: 60 });
+    }
+
+    #[test]
+    fn data_range_does_not_overflow() {
+        let error = data_range("box 'mdat'", usize::MAX - 4, 16, u64::MAX, usize::MAX).unwrap_err();
+        assert_eq!(error.claimed, u64::MAX);
+        assert_eq!(error.available, 4);
+        assert!(data_range("box 'mdat'", 100, 16, 0, 50).is_err());
+    }
+
+    #[test]
+    fn end_offset_buffer_length_and_slice_at_do_not_overflow() {
+        assert_eq!(end_offset("chunk 'data'", 12, 88, 100), Ok(100));
+        let error = end_offset("chunk 'data'", 12, u64::MAX, 100).unwrap_err();
+        assert_eq!((error.claimed, error.available), (u64::MAX, 88));
+        assert_eq!(buffer_length("box 'mdat'", 0, u64::MAX).is_err(), usize::BITS < 64);
+        assert_eq!(slice_at(&[1, 2, 3], usize::MAX, 2), None);
+        assert_eq!(slice_at(&[1, 2, 3], 1, 2), Some(&[2, 3][..]));
+    }
+
+    #[test]
+    fn id3v2_3_frame_with_an_adversarial_size() {
+        let body = [id3v2_3_frame(b"TIT2", 6, b"\0Title"), id3v2_3_frame(b"TALB", ADVERSARIAL, b"\0Album")].concat();
+        let tag = id3v2_3_tag(&body);
+
+        assert!(parse_id3v2_3_frame(&body, 16).is_none());
+        let error = data_range("frame 'TALB'", 16, 10, ADVERSARIAL as u64, body.len()).unwrap_err();
+        assert_eq!(error.available, body.len() as u64 - 16);
+
+        // The walker keeps the frames before the corrupt one and stops there
+        let parsed = Id3v2Tag::read(&mut Cursor::new(&tag)).unwrap().unwrap();
+        let ids: Vec<&str> = parsed.frames.iter().map(|frame| frame.id.as_str()).collect();
+        assert_eq!(ids, ["TIT2"]);
+
+        let validation = validate_tag(&mut Cursor::new(&tag)).unwrap();
+        assert!(validation.findings.iter().any(|finding| finding.rule == FRAME_SIZE.id && finding.finding.location == "TALB"));
+    }
+
+    #[test]
+    fn id3v2_3_extended_header_with_an_adversarial_size() {
+        let body = [&ADVERSARIAL.to_be_bytes()[..], &[0; 6], &id3v2_3_frame(b"TIT2", 6, b"\0Title")].concat();
+        let mut tag = id3v2_3_tag(&body);
+        tag[5] = 0x40;
+
+        let validation = validate_tag(&mut Cursor::new(&tag)).unwrap();
+        let finding = validation.findings.iter().find(|finding| finding.finding.location == "extended header").unwrap();
+        assert_eq!(finding.rule, FRAME_SIZE.id);
+        assert!(finding.finding.message.contains(&format!("claims {} bytes", ADVERSARIAL as u64 + 4)));
+        assert!(Id3v2Tag::read(&mut Cursor::new(&tag)).unwrap().unwrap().frames.is_empty());
+    }
+
+    #[test]
+    fn repair_drops_an_extended_header_with_an_adversarial_size() {
+        let body = [&ADVERSARIAL.to_be_bytes()[..], &[0; 6], &id3v2_3_frame(b"TIT2", 6, b"\0Title"), &[0; 8]].concat();
+        let mut tag = id3v2_3_tag(&body);
+        tag[5] = 0x40;
+
+        let repair = repair_tag(&mut Cursor::new(&tag)).unwrap().unwrap();
+        assert!(repair.fixes.iter().any(|fix| fix.starts_with("extended header does not fit")));
+        assert_eq!(repair.bytes[5] & 0x40, 0);
+        assert_eq!(&repair.bytes[10..14], b"TIT2");
+    }
+
+    #[test]
+    fn riff_chunk_with_an_adversarial_size() {
+        let chunks = [chunk(b"fmt ", 16, &[0; 16]), chunk(b"data", ADVERSARIAL, &[0; 8])].concat();
+        let file = [&b"RIFF"[..], &(chunks.len() as u32 + 4).to_le_bytes(), b"WAVE", &chunks].concat();
+
+        let riff = RiffFile::read(&mut Cursor::new(&file)).unwrap().unwrap();
+        let data = riff.chunk("data").unwrap();
+        assert_eq!(data.size, 8);
+        assert!(riff.problems.iter().any(|problem| problem.contains("claims 4294967295 bytes but only 8 remain")));
+    }
+
+    #[test]
+    fn rf64_chunk_with_an_adversarial_ds64_size() {
+        let ds64 = [&u64::MAX.to_le_bytes()[..], &u64::MAX.to_le_bytes(), &0u64.to_le_bytes(), &0u32.to_le_bytes()].concat();
+        let chunks = [chunk(b"ds64", ds64.len() as u32, &ds64), chunk(b"data", ADVERSARIAL, &[0; 8])].concat();
+        let file = [&b"RF64"[..], &ADVERSARIAL.to_le_bytes(), b"WAVE", &chunks].concat();
+
+        let riff = RiffFile::read(&mut Cursor::new(&file)).unwrap().unwrap();
+        let data = riff.chunk("data").unwrap();
+        assert!(data.size_from_ds64);
+        assert_eq!(data.size, 8);
+        assert!(riff.problems.iter().any(|problem| problem.contains(&format!("claims {} bytes", u64::MAX))));
+        assert!(riff.problems.iter().any(|problem| problem.contains("does not match the file size")));
+    }
+
+    #[test]
+    fn isobmff_box_with_an_adversarial_64_bit_size() {
+        let file = [&1u32.to_be_bytes()[..], b"mdat", &u64::MAX.to_be_bytes(), &[0; 8]].concat();
+
+        assert!(read_boxes(&mut Cursor::new(&file), 0, file.len() as u64).unwrap().is_empty());
+        let error = parse_atoms(&file).err().unwrap();
+        assert!(error.contains("box 'mdat' at offset 0 claims") || error == "box too large", "{}", error);
+        assert!(end_offset("box 'mdat'", 0, u64::MAX, file.len() as u64).is_err());
+    }
+
+    #[test]
+    fn zip_member_with_an_adversarial_size() {
+        let file = zip_archive("track.mp3", b"ID3", ADVERSARIAL, None);
+        let archive = ZipArchive::read(&mut Cursor::new(&file)).unwrap();
+
+        let error = archive.open_member(&mut Cursor::new(&file), "track.mp3").err().unwrap();
+        let error = error.downcast_ref::<SizeError>().unwrap();
+        assert_eq!((error.structure.as_str(), error.claimed), ("ZIP member 'track.mp3'", ADVERSARIAL as u64));
+    }
+
+    #[test]
+    fn zip_central_directory_with_an_adversarial_size() {
+        let file = zip_archive("track.mp3", b"ID3", 3, Some(ADVERSARIAL));
+
+        let error = ZipArchive::read(&mut Cursor::new(&file)).err().unwrap();
+        let error = error.downcast_ref::<SizeError>().unwrap();
+        assert_eq!((error.structure.as_str(), error.claimed), ("ZIP central directory", ADVERSARIAL as u64));
+    }
+}
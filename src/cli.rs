@@ -1,5 +1,9 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use supertool::health_score::CategoryWeight;
+use supertool::id3v2_genre::GenreStyle;
+use supertool::id3v2_text_encoding::TextEncoding;
+use supertool::options::{AssumedEncoding, ChapterFormat, CrcReport, EllipsisStyle, EventsFormat, FollowLinks, LyricsFormat, LyricsFrameName, Mp4ChapterStyle, ReportFormat, SidecarFormat, TimeFormat, TimelineFormat};
 
 #[derive(Parser)]
 #[command(name = "supertool")]
@@ -8,14 +12,77 @@ use std::path::PathBuf;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Format of all times and durations (chapters, timelines, media durations)
+    #[arg(long, global = true, value_enum, default_value = "hms")]
+    pub time_format: TimeFormat,
+
+    /// Shorten displayed text values to this many characters (grapheme clusters), the ellipsis included
+    #[arg(long, global = true, value_name = "CHARACTERS")]
+    pub max_text_width: Option<usize>,
+
+    /// Which part of a shortened value is replaced by the ellipsis
+    #[arg(long, global = true, value_enum, default_value = "end")]
+    pub ellipsis: EllipsisStyle,
+
+    /// Deepest nesting of CHAP/CTOC frames and CTOC references that is dissected
+    #[arg(long, global = true, value_name = "LEVELS", default_value_t = supertool::id3v2_tools::DEFAULT_MAX_EMBEDDING_DEPTH)]
+    pub max_frame_depth: usize,
+
+    /// Parse de-facto experimental frames (XSOP, XSOA, XSOT, XDOR) like their standard counterparts
+    #[arg(long, global = true)]
+    pub lenient: bool,
+
+    /// Read text that frames declare as ISO-8859-1 in this legacy code page (for display and for tag reencode)
+    #[arg(long, global = true, value_name = "CODEPAGE")]
+    pub assume_encoding: Option<AssumedEncoding>,
+
+    /// Normalize all decoded text to Unicode NFC (precomposed characters)
+    #[arg(long, global = true)]
+    pub nfc: bool,
+
+    /// Write progress events (file-start, tag-found, frame-count, warning, file-done) to stderr for wrapping programs
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub events: Option<EventsFormat>,
+
+    /// Let batch runs succeed although some files or directories could not be read (they are still listed)
+    #[arg(long, global = true)]
+    pub continue_on_error: bool,
+
+    /// Parse each file of debug and daemon in a child process with resource limits (and seccomp on Linux), so a parser crash fails that file only
+    #[arg(long, global = true)]
+    pub sandbox: bool,
 }
 
+// Parsed once per run, so the size of the debug variant does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Debug and analyze media files (ID3v2/MP3, ISO BMFF/MP4)
     Debug {
-        /// Path to the media file to analyze
-        file: PathBuf,
+        /// Media files, directories (with --recursive) or glob patterns to analyze (e.g. "album/*.mp3", "feed/**/*.m4a")
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Walk directories and analyze every media file below them (files no dissector recognizes are left out)
+        #[arg(long, short)]
+        recursive: bool,
+
+        /// Analyze only files of directories and patterns with these extensions, comma-separated (e.g. mp3,m4a)
+        #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+        ext: Vec<String>,
+
+        /// Leave out files of directories and patterns with these extensions, comma-separated
+        #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+        exclude_ext: Vec<String>,
+
+        /// Symbolic links to follow when walking directories (files reached through several links are analyzed once)
+        #[arg(long, value_enum, value_name = "POLICY", default_value = "never")]
+        follow_links: FollowLinks,
+
+        /// Dissect this many files at the same time (default: one per CPU)
+        #[arg(long, short, value_name = "N")]
+        jobs: Option<usize>,
 
         /// Show only header information (ID3v2/ISO BMFF header)
         #[arg(long)]
@@ -28,29 +95,683 @@ pub enum Commands {
         /// Show both header and frames/boxes (default if no options specified)
         #[arg(long)]
         all: bool,
+
+        /// List only the frames (ID3v2) or boxes (MP4) with these IDs, comma-separated (e.g. TIT2,APIC or moov,trak)
+        #[arg(long, value_name = "IDS", value_delimiter = ',')]
+        frame_id: Vec<String>,
+
+        /// Annotate the raw bytes of every frame with this ID field by field (e.g. TIT2)
+        #[arg(long, value_name = "FRAME_ID")]
+        explain_frame: Option<String>,
+
+        /// Write a machine-readable summary next to each analyzed file (<file>.supertool.json, .yaml or .xml)
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        sidecar: Option<SidecarFormat>,
+
+        /// Write sidecar files into this directory, mirroring the input paths, instead of next to the files
+        #[arg(long, value_name = "DIR", requires = "sidecar")]
+        sidecar_dir: Option<PathBuf>,
+
+        /// Send HEAD requests to the URLs of W*** and WXXX frames and report status and redirects (requires the network feature)
+        #[arg(long)]
+        check_urls: bool,
+
+        /// Only show COMM, USLT and SYLT frames in this ISO 639-2 language (e.g. deu)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Verify the CRC of every protected MPEG audio frame and map the corrupt ones (score prints only the health percentage)
+        #[arg(long, value_enum, value_name = "REPORT", num_args = 0..=1, require_equals = true, default_missing_value = "map")]
+        verify_crc: Option<CrcReport>,
+
+        /// Report CBR/VBR, a bitrate histogram and whether the Xing/Info/VBRI header matches the MPEG frames
+        #[arg(long)]
+        bitrates: bool,
+
+        /// Draw the chapters as a timeline with gaps and overlaps marked (html requires the report feature)
+        #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "ascii")]
+        timeline: Option<TimelineFormat>,
+
+        /// Break the file size down into tags, frame types, audio and boxes, with their share of the file
+        #[arg(long)]
+        sizes: bool,
+
+        /// List the sync samples and fragment random access points of MP4 tracks and flag long stretches without them
+        #[arg(long)]
+        seek_points: bool,
+
+        /// Map which byte ranges of an MP4 file each interval of media time needs (default 1 second) and check for fast start
+        #[arg(long, value_name = "SECONDS", num_args = 0..=1, require_equals = true, default_missing_value = "1")]
+        chunk_map: Option<f64>,
+
+        /// Deepest nesting of MP4 container boxes (moov, trak, mdia, ...) that is listed; 0 lists the top-level boxes only [default: 8]
+        #[arg(long, value_name = "LEVELS")]
+        box_depth: Option<usize>,
+
+        /// Write the objects embedded in GEOB frames into this directory under their original filenames
+        #[arg(long, value_name = "DIR")]
+        extract_geob: Option<PathBuf>,
+
+        /// Dissect this member of a ZIP bundle (path within the archive or file name) instead of the archive
+        #[arg(long, value_name = "NAME")]
+        inner: Option<String>,
+
+        /// Print the structures of the file as a tree with offsets, values and diagnostics instead of the dissection
+        #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["sidecar", "forensic"])]
+        report: Option<ReportFormat>,
+
+        /// Hash the file before and after and write a signed JSON evidence report with the provenance of every structure
+        #[arg(long, value_name = "REPORT", requires = "forensic_key", conflicts_with_all = ["sidecar", "inner", "extract_geob"])]
+        forensic: Option<PathBuf>,
+
+        /// File whose contents are the HMAC-SHA256 key that signs the evidence report
+        #[arg(long, value_name = "KEY_FILE", requires = "forensic")]
+        forensic_key: Option<PathBuf>,
+    },
+    /// Export the chapters (CHAP) as CUE sheet, ffmpeg metadata, WebVTT or Podlove Simple Chapters JSON
+    Chapters {
+        /// Media file to read the chapters from
+        file: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum)]
+        format: ChapterFormat,
+
+        /// Write the chapters to this file instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Diff the ID3v2 tags of two files: frames only in one, differing values, artwork sizes and tag versions
+    Compare {
+        /// Original file
+        first: PathBuf,
+
+        /// File to compare with it (e.g. after a rewrite by another tool)
+        second: PathBuf,
+    },
+    /// Count the files of a large directory tree by format from their first bytes and report the crawl throughput
+    Crawl {
+        /// Directory to crawl recursively
+        dir: PathBuf,
+
+        /// Open and read this many files at the same time (default: four per CPU)
+        #[arg(long, short, value_name = "N")]
+        jobs: Option<usize>,
+
+        /// Also list every file with its format and ID3v2 tag header
+        #[arg(long)]
+        list: bool,
+
+        /// Symbolic links to follow (files reached through several links are counted once)
+        #[arg(long, value_enum, value_name = "POLICY", default_value = "never")]
+        follow_links: FollowLinks,
+    },
+    /// Serve analysis requests over a local socket (length-prefixed JSON) for front-ends, with summaries cached between requests
+    Daemon {
+        /// Path of the Unix domain socket to listen on
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+    },
+    /// Find likely duplicate tracks in a directory (same audio or same MusicBrainz recording)
+    Dupes {
+        /// Directory to scan recursively
+        dir: PathBuf,
+    },
+    /// Report which text encodings are used by which frames and plan the normalization to UTF-8
+    Encodings {
+        /// Media file or directory (scanned recursively)
+        path: PathBuf,
+    },
+    /// Write a copy of an MP4 file with the movie box (moov) moved in front of the media data, for progressive download
+    Faststart {
+        /// MP4/M4A/MOV file to read
+        input: PathBuf,
+
+        /// File to write the fast-start copy to
+        output: PathBuf,
+    },
+    /// List the supported formats with their extensions, signatures and operations
+    Formats {
+        /// Print the list as JSON for scripts
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the ICY (SHOUTcast/Icecast) metadata blocks of a captured stream with their offsets and estimated times
+    Icy {
+        /// Stream dump as received with 'Icy-MetaData: 1' (audio with interleaved metadata blocks)
+        file: PathBuf,
+
+        /// Audio bytes between metadata blocks (the icy-metaint response header of the server)
+        #[arg(long, value_name = "BYTES")]
+        metaint: u64,
+
+        /// Bitrate in kbit/s for the time estimates (default: from the first MPEG audio frame)
+        #[arg(long, value_name = "KBPS")]
+        bitrate: Option<u32>,
+    },
+    /// Write a copy of an MP4/M4A/M4B file with the chapters (CHAP) of an ID3v2 tag as chpl box and QuickTime chapter track
+    Mp4Chapters {
+        /// MP4/M4A/M4B file to add the chapters to
+        input: PathBuf,
+
+        /// File to write the copy with chapters to
+        output: PathBuf,
+
+        /// File whose ID3v2 tag has the chapters (e.g. the MP3 edition)
+        #[arg(long, value_name = "FILE")]
+        from: PathBuf,
+
+        /// Where to write the chapters
+        #[arg(long, value_enum, default_value = "both")]
+        style: Mp4ChapterStyle,
+    },
+    /// Repair broken sizes, truncated frames and garbage padding in ID3v2.3 and ID3v2.4 tags
+    Repair {
+        /// Files to repair
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Write the repaired copy of a single file here instead of changing it in place
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Show the fixes without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove the ID3v2 tags (and optionally ID3v1 and APE tags) from MP3 files
+    Strip {
+        /// Files to strip
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Also remove ID3v1 tags (and Enhanced ID3v1 blocks) at the end
+        #[arg(long)]
+        id3v1: bool,
+
+        /// Also remove APEv1 and APEv2 tags at the end
+        #[arg(long)]
+        ape: bool,
+
+        /// Write the stripped copy of a single file here instead of changing it in place
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Show which tags would be removed and how many bytes that reclaims without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Score media files from 0 to 100 by the problems found in them and list them worst first
+    Triage {
+        /// Media file or directory (scanned recursively)
+        path: PathBuf,
+
+        /// Most points a category takes off the score (corruption, mismatch, encoding, artwork, metadata); repeatable
+        #[arg(long, value_name = "CATEGORY=POINTS")]
+        weight: Vec<CategoryWeight>,
+
+        /// List the problems behind each score
+        #[arg(long)]
+        details: bool,
+
+        /// Also list the files without problems
+        #[arg(long)]
+        all: bool,
+    },
+    /// Check ID3v2 tags against the specification and report violations by rule ID; exits non-zero if a file has errors
+    Validate {
+        /// Media files, directories (with --recursive) or glob patterns to validate
+        #[arg(required_unless_present = "rules")]
+        files: Vec<PathBuf>,
+
+        /// Walk directories and validate every media file below them
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Leave out the findings of these rules, comma-separated (e.g. frame-id-unknown,language-case)
+        #[arg(long, value_name = "RULES", value_delimiter = ',')]
+        ignore: Vec<String>,
+
+        /// Also fail for warnings
+        #[arg(long)]
+        strict: bool,
+
+        /// Print the findings as JSON for scripts
+        #[arg(long)]
+        json: bool,
+
+        /// List the rules with their IDs and severities
+        #[arg(long)]
+        rules: bool,
+    },
+    /// Record the tags and audio hashes of a directory, or check it against such a record
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineCommands,
+    },
+    /// Export embedded data to files
+    Extract {
+        #[command(subcommand)]
+        action: ExtractCommands,
+    },
+    /// Edit metadata tags
+    Tag {
+        /// Keep the modification time of rewritten files
+        #[arg(long, global = true)]
+        preserve_mtime: bool,
+
+        /// Write the frames in the recommended order (identifiers first, pictures last)
+        #[arg(long, global = true)]
+        canonical_order: bool,
+
+        /// Store the text of text, comment and lyrics frames NFC-normalized (precomposed characters)
+        #[arg(long, global = true)]
+        store_nfc: bool,
+
+        /// Write even if the file holds structures the rewrite could damage (appended tags, unknown trailing data, encrypted frames)
+        #[arg(long, global = true)]
+        force: bool,
+
+        #[command(subcommand)]
+        action: TagCommands,
     },
 }
 
-/// Options for controlling debug output
-#[derive(Debug, Clone)]
-pub struct DebugOptions {
-    pub show_header: bool,
-    pub show_frames: bool,
+#[derive(Subcommand)]
+pub enum ExtractCommands {
+    /// Export the lyrics (SYLT preferred, USLT as fallback) as LRC or plain text
+    Lyrics {
+        /// Media file to read the lyrics from
+        file: PathBuf,
+
+        /// Output format (LRC keeps the time stamps of synchronised lyrics)
+        #[arg(long, value_enum, default_value = "lrc")]
+        format: LyricsFormat,
+
+        /// Only use lyrics frames in this ISO 639-2 language (e.g. deu)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Write the lyrics to this file instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Write every attached picture (APIC), including chapter images, to files named by picture type and chapter
+    Artwork {
+        /// Media file to read the pictures from
+        file: PathBuf,
+
+        /// Directory to write the pictures to (created if missing)
+        #[arg(long, short, value_name = "DIR")]
+        out: PathBuf,
+    },
+    /// Export the raw payload of an ISO BMFF box (everything after its header)
+    Box {
+        /// MP4/M4A/MOV file to read the box from
+        file: PathBuf,
+
+        /// Box path from the top level, e.g. moov/udta/meta/ilst; trak[1] is the second 'trak' box
+        #[arg(long, value_name = "PATH")]
+        path: String,
+
+        /// File to write the payload to
+        #[arg(long, short, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Demux one track of an MP4/M4A/MOV file into an elementary stream (AAC as ADTS, H.264/H.265 as Annex B)
+    Track {
+        /// MP4/M4A/MOV file to read the track from
+        file: PathBuf,
+
+        /// Track ID as shown by 'debug' (e.g. 2)
+        #[arg(long, value_name = "ID")]
+        track: u32,
+
+        /// File to write the stream to
+        #[arg(long, short, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Write the raw GPMF telemetry samples of a GoPro MP4 ('gpmd' track) to a file
+    Gpmf {
+        /// MP4 file to read the telemetry from
+        file: PathBuf,
+
+        /// Track ID of the GPMF track (default: the first one)
+        #[arg(long, value_name = "ID")]
+        track: Option<u32>,
+
+        /// File to write the samples to
+        #[arg(long, short, value_name = "FILE")]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BaselineCommands {
+    /// Record the frames (values and hashes) and audio hash of every media file in a directory
+    Save {
+        /// Directory to scan recursively
+        dir: PathBuf,
+
+        /// File to write the baseline (JSON) to
+        #[arg(long, short, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Report files, frames, values and hashes that changed since the baseline (fails if anything changed)
+    Check {
+        /// Directory to scan recursively
+        dir: PathBuf,
+
+        /// Baseline written by 'baseline save'
+        #[arg(long, value_name = "FILE")]
+        against: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    /// Set text frames (--set TIT2="New Title") and attach artwork, keeping the tag version (new tags are ID3v2.4)
+    Write {
+        /// Files to write
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Field to set: a text frame ID, TXXX:<description> or a name like title, artist, album, year; repeatable
+        #[arg(long, value_name = "FIELD=VALUE")]
+        set: Vec<String>,
+
+        /// JPEG or PNG picture to attach (replaces the pictures of the same type)
+        #[arg(long, value_name = "FILE")]
+        artwork: Option<PathBuf>,
+
+        /// APIC picture type of the artwork (3 = front cover, 4 = back cover, ...)
+        #[arg(long, default_value_t = 3, requires = "artwork")]
+        picture_type: u8,
+
+        /// Show the changes without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Apply tag values from a CSV file (one row per file, 'path' column plus one column per field)
+    ApplyCsv {
+        /// CSV file with a 'path' column and columns like title, artist, TIT2 or TXXX:<description>
+        csv: PathBuf,
+
+        /// Show the changes without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Normalize genres (TCON) and rewrite them in one style
+    Genres {
+        /// Files to rewrite
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Genre style: "(17)(4)Eurodisco" references, "Rock/Disco" or one value per genre (ID3v2.4 only)
+        #[arg(long, value_enum)]
+        style: GenreStyleName,
+
+        /// Show the new genre values without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Set the lyrics (SYLT/USLT) from an LRC or text file, or convert between the two frames
+    SetLyrics {
+        /// File to write the lyrics to
+        file: PathBuf,
+
+        /// LRC file (sets SYLT and USLT) or plain text file (sets USLT)
+        #[arg(long, value_name = "LYRICS_FILE", required_unless_present = "from_frame", conflicts_with = "from_frame")]
+        from: Option<PathBuf>,
+
+        /// Build USLT from the SYLT frame, or SYLT from a USLT frame containing LRC
+        #[arg(long, value_enum, value_name = "FRAME")]
+        from_frame: Option<LyricsFrameName>,
+
+        /// ISO 639-2 language code of the lyrics frames
+        #[arg(long, default_value = "eng")]
+        lang: String,
+
+        /// Show which frames would be set without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert ID3v2 tags between ID3v2.3 and ID3v2.4, mapping the frames and reporting every decision
+    Convert {
+        /// Files to convert
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Target version (TYER/TDAT/TIME become TDRC, IPLS becomes TIPL, and back)
+        #[arg(long, value_enum)]
+        to: TagVersionName,
+
+        /// Show the mapping decisions without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-encode all text frames of ID3v2 tags
+    Reencode {
+        /// Files to re-encode
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Target text encoding (UTF-8 and UTF-16BE require ID3v2.4)
+        #[arg(long, value_enum, default_value = "utf8")]
+        encoding: EncodingName,
+
+        /// Show how many frames would change without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Merge, split, shift or normalize chapters (CHAP/CTOC), renumbering their element IDs
+    Chapters {
+        #[command(subcommand)]
+        action: ChapterCommands,
+    },
+    /// Edit the language and name of MP4 tracks
+    Mp4 {
+        #[command(subcommand)]
+        action: Mp4Commands,
+    },
+    /// Fix empty or mismatching sort order frames (TSOT, TSOP, TSOA, TSO2, TSOC), leaving out leading articles
+    FixSort {
+        /// Files to fix
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Show the sort values that would be set or removed without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Set the disc position (TPOS, 'disk') and compilation flag (TCMP, 'cpil') of ID3v2 tags and MP4 files
+    SetDisc {
+        /// Files to change
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Disc within the set, with the number of discs if known (e.g. 2 or 2/3)
+        #[arg(long, value_name = "N[/M]", required_unless_present_any = ["compilation", "no_compilation"])]
+        disc: Option<String>,
+
+        /// Mark the files as part of a compilation
+        #[arg(long, conflicts_with = "no_compilation")]
+        compilation: bool,
+
+        /// Mark the files as not part of a compilation (removes TCMP from ID3v2 tags)
+        #[arg(long)]
+        no_compilation: bool,
+
+        /// Check the values without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Put the frames of ID3v2 tags into the recommended order
+    Reorder {
+        /// Files to rewrite
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Show how many frames would move without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum Mp4Commands {
+    /// Set the language of a track (mdhd, and elng with --elng), in place when the boxes keep their size
+    SetTrackLang {
+        /// MP4/M4A/MOV file to edit
+        file: PathBuf,
+
+        /// Track ID (as listed by debug)
+        #[arg(long, value_name = "ID")]
+        track: u32,
+
+        /// ISO 639-2/T language code, e.g. deu, eng or und
+        #[arg(long, value_name = "CODE")]
+        lang: String,
+
+        /// Also write this BCP 47 tag (e.g. de-CH) as extended language; without it an existing elng box is removed
+        #[arg(long, value_name = "TAG")]
+        elng: Option<String>,
+
+        /// Show the change without writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Set the name of a track (udta/name), in place when the boxes keep their size
+    SetTrackName {
+        /// MP4/M4A/MOV file to edit
+        file: PathBuf,
+
+        /// Track ID (as listed by debug)
+        #[arg(long, value_name = "ID")]
+        track: u32,
+
+        /// New track name
+        #[arg(long)]
+        name: String,
+
+        /// Show the change without writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ChapterCommands {
+    /// Merge the two chapters that meet at a time into one
+    Merge {
+        /// File to edit
+        file: PathBuf,
+
+        /// Start of the second chapter (hh:mm:ss.mmm, mm:ss or seconds, within one second)
+        #[arg(long, value_name = "TIME")]
+        at: String,
+
+        /// Show the resulting chapters without writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Split a chapter into two at a time inside it
+    Split {
+        /// File to edit
+        file: PathBuf,
+
+        /// Number of the chapter in start time order (1 is the first)
+        #[arg(long, value_name = "N")]
+        chapter: usize,
+
+        /// Start of the new chapter (hh:mm:ss.mmm, mm:ss or seconds)
+        #[arg(long, value_name = "TIME")]
+        at: String,
+
+        /// Show the resulting chapters without writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Move all chapters by an offset
+    Shift {
+        /// File to edit
+        file: PathBuf,
+
+        /// Offset to add to all chapter times, e.g. -2.5s, +1500ms or -00:01.5
+        #[arg(long, value_name = "OFFSET", allow_hyphen_values = true)]
+        offset: String,
+
+        /// Show the resulting chapters without writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rename the chapters to chp001, chp002, ... and rebuild a single top-level ordered CTOC
+    Normalize {
+        /// Files to normalize
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Show the resulting chapters without writing any files
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
-impl DebugOptions {
-    pub fn from_flags(header: bool, frames: bool, all: bool) -> Self {
-        // If no flags specified, default to showing everything
-        if !header && !frames && !all {
-            return DebugOptions { show_header: true, show_frames: true };
+/// Genre styles that can be selected on the command line
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum GenreStyleName {
+    Id3v23,
+    Slash,
+    Multi,
+}
+
+impl GenreStyleName {
+    /// The genre style for this name
+    pub fn genre_style(&self) -> GenreStyle {
+        match self {
+            | GenreStyleName::Id3v23 => GenreStyle::Id3v23,
+            | GenreStyleName::Slash => GenreStyle::Slash,
+            | GenreStyleName::Multi => GenreStyle::MultiValue,
         }
+    }
+}
 
-        // If --all is specified, show everything regardless of other flags
-        if all {
-            return DebugOptions { show_header: true, show_frames: true };
+/// ID3v2 versions tags can be converted to
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TagVersionName {
+    #[value(name = "2.3")]
+    V2_3,
+    #[value(name = "2.4")]
+    V2_4,
+}
+
+impl TagVersionName {
+    /// The major version number for this name
+    pub fn version_major(&self) -> u8 {
+        match self {
+            | TagVersionName::V2_3 => 3,
+            | TagVersionName::V2_4 => 4,
         }
+    }
+}
 
-        // Otherwise, use the specific flags
-        DebugOptions { show_header: header, show_frames: frames }
+/// Text encodings that can be selected on the command line
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum EncodingName {
+    Latin1,
+    Utf16,
+    Utf16be,
+    Utf8,
+}
+
+impl EncodingName {
+    /// The ID3v2 text encoding for this name
+    pub fn text_encoding(&self) -> TextEncoding {
+        match self {
+            | EncodingName::Latin1 => TextEncoding::Iso88591,
+            | EncodingName::Utf16 => TextEncoding::Utf16Bom,
+            | EncodingName::Utf16be => TextEncoding::Utf16Be,
+            | EncodingName::Utf8 => TextEncoding::Utf8,
+        }
     }
 }
@@ -0,0 +1,225 @@
+/// Byte coverage of a dissection
+///
+/// Each dissector records which byte ranges of the file it interpreted and which it skipped
+/// (padding, unknown boxes or chunks, unparsed frames, junk between MPEG frames). Bytes in
+/// neither list are reported as unaccounted. A low percentage of interpreted bytes hints that
+/// the file was handed to the wrong dissector.
+use crate::id3v2_frame::Id3v2FrameContent;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::{read_audio_data, scan_frames};
+use crate::stream_tags::find_inline_tags;
+use crate::trailer_tags::trailers;
+use serde::Serialize;
+use std::io::{Read, SeekFrom};
+
+/// Skipped ranges listed in the text output before the rest is summarized
+const MAX_LISTED_RANGES: usize = 20;
+
+/// Byte range the dissector did not interpret
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedRange {
+    pub offset: u64,
+    pub size: u64,
+    /// Why the bytes were skipped (e.g. "padding", "unknown box 'abcd'")
+    pub reason: String,
+}
+
+/// Interpreted and skipped bytes of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct Coverage {
+    pub file_size: u64,
+    /// Number of bytes the dissector interpreted
+    pub interpreted: u64,
+    /// Skipped ranges in file order, including the bytes no structure accounts for
+    pub skipped: Vec<SkippedRange>,
+    /// Interpreted ranges (start, end), collected until `finish`
+    #[serde(skip)]
+    ranges: Vec<(u64, u64)>,
+}
+
+impl Coverage {
+    pub fn new(file_size: u64) -> Self {
+        Coverage { file_size, interpreted: 0, skipped: Vec::new(), ranges: Vec::new() }
+    }
+
+    /// Record `size` bytes at `offset` as interpreted
+    pub fn interpret(&mut self, offset: u64, size: u64) {
+        let end = offset.saturating_add(size).min(self.file_size);
+        if offset < end {
+            self.ranges.push((offset, end));
+        }
+    }
+
+    /// Record `size` bytes at `offset` as skipped
+    pub fn skip(&mut self, offset: u64, size: u64, reason: impl Into<String>) {
+        let size = size.min(self.file_size.saturating_sub(offset));
+        if size > 0 {
+            self.skipped.push(SkippedRange { offset, size, reason: reason.into() });
+        }
+    }
+
+    /// Count the interpreted bytes and add the gaps between all recorded ranges as unaccounted
+    pub fn finish(mut self) -> Self {
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::new();
+        for (start, end) in self.ranges.drain(..) {
+            match merged.last_mut() {
+                | Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                | _ => merged.push((start, end)),
+            }
+        }
+        self.interpreted = merged.iter().map(|(start, end)| end - start).sum();
+
+        let mut covered: Vec<(u64, u64)> = merged.into_iter().chain(self.skipped.iter().map(|range| (range.offset, range.offset + range.size))).collect();
+        covered.sort_unstable();
+        let mut pos = 0;
+        let mut gaps = Vec::new();
+        for (start, end) in covered {
+            if start > pos {
+                gaps.push(SkippedRange { offset: pos, size: start - pos, reason: "not accounted for by any structure".to_string() });
+            }
+            pos = pos.max(end);
+        }
+        if pos < self.file_size {
+            gaps.push(SkippedRange { offset: pos, size: self.file_size - pos, reason: "not accounted for by any structure".to_string() });
+        }
+        self.skipped.extend(gaps);
+        self.skipped.sort_by_key(|range| range.offset);
+        self
+    }
+
+    /// Share of the file that was interpreted, in percent
+    pub fn percentage(&self) -> f64 {
+        if self.file_size == 0 {
+            return 100.0;
+        }
+        self.interpreted as f64 * 100.0 / self.file_size as f64
+    }
+}
+
+/// Reason of skipped bytes after the last frame of an ID3v2 tag that are not zeros
+pub const UNPARSED_TAG_DATA: &str = "unparsed data after the last frame";
+
+/// Coverage of a file no dissector understands: everything is unaccounted
+pub fn not_dissected(file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+    Ok(Coverage::new(file.byte_len()?).finish())
+}
+
+/// Coverage of an MP3 file: ID3v2 tag, MPEG audio frames and the tag blocks after the audio
+pub fn mp3_coverage(file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+    let mut coverage = Coverage::new(file.byte_len()?);
+
+    if let Some(tag) = Id3v2Tag::read(file)? {
+        let body_end = 10 + tag.size as u64;
+        coverage.interpret(0, 10);
+        if tag.flags & 0x80 != 0 {
+            // Frame offsets refer to the resynchronised body, so the tag is taken as a whole
+            coverage.interpret(10, tag.size as u64);
+        } else {
+            let mut frames_end = 10;
+            if let Some(first) = tag.frames.first().and_then(|frame| frame.offset) {
+                coverage.interpret(10, first as u64);
+            }
+            for frame in &tag.frames {
+                let Some(offset) = frame.offset else {
+                    continue;
+                };
+                let start = 10 + offset as u64;
+                let size = tag.frame_header_size() + frame.size as u64;
+                match &frame.content {
+                    | Some(Id3v2FrameContent::Binary) => coverage.skip(start, size, format!("'{}' frame not dissected", frame.id)),
+                    | None => coverage.skip(start, size, format!("unparsed frame {}", frame.id)),
+                    | Some(_) => coverage.interpret(start, size),
+                }
+                frames_end = start + size;
+            }
+            // Padding is zeros; anything else after the last frame (e.g. a frame whose size is
+            // corrupt) is data the walk could not parse
+            let data_end = nonzero_end(file, frames_end, body_end.saturating_sub(frames_end))?;
+            coverage.skip(frames_end, data_end - frames_end, UNPARSED_TAG_DATA);
+            coverage.skip(data_end, body_end.saturating_sub(data_end), "padding");
+        }
+        if tag.total_size() > body_end {
+            coverage.interpret(body_end, tag.total_size() - body_end);
+        }
+    }
+
+    if let Some((audio_start, data)) = read_audio_data(file)? {
+        // Tags of stream rips between the frames are interpreted, the rest of the gaps skipped
+        let inline_tags: Vec<(u64, u64)> = find_inline_tags(&data, audio_start).iter().map(|tag| (tag.offset, tag.offset + tag.size)).collect();
+        for &(start, end) in &inline_tags {
+            coverage.interpret(start, end - start);
+        }
+        let mut pos = 0;
+        for (offset, header) in scan_frames(&data) {
+            let Some(length) = header.frame_length() else {
+                continue;
+            };
+            let reason = if pos == 0 {
+                "data before the first MPEG audio frame"
+            } else {
+                "junk between MPEG audio frames (resync)"
+            };
+            skip_outside(&mut coverage, audio_start + pos as u64, audio_start + offset as u64, reason, &inline_tags);
+            coverage.interpret(audio_start + offset as u64, length as u64);
+            pos = offset + length;
+        }
+        let reason = if pos == 0 {
+            "no MPEG audio frames found"
+        } else {
+            "data after the last MPEG audio frame"
+        };
+        skip_outside(&mut coverage, audio_start + pos as u64, audio_start + data.len() as u64, reason, &inline_tags);
+    }
+
+    for block in trailers(file)? {
+        coverage.interpret(block.offset, block.size);
+    }
+
+    Ok(coverage.finish())
+}
+
+/// End of the last non-zero byte among `size` bytes at `offset` (as far as the file goes); `offset` if all are zeros
+fn nonzero_end(file: &mut dyn MediaSource, offset: u64, size: u64) -> Result<u64, Box<dyn std::error::Error>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = file.take(size);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let (mut pos, mut end) = (offset, offset);
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(end);
+        }
+        if let Some(last) = buffer[..read].iter().rposition(|&byte| byte != 0) {
+            end = pos + last as u64 + 1;
+        }
+        pos += read as u64;
+    }
+}
+
+/// Skip the bytes from `start` to `end` that none of the `interpreted` ranges covers
+fn skip_outside(coverage: &mut Coverage, start: u64, end: u64, reason: &str, interpreted: &[(u64, u64)]) {
+    let mut pos = start;
+    for &(range_start, range_end) in interpreted.iter().filter(|(range_start, range_end)| *range_start < end && *range_end > start) {
+        if range_start > pos {
+            coverage.skip(pos, range_start - pos, reason);
+        }
+        pos = pos.max(range_end);
+    }
+    if end > pos {
+        coverage.skip(pos, end - pos, reason);
+    }
+}
+
+/// Print the share of interpreted bytes and the skipped ranges
+pub fn print_coverage(coverage: &Coverage) {
+    outln!("\nCoverage: {:.1}% of {} bytes interpreted", coverage.percentage(), coverage.file_size);
+    for range in coverage.skipped.iter().take(MAX_LISTED_RANGES) {
+        outln!("  Skipped 0x{:08X}-0x{:08X} ({} bytes): {}", range.offset, range.offset + range.size, range.size, range.reason);
+    }
+    if coverage.skipped.len() > MAX_LISTED_RANGES {
+        let rest = &coverage.skipped[MAX_LISTED_RANGES..];
+        outln!("  ... {} more skipped ranges ({} bytes)", rest.len(), rest.iter().map(|range| range.size).sum::<u64>());
+    }
+}
@@ -0,0 +1,305 @@
+/// Fast inventory of large directory trees (`crawl`)
+///
+/// Dissecting every file of a library one after the other reads far more than an inventory
+/// needs. The crawler walks the tree on one thread and hands the files through a bounded queue to
+/// worker threads, which open each file and read only its first bytes: enough for the format
+/// signature and, for ID3v2 tags, the tag header with version and size. Memory stays flat on trees
+/// with millions of files: the walker never runs far ahead of the workers, and the results are
+/// counted as they arrive. At the end the files are counted by format and the throughput (files
+/// per second and minute) is reported, so the crawl can be tuned with `--jobs` for local disks or
+/// network shares. A file reached through several paths (hard links, followed symbolic links) is
+/// counted once and the other paths are reported as links; only files that can be reached twice
+/// are remembered for that.
+use crate::batch_errors::BatchErrors;
+use crate::dissector_builder::DissectorBuilder;
+use crate::file_links::{FileId, LinkTracker, file_id, has_hard_links};
+use crate::id3v2_tools::decode_synchsafe_int;
+use crate::options::FollowLinks;
+use crate::path_display::{display_path, long_path};
+use owo_colors::OwoColorize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// Bytes read from every file: the format signatures and the 10-byte ID3v2 header
+const HEADER_SIZE: usize = 12;
+
+/// Files queued for the workers per worker thread
+const QUEUE_PER_WORKER: usize = 64;
+
+/// What the crawl found out about one file
+struct CrawledFile {
+    path: PathBuf,
+    size: u64,
+    result: Result<CrawlInfo, io::Error>,
+}
+
+/// Format and tag header of a file, from its first bytes
+struct CrawlInfo {
+    /// Media type of the dissector that recognizes the file
+    format: Option<&'static str>,
+    /// Major version and size (header included) of an ID3v2 tag at the start
+    id3v2: Option<(u8, u32)>,
+    bytes_read: usize,
+}
+
+/// Crawl `dir` with `jobs` worker threads and print the inventory and throughput
+pub fn crawl(dir: &Path, jobs: Option<usize>, list: bool, follow: FollowLinks) -> Result<(), Box<dyn std::error::Error>> {
+    if !long_path(dir).is_dir() {
+        return Err(format!("{} is not a directory", display_path(dir)).into());
+    }
+    let jobs = jobs.unwrap_or_else(|| thread::available_parallelism().map_or(4, |cores| cores.get() * 4)).max(1);
+    let started = Instant::now();
+
+    let (path_sender, path_receiver) = mpsc::sync_channel::<(PathBuf, u64)>(jobs * QUEUE_PER_WORKER);
+    let (result_sender, result_receiver) = mpsc::channel::<CrawledFile>();
+    let path_receiver = Arc::new(Mutex::new(path_receiver));
+
+    let walker = {
+        let dir = long_path(dir).into_owned();
+        thread::spawn(move || {
+            let mut queue = |path, size| path_sender.send((path, size)).is_ok();
+            let mut walk = Walk { follow, queue: &mut queue, links: LinkTracker::default(), walked: HashSet::new(), linked_dirs: Vec::new(), unfollowed: 0, errors: BatchErrors::default() };
+            walk.walk_tree(&dir);
+            (walk.errors, walk.links.links, walk.unfollowed)
+        })
+    };
+    let workers: Vec<_> = (0..jobs)
+        .map(|_| {
+            let path_receiver = Arc::clone(&path_receiver);
+            let result_sender = result_sender.clone();
+            thread::spawn(move || work(&path_receiver, |file| result_sender.send(file).is_ok()))
+        })
+        .collect();
+    drop(result_sender);
+
+    // Results are counted as they arrive; only --list keeps a line per file (printed sorted)
+    let mut crawled = 0;
+    let mut formats: Vec<(&'static str, usize, u64)> = Vec::new();
+    let mut tag_versions: Vec<(u8, usize)> = Vec::new();
+    let mut total_size = 0;
+    let mut bytes_read = 0;
+    let mut other = 0;
+    let mut unreadable = BatchErrors::default();
+    let mut lines = Vec::new();
+    for file in result_receiver {
+        crawled += 1;
+        total_size += file.size;
+        let info = match file.result {
+            | Ok(info) => info,
+            | Err(e) => {
+                unreadable.record(&file.path, &e);
+                continue;
+            }
+        };
+        bytes_read += info.bytes_read;
+        match info.format {
+            | Some(format) => match formats.iter_mut().find(|(known, _, _)| *known == format) {
+                | Some((_, count, size)) => {
+                    *count += 1;
+                    *size += file.size;
+                }
+                | None => formats.push((format, 1, file.size)),
+            },
+            | None => other += 1,
+        }
+        if let Some((version, _)) = info.id3v2 {
+            match tag_versions.iter_mut().find(|(known, _)| *known == version) {
+                | Some((_, count)) => *count += 1,
+                | None => tag_versions.push((version, 1)),
+            }
+        }
+        if list {
+            let tag = info.id3v2.map(|(version, size)| format!(", ID3v2.{} tag of {} bytes", version, size)).unwrap_or_default();
+            lines.push((file.path, format!("{}{}", info.format.unwrap_or("not a media file"), tag)));
+        }
+    }
+    let (mut errors, mut links, unfollowed) = walker.join().map_err(|_| "the directory walker panicked")?;
+    for worker in workers {
+        worker.join().map_err(|_| "a crawl worker panicked")?;
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    lines.sort();
+    errors.extend(unreadable);
+    for (path, line) in &lines {
+        outln!("{}: {}", display_path(path), line);
+    }
+    if !lines.is_empty() {
+        outln!();
+    }
+    outln!("{}", "Crawl:".bright_cyan());
+    formats.sort_by(|(a_format, a, _), (b_format, b, _)| b.cmp(a).then_with(|| a_format.cmp(b_format)));
+    for (format, count, size) in &formats {
+        outln!("  {}: {} file(s), {} bytes", format, count, size);
+    }
+    outln!("  Other files: {}", other);
+    tag_versions.sort();
+    if !tag_versions.is_empty() {
+        let versions: Vec<String> = tag_versions.iter().map(|(version, count)| format!("ID3v2.{} ({})", version, count)).collect();
+        outln!("  ID3v2 tags: {}", versions.join(", "));
+    }
+    if !links.is_empty() {
+        outln!("  Linked paths: {} (counted once, under the first path)", links.len());
+        links.sort_by(|a, b| a.path.cmp(&b.path));
+        for link in &links {
+            outln!("    {}", link);
+        }
+    }
+    if unfollowed > 0 {
+        outln!("  Symbolic links not followed: {} (see --follow-links)", unfollowed);
+    }
+    errors.print_summary("  ");
+
+    let per_second = crawled as f64 / elapsed.max(f64::EPSILON);
+    outln!(
+        "  Crawled {} file(s) ({} bytes) in {:.2} s with {} worker(s): {:.0} files/s, {:.0} files/min, {} bytes read",
+        crawled,
+        total_size,
+        elapsed,
+        jobs,
+        per_second,
+        per_second * 60.0,
+        bytes_read
+    );
+
+    errors.finish()
+}
+
+/// State of the directory walk
+struct Walk<'a> {
+    follow: FollowLinks,
+    /// Queues a file with its size; false when the workers are gone
+    queue: &'a mut dyn FnMut(PathBuf, u64) -> bool,
+    links: LinkTracker,
+    /// Directories walked, when directory links are followed
+    walked: HashSet<FileId>,
+    /// Directory links still to follow
+    linked_dirs: Vec<PathBuf>,
+    /// Symbolic links left out by the follow policy or pointing nowhere
+    unfollowed: usize,
+    errors: BatchErrors,
+}
+
+impl Walk<'_> {
+    /// Walk the tree below `dir`, then the directories that links lead to (each directory once)
+    ///
+    /// Following the links last keeps the real paths of files that have one.
+    fn walk_tree(&mut self, dir: &Path) {
+        self.walked.extend(file_id(dir));
+        let mut open = self.walk(dir);
+        while let Some(linked_dir) = self.linked_dirs.pop().filter(|_| open) {
+            if file_id(&linked_dir).is_none_or(|id| self.walked.insert(id)) {
+                open = self.walk(&linked_dir);
+            }
+        }
+    }
+
+    /// Walk `dir` and queue every regular file with its size; false once the queue is closed
+    ///
+    /// Unreadable directories are recorded in `errors` and skipped, so one locked folder does
+    /// not end the crawl.
+    fn walk(&mut self, dir: &Path) -> bool {
+        let entries = match fs::read_dir(dir) {
+            | Ok(entries) => entries,
+            | Err(e) => {
+                self.errors.record(dir, &e);
+                return true;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                | Ok(entry) => entry,
+                | Err(e) => {
+                    self.errors.record(dir, &e);
+                    continue;
+                }
+            };
+            let Ok(mut file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            let mut metadata = None;
+            if file_type.is_symlink() {
+                let target = match self.follow {
+                    | FollowLinks::Never => None,
+                    | FollowLinks::Files => fs::metadata(&path).ok().filter(|target| target.is_file()),
+                    | FollowLinks::All => fs::metadata(&path).ok(),
+                };
+                match target {
+                    | Some(target) if target.is_dir() => {
+                        self.linked_dirs.push(path);
+                        continue;
+                    }
+                    | Some(target) => {
+                        file_type = target.file_type();
+                        metadata = Some(target);
+                    }
+                    | None => {
+                        self.unfollowed += 1;
+                        continue;
+                    }
+                }
+            }
+            if file_type.is_dir() {
+                if self.follow == FollowLinks::All {
+                    self.walked.extend(file_id(&path));
+                }
+                if !self.walk(&path) {
+                    return false;
+                }
+            } else if file_type.is_file() {
+                let metadata = metadata.or_else(|| entry.metadata().ok());
+                // Without followed links only files with several hard links can be reached twice
+                let reachable_twice = self.follow != FollowLinks::Never || metadata.as_ref().is_some_and(has_hard_links);
+                if reachable_twice && !self.links.first_visit(&path) {
+                    continue;
+                }
+                if !(self.queue)(path, metadata.map_or(0, |metadata| metadata.len())) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Worker loop: take files from the queue until it is closed and report what their headers say
+fn work(paths: &Mutex<Receiver<(PathBuf, u64)>>, mut report: impl FnMut(CrawledFile) -> bool) {
+    let dissectors = DissectorBuilder::new().dissectors();
+    loop {
+        // The lock is held only while taking the next path, not while reading
+        let next = paths.lock().map(|receiver| receiver.recv());
+        let Ok(Ok((path, size))) = next else {
+            return;
+        };
+        let result = read_header(&path).map(|(header, bytes_read)| CrawlInfo {
+            // Files shorter than the signatures are no media files, as for the dissectors
+            format: dissectors.iter().find(|dissector| bytes_read == HEADER_SIZE && dissector.can_handle(&header)).map(|dissector| dissector.media_type()),
+            id3v2: (bytes_read >= 10 && header.starts_with(b"ID3")).then(|| (header[3], decode_synchsafe_int(&header[6..10]) + 10)),
+            bytes_read,
+        });
+        if !report(CrawledFile { path, size, result }) {
+            return;
+        }
+    }
+}
+
+/// Read up to `HEADER_SIZE` bytes from the start of a file
+fn read_header(path: &Path) -> io::Result<([u8; HEADER_SIZE], usize)> {
+    let mut header = [0u8; HEADER_SIZE];
+    let mut file = File::open(path)?;
+    let mut filled = 0;
+    while filled < HEADER_SIZE {
+        match file.read(&mut header[filled..])? {
+            | 0 => break,
+            | read => filled += read,
+        }
+    }
+    Ok((header, filled))
+}
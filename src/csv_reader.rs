@@ -0,0 +1,75 @@
+/// Minimal CSV reader (RFC 4180 quoting) for spreadsheet exports
+///
+/// Handles quoted fields with embedded delimiters, quotes and line breaks, CRLF line
+/// endings and a leading UTF-8 BOM. The delimiter is ',' unless the header line only
+/// contains ';' separators, as produced by spreadsheets in many European locales.
+use std::mem;
+
+/// Parse CSV text into rows of fields, skipping empty lines
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    let delimiter = detect_delimiter(text);
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                | '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                | '"' => in_quotes = false,
+                | _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+
+        match c {
+            | '"' if field.is_empty() => in_quotes = true,
+            | '\r' if chars.peek() == Some(&'\n') => {}
+            | '\n' | '\r' => {
+                row.push(mem::take(&mut field));
+                if row.len() > 1 || !row[0].is_empty() {
+                    rows.push(mem::take(&mut row));
+                } else {
+                    row.clear();
+                }
+                line += 1;
+            }
+            | _ if c == delimiter => row.push(mem::take(&mut field)),
+            | _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Unterminated quoted field starting before line {}", line));
+    }
+
+    row.push(field);
+    if row.len() > 1 || !row[0].is_empty() {
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Use ';' if the first line has semicolons but no commas, ',' otherwise
+fn detect_delimiter(text: &str) -> char {
+    let first_line = text.lines().next().unwrap_or("");
+    if first_line.contains(';') && !first_line.contains(',') {
+        ';'
+    } else {
+        ','
+    }
+}
@@ -0,0 +1,287 @@
+// Only the Unix socket server uses the protocol
+#![cfg_attr(not(unix), allow(dead_code))]
+
+/// Long-running analysis server on a local socket (`daemon`)
+///
+/// Front-ends send requests over a Unix domain socket instead of starting a process per file.
+/// Every message in both directions is a 4-byte big-endian length followed by that many bytes
+/// of JSON. A connection may carry any number of requests, answered in order:
+///
+/// - `{"command": "analyze", "path": "..."}` answers with the `summary` (as in sidecar files)
+/// - `{"command": "triage", "path": "...", "weights": ["artwork=30"]}` answers with the `health` score
+/// - `{"command": "dissect", "path": "...", "header": true}` answers with the text `output` of `debug`
+/// - `{"command": "ping"}` and `{"command": "shutdown"}`
+///
+/// A request can decode and show its file with other options than the daemon was started with:
+/// `"options": {"lenient": true, "max-frame-depth": 4, "assume-encoding": "cp1251"}` sets any of
+/// `time-format`, `max-text-width`, `ellipsis`, `max-frame-depth`, `lenient`, `assume-encoding`
+/// and `nfc` for that request only.
+///
+/// Answers carry `"ok": true`, or `"ok": false` and the `error`. Summaries stay cached while the
+/// size and modification time of their file and the options they were made with do not change,
+/// so repeated requests for the files shown in a front-end are answered without reading them
+/// again. With `--sandbox`, every request parses its file in a worker process and the cache is
+/// not used.
+use crate::analysis::{analyze_file, analyze_source, dissect_file};
+use crate::health_score::{CategoryWeight, HealthWeights, health_score};
+use crate::media_summary::MediaSummary;
+use crate::options::{AssumedEncoding, CommonOptions, DebugOptions, EllipsisStyle, TimeFormat};
+use crate::output;
+use crate::path_display::long_path;
+use crate::sandbox;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Largest request that is accepted
+const MAX_REQUEST_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Summaries kept before the cache is emptied
+const CACHE_LIMIT: usize = 10_000;
+
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+enum Request {
+    Analyze {
+        path: PathBuf,
+    },
+    Triage {
+        path: PathBuf,
+        #[serde(default)]
+        weights: Vec<String>,
+    },
+    Dissect {
+        path: PathBuf,
+        #[serde(default)]
+        header: bool,
+        #[serde(default)]
+        frames: bool,
+        #[serde(default)]
+        lang: Option<String>,
+        #[serde(default)]
+        frame_ids: Vec<String>,
+    },
+    Ping,
+    Shutdown,
+}
+
+/// A request with the options it is answered with
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(flatten)]
+    request: Request,
+    #[serde(default)]
+    options: RequestOptions,
+}
+
+/// Decoding and display options a request sets for itself; the others are those of the daemon
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct RequestOptions {
+    time_format: Option<TimeFormat>,
+    max_text_width: Option<usize>,
+    ellipsis: Option<EllipsisStyle>,
+    max_frame_depth: Option<usize>,
+    lenient: Option<bool>,
+    assume_encoding: Option<AssumedEncoding>,
+    nfc: Option<bool>,
+}
+
+impl RequestOptions {
+    /// The options of the daemon with those of the request in their place
+    fn over(self, daemon: CommonOptions) -> CommonOptions {
+        CommonOptions {
+            time_format: self.time_format.unwrap_or(daemon.time_format),
+            max_text_width: self.max_text_width.or(daemon.max_text_width),
+            ellipsis: self.ellipsis.unwrap_or(daemon.ellipsis),
+            max_frame_depth: self.max_frame_depth.unwrap_or(daemon.max_frame_depth),
+            lenient: self.lenient.unwrap_or(daemon.lenient),
+            assume_encoding: self.assume_encoding.or(daemon.assume_encoding),
+            nfc: self.nfc.unwrap_or(daemon.nfc),
+            ..daemon
+        }
+    }
+}
+
+/// Summary with the size and modification time of the file it was made from, and the options
+struct CachedSummary {
+    size: u64,
+    modified: Option<SystemTime>,
+    options: CommonOptions,
+    summary: MediaSummary,
+}
+
+/// Summaries by path, reused while their file is unchanged
+#[derive(Default)]
+struct SummaryCache {
+    entries: Mutex<HashMap<PathBuf, CachedSummary>>,
+}
+
+impl SummaryCache {
+    fn summary(&self, path: &Path) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let metadata = std::fs::metadata(path)?;
+        let (size, modified) = (metadata.len(), metadata.modified().ok());
+        let options = CommonOptions::current();
+        if let Some(cached) = self.entries.lock().map_err(|_| "summary cache poisoned")?.get(path)
+            && cached.size == size
+            && cached.modified == modified
+            && cached.options == options
+        {
+            return Ok(cached.summary.clone());
+        }
+
+        let summary = analyze_file(path)?;
+        let mut entries = self.entries.lock().map_err(|_| "summary cache poisoned")?;
+        if entries.len() >= CACHE_LIMIT {
+            entries.clear();
+        }
+        entries.insert(path.to_path_buf(), CachedSummary { size, modified, options, summary: summary.clone() });
+        Ok(summary)
+    }
+}
+
+/// Answer of a request, and whether the daemon should stop afterwards
+fn handle(request: &[u8], cache: &SummaryCache, options: CommonOptions) -> (Value, bool) {
+    let Envelope { request, options: request_options } = match serde_json::from_slice(request) {
+        | Ok(envelope) => envelope,
+        | Err(e) => return (json!({ "ok": false, "error": format!("invalid request: {}", e) }), false),
+    };
+    request_options.over(options).apply(|| answer(request, cache))
+}
+
+fn answer(request: Request, cache: &SummaryCache) -> (Value, bool) {
+    let answer = match request {
+        | Request::Analyze { path } if sandbox::enabled() => sandboxed(&path, |summary| json!({ "ok": true, "summary": summary })),
+        | Request::Analyze { path } => cache.summary(&path).map(|summary| json!({ "ok": true, "summary": summary })),
+        | Request::Triage { path, weights } => weights
+            .iter()
+            .map(|weight| weight.parse::<CategoryWeight>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Into::into)
+            .and_then(|weights| {
+                let weights = HealthWeights::default().with(&weights);
+                if sandbox::enabled() {
+                    return sandboxed(&path, |summary| json!({ "ok": true, "health": health_score(&summary, &weights) }));
+                }
+                let summary = cache.summary(&path)?;
+                Ok(json!({ "ok": true, "health": health_score(&summary, &weights) }))
+            }),
+        | Request::Dissect { path, header, frames, lang, frame_ids } => {
+            let options = DebugOptions::from_flags(header, frames, false).with_language(lang).with_frame_ids(frame_ids).with_common(CommonOptions::current());
+            let (result, text) = output::captured(|| dissect_file(&path, &options));
+            result.map(|()| json!({ "ok": true, "output": text }))
+        }
+        | Request::Ping => Ok(json!({ "ok": true })),
+        | Request::Shutdown => return (json!({ "ok": true }), true),
+    };
+    let answer = answer.unwrap_or_else(|e| json!({ "ok": false, "error": e.to_string() }));
+    (answer, false)
+}
+
+/// Answer made from the summary of a file that is analyzed in a worker process (`--sandbox`)
+///
+/// Summaries cannot be rebuilt from their JSON, so the worker makes the whole answer and nothing
+/// is cached.
+fn sandboxed(path: &Path, answer: impl FnOnce(MediaSummary) -> Value) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut file = std::fs::File::open(long_path(path))?;
+    sandbox::isolated(&mut file, |file| Ok(answer(analyze_source(file, path)?)))
+}
+
+/// Read one length-prefixed message; `None` when the client closed the connection
+fn read_message(stream: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match stream.read_exact(&mut length) {
+        | Ok(()) => {}
+        | Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        | Err(e) => return Err(e),
+    }
+    let length = u32::from_be_bytes(length);
+    if length > MAX_REQUEST_SIZE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("request of {} bytes exceeds the limit", length)));
+    }
+    let mut message = vec![0u8; length as usize];
+    stream.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+fn write_message(stream: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let bytes = serde_json::to_vec(message)?;
+    stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+/// Serve requests on the Unix domain socket at `socket` until a client sends `shutdown`
+///
+/// Requests are answered with `options` unless they set their own. A socket file left behind by
+/// a daemon that did not shut down cleanly is replaced; if another daemon still answers on it,
+/// the command fails.
+#[cfg(unix)]
+pub fn run_daemon(socket: &Path, options: CommonOptions) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::path_display::display_path;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(format!("a daemon is already listening on {}", display_path(socket)).into());
+        }
+        std::fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+    outln!("Listening on {}", display_path(socket));
+
+    let cache = Arc::new(SummaryCache::default());
+    let stopping = Arc::new(AtomicBool::new(false));
+    for stream in listener.incoming() {
+        if stopping.load(Ordering::SeqCst) {
+            break;
+        }
+        let mut stream = match stream {
+            | Ok(stream) => stream,
+            | Err(e) => {
+                eprintln!("Connection failed: {}", e);
+                continue;
+            }
+        };
+        let cache = Arc::clone(&cache);
+        let stopping = Arc::clone(&stopping);
+        let socket = socket.to_path_buf();
+        std::thread::spawn(move || {
+            loop {
+                let request = match read_message(&mut stream) {
+                    | Ok(Some(request)) => request,
+                    | Ok(None) => break,
+                    | Err(e) => {
+                        let _ = write_message(&mut stream, &json!({ "ok": false, "error": e.to_string() }));
+                        break;
+                    }
+                };
+                let (answer, shutdown) = handle(&request, &cache, options);
+                if write_message(&mut stream, &answer).is_err() {
+                    break;
+                }
+                if shutdown {
+                    stopping.store(true, Ordering::SeqCst);
+                    // Wake the accept loop so it sees the flag
+                    let _ = UnixStream::connect(&socket);
+                    break;
+                }
+            }
+        });
+    }
+
+    std::fs::remove_file(socket)?;
+    outln!("Daemon stopped");
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run_daemon(_socket: &Path, _options: CommonOptions) -> Result<(), Box<dyn std::error::Error>> {
+    Err("the daemon needs Unix domain sockets, which this platform does not provide".into())
+}
@@ -0,0 +1,185 @@
+/// Disc, track and compilation fields of ID3v2 tags and MP4 metadata
+///
+/// Players group the tracks of an album by these fields: the disc position (TPOS frame, `disk`
+/// item) keeps the discs of a set apart, and the compilation flag (the iTunes TCMP frame, `cpil`
+/// item) lists the tracks of many artists as one album. Both formats are read into the same model,
+/// so a set split over MP3 and M4A files can be checked in one go. The number format of TPOS and
+/// TRCK is checked with the other frame contents (`id3v2_plausibility`); MP4 stores numbers.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::Id3v2Frame;
+use crate::isobmff_box::{find_path, read_boxes, read_children, read_payload};
+use crate::media_source::MediaSource;
+use serde::Serialize;
+use std::fmt;
+
+/// Position within a set: a number and, if known, the total
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Position {
+    pub number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+}
+
+impl Position {
+    /// Parse "2" or "2/3"; `None` for text that is not a number (reported by the frame content checks)
+    pub fn parse(text: &str) -> Option<Self> {
+        let (number, total) = match text.trim().split_once('/') {
+            | Some((number, total)) => (number, Some(total.trim().parse().ok()?)),
+            | None => (text.trim(), None),
+        };
+        Some(Position { number: number.trim().parse().ok()?, total })
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.total {
+            | Some(total) => write!(f, "{} of {}", self.number, total),
+            | None => write!(f, "{}", self.number),
+        }
+    }
+}
+
+/// Disc and track position and compilation flag of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscInfo {
+    /// Disc within the set (TPOS frame, `disk` item)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc: Option<Position>,
+    /// Track on the disc (TRCK frame, `trkn` item)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<Position>,
+    /// Compilation flag (TCMP frame, `cpil` item); `None` if the file has none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compilation: Option<bool>,
+    /// Incomplete or contradictory positions and compilation flags
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<Finding>,
+}
+
+/// Names of the fields in a format, for the findings
+struct FieldNames {
+    disc: &'static str,
+    compilation: &'static str,
+    album_artist: &'static str,
+}
+
+impl DiscInfo {
+    /// Disc fields of the top-level frames of an ID3v2 tag; `None` if the tag has neither TPOS nor TCMP
+    pub fn from_frames(frames: &[Id3v2Frame]) -> Option<Self> {
+        let text = |id: &str| frames.iter().find(|frame| frame.id == id).and_then(|frame| frame.get_text());
+        let mut findings = Vec::new();
+        let compilation = text("TCMP").map(|value| match value.trim() {
+            | "1" => true,
+            | "0" => false,
+            | other => {
+                findings.push(Finding::new(Severity::Warning, "TCMP", format!("'{}' is not 1 (compilation) or 0; iTunes only reads 1", other)));
+                !other.trim().is_empty()
+            }
+        });
+        let disc = text("TPOS").and_then(Position::parse);
+        if disc.is_none() && compilation.is_none() {
+            return None;
+        }
+        let info = DiscInfo { disc, track: text("TRCK").and_then(Position::parse), compilation, findings };
+        Some(info.checked(text("TPE2").is_some(), FieldNames { disc: "TPOS", compilation: "TCMP", album_artist: "TPE2" }))
+    }
+
+    /// Disc items of the `moov/udta/meta/ilst` box of an MP4 file; `None` if it has neither `disk` nor `cpil`
+    pub fn from_isobmff(file: &mut dyn MediaSource) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let Some(moov) = read_boxes(file, 0, file_size)?.into_iter().find(|box_header| box_header.box_type == "moov") else {
+            return Ok(None);
+        };
+        let Some(ilst) = find_path(file, &moov, &["udta", "meta", "ilst"])? else {
+            return Ok(None);
+        };
+
+        let mut disc = None;
+        let mut track = None;
+        let mut compilation = None;
+        let mut album_artist = false;
+        for item in read_children(file, &ilst)? {
+            if !matches!(item.box_type.as_str(), "disk" | "trkn" | "cpil" | "aART") {
+                continue;
+            }
+            let Some(data) = read_children(file, &item)?.into_iter().find(|child| child.box_type == "data") else {
+                continue;
+            };
+            // Type indicator and locale precede the value
+            let payload = read_payload(file, &data)?;
+            let value = payload.get(8..).unwrap_or_default();
+            match item.box_type.as_str() {
+                | "disk" => disc = read_position(value),
+                | "trkn" => track = read_position(value),
+                | "cpil" => compilation = Some(value.iter().any(|&byte| byte != 0)),
+                | _ => album_artist = true,
+            }
+        }
+        if disc.is_none() && compilation.is_none() {
+            return Ok(None);
+        }
+
+        // TPOS values are checked with the frame contents; MP4 numbers are checked here
+        let mut findings = Vec::new();
+        if let Some(disc) = disc {
+            if disc.number == 0 {
+                findings.push(Finding::new(Severity::Warning, "disk", "disc 0, discs count from 1"));
+            } else if disc.total.is_some_and(|total| total > 0 && disc.number > total) {
+                findings.push(Finding::new(Severity::Warning, "disk", format!("disc {} is past the set of {}", disc.number, disc.total.unwrap_or_default())));
+            }
+        }
+        let info = DiscInfo { disc, track, compilation, findings };
+        Ok(Some(info.checked(album_artist, FieldNames { disc: "disk", compilation: "cpil", album_artist: "aART" })))
+    }
+
+    fn checked(mut self, album_artist: bool, names: FieldNames) -> Self {
+        if let Some(disc) = self.disc
+            && disc.total.is_none_or(|total| total == 0)
+        {
+            self.findings.push(Finding::new(Severity::Info, names.disc, format!("disc {} without the number of discs; players cannot tell whether the set is complete", disc.number)));
+        }
+        if self.compilation == Some(true) && !album_artist {
+            self.findings.push(Finding::new(
+                Severity::Info,
+                names.compilation,
+                format!("compilation without an album artist ({}); players that ignore {} split the album by artist", names.album_artist, names.compilation),
+            ));
+        }
+        self
+    }
+}
+
+/// Number and total of a `disk` or `trkn` item (reserved 16 bits, number, total)
+fn read_position(value: &[u8]) -> Option<Position> {
+    let number = u16::from_be_bytes(value.get(2..4)?.try_into().ok()?);
+    let total = value.get(4..6).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]])).filter(|&total| total > 0);
+    Some(Position { number: number as u32, total: total.map(u32::from) })
+}
+
+/// Print the disc position and compilation flag with their findings, if the file has any
+pub fn print_disc_info(info: Option<&DiscInfo>) {
+    let Some(info) = info else {
+        return;
+    };
+    outln!("\nDisc and Compilation:");
+    if let Some(disc) = &info.disc {
+        outln!("  Disc: {}", disc);
+    }
+    if let Some(track) = &info.track {
+        outln!("  Track: {}", track);
+    }
+    if let Some(compilation) = info.compilation {
+        outln!(
+            "  Compilation: {}",
+            if compilation {
+                "yes"
+            } else {
+                "no"
+            }
+        );
+    }
+    for finding in &info.findings {
+        outln!("    {}", finding);
+    }
+}
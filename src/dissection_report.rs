@@ -0,0 +1,225 @@
+/// Structured dissection result (`debug --report`)
+///
+/// A `DissectionReport` is a tree of nodes with a name, the byte range in the file (where it is
+/// known), a value and the diagnostics found there. Renderers turn it into indented colored text or
+/// JSON, so machine-readable output and tests work on the tree instead of parsing printed text.
+///
+/// The tree is not built while parsing: the dissectors still print the normal dissection as they
+/// go, and `MediaDissector::report` builds the tree afterwards from the summary of the file (tag
+/// frames with their sub-frames, RIFF chunks, Ogg streams, ZIP members, trailing and inline tags,
+/// skipped ranges). Only ISO BMFF walks the file again to add its box tree. Structures that only
+/// the printed dissection shows (e.g. MPEG frame headers, Ogg page headers) are not in the tree.
+use crate::finding::{Finding, Severity};
+use crate::media_summary::{FrameSummary, MediaSummary};
+use crate::options::ReportFormat;
+use crate::text_truncate::truncate_text;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Dissection of one file as a tree
+#[derive(Debug, Clone, Serialize)]
+pub struct DissectionReport {
+    pub path: String,
+    pub format: String,
+    pub dissector: String,
+    pub root: ReportNode,
+}
+
+/// One structure of the file
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportNode {
+    /// What the structure is (e.g. "ID3v2.4 tag", "frame TIT2", "box 'moov'")
+    pub name: String,
+    /// File offset, if the structure has a known place in the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// Size in bytes, header included
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+    /// Decoded value, for structures that carry one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ReportNode>,
+    /// Problems found in this structure
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Finding>,
+}
+
+impl ReportNode {
+    pub fn new(name: impl Into<String>) -> Self {
+        ReportNode { name: name.into(), offset: None, length: None, value: None, children: Vec::new(), diagnostics: Vec::new() }
+    }
+
+    /// Place the node at `length` bytes from `offset`
+    pub fn at(mut self, offset: u64, length: u64) -> Self {
+        self.offset = Some(offset);
+        self.length = Some(length);
+        self
+    }
+
+    pub fn with_value(mut self, value: impl Into<String>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    pub fn with_children(mut self, children: Vec<ReportNode>) -> Self {
+        self.children = children;
+        self
+    }
+
+    pub fn with_diagnostics(mut self, diagnostics: Vec<Finding>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+}
+
+/// Report tree built from the summary of a file of `file_size` bytes
+pub fn summary_report(summary: &MediaSummary, file_size: u64) -> ReportNode {
+    let mut children = Vec::new();
+
+    if let Some(tag) = &summary.tag {
+        // Frame offsets count from the end of the tag header, and only tags at the start of the file have a known place
+        let at_start = summary.format.starts_with("ID3v2");
+        let header_size = if tag.version.starts_with("2.2") {
+            6
+        } else {
+            10
+        };
+        let frames_start = (at_start && tag.flags & 0x80 == 0).then_some(10);
+        let mut findings = tag.findings.clone();
+        let frames = tag.frames.iter().map(|frame| frame_node(frame, frames_start, header_size, &mut findings)).collect();
+        let mut node = ReportNode::new(format!("{} tag", tag.tag_type)).with_value(format!("version {}", tag.version)).with_children(frames).with_diagnostics(findings);
+        if at_start {
+            node = node.at(0, tag.size);
+        }
+        children.push(node);
+    }
+
+    if let Some(riff) = &summary.riff {
+        let chunks = riff.chunks.iter().map(|chunk| ReportNode::new(format!("chunk '{}'", chunk.id)).at(chunk.offset, chunk.end() - chunk.offset)).collect();
+        children.push(ReportNode::new(format!("RIFF '{}'", riff.form)).at(0, file_size).with_children(chunks).with_diagnostics(problems(&riff.problems, "RIFF")));
+    }
+
+    if let Some(ogg) = &summary.ogg {
+        let streams = ogg
+            .streams
+            .iter()
+            .map(|stream| {
+                let mut node = ReportNode::new(format!("stream {:08X}", stream.serial)).with_value(format!("{}, {} pages, {} bytes", stream.codec, stream.pages, stream.size));
+                if let Some(comment) = &stream.comment {
+                    node.children = comment.fields.iter().map(|field| ReportNode::new(field.name.clone()).with_value(field.value.clone())).collect();
+                }
+                node
+            })
+            .collect();
+        children.push(ReportNode::new("Ogg").at(0, file_size).with_value(format!("{} pages", ogg.pages)).with_children(streams).with_diagnostics(problems(&ogg.problems, "Ogg")));
+    }
+
+    if let Some(bundle) = &summary.bundle {
+        let mut entries: Vec<ReportNode> = bundle
+            .entries
+            .iter()
+            .map(|entry| ReportNode::new(format!("member '{}'", entry.name)).at(entry.local_header_offset, entry.compressed_size).with_value(format!("{} bytes, CRC-32 {:08X}", entry.size, entry.crc32)))
+            .collect();
+        entries.push(ReportNode::new("central directory").at(bundle.directory_offset, file_size.saturating_sub(bundle.directory_offset)));
+        children.push(ReportNode::new("ZIP").at(0, file_size).with_children(entries));
+    }
+
+    if let Some(movie) = &summary.movie {
+        children.push(ReportNode::new("movie").with_value(format!("timescale {}, {} ms", movie.timescale, movie.duration_ms)));
+    }
+    for track in &summary.tracks {
+        let mut value = track.handler.clone().unwrap_or_else(|| "unknown handler".to_string());
+        if let Some(duration_ms) = track.duration_ms {
+            value.push_str(&format!(", {} ms", duration_ms));
+        }
+        if let Some(audio) = &track.audio {
+            value.push_str(&format!(", {}", audio));
+        }
+        children.push(ReportNode::new(format!("track {}", track.track_id)).with_value(value));
+    }
+
+    for block in &summary.trailers {
+        children.push(ReportNode::new(format!("{} block", block.kind)).at(block.offset, block.size));
+    }
+    if let Some(id3v1) = &summary.id3v1 {
+        let fields = [("title", &id3v1.title), ("artist", &id3v1.artist), ("album", &id3v1.album), ("year", &id3v1.year), ("comment", &id3v1.comment)];
+        let fields = fields.into_iter().filter(|(_, value)| !value.is_empty()).map(|(name, value)| ReportNode::new(name).with_value(value.clone())).collect();
+        children.push(ReportNode::new(format!("{} tag", id3v1.version)).at(id3v1.offset, 128).with_children(fields).with_diagnostics(id3v1.findings.clone()));
+    }
+    for inline in &summary.inline_tags {
+        let fields = inline.fields.iter().map(|(name, value)| ReportNode::new(name.clone()).with_value(value.clone())).collect();
+        children.push(ReportNode::new(format!("inline {}", inline.kind)).at(inline.offset, inline.size).with_children(fields));
+    }
+    if let Some(coverage) = &summary.coverage {
+        for range in &coverage.skipped {
+            children.push(ReportNode::new("skipped").at(range.offset, range.size).with_value(range.reason.clone()));
+        }
+    }
+
+    // Structures in file order, those without a place in the file last
+    children.sort_by_key(|node| node.offset.unwrap_or(u64::MAX));
+    ReportNode::new("file").at(0, file_size).with_value(summary.format.clone()).with_children(children).with_diagnostics(summary.format_findings.clone())
+}
+
+/// Node of a frame, with the findings located at it moved over from `findings`
+fn frame_node(frame: &FrameSummary, data_start: Option<u64>, header_size: u64, findings: &mut Vec<Finding>) -> ReportNode {
+    let offset = data_start.zip(frame.offset).map(|(start, offset)| start + offset as u64);
+    let mut node = ReportNode::new(format!("frame {}", frame.id));
+    if let Some(offset) = offset {
+        node = node.at(offset, header_size + frame.size as u64);
+    }
+    node.value = frame.value.clone().or_else(|| Some(frame.description.clone()));
+    // Sub-frame offsets count from the start of the parent's data
+    let sub_start = offset.map(|offset| offset + header_size);
+    node.children = frame.sub_frames.iter().map(|sub_frame| frame_node(sub_frame, sub_start, header_size, &mut Vec::new())).collect();
+    let (own, rest): (Vec<Finding>, Vec<Finding>) = std::mem::take(findings).into_iter().partition(|finding| finding.location == frame.id);
+    *findings = rest;
+    node.diagnostics = own;
+    node
+}
+
+fn problems(problems: &[String], location: &str) -> Vec<Finding> {
+    problems.iter().map(|problem| Finding::new(Severity::Warning, location, problem.clone())).collect()
+}
+
+/// Print the report in `format`
+pub fn print_report(report: &DissectionReport, format: ReportFormat) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        | ReportFormat::Text => out!("{}", render_text(report)),
+        | ReportFormat::Json => outln!("{}", serde_json::to_string_pretty(report)?),
+    }
+    Ok(())
+}
+
+/// The report as an indented tree with colored names and diagnostics
+pub fn render_text(report: &DissectionReport) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "{} ({})", report.path.bold(), report.dissector);
+    render_node(&mut text, &report.root, 0);
+    text
+}
+
+fn render_node(text: &mut String, node: &ReportNode, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let range = match (node.offset, node.length) {
+        | (Some(offset), Some(length)) => format!(" @0x{:08X} +{}", offset, length).dimmed().to_string(),
+        | _ => String::new(),
+    };
+    let value = node.value.as_deref().map(|value| format!(" = \"{}\"", truncate_text(value))).unwrap_or_default();
+    let _ = writeln!(text, "{}{}{}{}", indent, node.name.bright_cyan(), range, value);
+    for diagnostic in &node.diagnostics {
+        let line = format!("{}: {}", diagnostic.severity, diagnostic.message);
+        let line = match diagnostic.severity {
+            | Severity::Error => line.bright_red().to_string(),
+            | Severity::Warning => line.bright_yellow().to_string(),
+            | Severity::Info => line,
+        };
+        let _ = writeln!(text, "{}  {}", indent, line);
+    }
+    for child in &node.children {
+        render_node(text, child, depth + 1);
+    }
+}
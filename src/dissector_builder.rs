@@ -1,7 +1,7 @@
 use crate::media_dissector::MediaDissector;
+use crate::media_source::MediaSource;
 use crate::unknown_dissector::UnknownDissector;
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::SeekFrom;
 
 /// Builder for creating the appropriate dissector based on file content
 pub struct DissectorBuilder;
@@ -13,7 +13,7 @@ impl DissectorBuilder {
     }
 
     /// Analyze file header and return the appropriate dissector
-    pub fn build_for_file(&self, file: &mut File) -> Result<Box<dyn MediaDissector>, Box<dyn std::error::Error>> {
+    pub fn build_for_file(&self, file: &mut dyn MediaSource) -> Result<Box<dyn MediaDissector>, Box<dyn std::error::Error>> {
         // Read file header for format detection
         let mut header = [0u8; 12];
         file.seek(SeekFrom::Start(0))?;
@@ -21,13 +21,7 @@ impl DissectorBuilder {
         file.seek(SeekFrom::Start(0))?; // Reset position
 
         // Try each dissector type in order of preference
-        let dissectors: Vec<Box<dyn MediaDissector>> = vec![
-            Box::new(crate::id3v2_3_dissector::Id3v23Dissector),
-            Box::new(crate::id3v2_4_dissector::Id3v24Dissector),
-            Box::new(crate::isobmff_dissector::IsobmffDissector),
-        ];
-
-        for dissector in dissectors {
+        for dissector in self.dissectors() {
             if dissector.can_handle(&header) {
                 return Ok(dissector);
             }
@@ -36,6 +30,29 @@ impl DissectorBuilder {
         // If no specific dissector found, return an unknown format dissector
         Ok(Box::new(UnknownDissector))
     }
+
+    /// Whether a format dissector (not the fallback for unknown files) handles the file
+    pub fn recognizes(&self, file: &mut dyn MediaSource) -> bool {
+        let mut header = [0u8; 12];
+        if file.seek(SeekFrom::Start(0)).is_err() || file.read_exact(&mut header).is_err() {
+            return false;
+        }
+        self.dissectors().iter().any(|dissector| dissector.can_handle(&header))
+    }
+
+    /// All format dissectors in order of preference (without the fallback for unknown files)
+    pub fn dissectors(&self) -> Vec<Box<dyn MediaDissector>> {
+        vec![
+            Box::new(crate::id3v2_2_dissector::Id3v22Dissector),
+            Box::new(crate::id3v2_3_dissector::Id3v23Dissector),
+            Box::new(crate::id3v2_4_dissector::Id3v24Dissector),
+            Box::new(crate::isobmff_dissector::IsobmffDissector),
+            Box::new(crate::wave_dissector::WaveDissector),
+            Box::new(crate::dsf_dissector::DsfDissector),
+            Box::new(crate::ogg_dissector::OggDissector),
+            Box::new(crate::zip_dissector::ZipDissector),
+        ]
+    }
 }
 
 impl Default for DissectorBuilder {
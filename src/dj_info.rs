@@ -0,0 +1,387 @@
+/// DJ software fields of ID3v2 tags (Traktor, Rekordbox, Mixed In Key, Serato)
+///
+/// DJ applications keep the key, tempo and energy of a track in its tag: TKEY and TBPM are
+/// standard frames, Rekordbox and Traktor also write the key as TXXX "INITIALKEY", and Mixed In
+/// Key writes the energy (1 to 10) as TXXX "EnergyLevel" and as a "8A - Energy 6" comment. The
+/// key comes in three notations, musical ("Am"), Camelot ("8A") and Open Key ("1m"); each is
+/// shown with its counterparts, since DJs mix by the wheel position.
+///
+/// Traktor stores its analysis in a PRIV frame owned by "TRAKTOR4": a tree of chunks with
+/// reversed four-character IDs, of which the cue points (CUEP) are decoded. Rekordbox keeps cues
+/// and beatgrids in its own database, not in the file; Serato's GEOB frames are decoded with the
+/// frames themselves and only listed here.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::serato::{BEATGRID_DESCRIPTION, MARKERS_DESCRIPTION};
+use crate::time_format::format_duration;
+use serde::Serialize;
+use std::fmt;
+
+/// Owner identifier of Traktor's PRIV frame
+pub const TRAKTOR_OWNER: &str = "TRAKTOR4";
+
+/// TXXX descriptions of the key and the energy (matched case-insensitively)
+const KEY_DESCRIPTIONS: &[&str] = &["INITIALKEY", "KEY"];
+const ENERGY_DESCRIPTIONS: &[&str] = &["EnergyLevel", "ENERGY"];
+
+/// Deepest Traktor chunk level that is listed
+const MAX_TRAKTOR_DEPTH: usize = 4;
+
+/// Key, tempo, energy and DJ software data of a tag
+#[derive(Debug, Clone, Serialize)]
+pub struct DjInfo {
+    /// Keys in the order TKEY, TXXX, comment
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<KeyField>,
+    /// TBPM as written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<String>,
+    /// Energy rating (1 to 10) with the frame it was read from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub energy: Option<(String, u32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub traktor: Option<TraktorData>,
+    /// Serato GEOB frames present ("Serato Markers_", "Serato BeatGrid")
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub serato: Vec<String>,
+    /// Unreadable keys, keys that disagree, energy out of range
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<Finding>,
+}
+
+/// A key as written, with the notations it converts to
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyField {
+    /// Frame the key was read from ("TKEY", "TXXX:INITIALKEY", "COMM")
+    pub source: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<MusicalKey>,
+}
+
+/// Key as pitch class of the tonic and mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MusicalKey {
+    /// 0 = C, 1 = C#/Db, ..., 11 = B
+    pub pitch: u8,
+    pub minor: bool,
+}
+
+impl MusicalKey {
+    /// Parse musical ("F#m", "Bb", "A minor"), Camelot ("8A") or Open Key ("1m") notation
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+        if !digits.is_empty() {
+            let number: u8 = digits.parse().ok().filter(|number| (1..=12).contains(number))?;
+            let suffix = value[digits.len()..].trim();
+            // Camelot 8B and Open Key 1d are C major, 8A and 1m A minor
+            let (camelot, minor) = match suffix {
+                | "A" | "a" => (number, true),
+                | "B" | "b" => (number, false),
+                | "m" | "M" => ((number + 6) % 12 + 1, true),
+                | "d" | "D" => ((number + 6) % 12 + 1, false),
+                | _ => return None,
+            };
+            // Camelot numbers step by fifths: C major is 8, G major 9
+            let fifths = (camelot + 12 - 8) % 12;
+            let major_pitch = fifths * 7 % 12;
+            let pitch = if minor {
+                (major_pitch + 9) % 12
+            } else {
+                major_pitch
+            };
+            return Some(MusicalKey { pitch, minor });
+        }
+
+        let mut chars = value.chars();
+        let tonic = match chars.next()?.to_ascii_uppercase() {
+            | 'C' => 0,
+            | 'D' => 2,
+            | 'E' => 4,
+            | 'F' => 5,
+            | 'G' => 7,
+            | 'A' => 9,
+            | 'B' => 11,
+            | _ => return None,
+        };
+        let mut rest = chars.as_str();
+        let mut pitch = tonic;
+        if let Some(stripped) = rest.strip_prefix(['#', '♯']) {
+            pitch = (tonic + 1) % 12;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix(['b', '♭']) {
+            pitch = (tonic + 11) % 12;
+            rest = stripped;
+        }
+        let minor = match rest.trim().to_ascii_lowercase().as_str() {
+            | "" | "maj" | "major" => false,
+            | "m" | "min" | "minor" => true,
+            | _ => return None,
+        };
+        Some(MusicalKey { pitch, minor })
+    }
+
+    /// Position on the Camelot wheel, e.g. "8A" for A minor
+    pub fn camelot(&self) -> String {
+        let major_pitch = if self.minor {
+            (self.pitch + 3) % 12
+        } else {
+            self.pitch
+        };
+        // Position in the circle of fifths, counted from C
+        let fifths = major_pitch * 7 % 12;
+        let mode = if self.minor {
+            'A'
+        } else {
+            'B'
+        };
+        format!("{}{}", (fifths + 7) % 12 + 1, mode)
+    }
+
+    /// Open Key notation (Traktor), e.g. "1m" for A minor
+    pub fn open_key(&self) -> String {
+        let camelot = self.camelot();
+        let number: u8 = camelot[..camelot.len() - 1].parse().unwrap_or(1);
+        let mode = if self.minor {
+            'm'
+        } else {
+            'd'
+        };
+        format!("{}{}", (number + 4) % 12 + 1, mode)
+    }
+}
+
+impl fmt::Display for MusicalKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MAJOR: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+        const MINOR: [&str; 12] = ["Cm", "C#m", "Dm", "Ebm", "Em", "Fm", "F#m", "Gm", "G#m", "Am", "Bbm", "Bm"];
+        let name = if self.minor {
+            MINOR[self.pitch as usize]
+        } else {
+            MAJOR[self.pitch as usize]
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Chunk tree and cue points of Traktor's PRIV frame
+#[derive(Debug, Clone, Serialize)]
+pub struct TraktorData {
+    pub size: usize,
+    /// Chunk paths with their sizes, e.g. ("TRMD/DATA/CUEP", 240)
+    pub chunks: Vec<(String, usize)>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cues: Vec<TraktorCue>,
+    /// Why the chunk tree could not be read completely
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+}
+
+/// Cue point of the CUEP chunk (the fields of CUE_V2 in Traktor's collection.nml)
+#[derive(Debug, Clone, Serialize)]
+pub struct TraktorCue {
+    pub name: String,
+    /// 0 cue, 1 fade-in, 2 fade-out, 3 load, 4 grid, 5 loop
+    pub cue_type: u32,
+    pub start_ms: f64,
+    pub length_ms: f64,
+    /// Hot cue slot (0-based), -1 if none
+    pub hotcue: i32,
+}
+
+impl TraktorCue {
+    fn type_name(&self) -> &'static str {
+        match self.cue_type {
+            | 0 => "Cue",
+            | 1 => "Fade-in",
+            | 2 => "Fade-out",
+            | 3 => "Load",
+            | 4 => "Grid",
+            | 5 => "Loop",
+            | _ => "Unknown",
+        }
+    }
+}
+
+impl TraktorData {
+    /// Read the chunk tree: reversed ID, size after the header and child count (little-endian)
+    pub fn parse(data: &[u8]) -> Self {
+        let mut traktor = TraktorData { size: data.len(), chunks: Vec::new(), cues: Vec::new(), problem: None };
+        if let Err(problem) = traktor.read_chunks(data, "", 0) {
+            traktor.problem = Some(problem);
+        }
+        traktor
+    }
+
+    fn read_chunks(&mut self, data: &[u8], parent: &str, depth: usize) -> Result<(), String> {
+        let mut pos = 0;
+        while pos + 12 <= data.len() {
+            let mut id = data[pos..pos + 4].to_vec();
+            id.reverse();
+            let id = String::from_utf8_lossy(&id).into_owned();
+            let size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+            let children = u32::from_le_bytes([data[pos + 8], data[pos + 9], data[pos + 10], data[pos + 11]]);
+            let Some(payload) = data.get(pos + 12..(pos + 12).saturating_add(size)) else {
+                return Err(format!("chunk '{}' at offset {} claims {} bytes, {} are left", id, pos, size, data.len() - pos - 12));
+            };
+            let path = if parent.is_empty() {
+                id.clone()
+            } else {
+                format!("{}/{}", parent, id)
+            };
+            if depth < MAX_TRAKTOR_DEPTH {
+                self.chunks.push((path.clone(), size));
+            }
+            if children > 0 {
+                self.read_chunks(payload, &path, depth + 1)?;
+            } else if id == "CUEP" {
+                self.cues = parse_cues(payload).ok_or_else(|| "CUEP chunk does not hold a readable cue list".to_string())?;
+            }
+            pos += 12 + size;
+        }
+        Ok(())
+    }
+}
+
+/// Cue list: count, then per cue a version, the UTF-16 name with its length in characters,
+/// display order, type, start, length and repeats (milliseconds as doubles) and the hot cue slot
+fn parse_cues(data: &[u8]) -> Option<Vec<TraktorCue>> {
+    let read_u32 = |pos: usize| data.get(pos..pos + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    let read_f64 = |pos: usize| data.get(pos..pos + 8).map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap_or_default()));
+    let count = read_u32(0)?;
+    let mut cues = Vec::new();
+    let mut pos = 4;
+    for _ in 0..count.min(256) {
+        let name_length = read_u32(pos + 4)? as usize;
+        let name_bytes = data.get(pos + 8..pos + 8 + name_length.checked_mul(2)?)?;
+        let units: Vec<u16> = name_bytes.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+        pos += 8 + name_bytes.len();
+        cues.push(TraktorCue {
+            name: String::from_utf16_lossy(&units),
+            cue_type: read_u32(pos + 4)?,
+            start_ms: read_f64(pos + 8)?,
+            length_ms: read_f64(pos + 16)?,
+            hotcue: read_u32(pos + 32)? as i32,
+        });
+        pos += 36;
+    }
+    Some(cues)
+}
+
+impl DjInfo {
+    /// DJ fields of the top-level frames of an ID3v2 tag; `None` if the tag has none of them
+    pub fn from_frames(frames: &[Id3v2Frame]) -> Option<Self> {
+        let mut info = DjInfo { keys: Vec::new(), bpm: None, energy: None, traktor: None, serato: Vec::new(), findings: Vec::new() };
+        for frame in frames {
+            match (&frame.content, frame.id.as_str()) {
+                | (Some(Id3v2FrameContent::Text(_)), "TKEY") => info.add_key("TKEY", frame.get_text().unwrap_or_default()),
+                | (Some(Id3v2FrameContent::Text(_)), "TBPM") => info.bpm = frame.get_text().map(str::to_string),
+                | (Some(Id3v2FrameContent::UserText(user_text)), _) => {
+                    let description = user_text.description.trim();
+                    let source = format!("TXXX:{}", description);
+                    if KEY_DESCRIPTIONS.iter().any(|known| known.eq_ignore_ascii_case(description)) {
+                        info.add_key(&source, &user_text.value);
+                    } else if ENERGY_DESCRIPTIONS.iter().any(|known| known.eq_ignore_ascii_case(description)) {
+                        info.add_energy(&source, &user_text.value);
+                    }
+                }
+                // Mixed In Key: "8A - Energy 6"
+                | (Some(Id3v2FrameContent::Comment(comment)), "COMM") => {
+                    if let Some((key, energy)) = comment.text.trim_end_matches('\0').split_once(" - Energy ") {
+                        info.add_key("COMM", key);
+                        if info.energy.is_none() {
+                            info.add_energy("COMM", energy);
+                        }
+                    }
+                }
+                | (Some(Id3v2FrameContent::Private(private)), _) if private.owner_identifier == TRAKTOR_OWNER => {
+                    let traktor = TraktorData::parse(&private.data);
+                    if let Some(problem) = &traktor.problem {
+                        info.findings.push(Finding::new(Severity::Warning, "PRIV:TRAKTOR4", problem.as_str()));
+                    }
+                    info.traktor = Some(traktor);
+                }
+                | (Some(Id3v2FrameContent::GeneralObject(object)), _) if matches!(object.description.as_str(), MARKERS_DESCRIPTION | BEATGRID_DESCRIPTION) => {
+                    info.serato.push(object.description.clone());
+                }
+                | _ => {}
+            }
+        }
+
+        // All keys should name the same key, whatever the notation
+        let parsed: Vec<&KeyField> = info.keys.iter().filter(|field| field.key.is_some()).collect();
+        if let Some(first) = parsed.first()
+            && let Some(other) = parsed.iter().find(|field| field.key != first.key)
+        {
+            info.findings.push(Finding::new(
+                Severity::Warning,
+                &other.source,
+                format!("key '{}' differs from '{}' in {}", other.value, first.value, first.source),
+            ));
+        }
+
+        let empty = info.keys.is_empty() && info.bpm.is_none() && info.energy.is_none() && info.traktor.is_none() && info.serato.is_empty();
+        (!empty).then_some(info)
+    }
+
+    fn add_key(&mut self, source: &str, value: &str) {
+        let value = value.trim_end_matches('\0').trim();
+        let key = MusicalKey::parse(value);
+        // "o" marks an off-key track in TKEY
+        if key.is_none() && value != "o" {
+            self.findings.push(Finding::new(Severity::Warning, source, format!("'{}' is not a key in musical, Camelot or Open Key notation", value)));
+        }
+        self.keys.push(KeyField { source: source.to_string(), value: value.to_string(), key });
+    }
+
+    fn add_energy(&mut self, source: &str, value: &str) {
+        let value = value.trim_end_matches('\0').trim();
+        match value.parse::<u32>() {
+            | Ok(energy) if (1..=10).contains(&energy) => self.energy = Some((source.to_string(), energy)),
+            | _ => self.findings.push(Finding::new(Severity::Warning, source, format!("energy '{}' is not a number from 1 to 10", value))),
+        }
+    }
+}
+
+/// Print the DJ fields and their findings, if the tag has any
+pub fn print_dj_info(info: Option<&DjInfo>) {
+    let Some(info) = info else {
+        return;
+    };
+    outln!("\nDJ Fields:");
+    for field in &info.keys {
+        match &field.key {
+            | Some(key) => outln!("  Key ({}): {} ({}, Camelot {}, Open Key {})", field.source, field.value, key, key.camelot(), key.open_key()),
+            | None => outln!("  Key ({}): {}", field.source, field.value),
+        }
+    }
+    if let Some(bpm) = &info.bpm {
+        outln!("  BPM (TBPM): {}", bpm);
+    }
+    if let Some((source, energy)) = &info.energy {
+        outln!("  Energy ({}): {}", source, energy);
+    }
+    if let Some(traktor) = &info.traktor {
+        outln!("  Traktor (PRIV:TRAKTOR4, {} bytes):", traktor.size);
+        for (path, size) in &traktor.chunks {
+            outln!("    {} ({} bytes)", path, size);
+        }
+        for cue in &traktor.cues {
+            let mut line = format!("    {} \"{}\" at {}", cue.type_name(), cue.name, format_duration(cue.start_ms.max(0.0) as u64));
+            if cue.length_ms > 0.0 {
+                line.push_str(&format!(", length {}", format_duration(cue.length_ms as u64)));
+            }
+            if cue.hotcue >= 0 {
+                line.push_str(&format!(", hot cue {}", cue.hotcue + 1));
+            }
+            outln!("{}", line);
+        }
+    }
+    if !info.serato.is_empty() {
+        outln!("  Serato (GEOB): {}", info.serato.join(", "));
+    }
+    for finding in &info.findings {
+        outln!("    {}", finding);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::coverage::Coverage;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::media_dissector::{Capabilities, MediaDissector, Operation, Signature};
+use crate::media_source::MediaSource;
+use crate::media_summary::{MediaSummary, TagSummary};
+use crate::options::DebugOptions;
+use crate::size_tree::{SizeNode, tag_node};
+use crate::time_format::format_duration;
+use std::io::SeekFrom;
+
+/// Dissector for DSD Stream Files (Sony DSF)
+///
+/// DSF files use 64-bit little-endian chunk sizes. The "DSD " header chunk holds the file
+/// size and a pointer to the ID3v2 tag at the end of the file (0 if there is none).
+pub struct DsfDissector;
+
+/// Fields of the "DSD " and "fmt " chunks
+struct DsfHeader {
+    file_size: u64,
+    metadata_offset: u64,
+    channels: u32,
+    sample_rate: u32,
+    bits_per_sample: u32,
+    sample_count: u64,
+}
+
+impl MediaDissector for DsfDissector {
+    fn media_type(&self) -> &'static str {
+        "DSF"
+    }
+
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+        dissect_dsf_with_options(file, options)
+    }
+
+    fn can_handle(&self, header: &[u8]) -> bool {
+        header.len() >= 4 && &header[0..4] == b"DSD "
+    }
+
+    fn name(&self) -> &'static str {
+        "DSF Dissector"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            extensions: &["dsf"],
+            signatures: &[
+                Signature { offset: 0, magic: b"DSD ", description: "DSD chunk" },
+            ],
+            operations: &[Operation::Read, Operation::Validate],
+        }
+    }
+
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        let mut coverage = Coverage::new(file.byte_len()?);
+        let header = read_header(file)?;
+        coverage.interpret(0, 28 + 52);
+
+        // The data chunk follows the fmt chunk: "data" and a 64-bit size including its 12-byte header
+        let mut data_header = [0u8; 12];
+        file.seek(SeekFrom::Start(28 + 52))?;
+        if file.read_exact(&mut data_header).is_ok() && &data_header[0..4] == b"data" {
+            coverage.interpret(28 + 52, u64::from_le_bytes(data_header[4..12].try_into().unwrap_or_default()));
+        }
+        if read_tag(file, &header)?.is_some() {
+            coverage.interpret(header.metadata_offset, coverage.file_size - header.metadata_offset);
+        }
+        Ok(coverage.finish())
+    }
+
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let header = read_header(file)?;
+        let mut children = vec![SizeNode::new("'DSD ' chunk", 28), SizeNode::new("'fmt ' chunk", 52)];
+        let data_end = match read_tag(file, &header)? {
+            | Some(tag) => {
+                children.push(tag_node(&tag));
+                header.metadata_offset
+            }
+            | None => file_size,
+        };
+        children.push(SizeNode::new("'data' chunk", data_end.saturating_sub(28 + 52)));
+        Ok(SizeNode::with_children("file", file_size, children, "other"))
+    }
+
+    fn summarize(&self, file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let mut summary = MediaSummary::new(self.media_type(), self.name());
+        let header = read_header(file)?;
+        summary.tag = read_tag(file, &header)?.map(|tag| TagSummary::from(&tag));
+        Ok(summary)
+    }
+}
+
+pub fn dissect_dsf_with_options(file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let header = read_header(file)?;
+    let actual_size = file.byte_len()?;
+
+    if options.show_header {
+        outln!("\nDSF Container:");
+        outln!("  File Size: {} bytes", header.file_size);
+        if header.file_size != actual_size {
+            outln!("  WARNING: header file size does not match the actual size {}", actual_size);
+        }
+        outln!("  Metadata Offset: {}", header.metadata_offset);
+        outln!("  Channels: {}", header.channels);
+        outln!("  Sample Rate: {} Hz", header.sample_rate);
+        outln!("  Bits per Sample: {}", header.bits_per_sample);
+        if header.sample_rate > 0 {
+            outln!("  Duration: {} ({} samples)", format_duration(header.sample_count * 1000 / header.sample_rate as u64), header.sample_count);
+        }
+    }
+
+    if !options.show_frames {
+        return Ok(());
+    }
+
+    match read_tag(file, &header)? {
+        | Some(tag) => crate::id3v2_embedded::print_embedded_tag(&tag, &format!("metadata at 0x{:08X}", header.metadata_offset), options)?,
+        | None if header.metadata_offset != 0 => outln!("\nWARNING: no ID3v2 tag at the metadata offset {}", header.metadata_offset),
+        | None => outln!("\nNo metadata (ID3v2 tag) present"),
+    }
+    Ok(())
+}
+
+/// Read the "DSD " chunk (28 bytes) and the following "fmt " chunk
+fn read_header(file: &mut dyn MediaSource) -> Result<DsfHeader, Box<dyn std::error::Error>> {
+    let mut buffer = [0u8; 28 + 52];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut buffer)?;
+    if &buffer[28..32] != b"fmt " {
+        return Err("DSF file without a fmt chunk after the DSD chunk".into());
+    }
+    let read_u32 = |pos: usize| u32::from_le_bytes(buffer[pos..pos + 4].try_into().unwrap_or_default());
+    let read_u64 = |pos: usize| u64::from_le_bytes(buffer[pos..pos + 8].try_into().unwrap_or_default());
+    Ok(DsfHeader {
+        file_size: read_u64(12),
+        metadata_offset: read_u64(20),
+        channels: read_u32(28 + 24),
+        sample_rate: read_u32(28 + 28),
+        bits_per_sample: read_u32(28 + 32),
+        sample_count: read_u64(28 + 36),
+    })
+}
+
+/// The ID3v2 tag the metadata pointer refers to, if any
+fn read_tag(file: &mut dyn MediaSource, header: &DsfHeader) -> Result<Option<Id3v2Tag>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    if header.metadata_offset == 0 || header.metadata_offset >= file_size {
+        return Ok(None);
+    }
+    let mut data = vec![0u8; (file_size - header.metadata_offset) as usize];
+    file.seek(SeekFrom::Start(header.metadata_offset))?;
+    file.read_exact(&mut data)?;
+    Ok(Id3v2Tag::parse(&data))
+}
@@ -0,0 +1,155 @@
+/// Duplicate-track detection across a library (`dupes`)
+///
+/// Files are grouped when their audio payload hashes match (same audio, different tags)
+/// or when they carry the same MusicBrainz recording ID but different audio (re-encodes).
+/// The largest file of each group is considered the one to keep; the rest is wasted space.
+/// Hard links of one file are listed as such instead, as deleting them reclaims nothing.
+use crate::audio_hash::audio_hash;
+use crate::batch_errors::BatchErrors;
+use crate::dissector_builder::DissectorBuilder;
+use crate::file_collector::collect_files;
+use crate::file_links::unique_files;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::path_display::display_path;
+use crate::progress_events::FileProgress;
+use owo_colors::OwoColorize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// UFID owner used by MusicBrainz Picard for recording IDs
+const MUSICBRAINZ_UFID_OWNER: &str = "http://musicbrainz.org";
+
+/// Media file considered for duplicate detection
+#[derive(Debug, Clone)]
+struct LibraryTrack {
+    path: PathBuf,
+    size: u64,
+    format: &'static str,
+    audio_hash: String,
+    recording_id: Option<String>,
+}
+
+/// Reason files were grouped together
+#[derive(Debug, Clone)]
+enum DuplicateKind {
+    /// Identical audio payload
+    SameAudio(String),
+    /// Same MusicBrainz recording with different audio
+    SameRecording(String),
+}
+
+#[derive(Debug, Clone)]
+struct DuplicateGroup {
+    kind: DuplicateKind,
+    tracks: Vec<LibraryTrack>,
+}
+
+/// Scan `dir` recursively and report groups of likely duplicates
+pub fn find_duplicates(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    // Hard links share their data, so they are one file rather than duplicates
+    let mut errors = BatchErrors::default();
+    let (files, links) = unique_files(&collect_files(dir, &mut errors)?);
+    let mut tracks = Vec::new();
+    let mut skipped = 0;
+
+    let total = files.len();
+    for (index, path) in files.into_iter().enumerate() {
+        let progress = FileProgress::start(&path, index + 1, total);
+        let result = read_track(&path);
+        progress.done(&result);
+        match result {
+            | Ok(Some(track)) => tracks.push(track),
+            | Ok(None) => skipped += 1,
+            | Err(e) if errors.record(&path, &*e) => {}
+            | Err(e) => {
+                outln!("{}", format!("Skipping {}: {}", display_path(&path), e).bright_red());
+                skipped += 1;
+            }
+        }
+    }
+
+    outln!("Scanned {} media file(s) in {} ({} other file(s) skipped)", tracks.len(), display_path(dir), skipped);
+    for link in &links {
+        outln!("  {}", link);
+    }
+    errors.print_summary("  ");
+
+    let groups = group_duplicates(&tracks);
+    let mut wasted_paths = HashSet::new();
+    let mut wasted_bytes = 0u64;
+
+    for group in &groups {
+        let keep = group.tracks.iter().max_by_key(|track| track.size).map(|track| &track.path);
+        let group_wasted: u64 = group.tracks.iter().filter(|track| Some(&track.path) != keep).map(|track| track.size).sum();
+
+        let title = match &group.kind {
+            | DuplicateKind::SameAudio(hash) => format!("Identical audio (sha256 {})", &hash[..16]),
+            | DuplicateKind::SameRecording(id) => format!("Same MusicBrainz recording {}", id),
+        };
+        outln!("\n{}: {} files, {} bytes reclaimable", title.bright_yellow(), group.tracks.len(), group_wasted);
+
+        for track in &group.tracks {
+            let marker = if Some(&track.path) == keep {
+                "keep"
+            } else {
+                "dupe"
+            };
+            outln!("  [{}] {} ({}, {} bytes)", marker, display_path(&track.path), track.format, track.size);
+            if Some(&track.path) != keep && wasted_paths.insert(track.path.clone()) {
+                wasted_bytes += track.size;
+            }
+        }
+    }
+
+    outln!("\nSummary: {} duplicate group(s), {} redundant file(s), {} bytes wasted", groups.len(), wasted_paths.len(), wasted_bytes);
+    errors.finish()
+}
+
+/// Hash a file and read its recording ID; `None` for files no dissector recognizes
+fn read_track(path: &Path) -> Result<Option<LibraryTrack>, Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    // Files too short for format detection cannot be media files
+    let Ok(dissector) = DissectorBuilder::new().build_for_file(&mut file) else {
+        return Ok(None);
+    };
+    let format = dissector.media_type();
+    if format == "Unknown" {
+        return Ok(None);
+    }
+
+    let recording_id = Id3v2Tag::read(&mut file)?.and_then(|tag| tag.unique_file_id(MUSICBRAINZ_UFID_OWNER));
+    let audio_hash = audio_hash(&mut file)?;
+
+    Ok(Some(LibraryTrack { path: path.to_path_buf(), size, format, audio_hash, recording_id }))
+}
+
+/// Group tracks by audio hash, then by recording ID for tracks whose audio differs
+fn group_duplicates(tracks: &[LibraryTrack]) -> Vec<DuplicateGroup> {
+    let mut by_hash: BTreeMap<&str, Vec<&LibraryTrack>> = BTreeMap::new();
+    let mut by_recording: BTreeMap<&str, Vec<&LibraryTrack>> = BTreeMap::new();
+    for track in tracks {
+        by_hash.entry(&track.audio_hash).or_default().push(track);
+        if let Some(id) = &track.recording_id {
+            by_recording.entry(id).or_default().push(track);
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(hash, members)| DuplicateGroup { kind: DuplicateKind::SameAudio(hash.to_string()), tracks: members.into_iter().cloned().collect() })
+        .collect();
+
+    // Recordings whose files all share one audio hash are already reported above
+    groups.extend(
+        by_recording
+            .into_iter()
+            .filter(|(_, members)| members.iter().map(|track| &track.audio_hash).collect::<HashSet<_>>().len() > 1)
+            .map(|(id, members)| DuplicateGroup { kind: DuplicateKind::SameRecording(id.to_string()), tracks: members.into_iter().cloned().collect() }),
+    );
+
+    groups
+}
@@ -0,0 +1,161 @@
+/// Text encoding census and UTF-8 normalization planner (`encodings`)
+///
+/// Lists which text encodings are used by which frames, per file and for the whole
+/// library, and prints the `tag reencode` commands that bring every tag to UTF-8.
+use crate::batch_errors::BatchErrors;
+use crate::file_collector::collect_files;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_text_encoding::{TextEncoding, assumed_encoding};
+use crate::path_display::display_path;
+use crate::progress_events::FileProgress;
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// All encodings in the order they are reported
+const ENCODINGS: [TextEncoding; 4] = [TextEncoding::Iso88591, TextEncoding::Utf16Bom, TextEncoding::Utf16Be, TextEncoding::Utf8];
+
+/// Encodings found in one file's tag
+#[derive(Debug)]
+struct FileCensus {
+    path: PathBuf,
+    version_major: u8,
+    /// Frame IDs per encoding byte (sub-frames as "CHAP/TIT2")
+    frames: BTreeMap<u8, Vec<String>>,
+}
+
+impl FileCensus {
+    fn needs_reencode(&self) -> bool {
+        self.frames.keys().any(|&encoding| encoding != TextEncoding::Utf8 as u8)
+    }
+}
+
+/// Print the encoding census for a file or directory and the UTF-8 normalization plan
+pub fn encoding_census(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut errors = BatchErrors::default();
+    let files = if path.is_dir() {
+        collect_files(path, &mut errors)?
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut census = Vec::new();
+    let mut untagged = 0;
+    let total = files.len();
+    for (index, file_path) in files.into_iter().enumerate() {
+        let progress = FileProgress::start(&file_path, index + 1, total);
+        let result = read_census(&file_path);
+        progress.done(&result);
+        match result {
+            | Ok(Some(file_census)) => census.push(file_census),
+            | Ok(None) => untagged += 1,
+            | Err(e) if errors.record(&file_path, &*e) => {}
+            | Err(e) => outln!("{}", format!("Skipping {}: {}", display_path(&file_path), e).bright_red()),
+        }
+    }
+
+    for file_census in &census {
+        outln!("{} (ID3v2.{})", display_path(&file_census.path).bright_cyan(), file_census.version_major);
+        if file_census.frames.is_empty() {
+            outln!("  (no text frames)");
+        }
+        for (&encoding, frame_ids) in &file_census.frames {
+            outln!("  {:<16} {}", encoding_name(encoding), frame_ids.join(", "));
+        }
+    }
+
+    outln!("\nLibrary totals ({} tagged file(s), {} without ID3v2 tag):", census.len(), untagged);
+    errors.print_summary("  ");
+    for encoding in ENCODINGS {
+        let mut frame_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut file_count = 0;
+        for file_census in &census {
+            if let Some(frame_ids) = file_census.frames.get(&(encoding as u8)) {
+                file_count += 1;
+                for frame_id in frame_ids {
+                    *frame_counts.entry(frame_id).or_default() += 1;
+                }
+            }
+        }
+        if file_count == 0 {
+            continue;
+        }
+        let total: usize = frame_counts.values().sum();
+        let breakdown: Vec<String> = frame_counts.iter().map(|(frame_id, count)| format!("{} x{}", frame_id, count)).collect();
+        outln!("  {:<16} {} frame(s) in {} file(s): {}", encoding.to_string(), total, file_count, breakdown.join(", "));
+    }
+
+    let pending: Vec<&FileCensus> = census.iter().filter(|file_census| file_census.needs_reencode()).collect();
+    outln!("\nNormalization plan (UTF-8):");
+    if pending.is_empty() {
+        outln!("  Nothing to do, all text frames are UTF-8");
+    }
+    // The plan reads ISO-8859-1 text in the same code page as this census
+    let assumed = assumed_encoding().and_then(|encoding| encoding.to_possible_value()).map(|value| format!("--assume-encoding {} ", value.get_name())).unwrap_or_default();
+    for file_census in pending {
+        if TextEncoding::Utf8.is_valid_for_version(file_census.version_major) {
+            outln!("supertool {}tag reencode --encoding utf8 {}", assumed, shell_quote(&file_census.path));
+        } else {
+            outln!("# {}: ID3v2.{} cannot store UTF-8 (convert the tag to ID3v2.4 first)", display_path(&file_census.path), file_census.version_major);
+        }
+    }
+
+    errors.finish()
+}
+
+/// Collect the encodings of a file's tag; `None` if the file has no ID3v2 tag
+fn read_census(path: &Path) -> Result<Option<FileCensus>, Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let Some(tag) = Id3v2Tag::read(&mut file)? else {
+        return Ok(None);
+    };
+
+    let mut frames = BTreeMap::new();
+    collect_encodings(&tag.frames, "", &mut frames);
+    Ok(Some(FileCensus { path: path.to_path_buf(), version_major: tag.version_major, frames }))
+}
+
+fn collect_encodings(frames: &[Id3v2Frame], prefix: &str, census: &mut BTreeMap<u8, Vec<String>>) {
+    for frame in frames {
+        if let Some(encoding) = frame.text_encoding() {
+            census.entry(encoding as u8).or_default().push(format!("{}{}", prefix, frame.id));
+        }
+        let sub_frames = match &frame.content {
+            | Some(Id3v2FrameContent::Chapter(chapter)) => &chapter.sub_frames,
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => &toc.sub_frames,
+            | _ => continue,
+        };
+        collect_encodings(sub_frames, &format!("{}{}/", prefix, frame.id), census);
+    }
+}
+
+fn encoding_name(encoding: u8) -> String {
+    TextEncoding::from_byte(encoding).map(|encoding| encoding.to_string()).unwrap_or_else(|error| error.to_string())
+}
+
+/// Quote a path for POSIX shells
+///
+/// Names with control characters or bytes that are not valid UTF-8 use `$'...'` quoting with
+/// `\xNN` escapes, so the script names exactly the file that was scanned.
+fn shell_quote(path: &Path) -> String {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    if let Some(name) = path.to_str().filter(|name| !name.chars().any(char::is_control)) {
+        return format!("'{}'", name.replace('\'', "'\\''"));
+    }
+    let mut quoted = String::from("$'");
+    for &byte in bytes {
+        match byte {
+            | b'\'' | b'\\' => {
+                quoted.push('\\');
+                quoted.push(byte as char);
+            }
+            | 0x20..=0x7E => quoted.push(byte as char),
+            | _ => quoted.push_str(&format!("\\x{:02X}", byte)),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
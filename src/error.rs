@@ -0,0 +1,109 @@
+/// Typed errors of the parsers
+///
+/// The frame parsers, text decoding and `Id3v2Tag::read` fail with a `SupertoolError`, so
+/// library users can match on the kind of failure instead of on message text. Functions that
+/// combine several steps keep returning `Box<dyn Error>`; the typed error travels inside it and
+/// `exit_code` finds it again, which the command line uses as its exit status.
+use crate::checked_size::SizeError;
+use std::fmt;
+use std::io;
+
+/// Exit status for files that could not be read or written (sysexits EX_IOERR)
+pub const EXIT_IO: i32 = 74;
+
+/// Exit status for malformed input data (sysexits EX_DATAERR)
+pub const EXIT_DATA: i32 = 65;
+
+/// Exit status for data in a version or form that is not supported (sysexits EX_UNAVAILABLE)
+pub const EXIT_UNSUPPORTED: i32 = 69;
+
+/// Exit status for all other errors
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Why parsing failed
+#[derive(Debug)]
+pub enum SupertoolError {
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// A tag, frame or box header that cannot be read
+    InvalidHeader(String),
+    /// A format version the parsers do not handle (e.g. "ID3v2.5.0")
+    UnsupportedVersion(String),
+    /// Text that cannot be decoded or encoded in its declared encoding
+    BadEncoding(String),
+    /// Frame content that ends before a required field or terminator
+    TruncatedFrame(String),
+    /// Frame content that is complete but malformed
+    InvalidFrame(String),
+    /// Frame content that cannot be read (encrypted)
+    UnsupportedFrame(String),
+    /// A structure claims more bytes than there are
+    Size(SizeError),
+}
+
+impl SupertoolError {
+    /// Exit status of the command line for this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            | SupertoolError::Io(error) => io_exit_code(error),
+            | SupertoolError::InvalidHeader(_) | SupertoolError::BadEncoding(_) | SupertoolError::TruncatedFrame(_) | SupertoolError::InvalidFrame(_) | SupertoolError::Size(_) => EXIT_DATA,
+            | SupertoolError::UnsupportedVersion(_) | SupertoolError::UnsupportedFrame(_) => EXIT_UNSUPPORTED,
+        }
+    }
+}
+
+impl fmt::Display for SupertoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | SupertoolError::Io(error) => write!(f, "{}", error),
+            | SupertoolError::UnsupportedVersion(version) => write!(f, "unsupported version {}", version),
+            | SupertoolError::Size(error) => write!(f, "{}", error),
+            | SupertoolError::InvalidHeader(message) | SupertoolError::BadEncoding(message) | SupertoolError::TruncatedFrame(message) | SupertoolError::InvalidFrame(message) | SupertoolError::UnsupportedFrame(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SupertoolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            | SupertoolError::Io(error) => Some(error),
+            | SupertoolError::Size(error) => Some(error),
+            | _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SupertoolError {
+    fn from(error: io::Error) -> Self {
+        SupertoolError::Io(error)
+    }
+}
+
+impl From<SizeError> for SupertoolError {
+    fn from(error: SizeError) -> Self {
+        SupertoolError::Size(error)
+    }
+}
+
+/// Exit status for an error returned by a command: the typed errors by kind, everything else 1
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> i32 {
+    if let Some(error) = error.downcast_ref::<SupertoolError>() {
+        error.exit_code()
+    } else if let Some(error) = error.downcast_ref::<io::Error>() {
+        io_exit_code(error)
+    } else if error.is::<SizeError>() {
+        EXIT_DATA
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+/// A file that ends too early or holds undecodable data is malformed, not unreadable
+fn io_exit_code(error: &io::Error) -> i32 {
+    match error.kind() {
+        | io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData => EXIT_DATA,
+        | _ => EXIT_IO,
+    }
+}
@@ -0,0 +1,263 @@
+/// Collection of files for commands that operate on whole directories or several files
+use crate::batch_errors::BatchErrors;
+use crate::dissector_builder::DissectorBuilder;
+use crate::file_links::{FileId, file_id};
+use crate::options::FollowLinks;
+use crate::path_display::{display_path, long_path};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Collect all regular files below `dir` in sorted order
+///
+/// Symbolic links are not followed, so link cycles cannot cause endless recursion. File names
+/// are kept as they are (`OsStr`), whether or not they are valid UTF-8; deep trees on Windows
+/// are read through extended-length paths. Subdirectories that cannot be read are recorded in
+/// `errors` and left out; only an unreadable `dir` is an error.
+pub fn collect_files(dir: &Path, errors: &mut BatchErrors) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    collect_files_following(dir, FollowLinks::Never, errors)
+}
+
+/// Collect all regular files below `dir` in sorted order, following symbolic links as `follow` says
+///
+/// Directory links are followed after the rest of the tree has been walked, and every directory
+/// is walked once, so files keep their real paths where they have one and a link back to a parent
+/// ends the recursion. Links whose target is missing are left out.
+pub fn collect_files_following(dir: &Path, follow: FollowLinks, errors: &mut BatchErrors) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let dir = long_path(dir);
+    let entries = fs::read_dir(&dir)?;
+    let mut walk = CollectWalk { follow, walked: HashSet::new(), linked_dirs: Vec::new(), files: Vec::new(), errors };
+    walk.walked.extend(file_id(&dir));
+    walk.collect_entries(&dir, entries);
+    while let Some(linked_dir) = walk.linked_dirs.pop() {
+        if file_id(&linked_dir).is_none_or(|id| walk.walked.insert(id)) {
+            walk.collect_into(&linked_dir);
+        }
+    }
+    walk.files.sort();
+    Ok(walk.files)
+}
+
+/// State of `collect_files_following`
+struct CollectWalk<'a> {
+    follow: FollowLinks,
+    /// Directories walked, when directory links are followed
+    walked: HashSet<FileId>,
+    /// Directory links still to follow
+    linked_dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+    errors: &'a mut BatchErrors,
+}
+
+impl CollectWalk<'_> {
+    fn collect_into(&mut self, dir: &Path) {
+        match fs::read_dir(dir) {
+            | Ok(entries) => self.collect_entries(dir, entries),
+            | Err(e) => {
+                self.errors.record(dir, &e);
+            }
+        }
+    }
+
+    fn collect_entries(&mut self, dir: &Path, entries: fs::ReadDir) {
+        for entry in entries {
+            let (entry, file_type) = match entry.and_then(|entry| entry.file_type().map(|file_type| (entry, file_type))) {
+                | Ok(entry) => entry,
+                | Err(e) => {
+                    self.errors.record(dir, &e);
+                    continue;
+                }
+            };
+            if file_type.is_symlink() {
+                if self.follow == FollowLinks::Never {
+                    continue;
+                }
+                match fs::metadata(entry.path()) {
+                    | Ok(target) if target.is_dir() && self.follow == FollowLinks::All => self.linked_dirs.push(entry.path()),
+                    | Ok(target) if target.is_file() => self.files.push(entry.path()),
+                    | _ => {}
+                }
+            } else if file_type.is_dir() {
+                if self.follow == FollowLinks::All {
+                    self.walked.extend(file_id(&entry.path()));
+                }
+                self.collect_into(&entry.path());
+            } else if file_type.is_file() {
+                self.files.push(entry.path());
+            }
+        }
+    }
+}
+
+/// Which files directory and pattern arguments stand for (`--recursive`, `--ext`, `--exclude-ext`)
+#[derive(Debug, Clone, Default)]
+pub struct FileSelection {
+    /// Walk directory arguments; without it a directory is an error
+    pub recursive: bool,
+    /// Extensions to keep (without dot, any case); empty keeps all
+    pub include: Vec<String>,
+    /// Extensions to leave out
+    pub exclude: Vec<String>,
+    /// Symbolic links followed when walking directories
+    pub follow_links: FollowLinks,
+}
+
+impl FileSelection {
+    /// Whether the extension filters keep the file
+    pub fn matches(&self, path: &Path) -> bool {
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let listed = |extensions: &[String]| extensions.iter().any(|listed| listed.trim_start_matches('.').eq_ignore_ascii_case(&extension));
+        (self.include.is_empty() || listed(&self.include)) && !listed(&self.exclude)
+    }
+}
+
+/// Files named by command-line arguments: files as given, directories by their media files, and glob patterns
+///
+/// An argument that exists is taken literally, so names like "Song [Live].mp3" need no escaping.
+/// Otherwise `*`, `?` and `[...]` (with ranges and `!` for negation) match within one path
+/// component and `**` matches any number of directories, as shells do; this covers shells that
+/// do not expand patterns (Windows) and quoted patterns. Each pattern's files are sorted, and a
+/// file named twice is kept once.
+///
+/// Directories are walked with `selection.recursive` only, and keep the files a format dissector
+/// recognizes, so covers, playlists and text files are left out. The extension filters apply to
+/// the files of directories and patterns; files named as they are always stay. Files and
+/// subdirectories of a walked directory that cannot be read are recorded in `errors`.
+pub fn expand_paths(arguments: &[PathBuf], selection: &FileSelection, errors: &mut BatchErrors) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for argument in arguments {
+        let expanded = if long_path(argument).is_dir() {
+            if !selection.recursive {
+                return Err(format!("{} is a directory (use --recursive to analyze the media files below it)", display_path(argument)).into());
+            }
+            collect_files_following(argument, selection.follow_links, errors)?.into_iter().filter(|file| selection.matches(file) && is_media_file(file, errors)).collect()
+        } else if long_path(argument).exists() || !is_pattern(&argument.to_string_lossy()) {
+            vec![argument.clone()]
+        } else {
+            let matches: Vec<PathBuf> = glob(argument)?.into_iter().filter(|file| selection.matches(file)).collect();
+            if matches.is_empty() {
+                return Err(format!("no files match {}", display_path(argument)).into());
+            }
+            matches
+        };
+        for file in expanded {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Whether a format dissector recognizes the file; files that cannot be opened are recorded in `errors`
+fn is_media_file(path: &Path, errors: &mut BatchErrors) -> bool {
+    match fs::File::open(long_path(path)) {
+        | Ok(mut file) => DissectorBuilder::new().recognizes(&mut file),
+        | Err(e) => {
+            errors.record(path, &e);
+            false
+        }
+    }
+}
+
+fn is_pattern(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+/// Regular files matching a glob pattern, in sorted order
+fn glob(pattern: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut candidates = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let name = component.as_os_str().to_string_lossy();
+        if name == "**" {
+            let mut expanded = Vec::new();
+            for candidate in candidates {
+                collect_dirs(&candidate, &mut expanded);
+            }
+            candidates = expanded;
+        } else if is_pattern(&name) {
+            let mut matched = Vec::new();
+            for candidate in &candidates {
+                let dir = if candidate.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    candidate.as_path()
+                };
+                let Ok(entries) = fs::read_dir(long_path(dir)) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let file_name = entry.file_name();
+                    let entry_name = file_name.to_string_lossy();
+                    // Hidden files only match patterns that start with a dot
+                    if entry_name.starts_with('.') && !name.starts_with('.') {
+                        continue;
+                    }
+                    if wildcard_match(&name.chars().collect::<Vec<_>>(), &entry_name.chars().collect::<Vec<_>>()) {
+                        matched.push(candidate.join(&file_name));
+                    }
+                }
+            }
+            candidates = matched;
+        } else {
+            candidates = candidates.into_iter().map(|candidate| candidate.join(component)).collect();
+        }
+    }
+    let mut files: Vec<PathBuf> = candidates.into_iter().filter(|candidate| long_path(candidate).is_file()).collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// `dir` and all directories below it (for `**`), without following symbolic links
+fn collect_dirs(dir: &Path, dirs: &mut Vec<PathBuf>) {
+    dirs.push(dir.to_path_buf());
+    let read_from = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let Ok(entries) = fs::read_dir(long_path(read_from)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            collect_dirs(&dir.join(entry.file_name()), dirs);
+        }
+    }
+}
+
+/// Whether `name` matches the wildcard `pattern` (`*`, `?`, `[abc]`, `[a-z]`, `[!abc]`)
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        | None => name.is_empty(),
+        | Some('*') => (0..=name.len()).any(|skip| wildcard_match(&pattern[1..], &name[skip..])),
+        | Some('?') => !name.is_empty() && wildcard_match(&pattern[1..], &name[1..]),
+        | Some('[') => {
+            let Some(close) = pattern.iter().skip(2).position(|&c| c == ']').map(|pos| pos + 2) else {
+                // An unclosed bracket is a literal character
+                return name.first() == Some(&'[') && wildcard_match(&pattern[1..], &name[1..]);
+            };
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            let (negated, set) = match pattern[1] {
+                | '!' | '^' => (true, &pattern[2..close]),
+                | _ => (false, &pattern[1..close]),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    found |= set[i] <= c && c <= set[i + 2];
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && wildcard_match(&pattern[close + 1..], &name[1..])
+        }
+        | Some(&literal) => name.first() == Some(&literal) && wildcard_match(&pattern[1..], &name[1..]),
+    }
+}
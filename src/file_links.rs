@@ -0,0 +1,124 @@
+/// Hard links and symbolic links among the files of a batch
+///
+/// A library often reaches one file through several paths: hard links left by deduplicating
+/// tools, symbolic links into a shared folder. Batch commands dissect such a file once, under the
+/// first of its paths, and report the other paths as links of it, so statistics count it once.
+/// Files are identified by device and inode number; the standard library exposes them on Unix
+/// only, so other platforms compare canonical paths, which recognizes symbolic links but not hard
+/// links.
+use crate::path_display::{display_path, long_path};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+/// Identity of a file on disk, shared by all of its paths
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileId {
+    /// Device and inode number
+    Inode(u64, u64),
+    /// Canonical path, where inode numbers are not available
+    Path(PathBuf),
+}
+
+/// The identity of the file `path` leads to (symbolic links followed); `None` if it cannot be read
+#[cfg(unix)]
+pub fn file_id(path: &Path) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(long_path(path)).ok()?;
+    Some(FileId::Inode(metadata.dev(), metadata.ino()))
+}
+
+/// The identity of the file `path` leads to (symbolic links followed); `None` if it cannot be read
+#[cfg(not(unix))]
+pub fn file_id(path: &Path) -> Option<FileId> {
+    fs::canonicalize(long_path(path)).ok().map(FileId::Path)
+}
+
+/// Whether the file has further hard links (always false where link counts are not available)
+#[cfg(unix)]
+pub fn has_hard_links(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+/// Whether the file has further hard links (always false where link counts are not available)
+#[cfg(not(unix))]
+pub fn has_hard_links(_metadata: &Metadata) -> bool {
+    false
+}
+
+/// How a path leads to a file that was reached before
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkKind {
+    /// Another directory entry of the same inode
+    HardLink,
+    /// A symbolic link (of the file or of a directory on the way) resolving to the same path
+    SymbolicLink,
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | LinkKind::HardLink => write!(f, "hard link"),
+            | LinkKind::SymbolicLink => write!(f, "symbolic link"),
+        }
+    }
+}
+
+/// A path that leads to a file already reached through `original`
+#[derive(Debug, Clone)]
+pub struct FileLink {
+    pub path: PathBuf,
+    pub original: PathBuf,
+    pub kind: LinkKind,
+}
+
+impl fmt::Display for FileLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: same file as {} ({})", display_path(&self.path), display_path(&self.original), self.kind)
+    }
+}
+
+/// Remembers the files reached so far and recognizes further paths to them
+#[derive(Debug, Default)]
+pub struct LinkTracker {
+    seen: HashMap<FileId, PathBuf>,
+    /// Paths that led to a file reached before, in the order they were found
+    pub links: Vec<FileLink>,
+}
+
+impl LinkTracker {
+    /// Record `path`; false if it leads to a file reached before (the link is recorded then)
+    ///
+    /// Paths whose identity cannot be read count as new files, so their errors show up where
+    /// the file is opened.
+    pub fn first_visit(&mut self, path: &Path) -> bool {
+        let Some(id) = file_id(path) else {
+            return true;
+        };
+        match self.seen.get(&id) {
+            | Some(original) => {
+                let same_target = |path: &Path| fs::canonicalize(long_path(path)).ok();
+                let kind = if same_target(path) == same_target(original) {
+                    LinkKind::SymbolicLink
+                } else {
+                    LinkKind::HardLink
+                };
+                self.links.push(FileLink { path: path.to_path_buf(), original: original.clone(), kind });
+                false
+            }
+            | None => {
+                self.seen.insert(id, path.to_path_buf());
+                true
+            }
+        }
+    }
+}
+
+/// The paths that lead to distinct files, in their order, and the links among the others
+pub fn unique_files(paths: &[PathBuf]) -> (Vec<PathBuf>, Vec<FileLink>) {
+    let mut tracker = LinkTracker::default();
+    let unique = paths.iter().filter(|path| tracker.first_visit(path)).cloned().collect();
+    (unique, tracker.links)
+}
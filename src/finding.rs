@@ -0,0 +1,49 @@
+/// Findings reported by validation checks
+///
+/// A finding is a problem (or noteworthy fact) detected in a file that does not prevent
+/// dissection, e.g. a malformed URL in a W*** frame.
+use serde::Serialize;
+use std::fmt;
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing, no action needed
+    Info,
+    /// Likely wrong, players may still cope
+    Warning,
+    /// Violates the specification or is unusable
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            | Severity::Info => "INFO",
+            | Severity::Warning => "WARNING",
+            | Severity::Error => "ERROR",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Where the problem was found (frame ID, "CHAP/WXXX" for sub-frames)
+    pub location: String,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(severity: Severity, location: &str, message: impl Into<String>) -> Self {
+        Finding { severity, location: location.to_string(), message: message.into() }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]: {}", self.severity, self.location, self.message)
+    }
+}
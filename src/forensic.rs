@@ -0,0 +1,251 @@
+/// Forensic mode of the `debug` command (`--forensic`)
+///
+/// Archives and legal teams use dissection output as evidence, so it has to be shown that the
+/// file was not altered and where every reported structure lies. In forensic mode the file is
+/// hashed (SHA-256) before and after the dissection, options that write anything next to it are
+/// refused, and a JSON evidence report is written: the hashes, sizes and modification times
+/// before and after, the summary of the file, and the provenance of every structure as file
+/// offset and size. The report is signed with HMAC-SHA256 under a key the user provides.
+///
+/// The signature covers the compact JSON of the `evidence` object (no whitespace, fields in the
+/// order they are written), so anyone holding the key can verify the report.
+use crate::analysis::analyze_file;
+use crate::isobmff_box::read_boxes;
+use crate::media_summary::MediaSummary;
+use crate::options::{DebugOptions, ForensicOptions, TimelineFormat};
+use crate::path_display::{display_path, long_path};
+use crate::time_format::iso8601_date;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Signed evidence report
+#[derive(Debug, Serialize)]
+pub struct EvidenceReport {
+    pub evidence: Evidence,
+    pub signature: Signature,
+}
+
+/// Everything the signature covers
+#[derive(Debug, Serialize)]
+pub struct Evidence {
+    /// Tool name and version
+    pub tool: String,
+    /// When the report was created (ISO 8601, UTC)
+    pub created: String,
+    /// Command line of the run
+    pub command: Vec<String>,
+    /// Path of the file as given on the command line
+    pub file: String,
+    /// The file was only ever opened for reading
+    pub access: &'static str,
+    pub before: FileState,
+    pub after: FileState,
+    /// Hash, size and modification time are the same before and after
+    pub unchanged: bool,
+    /// Structures of the file with their offsets, in file order
+    pub provenance: Vec<Provenance>,
+    pub summary: MediaSummary,
+}
+
+/// Hash, size and modification time of the file at one point in time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileState {
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub modified: Option<u64>,
+    /// SHA-256 of the whole file as lowercase hex
+    pub sha256: String,
+}
+
+/// Byte range of one structure
+#[derive(Debug, Serialize)]
+pub struct Provenance {
+    pub offset: u64,
+    pub size: u64,
+    /// What the bytes are (e.g. "ID3v2 frame TIT2", "RIFF chunk 'data'", "skipped: padding")
+    pub structure: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Signature {
+    pub algorithm: &'static str,
+    /// HMAC of the compact `evidence` JSON as lowercase hex
+    pub value: String,
+}
+
+/// Refuse the options that would write files next to the evidence
+pub fn check_options(file_path: &Path, options: &DebugOptions, forensic: &ForensicOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if options.sidecar.is_some() {
+        return Err("--forensic cannot be combined with --sidecar; the evidence report holds the summary".into());
+    }
+    if options.timeline == Some(TimelineFormat::Html) {
+        return Err("--forensic cannot be combined with --timeline=html, which writes next to the file".into());
+    }
+    if options.extract_geob.is_some() {
+        return Err("--forensic cannot be combined with --extract-geob, which writes the objects of GEOB frames to files".into());
+    }
+    if options.inner.is_some() {
+        return Err("--forensic cannot be combined with --inner; the evidence is the archive as a whole".into());
+    }
+    if let (Ok(report), Ok(file)) = (fs::canonicalize(&forensic.report), fs::canonicalize(file_path))
+        && report == file
+    {
+        return Err("the evidence report would overwrite the analyzed file".into());
+    }
+    Ok(())
+}
+
+/// Run the dissection in `dissect` between two hashes of the file and write the signed report
+///
+/// Fails after writing the report if the file changed in between.
+pub fn with_evidence(file_path: &Path, options: &DebugOptions, forensic: &ForensicOptions, dissect: impl FnOnce() -> Result<(), Box<dyn std::error::Error>>) -> Result<(), Box<dyn std::error::Error>> {
+    check_options(file_path, options, forensic)?;
+    let key = fs::read(&forensic.key)?;
+    if key.is_empty() {
+        return Err(format!("the key file {} is empty", display_path(&forensic.key)).into());
+    }
+
+    let before = file_state(file_path)?;
+    dissect()?;
+    let summary = analyze_file(file_path)?;
+    let provenance = provenance(file_path, &summary)?;
+    let after = file_state(file_path)?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64);
+    let evidence = Evidence {
+        tool: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        created: iso8601_date(now),
+        command: std::env::args_os().map(|arg| arg.to_string_lossy().into_owned()).collect(),
+        file: file_path.to_string_lossy().into_owned(),
+        access: "read-only",
+        unchanged: before == after,
+        before,
+        after,
+        provenance,
+        summary,
+    };
+    let signature = Signature { algorithm: "HMAC-SHA256", value: hex(&hmac_sha256(&key, serde_json::to_string(&evidence)?.as_bytes())) };
+    let unchanged = evidence.unchanged;
+    let (before_hash, after_hash) = (evidence.before.sha256.clone(), evidence.after.sha256.clone());
+    let report = EvidenceReport { evidence, signature };
+    fs::write(&forensic.report, serde_json::to_string_pretty(&report)? + "\n")?;
+
+    outln!("\nEvidence report written: {} (SHA-256 {}, signed with HMAC-SHA256)", display_path(&forensic.report), before_hash);
+    if !unchanged {
+        return Err(format!("the file changed during the dissection (SHA-256 {} before, {} after)", before_hash, after_hash).into());
+    }
+    Ok(())
+}
+
+/// Hash, size and modification time of the file
+fn file_state(file_path: &Path) -> Result<FileState, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(file_path))?;
+    let metadata = file.metadata()?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(FileState {
+        size: metadata.len(),
+        modified: metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|duration| duration.as_secs()),
+        sha256: hex(&hasher.finalize()),
+    })
+}
+
+/// File offsets of the structures in the summary, and of the top-level boxes of ISO BMFF files
+fn provenance(file_path: &Path, summary: &MediaSummary) -> Result<Vec<Provenance>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    let mut add = |offset: u64, size: u64, structure: String| entries.push(Provenance { offset, size, structure });
+
+    // Tags at the start of the file; tags in WAVE chunks or DSF blocks are covered by their container
+    if let Some(tag) = summary.tag.as_ref().filter(|_| summary.format.starts_with("ID3v2")) {
+        add(0, tag.size, format!("{} tag", tag.tag_type));
+        // Frame offsets count from the end of the tag header; unsynchronised tags have no such mapping
+        if tag.flags & 0x80 == 0 {
+            let header_size = if tag.version.starts_with("2.2") {
+                6
+            } else {
+                10
+            };
+            for frame in &tag.frames {
+                if let Some(offset) = frame.offset {
+                    add(10 + offset as u64, header_size + frame.size as u64, format!("ID3v2 frame {}", frame.id));
+                }
+            }
+        }
+    }
+    if let Some(riff) = &summary.riff {
+        for chunk in &riff.chunks {
+            add(chunk.offset, chunk.end() - chunk.offset, format!("RIFF chunk '{}'", chunk.id));
+        }
+    }
+    if let Some(bundle) = &summary.bundle {
+        // Members whose local header cannot be read are listed with the skipped ranges
+        let mut file = File::open(long_path(file_path))?;
+        for entry in &bundle.entries {
+            if let Ok(data_start) = bundle.data_start(&mut file, entry) {
+                add(entry.local_header_offset, (data_start - entry.local_header_offset).saturating_add(entry.compressed_size), format!("ZIP member '{}' (local header and data)", entry.name));
+            }
+        }
+        add(bundle.directory_offset, summary.file_size.saturating_sub(bundle.directory_offset), "ZIP central directory".to_string());
+    }
+    if summary.movie.is_some() || !summary.tracks.is_empty() {
+        let mut file = File::open(long_path(file_path))?;
+        for box_header in read_boxes(&mut file, 0, summary.file_size)? {
+            add(box_header.offset, box_header.size, format!("ISO BMFF box '{}'", box_header.box_type));
+        }
+    }
+    for block in &summary.trailers {
+        add(block.offset, block.size, format!("{} block", block.kind));
+    }
+    for inline in &summary.inline_tags {
+        add(inline.offset, inline.size, format!("inline {}", inline.kind));
+    }
+    if let Some(coverage) = &summary.coverage {
+        for range in &coverage.skipped {
+            add(range.offset, range.size, format!("skipped: {}", range.reason));
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.offset);
+    Ok(entries)
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Known answers of RFC 4231, section 4
+    #[test]
+    fn hmac_sha256_matches_rfc_4231() {
+        assert_eq!(hex(&hmac_sha256(&[0x0B; 20], b"Hi There")), "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+        assert_eq!(hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn hmac_sha256_hashes_keys_longer_than_a_block() {
+        let message = b"Test Using Larger Than Block-Size Key - Hash Key First";
+        assert_eq!(hex(&hmac_sha256(&[0xAA; 131], message)), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+    }
+}
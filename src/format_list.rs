@@ -0,0 +1,79 @@
+/// Supported formats and build features (`supertool formats`)
+///
+/// Lists the capabilities every dissector declares, so users and scripts can find out what
+/// the installed build recognizes and which optional features it was compiled with.
+use crate::dissector_builder::DissectorBuilder;
+use crate::media_dissector::Capabilities;
+use serde::Serialize;
+
+/// One supported format
+#[derive(Debug, Serialize)]
+struct FormatEntry {
+    media_type: &'static str,
+    dissector: &'static str,
+    #[serde(flatten)]
+    capabilities: Capabilities,
+}
+
+/// Cargo features and whether this build includes them
+#[derive(Debug, Serialize)]
+struct BuildFeatures {
+    /// `--check-urls`
+    network: bool,
+    /// `--timeline=html`
+    report: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct FormatList {
+    version: &'static str,
+    formats: Vec<FormatEntry>,
+    features: BuildFeatures,
+}
+
+/// Print the supported formats as text or JSON
+pub fn list_formats(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let formats = DissectorBuilder::new()
+        .dissectors()
+        .iter()
+        .map(|dissector| FormatEntry { media_type: dissector.media_type(), dissector: dissector.name(), capabilities: dissector.capabilities() })
+        .collect();
+    let list = FormatList {
+        version: env!("CARGO_PKG_VERSION"),
+        formats,
+        features: BuildFeatures { network: cfg!(feature = "network"), report: cfg!(feature = "report") },
+    };
+
+    if json {
+        outln!("{}", serde_json::to_string_pretty(&list)?);
+        return Ok(());
+    }
+
+    outln!("Supported formats (supertool {}):", list.version);
+    for entry in &list.formats {
+        let capabilities = &entry.capabilities;
+        outln!("\n{} ({})", entry.media_type, entry.dissector);
+        outln!("  Extensions: {}", capabilities.extensions.join(", "));
+        for (i, signature) in capabilities.signatures.iter().enumerate() {
+            let label = if i == 0 {
+                "Signatures:"
+            } else {
+                ""
+            };
+            outln!("  {:<12}{}", label, signature);
+        }
+        outln!("  Operations: {}", capabilities.operations.iter().map(|operation| operation.to_string()).collect::<Vec<_>>().join(", "));
+    }
+
+    let enabled = |on: bool| {
+        if on {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    };
+    outln!("\nBuild features:");
+    outln!("  network: {} (--check-urls)", enabled(list.features.network));
+    outln!("  report: {} (--timeline=html)", enabled(list.features.report));
+    Ok(())
+}
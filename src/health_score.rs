@@ -0,0 +1,372 @@
+/// Triage score of media files (`triage`)
+///
+/// Condenses the problems found in a file into a score from 0 (badly broken) to 100 (nothing
+/// found), so that libraries of thousands of files can be sorted by how broken they are. Each
+/// category of problems takes off at most its weight: an error costs the full weight, a
+/// warning half of it, and informational findings nothing.
+use crate::analysis::analyze_file;
+use crate::batch_errors::BatchErrors;
+use crate::coverage::UNPARSED_TAG_DATA;
+use crate::file_collector::collect_files;
+use crate::finding::{Finding, Severity};
+use crate::media_summary::{FrameSummary, MediaSummary};
+use crate::ogg_page::OUTSIDE_PAGES;
+use crate::path_display::display_path;
+use crate::progress_events::FileProgress;
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Pictures larger than this slow down players and bloat every copy of the file
+pub const OVERSIZE_ART_BYTES: u32 = 1024 * 1024;
+
+/// Kinds of problems that are weighted separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueCategory {
+    /// Damaged structures, unparsable frames and junk in the audio data
+    Corruption,
+    /// Container and codec disagree on the audio format
+    Mismatch,
+    /// Text that cannot be decoded with its declared encoding
+    Encoding,
+    /// Oversize attached pictures
+    Artwork,
+    /// Invalid values, links, chapters and times in the metadata
+    Metadata,
+}
+
+impl IssueCategory {
+    const ALL: [IssueCategory; 5] = [IssueCategory::Corruption, IssueCategory::Mismatch, IssueCategory::Encoding, IssueCategory::Artwork, IssueCategory::Metadata];
+}
+
+impl fmt::Display for IssueCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            | IssueCategory::Corruption => "corruption",
+            | IssueCategory::Mismatch => "mismatch",
+            | IssueCategory::Encoding => "encoding",
+            | IssueCategory::Artwork => "artwork",
+            | IssueCategory::Metadata => "metadata",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Most points each category can take off the score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthWeights {
+    pub corruption: u32,
+    pub mismatch: u32,
+    pub encoding: u32,
+    pub artwork: u32,
+    pub metadata: u32,
+}
+
+impl Default for HealthWeights {
+    fn default() -> Self {
+        HealthWeights { corruption: 40, mismatch: 25, encoding: 15, artwork: 10, metadata: 10 }
+    }
+}
+
+impl HealthWeights {
+    pub fn weight(&self, category: IssueCategory) -> u32 {
+        match category {
+            | IssueCategory::Corruption => self.corruption,
+            | IssueCategory::Mismatch => self.mismatch,
+            | IssueCategory::Encoding => self.encoding,
+            | IssueCategory::Artwork => self.artwork,
+            | IssueCategory::Metadata => self.metadata,
+        }
+    }
+
+    /// Weights with the given categories changed
+    pub fn with(mut self, weights: &[CategoryWeight]) -> Self {
+        for &CategoryWeight(category, weight) in weights {
+            let slot = match category {
+                | IssueCategory::Corruption => &mut self.corruption,
+                | IssueCategory::Mismatch => &mut self.mismatch,
+                | IssueCategory::Encoding => &mut self.encoding,
+                | IssueCategory::Artwork => &mut self.artwork,
+                | IssueCategory::Metadata => &mut self.metadata,
+            };
+            *slot = weight;
+        }
+        self
+    }
+}
+
+/// Weight of one category as given on the command line ("corruption=60")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CategoryWeight(pub IssueCategory, pub u32);
+
+impl FromStr for CategoryWeight {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, weight) = value.split_once('=').ok_or_else(|| format!("expected CATEGORY=WEIGHT, got '{}'", value))?;
+        let category = IssueCategory::from_str(name.trim(), true).map_err(|_| {
+            let names: Vec<String> = IssueCategory::ALL.iter().map(IssueCategory::to_string).collect();
+            format!("unknown category '{}' (expected one of: {})", name, names.join(", "))
+        })?;
+        let weight = weight.trim().parse().map_err(|_| format!("weight must be a whole number of points, got '{}'", weight))?;
+        Ok(CategoryWeight(category, weight))
+    }
+}
+
+/// A finding with the category it is weighted in
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthIssue {
+    pub category: IssueCategory,
+    pub finding: Finding,
+}
+
+/// Score of a file with the problems that lowered it
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthScore {
+    /// 0 (badly broken) to 100 (nothing found)
+    pub score: u32,
+    /// Points taken off per category, in category order
+    pub deductions: Vec<(IssueCategory, u32)>,
+    pub issues: Vec<HealthIssue>,
+}
+
+/// Score a summary (from `analyze_file`) with the given weights
+pub fn health_score(summary: &MediaSummary, weights: &HealthWeights) -> HealthScore {
+    let issues = collect_issues(summary);
+    let mut deductions = Vec::new();
+    for category in IssueCategory::ALL {
+        let weight = weights.weight(category);
+        let points: u32 = issues
+            .iter()
+            .filter(|issue| issue.category == category)
+            .map(|issue| match issue.finding.severity {
+                | Severity::Error => weight,
+                | Severity::Warning => weight.div_ceil(2),
+                | Severity::Info => 0,
+            })
+            .sum();
+        if points > 0 {
+            deductions.push((category, points.min(weight)));
+        }
+    }
+    let score = 100u32.saturating_sub(deductions.iter().map(|(_, points)| points).sum());
+    HealthScore { score, deductions, issues }
+}
+
+/// All problems found in a summary with the category they are weighted in
+pub fn collect_issues(summary: &MediaSummary) -> Vec<HealthIssue> {
+    let mut issues = Vec::new();
+    let mut add = |category: IssueCategory, finding: Finding| issues.push(HealthIssue { category, finding });
+
+    if let Some(coverage) = &summary.coverage {
+        for range in &coverage.skipped {
+            let location = format!("0x{:08X}", range.offset);
+            let message = format!("{} bytes skipped: {}", range.size, range.reason);
+            if let Some(frame_id) = range.reason.strip_prefix("unparsed frame ") {
+                // Text frames mostly fail because their text does not decode with the declared encoding
+                let category = if is_text_frame(frame_id) {
+                    IssueCategory::Encoding
+                } else {
+                    IssueCategory::Corruption
+                };
+                add(category, Finding::new(Severity::Error, frame_id, message));
+                continue;
+            }
+            let severity = match range.reason.as_str() {
+                | "no MPEG audio frames found" => Severity::Error,
+                | "not accounted for by any structure" | "data before the first MPEG audio frame" | "junk between MPEG audio frames (resync)" | "data after the last MPEG audio frame" | UNPARSED_TAG_DATA | OUTSIDE_PAGES => {
+                    Severity::Warning
+                }
+                // Padding, free space and structures that are not dissected are not problems
+                | reason if reason.contains("padding") || reason.contains("free space") || reason.ends_with("not dissected") => continue,
+                // ZIP members whose local headers could not be read
+                | _ => Severity::Error,
+            };
+            add(IssueCategory::Corruption, Finding::new(severity, &location, message));
+        }
+    }
+    if let Some(riff) = &summary.riff {
+        for problem in &riff.problems {
+            add(IssueCategory::Corruption, Finding::new(Severity::Error, &riff.form, problem.as_str()));
+        }
+    }
+
+    if let Some(ogg) = &summary.ogg {
+        for problem in &ogg.problems {
+            add(IssueCategory::Corruption, Finding::new(Severity::Error, "Ogg", problem.as_str()));
+        }
+        for comment in ogg.streams.iter().filter_map(|stream| stream.comment.as_ref()) {
+            for problem in &comment.problems {
+                add(IssueCategory::Metadata, Finding::new(Severity::Warning, "Vorbis comment", problem.as_str()));
+            }
+            for (i, picture) in comment.pictures.iter().enumerate() {
+                if picture.size > OVERSIZE_ART_BYTES as usize {
+                    add(IssueCategory::Artwork, oversize_art(&format!("PICTURE[{}]", i), picture.size as u64));
+                }
+            }
+            for finding in comment.picture_findings() {
+                add(IssueCategory::Artwork, finding);
+            }
+        }
+    }
+
+    for finding in &summary.format_findings {
+        add(IssueCategory::Mismatch, finding.clone());
+    }
+
+    if let Some(tag) = &summary.tag {
+        collect_frame_issues(&tag.frames, "", &mut add);
+        for chapter in &tag.chapters {
+            if let Some(image) = chapter.image.as_ref().filter(|image| image.size > OVERSIZE_ART_BYTES as usize) {
+                add(IssueCategory::Artwork, oversize_art(&format!("CHAP {}", chapter.element_id), image.size as u64));
+            }
+        }
+        for finding in &tag.findings {
+            // Pictures that players cannot show are artwork problems
+            let category = if finding.location.ends_with("APIC") {
+                IssueCategory::Artwork
+            } else {
+                IssueCategory::Metadata
+            };
+            add(category, finding.clone());
+        }
+        for field in &tag.known_fields {
+            if let Some(problem) = &field.problem {
+                add(IssueCategory::Metadata, Finding::new(Severity::Warning, &field.description, problem.as_str()));
+            }
+        }
+    }
+    for (i, image) in summary.cover_art.iter().enumerate() {
+        for finding in image.findings(&format!("covr[{}]", i)) {
+            add(IssueCategory::Artwork, finding);
+        }
+    }
+    for code in &summary.release_codes {
+        if let Some(problem) = &code.problem {
+            add(IssueCategory::Metadata, Finding::new(Severity::Warning, &format!("----:{}", code.description), problem.as_str()));
+        }
+    }
+    if let Some(podcast) = &summary.podcast {
+        for finding in &podcast.findings {
+            add(IssueCategory::Metadata, finding.clone());
+        }
+    }
+    if let Some(disc) = &summary.disc {
+        for finding in &disc.findings {
+            add(IssueCategory::Metadata, finding.clone());
+        }
+    }
+    if let Some(dj) = &summary.dj {
+        for finding in &dj.findings {
+            add(IssueCategory::Metadata, finding.clone());
+        }
+    }
+    let movie_times = summary.movie.iter().flat_map(|movie| [("mvhd creation time", &movie.creation_time), ("mvhd modification time", &movie.modification_time)]);
+    let track_times = summary.tracks.iter().flat_map(|track| [("tkhd creation time", &track.creation_time), ("tkhd modification time", &track.modification_time)]);
+    for (location, time) in movie_times.chain(track_times) {
+        if let Some(problem) = &time.problem {
+            // Many muxers never set the times, which does not bother players
+            let severity = if time.raw == 0 {
+                Severity::Info
+            } else {
+                Severity::Warning
+            };
+            add(IssueCategory::Metadata, Finding::new(severity, location, problem.as_str()));
+        }
+    }
+    issues
+}
+
+/// Oversize pictures and undecodable text of the frames (sub-frames of CHAP/CTOC as "CHAP/APIC")
+fn collect_frame_issues(frames: &[FrameSummary], prefix: &str, add: &mut impl FnMut(IssueCategory, Finding)) {
+    for frame in frames {
+        let location = format!("{}{}", prefix, frame.id);
+        // Chapter images are reported with their chapter
+        if frame.id == "APIC" && prefix.is_empty() && frame.size > OVERSIZE_ART_BYTES {
+            add(IssueCategory::Artwork, oversize_art(&location, frame.size as u64));
+        }
+        if frame.values.iter().chain(frame.value.iter()).any(|value| value.contains('\u{FFFD}')) {
+            add(IssueCategory::Encoding, Finding::new(Severity::Warning, &location, "text contains characters that could not be decoded"));
+        }
+        collect_frame_issues(&frame.sub_frames, &format!("{}/", location), add);
+    }
+}
+
+fn oversize_art(location: &str, size: u64) -> Finding {
+    Finding::new(Severity::Warning, location, format!("picture of {} KiB exceeds {} KiB", size / 1024, OVERSIZE_ART_BYTES / 1024))
+}
+
+/// Frames whose content is text in a declared encoding
+fn is_text_frame(frame_id: &str) -> bool {
+    frame_id.starts_with('T') || matches!(frame_id, "COMM" | "USLT" | "SYLT" | "WXXX" | "USER" | "OWNE" | "COMR")
+}
+
+/// Score every media file of a file or directory and list them worst first
+///
+/// With `details` the problems behind each score are listed below the file; files without
+/// problems are left out of the list unless `all` is set.
+pub fn triage(path: &Path, weights: &HealthWeights, details: bool, all: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut errors = BatchErrors::default();
+    let files = if path.is_dir() {
+        collect_files(path, &mut errors)?
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut scored: Vec<(PathBuf, HealthScore)> = Vec::new();
+    let mut skipped = 0;
+    let total = files.len();
+    for (index, file_path) in files.into_iter().enumerate() {
+        let progress = FileProgress::start(&file_path, index + 1, total);
+        let result = analyze_file(&file_path);
+        if let Ok(summary) = &result {
+            progress.summary(summary);
+        }
+        progress.done(&result);
+        match result {
+            | Ok(summary) if summary.format == "Unknown" => skipped += 1,
+            | Ok(summary) => scored.push((file_path, health_score(&summary, weights))),
+            | Err(e) if errors.record(&file_path, &*e) => {}
+            | Err(e) => {
+                outln!("{}", format!("Skipping {}: {}", display_path(&file_path), e).bright_red());
+                skipped += 1;
+            }
+        }
+    }
+    // Worst first; equal scores in path order
+    scored.sort_by(|(a_path, a), (b_path, b)| a.score.cmp(&b.score).then_with(|| a_path.cmp(b_path)));
+
+    outln!("Scored {} media file(s) in {} ({} other file(s) skipped)", scored.len(), display_path(path), skipped);
+    let healthy = scored.iter().filter(|(_, health)| health.score == 100).count();
+    for (file_path, health) in &scored {
+        if health.score == 100 && !all {
+            continue;
+        }
+        let deductions: Vec<String> = health.deductions.iter().map(|(category, points)| format!("{} -{}", category, points)).collect();
+        let score = format!("{:>3}", health.score);
+        let score = match health.score {
+            | 0..50 => score.bright_red().to_string(),
+            | 50..100 => score.bright_yellow().to_string(),
+            | _ => score.bright_green().to_string(),
+        };
+        if deductions.is_empty() {
+            outln!("{}  {}", score, display_path(file_path));
+        } else {
+            outln!("{}  {}  ({})", score, display_path(file_path), deductions.join(", "));
+        }
+        if details {
+            for issue in health.issues.iter().filter(|issue| issue.finding.severity > Severity::Info) {
+                outln!("       {}: {}", issue.category, issue.finding);
+            }
+        }
+    }
+    if healthy > 0 && !all {
+        outln!("{} file(s) without problems not listed (use --all)", healthy);
+    }
+    errors.print_summary("");
+    errors.finish()
+}
@@ -0,0 +1,125 @@
+/// ICY (SHOUTcast/Icecast) metadata of captured streams (`icy` command)
+///
+/// A server that was asked for metadata (`Icy-MetaData: 1`) inserts a metadata block after
+/// every `icy-metaint` bytes of audio: a length byte counting 16-byte units, then the text
+/// (`StreamTitle='...';StreamUrl='...';`) padded with zero bytes. Most blocks are empty or
+/// repeat the current title; the report lists where the title changes, with the time estimated
+/// from the bitrate of the audio.
+use crate::mpeg_audio::find_first_frame;
+use crate::path_display::display_path;
+use crate::stream_tags::{icy_fields, icy_text};
+use crate::time_format::format_duration;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::Path;
+
+/// Audio bytes searched for an MPEG frame header to take the bitrate from
+const BITRATE_PROBE_SIZE: usize = 64 * 1024;
+
+/// Non-empty metadata block of a stream
+struct MetadataBlock {
+    /// File offset of the length byte
+    offset: u64,
+    /// Audio bytes before the block
+    audio_position: u64,
+    text: String,
+}
+
+/// List the metadata blocks of a stream dump captured with the given `icy-metaint`
+///
+/// `bitrate` (kbit/s) overrides the bitrate of the first MPEG audio frame for the time estimates.
+pub fn analyze_icy(path: &Path, metaint: u64, bitrate: Option<u32>) -> Result<(), Box<dyn std::error::Error>> {
+    if metaint == 0 {
+        return Err("icy-metaint must be greater than 0".into());
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut audio_probe = Vec::new();
+    let mut blocks = Vec::new();
+    let mut empty_blocks = 0u64;
+    let mut offset = 0u64;
+    let mut audio_position = 0u64;
+    let mut problem = None;
+
+    loop {
+        // Audio up to the next metadata block
+        let mut audio = (&mut reader).take(metaint);
+        let read = if audio_probe.len() < BITRATE_PROBE_SIZE {
+            let mut chunk = Vec::new();
+            let read = audio.read_to_end(&mut chunk)? as u64;
+            audio_probe.extend(chunk.into_iter().take(BITRATE_PROBE_SIZE));
+            read
+        } else {
+            std::io::copy(&mut audio, &mut std::io::sink())?
+        };
+        offset += read;
+        audio_position += read;
+        if read < metaint {
+            break;
+        }
+
+        let mut length = [0u8; 1];
+        match reader.read_exact(&mut length) {
+            | Ok(()) => {}
+            | Err(error) if error.kind() == ErrorKind::UnexpectedEof => break,
+            | Err(error) => return Err(error.into()),
+        }
+        let block_offset = offset;
+        let mut block = vec![0u8; length[0] as usize * 16];
+        if let Err(error) = reader.read_exact(&mut block) {
+            if error.kind() == ErrorKind::UnexpectedEof {
+                problem = Some(format!("metadata block at 0x{:08X} is cut off by the end of the file", block_offset));
+                break;
+            }
+            return Err(error.into());
+        }
+        offset += 1 + block.len() as u64;
+
+        if block.is_empty() {
+            empty_blocks += 1;
+            continue;
+        }
+        let text_length = block.iter().position(|&byte| byte == 0).unwrap_or(block.len());
+        let text = icy_text(&block[..text_length]);
+        if !text.contains("='") {
+            problem = Some(format!("block at 0x{:08X} is not ICY metadata; check the icy-metaint (the stream is read up to here)", block_offset));
+            break;
+        }
+        blocks.push(MetadataBlock { offset: block_offset, audio_position, text });
+    }
+
+    let bitrate = bitrate.or_else(|| find_first_frame(&audio_probe).map(|(_, header)| header.bitrate as u32).filter(|&bitrate| bitrate > 0));
+
+    outln!("ICY metadata of {} (icy-metaint {}):", display_path(path), metaint);
+    outln!("  Audio: {} bytes", audio_position);
+    match bitrate {
+        | Some(bitrate) => outln!("  Times estimated from {} kbit/s", bitrate),
+        | None => outln!("  No MPEG audio frame found; times need --bitrate"),
+    }
+    outln!("  Metadata blocks: {} with text, {} empty", blocks.len(), empty_blocks);
+
+    let mut current: Option<&str> = None;
+    let mut changes = 0;
+    for block in &blocks {
+        if current == Some(block.text.as_str()) {
+            continue;
+        }
+        current = Some(&block.text);
+        changes += 1;
+        let time = bitrate.map_or(String::new(), |bitrate| format!(" {}", format_duration(block.audio_position * 8 / bitrate as u64)));
+        let fields = icy_fields(&block.text);
+        let description = match fields.iter().find(|(key, _)| key == "StreamTitle") {
+            | Some((_, title)) if title.is_empty() => "(empty StreamTitle)".to_string(),
+            | Some((_, title)) => title.clone(),
+            | None => block.text.clone(),
+        };
+        outln!("  0x{:08X}{}: {}", block.offset, time, description);
+        for (key, value) in fields.iter().filter(|(key, _)| key != "StreamTitle") {
+            outln!("    {}: {}", key, value);
+        }
+    }
+    outln!("  Title changes: {}", changes);
+    if let Some(problem) = problem {
+        outln!("  WARNING: {}", problem);
+    }
+    Ok(())
+}
@@ -0,0 +1,225 @@
+/// ID3v1 and ID3v1.1 tags at the end of MP3 files
+///
+/// The 128-byte block starts with "TAG" and holds title, artist and album (30 bytes each), the
+/// year (4), a comment (30) and a genre number. ID3v1.1 takes the last two comment bytes for a
+/// zero byte and the track number. An Enhanced ID3v1 block ("TAG+", 227 bytes) in front of it
+/// continues title, artist and album by 60 bytes each. The text is ISO-8859-1, or the code page
+/// chosen with `--assume-encoding`, padded with zero bytes or spaces.
+///
+/// Taggers that write both versions usually copy the ID3v2 values into the ID3v1 tag, cut to
+/// the field widths. When a file has both, every field is compared with its ID3v2 frame: cut
+/// values are noted, values that differ or exist in only one of the tags are reported.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_genre::{id3v1_genre_name, parse_genres};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_text_encoding::decode_legacy_string;
+use crate::media_source::MediaSource;
+use crate::text_truncate::truncate_text;
+use crate::trailer_tags::trailers;
+use serde::Serialize;
+use std::io::SeekFrom;
+
+/// Width of the title, artist, album and comment fields
+const FIELD_SIZE: usize = 30;
+
+/// Parsed ID3v1 tag, with its differences to the ID3v2 tag of the file
+#[derive(Debug, Clone, Serialize)]
+pub struct Id3v1Tag {
+    /// "ID3v1" or "ID3v1.1"
+    pub version: &'static str,
+    /// File offset of the "TAG" block
+    pub offset: u64,
+    /// Title, artist and album were continued by an Enhanced ID3v1 block ("TAG+")
+    pub enhanced: bool,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    /// Track number (ID3v1.1 only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<u8>,
+    /// Genre number, 255 for none
+    pub genre: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre_name: Option<&'static str>,
+    /// Fields that differ from the ID3v2 tag or exist in only one of the tags
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<Finding>,
+}
+
+impl Id3v1Tag {
+    /// Parse a 128-byte block starting with "TAG"
+    pub fn parse(block: &[u8], offset: u64) -> Option<Self> {
+        if block.len() != 128 || &block[0..3] != b"TAG" {
+            return None;
+        }
+        let comment = &block[97..127];
+        // ID3v1.1: a zero byte before a non-zero track number
+        let (version, comment, track) = if comment[28] == 0 && comment[29] != 0 {
+            ("ID3v1.1", &comment[..28], Some(comment[29]))
+        } else {
+            ("ID3v1", comment, None)
+        };
+        let genre = block[127];
+        Some(Id3v1Tag {
+            version,
+            offset,
+            enhanced: false,
+            title: field_text(&block[3..33]),
+            artist: field_text(&block[33..63]),
+            album: field_text(&block[63..93]),
+            year: field_text(&block[93..97]),
+            comment: field_text(comment),
+            track,
+            genre,
+            genre_name: id3v1_genre_name(genre as usize),
+            findings: Vec::new(),
+        })
+    }
+
+    /// Read the ID3v1 tag at the end of the file (with its Enhanced block, if any)
+    pub fn read(file: &mut dyn MediaSource) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let blocks = trailers(file)?;
+        let Some(position) = blocks.iter().rposition(|block| block.kind == "ID3v1") else {
+            return Ok(None);
+        };
+        let Some(mut tag) = Id3v1Tag::parse(&read_at(file, blocks[position].offset, 128)?, blocks[position].offset) else {
+            return Ok(None);
+        };
+        if let Some(enhanced) = position.checked_sub(1).map(|previous| &blocks[previous]).filter(|block| block.kind == "ID3v1 Enhanced") {
+            let block = read_at(file, enhanced.offset, enhanced.size as usize)?;
+            tag.enhanced = true;
+            tag.title = extend(&tag.title, &block[4..64]);
+            tag.artist = extend(&tag.artist, &block[64..124]);
+            tag.album = extend(&tag.album, &block[124..184]);
+        }
+        Ok(Some(tag))
+    }
+
+    /// Read the ID3v1 tag and compare it with the file's ID3v2 tag
+    pub fn read_compared(file: &mut dyn MediaSource, id3v2: Option<&Id3v2Tag>) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let mut tag = Id3v1Tag::read(file)?;
+        if let (Some(tag), Some(id3v2)) = (&mut tag, id3v2) {
+            tag.findings = tag.compare(&id3v2.frames);
+        }
+        Ok(tag)
+    }
+
+    /// Differences between the fields and their ID3v2 frames
+    pub fn compare(&self, frames: &[Id3v2Frame]) -> Vec<Finding> {
+        let text = |id: &str| frames.iter().find(|frame| frame.id == id).and_then(|frame| frame.get_text()).map(|text| text.trim_end_matches('\0').trim().to_string());
+        let comment = frames
+            .iter()
+            .filter(|frame| frame.id == "COMM")
+            .find_map(|frame| match &frame.content {
+                | Some(Id3v2FrameContent::Comment(comment)) if comment.description.is_empty() => Some(comment.text.trim_end_matches('\0').trim().to_string()),
+                | _ => None,
+            });
+        let year = text("TDRC").or_else(|| text("TYER")).map(|year| year.chars().take(4).collect::<String>());
+        let track = text("TRCK").map(|track| track.split('/').next().unwrap_or_default().trim().trim_start_matches('0').to_string());
+        let genre = frames
+            .iter()
+            .find(|frame| frame.id == "TCON")
+            .and_then(|frame| match &frame.content {
+                | Some(Id3v2FrameContent::Text(text)) => parse_genres(&text.strings).into_iter().next(),
+                | _ => None,
+            });
+
+        let title_width = if self.enhanced {
+            FIELD_SIZE + 60
+        } else {
+            FIELD_SIZE
+        };
+        let comment_width = if self.track.is_some() {
+            28
+        } else {
+            FIELD_SIZE
+        };
+        let mut findings = Vec::new();
+        compare_field(&mut findings, "title", &self.title, text("TIT2"), "TIT2", title_width);
+        compare_field(&mut findings, "artist", &self.artist, text("TPE1"), "TPE1", title_width);
+        compare_field(&mut findings, "album", &self.album, text("TALB"), "TALB", title_width);
+        compare_field(&mut findings, "year", &self.year, year, "TDRC/TYER", 4);
+        compare_field(&mut findings, "comment", &self.comment, comment, "COMM", comment_width);
+        compare_field(&mut findings, "track", &self.track.map(|track| track.to_string()).unwrap_or_default(), track, "TRCK", 3);
+        compare_field(&mut findings, "genre", self.genre_name.unwrap_or_default(), genre, "TCON", usize::MAX);
+        findings
+    }
+}
+
+/// Compare one ID3v1 field with the value of its ID3v2 frame
+fn compare_field(findings: &mut Vec<Finding>, field: &str, v1: &str, v2: Option<String>, frame: &str, width: usize) {
+    let location = format!("ID3v1 {}", field);
+    let v2 = v2.unwrap_or_default();
+    match (v1.is_empty(), v2.is_empty()) {
+        | (true, true) => {}
+        | (false, true) => findings.push(Finding::new(Severity::Warning, &location, format!("\"{}\" is only in the ID3v1 tag, ID3v2 has no {}", v1, frame))),
+        | (true, false) => findings.push(Finding::new(Severity::Info, &location, format!("empty, ID3v2 {} is \"{}\"", frame, v2))),
+        | (false, false) if v1 == v2 || (field == "genre" && v1.eq_ignore_ascii_case(&v2)) => {}
+        // Cut to the field width (bytes, one per character in ISO-8859-1)
+        | (false, false) if v1.chars().count() >= width.min(v2.chars().count()) && v2.starts_with(v1) => {
+            findings.push(Finding::new(Severity::Info, &location, format!("cut to {} characters from ID3v2 {} \"{}\"", v1.chars().count(), frame, v2)));
+        }
+        | (false, false) => findings.push(Finding::new(Severity::Warning, &location, format!("\"{}\" differs from ID3v2 {} \"{}\"", v1, frame, v2))),
+    }
+}
+
+/// Print the ID3v1 tag of the file and how it compares with the ID3v2 tag
+pub fn print_id3v1(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    let id3v2 = Id3v2Tag::read(file).ok().flatten();
+    let Some(tag) = Id3v1Tag::read_compared(file, id3v2.as_ref())? else {
+        return Ok(());
+    };
+    let enhanced = if tag.enhanced {
+        ", with Enhanced block"
+    } else {
+        ""
+    };
+    outln!("\n{} Tag (at 0x{:08X}{}):", tag.version, tag.offset, enhanced);
+    outln!("  Title: \"{}\"", truncate_text(&tag.title));
+    outln!("  Artist: \"{}\"", truncate_text(&tag.artist));
+    outln!("  Album: \"{}\"", truncate_text(&tag.album));
+    outln!("  Year: \"{}\"", tag.year);
+    outln!("  Comment: \"{}\"", truncate_text(&tag.comment));
+    if let Some(track) = tag.track {
+        outln!("  Track: {}", track);
+    }
+    match tag.genre_name {
+        | Some(name) => outln!("  Genre: {} ({})", tag.genre, name),
+        | None => outln!("  Genre: {} (none)", tag.genre),
+    }
+    if id3v2.is_some() {
+        if tag.findings.is_empty() {
+            outln!("  Consistent with the ID3v2 tag");
+        }
+        for finding in &tag.findings {
+            outln!("  {}", finding);
+        }
+    }
+    Ok(())
+}
+
+/// Text of a fixed-width field without the zero or space padding
+fn field_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    decode_legacy_string(&bytes[..end]).trim_end().to_string()
+}
+
+/// Field continued by its 60 bytes in the Enhanced block
+fn extend(value: &str, continuation: &[u8]) -> String {
+    let continuation = field_text(continuation);
+    if continuation.is_empty() {
+        value.to_string()
+    } else {
+        format!("{}{}", value, continuation)
+    }
+}
+
+fn read_at(file: &mut dyn MediaSource, offset: u64, length: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut buffer = vec![0u8; length];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
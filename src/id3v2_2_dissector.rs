@@ -0,0 +1,336 @@
+/// ID3v2.2 tags, as written by old iTunes versions
+///
+/// ID3v2.2 frames have 3-character IDs, 3-byte sizes and no flags. Each frame is read under the
+/// ID of its ID3v2.3 counterpart (TT2 as TIT2, PIC as APIC, ...), so the frame parsers, checks
+/// and summaries of the later versions apply unchanged. Frames without a counterpart keep their
+/// 3-character ID and raw data. ID3v2.2 tags are read only; the `tag` commands refuse them.
+use crate::checked_size::data_range;
+use crate::coverage::Coverage;
+use crate::disc_info::DiscInfo;
+use crate::dj_info::DjInfo;
+use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_tools::*;
+use crate::media_dissector::{Capabilities, MediaDissector, Operation, Signature};
+use crate::media_source::MediaSource;
+use crate::media_summary::{MediaSummary, TagSummary};
+use crate::options::DebugOptions;
+use crate::podcast_info::PodcastInfo;
+use crate::size_tree::SizeNode;
+use owo_colors::OwoColorize;
+
+/// ID3v2.2 frame IDs and their ID3v2.3 counterparts, including the non-standard iTunes frames
+///
+/// ID3v2.3 has no sort order frames; TSA, TSP and TST become the de-facto XSOA, XSOP and XSOT
+/// frames, which are parsed with `--lenient`.
+const FRAME_ID_MAP: &[(&str, &str)] = &[
+    ("BUF", "RBUF"),
+    ("CNT", "PCNT"),
+    ("COM", "COMM"),
+    ("CRA", "AENC"),
+    ("ETC", "ETCO"),
+    ("EQU", "EQUA"),
+    ("GEO", "GEOB"),
+    ("IPL", "IPLS"),
+    ("MCI", "MCDI"),
+    ("MLL", "MLLT"),
+    ("PCS", "PCST"),
+    ("PIC", "APIC"),
+    ("POP", "POPM"),
+    ("REV", "RVRB"),
+    ("RVA", "RVAD"),
+    ("SLT", "SYLT"),
+    ("STC", "SYTC"),
+    ("TAL", "TALB"),
+    ("TBP", "TBPM"),
+    ("TCM", "TCOM"),
+    ("TCO", "TCON"),
+    ("TCP", "TCMP"),
+    ("TCR", "TCOP"),
+    ("TCT", "TCAT"),
+    ("TDA", "TDAT"),
+    ("TDS", "TDES"),
+    ("TDY", "TDLY"),
+    ("TEN", "TENC"),
+    ("TFT", "TFLT"),
+    ("TID", "TGID"),
+    ("TIM", "TIME"),
+    ("TKE", "TKEY"),
+    ("TKW", "TKWD"),
+    ("TLA", "TLAN"),
+    ("TLE", "TLEN"),
+    ("TMT", "TMED"),
+    ("TOA", "TOPE"),
+    ("TOF", "TOFN"),
+    ("TOL", "TOLY"),
+    ("TOR", "TORY"),
+    ("TOT", "TOAL"),
+    ("TP1", "TPE1"),
+    ("TP2", "TPE2"),
+    ("TP3", "TPE3"),
+    ("TP4", "TPE4"),
+    ("TPA", "TPOS"),
+    ("TPB", "TPUB"),
+    ("TRC", "TSRC"),
+    ("TRD", "TRDA"),
+    ("TRK", "TRCK"),
+    ("TS2", "TSO2"),
+    ("TSA", "XSOA"),
+    ("TSC", "TSOC"),
+    ("TSI", "TSIZ"),
+    ("TSP", "XSOP"),
+    ("TSS", "TSSE"),
+    ("TST", "XSOT"),
+    ("TT1", "TIT1"),
+    ("TT2", "TIT2"),
+    ("TT3", "TIT3"),
+    ("TXT", "TEXT"),
+    ("TXX", "TXXX"),
+    ("TYE", "TYER"),
+    ("UFI", "UFID"),
+    ("ULT", "USLT"),
+    ("WAF", "WOAF"),
+    ("WAR", "WOAR"),
+    ("WAS", "WOAS"),
+    ("WCM", "WCOM"),
+    ("WCP", "WCOP"),
+    ("WFD", "WFED"),
+    ("WPB", "WPUB"),
+    ("WXX", "WXXX"),
+];
+
+/// ID3v2.2 dissector for MP3 files tagged by old iTunes versions
+pub struct Id3v22Dissector;
+
+/// ID3v2.3 counterpart of an ID3v2.2 frame ID
+pub fn v2_3_frame_id(id: &str) -> Option<&'static str> {
+    FRAME_ID_MAP.iter().find(|(v2_2, _)| *v2_2 == id).map(|(_, v2_3)| *v2_3)
+}
+
+/// Parse an ID3v2.2 frame from raw buffer data, under the ID of its ID3v2.3 counterpart
+pub fn parse_id3v2_2_frame(buffer: &[u8], pos: usize) -> Option<Id3v2Frame> {
+    if pos + 6 > buffer.len() {
+        return None;
+    }
+
+    let frame_id = &buffer[pos..pos + 3];
+    if !frame_id.iter().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+        return None;
+    }
+    let frame_id = String::from_utf8_lossy(frame_id).to_string();
+
+    // ID3v2.2 uses 3-byte big-endian sizes
+    let frame_size = u32::from_be_bytes([0, buffer[pos + 3], buffer[pos + 4], buffer[pos + 5]]);
+    if frame_size == 0 {
+        return None;
+    }
+    let range = data_range(format!("frame '{}'", frame_id), pos, 6, frame_size as u64, buffer.len()).ok()?;
+    let data = buffer[range].to_vec();
+
+    let Some(id) = v2_3_frame_id(&frame_id) else {
+        // No counterpart: keep the frame as raw data under its own ID
+        return Some(Id3v2Frame::new_with_offset(frame_id, frame_size, 0, pos, data));
+    };
+    let data = if id == "APIC" {
+        picture_to_apic(&data)?
+    } else {
+        data
+    };
+    let mut frame = Id3v2Frame::new_with_offset(id.to_string(), frame_size, 0, pos, data);
+    let _ = frame.parse_content(3); // Ignore parsing errors, keep raw data
+    Some(frame)
+}
+
+/// APIC data for the data of a PIC frame, which names the image format with three characters instead of a MIME type
+fn picture_to_apic(data: &[u8]) -> Option<Vec<u8>> {
+    let image_format = String::from_utf8_lossy(data.get(1..4)?).to_ascii_lowercase();
+    let mime_type = match image_format.as_str() {
+        | "jpg" => "image/jpeg".to_string(),
+        | "-->" => "-->".to_string(),
+        | format => format!("image/{}", format.trim_end_matches(['\0', ' '])),
+    };
+    let mut apic = vec![data[0]];
+    apic.extend(mime_type.as_bytes());
+    apic.push(0);
+    apic.extend(&data[4..]);
+    Some(apic)
+}
+
+impl MediaDissector for Id3v22Dissector {
+    fn media_type(&self) -> &'static str {
+        "ID3v2.2"
+    }
+
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+        dissect_id3v2_2_file_with_options(file, options)
+    }
+
+    fn can_handle(&self, header: &[u8]) -> bool {
+        matches!(detect_id3v2_version(header), Some((2, _)))
+    }
+
+    fn name(&self) -> &'static str {
+        "ID3v2.2 Dissector"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            extensions: &["mp3"],
+            signatures: &[Signature { offset: 0, magic: b"ID3\x02", description: "ID3v2.2 tag" }],
+            operations: &[Operation::Read, Operation::Validate],
+        }
+    }
+
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        crate::coverage::mp3_coverage(file)
+    }
+
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        crate::size_tree::mp3_size_tree(file)
+    }
+
+    fn summarize(&self, file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let mut summary = MediaSummary::new(self.media_type(), self.name());
+        let tag = Id3v2Tag::read(file)?;
+        summary.podcast = tag.as_ref().and_then(|tag| PodcastInfo::from_frames(&tag.frames));
+        summary.disc = tag.as_ref().and_then(|tag| DiscInfo::from_frames(&tag.frames));
+        summary.dj = tag.as_ref().and_then(|tag| DjInfo::from_frames(&tag.frames));
+        let duration_findings = match &tag {
+            | Some(tag) => crate::id3v2_plausibility::duration_findings(&tag.frames, file)?,
+            | None => Vec::new(),
+        };
+        summary.id3v1 = crate::id3v1_tag::Id3v1Tag::read_compared(file, tag.as_ref())?;
+        summary.tag = tag.map(|tag| TagSummary::from(&tag));
+        if let Some(tag) = &mut summary.tag {
+            tag.findings.extend(crate::tag_boundary::check_tag_boundary(file)?);
+            tag.findings.extend(duration_findings);
+        }
+        summary.trailers = crate::trailer_tags::trailers(file)?;
+        summary.inline_tags = crate::stream_tags::inline_tags(file)?;
+        Ok(summary)
+    }
+}
+
+/// Dissect an ID3v2.2 file from the beginning with specific options
+pub fn dissect_id3v2_2_file_with_options(file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((major, minor, flags, size)) = read_id3v2_header(file, options.show_header)? else {
+        if options.show_header {
+            outln!("No ID3v2 header found");
+        }
+        return Ok(());
+    };
+    if major != 2 {
+        if options.show_header {
+            outln!("  Expected ID3v2.2, found version 2.{}", major);
+        }
+        return Ok(());
+    }
+
+    if options.show_header {
+        outln!("\nID3v2 Header Found:");
+        outln!("  Version: 2.{}.{}", major, minor);
+        outln!("  Flags: 0x{:02X}", flags);
+        if flags & 0x80 != 0 {
+            outln!("    Active: unsynchronisation");
+        }
+        outln!("  Tag Size: {} bytes", size);
+    }
+
+    // Bit 6 announced a compression scheme that was never defined
+    if flags & 0x40 != 0 {
+        outln!("  {}", "ERROR: compression flag set; ID3v2.2 defines no compression, the frames cannot be read".bright_red());
+    } else if size > 0 && options.show_frames {
+        dissect_id3v2_2_frames(file, size, flags, options)?;
+    }
+
+    crate::tag_boundary::print_tag_boundary(file)?;
+    crate::stream_tags::print_inline_tags(file)?;
+    crate::trailer_tags::print_trailers(file)?;
+    crate::id3v1_tag::print_id3v1(file)?;
+
+    Ok(())
+}
+
+fn dissect_id3v2_2_frames(file: &mut dyn MediaSource, tag_size: u32, flags: u8, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    outln!("\nDissecting ID3v2.2 tag (size: {} bytes, flags: 0x{:02X})...", tag_size, flags);
+
+    let mut buffer = vec![0u8; tag_size as usize];
+    if let Err(e) = file.read_exact(&mut buffer) {
+        outln!("{}", format!("ERROR: Failed to read tag data: {}", e).bright_red());
+        return Err(Box::new(e));
+    }
+    if flags & 0x80 != 0 {
+        outln!("  Unsynchronization detected - removing sync bytes");
+        buffer = remove_unsynchronization(&buffer);
+    }
+
+    outln!("\nID3v2.2 Frames:");
+    let mut pos = 0;
+    let mut frames = Vec::new();
+    while pos + 6 <= buffer.len() {
+        let frame_id_bytes = &buffer[pos..pos + 3];
+        if frame_id_bytes[0] == 0 {
+            outln!("  Reached padding or end of frames at position 0x{:08X}", pos);
+            break;
+        }
+        let frame_id = String::from_utf8_lossy(frame_id_bytes).to_string();
+        let frame_size = u32::from_be_bytes([0, buffer[pos + 3], buffer[pos + 4], buffer[pos + 5]]);
+        let range = match data_range(format!("frame '{}'", frame_id), pos, 6, frame_size as u64, buffer.len()) {
+            | Ok(range) => range,
+            | Err(error) => {
+                outln!("  Stopping: {}", error);
+                break;
+            }
+        };
+
+        if frame_size == 0 {
+            outln!("  Frame '{}' has zero size, skipping", frame_id);
+            pos += 6;
+            continue;
+        }
+
+        // A selection by --frame-id matches the ID3v2.2 ID or its ID3v2.3 counterpart
+        if !options.lists_id(&frame_id) && !v2_3_frame_id(&frame_id).is_some_and(|id| options.lists_id(id)) {
+            frames.extend(parse_id3v2_2_frame(&buffer, pos));
+            pos = range.end;
+            continue;
+        }
+
+        let counterpart = v2_3_frame_id(&frame_id).map_or(String::new(), |id| format!(" (read as {})", id));
+        outln!(
+            "    Frame offset 0x{:08X}, ID: \"{}\"{}, Size: [0x{:02X}, 0x{:02X}, 0x{:02X}] = {}",
+            pos,
+            frame_id,
+            counterpart,
+            buffer[pos + 3],
+            buffer[pos + 4],
+            buffer[pos + 5],
+            frame_size
+        );
+        match parse_id3v2_2_frame(&buffer, pos) {
+            | Some(frame) => {
+                match frame.language() {
+                    | Some(language) if !options.shows_frame(&frame) => outln!("    Language \"{}\" not selected (--lang)\n", language),
+                    | _ => out!("    {}", frame),
+                }
+                frames.push(frame);
+            }
+            | None => {
+                outln!("    {}", format!("ERROR: '{}' is not a valid ID3v2.2 frame", frame_id).bright_red());
+                outln!();
+            }
+        }
+
+        pos = range.end;
+    }
+
+    crate::id3v2_frame_order::print_order(&frames);
+    crate::metadata_fingerprint::print_fingerprint(&frames);
+    crate::id3v2_plausibility::print_plausibility(&frames, Some(file))?;
+    crate::podcast_info::print_podcast(PodcastInfo::from_frames(&frames).as_ref());
+    crate::disc_info::print_disc_info(DiscInfo::from_frames(&frames).as_ref());
+    crate::dj_info::print_dj_info(DjInfo::from_frames(&frames).as_ref());
+    crate::id3v2_languages::print_inventory(&frames, options.language.as_deref());
+
+    Ok(())
+}
@@ -1,10 +1,17 @@
-use crate::cli::DebugOptions;
+use crate::checked_size::data_range;
+use crate::coverage::Coverage;
+use crate::disc_info::DiscInfo;
+use crate::dj_info::DjInfo;
 use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_tag::Id3v2Tag;
 use crate::id3v2_tools::*;
-use crate::media_dissector::MediaDissector;
+use crate::media_dissector::{Capabilities, MediaDissector, Operation, Signature};
+use crate::media_source::MediaSource;
+use crate::media_summary::{MediaSummary, TagSummary};
+use crate::options::DebugOptions;
+use crate::podcast_info::PodcastInfo;
+use crate::size_tree::SizeNode;
 use owo_colors::OwoColorize;
-use std::fs::File;
-use std::io::Read;
 
 /// ID3v2.3 dissector for MP3 files
 pub struct Id3v23Dissector;
@@ -31,11 +38,11 @@ pub fn parse_id3v2_3_frame(buffer: &[u8], pos: usize) -> Option<Id3v2Frame> {
     let frame_size = u32::from_be_bytes([buffer[pos + 4], buffer[pos + 5], buffer[pos + 6], buffer[pos + 7]]);
     let frame_flags = u16::from_be_bytes([buffer[pos + 8], buffer[pos + 9]]);
 
-    if frame_size == 0 || frame_size > (buffer.len() - pos - 10) as u32 {
+    if frame_size == 0 {
         return None;
     }
-
-    let data = buffer[pos + 10..pos + 10 + frame_size as usize].to_vec();
+    let range = data_range(format!("frame '{}'", frame_id), pos, 10, frame_size as u64, buffer.len()).ok()?;
+    let data = buffer[range].to_vec();
 
     let mut frame = Id3v2Frame::new_with_offset(frame_id.clone(), frame_size, frame_flags, pos, data);
 
@@ -50,7 +57,7 @@ impl MediaDissector for Id3v23Dissector {
         "ID3v2.3"
     }
 
-    fn dissect_with_options(&self, file: &mut File, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
         dissect_id3v2_3_file_with_options(file, options)
     }
 
@@ -67,21 +74,61 @@ impl MediaDissector for Id3v23Dissector {
     fn name(&self) -> &'static str {
         "ID3v2.3 Dissector"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            extensions: &["mp3"],
+            signatures: &[
+                Signature { offset: 0, magic: b"ID3\x03", description: "ID3v2.3 tag" },
+                Signature { offset: 0, magic: &[0xFF], description: "MPEG audio frame sync, files without a tag" },
+            ],
+            operations: &[Operation::Read, Operation::Write, Operation::Validate],
+        }
+    }
+
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        crate::coverage::mp3_coverage(file)
+    }
+
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        crate::size_tree::mp3_size_tree(file)
+    }
+
+    fn summarize(&self, file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let mut summary = MediaSummary::new(self.media_type(), self.name());
+        let tag = Id3v2Tag::read(file)?;
+        summary.podcast = tag.as_ref().and_then(|tag| PodcastInfo::from_frames(&tag.frames));
+        summary.disc = tag.as_ref().and_then(|tag| DiscInfo::from_frames(&tag.frames));
+        summary.dj = tag.as_ref().and_then(|tag| DjInfo::from_frames(&tag.frames));
+        let duration_findings = match &tag {
+            | Some(tag) => crate::id3v2_plausibility::duration_findings(&tag.frames, file)?,
+            | None => Vec::new(),
+        };
+        summary.id3v1 = crate::id3v1_tag::Id3v1Tag::read_compared(file, tag.as_ref())?;
+        summary.tag = tag.map(|tag| TagSummary::from(&tag));
+        if let Some(tag) = &mut summary.tag {
+            tag.findings.extend(crate::tag_boundary::check_tag_boundary(file)?);
+            tag.findings.extend(duration_findings);
+        }
+        summary.trailers = crate::trailer_tags::trailers(file)?;
+        summary.inline_tags = crate::stream_tags::inline_tags(file)?;
+        Ok(summary)
+    }
 }
 
 /// Dissect an ID3v2.3 file from the beginning with specific options
-pub fn dissect_id3v2_3_file_with_options(file: &mut File, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+pub fn dissect_id3v2_3_file_with_options(file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
     // Read and parse ID3v2 header
-    if let Some((major, minor, flags, size)) = read_id3v2_header(file)? {
+    if let Some((major, minor, flags, size)) = read_id3v2_header(file, options.show_header)? {
         if major == 3 {
             if options.show_header {
-                println!("\nID3v2 Header Found:");
-                println!("  Version: 2.{}.{}", major, minor);
-                println!("  Flags: 0x{:02X}", flags);
+                outln!("\nID3v2 Header Found:");
+                outln!("  Version: 2.{}.{}", major, minor);
+                outln!("  Flags: 0x{:02X}", flags);
 
                 // Interpret header flags
                 if flags != 0 {
-                    print!("    ");
+                    out!("    ");
                     let mut flag_parts = Vec::new();
                     if flags & 0x80 != 0 {
                         flag_parts.push("unsynchronisation");
@@ -93,18 +140,18 @@ pub fn dissect_id3v2_3_file_with_options(file: &mut File, options: &DebugOptions
                         flag_parts.push("experimental");
                     }
                     if !flag_parts.is_empty() {
-                        println!("Active: {}", flag_parts.join(", "));
+                        outln!("Active: {}", flag_parts.join(", "));
                     }
                 }
 
-                println!("  Tag Size: {} bytes", size);
+                outln!("  Tag Size: {} bytes", size);
 
                 if size > 100_000_000 {
-                    println!("  WARNING: Extremely large tag size (> 100MB), verify file integrity");
+                    outln!("  WARNING: Extremely large tag size (> 100MB), verify file integrity");
                 } else if size > 50_000_000 {
-                    println!("  WARNING: Tag size is very large (> 50MB), likely rich podcast with chapter images");
+                    outln!("  WARNING: Tag size is very large (> 50MB), likely rich podcast with chapter images");
                 } else if size > 10_000_000 {
-                    println!("  INFO: Large tag size (> 10MB), possibly podcast with embedded chapter content");
+                    outln!("  INFO: Large tag size (> 10MB), possibly podcast with embedded chapter content");
                 }
             }
 
@@ -112,21 +159,27 @@ pub fn dissect_id3v2_3_file_with_options(file: &mut File, options: &DebugOptions
                 // Allow very large tags for podcast content with chapter images
                 dissect_id3v2_3_with_options(file, size, flags, options)?;
             }
+
+            crate::tag_boundary::print_tag_boundary(file)?;
         } else {
             if options.show_header {
-                println!("  Expected ID3v2.3, found version 2.{}", major);
+                outln!("  Expected ID3v2.3, found version 2.{}", major);
             }
         }
     } else {
         if options.show_header {
-            println!("No ID3v2 header found");
+            outln!("No ID3v2 header found");
         }
     }
 
+    crate::stream_tags::print_inline_tags(file)?;
+    crate::trailer_tags::print_trailers(file)?;
+    crate::id3v1_tag::print_id3v1(file)?;
+
     Ok(())
 }
 
-pub fn dissect_id3v2_3_with_options(file: &mut File, tag_size: u32, flags: u8, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+pub fn dissect_id3v2_3_with_options(file: &mut dyn MediaSource, tag_size: u32, flags: u8, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
     if !options.show_frames {
         // If not showing frames, skip the tag data entirely
         let mut buffer = vec![0u8; tag_size as usize];
@@ -135,7 +188,7 @@ pub fn dissect_id3v2_3_with_options(file: &mut File, tag_size: u32, flags: u8, o
                 // Successfully skipped tag data
             }
             | Err(e) => {
-                println!("{}", format!("ERROR: Failed to skip tag data: {}", e).bright_red());
+                outln!("{}", format!("ERROR: Failed to skip tag data: {}", e).bright_red());
                 return Err(Box::new(e));
             }
         }
@@ -143,15 +196,15 @@ pub fn dissect_id3v2_3_with_options(file: &mut File, tag_size: u32, flags: u8, o
     }
 
     // Diagnostic output
-    println!("\nDissecting ID3v2.3 tag (size: {} bytes, flags: 0x{:02X})...", tag_size, flags);
+    outln!("\nDissecting ID3v2.3 tag (size: {} bytes, flags: 0x{:02X})...", tag_size, flags);
 
     let mut buffer = vec![0u8; tag_size as usize];
     match file.read_exact(&mut buffer) {
         | Ok(_) => {
-            println!("Successfully read {} bytes of tag data", tag_size);
+            outln!("Successfully read {} bytes of tag data", tag_size);
         }
         | Err(e) => {
-            println!("{}", format!("ERROR: Failed to read tag data: {}", e).bright_red());
+            outln!("{}", format!("ERROR: Failed to read tag data: {}", e).bright_red());
             return Err(Box::new(e));
         }
     }
@@ -159,70 +212,79 @@ pub fn dissect_id3v2_3_with_options(file: &mut File, tag_size: u32, flags: u8, o
     // Handle unsynchronization if flag is set
     let unsync_flag = flags & 0x80 != 0; // Bit 7
     if unsync_flag {
-        println!("  Unsynchronization detected - removing sync bytes");
+        outln!("  Unsynchronization detected - removing sync bytes");
         buffer = remove_unsynchronization(&buffer);
-        println!("  After unsynchronization removal: {} bytes", buffer.len());
+        outln!("  After unsynchronization removal: {} bytes", buffer.len());
     }
 
-    println!("\nID3v2.3 Frames:");
+    outln!("\nID3v2.3 Frames:");
 
     // Check for extended header
     let mut frame_start = 0;
     if flags & 0x40 != 0 {
         // Extended header flag
-        println!("Extended header flag set, parsing...");
+        outln!("Extended header flag set, parsing...");
 
         if buffer.len() >= 4 {
             // ID3v2.3 uses regular big-endian integer for extended header size
             let extended_size = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
-            frame_start = 4 + extended_size as usize;
-
-            println!("  Extended header size: {} bytes", extended_size);
-            println!("  Frame data starts at offset: {}", frame_start);
-
-            if frame_start > buffer.len() {
-                println!("  {}", format!("ERROR: Extended header size exceeds buffer length").bright_red());
-                return Err("Invalid extended header size".into());
+            outln!("  Extended header size: {} bytes", extended_size);
+            match data_range("extended header", 0, 4, extended_size as u64, buffer.len()) {
+                | Ok(range) => {
+                    frame_start = range.end;
+                    outln!("  Frame data starts at offset: {}", frame_start);
+                }
+                | Err(error) => {
+                    outln!("  {}", format!("ERROR: {}", error).bright_red());
+                    return Err(error.to_string().into());
+                }
             }
         } else {
-            println!("  {}", format!("ERROR: Buffer too small to read extended header size").bright_red());
+            outln!("  {}", "ERROR: Buffer too small to read extended header size".bright_red());
             return Err("Buffer too small for extended header".into());
         }
     }
 
     let mut pos = frame_start;
+    let mut frames = Vec::new();
 
     while pos + 10 <= buffer.len() {
         // ID3v2.3 frame header: 4 bytes ID + 4 bytes size + 2 bytes flags
         let frame_id_bytes = &buffer[pos..pos + 4];
-        let frame_id = std::str::from_utf8(frame_id_bytes).unwrap_or("????");
-
-        // Stop if we hit padding (null bytes)
-        if frame_id.starts_with('\0') || !frame_id.chars().all(|c| c.is_ascii_alphanumeric()) {
-            println!("  Reached padding or end of frames at position 0x{:08X}", pos);
-            break;
-        }
-
+        let frame_id = frame_id_to_string(frame_id_bytes);
+        let frame_id = frame_id.as_str();
         // ID3v2.3 uses regular big-endian integers (not synchsafe)
         let frame_size = u32::from_be_bytes([buffer[pos + 4], buffer[pos + 5], buffer[pos + 6], buffer[pos + 7]]);
         let frame_flags = u16::from_be_bytes([buffer[pos + 8], buffer[pos + 9]]);
+        let extent = data_range(format!("frame '{}'", frame_id), pos, 10, frame_size as u64, buffer.len());
+        let fits = frame_size > 0 && extent.is_ok();
+
+        // Stop if we hit padding (null bytes) or garbage; a non-alphanumeric ID whose size fits is a corrupt frame
+        if frame_id_bytes[0] == 0 || (!frame_id_bytes.iter().all(|c| c.is_ascii_alphanumeric()) && !fits) {
+            outln!("  Reached padding or end of frames at position 0x{:08X}", pos);
+            break;
+        }
 
         // Check if this is a valid ID3v2.3 frame ID
         if !is_valid_frame_for_version(frame_id, 3) {
             // Create a temporary frame for header display even though it's invalid
-            let temp_frame = crate::id3v2_frame::Id3v2Frame::new_with_offset(frame_id.to_string(), frame_size, frame_flags, pos, Vec::new());
+            let temp_frame = crate::id3v2_frame::Id3v2Frame::from_id_bytes(frame_id_bytes, frame_size, frame_flags, pos, Vec::new());
 
             // Use the unified frame header display function
-            crate::id3v2_tools::display_frame_header(&mut std::io::stdout(), &temp_frame, "    ")?;
+            crate::id3v2_tools::display_frame_header(&mut crate::output::writer(), &temp_frame, "    ")?;
 
-            println!("    {}", format!("ERROR: '{}' is not a valid ID3v2.3 frame ID (may be from ID3v2.4 or other version)", frame_id).bright_red());
-            println!();
+            if temp_frame.raw_id.is_some() {
+                outln!("    {}", format!("ERROR: frame ID {} is not ASCII (kept as raw bytes when the tag is rewritten)", frame_id).bright_red());
+            } else {
+                outln!("    {}", format!("ERROR: '{}' is not a valid ID3v2.3 frame ID (may be from ID3v2.4 or other version)", frame_id).bright_red());
+            }
+            outln!();
 
             // Skip the entire frame (header + data) instead of just 1 byte
-            if frame_size > 0 && frame_size <= (buffer.len() - pos - 10) as u32 {
-                pos += 10 + frame_size as usize;
+            if let (true, Ok(range)) = (fits, &extent) {
+                pos = range.end;
             } else {
-                println!("    {}", format!("ERROR: Invalid frame size {}, falling back to 1-byte skip", frame_size).bright_red());
+                outln!("    {}", format!("ERROR: Invalid frame size {}, falling back to 1-byte skip", frame_size).bright_red());
                 pos += 1;
             }
             continue;
@@ -230,14 +292,24 @@ pub fn dissect_id3v2_3_with_options(file: &mut File, tag_size: u32, flags: u8, o
 
         // Sanity check frame size
         if frame_size == 0 {
-            println!("  Frame '{}' has zero size, skipping", frame_id);
+            outln!("  Frame '{}' has zero size, skipping", frame_id);
             pos += 10;
             continue;
         }
 
-        if frame_size > (buffer.len() - pos - 10) as u32 {
-            println!("  Frame '{}' size ({} bytes) exceeds remaining buffer, stopping", frame_id, frame_size);
-            break;
+        let range = match extent {
+            | Ok(range) => range,
+            | Err(error) => {
+                outln!("  Stopping: {}", error);
+                break;
+            }
+        };
+
+        // Frames left out by --frame-id still count for the outline and the checks below
+        if !options.lists_id(frame_id) {
+            frames.extend(parse_id3v2_3_frame(&buffer, pos));
+            pos = range.end;
+            continue;
         }
 
         // Create a temporary frame for header display (before full parsing)
@@ -250,29 +322,47 @@ pub fn dissect_id3v2_3_with_options(file: &mut File, tag_size: u32, flags: u8, o
         );
 
         // Use the unified frame header display function
-        crate::id3v2_tools::display_frame_header(&mut std::io::stdout(), &temp_frame, "    ")?;
+        crate::id3v2_tools::display_frame_header(&mut crate::output::writer(), &temp_frame, "    ")?;
 
         // Parse the frame using the new typed system
         match parse_id3v2_3_frame(&buffer, pos) {
             | Some(frame) => {
-                print!("    {}", frame);
+                match frame.language() {
+                    | Some(language) if !options.shows_frame(&frame) => outln!("    Language \"{}\" not selected (--lang)\n", language),
+                    | _ => out!("    {}", frame),
+                }
+
+                if let Some(explain_id) = &options.explain_frame {
+                    let raw = &buffer[pos..range.end];
+                    crate::id3v2_frame_explainer::explain_matching_frames(&mut crate::output::writer(), raw, &frame, explain_id, 3, "    ")?;
+                }
+                frames.push(frame);
             }
             | None => {
-                println!("        WARNING: Failed to parse frame, showing raw info");
+                outln!("        WARNING: Failed to parse frame, showing raw info");
 
                 let preview_len = std::cmp::min(20, frame_size as usize);
-                let preview_data = &buffer[pos + 10..pos + 10 + preview_len];
-                print!("          Raw data preview: ");
+                let preview_data = &buffer[range.start..range.start + preview_len];
+                out!("          Raw data preview: ");
                 for byte in preview_data {
-                    print!("{:02X} ", byte);
+                    out!("{:02X} ", byte);
                 }
-                println!();
+                outln!();
             }
         }
 
         // Move to next frame
-        pos += 10 + frame_size as usize;
+        pos = range.end;
     }
 
+    crate::id3v2_chapter_tree::print_outline(&frames)?;
+    crate::id3v2_frame_order::print_order(&frames);
+    crate::metadata_fingerprint::print_fingerprint(&frames);
+    crate::id3v2_plausibility::print_plausibility(&frames, Some(file))?;
+    crate::podcast_info::print_podcast(PodcastInfo::from_frames(&frames).as_ref());
+    crate::disc_info::print_disc_info(DiscInfo::from_frames(&frames).as_ref());
+    crate::dj_info::print_dj_info(DjInfo::from_frames(&frames).as_ref());
+    crate::id3v2_languages::print_inventory(&frames, options.language.as_deref());
+
     Ok(())
 }
@@ -1,10 +1,17 @@
 /// Attached Picture Frame (APIC)
 ///
 /// Structure: Text encoding + MIME type + Picture type + Description + Picture data
-use crate::id3v2_text_encoding::{TextEncoding, decode_iso88591_string, decode_text_with_encoding_simple, get_terminator_length, is_null_terminator};
+use crate::error::SupertoolError;
+use crate::id3v2_text_encoding::{
+    TextEncoding, decode_iso88591_string, decode_text_with_encoding_simple, encode_text, get_terminator_length, is_null_terminator, terminator_bytes,
+};
+use crate::image_info::ImageInfo;
+use crate::text_truncate::truncate_text;
+use crate::xmp::{XmpPacket, jpeg_xmp};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttachedPictureFrame {
     pub encoding: TextEncoding,
     pub mime_type: String,
@@ -15,9 +22,9 @@ pub struct AttachedPictureFrame {
 
 impl AttachedPictureFrame {
     /// Parse an APIC frame from raw data
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
         if data.len() < 2 {
-            return Err("Picture frame data too short".to_string());
+            return Err(SupertoolError::TruncatedFrame("Picture frame data too short".to_string()));
         }
 
         let encoding = TextEncoding::from_byte(data[0])?;
@@ -29,14 +36,14 @@ impl AttachedPictureFrame {
             pos += 1;
         }
         if pos >= data.len() {
-            return Err("Picture frame MIME type not null-terminated".to_string());
+            return Err(SupertoolError::TruncatedFrame("Picture frame MIME type not null-terminated".to_string()));
         }
         let mime_type = decode_iso88591_string(&data[mime_start..pos]);
         pos += 1; // Skip null terminator
 
         // Picture type (1 byte)
         if pos >= data.len() {
-            return Err("Picture frame missing picture type".to_string());
+            return Err(SupertoolError::TruncatedFrame("Picture frame missing picture type".to_string()));
         }
         let picture_type = data[pos];
         pos += 1;
@@ -53,7 +60,7 @@ impl AttachedPictureFrame {
             pos += 1;
         }
         if pos + terminator_len > data.len() {
-            return Err("Picture frame description not properly terminated".to_string());
+            return Err(SupertoolError::TruncatedFrame("Picture frame description not properly terminated".to_string()));
         }
 
         let description = decode_text_with_encoding_simple(&data[desc_start..pos], encoding)?;
@@ -65,32 +72,59 @@ impl AttachedPictureFrame {
         Ok(AttachedPictureFrame { encoding, mime_type, picture_type, description, picture_data })
     }
 
+    /// Serialize the frame content (encoding, MIME type, picture type, description, picture data)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.encoding as u8];
+        bytes.extend(encode_text(&self.mime_type, TextEncoding::Iso88591));
+        bytes.push(0);
+        bytes.push(self.picture_type);
+        bytes.extend(encode_text(&self.description, self.encoding));
+        bytes.extend_from_slice(terminator_bytes(self.encoding));
+        bytes.extend_from_slice(&self.picture_data);
+        bytes
+    }
+
+    /// The XMP packet of a JPEG picture (APP1 segment), if it has one
+    pub fn xmp(&self) -> Option<Result<XmpPacket, String>> {
+        jpeg_xmp(&self.picture_data).map(|data| XmpPacket::parse("APIC frame (JPEG APP1)", data))
+    }
+
+    /// Format, dimensions, color space and ICC profile of a JPEG or PNG picture
+    pub fn image_info(&self) -> Option<ImageInfo> {
+        ImageInfo::inspect(&self.picture_data)
+    }
+
     /// Get picture type description
     pub fn picture_type_description(&self) -> &'static str {
-        match self.picture_type {
-            | 0x00 => "Other",
-            | 0x01 => "32x32 pixels 'file icon' (PNG only)",
-            | 0x02 => "Other file icon",
-            | 0x03 => "Cover (front)",
-            | 0x04 => "Cover (back)",
-            | 0x05 => "Leaflet page",
-            | 0x06 => "Media (e.g. label side of CD)",
-            | 0x07 => "Lead artist/lead performer/soloist",
-            | 0x08 => "Artist/performer",
-            | 0x09 => "Conductor",
-            | 0x0A => "Band/Orchestra",
-            | 0x0B => "Composer",
-            | 0x0C => "Lyricist/text writer",
-            | 0x0D => "Recording Location",
-            | 0x0E => "During recording",
-            | 0x0F => "During performance",
-            | 0x10 => "Movie/video screen capture",
-            | 0x11 => "A bright coloured fish",
-            | 0x12 => "Illustration",
-            | 0x13 => "Band/artist logotype",
-            | 0x14 => "Publisher/Studio logotype",
-            | _ => "Unknown",
-        }
+        picture_type_description(self.picture_type)
+    }
+}
+
+/// Description of an APIC picture type (also used by FLAC picture blocks)
+pub fn picture_type_description(picture_type: u8) -> &'static str {
+    match picture_type {
+        | 0x00 => "Other",
+        | 0x01 => "32x32 pixels 'file icon' (PNG only)",
+        | 0x02 => "Other file icon",
+        | 0x03 => "Cover (front)",
+        | 0x04 => "Cover (back)",
+        | 0x05 => "Leaflet page",
+        | 0x06 => "Media (e.g. label side of CD)",
+        | 0x07 => "Lead artist/lead performer/soloist",
+        | 0x08 => "Artist/performer",
+        | 0x09 => "Conductor",
+        | 0x0A => "Band/Orchestra",
+        | 0x0B => "Composer",
+        | 0x0C => "Lyricist/text writer",
+        | 0x0D => "Recording Location",
+        | 0x0E => "During recording",
+        | 0x0F => "During performance",
+        | 0x10 => "Movie/video screen capture",
+        | 0x11 => "A bright coloured fish",
+        | 0x12 => "Illustration",
+        | 0x13 => "Band/artist logotype",
+        | 0x14 => "Publisher/Studio logotype",
+        | _ => "Unknown",
     }
 }
 
@@ -100,9 +134,20 @@ impl fmt::Display for AttachedPictureFrame {
         writeln!(f, "MIME type: {}", self.mime_type)?;
         writeln!(f, "Picture type: {} ({})", self.picture_type, self.picture_type_description())?;
         if !self.description.is_empty() {
-            writeln!(f, "Description: \"{}\"", self.description)?;
+            writeln!(f, "Description: \"{}\"", truncate_text(&self.description))?;
         }
         writeln!(f, "Data size: {} bytes", self.picture_data.len())?;
+        if let Some(image) = self.image_info() {
+            writeln!(f, "Image: {}", image)?;
+            for finding in image.findings("APIC") {
+                writeln!(f, "{}: {}", finding.severity, finding.message)?;
+            }
+        }
+        match self.xmp() {
+            | Some(Ok(packet)) => write!(f, "{}", packet)?,
+            | Some(Err(error)) => writeln!(f, "WARNING: Invalid XMP packet: {}", error)?,
+            | None => {}
+        }
         Ok(())
     }
 }
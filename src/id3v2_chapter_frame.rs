@@ -1,24 +1,19 @@
-use crate::id3v2_frame::Id3v2Frame;
+use crate::error::SupertoolError;
+use crate::id3v2_attached_picture_frame::AttachedPictureFrame;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
 /// Chapter Frame (CHAP)
 ///
 /// Structure: Element ID + Start time + End time + Start offset + End offset + Sub-frames
 /// Part of ID3v2 Chapter Frame Addendum specification
 use crate::id3v2_text_encoding::decode_iso88591_string;
 use crate::id3v2_tools::get_frame_description;
+use crate::id3v2_user_url_frame::UserUrlFrame;
+use crate::text_truncate::truncate_text;
+use crate::time_format::format_duration;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-/// Format milliseconds as hh:mm:ss.ms
-fn format_timestamp(ms: u32) -> String {
-    let total_seconds = ms / 1000;
-    let milliseconds = ms % 1000;
-    let hours = total_seconds / 3600;
-    let minutes = (total_seconds % 3600) / 60;
-    let seconds = total_seconds % 60;
-
-    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, milliseconds)
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChapterFrame {
     /// Element ID (null-terminated)
     pub element_id: String,
@@ -35,10 +30,10 @@ pub struct ChapterFrame {
 }
 
 impl ChapterFrame {
-    /// Parse a CHAP frame from raw data
-    pub fn parse(data: &[u8], version_major: u8) -> Result<Self, String> {
+    /// Parse a CHAP frame from raw data; `depth` is its nesting level (0 at the top level of the tag)
+    pub fn parse(data: &[u8], version_major: u8, depth: usize) -> Result<Self, SupertoolError> {
         if data.is_empty() {
-            return Err("Chapter frame data is empty".to_string());
+            return Err(SupertoolError::TruncatedFrame("Chapter frame data is empty".to_string()));
         }
 
         let mut pos = 0;
@@ -49,42 +44,42 @@ impl ChapterFrame {
             pos += 1;
         }
         if pos >= data.len() {
-            return Err("Chapter frame element ID not null-terminated".to_string());
+            return Err(SupertoolError::TruncatedFrame("Chapter frame element ID not null-terminated".to_string()));
         }
         let element_id = decode_iso88591_string(&data[element_id_start..pos]);
         pos += 1; // Skip null terminator
 
         // Start time (4 bytes)
         if pos + 4 > data.len() {
-            return Err("Chapter frame missing start time".to_string());
+            return Err(SupertoolError::TruncatedFrame("Chapter frame missing start time".to_string()));
         }
         let start_time = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
         pos += 4;
 
         // End time (4 bytes)
         if pos + 4 > data.len() {
-            return Err("Chapter frame missing end time".to_string());
+            return Err(SupertoolError::TruncatedFrame("Chapter frame missing end time".to_string()));
         }
         let end_time = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
         pos += 4;
 
         // Start offset (4 bytes)
         if pos + 4 > data.len() {
-            return Err("Chapter frame missing start offset".to_string());
+            return Err(SupertoolError::TruncatedFrame("Chapter frame missing start offset".to_string()));
         }
         let start_offset = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
         pos += 4;
 
         // End offset (4 bytes)
         if pos + 4 > data.len() {
-            return Err("Chapter frame missing end offset".to_string());
+            return Err(SupertoolError::TruncatedFrame("Chapter frame missing end offset".to_string()));
         }
         let end_offset = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
         pos += 4;
 
         // Parse embedded sub-frames (rest of the data)
         let sub_frames = if pos < data.len() {
-            crate::id3v2_tools::parse_embedded_frames(&data[pos..], version_major)
+            crate::id3v2_tools::parse_embedded_frames(&data[pos..], version_major, depth + 1)
         } else {
             Vec::new()
         };
@@ -92,6 +87,20 @@ impl ChapterFrame {
         Ok(ChapterFrame { element_id, start_time, end_time, start_offset, end_offset, sub_frames })
     }
 
+    /// Serialize the frame content including its sub-frames for the given ID3v2 version
+    pub fn to_bytes(&self, version_major: u8) -> Vec<u8> {
+        let mut bytes = self.element_id.as_bytes().to_vec();
+        bytes.push(0);
+        bytes.extend_from_slice(&self.start_time.to_be_bytes());
+        bytes.extend_from_slice(&self.end_time.to_be_bytes());
+        bytes.extend_from_slice(&self.start_offset.to_be_bytes());
+        bytes.extend_from_slice(&self.end_offset.to_be_bytes());
+        for sub_frame in &self.sub_frames {
+            bytes.extend(sub_frame.to_bytes(version_major));
+        }
+        bytes
+    }
+
     /// Check if byte offsets are used (not 0xFFFFFFFF)
     pub fn has_byte_offsets(&self) -> bool {
         self.start_offset != 0xFFFFFFFF && self.end_offset != 0xFFFFFFFF
@@ -99,24 +108,62 @@ impl ChapterFrame {
 
     /// Get chapter duration in milliseconds
     pub fn duration(&self) -> u32 {
-        if self.end_time >= self.start_time {
-            self.end_time - self.start_time
-        } else {
-            0
-        }
+        self.end_time.saturating_sub(self.start_time)
+    }
+
+    /// Chapter title (TIT2 sub-frame, as recommended by the Chapter Frame Addendum)
+    pub fn title(&self) -> Option<&str> {
+        self.sub_frame_text("TIT2")
+    }
+
+    /// Chapter subtitle or description (TIT3 sub-frame)
+    pub fn subtitle(&self) -> Option<&str> {
+        self.sub_frame_text("TIT3")
+    }
+
+    /// Link associated with the chapter (WXXX sub-frame)
+    pub fn link(&self) -> Option<&UserUrlFrame> {
+        self.sub_frames.iter().find_map(|frame| match &frame.content {
+            | Some(Id3v2FrameContent::UserUrl(user_url)) => Some(user_url),
+            | _ => None,
+        })
+    }
+
+    /// Image shown during the chapter (APIC sub-frame)
+    pub fn image(&self) -> Option<&AttachedPictureFrame> {
+        self.sub_frames.iter().find_map(|frame| match &frame.content {
+            | Some(Id3v2FrameContent::Picture(picture)) => Some(picture),
+            | _ => None,
+        })
+    }
+
+    fn sub_frame_text(&self, id: &str) -> Option<&str> {
+        self.sub_frames.iter().find(|frame| frame.id == id).and_then(|frame| frame.get_text())
     }
 }
 
 impl fmt::Display for ChapterFrame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Element ID: \"{}\"", self.element_id)?;
-        let start_formatted = format_timestamp(self.start_time);
-        let end_formatted = format_timestamp(self.end_time);
-        let duration_formatted = format_timestamp(self.duration());
+        let start_formatted = format_duration(self.start_time as u64);
+        let end_formatted = format_duration(self.end_time as u64);
+        let duration_formatted = format_duration(self.duration() as u64);
         writeln!(f, "Time: {} - {} (duration: {})", start_formatted, end_formatted, duration_formatted)?;
         if self.has_byte_offsets() {
             writeln!(f, "Byte offsets: {} - {}", self.start_offset, self.end_offset)?;
         }
+        if let Some(title) = self.title() {
+            writeln!(f, "Title: \"{}\"", truncate_text(title))?;
+        }
+        if let Some(subtitle) = self.subtitle() {
+            writeln!(f, "Subtitle: \"{}\"", truncate_text(subtitle))?;
+        }
+        if let Some(link) = self.link() {
+            writeln!(f, "Link: \"{}\"", link.url)?;
+        }
+        if let Some(image) = self.image() {
+            writeln!(f, "Image: {} ({}, {} bytes)", image.mime_type, image.picture_type_description(), image.picture_data.len())?;
+        }
         if !self.sub_frames.is_empty() {
             writeln!(f, "Sub-frames: {} embedded frame(s)", self.sub_frames.len())?;
             writeln!(f)?; // Add newline before first embedded frame
@@ -137,7 +184,7 @@ impl fmt::Display for ChapterFrame {
 pub fn display_embedded_frame_content(f: &mut fmt::Formatter<'_>, frame: &Id3v2Frame) -> fmt::Result {
     // Use the new unified frame header display function
     let mut buffer = Vec::new();
-    if let Err(_) = crate::id3v2_tools::display_frame_header(&mut buffer, frame, "        ") {
+    if crate::id3v2_tools::display_frame_header(&mut buffer, frame, "        ").is_err() {
         // Fallback to basic display if header function fails
         writeln!(f, "        Frame: {} - Size: {} bytes", frame.id, frame.size)?;
     } else {
@@ -164,7 +211,7 @@ pub fn display_embedded_frame_content(f: &mut fmt::Formatter<'_>, frame: &Id3v2F
         // Fallback for unparsed frames
         if let Some(text) = frame.get_text() {
             if !text.is_empty() {
-                writeln!(f, "            Text: \"{}\"", text)?;
+                writeln!(f, "            Text: \"{}\"", truncate_text(text))?;
             }
         } else if let Some(url) = frame.get_url() {
             writeln!(f, "            URL: \"{}\"", url)?;
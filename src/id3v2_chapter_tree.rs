@@ -0,0 +1,219 @@
+/// Table of contents hierarchy (CTOC/CHAP tree)
+///
+/// CTOC frames reference chapters and other CTOC frames by element ID. This module resolves
+/// those references into a tree starting at the top-level CTOC, renders it as an indented
+/// outline and reports structural problems (no or several top-level CTOCs, dangling
+/// references, cycles, chapters no CTOC refers to, CTOCs nested beyond `--max-frame-depth`).
+use crate::finding::{Finding, Severity};
+use crate::id3v2_chapter_frame::ChapterFrame;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_table_of_contents_frame::TableOfContentsFrame;
+use crate::text_truncate::truncate_text;
+use crate::time_format::format_duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+
+/// Node of the table of contents tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TocEntry {
+    /// Table of contents (CTOC) with its resolved children
+    Toc {
+        element_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        ordered: bool,
+        children: Vec<TocEntry>,
+    },
+    /// Chapter (CHAP)
+    Chapter {
+        element_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        start_ms: u32,
+        end_ms: u32,
+    },
+    /// Child element ID without a matching CHAP or CTOC frame
+    Missing { element_id: String },
+}
+
+/// Resolved table of contents of a tag
+#[derive(Debug, Clone, Default)]
+pub struct ChapterTree {
+    /// Root entries: the top-level CTOC (several if the tag is broken), or the bare chapters
+    /// if the tag has no CTOC at all
+    pub roots: Vec<TocEntry>,
+    /// Structural problems found while resolving the tree
+    pub findings: Vec<Finding>,
+}
+
+/// Resolve the CTOC/CHAP frames of a tag into a tree
+pub fn build_chapter_tree(frames: &[Id3v2Frame]) -> ChapterTree {
+    let mut tocs = Vec::new();
+    let mut chapters = Vec::new();
+    for frame in frames {
+        match &frame.content {
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => tocs.push(toc),
+            | Some(Id3v2FrameContent::Chapter(chapter)) => chapters.push(chapter),
+            | _ => {}
+        }
+    }
+
+    let mut tree = ChapterTree::default();
+    if tocs.is_empty() {
+        if !chapters.is_empty() {
+            tree.findings.push(Finding::new(Severity::Warning, "CTOC", "chapters exist but there is no table of contents (CTOC)"));
+            tree.roots = chapters.iter().map(|chapter| chapter_entry(chapter)).collect();
+        }
+        return tree;
+    }
+
+    let mut seen_ids = HashSet::new();
+    for element_id in tocs.iter().map(|toc| &toc.element_id).chain(chapters.iter().map(|chapter| &chapter.element_id)) {
+        if !seen_ids.insert(element_id.as_str()) {
+            tree.findings.push(Finding::new(Severity::Error, "CTOC", format!("element ID '{}' is used by more than one CHAP/CTOC frame", element_id)));
+        }
+    }
+
+    let top_level: Vec<&TableOfContentsFrame> = tocs.iter().copied().filter(|toc| toc.top_level).collect();
+    let roots = match top_level.len() {
+        | 1 => top_level,
+        | 0 => {
+            tree.findings.push(Finding::new(Severity::Error, "CTOC", "no CTOC frame has the top-level flag set"));
+            // Fall back to the CTOCs no other CTOC refers to
+            let referenced: HashSet<&str> = tocs.iter().flat_map(|toc| toc.child_element_ids.iter().map(String::as_str)).collect();
+            tocs.iter().copied().filter(|toc| !referenced.contains(toc.element_id.as_str())).collect()
+        }
+        | count => {
+            tree.findings.push(Finding::new(Severity::Error, "CTOC", format!("{} CTOC frames have the top-level flag set (expected exactly one)", count)));
+            top_level
+        }
+    };
+
+    let mut resolver = Resolver { tocs: &tocs, chapters: &chapters, reached: HashSet::new(), depth_limited: false, findings: Vec::new() };
+    tree.roots = roots.iter().map(|toc| resolver.toc_entry(toc, &mut Vec::new())).collect();
+
+    // Elements below the depth limit were never visited, so reachability is unknown
+    if resolver.depth_limited {
+        tree.findings.extend(resolver.findings);
+        return tree;
+    }
+    for chapter in &chapters {
+        if !resolver.reached.contains(chapter.element_id.as_str()) {
+            resolver.findings.push(Finding::new(Severity::Warning, "CHAP", format!("chapter '{}' is not referenced by the table of contents", chapter.element_id)));
+        }
+    }
+    for toc in &tocs {
+        if !resolver.reached.contains(toc.element_id.as_str()) {
+            resolver.findings.push(Finding::new(Severity::Warning, "CTOC", format!("CTOC '{}' is not reachable from the top-level CTOC", toc.element_id)));
+        }
+    }
+
+    tree.findings.extend(resolver.findings);
+    tree
+}
+
+struct Resolver<'a> {
+    tocs: &'a [&'a TableOfContentsFrame],
+    chapters: &'a [&'a ChapterFrame],
+    /// Element IDs already placed in the tree
+    reached: HashSet<&'a str>,
+    /// Whether a CTOC was not resolved because of the maximum depth
+    depth_limited: bool,
+    findings: Vec<Finding>,
+}
+
+impl<'a> Resolver<'a> {
+    /// Build the entry for a CTOC; `path` holds the CTOCs above it to detect cycles
+    fn toc_entry(&mut self, toc: &'a TableOfContentsFrame, path: &mut Vec<&'a str>) -> TocEntry {
+        self.reached.insert(&toc.element_id);
+        path.push(&toc.element_id);
+
+        let mut children = Vec::new();
+        let max_depth = crate::id3v2_tools::max_embedding_depth();
+        for child_id in &toc.child_element_ids {
+            if path.len() > max_depth && self.tocs.iter().any(|candidate| &candidate.element_id == child_id) {
+                self.depth_limited = true;
+                self.findings.push(Finding::new(
+                    Severity::Error,
+                    "CTOC",
+                    format!("CTOC '{}' is nested more than {} levels deep, child '{}' not resolved", toc.element_id, max_depth, child_id),
+                ));
+            } else if path.contains(&child_id.as_str()) {
+                self.findings.push(Finding::new(Severity::Error, "CTOC", format!("CTOC '{}' refers back to '{}' (cycle)", toc.element_id, child_id)));
+            } else if let Some(child_toc) = self.tocs.iter().find(|candidate| &candidate.element_id == child_id) {
+                children.push(self.toc_entry(child_toc, path));
+            } else if let Some(chapter) = self.chapters.iter().find(|candidate| &candidate.element_id == child_id) {
+                self.reached.insert(&chapter.element_id);
+                children.push(chapter_entry(chapter));
+            } else {
+                self.findings.push(Finding::new(Severity::Warning, "CTOC", format!("CTOC '{}' refers to missing element '{}'", toc.element_id, child_id)));
+                children.push(TocEntry::Missing { element_id: child_id.clone() });
+            }
+        }
+
+        path.pop();
+        TocEntry::Toc { element_id: toc.element_id.clone(), title: toc.title().map(str::to_string), ordered: toc.ordered, children }
+    }
+}
+
+fn chapter_entry(chapter: &ChapterFrame) -> TocEntry {
+    TocEntry::Chapter {
+        element_id: chapter.element_id.clone(),
+        title: chapter.title().map(str::to_string),
+        start_ms: chapter.start_time,
+        end_ms: chapter.end_time,
+    }
+}
+
+/// Print the table of contents of the dissected frames, if the tag has chapters
+pub fn print_outline(frames: &[Id3v2Frame]) -> std::io::Result<()> {
+    let tree = build_chapter_tree(frames);
+    if tree.roots.is_empty() && tree.findings.is_empty() {
+        return Ok(());
+    }
+    outln!("\nTable of Contents:");
+    write_outline(&mut crate::output::writer(), &tree, "    ")
+}
+
+/// Print the tree as an indented outline followed by its findings
+pub fn write_outline(out: &mut dyn Write, tree: &ChapterTree, indent: &str) -> std::io::Result<()> {
+    for entry in &tree.roots {
+        write_entry(out, entry, indent)?;
+    }
+    for finding in &tree.findings {
+        writeln!(out, "{}{}: {}", indent, finding.severity, finding.message)?;
+    }
+    Ok(())
+}
+
+fn write_entry(out: &mut dyn Write, entry: &TocEntry, indent: &str) -> std::io::Result<()> {
+    match entry {
+        | TocEntry::Toc { element_id, title, ordered, children } => {
+            let order = if *ordered {
+                "ordered"
+            } else {
+                "unordered"
+            };
+            write!(out, "{}{} ({})", indent, element_id, order)?;
+            if let Some(title) = title {
+                write!(out, " \"{}\"", truncate_text(title))?;
+            }
+            writeln!(out)?;
+            let child_indent = format!("{}    ", indent);
+            for child in children {
+                write_entry(out, child, &child_indent)?;
+            }
+        }
+        | TocEntry::Chapter { element_id, title, start_ms, end_ms } => {
+            write!(out, "{}{}  {} - {}", indent, element_id, format_duration(*start_ms as u64), format_duration(*end_ms as u64))?;
+            if let Some(title) = title {
+                write!(out, "  \"{}\"", truncate_text(title))?;
+            }
+            writeln!(out)?;
+        }
+        | TocEntry::Missing { element_id } => writeln!(out, "{}{}  (missing)", indent, element_id)?,
+    }
+    Ok(())
+}
@@ -1,10 +1,13 @@
 /// Comment Frame (COMM, USLT)
 ///
 /// Structure: Text encoding + Language + Short description + Full text
-use crate::id3v2_text_encoding::{TextEncoding, split_terminated_text};
+use crate::error::SupertoolError;
+use crate::id3v2_text_encoding::{TextEncoding, encode_text, split_terminated_text, terminator_bytes};
+use crate::text_truncate::truncate_text;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommentFrame {
     pub encoding: TextEncoding,
     pub language: String,
@@ -14,9 +17,9 @@ pub struct CommentFrame {
 
 impl CommentFrame {
     /// Parse a COMM or USLT frame from raw data
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
         if data.len() < 5 {
-            return Err("Comment frame data too short".to_string());
+            return Err(SupertoolError::TruncatedFrame("Comment frame data too short".to_string()));
         }
 
         let encoding = TextEncoding::from_byte(data[0])?;
@@ -30,6 +33,18 @@ impl CommentFrame {
 
         Ok(CommentFrame { encoding, language, description, text })
     }
+
+    /// Serialize the frame content (encoding byte, language, terminated description, text)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.encoding as u8];
+        let mut language = self.language.as_bytes().to_vec();
+        language.resize(3, b' ');
+        bytes.extend_from_slice(&language[..3]);
+        bytes.extend(encode_text(&self.description, self.encoding));
+        bytes.extend_from_slice(terminator_bytes(self.encoding));
+        bytes.extend(encode_text(&self.text, self.encoding));
+        bytes
+    }
 }
 
 impl fmt::Display for CommentFrame {
@@ -37,9 +52,9 @@ impl fmt::Display for CommentFrame {
         writeln!(f, "Encoding: {}", self.encoding)?;
         writeln!(f, "Language: \"{}\"", self.language)?;
         if !self.description.is_empty() {
-            writeln!(f, "Description: \"{}\"", self.description)?;
+            writeln!(f, "Description: \"{}\"", truncate_text(&self.description))?;
         }
-        writeln!(f, "Text: \"{}\"", self.text)?;
+        writeln!(f, "Text: \"{}\"", truncate_text(&self.text))?;
         Ok(())
     }
 }
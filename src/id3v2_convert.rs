@@ -0,0 +1,337 @@
+/// Conversion of ID3v2 tags between ID3v2.3 and ID3v2.4 (`tag convert`)
+///
+/// The two versions differ in more than the header: ID3v2.4 replaced the date frames TYER, TDAT,
+/// TIME and TORY by the timestamps TDRC and TDOR and split IPLS into TIPL and TMCL, added UTF-8
+/// and UTF-16BE text and multi-value text frames, dropped frames like TSIZ and RVAD and moved the
+/// frame status flags. The conversion maps every frame to its counterpart and reports each
+/// decision, including the frames it has to drop. Format flags (compression, grouping,
+/// unsynchronisation) are removed and the content is stored plainly; the frame sizes are written
+/// plain or synchsafe for the target version by `id3v2_writer`.
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_frame_format::FrameFormat;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_text_encoding::TextEncoding;
+use crate::id3v2_text_frame::{ID3V2_3_VALUE_SEPARATOR, TextFrame};
+use crate::id3v2_tools::is_valid_frame_for_version;
+use crate::id3v2_writer::write_tag;
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// ID3v2.3 frames without an ID3v2.4 counterpart, with the reason
+const DROPPED_IN_ID3V2_4: &[(&str, &str)] = &[
+    ("TRDA", "ID3v2.4 has no recording dates frame"),
+    ("TSIZ", "ID3v2.4 removed the size frame"),
+    ("RVAD", "RVA2 stores the volume adjustment per channel in another layout"),
+    ("EQUA", "EQU2 stores the equalisation in another layout"),
+];
+
+/// ID3v2.4 frames without an ID3v2.3 counterpart, with the reason
+const DROPPED_IN_ID3V2_3: &[(&str, &str)] = &[
+    ("TDRL", "ID3v2.3 has no release time frame"),
+    ("TDTG", "ID3v2.3 has no tagging time frame"),
+    ("TDEN", "ID3v2.3 has no encoding time frame"),
+    ("TMOO", "ID3v2.3 has no mood frame"),
+    ("TPRO", "ID3v2.3 has no produced notice frame"),
+    ("TSST", "ID3v2.3 has no set subtitle frame"),
+    ("RVA2", "RVAD stores the volume adjustment in another layout"),
+    ("EQU2", "EQUA stores the equalisation in another layout"),
+    ("SEEK", "ID3v2.3 has no seek frame"),
+    ("ASPI", "ID3v2.3 has no audio seek point index"),
+    ("SIGN", "ID3v2.3 has no signature frame"),
+];
+
+/// Sort order frames of ID3v2.4 and the experimental frames taggers use for them in ID3v2.3
+const SORT_ORDER_FRAMES: &[(&str, &str)] = &[("TSOA", "XSOA"), ("TSOP", "XSOP"), ("TSOT", "XSOT")];
+
+/// Convert the tag of every file to ID3v2.`version_major`
+pub fn convert_files(files: &[PathBuf], version_major: u8, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unreadable = BatchErrors::default();
+    // The decisions of one file are printed as one block
+    let failed = unreadable.change_files(files, |path| crate::output::buffered(|| convert_file(path, version_major, dry_run, options)));
+    unreadable.finish_files(failed, "converted", dry_run)
+}
+
+/// Convert one file and print the mapping decisions
+fn convert_file(path: &Path, version_major: u8, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tag = Id3v2Tag::read(&mut File::open(long_path(path))?)?.ok_or("no ID3v2 tag")?;
+    if tag.version_major != 3 && tag.version_major != 4 {
+        return Err(format!("ID3v2.{} tags cannot be converted", tag.version_major).into());
+    }
+    if tag.version_major == version_major {
+        outln!("{}: already ID3v2.{}", display_path(path), version_major);
+        return Ok(());
+    }
+
+    outln!("{}: ID3v2.{} -> ID3v2.{}", display_path(path), tag.version_major, version_major);
+    let decisions = convert_tag(&mut tag, version_major)?;
+    for decision in &decisions {
+        outln!("  {}", decision);
+    }
+    if !dry_run {
+        let result = write_tag(path, &tag, options)?;
+        outln!("  Written: tag size {} -> {} bytes", result.old_tag_size, result.new_tag_size);
+    }
+    Ok(())
+}
+
+/// Convert a tag to ID3v2.`version_major` and return the mapping decisions
+pub fn convert_tag(tag: &mut Id3v2Tag, version_major: u8) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut decisions = Vec::new();
+    tag.frames = convert_frames(std::mem::take(&mut tag.frames), tag.version_major, version_major, "", &mut decisions)?;
+    tag.version_major = version_major;
+    tag.version_minor = 0;
+    if decisions.is_empty() {
+        decisions.push("All frames carried over unchanged".to_string());
+    }
+    Ok(decisions)
+}
+
+/// Convert frames (of the tag or of a CHAP/CTOC frame); decisions are prefixed with `location`
+fn convert_frames(frames: Vec<Id3v2Frame>, source: u8, target: u8, location: &str, decisions: &mut Vec<String>) -> Result<Vec<Id3v2Frame>, Box<dyn std::error::Error>> {
+    let frames = frames.into_iter().map(|frame| plain_frame(frame, source, target, location, decisions)).collect::<Result<Vec<_>, _>>()?;
+
+    // Frames made from several frames of the other version replace the first of them
+    let mut groups: Vec<(&[&str], Option<Vec<Id3v2Frame>>)> = Vec::new();
+    if target == 4 {
+        groups.push((&["TYER", "TDAT", "TIME"], combine_dates(&frames, location, decisions)));
+        if let Some(frame) = frames.iter().find(|frame| frame.id == "IPLS") {
+            let pairs = TextFrame::parse(&frame.data).map(|text_frame| text_frame.strings).unwrap_or_default();
+            decisions.push(format!("{}IPLS -> TIPL ({} involvement(s))", location, pairs.len() / 2));
+            groups.push((&["IPLS"], Some(vec![Id3v2Frame::new_text("TIPL", pairs, target)])));
+        }
+    } else {
+        groups.push((&["TIPL", "TMCL"], combine_involved_people(&frames, location, decisions)));
+    }
+
+    let mut converted = Vec::new();
+    for frame in frames {
+        if let Some((_, group_frames)) = groups.iter_mut().find(|(ids, _)| ids.contains(&frame.id.as_str())) {
+            converted.extend(group_frames.take().unwrap_or_default());
+            continue;
+        }
+        converted.extend(convert_frame(frame, source, target, location, decisions)?);
+    }
+    Ok(converted)
+}
+
+/// Store the content of a frame without format flags and move its status flags
+fn plain_frame(mut frame: Id3v2Frame, source: u8, target: u8, location: &str, decisions: &mut Vec<String>) -> Result<Id3v2Frame, Box<dyn std::error::Error>> {
+    if !frame.format.is_plain() {
+        let data = frame.format.content(&frame.data).map_err(|e| format!("{}{}: {}", location, frame.id, e))?.into_owned();
+        decisions.push(format!("{}{}: {} removed, content stored plainly", location, frame.id, frame.format));
+        frame.size = data.len() as u32;
+        frame.data = data;
+        frame.format = FrameFormat::default();
+    }
+    // Tag alter, file alter and read-only preservation: bits 15-13 in ID3v2.3, bits 14-12 in ID3v2.4
+    let status = if source == 4 {
+        (frame.flags & 0x7000) << 1
+    } else {
+        frame.flags & 0xE000
+    };
+    frame.flags = if target == 4 {
+        status >> 1
+    } else {
+        status
+    };
+    Ok(frame)
+}
+
+/// Map a single frame to the frames of the target version (none if it is dropped)
+fn convert_frame(mut frame: Id3v2Frame, source: u8, target: u8, location: &str, decisions: &mut Vec<String>) -> Result<Vec<Id3v2Frame>, Box<dyn std::error::Error>> {
+    let dropped = if target == 4 {
+        DROPPED_IN_ID3V2_4
+    } else {
+        DROPPED_IN_ID3V2_3
+    };
+    if let Some((id, reason)) = dropped.iter().find(|(id, _)| *id == frame.id) {
+        decisions.push(format!("{}{}{} dropped ({})", location, id, quoted(frame.get_text()), reason));
+        return Ok(Vec::new());
+    }
+
+    match (frame.id.as_str(), target) {
+        | ("TORY", 4) => {
+            let value = frame.get_text().unwrap_or_default().trim().to_string();
+            decisions.push(format!("{}TORY \"{}\" -> TDOR \"{}\"", location, value, value));
+            return Ok(vec![Id3v2Frame::new_text("TDOR", vec![value], target)]);
+        }
+        | ("TDOR", 3) => {
+            let value = frame.get_text().unwrap_or_default().trim().to_string();
+            return Ok(match timestamp_parts(&value) {
+                | Some(parts) => {
+                    let dropped = if parts.len() > 1 {
+                        " (only the year fits)"
+                    } else {
+                        ""
+                    };
+                    decisions.push(format!("{}TDOR \"{}\" -> TORY \"{}\"{}", location, value, parts[0], dropped));
+                    vec![Id3v2Frame::new_text("TORY", vec![parts[0].to_string()], target)]
+                }
+                | None => {
+                    decisions.push(format!("{}TDOR \"{}\" dropped (not a timestamp, TORY takes a year)", location, value));
+                    Vec::new()
+                }
+            });
+        }
+        | ("TDRC", 3) => return Ok(split_timestamp(&frame, location, decisions)),
+        | _ => {}
+    }
+
+    // Sort order frames: experimental X*** frames in ID3v2.3, T*** frames in ID3v2.4
+    let renamed = SORT_ORDER_FRAMES.iter().find_map(|(v4_id, v3_id)| match target {
+        | 4 if frame.id == *v3_id => Some(*v4_id),
+        | 3 if frame.id == *v4_id => Some(*v3_id),
+        | _ => None,
+    });
+    if let Some(new_id) = renamed {
+        decisions.push(format!("{}{} -> {}", location, frame.id, new_id));
+        frame.id = new_id.to_string();
+        frame.raw_id = None;
+    }
+
+    if is_valid_frame_for_version(&frame.id, source) && !is_valid_frame_for_version(&frame.id, target) {
+        decisions.push(format!("{}{} dropped (not defined in ID3v2.{})", location, frame.id, target));
+        return Ok(Vec::new());
+    }
+
+    match &mut frame.content {
+        | Some(Id3v2FrameContent::Chapter(chapter)) => {
+            let sub_location = format!("{}CHAP {}: ", location, chapter.element_id);
+            chapter.sub_frames = convert_frames(std::mem::take(&mut chapter.sub_frames), source, target, &sub_location, decisions)?;
+            frame.data = chapter.to_bytes(target);
+        }
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => {
+            let sub_location = format!("{}CTOC {}: ", location, toc.element_id);
+            toc.sub_frames = convert_frames(std::mem::take(&mut toc.sub_frames), source, target, &sub_location, decisions)?;
+            frame.data = toc.to_bytes(target);
+        }
+        | Some(Id3v2FrameContent::Text(text_frame)) if target == 3 && text_frame.strings.len() > 1 => {
+            decisions.push(format!("{}{}: {} values joined with \"{}\"", location, frame.id, text_frame.strings.len(), ID3V2_3_VALUE_SEPARATOR));
+            *text_frame = TextFrame::new(text_frame.encoding, vec![text_frame.strings.join(ID3V2_3_VALUE_SEPARATOR)]);
+            frame.data = text_frame.to_bytes(target);
+        }
+        | _ => {}
+    }
+    frame.size = frame.data.len() as u32;
+
+    // ID3v2.3 knows ISO-8859-1 and UTF-16 with BOM only
+    if let Some(encoding) = frame.text_encoding().filter(|encoding| !encoding.is_valid_for_version(target)) {
+        frame.reencode(TextEncoding::Utf16Bom, target)?;
+        decisions.push(format!("{}{}: {} -> {} (not allowed in ID3v2.{})", location, frame.id, encoding, TextEncoding::Utf16Bom, target));
+    }
+    Ok(vec![frame])
+}
+
+/// Combine TYER, TDAT and TIME into a TDRC timestamp (ID3v2.3 to ID3v2.4); `None` without these frames
+fn combine_dates(frames: &[Id3v2Frame], location: &str, decisions: &mut Vec<String>) -> Option<Vec<Id3v2Frame>> {
+    let value = |id: &str| frames.iter().find(|frame| frame.id == id).map(|frame| frame.get_text().unwrap_or_default().trim().to_string());
+    let (year, date, time) = (value("TYER"), value("TDAT"), value("TIME"));
+    let mut sources = Vec::new();
+    for (id, value) in [("TYER", &year), ("TDAT", &date), ("TIME", &time)] {
+        if let Some(value) = value {
+            sources.push(format!("{} \"{}\"", id, value));
+        }
+    }
+    if sources.is_empty() {
+        return None;
+    }
+
+    let Some(year) = year.filter(|year| is_digits(year, 4)) else {
+        decisions.push(format!("{}{} dropped (TDRC needs a four-digit year from TYER)", location, sources.join(" + ")));
+        return Some(Vec::new());
+    };
+    let mut timestamp = year;
+    let mut notes = Vec::new();
+    // TDAT is DDMM, TIME is HHMM; a time without a date cannot be placed in a timestamp
+    match date {
+        | Some(date) if is_digits(&date, 4) => {
+            timestamp = format!("{}-{}-{}", timestamp, &date[2..4], &date[0..2]);
+            match time {
+                | Some(time) if is_digits(&time, 4) => timestamp = format!("{}T{}:{}", timestamp, &time[0..2], &time[2..4]),
+                | Some(_) => notes.push("TIME is not HHMM"),
+                | None => {}
+            }
+        }
+        | Some(_) => notes.push("TDAT is not DDMM"),
+        | None if time.is_some() => notes.push("TIME without TDAT"),
+        | None => {}
+    }
+    let notes = if notes.is_empty() {
+        String::new()
+    } else {
+        format!(" ({}, left out)", notes.join(", "))
+    };
+    decisions.push(format!("{}{} -> TDRC \"{}\"{}", location, sources.join(" + "), timestamp, notes));
+    Some(vec![Id3v2Frame::new_text("TDRC", vec![timestamp], 4)])
+}
+
+/// Split a TDRC timestamp into TYER, TDAT and TIME (ID3v2.4 to ID3v2.3), as far as it has a date and time
+fn split_timestamp(frame: &Id3v2Frame, location: &str, decisions: &mut Vec<String>) -> Vec<Id3v2Frame> {
+    let value = frame.get_text().unwrap_or_default().trim().to_string();
+    let Some(parts) = timestamp_parts(&value) else {
+        decisions.push(format!("{}TDRC \"{}\" dropped (not a timestamp, TYER takes a year)", location, value));
+        return Vec::new();
+    };
+
+    let mut targets = vec![format!("TYER \"{}\"", parts[0])];
+    let mut frames = vec![Id3v2Frame::new_text("TYER", vec![parts[0].to_string()], 3)];
+    if let (Some(month), Some(day)) = (parts.get(1), parts.get(2)) {
+        let date = format!("{}{}", day, month);
+        targets.push(format!("TDAT \"{}\"", date));
+        frames.push(Id3v2Frame::new_text("TDAT", vec![date], 3));
+        if let (Some(hour), Some(minute)) = (parts.get(3), parts.get(4)) {
+            let time = format!("{}{}", hour, minute);
+            targets.push(format!("TIME \"{}\"", time));
+            frames.push(Id3v2Frame::new_text("TIME", vec![time], 3));
+        }
+    }
+    let left_out = match parts.len() {
+        | 2 => " (the month without a day does not fit TDAT)",
+        | 4 => " (the hour without minutes does not fit TIME)",
+        | 6 => " (TIME has no seconds)",
+        | _ => "",
+    };
+    decisions.push(format!("{}TDRC \"{}\" -> {}{}", location, value, targets.join(" + "), left_out));
+    frames
+}
+
+/// Combine TIPL and TMCL into an IPLS frame (ID3v2.4 to ID3v2.3); `None` without these frames
+fn combine_involved_people(frames: &[Id3v2Frame], location: &str, decisions: &mut Vec<String>) -> Option<Vec<Id3v2Frame>> {
+    let mut pairs = Vec::new();
+    let mut sources = Vec::new();
+    for id in ["TIPL", "TMCL"] {
+        for frame in frames.iter().filter(|frame| frame.id == id) {
+            let values = frame.get_text_values().unwrap_or_default();
+            sources.push(format!("{} ({} pair(s))", id, values.len() / 2));
+            pairs.extend(values.iter().cloned());
+        }
+    }
+    if sources.is_empty() {
+        return None;
+    }
+    let note = if sources.iter().any(|source| source.starts_with("TMCL")) {
+        " (musician credits become involvements)"
+    } else {
+        ""
+    };
+    decisions.push(format!("{}{} -> IPLS{}", location, sources.join(" + "), note));
+    Some(vec![Id3v2Frame::new_involved_people(pairs)])
+}
+
+/// Parts of an ID3v2.4 timestamp (yyyy, MM, dd, HH, mm, ss), as far as present and well-formed
+fn timestamp_parts(value: &str) -> Option<Vec<&str>> {
+    let parts: Vec<&str> = value.split(['-', 'T', ':']).collect();
+    let well_formed = is_digits(parts[0], 4) && parts.len() <= 6 && parts[1..].iter().all(|part| is_digits(part, 2));
+    well_formed.then_some(parts)
+}
+
+fn is_digits(value: &str, count: usize) -> bool {
+    value.len() == count && value.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn quoted(value: Option<&str>) -> String {
+    value.map(|value| format!(" \"{}\"", value)).unwrap_or_default()
+}
@@ -0,0 +1,33 @@
+/// ID3v2 tags embedded in other containers (WAVE id3 chunk, DSF metadata block)
+use crate::id3v2_tag::Id3v2Tag;
+use crate::options::DebugOptions;
+
+/// Print an embedded tag found at `location` with its frames, honouring the frame selection of `options`
+pub fn print_embedded_tag(tag: &Id3v2Tag, location: &str, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    outln!("\nEmbedded ID3v2 Tag ({}):", location);
+    outln!("  Version: 2.{}.{}", tag.version_major, tag.version_minor);
+    outln!("  Flags: 0x{:02X}", tag.flags);
+    outln!("  Tag Size: {} bytes", tag.size);
+    if tag.version_major != 3 && tag.version_major != 4 {
+        outln!("  Unsupported ID3v2 version, frames not parsed");
+        return Ok(());
+    }
+
+    outln!("\n{} Frames:", tag.tag_type());
+    for frame in tag.frames.iter().filter(|frame| options.lists_id(&frame.id)) {
+        match frame.language() {
+            | Some(language) if !options.shows_frame(frame) => outln!("    Language \"{}\" not selected (--lang)\n", language),
+            | _ => out!("    {}", frame),
+        }
+    }
+
+    crate::id3v2_chapter_tree::print_outline(&tag.frames)?;
+    crate::id3v2_frame_order::print_order(&tag.frames);
+    crate::metadata_fingerprint::print_fingerprint(&tag.frames);
+    crate::id3v2_plausibility::print_plausibility(&tag.frames, None)?;
+    crate::podcast_info::print_podcast(crate::podcast_info::PodcastInfo::from_frames(&tag.frames).as_ref());
+    crate::disc_info::print_disc_info(crate::disc_info::DiscInfo::from_frames(&tag.frames).as_ref());
+    crate::dj_info::print_dj_info(crate::dj_info::DjInfo::from_frames(&tag.frames).as_ref());
+    crate::id3v2_languages::print_inventory(&tag.frames, options.language.as_deref());
+    Ok(())
+}
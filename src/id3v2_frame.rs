@@ -1,17 +1,31 @@
+use crate::error::SupertoolError;
+use crate::finding::Finding;
 use crate::id3v2_attached_picture_frame::AttachedPictureFrame;
 use crate::id3v2_chapter_frame::ChapterFrame;
 use crate::id3v2_comment_frame::CommentFrame;
+use crate::id3v2_frame_format::FrameFormat;
+use crate::id3v2_general_object_frame::GeneralObjectFrame;
+use crate::id3v2_genre::parse_genres;
+use crate::id3v2_known_user_text::{interpret_comment, interpret_user_text};
+use crate::id3v2_private_frame::PrivateFrame;
 use crate::id3v2_table_of_contents_frame::TableOfContentsFrame;
-use crate::id3v2_text_frame::TextFrame;
-use crate::id3v2_tools::get_frame_description;
+use crate::id3v2_synced_lyrics_frame::{CONTENT_TYPE_LYRICS, SyncedLyricsFrame, TIMESTAMP_MILLISECONDS};
+use crate::id3v2_text_encoding::{TextEncoding, can_encode, decode_stored_strings};
+use crate::id3v2_text_frame::{ID3V2_3_VALUE_SEPARATOR, TextFrame};
+use crate::id3v2_tools::{encode_synchsafe_int, frame_id_to_string, get_frame_description};
 use crate::id3v2_unique_file_id_frame::UniqueFileIdFrame;
 use crate::id3v2_url_frame::UrlFrame;
+use crate::id3v2_url_validation::validate_url;
 use crate::id3v2_user_text_frame::UserTextFrame;
 use crate::id3v2_user_url_frame::UserUrlFrame;
+use crate::text_normalization::{changes_under_nfc, to_nfc};
+use crate::text_truncate::truncate_text;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Parsed content of an ID3v2 frame
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Id3v2FrameContent {
     /// Text information frame (T*** except TXXX)
     Text(TextFrame),
@@ -23,14 +37,22 @@ pub enum Id3v2FrameContent {
     UserUrl(UserUrlFrame),
     /// Comment frame (COMM, USLT)
     Comment(CommentFrame),
+    /// Synchronised lyrics/text frame (SYLT)
+    SyncedLyrics(SyncedLyricsFrame),
     /// Attached picture frame (APIC)
     Picture(AttachedPictureFrame),
+    /// General encapsulated object (GEOB)
+    GeneralObject(GeneralObjectFrame),
     /// Unique file identifier (UFID)
     UniqueFileId(UniqueFileIdFrame),
+    /// Private frame (PRIV)
+    Private(PrivateFrame),
     /// Chapter frame (CHAP)
     Chapter(ChapterFrame),
     /// Table of contents frame (CTOC)
     TableOfContents(TableOfContentsFrame),
+    /// CHAP or CTOC frame nested deeper than the maximum embedding depth, kept as raw data
+    TooDeep { depth: usize },
     /// Raw binary data for unsupported/unknown frames
     Binary,
 }
@@ -43,26 +65,36 @@ impl fmt::Display for Id3v2FrameContent {
             | Id3v2FrameContent::UserText(user_text_frame) => write!(f, "{}", user_text_frame),
             | Id3v2FrameContent::UserUrl(user_url_frame) => write!(f, "{}", user_url_frame),
             | Id3v2FrameContent::Comment(comment_frame) => write!(f, "{}", comment_frame),
+            | Id3v2FrameContent::SyncedLyrics(synced_lyrics_frame) => write!(f, "{}", synced_lyrics_frame),
             | Id3v2FrameContent::Picture(picture_frame) => write!(f, "{}", picture_frame),
+            | Id3v2FrameContent::GeneralObject(object_frame) => write!(f, "{}", object_frame),
             | Id3v2FrameContent::UniqueFileId(ufid_frame) => write!(f, "{}", ufid_frame),
+            | Id3v2FrameContent::Private(private_frame) => write!(f, "{}", private_frame),
             | Id3v2FrameContent::Chapter(chapter_frame) => write!(f, "{}", chapter_frame),
             | Id3v2FrameContent::TableOfContents(toc_frame) => write!(f, "{}", toc_frame),
+            | Id3v2FrameContent::TooDeep { depth } => {
+                writeln!(f, "Not dissected: nested {} levels deep (maximum {}, see --max-frame-depth)", depth, crate::id3v2_tools::max_embedding_depth())
+            }
             | Id3v2FrameContent::Binary => Ok(()),
         }
     }
 }
 
 /// ID3v2 frame representation for all versions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Id3v2Frame {
-    /// Four-character frame identifier (e.g., "TIT2", "TPE1", "TALB")
+    /// Four-character frame identifier (e.g., "TIT2", "TPE1", "TALB"), or the hex form of `raw_id`
     pub id: String,
+    /// Original ID bytes when they are not printable ASCII, written back unchanged
+    pub raw_id: Option<[u8; 4]>,
     /// Size of the frame data (excluding header)
     pub size: u32,
     /// Frame flags (meaning varies by ID3v2 version)
     pub flags: u16,
     /// Frame offset in the file (for top-level frames) or within parent frame (for embedded frames)
     pub offset: Option<usize>,
+    /// Group identifier, encryption, compression and other format flags (read with the content)
+    pub format: FrameFormat,
     /// Raw frame data content
     pub data: Vec<u8>,
     /// Parsed frame content (if successfully parsed)
@@ -74,11 +106,277 @@ pub struct Id3v2Frame {
 impl Id3v2Frame {
     /// Create a new ID3v2 frame with offset information
     pub fn new_with_offset(id: String, size: u32, flags: u16, offset: usize, data: Vec<u8>) -> Self {
-        Self { id, size, flags, offset: Some(offset), data, content: None, embedded_frames: None }
+        Self { id, size, flags, offset: Some(offset), format: FrameFormat::default(), raw_id: None, data, content: None, embedded_frames: None }
+    }
+
+    /// Create a frame from the ID bytes of its header, keeping IDs that are not printable ASCII in `raw_id`
+    pub fn from_id_bytes(id_bytes: &[u8], size: u32, flags: u16, offset: usize, data: Vec<u8>) -> Self {
+        let mut frame = Self::new_with_offset(frame_id_to_string(id_bytes), size, flags, offset, data);
+        if !id_bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            frame.raw_id = id_bytes.try_into().ok();
+        }
+        frame
+    }
+
+    /// The four ID bytes as they appear in the frame header
+    pub fn id_bytes(&self) -> &[u8] {
+        match &self.raw_id {
+            | Some(raw_id) => raw_id,
+            | None => self.id.as_bytes(),
+        }
+    }
+
+    /// Create a text information frame (T***) with one or more values
+    ///
+    /// ID3v2.3 has no multi-value text frames, so several values are joined with "/" there.
+    pub fn new_text(id: &str, values: Vec<String>, version_major: u8) -> Self {
+        let values = if version_major < 4 && values.len() > 1 {
+            vec![values.join(ID3V2_3_VALUE_SEPARATOR)]
+        } else {
+            values
+        };
+        let encoding = TextEncoding::preferred_for_version(version_major, &values.concat());
+        let text_frame = TextFrame::new(encoding, values);
+        Self::new_with_content(id, text_frame.to_bytes(version_major), Id3v2FrameContent::Text(text_frame))
+    }
+
+    /// Create an ID3v2.3 involved people list (IPLS) from alternating involvements and names
+    pub fn new_involved_people(pairs: Vec<String>) -> Self {
+        let encoding = TextEncoding::preferred_for_version(3, &pairs.concat());
+        // IPLS separates its strings with null terminators like the multi-value frames of ID3v2.4
+        let data = TextFrame::new(encoding, pairs).to_bytes(4);
+        Self::new_with_content("IPLS", data, Id3v2FrameContent::Binary)
+    }
+
+    /// Create a user-defined text frame (TXXX)
+    pub fn new_user_text(description: &str, value: &str, version_major: u8) -> Self {
+        let encoding = TextEncoding::preferred_for_version(version_major, &format!("{}{}", description, value));
+        let user_text_frame = UserTextFrame { encoding, description: description.to_string(), value: value.to_string() };
+        Self::new_with_content("TXXX", user_text_frame.to_bytes(), Id3v2FrameContent::UserText(user_text_frame))
+    }
+
+    /// Create an unsynchronised lyrics frame (USLT)
+    pub fn new_lyrics(language: &str, description: &str, text: &str, version_major: u8) -> Self {
+        let encoding = TextEncoding::preferred_for_version(version_major, &format!("{}{}", description, text));
+        let comment_frame = CommentFrame { encoding, language: language.to_string(), description: description.to_string(), text: text.to_string() };
+        Self::new_with_content("USLT", comment_frame.to_bytes(), Id3v2FrameContent::Comment(comment_frame))
+    }
+
+    /// Create a synchronised lyrics frame (SYLT) with millisecond time stamps
+    pub fn new_synced_lyrics(language: &str, description: &str, entries: Vec<(String, u32)>, version_major: u8) -> Self {
+        let all_text: String = entries.iter().map(|(text, _)| text.as_str()).chain([description]).collect();
+        let encoding = TextEncoding::preferred_for_version(version_major, &all_text);
+        let synced_lyrics_frame = SyncedLyricsFrame {
+            encoding,
+            language: language.to_string(),
+            timestamp_format: TIMESTAMP_MILLISECONDS,
+            content_type: CONTENT_TYPE_LYRICS,
+            description: description.to_string(),
+            entries,
+        };
+        Self::new_with_content("SYLT", synced_lyrics_frame.to_bytes(), Id3v2FrameContent::SyncedLyrics(synced_lyrics_frame))
+    }
+
+    /// Create an attached picture frame (APIC)
+    pub fn new_picture(mime_type: &str, picture_type: u8, description: &str, picture_data: Vec<u8>, version_major: u8) -> Self {
+        let encoding = TextEncoding::preferred_for_version(version_major, description);
+        let picture_frame = AttachedPictureFrame { encoding, mime_type: mime_type.to_string(), picture_type, description: description.to_string(), picture_data };
+        Self::new_with_content("APIC", picture_frame.to_bytes(), Id3v2FrameContent::Picture(picture_frame))
+    }
+
+    /// Create a chapter frame (CHAP) with its sub-frames
+    pub fn new_chapter(chapter_frame: ChapterFrame, version_major: u8) -> Self {
+        Self::new_with_content("CHAP", chapter_frame.to_bytes(version_major), Id3v2FrameContent::Chapter(chapter_frame))
+    }
+
+    /// Create a table of contents frame (CTOC) with its sub-frames
+    pub fn new_table_of_contents(toc_frame: TableOfContentsFrame, version_major: u8) -> Self {
+        Self::new_with_content("CTOC", toc_frame.to_bytes(version_major), Id3v2FrameContent::TableOfContents(toc_frame))
+    }
+
+    /// Create a frame from already serialized data and its parsed content
+    fn new_with_content(id: &str, data: Vec<u8>, content: Id3v2FrameContent) -> Self {
+        Self { id: id.to_string(), size: data.len() as u32, flags: 0, offset: None, format: FrameFormat::default(), raw_id: None, data, content: Some(content), embedded_frames: None }
+    }
+
+    /// Serialize the frame (10-byte header followed by the data) for the given ID3v2 version
+    ///
+    /// Multi-value text frames are joined into a single value when written as ID3v2.3, which
+    /// writes the content plainly, without the format flags of the original frame.
+    pub fn to_bytes(&self, version_major: u8) -> Vec<u8> {
+        let joined;
+        let (data, flags) = match &self.content {
+            | Some(Id3v2FrameContent::Text(text_frame)) if version_major < 4 && text_frame.values().len() > 1 => {
+                joined = text_frame.to_bytes(version_major);
+                (&joined, self.flags & !FrameFormat::flag_mask(version_major))
+            }
+            | _ => (&self.data, self.flags),
+        };
+
+        let size = data.len() as u32;
+        let mut bytes = Vec::with_capacity(10 + data.len());
+        bytes.extend_from_slice(self.id_bytes());
+        if version_major == 4 {
+            bytes.extend_from_slice(&encode_synchsafe_int(size));
+        } else {
+            bytes.extend_from_slice(&size.to_be_bytes());
+        }
+        bytes.extend_from_slice(&flags.to_be_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    /// Text encoding used by the frame content, if it carries encoded text
+    pub fn text_encoding(&self) -> Option<TextEncoding> {
+        match &self.content {
+            | Some(Id3v2FrameContent::Text(text_frame)) => Some(text_frame.encoding),
+            | Some(Id3v2FrameContent::UserText(user_text_frame)) => Some(user_text_frame.encoding),
+            | Some(Id3v2FrameContent::UserUrl(user_url_frame)) => Some(user_url_frame.encoding),
+            | Some(Id3v2FrameContent::Comment(comment_frame)) => Some(comment_frame.encoding),
+            | Some(Id3v2FrameContent::SyncedLyrics(synced_lyrics_frame)) => Some(synced_lyrics_frame.encoding),
+            | Some(Id3v2FrameContent::Picture(picture_frame)) => Some(picture_frame.encoding),
+            | Some(Id3v2FrameContent::GeneralObject(object_frame)) => Some(object_frame.encoding),
+            | _ => None,
+        }
+    }
+
+    /// Re-encode the text of this frame and its sub-frames, returning the number of frames changed
+    ///
+    /// Fails without modifying the frame if its text cannot be represented in `encoding`.
+    pub fn reencode(&mut self, encoding: TextEncoding, version_major: u8) -> Result<usize, SupertoolError> {
+        if self.text_encoding().is_none_or(|current| current == encoding)
+            && !matches!(self.content, Some(Id3v2FrameContent::Chapter(_) | Id3v2FrameContent::TableOfContents(_)))
+        {
+            return Ok(0);
+        }
+
+        let representable = match &self.content {
+            | Some(Id3v2FrameContent::Text(text_frame)) => text_frame.strings.iter().all(|string| can_encode(string, encoding)),
+            | Some(Id3v2FrameContent::UserText(user_text_frame)) => can_encode(&user_text_frame.description, encoding) && can_encode(&user_text_frame.value, encoding),
+            | Some(Id3v2FrameContent::UserUrl(user_url_frame)) => can_encode(&user_url_frame.description, encoding),
+            | Some(Id3v2FrameContent::Comment(comment_frame)) => can_encode(&comment_frame.description, encoding) && can_encode(&comment_frame.text, encoding),
+            | Some(Id3v2FrameContent::SyncedLyrics(synced_lyrics_frame)) => {
+                can_encode(&synced_lyrics_frame.description, encoding) && synced_lyrics_frame.entries.iter().all(|(text, _)| can_encode(text, encoding))
+            }
+            | Some(Id3v2FrameContent::Picture(picture_frame)) => can_encode(&picture_frame.description, encoding),
+            | Some(Id3v2FrameContent::GeneralObject(object_frame)) => can_encode(&object_frame.filename, encoding) && can_encode(&object_frame.description, encoding),
+            | _ => true,
+        };
+        if !representable {
+            return Err(SupertoolError::BadEncoding(format!("{} text cannot be represented in {}", self.id, encoding)));
+        }
+
+        let (data, changed) = match &mut self.content {
+            | Some(Id3v2FrameContent::Text(text_frame)) => {
+                text_frame.encoding = encoding;
+                (text_frame.to_bytes(version_major), 1)
+            }
+            | Some(Id3v2FrameContent::UserText(user_text_frame)) => {
+                user_text_frame.encoding = encoding;
+                (user_text_frame.to_bytes(), 1)
+            }
+            | Some(Id3v2FrameContent::UserUrl(user_url_frame)) => {
+                user_url_frame.encoding = encoding;
+                (user_url_frame.to_bytes(), 1)
+            }
+            | Some(Id3v2FrameContent::Comment(comment_frame)) => {
+                comment_frame.encoding = encoding;
+                (comment_frame.to_bytes(), 1)
+            }
+            | Some(Id3v2FrameContent::SyncedLyrics(synced_lyrics_frame)) => {
+                synced_lyrics_frame.encoding = encoding;
+                (synced_lyrics_frame.to_bytes(), 1)
+            }
+            | Some(Id3v2FrameContent::Picture(picture_frame)) => {
+                picture_frame.encoding = encoding;
+                (picture_frame.to_bytes(), 1)
+            }
+            | Some(Id3v2FrameContent::GeneralObject(object_frame)) => {
+                object_frame.encoding = encoding;
+                (object_frame.to_bytes(), 1)
+            }
+            | Some(Id3v2FrameContent::Chapter(chapter_frame)) => {
+                let changed = reencode_all(&mut chapter_frame.sub_frames, encoding, version_major)?;
+                (chapter_frame.to_bytes(version_major), changed)
+            }
+            | Some(Id3v2FrameContent::TableOfContents(toc_frame)) => {
+                let changed = reencode_all(&mut toc_frame.sub_frames, encoding, version_major)?;
+                (toc_frame.to_bytes(version_major), changed)
+            }
+            | _ => return Ok(0),
+        };
+
+        // The new data is the plain content, so the group byte, compression etc. no longer apply
+        if changed > 0 {
+            self.size = data.len() as u32;
+            self.data = data;
+            self.flags &= !FrameFormat::flag_mask(version_major);
+            self.format = FrameFormat::default();
+        }
+        Ok(changed)
+    }
+
+    /// Whether the text of a text, user text, comment or lyrics (USLT) frame is stored in a form
+    /// that changes under NFC normalization, whether or not `--nfc` normalized it when decoding
+    pub fn stored_text_changes_under_nfc(&self) -> bool {
+        let text_start = match &self.content {
+            | Some(Id3v2FrameContent::Text(_) | Id3v2FrameContent::UserText(_)) => 1,
+            | Some(Id3v2FrameContent::Comment(_)) => 4,
+            | _ => return false,
+        };
+        let Ok(data) = self.format.content(&self.data) else {
+            return false;
+        };
+        let Some(encoding) = data.first().and_then(|&byte| TextEncoding::from_byte(byte).ok()) else {
+            return false;
+        };
+        decode_stored_strings(data.get(text_start..).unwrap_or_default(), encoding).is_ok_and(|strings| strings.iter().any(|string| changes_under_nfc(string)))
+    }
+
+    /// Store the text of this frame and its sub-frames in NFC, returning the number of frames changed
+    pub fn normalize_text(&mut self, version_major: u8) -> usize {
+        let stored_changes = self.stored_text_changes_under_nfc();
+        let (data, changed) = match &mut self.content {
+            | Some(Id3v2FrameContent::Text(text_frame)) => {
+                let changed = normalize_strings(text_frame.strings.iter_mut().chain([&mut text_frame.text])) || stored_changes;
+                (text_frame.to_bytes(version_major), usize::from(changed))
+            }
+            | Some(Id3v2FrameContent::UserText(user_text_frame)) => {
+                let changed = normalize_strings([&mut user_text_frame.description, &mut user_text_frame.value]) || stored_changes;
+                (user_text_frame.to_bytes(), usize::from(changed))
+            }
+            | Some(Id3v2FrameContent::Comment(comment_frame)) => {
+                let changed = normalize_strings([&mut comment_frame.description, &mut comment_frame.text]) || stored_changes;
+                (comment_frame.to_bytes(), usize::from(changed))
+            }
+            | Some(Id3v2FrameContent::Chapter(chapter_frame)) => {
+                let changed = chapter_frame.sub_frames.iter_mut().map(|frame| frame.normalize_text(version_major)).sum();
+                (chapter_frame.to_bytes(version_major), changed)
+            }
+            | Some(Id3v2FrameContent::TableOfContents(toc_frame)) => {
+                let changed = toc_frame.sub_frames.iter_mut().map(|frame| frame.normalize_text(version_major)).sum();
+                (toc_frame.to_bytes(version_major), changed)
+            }
+            | _ => return 0,
+        };
+
+        // As with reencode, the new data is the plain content
+        if changed > 0 {
+            self.size = data.len() as u32;
+            self.data = data;
+            self.flags &= !FrameFormat::flag_mask(version_major);
+            self.format = FrameFormat::default();
+        }
+        changed
     }
 
     /// Parse frame content based on frame ID
-    pub fn parse_content(&mut self, version_major: u8) -> Result<(), String> {
+    pub fn parse_content(&mut self, version_major: u8) -> Result<(), SupertoolError> {
+        self.parse_content_at_depth(version_major, 0)
+    }
+
+    /// Parse frame content of a frame nested `depth` levels deep in CHAP/CTOC frames
+    pub fn parse_content_at_depth(&mut self, version_major: u8, depth: usize) -> Result<(), SupertoolError> {
         // Validate that this frame is valid for the given ID3v2 version
         if !crate::id3v2_tools::is_valid_frame_for_version(&self.id, version_major) {
             // Invalid frame for this version, store as binary data
@@ -86,58 +384,96 @@ impl Id3v2Frame {
             return Ok(());
         }
 
-        let content = match self.id.as_str() {
+        // Skip the bytes the format flags add and decode compressed or unsynchronised content
+        self.format = FrameFormat::read(self.flags, &self.data, version_major)?;
+        if self.format.encryption_method.is_some() {
+            self.content = Some(Id3v2FrameContent::Binary);
+            return Ok(());
+        }
+        let data = self.format.content(&self.data)?;
+
+        // With --lenient, de-facto experimental frames are parsed like the frame whose layout they use
+        let layout = match crate::id3v2_tools::de_facto_layout(&self.id) {
+            | Some(layout) if crate::id3v2_tools::lenient() => layout,
+            | _ => self.id.as_str(),
+        };
+        let content = match layout {
             // Text information frames
             | id if id.starts_with('T') && id != "TXXX" => {
-                let text_frame = TextFrame::parse(&self.data)?;
+                let text_frame = TextFrame::parse(&data)?;
                 // Validate text encoding for this ID3v2 version
                 if !text_frame.encoding.is_valid_for_version(version_major) {
-                    return Err(format!("Text encoding {:?} is not valid for ID3v2.{}", text_frame.encoding, version_major));
+                    return Err(SupertoolError::BadEncoding(format!("Text encoding {:?} is not valid for ID3v2.{}", text_frame.encoding, version_major)));
                 }
                 Id3v2FrameContent::Text(text_frame)
             }
+            // iTunes writes the feed URL like a text frame, with an encoding byte; others like a URL frame
+            | "WFED" => match TextFrame::parse(&data) {
+                | Ok(text_frame) if data.first().is_some_and(|&encoding| encoding <= 3) => Id3v2FrameContent::Text(text_frame),
+                | _ => Id3v2FrameContent::Url(UrlFrame::parse(&data)?),
+            },
             // URL link frames (no encoding to validate)
-            | id if id.starts_with('W') && id != "WXXX" => Id3v2FrameContent::Url(UrlFrame::parse(&self.data)?),
+            | id if id.starts_with('W') && id != "WXXX" => Id3v2FrameContent::Url(UrlFrame::parse(&data)?),
             // User-defined frames
             | "TXXX" => {
-                let user_text_frame = UserTextFrame::parse(&self.data)?;
+                let user_text_frame = UserTextFrame::parse(&data)?;
                 // Validate text encoding for this ID3v2 version
                 if !user_text_frame.encoding.is_valid_for_version(version_major) {
-                    return Err(format!("Text encoding {:?} is not valid for ID3v2.{}", user_text_frame.encoding, version_major));
+                    return Err(SupertoolError::BadEncoding(format!("Text encoding {:?} is not valid for ID3v2.{}", user_text_frame.encoding, version_major)));
                 }
                 Id3v2FrameContent::UserText(user_text_frame)
             }
             | "WXXX" => {
-                let user_url_frame = UserUrlFrame::parse(&self.data)?;
+                let user_url_frame = UserUrlFrame::parse(&data)?;
                 // Validate text encoding for this ID3v2 version
                 if !user_url_frame.encoding.is_valid_for_version(version_major) {
-                    return Err(format!("Text encoding {:?} is not valid for ID3v2.{}", user_url_frame.encoding, version_major));
+                    return Err(SupertoolError::BadEncoding(format!("Text encoding {:?} is not valid for ID3v2.{}", user_url_frame.encoding, version_major)));
                 }
                 Id3v2FrameContent::UserUrl(user_url_frame)
             }
             // Comment frames
             | "COMM" | "USLT" => {
-                let comment_frame = CommentFrame::parse(&self.data)?;
+                let comment_frame = CommentFrame::parse(&data)?;
                 // Validate text encoding for this ID3v2 version
                 if !comment_frame.encoding.is_valid_for_version(version_major) {
-                    return Err(format!("Text encoding {:?} is not valid for ID3v2.{}", comment_frame.encoding, version_major));
+                    return Err(SupertoolError::BadEncoding(format!("Text encoding {:?} is not valid for ID3v2.{}", comment_frame.encoding, version_major)));
                 }
                 Id3v2FrameContent::Comment(comment_frame)
             }
+            // Synchronised lyrics
+            | "SYLT" => {
+                let synced_lyrics_frame = SyncedLyricsFrame::parse(&data)?;
+                // Validate text encoding for this ID3v2 version
+                if !synced_lyrics_frame.encoding.is_valid_for_version(version_major) {
+                    return Err(SupertoolError::BadEncoding(format!("Text encoding {:?} is not valid for ID3v2.{}", synced_lyrics_frame.encoding, version_major)));
+                }
+                Id3v2FrameContent::SyncedLyrics(synced_lyrics_frame)
+            }
             // Attached picture
             | "APIC" => {
-                let picture_frame = AttachedPictureFrame::parse(&self.data)?;
+                let picture_frame = AttachedPictureFrame::parse(&data)?;
                 // Validate text encoding for this ID3v2 version
                 if !picture_frame.encoding.is_valid_for_version(version_major) {
-                    return Err(format!("Text encoding {:?} is not valid for ID3v2.{}", picture_frame.encoding, version_major));
+                    return Err(SupertoolError::BadEncoding(format!("Text encoding {:?} is not valid for ID3v2.{}", picture_frame.encoding, version_major)));
                 }
                 Id3v2FrameContent::Picture(picture_frame)
             }
+            // General encapsulated object
+            | "GEOB" => {
+                let object_frame = GeneralObjectFrame::parse(&data)?;
+                // Validate text encoding for this ID3v2 version
+                if !object_frame.encoding.is_valid_for_version(version_major) {
+                    return Err(SupertoolError::BadEncoding(format!("Text encoding {:?} is not valid for ID3v2.{}", object_frame.encoding, version_major)));
+                }
+                Id3v2FrameContent::GeneralObject(object_frame)
+            }
             // Unique file identifier (no encoding)
-            | "UFID" => Id3v2FrameContent::UniqueFileId(UniqueFileIdFrame::parse(&self.data)?),
+            | "UFID" => Id3v2FrameContent::UniqueFileId(UniqueFileIdFrame::parse(&data)?),
+            | "PRIV" => Id3v2FrameContent::Private(PrivateFrame::parse(&data)?),
             // Chapter frames (may contain sub-frames with their own validation)
-            | "CHAP" => Id3v2FrameContent::Chapter(ChapterFrame::parse(&self.data, version_major)?),
-            | "CTOC" => Id3v2FrameContent::TableOfContents(TableOfContentsFrame::parse(&self.data, version_major)?),
+            | "CHAP" | "CTOC" if depth > crate::id3v2_tools::max_embedding_depth() => Id3v2FrameContent::TooDeep { depth },
+            | "CHAP" => Id3v2FrameContent::Chapter(ChapterFrame::parse(&data, version_major, depth)?),
+            | "CTOC" => Id3v2FrameContent::TableOfContents(TableOfContentsFrame::parse(&data, version_major, depth)?),
             // Other frames remain as binary data
             | _ => Id3v2FrameContent::Binary,
         };
@@ -156,6 +492,32 @@ impl Id3v2Frame {
         }
     }
 
+    /// ISO 639-2 language of COMM, USLT and SYLT frames
+    pub fn language(&self) -> Option<&str> {
+        match &self.content {
+            | Some(Id3v2FrameContent::Comment(comment_frame)) => Some(&comment_frame.language),
+            | Some(Id3v2FrameContent::SyncedLyrics(synced_lyrics_frame)) => Some(&synced_lyrics_frame.language),
+            | _ => None,
+        }
+    }
+
+    /// Get all values if this is a text frame (several for ID3v2.4 multi-value frames)
+    pub fn get_text_values(&self) -> Option<&[String]> {
+        match &self.content {
+            | Some(Id3v2FrameContent::Text(text_frame)) => Some(text_frame.values()),
+            | _ => None,
+        }
+    }
+
+    /// Validate the URL of W*** and WXXX frames, reporting findings at `location`
+    pub fn url_findings(&self, location: &str) -> Vec<Finding> {
+        match &self.content {
+            | Some(Id3v2FrameContent::Url(url_frame)) => validate_url(&url_frame.url, location),
+            | Some(Id3v2FrameContent::UserUrl(user_url_frame)) => validate_url(&user_url_frame.url, location),
+            | _ => Vec::new(),
+        }
+    }
+
     /// Get URL if this is a URL frame
     pub fn get_url(&self) -> Option<&str> {
         match &self.content {
@@ -174,6 +536,9 @@ impl fmt::Display for Id3v2Frame {
         if self.flags != 0 {
             write!(f, " - Flags: 0x{:04X}", self.flags)?;
         }
+        if !self.format.is_plain() {
+            write!(f, " ({})", self.format)?;
+        }
 
         // Show detailed parsed content using the frame's own Display implementation
         if let Some(content) = &self.content {
@@ -187,26 +552,73 @@ impl fmt::Display for Id3v2Frame {
                     writeln!(f)?;
                 }
             }
+            // Show how the genre references and separators resolve
+            if self.id == "TCON"
+                && let Some(values) = self.get_text_values()
+            {
+                let genres = parse_genres(values);
+                if !genres.is_empty() {
+                    writeln!(f, "    Genres: {}", genres.join(", "))?;
+                }
+            }
+            // Report malformed URLs
+            for finding in self.url_findings(&self.id) {
+                writeln!(f, "    {}: {}", finding.severity, finding.message)?;
+            }
+            // Show the typed value of well-known TXXX descriptions and iTunes data comments
+            let known = match content {
+                | Id3v2FrameContent::UserText(user_text) => interpret_user_text(&user_text.description, &user_text.value),
+                | Id3v2FrameContent::Comment(comment_frame) if self.id == "COMM" => interpret_comment(&comment_frame.description, &comment_frame.text),
+                | _ => None,
+            };
+            if let Some(known) = known {
+                match (&known.value, &known.problem) {
+                    | (Some(value), _) => writeln!(f, "    Interpreted: {} = {}", known.key, value)?,
+                    | (None, Some(problem)) => writeln!(f, "    WARNING: Invalid {}: {}", known.key, problem)?,
+                    | (None, None) => {}
+                }
+            }
         } else {
             // Fallback for unparsed content
             if let Some(text) = self.get_text() {
                 if !text.is_empty() {
-                    write!(f, " - Text: \"{}\"", text)?;
-                }
-            } else if let Some(url) = self.get_url() {
-                if !url.is_empty() {
-                    write!(f, " - URL: \"{}\"", url)?;
+                    write!(f, " - Text: \"{}\"", truncate_text(text))?;
                 }
+            } else if let Some(url) = self.get_url()
+                && !url.is_empty()
+            {
+                write!(f, " - URL: \"{}\"", url)?;
             }
         }
 
-        if let Some(embedded) = &self.embedded_frames {
-            if !embedded.is_empty() {
-                writeln!(f, "    {} embedded sub-frame(s)", embedded.len())?;
-            }
+        if let Some(embedded) = &self.embedded_frames
+            && !embedded.is_empty()
+        {
+            writeln!(f, "    {} embedded sub-frame(s)", embedded.len())?;
         }
 
         writeln!(f)?; // Add newline at the end of frame display
         Ok(())
     }
 }
+
+/// Re-encode a list of frames, stopping at the first frame that cannot be converted
+fn reencode_all(frames: &mut [Id3v2Frame], encoding: TextEncoding, version_major: u8) -> Result<usize, SupertoolError> {
+    let mut changed = 0;
+    for frame in frames {
+        changed += frame.reencode(encoding, version_major)?;
+    }
+    Ok(changed)
+}
+
+/// Normalize strings to NFC in place, returning whether any of them changed
+fn normalize_strings<'a>(strings: impl IntoIterator<Item = &'a mut String>) -> bool {
+    let mut changed = false;
+    for string in strings {
+        if changes_under_nfc(string) {
+            *string = to_nfc(std::mem::take(string));
+            changed = true;
+        }
+    }
+    changed
+}
@@ -0,0 +1,404 @@
+use crate::checked_size::data_range;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+/// Field-by-field explanation of raw ID3v2 frame bytes
+///
+/// Shows how a frame was serialized (header, encoding byte, BOMs, strings,
+/// terminators and padding) so tagger developers can verify their output.
+use crate::id3v2_text_encoding::{TextEncoding, decode_text_with_encoding_simple, get_terminator_length};
+use crate::id3v2_tools::{describe_frame_flags, get_frame_description};
+use std::io::Write;
+
+/// Maximum number of bytes shown in the hex column of a single field
+const MAX_HEX_BYTES: usize = 8;
+
+/// Explain every frame matching `frame_id`, including frames embedded in CHAP/CTOC
+///
+/// `raw` must contain the complete frame (10-byte header followed by the payload).
+pub fn explain_matching_frames(output: &mut dyn Write, raw: &[u8], frame: &Id3v2Frame, frame_id: &str, version_major: u8, indentation: &str) -> std::io::Result<()> {
+    if frame.id.eq_ignore_ascii_case(frame_id) {
+        return explain_frame(output, raw, frame, version_major, indentation);
+    }
+
+    let sub_frames = match &frame.content {
+        | Some(Id3v2FrameContent::Chapter(chapter)) => &chapter.sub_frames,
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => &toc.sub_frames,
+        | _ => return Ok(()),
+    };
+
+    if let Some(start) = embedded_frames_start(frame) {
+        for sub_frame in sub_frames {
+            if let Some(sub_raw) = embedded_frame_bytes(raw, start, sub_frame) {
+                explain_matching_frames(output, sub_raw, sub_frame, frame_id, version_major, indentation)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Explain a single frame field by field
+pub fn explain_frame(output: &mut dyn Write, raw: &[u8], frame: &Id3v2Frame, version_major: u8, indentation: &str) -> std::io::Result<()> {
+    if raw.len() < 10 {
+        return writeln!(output, "{}Cannot explain frame '{}': header truncated", indentation, frame.id);
+    }
+
+    let payload = &raw[10..];
+    writeln!(output, "{}Explain frame {} ({}), {} bytes payload, {} bytes total:", indentation, frame.id, get_frame_description(&frame.id), payload.len(), raw.len())?;
+
+    let field_indentation = format!("{}  ", indentation);
+    let mut explainer = FieldWriter { output, indentation: &field_indentation, base: 0 };
+    explain_header(&mut explainer, &raw[0..10], version_major)?;
+
+    explainer.base = 10;
+    explain_payload(&mut explainer, payload, frame, version_major)?;
+
+    writeln!(explainer.output)
+}
+
+/// Writes annotated byte ranges relative to the start of the frame
+struct FieldWriter<'a> {
+    output: &'a mut dyn Write,
+    indentation: &'a str,
+    /// Offset of the current data slice relative to the start of the frame
+    base: usize,
+}
+
+impl FieldWriter<'_> {
+    fn field(&mut self, offset: usize, bytes: &[u8], label: &str) -> std::io::Result<()> {
+        let mut hex = bytes.iter().take(MAX_HEX_BYTES).map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        if bytes.len() > MAX_HEX_BYTES {
+            hex.push_str(" ..");
+        }
+        writeln!(self.output, "{}+0x{:04X}  {:<27}  {}", self.indentation, self.base + offset, hex, label)
+    }
+}
+
+fn explain_header(fw: &mut FieldWriter<'_>, header: &[u8], version_major: u8) -> std::io::Result<()> {
+    fw.field(0, &header[0..4], &format!("Frame ID: \"{}\"", crate::id3v2_tools::frame_id_to_string(&header[0..4])))?;
+
+    if version_major == 4 {
+        let size = crate::id3v2_tools::decode_synchsafe_int(&header[4..8]);
+        if header[4..8].iter().any(|b| b & 0x80 != 0) {
+            fw.field(
+                4,
+                &header[4..8],
+                &format!("Size: {} (synchsafe, INVALID: MSB set, big-endian would be {})", size, u32::from_be_bytes([header[4], header[5], header[6], header[7]])),
+            )?;
+        } else {
+            fw.field(4, &header[4..8], &format!("Size: {} (synchsafe)", size))?;
+        }
+    } else {
+        fw.field(4, &header[4..8], &format!("Size: {} (32-bit big-endian)", u32::from_be_bytes([header[4], header[5], header[6], header[7]])))?;
+    }
+
+    let flags = u16::from_be_bytes([header[8], header[9]]);
+    let flag_names = describe_frame_flags(flags, version_major);
+    if flag_names.is_empty() {
+        fw.field(8, &header[8..10], &format!("Flags: 0x{:04X}", flags))
+    } else {
+        fw.field(8, &header[8..10], &format!("Flags: 0x{:04X} ({})", flags, flag_names.join(", ")))
+    }
+}
+
+/// Explain the bytes added by the format flags, then the content
+fn explain_payload(fw: &mut FieldWriter<'_>, payload: &[u8], frame: &Id3v2Frame, version_major: u8) -> std::io::Result<()> {
+    let format = &frame.format;
+    if format.is_plain() {
+        return explain_content(fw, payload, frame, version_major);
+    }
+
+    let group = format.group_id.map(|group_id| (1, format!("Group identifier: 0x{:02X}", group_id)));
+    let encryption = format.encryption_method.map(|method| (1, format!("Encryption method: 0x{:02X}", method)));
+    let additions = if version_major == 4 {
+        [group, encryption, format.data_length.map(|length| (4, format!("Data length indicator: {} (synchsafe)", length)))]
+    } else {
+        [format.data_length.map(|length| (4, format!("Decompressed size: {}", length))), encryption, group]
+    };
+    let mut pos = 0;
+    for (length, label) in additions.into_iter().flatten() {
+        if pos + length > payload.len() {
+            break;
+        }
+        fw.field(pos, &payload[pos..pos + length], &label)?;
+        pos += length;
+    }
+
+    if format.encryption_method.is_some() {
+        return explain_binary(fw, payload, pos, "Encrypted content (not interpreted)");
+    }
+    if format.compressed || format.unsynchronised {
+        return explain_binary(fw, payload, pos, "Compressed or unsynchronised content (dissected after decoding)");
+    }
+    let base = fw.base;
+    fw.base += pos;
+    explain_content(fw, &payload[pos..], frame, version_major)?;
+    fw.base = base;
+    Ok(())
+}
+
+fn explain_content(fw: &mut FieldWriter<'_>, payload: &[u8], frame: &Id3v2Frame, version_major: u8) -> std::io::Result<()> {
+    match &frame.content {
+        | Some(Id3v2FrameContent::Text(_)) => {
+            let encoding = explain_encoding_byte(fw, payload)?;
+            explain_string_list(fw, payload, 1, encoding)
+        }
+        | Some(Id3v2FrameContent::Url(_)) => {
+            let url_end = payload.iter().position(|&b| b == 0).unwrap_or(payload.len());
+            fw.field(0, &payload[..url_end], &format!("URL ({} bytes, ISO-8859-1): \"{}\"", url_end, decode_text(&payload[..url_end], TextEncoding::Iso88591)))?;
+            explain_padding(fw, payload, url_end)
+        }
+        | Some(Id3v2FrameContent::UserText(_)) => {
+            let encoding = explain_encoding_byte(fw, payload)?;
+            let pos = explain_string(fw, payload, 1, encoding, "Description")?;
+            explain_string_list(fw, payload, pos, encoding)
+        }
+        | Some(Id3v2FrameContent::UserUrl(_)) => {
+            let encoding = explain_encoding_byte(fw, payload)?;
+            let pos = explain_string(fw, payload, 1, encoding, "Description")?;
+            let pos = explain_string(fw, payload, pos, TextEncoding::Iso88591, "URL")?;
+            explain_padding(fw, payload, pos)
+        }
+        | Some(Id3v2FrameContent::Comment(_)) => {
+            let encoding = explain_encoding_byte(fw, payload)?;
+            fw.field(1, &payload[1..4], &format!("Language: \"{}\"", String::from_utf8_lossy(&payload[1..4])))?;
+            let pos = explain_string(fw, payload, 4, encoding, "Description")?;
+            let pos = explain_string(fw, payload, pos, encoding, "Text")?;
+            explain_padding(fw, payload, pos)
+        }
+        | Some(Id3v2FrameContent::SyncedLyrics(synced_lyrics)) => {
+            let encoding = explain_encoding_byte(fw, payload)?;
+            if payload.len() < 6 {
+                return explain_binary(fw, payload, 1, "Truncated data");
+            }
+            fw.field(1, &payload[1..4], &format!("Language: \"{}\"", String::from_utf8_lossy(&payload[1..4])))?;
+            fw.field(4, &payload[4..5], &format!("Time stamp format: {}", synced_lyrics.timestamp_format))?;
+            fw.field(5, &payload[5..6], &format!("Content type: {} ({})", synced_lyrics.content_type, synced_lyrics.content_type_description()))?;
+            let mut pos = explain_string(fw, payload, 6, encoding, "Description")?;
+            for i in 0..synced_lyrics.entries.len() {
+                pos = explain_string(fw, payload, pos, encoding, &format!("Text [{}]", i + 1))?;
+                if pos + 4 > payload.len() {
+                    return explain_binary(fw, payload, pos, "Truncated data");
+                }
+                let timestamp = u32::from_be_bytes([payload[pos], payload[pos + 1], payload[pos + 2], payload[pos + 3]]);
+                fw.field(pos, &payload[pos..pos + 4], &format!("Time stamp [{}]: {}", i + 1, timestamp))?;
+                pos += 4;
+            }
+            explain_padding(fw, payload, pos)
+        }
+        | Some(Id3v2FrameContent::Picture(picture)) => {
+            let encoding = explain_encoding_byte(fw, payload)?;
+            let pos = explain_string(fw, payload, 1, TextEncoding::Iso88591, "MIME type")?;
+            if pos < payload.len() {
+                fw.field(pos, &payload[pos..pos + 1], &format!("Picture type: {} ({})", picture.picture_type, picture.picture_type_description()))?;
+            }
+            let pos = explain_string(fw, payload, pos + 1, encoding, "Description")?;
+            explain_binary(fw, payload, pos, "Picture data")
+        }
+        | Some(Id3v2FrameContent::GeneralObject(_)) => {
+            let encoding = explain_encoding_byte(fw, payload)?;
+            let pos = explain_string(fw, payload, 1, TextEncoding::Iso88591, "MIME type")?;
+            let pos = explain_string(fw, payload, pos, encoding, "Filename")?;
+            let pos = explain_string(fw, payload, pos, encoding, "Description")?;
+            explain_binary(fw, payload, pos, "Object data")
+        }
+        | Some(Id3v2FrameContent::UniqueFileId(_)) => {
+            let pos = explain_string(fw, payload, 0, TextEncoding::Iso88591, "Owner identifier")?;
+            explain_binary(fw, payload, pos, "Identifier")
+        }
+        | Some(Id3v2FrameContent::Private(_)) => {
+            let pos = explain_string(fw, payload, 0, TextEncoding::Iso88591, "Owner identifier")?;
+            explain_binary(fw, payload, pos, "Private data")
+        }
+        | Some(Id3v2FrameContent::Chapter(_)) => {
+            let pos = explain_string(fw, payload, 0, TextEncoding::Iso88591, "Element ID")?;
+            let labels = ["Start time (ms)", "End time (ms)", "Start offset", "End offset"];
+            for (i, label) in labels.iter().enumerate() {
+                let field_pos = pos + i * 4;
+                if field_pos + 4 > payload.len() {
+                    return explain_binary(fw, payload, field_pos, "Truncated data");
+                }
+                let value = u32::from_be_bytes([payload[field_pos], payload[field_pos + 1], payload[field_pos + 2], payload[field_pos + 3]]);
+                if value == 0xFFFFFFFF {
+                    fw.field(field_pos, &payload[field_pos..field_pos + 4], &format!("{}: unused (0xFFFFFFFF)", label))?;
+                } else {
+                    fw.field(field_pos, &payload[field_pos..field_pos + 4], &format!("{}: {}", label, value))?;
+                }
+            }
+            explain_sub_frames(fw, payload, frame, version_major)
+        }
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => {
+            let mut pos = explain_string(fw, payload, 0, TextEncoding::Iso88591, "Element ID")?;
+            if pos + 2 > payload.len() {
+                return explain_binary(fw, payload, pos, "Truncated data");
+            }
+            fw.field(pos, &payload[pos..pos + 1], &format!("TOC flags: 0x{:02X} (top-level: {}, ordered: {})", payload[pos], toc.top_level, toc.ordered))?;
+            fw.field(pos + 1, &payload[pos + 1..pos + 2], &format!("Entry count: {}", payload[pos + 1]))?;
+            pos += 2;
+            for i in 0..toc.child_count() {
+                pos = explain_string(fw, payload, pos, TextEncoding::Iso88591, &format!("Child element ID [{}]", i + 1))?;
+            }
+            explain_sub_frames(fw, payload, frame, version_major)
+        }
+        | Some(Id3v2FrameContent::Binary | Id3v2FrameContent::TooDeep { .. }) | None => explain_binary(fw, payload, 0, "Frame data (not interpreted)"),
+    }
+}
+
+/// Explain the leading text encoding byte and return the decoded encoding
+fn explain_encoding_byte(fw: &mut FieldWriter<'_>, payload: &[u8]) -> std::io::Result<TextEncoding> {
+    // Parsed frames always have a valid encoding byte, fall back to ISO-8859-1 for safety
+    let encoding = payload.first().and_then(|&b| TextEncoding::from_byte(b).ok()).unwrap_or(TextEncoding::Iso88591);
+    if !payload.is_empty() {
+        fw.field(0, &payload[0..1], &format!("Text encoding: {}", encoding))?;
+    }
+    Ok(encoding)
+}
+
+/// Explain all remaining null-separated strings of a text frame, then any padding
+fn explain_string_list(fw: &mut FieldWriter<'_>, payload: &[u8], start: usize, encoding: TextEncoding) -> std::io::Result<()> {
+    let mut pos = start;
+    let mut index = 1;
+
+    while pos < payload.len() {
+        // Once at least one string has been seen, trailing null bytes are padding
+        if index > 1 && payload[pos..].iter().all(|&b| b == 0) {
+            return explain_padding(fw, payload, pos);
+        }
+        pos = explain_string(fw, payload, pos, encoding, &format!("String [{}]", index))?;
+        index += 1;
+    }
+
+    Ok(())
+}
+
+/// Explain a single (optionally BOM-prefixed) string and its terminator, returning the position after it
+fn explain_string(fw: &mut FieldWriter<'_>, payload: &[u8], start: usize, encoding: TextEncoding, label: &str) -> std::io::Result<usize> {
+    if start >= payload.len() {
+        fw.field(start, &[], &format!("{}: missing", label))?;
+        return Ok(start);
+    }
+
+    let mut pos = start;
+    if encoding == TextEncoding::Utf16Bom && pos + 2 <= payload.len() {
+        match (payload[pos], payload[pos + 1]) {
+            | (0xFF, 0xFE) => {
+                fw.field(pos, &payload[pos..pos + 2], "BOM: little-endian")?;
+                pos += 2;
+            }
+            | (0xFE, 0xFF) => {
+                fw.field(pos, &payload[pos..pos + 2], "BOM: big-endian")?;
+                pos += 2;
+            }
+            | _ => {}
+        }
+    }
+
+    let terminator_len = get_terminator_length(encoding);
+    let text_end = find_aligned_terminator(payload, pos, terminator_len);
+    let text_bytes = &payload[pos..text_end];
+    let decoded = if text_end > start && pos > start {
+        // Decode including the BOM so the endianness is honored
+        decode_text(&payload[start..text_end], encoding)
+    } else {
+        decode_text(text_bytes, encoding)
+    };
+    fw.field(pos, text_bytes, &format!("{} ({} bytes): \"{}\"", label, text_bytes.len(), decoded))?;
+
+    if text_end + terminator_len <= payload.len() {
+        fw.field(text_end, &payload[text_end..text_end + terminator_len], "Terminator")?;
+        Ok(text_end + terminator_len)
+    } else {
+        if text_end < payload.len() {
+            fw.field(text_end, &payload[text_end..], "Incomplete terminator")?;
+        }
+        Ok(payload.len())
+    }
+}
+
+/// Explain trailing null bytes (or trailing junk) after the last field
+fn explain_padding(fw: &mut FieldWriter<'_>, payload: &[u8], start: usize) -> std::io::Result<()> {
+    if start >= payload.len() {
+        return Ok(());
+    }
+    let rest = &payload[start..];
+    if rest.iter().all(|&b| b == 0) {
+        fw.field(start, rest, &format!("Padding ({} null bytes)", rest.len()))
+    } else {
+        fw.field(start, rest, &format!("Trailing data ({} bytes)", rest.len()))
+    }
+}
+
+/// Explain an opaque binary field that runs to the end of the payload
+fn explain_binary(fw: &mut FieldWriter<'_>, payload: &[u8], start: usize, label: &str) -> std::io::Result<()> {
+    if start >= payload.len() {
+        return fw.field(start, &[], &format!("{}: empty", label));
+    }
+    fw.field(start, &payload[start..], &format!("{} ({} bytes)", label, payload.len() - start))
+}
+
+/// Explain the embedded sub-frames of a CHAP or CTOC frame, one after another
+fn explain_sub_frames(fw: &mut FieldWriter<'_>, payload: &[u8], frame: &Id3v2Frame, version_major: u8) -> std::io::Result<()> {
+    let sub_frames = match &frame.content {
+        | Some(Id3v2FrameContent::Chapter(chapter)) => &chapter.sub_frames,
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => &toc.sub_frames,
+        | _ => return Ok(()),
+    };
+    let Some(start) = embedded_frames_start(frame) else {
+        return Ok(());
+    };
+
+    let mut end = start;
+    for sub_frame in sub_frames {
+        let Some(offset) = sub_frame.offset else {
+            continue;
+        };
+        let sub_start = start + offset;
+        let Ok(range) = data_range("sub-frame", sub_start, 10, sub_frame.size as u64, payload.len()) else {
+            break;
+        };
+
+        writeln!(fw.output, "{}Embedded frame {} at +0x{:04X}:", fw.indentation, sub_frame.id, fw.base + sub_start)?;
+        let nested_indentation = format!("{}  ", fw.indentation);
+        let mut nested = FieldWriter { output: &mut *fw.output, indentation: &nested_indentation, base: fw.base + sub_start };
+        explain_header(&mut nested, &payload[sub_start..sub_start + 10], version_major)?;
+        nested.base += 10;
+        explain_payload(&mut nested, &payload[range.clone()], sub_frame, version_major)?;
+        end = range.end;
+    }
+
+    explain_padding(fw, payload, end)
+}
+
+/// Offset of the embedded sub-frame area within a CHAP or CTOC payload
+fn embedded_frames_start(frame: &Id3v2Frame) -> Option<usize> {
+    // Element IDs are ISO-8859-1, so one character corresponds to one byte
+    match &frame.content {
+        | Some(Id3v2FrameContent::Chapter(chapter)) => Some(chapter.element_id.chars().count() + 1 + 16),
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => {
+            Some(toc.element_id.chars().count() + 1 + 2 + toc.child_element_ids.iter().map(|id| id.chars().count() + 1).sum::<usize>())
+        }
+        | _ => None,
+    }
+}
+
+/// Raw bytes (header and payload) of an embedded frame within its parent frame
+fn embedded_frame_bytes<'a>(parent_raw: &'a [u8], start: usize, sub_frame: &Id3v2Frame) -> Option<&'a [u8]> {
+    let sub_start = 10 + start + sub_frame.offset?;
+    let range = data_range("sub-frame", sub_start, 10, sub_frame.size as u64, parent_raw.len()).ok()?;
+    parent_raw.get(sub_start..range.end)
+}
+
+/// Find the next null terminator, stepping by the terminator width to stay aligned
+fn find_aligned_terminator(data: &[u8], start: usize, terminator_len: usize) -> usize {
+    let mut pos = start;
+    while pos + terminator_len <= data.len() {
+        if data[pos..pos + terminator_len].iter().all(|&b| b == 0) {
+            return pos;
+        }
+        pos += terminator_len;
+    }
+    data.len()
+}
+
+fn decode_text(data: &[u8], encoding: TextEncoding) -> String {
+    decode_text_with_encoding_simple(data, encoding).unwrap_or_else(|e| format!("<{}>", e))
+}
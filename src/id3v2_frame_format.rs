@@ -0,0 +1,148 @@
+/// Format flags of ID3v2 frames
+///
+/// Grouping identity, encryption and (ID3v2.4) data length indicator flags add bytes between
+/// the frame header and the content; compression (zlib) and the ID3v2.4 unsynchronisation flag
+/// change how the content is stored. The frame data keeps all of these bytes so a rewrite
+/// reproduces the frame, while the content is dissected from the decoded bytes.
+use crate::error::SupertoolError;
+use crate::id3v2_tools::{decode_synchsafe_int, remove_unsynchronization};
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Read;
+
+/// Limit for decompressed frame content without a size (there is always one in valid tags)
+const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Additions and transformations the format flags of a frame apply to its content
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameFormat {
+    /// Group identifier byte (grouping identity flag)
+    pub group_id: Option<u8>,
+    /// Encryption method byte (encryption flag); encrypted content is not dissected
+    pub encryption_method: Option<u8>,
+    /// Decompressed size (ID3v2.3 compression) or data length indicator (ID3v2.4)
+    pub data_length: Option<u32>,
+    /// Content is zlib-compressed
+    pub compressed: bool,
+    /// Content is unsynchronised (ID3v2.4 frame flag)
+    pub unsynchronised: bool,
+    /// Offset of the stored content within the frame data
+    pub content_start: usize,
+}
+
+impl FrameFormat {
+    /// Format flags of the given ID3v2 version (the status flags are not included)
+    pub fn flag_mask(version_major: u8) -> u16 {
+        if version_major == 4 {
+            0x004F
+        } else {
+            0x00E0
+        }
+    }
+
+    /// Read the format flags of a frame and the bytes they add in front of its `data`
+    ///
+    /// The additions follow the header in the order of their flags: ID3v2.3 writes the
+    /// decompressed size, the encryption method and the group identifier; ID3v2.4 writes the
+    /// group identifier, the encryption method and the data length indicator.
+    pub fn read(flags: u16, data: &[u8], version_major: u8) -> Result<Self, SupertoolError> {
+        let mut format = FrameFormat::default();
+        let mut take = |count: usize, name: &str| -> Result<&[u8], SupertoolError> {
+            let bytes = data.get(format.content_start..format.content_start + count).ok_or_else(|| SupertoolError::TruncatedFrame(format!("frame data too short for the {}", name)))?;
+            format.content_start += count;
+            Ok(bytes)
+        };
+
+        if version_major == 4 {
+            let group_id = if flags & 0x0040 != 0 {
+                Some(take(1, "group identifier")?[0])
+            } else {
+                None
+            };
+            let encryption_method = if flags & 0x0004 != 0 {
+                Some(take(1, "encryption method")?[0])
+            } else {
+                None
+            };
+            let data_length = if flags & 0x0001 != 0 {
+                Some(decode_synchsafe_int(take(4, "data length indicator")?))
+            } else {
+                None
+            };
+            format.group_id = group_id;
+            format.encryption_method = encryption_method;
+            format.data_length = data_length;
+            format.compressed = flags & 0x0008 != 0;
+            format.unsynchronised = flags & 0x0002 != 0;
+        } else {
+            let data_length = if flags & 0x0080 != 0 {
+                let bytes = take(4, "decompressed size")?;
+                Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            } else {
+                None
+            };
+            let encryption_method = if flags & 0x0040 != 0 {
+                Some(take(1, "encryption method")?[0])
+            } else {
+                None
+            };
+            let group_id = if flags & 0x0020 != 0 {
+                Some(take(1, "group identifier")?[0])
+            } else {
+                None
+            };
+            format.group_id = group_id;
+            format.encryption_method = encryption_method;
+            format.data_length = data_length;
+            format.compressed = flags & 0x0080 != 0;
+        }
+        Ok(format)
+    }
+
+    /// Whether the frame has no format flags set
+    pub fn is_plain(&self) -> bool {
+        *self == FrameFormat::default()
+    }
+
+    /// The content of a frame: its data without the additions, resynchronised and decompressed
+    pub fn content<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>, SupertoolError> {
+        if let Some(method) = self.encryption_method {
+            return Err(SupertoolError::UnsupportedFrame(format!("frame is encrypted (method 0x{:02X})", method)));
+        }
+        let mut content = Cow::Borrowed(&data[self.content_start.min(data.len())..]);
+        if self.unsynchronised {
+            content = Cow::Owned(remove_unsynchronization(&content));
+        }
+        if self.compressed {
+            let limit = self.data_length.map_or(MAX_DECOMPRESSED_SIZE, |length| length as u64);
+            let mut decompressed = Vec::new();
+            ZlibDecoder::new(&content[..]).take(limit).read_to_end(&mut decompressed).map_err(|error| SupertoolError::InvalidFrame(format!("cannot decompress frame: {}", error)))?;
+            content = Cow::Owned(decompressed);
+        }
+        Ok(content)
+    }
+}
+
+impl fmt::Display for FrameFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(group_id) = self.group_id {
+            parts.push(format!("group 0x{:02X}", group_id));
+        }
+        if let Some(method) = self.encryption_method {
+            parts.push(format!("encrypted (method 0x{:02X}, not dissected)", method));
+        }
+        if self.compressed {
+            parts.push("zlib-compressed".to_string());
+        }
+        if self.unsynchronised {
+            parts.push("unsynchronised".to_string());
+        }
+        if let Some(data_length) = self.data_length {
+            parts.push(format!("data length {} bytes", data_length));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
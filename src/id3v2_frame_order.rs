@@ -0,0 +1,113 @@
+/// Frame order of ID3v2 tags
+///
+/// The specifications do not prescribe an order, but some hardware players only look at the
+/// first frames or stop at large ones. The recommended order puts identifiers first, then the
+/// frames needed for playback (volume adjustment, seek tables, encryption and group
+/// registrations), the descriptive frames, the chapters and the pictures last.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::Id3v2Frame;
+
+/// Groups of frames in their recommended order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FrameGroup {
+    Identifier,
+    Playback,
+    Text,
+    Url,
+    Other,
+    TableOfContents,
+    Chapter,
+    Picture,
+}
+
+impl FrameGroup {
+    /// Group of a frame ID
+    pub fn of(frame_id: &str) -> Self {
+        match frame_id {
+            | "UFID" | "MCDI" => FrameGroup::Identifier,
+            | "RVA2" | "RVAD" | "XRVA" | "EQU2" | "EQUA" | "MLLT" | "SEEK" | "ASPI" | "ETCO" | "SYTC" | "RBUF" | "AENC" | "POSS" | "ENCR" | "GRID" => {
+                FrameGroup::Playback
+            }
+            | "CTOC" => FrameGroup::TableOfContents,
+            | "CHAP" => FrameGroup::Chapter,
+            | "APIC" => FrameGroup::Picture,
+            | id if id.starts_with('T') => FrameGroup::Text,
+            | id if id.starts_with('W') => FrameGroup::Url,
+            | _ => FrameGroup::Other,
+        }
+    }
+}
+
+/// Sort the frames into the recommended order, keeping the order within each group
+///
+/// Returns the number of frames that changed position.
+pub fn canonicalize(frames: &mut [Id3v2Frame]) -> usize {
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by_key(|&index| FrameGroup::of(&frames[index].id));
+    frames.sort_by_key(|frame| FrameGroup::of(&frame.id));
+    order.iter().enumerate().filter(|(position, index)| position != *index).count()
+}
+
+/// Compare the order of the top-level frames with the recommended order
+pub fn order_findings(frames: &[Id3v2Frame]) -> Vec<Finding> {
+    let groups: Vec<FrameGroup> = frames.iter().map(|frame| FrameGroup::of(&frame.id)).collect();
+    let mut findings = Vec::new();
+
+    for (index, frame) in frames.iter().enumerate() {
+        match groups[index] {
+            | FrameGroup::Identifier | FrameGroup::Playback => {
+                let Some(earlier) = (0..index).find(|&earlier| groups[earlier] > groups[index]) else {
+                    continue;
+                };
+                let (severity, recommendation) = if groups[index] == FrameGroup::Identifier {
+                    (Severity::Info, "identifiers are recommended before all other frames")
+                } else {
+                    (Severity::Warning, "frames needed for playback are recommended before the descriptive frames")
+                };
+                findings.push(Finding::new(
+                    severity,
+                    &frame.id,
+                    format!("at position {} follows {} at position {}; {}", index + 1, frames[earlier].id, earlier + 1, recommendation),
+                ));
+            }
+            | FrameGroup::Picture => {
+                let later = groups[index + 1..].iter().filter(|&&group| group < FrameGroup::Picture).count();
+                if later > 0 {
+                    findings.push(Finding::new(
+                        Severity::Info,
+                        &frame.id,
+                        format!("at position {} precedes {} other frame(s); pictures are recommended after all other frames", index + 1, later),
+                    ));
+                }
+            }
+            | _ => {}
+        }
+    }
+
+    let first_chapter = groups.iter().position(|&group| matches!(group, FrameGroup::TableOfContents | FrameGroup::Chapter));
+    let last_playback = groups.iter().rposition(|&group| group == FrameGroup::Playback);
+    if let (Some(chapter), Some(playback)) = (first_chapter, last_playback) {
+        if chapter < playback {
+            findings.push(Finding::new(
+                Severity::Warning,
+                &frames[chapter].id,
+                format!("chapter frames precede the playback frame {} at position {}", frames[playback].id, playback + 1),
+            ));
+        } else {
+            findings.push(Finding::new(Severity::Info, &frames[chapter].id, "chapter frames follow the playback frames"));
+        }
+    }
+    findings
+}
+
+/// Print the frame order findings, if there are any
+pub fn print_order(frames: &[Id3v2Frame]) {
+    let findings = order_findings(frames);
+    if findings.is_empty() {
+        return;
+    }
+    outln!("\nFrame Order:");
+    for finding in &findings {
+        outln!("    {}", finding);
+    }
+}
@@ -0,0 +1,124 @@
+/// General Encapsulated Object Frame (GEOB)
+///
+/// Structure: Text encoding + MIME type + Filename + Content description + Encapsulated object
+///
+/// Applications store arbitrary files in GEOB frames: DJ software its cue points and beat
+/// grids, taggers the original lyrics or license files. `debug --extract-geob` writes them
+/// back out under their original filenames. Serato hot cues, loops and beatgrids are decoded.
+use crate::error::SupertoolError;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_text_encoding::{TextEncoding, decode_iso88591_string, decode_text_with_encoding_simple, encode_text, get_terminator_length, is_null_terminator, terminator_bytes};
+use crate::media_source::MediaSource;
+use crate::path_display::{display_path, safe_filename, unused_path};
+use crate::serato::SeratoData;
+use crate::text_truncate::truncate_text;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneralObjectFrame {
+    pub encoding: TextEncoding,
+    pub mime_type: String,
+    pub filename: String,
+    pub description: String,
+    pub object_data: Vec<u8>,
+}
+
+impl GeneralObjectFrame {
+    /// Parse a GEOB frame from raw data
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
+        if data.is_empty() {
+            return Err(SupertoolError::TruncatedFrame("General object frame data too short".to_string()));
+        }
+
+        let encoding = TextEncoding::from_byte(data[0])?;
+
+        // MIME type (null-terminated, ISO-8859-1)
+        let Some(mime_length) = data[1..].iter().position(|&byte| byte == 0) else {
+            return Err(SupertoolError::TruncatedFrame("General object frame MIME type not null-terminated".to_string()));
+        };
+        let mime_type = decode_iso88591_string(&data[1..1 + mime_length]);
+        let rest = &data[1 + mime_length + 1..];
+
+        // Filename and description (null-terminated, according to encoding)
+        let (filename, rest) = split_terminated(rest, encoding).ok_or_else(|| SupertoolError::TruncatedFrame("General object frame filename not properly terminated".to_string()))?;
+        let filename = decode_text_with_encoding_simple(filename, encoding)?;
+        let (description, object_data) = split_terminated(rest, encoding).ok_or_else(|| SupertoolError::TruncatedFrame("General object frame description not properly terminated".to_string()))?;
+        let description = decode_text_with_encoding_simple(description, encoding)?;
+
+        Ok(GeneralObjectFrame { encoding, mime_type, filename, description, object_data: object_data.to_vec() })
+    }
+
+    /// Serialize the frame content (encoding, MIME type, filename, description, object data)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.encoding as u8];
+        bytes.extend(encode_text(&self.mime_type, TextEncoding::Iso88591));
+        bytes.push(0);
+        bytes.extend(encode_text(&self.filename, self.encoding));
+        bytes.extend_from_slice(terminator_bytes(self.encoding));
+        bytes.extend(encode_text(&self.description, self.encoding));
+        bytes.extend_from_slice(terminator_bytes(self.encoding));
+        bytes.extend_from_slice(&self.object_data);
+        bytes
+    }
+
+    /// The decoded object of a Serato GEOB frame ("Serato Markers_", "Serato BeatGrid")
+    pub fn serato(&self) -> Option<Result<SeratoData, String>> {
+        SeratoData::parse(&self.description, &self.object_data)
+    }
+}
+
+/// Split at the first terminator; UTF-16 terminators only count at even offsets
+fn split_terminated(data: &[u8], encoding: TextEncoding) -> Option<(&[u8], &[u8])> {
+    let terminator_len = get_terminator_length(encoding);
+    (0..data.len().saturating_sub(terminator_len - 1))
+        .step_by(terminator_len)
+        .find(|&pos| is_null_terminator(&data[pos..pos + terminator_len], encoding))
+        .map(|pos| (&data[..pos], &data[pos + terminator_len..]))
+}
+
+impl fmt::Display for GeneralObjectFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Encoding: {}", self.encoding)?;
+        writeln!(f, "MIME type: {}", self.mime_type)?;
+        if !self.filename.is_empty() {
+            writeln!(f, "Filename: \"{}\"", self.filename)?;
+        }
+        if !self.description.is_empty() {
+            writeln!(f, "Description: \"{}\"", truncate_text(&self.description))?;
+        }
+        writeln!(f, "Object size: {} bytes", self.object_data.len())?;
+        match self.serato() {
+            | Some(Ok(data)) => write!(f, "{}", data)?,
+            | Some(Err(error)) => writeln!(f, "WARNING: Invalid Serato data: {}", error)?,
+            | None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Write the objects of all GEOB frames (including CHAP/CTOC sub-frames) into `dir`
+///
+/// Files are named after the filename stored in the frame, reduced to its last path component;
+/// objects without a usable filename are written as `geob-N.bin`. Existing files are never
+/// overwritten: a number is appended to the name instead.
+pub fn extract_general_objects(file: &mut dyn MediaSource, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let tag = Id3v2Tag::read(file)?;
+    let objects = tag.as_ref().map(Id3v2Tag::general_objects).unwrap_or_default();
+    outln!("\nGEOB Objects:");
+    if objects.is_empty() {
+        outln!("    No GEOB frames found");
+        return Ok(());
+    }
+
+    fs::create_dir_all(dir)?;
+    for (index, (location, object)) in objects.iter().enumerate() {
+        let name = safe_filename(&object.filename).unwrap_or_else(|| format!("geob-{}.bin", index + 1));
+        let output = unused_path(dir, &name);
+        fs::write(&output, &object.object_data)?;
+        outln!("    {}: {} ({}, {} bytes)", location, display_path(&output), object.mime_type, object.object_data.len());
+    }
+    Ok(())
+}
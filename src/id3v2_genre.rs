@@ -0,0 +1,127 @@
+/// Genre (TCON) splitting and joining
+///
+/// Normalizes the different ways several genres end up in a TCON frame into a plain list:
+/// ID3v2.3 references with refinement ("(17)(4)Eurodisco"), slash-separated text ("Rock/Pop")
+/// and ID3v2.4 null-separated values (plain names or numeric ID3v1 genres), and renders
+/// such a list back in any of these styles.
+use crate::id3v2_text_frame::ID3V2_3_VALUE_SEPARATOR;
+
+/// ID3v1 genre names indexed by genre number (including the Winamp extensions)
+pub const ID3V1_GENRES: [&str; 192] = [
+    "Blues", "Classic Rock", "Country", "Dance", "Disco", "Funk", "Grunge", "Hip-Hop", "Jazz", "Metal", "New Age", "Oldies", "Other", "Pop", "R&B", "Rap", "Reggae", "Rock",
+    "Techno", "Industrial", "Alternative", "Ska", "Death Metal", "Pranks", "Soundtrack", "Euro-Techno", "Ambient", "Trip-Hop", "Vocal", "Jazz+Funk", "Fusion", "Trance",
+    "Classical", "Instrumental", "Acid", "House", "Game", "Sound Clip", "Gospel", "Noise", "AlternRock", "Bass", "Soul", "Punk", "Space", "Meditative", "Instrumental Pop",
+    "Instrumental Rock", "Ethnic", "Gothic", "Darkwave", "Techno-Industrial", "Electronic", "Pop-Folk", "Eurodance", "Dream", "Southern Rock", "Comedy", "Cult", "Gangsta",
+    "Top 40", "Christian Rap", "Pop/Funk", "Jungle", "Native American", "Cabaret", "New Wave", "Psychedelic", "Rave", "Showtunes", "Trailer", "Lo-Fi", "Tribal", "Acid Punk",
+    "Acid Jazz", "Polka", "Retro", "Musical", "Rock & Roll", "Hard Rock", "Folk", "Folk-Rock", "National Folk", "Swing", "Fast Fusion", "Bebop", "Latin", "Revival", "Celtic",
+    "Bluegrass", "Avantgarde", "Gothic Rock", "Progressive Rock", "Psychedelic Rock", "Symphonic Rock", "Slow Rock", "Big Band", "Chorus", "Easy Listening", "Acoustic",
+    "Humour", "Speech", "Chanson", "Opera", "Chamber Music", "Sonata", "Symphony", "Booty Bass", "Primus", "Porn Groove", "Satire", "Slow Jam", "Club", "Tango", "Samba",
+    "Folklore", "Ballad", "Power Ballad", "Rhythmic Soul", "Freestyle", "Duet", "Punk Rock", "Drum Solo", "A capella", "Euro-House", "Dance Hall", "Goa", "Drum & Bass",
+    "Club-House", "Hardcore Techno", "Terror", "Indie", "BritPop", "Negerpunk", "Polsk Punk", "Beat", "Christian Gangsta Rap", "Heavy Metal", "Black Metal", "Crossover",
+    "Contemporary Christian", "Christian Rock", "Merengue", "Salsa", "Thrash Metal", "Anime", "Jpop", "Synthpop", "Abstract", "Art Rock", "Baroque", "Bhangra", "Big Beat",
+    "Breakbeat", "Chillout", "Downtempo", "Dub", "EBM", "Eclectic", "Electro", "Electroclash", "Emo", "Experimental", "Garage", "Global", "IDM", "Illbient", "Industro-Goth",
+    "Jam Band", "Krautrock", "Leftfield", "Lounge", "Math Rock", "New Romantic", "Nu-Breakz", "Post-Punk", "Post-Rock", "Psytrance", "Shoegaze", "Space Rock", "Trop Rock",
+    "World Music", "Neoclassical", "Audiobook", "Audio Theatre", "Neue Deutsche Welle", "Podcast", "Indie Rock", "G-Funk", "Dubstep", "Garage Rock", "Psybient",
+];
+
+/// How a genre list is written into a TCON frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GenreStyle {
+    /// ID3v2.3 references with refinement text, e.g. "(17)(4)Eurodisco"
+    Id3v23,
+    /// A single slash-separated value, e.g. "Rock/Disco/Eurodisco"
+    Slash,
+    /// One value per genre (null-separated, ID3v2.4 only)
+    MultiValue,
+}
+
+/// Look up an ID3v1 genre name by number
+pub fn id3v1_genre_name(number: usize) -> Option<&'static str> {
+    ID3V1_GENRES.get(number).copied()
+}
+
+/// Look up the ID3v1 genre number of a name (case-insensitive)
+pub fn id3v1_genre_number(name: &str) -> Option<usize> {
+    ID3V1_GENRES.iter().position(|genre| genre.eq_ignore_ascii_case(name))
+}
+
+/// Normalize the values of a TCON frame into a list of genre names without duplicates
+pub fn parse_genres(values: &[String]) -> Vec<String> {
+    let mut genres: Vec<String> = Vec::new();
+    for value in values {
+        for genre in parse_genre_value(value) {
+            if !genres.iter().any(|existing| existing.eq_ignore_ascii_case(&genre)) {
+                genres.push(genre);
+            }
+        }
+    }
+    genres
+}
+
+/// Parse one TCON value: leading "(n)" references, refinement text and slash-separated names
+fn parse_genre_value(value: &str) -> Vec<String> {
+    let mut genres = Vec::new();
+    let mut rest = value.trim();
+
+    // Leading references; "((" starts refinement text that itself begins with "("
+    while rest.starts_with('(') && !rest.starts_with("((") {
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        let reference = &rest[1..end];
+        match reference {
+            | "RX" => genres.push("Remix".to_string()),
+            | "CR" => genres.push("Cover".to_string()),
+            | _ => match reference.parse::<usize>().ok().and_then(id3v1_genre_name) {
+                | Some(name) => genres.push(name.to_string()),
+                | None => break,
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+
+    let refinement = if rest.starts_with("((") {
+        &rest[1..]
+    } else {
+        rest
+    };
+    for part in refinement.split(ID3V2_3_VALUE_SEPARATOR) {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        // ID3v2.4 allows plain ID3v1 genre numbers as values
+        match part.parse::<usize>().ok().and_then(id3v1_genre_name) {
+            | Some(name) => genres.push(name.to_string()),
+            | None => genres.push(part.to_string()),
+        }
+    }
+
+    genres
+}
+
+/// Render a genre list as TCON values in the given style
+pub fn format_genres(genres: &[String], style: GenreStyle) -> Vec<String> {
+    match style {
+        | GenreStyle::MultiValue => genres.to_vec(),
+        | GenreStyle::Slash => vec![genres.join(ID3V2_3_VALUE_SEPARATOR)],
+        | GenreStyle::Id3v23 => {
+            let mut references = String::new();
+            let mut refinements = Vec::new();
+            for genre in genres {
+                match id3v1_genre_number(genre) {
+                    | Some(number) => references.push_str(&format!("({})", number)),
+                    | None if genre == "Remix" => references.push_str("(RX)"),
+                    | None if genre == "Cover" => references.push_str("(CR)"),
+                    | None => refinements.push(genre.as_str()),
+                }
+            }
+            let refinement = refinements.join(ID3V2_3_VALUE_SEPARATOR);
+            if refinement.starts_with('(') {
+                references.push('(');
+            }
+            references.push_str(&refinement);
+            vec![references]
+        }
+    }
+}
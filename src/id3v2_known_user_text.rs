@@ -0,0 +1,229 @@
+/// Interpretation of well-known TXXX descriptions
+///
+/// Taggers store many standardized fields as user-defined text frames: ReplayGain values,
+/// MusicBrainz and AcoustID identifiers, barcodes, ISRCs, catalog numbers and the compilation flag.
+/// iTunes uses comment frames instead for its normalization (iTunNORM) and gapless (iTunPGAP)
+/// data. This module maps those descriptions to typed, validated values.
+use crate::release_codes::{check_isrc, validate_barcode};
+use serde::Serialize;
+use std::fmt;
+
+/// How the value of a well-known TXXX frame is interpreted
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KnownKind {
+    /// Gain or loudness in dB ("-6.48 dB")
+    Decibels,
+    /// Linear peak amplitude (1.0 = full scale)
+    Peak,
+    /// One or more UUIDs
+    Uuid,
+    /// UPC/EAN/GTIN barcode with check digit
+    Barcode,
+    /// International Standard Recording Code
+    Isrc,
+    /// AcoustID fingerprint (URL-safe base64)
+    Fingerprint,
+    /// Boolean flag ("1"/"0")
+    Flag,
+    /// iTunes Sound Check values (ten hexadecimal numbers)
+    ItunesNormalization,
+    /// Free text
+    Text,
+}
+
+/// Well-known TXXX descriptions (matched case-insensitively), their canonical keys and kinds
+const KNOWN_DESCRIPTIONS: &[(&str, &str, KnownKind)] = &[
+    ("REPLAYGAIN_TRACK_GAIN", "replaygain_track_gain", KnownKind::Decibels),
+    ("REPLAYGAIN_TRACK_PEAK", "replaygain_track_peak", KnownKind::Peak),
+    ("REPLAYGAIN_ALBUM_GAIN", "replaygain_album_gain", KnownKind::Decibels),
+    ("REPLAYGAIN_ALBUM_PEAK", "replaygain_album_peak", KnownKind::Peak),
+    ("REPLAYGAIN_REFERENCE_LOUDNESS", "replaygain_reference_loudness", KnownKind::Decibels),
+    ("MusicBrainz Album Id", "musicbrainz_album_id", KnownKind::Uuid),
+    ("MusicBrainz Artist Id", "musicbrainz_artist_id", KnownKind::Uuid),
+    ("MusicBrainz Album Artist Id", "musicbrainz_album_artist_id", KnownKind::Uuid),
+    ("MusicBrainz Release Group Id", "musicbrainz_release_group_id", KnownKind::Uuid),
+    ("MusicBrainz Release Track Id", "musicbrainz_release_track_id", KnownKind::Uuid),
+    ("MusicBrainz Work Id", "musicbrainz_work_id", KnownKind::Uuid),
+    ("MusicBrainz Original Album Id", "musicbrainz_original_album_id", KnownKind::Uuid),
+    ("MusicBrainz Original Artist Id", "musicbrainz_original_artist_id", KnownKind::Uuid),
+    ("MusicBrainz TRM Id", "musicbrainz_trm_id", KnownKind::Uuid),
+    ("MusicBrainz Disc Id", "musicbrainz_disc_id", KnownKind::Text),
+    ("MusicBrainz Album Type", "musicbrainz_album_type", KnownKind::Text),
+    ("MusicBrainz Album Status", "musicbrainz_album_status", KnownKind::Text),
+    ("MusicBrainz Album Release Country", "musicbrainz_album_release_country", KnownKind::Text),
+    ("Acoustid Id", "acoustid_id", KnownKind::Uuid),
+    ("Acoustid Fingerprint", "acoustid_fingerprint", KnownKind::Fingerprint),
+    ("BARCODE", "barcode", KnownKind::Barcode),
+    ("UPC", "barcode", KnownKind::Barcode),
+    ("EAN", "barcode", KnownKind::Barcode),
+    ("ISRC", "isrc", KnownKind::Isrc),
+    ("CATALOGNUMBER", "catalog_number", KnownKind::Text),
+    ("COMPILATION", "compilation", KnownKind::Flag),
+];
+
+/// Well-known COMM descriptions written by iTunes, their canonical keys and kinds
+const KNOWN_COMMENT_DESCRIPTIONS: &[(&str, &str, KnownKind)] = &[
+    ("iTunNORM", "itunes_normalization", KnownKind::ItunesNormalization),
+    ("iTunPGAP", "itunes_gapless", KnownKind::Flag),
+];
+
+/// Typed value of a well-known TXXX frame
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum KnownValue {
+    /// Gain or loudness in dB
+    Decibels(f64),
+    /// Linear peak amplitude
+    Peak(f64),
+    /// Validated identifiers (lowercase UUIDs), barcode or ISRC
+    Identifiers(Vec<String>),
+    /// Boolean flag
+    Flag(bool),
+    /// iTunes Sound Check normalization
+    Normalization(ItunesNormalization),
+    /// Free text
+    Text(String),
+}
+
+/// Decoded iTunNORM values
+///
+/// Of the ten values only the gains and peaks are documented by observation: values 1/2 hold
+/// the adjustment per channel as a power ratio in 1/1000 W (3/4 the same in 1/2500 W),
+/// values 7/8 the peak sample per channel on a 16-bit scale.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ItunesNormalization {
+    /// Gain of the left channel in dB
+    pub gain_left_db: f64,
+    /// Gain of the right channel in dB
+    pub gain_right_db: f64,
+    /// Peak amplitude of the left channel (1.0 = full scale)
+    pub peak_left: f64,
+    /// Peak amplitude of the right channel (1.0 = full scale)
+    pub peak_right: f64,
+}
+
+impl fmt::Display for KnownValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | KnownValue::Decibels(db) => write!(f, "{:+.2} dB", db),
+            | KnownValue::Peak(peak) => write!(f, "{:.6}", peak),
+            | KnownValue::Identifiers(ids) => write!(f, "{}", ids.join(", ")),
+            | KnownValue::Flag(true) => write!(f, "yes"),
+            | KnownValue::Flag(false) => write!(f, "no"),
+            | KnownValue::Normalization(norm) => {
+                write!(f, "{:+.2} dB / {:+.2} dB (L/R), peak {:.6} / {:.6}", norm.gain_left_db, norm.gain_right_db, norm.peak_left, norm.peak_right)
+            }
+            | KnownValue::Text(text) => write!(f, "\"{}\"", text),
+        }
+    }
+}
+
+/// A TXXX frame recognized by its description
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnownUserText {
+    /// Canonical key (e.g. "replaygain_track_gain")
+    pub key: &'static str,
+    /// Description as found in the frame
+    pub description: String,
+    /// Typed value, if the frame value is valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<KnownValue>,
+    /// Why the frame value is invalid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+}
+
+/// Interpret a TXXX frame if its description is well-known
+pub fn interpret_user_text(description: &str, value: &str) -> Option<KnownUserText> {
+    interpret(KNOWN_DESCRIPTIONS, description, value)
+}
+
+/// Interpret a COMM frame if its description is one of the iTunes data comments
+pub fn interpret_comment(description: &str, text: &str) -> Option<KnownUserText> {
+    interpret(KNOWN_COMMENT_DESCRIPTIONS, description, text)
+}
+
+fn interpret(known_descriptions: &[(&str, &'static str, KnownKind)], description: &str, value: &str) -> Option<KnownUserText> {
+    let (_, key, kind) = known_descriptions.iter().find(|(known, _, _)| known.eq_ignore_ascii_case(description.trim()))?;
+
+    let (value, problem) = match parse_value(*kind, value.trim()) {
+        | Ok(parsed) => (Some(parsed), None),
+        | Err(problem) => (None, Some(problem)),
+    };
+    Some(KnownUserText { key, description: description.to_string(), value, problem })
+}
+
+fn parse_value(kind: KnownKind, value: &str) -> Result<KnownValue, String> {
+    if value.is_empty() {
+        return Err("empty value".to_string());
+    }
+
+    match kind {
+        | KnownKind::Decibels => {
+            let number = value.strip_suffix("dB").or_else(|| value.strip_suffix("db")).unwrap_or(value).trim();
+            number
+                .parse::<f64>()
+                .ok()
+                .filter(|db| db.is_finite())
+                .map(KnownValue::Decibels)
+                .ok_or_else(|| format!("'{}' is not a dB value", value))
+        }
+        | KnownKind::Peak => match value.parse::<f64>() {
+            | Ok(peak) if peak.is_finite() && peak >= 0.0 => Ok(KnownValue::Peak(peak)),
+            | _ => Err(format!("'{}' is not a non-negative peak amplitude", value)),
+        },
+        | KnownKind::Uuid => {
+            // Several IDs (e.g. multiple artists) are separated by "/", ";" or null characters
+            let ids: Vec<String> = value.split(['/', ';', '\0']).map(str::trim).filter(|id| !id.is_empty()).map(str::to_lowercase).collect();
+            match ids.iter().find(|id| !is_uuid(id)) {
+                | Some(invalid) => Err(format!("'{}' is not a valid UUID", invalid)),
+                | None => Ok(KnownValue::Identifiers(ids)),
+            }
+        }
+        | KnownKind::Barcode => validate_barcode(value).map(|()| KnownValue::Identifiers(vec![value.to_string()])),
+        | KnownKind::Isrc => match check_isrc(value) {
+            | Some(problem) => Err(problem),
+            | None => Ok(KnownValue::Identifiers(vec![value.to_string()])),
+        },
+        | KnownKind::Fingerprint => {
+            if value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                Ok(KnownValue::Text(value.to_string()))
+            } else {
+                Err("fingerprint contains characters outside URL-safe base64".to_string())
+            }
+        }
+        | KnownKind::Flag => match value.to_ascii_lowercase().as_str() {
+            | "1" | "true" | "yes" => Ok(KnownValue::Flag(true)),
+            | "0" | "false" | "no" => Ok(KnownValue::Flag(false)),
+            | _ => Err(format!("'{}' is not a flag (expected 1 or 0)", value)),
+        },
+        | KnownKind::ItunesNormalization => parse_itunes_normalization(value).map(KnownValue::Normalization),
+        | KnownKind::Text => Ok(KnownValue::Text(value.to_string())),
+    }
+}
+
+fn parse_itunes_normalization(value: &str) -> Result<ItunesNormalization, String> {
+    let numbers: Vec<u32> = value
+        .split_whitespace()
+        .map(|number| u32::from_str_radix(number, 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("'{}' is not a list of hexadecimal numbers", value))?;
+    if numbers.len() != 10 {
+        return Err(format!("iTunNORM has {} values (expected 10)", numbers.len()));
+    }
+
+    // A power ratio of 1000 (1/1000 W) means no adjustment
+    let gain = |ratio: u32| -10.0 * (ratio.max(1) as f64 / 1000.0).log10();
+    let peak = |sample: u32| sample as f64 / 32768.0;
+    Ok(ItunesNormalization { gain_left_db: gain(numbers[0]), gain_right_db: gain(numbers[1]), peak_left: peak(numbers[6]), peak_right: peak(numbers[7]) })
+}
+
+/// Check for the 8-4-4-4-12 hexadecimal UUID layout
+fn is_uuid(id: &str) -> bool {
+    let groups: Vec<&str> = id.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
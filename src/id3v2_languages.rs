@@ -0,0 +1,52 @@
+/// Language inventory of COMM, USLT and SYLT frames and `--lang` selection
+use crate::id3v2_frame::Id3v2Frame;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Frames carrying one language
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageUsage {
+    /// ISO 639-2 language code as stored in the frames
+    pub language: String,
+    /// IDs of the frames in this language, in file order
+    pub frames: Vec<String>,
+}
+
+/// Languages of the COMM, USLT and SYLT frames, sorted by language code
+pub fn language_inventory(frames: &[Id3v2Frame]) -> Vec<LanguageUsage> {
+    let mut languages: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for frame in frames {
+        if let Some(language) = frame.language() {
+            languages.entry(language).or_default().push(frame.id.clone());
+        }
+    }
+    languages.into_iter().map(|(language, frames)| LanguageUsage { language: language.to_string(), frames }).collect()
+}
+
+/// Whether a frame language matches the selected one (codes are compared case-insensitively)
+pub fn language_matches(language: &str, selected: &str) -> bool {
+    language.eq_ignore_ascii_case(selected)
+}
+
+/// Print the language inventory if the tag has several languages or one was selected with `--lang`
+pub fn print_inventory(frames: &[Id3v2Frame], selected: Option<&str>) {
+    let inventory = language_inventory(frames);
+    if inventory.len() < 2 && selected.is_none() {
+        return;
+    }
+
+    outln!("\nLanguages:");
+    for usage in &inventory {
+        let marker = if selected.is_some_and(|selected| language_matches(&usage.language, selected)) {
+            " (selected)"
+        } else {
+            ""
+        };
+        outln!("    {}: {}{}", usage.language, usage.frames.join(", "), marker);
+    }
+    if let Some(selected) = selected
+        && !inventory.iter().any(|usage| language_matches(&usage.language, selected))
+    {
+        outln!("    No frames in language '{}'", selected);
+    }
+}
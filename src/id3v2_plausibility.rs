@@ -0,0 +1,117 @@
+/// Plausibility checks of frame contents
+///
+/// Taggers often write free text into frames that the specification defines as numbers: "3 of 12"
+/// in TRCK, "120.5" in TBPM, seconds instead of milliseconds in TLEN. Players then ignore the
+/// frame or show nonsense. These checks report such values, invalid ISRCs in TSRC, a TLEN that
+/// does not match the measured playing time of the MPEG audio, and text not stored in NFC.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_sort_order::sort_findings;
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::estimate_duration_ms;
+use crate::release_codes::check_isrc;
+use crate::time_format::format_duration;
+
+/// Difference between TLEN and the measured playing time that is tolerated (milliseconds)
+const TLEN_TOLERANCE_MS: u64 = 2000;
+
+/// Findings for the values of a numeric text frame or TSRC, and for text stored in a form other than NFC
+pub fn content_findings(frame: &Id3v2Frame, location: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if frame.stored_text_changes_under_nfc() {
+        findings.push(Finding::new(Severity::Warning, location, "text is not NFC-normalized (decomposed characters), so it compares and sorts apart from the same text in NFC"));
+    }
+    let Some(values) = frame.get_text_values() else {
+        return findings;
+    };
+    let check: fn(&str) -> Option<String> = match frame.id.as_str() {
+        | "TRCK" | "TPOS" => check_position,
+        | "TBPM" => |value| check_number(value, "beats per minute"),
+        | "TLEN" => |value| check_number(value, "milliseconds"),
+        | "TYER" => check_year,
+        | "TSRC" => check_isrc,
+        | _ => return findings,
+    };
+    findings.extend(values.iter().filter_map(|value| check(value)).map(|problem| Finding::new(Severity::Warning, location, problem)));
+    findings
+}
+
+/// Track or disc position: a number, optionally followed by "/" and the total
+fn check_position(value: &str) -> Option<String> {
+    let (number, total) = match value.split_once('/') {
+        | Some((number, total)) => (number, Some(total)),
+        | None => (value, None),
+    };
+    let (Some(number), Some(total)) = (parse_number(number), total.map(parse_number).unwrap_or(Some(0))) else {
+        return Some(format!("'{}' is not a number or a number/total pair (e.g. 3/12)", value));
+    };
+    if value != value.trim() {
+        return Some(format!("'{}' has surrounding spaces", value));
+    }
+    if number == 0 {
+        return Some(format!("'{}' counts from 0, positions start at 1", value));
+    }
+    if total > 0 && number > total {
+        return Some(format!("'{}' is past the total of {}", value, total));
+    }
+    None
+}
+
+fn check_number(value: &str, unit: &str) -> Option<String> {
+    if parse_number(value).is_some() && value == value.trim() {
+        return None;
+    }
+    Some(format!("'{}' is not a whole number of {}", value, unit))
+}
+
+fn check_year(value: &str) -> Option<String> {
+    if value.len() == 4 && value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("'{}' is not a four-digit year (a full date belongs into TDAT/TDRC)", value))
+}
+
+/// Digits only (no sign, no decimal point)
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.is_empty() || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Compare TLEN with the playing time measured from the MPEG audio
+pub fn duration_findings(frames: &[Id3v2Frame], file: &mut dyn MediaSource) -> Result<Vec<Finding>, Box<dyn std::error::Error>> {
+    let Some(tlen) = frames.iter().find(|frame| frame.id == "TLEN").and_then(|frame| frame.get_text()).and_then(parse_number) else {
+        return Ok(Vec::new());
+    };
+    let Some(measured) = estimate_duration_ms(file)? else {
+        return Ok(Vec::new());
+    };
+    if tlen.abs_diff(measured) <= TLEN_TOLERANCE_MS.max(measured / 50) {
+        return Ok(Vec::new());
+    }
+    let message = if tlen.saturating_mul(1000).abs_diff(measured) <= TLEN_TOLERANCE_MS {
+        format!("TLEN is {}, the audio plays {}; TLEN seems to be in seconds instead of milliseconds", tlen, format_duration(measured))
+    } else {
+        format!("TLEN gives {}, the audio plays {}", format_duration(tlen), format_duration(measured))
+    };
+    Ok(vec![Finding::new(Severity::Warning, "TLEN", message)])
+}
+
+/// Print the content and sort order findings of the frames and, for MP3 files, the TLEN comparison, if there are any
+pub fn print_plausibility(frames: &[Id3v2Frame], mp3_file: Option<&mut dyn MediaSource>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut findings: Vec<Finding> = frames.iter().flat_map(|frame| content_findings(frame, &frame.id)).collect();
+    findings.extend(sort_findings(frames));
+    if let Some(file) = mp3_file {
+        findings.extend(duration_findings(frames, file)?);
+    }
+    if findings.is_empty() {
+        return Ok(());
+    }
+    outln!("\nFrame Content:");
+    for finding in &findings {
+        outln!("    {}", finding);
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+/// Private Frame (PRIV)
+///
+/// Structure: Owner identifier + Private data
+use crate::error::SupertoolError;
+use crate::id3v2_text_encoding::decode_iso88591_string;
+use crate::xmp::{PRIV_OWNER, XmpPacket};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivateFrame {
+    pub owner_identifier: String,
+    pub data: Vec<u8>,
+}
+
+impl PrivateFrame {
+    /// Parse a PRIV frame from raw data
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
+        let Some(terminator) = data.iter().position(|&byte| byte == 0) else {
+            return Err(SupertoolError::TruncatedFrame("PRIV owner identifier not null-terminated".to_string()));
+        };
+
+        let owner_identifier = decode_iso88591_string(&data[..terminator]);
+        Ok(PrivateFrame { owner_identifier, data: data[terminator + 1..].to_vec() })
+    }
+
+    /// The XMP packet of a PRIV frame owned by "XMP"
+    pub fn xmp(&self) -> Option<Result<XmpPacket, String>> {
+        (self.owner_identifier == PRIV_OWNER).then(|| XmpPacket::parse("PRIV frame", &self.data))
+    }
+}
+
+impl fmt::Display for PrivateFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Owner: \"{}\"", self.owner_identifier)?;
+        writeln!(f, "Data: {} bytes", self.data.len())?;
+        match self.xmp() {
+            | Some(Ok(packet)) => write!(f, "{}", packet)?,
+            | Some(Err(error)) => writeln!(f, "WARNING: Invalid XMP packet: {}", error)?,
+            | None => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+/// Repair of structural defects in ID3v2.3 and ID3v2.4 tags (`repair`)
+///
+/// `validate` and the dissectors detect defects in the structure of a tag that make readers stop
+/// early or skip frames. The repair fixes the common ones in the raw bytes, so the frames keep
+/// their content byte for byte:
+///
+/// - size bytes that are not synchsafe (tag, extended header, ID3v2.4 frames), as written by
+///   taggers that store ID3v2.4 sizes like ID3v2.3 ones; a frame size is read the other way when
+///   only that leads to the next frame
+/// - frame sizes that exceed the tag: the size is set to end at the next frame that can be found
+/// - truncated final frames, which are dropped
+/// - padding that holds other bytes than zeros, which is zeroed
+///
+/// The repaired tag keeps its place and length, so the audio after it is not touched. Sub-frames
+/// of CHAP and CTOC frames are not repaired; unsynchronised tags are left alone.
+use crate::batch_errors::BatchErrors;
+use crate::checked_size::data_range;
+use crate::id3v2_tools::{MAX_SYNCHSAFE, decode_synchsafe_int, encode_synchsafe_int, frame_id_to_string, is_valid_frame_for_version};
+use crate::id3v2_validation::{FRAME_SIZE, SYNCHSAFE_SIZE, TAG_SIZE, hex, validate_tag};
+use crate::media_source::MediaSource;
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use crate::safe_save::overwrite_start;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Cursor, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Repaired tag and the fixes applied to it
+#[derive(Debug, Clone)]
+pub struct TagRepair {
+    /// Tag as it was found: `ID3v2.3` or `ID3v2.4`
+    pub tag: String,
+    /// The repaired tag (header, body and footer), as long as the original
+    pub bytes: Vec<u8>,
+    /// One explanation per fix; empty if the tag had no defects
+    pub fixes: Vec<String>,
+}
+
+/// Repair the tag of every file, or write the repaired copy of a single file to `output`
+pub fn repair_files(files: &[PathBuf], output: Option<&Path>, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(output) = output {
+        let [input] = files else {
+            return Err("--output takes a single input file".into());
+        };
+        if let (Ok(input), Ok(output)) = (fs::canonicalize(input), fs::canonicalize(output))
+            && input == output
+        {
+            return Err("the output would overwrite the input file; leave out --output to repair in place".into());
+        }
+    }
+
+    let mut unreadable = BatchErrors::default();
+    // The fixes of one file are printed as one block
+    let failed = unreadable.change_files(files, |path| crate::output::buffered(|| repair_file(path, output, dry_run)));
+    unreadable.finish_files(failed, "repaired", dry_run)
+}
+
+/// Repair one file and print the fixes
+fn repair_file(path: &Path, output: Option<&Path>, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let Some(repair) = repair_tag(&mut file)? else {
+        outln!("{}: no ID3v2 tag", display_path(path));
+        return Ok(());
+    };
+    if repair.fixes.is_empty() {
+        outln!("{}: {}, nothing to repair", display_path(path), repair.tag);
+        if output.is_none() {
+            return Ok(());
+        }
+    } else {
+        outln!("{}: {}, {} fix(es)", display_path(path), repair.tag, repair.fixes.len());
+        for fix in &repair.fixes {
+            outln!("  {}", fix);
+        }
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    match output {
+        | Some(output) => {
+            let file_size = file.byte_len()?;
+            let mut writer = BufWriter::new(File::create(long_path(output))?);
+            writer.write_all(&repair.bytes)?;
+            file.seek(SeekFrom::Start(repair.bytes.len() as u64))?;
+            io::copy(&mut file, &mut writer)?;
+            writer.flush()?;
+            drop(writer);
+            let written_size = fs::metadata(long_path(output))?.len();
+            if written_size != file_size {
+                return Err(format!("the repaired copy has {} bytes instead of {}", written_size, file_size).into());
+            }
+            outln!("  Repaired copy written: {}", display_path(output));
+        }
+        | None => {
+            drop(file);
+            overwrite_start(path, &repair.bytes, &WriteOptions::default())?;
+            outln!("  Written: {} bytes of tag repaired in place", repair.bytes.len());
+        }
+    }
+    Ok(())
+}
+
+/// Repair the ID3v2 tag at the start of the file; `None` if there is none
+///
+/// Fails for ID3v2.2 and unsynchronised tags, for plain tag sizes too large for a synchsafe size,
+/// and if the repaired tag would still fail the structural rules of `validate` (`tag-size`,
+/// `synchsafe-size`, `frame-size`).
+pub fn repair_tag(file: &mut dyn MediaSource) -> Result<Option<TagRepair>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut header = [0u8; 10];
+    file.seek(SeekFrom::Start(0))?;
+    if file_size < 10 || file.read_exact(&mut header).is_err() || &header[0..3] != b"ID3" {
+        return Ok(None);
+    }
+    let version_major = header[3];
+    let mut flags = header[5];
+    match version_major {
+        | 3 | 4 => {}
+        | 2 => return Err("ID3v2.2 tags are not repaired".into()),
+        | _ => return Err(format!("ID3v2.{} is not defined", version_major).into()),
+    }
+    if flags & 0x80 != 0 {
+        return Err("unsynchronised tags are not repaired".into());
+    }
+
+    let mut fixes = Vec::new();
+    let available = file_size - 10;
+    let mut footer = if version_major == 4 && flags & 0x10 != 0 {
+        10
+    } else {
+        0
+    };
+
+    // Tag size: taggers that do not know synchsafe integers store it as a plain integer
+    let size_bytes = &header[6..10];
+    let mut size = decode_synchsafe_int(size_bytes) as u64;
+    if size_bytes.iter().any(|&byte| byte & 0x80 != 0) {
+        let plain = u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]) as u64;
+        if plain + footer <= available {
+            // A size that needs more than 28 bits cannot be written back as a synchsafe integer
+            if plain > MAX_SYNCHSAFE as u64 {
+                return Err(format!("tag size bytes {} are not synchsafe and read as {} bytes, more than a synchsafe size holds ({})", hex(size_bytes), plain, MAX_SYNCHSAFE).into());
+            }
+            size = plain;
+        }
+        fixes.push(format!("tag size bytes {} are not synchsafe: read as {} bytes, stored as {}", hex(size_bytes), size, hex(&encode_synchsafe_int(size as u32))));
+    }
+    if size + footer > available {
+        fixes.push(format!("tag claims {} bytes, only {} follow the header: the tag now ends with the file", size + footer, available));
+        if footer > 0 {
+            fixes.push("footer lost with the end of the tag: footer flag cleared".to_string());
+            flags &= !0x10;
+            footer = 0;
+        }
+        size = available;
+    }
+
+    let mut body = vec![0u8; size as usize];
+    file.read_exact(&mut body)?;
+    let mut repaired = Vec::with_capacity(body.len());
+
+    // Extended header: ID3v2.4 counts the synchsafe size field itself, ID3v2.3 does not
+    let mut pos = 0;
+    if flags & 0x40 != 0 {
+        let extended_size = body.get(0..4).and_then(|size_bytes| {
+            let range = if version_major == 4 {
+                data_range("extended header", 0, 0, decode_synchsafe_int(size_bytes) as u64, body.len())
+            } else {
+                data_range("extended header", 0, 4, u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]) as u64, body.len())
+            };
+            range.ok().map(|range| range.end)
+        });
+        match extended_size {
+            | Some(extended_size) if extended_size >= 4 => {
+                repaired.extend_from_slice(&body[..extended_size]);
+                if version_major == 4 && body[0..4].iter().any(|&byte| byte & 0x80 != 0) {
+                    fixes.push(format!("extended header size bytes {} are not synchsafe: stored as {}", hex(&body[0..4]), hex(&encode_synchsafe_int(extended_size as u32))));
+                    repaired[0..4].copy_from_slice(&encode_synchsafe_int(extended_size as u32));
+                }
+                pos = extended_size;
+            }
+            | _ => {
+                // Without its size the end of the extended header is lost; the frames start at the first frame header
+                pos = next_frame(&body, 0, version_major).unwrap_or(body.len());
+                fixes.push(format!("extended header does not fit the tag of {} bytes: dropped, frames start at 0x{:08X}", body.len(), 10 + pos));
+                flags &= !0x40;
+            }
+        }
+    }
+
+    // Frames, each with its size as it is meant, until padding or bytes that are no frame
+    let mut truncated = false;
+    while pos + 10 <= body.len() {
+        let id_bytes = &body[pos..pos + 4];
+        if !id_bytes.iter().all(|&byte| byte.is_ascii_uppercase() || byte.is_ascii_digit()) {
+            break;
+        }
+        let frame_id = frame_id_to_string(id_bytes);
+        let location = format!("frame '{}' at 0x{:08X}", frame_id, 10 + pos);
+        let size_bytes = [body[pos + 4], body[pos + 5], body[pos + 6], body[pos + 7]];
+        let (declared, other) = frame_sizes(version_major, &size_bytes);
+        let start = pos + 10;
+        let left = body.len() - start;
+
+        let frame_size = if ends_at_boundary(&body, start, declared) {
+            if version_major == 4 && size_bytes.iter().any(|&byte| byte & 0x80 != 0) {
+                fixes.push(format!("{}: size bytes {} are not synchsafe: stored as {}", location, hex(&size_bytes), hex(&encode_synchsafe_int(declared as u32))));
+            }
+            declared
+        } else if ends_at_boundary(&body, start, other) {
+            let (written, meant) = if version_major == 4 {
+                ("plain", "synchsafe")
+            } else {
+                ("synchsafe", "plain")
+            };
+            fixes.push(format!("{}: size bytes {} are {} instead of {}: read as {} bytes", location, hex(&size_bytes), written, meant, other));
+            other
+        } else if declared <= left {
+            // The size fits the tag; what follows is no frame, the walk stops there
+            declared
+        } else if let Some(next) = next_frame(&body, start, version_major) {
+            let frame_size = next - start;
+            fixes.push(format!("{}: claims {} bytes, only {} are left: size set to {} bytes, up to the frame at 0x{:08X}", location, declared, left, frame_size, 10 + next));
+            frame_size
+        } else {
+            fixes.push(format!("{}: truncated final frame (claims {} bytes, only {} are left): dropped", location, declared, left));
+            truncated = true;
+            break;
+        };
+
+        repaired.extend_from_slice(&body[pos..pos + 4]);
+        repaired.extend_from_slice(&encode_frame_size(version_major, frame_size));
+        repaired.extend_from_slice(&body[pos + 8..start + frame_size]);
+        pos = start + frame_size;
+    }
+
+    // The rest of the tag is padding, which has to be zeros
+    let padding = &body[pos..];
+    let garbage = padding.iter().filter(|&&byte| byte != 0).count();
+    if garbage > 0 && !truncated {
+        fixes.push(format!("padding of {} bytes at 0x{:08X} held {} non-zero byte(s): zeroed", padding.len(), 10 + pos, garbage));
+    }
+    repaired.resize(body.len(), 0);
+
+    let mut bytes = Vec::with_capacity(10 + repaired.len() + footer as usize);
+    bytes.extend_from_slice(&[b'I', b'D', b'3', version_major, header[4], flags]);
+    bytes.extend_from_slice(&encode_synchsafe_int(repaired.len() as u32));
+    bytes.extend_from_slice(&repaired);
+    if footer > 0 {
+        bytes.extend_from_slice(&[b'3', b'D', b'I', version_major, header[4], flags]);
+        bytes.extend_from_slice(&encode_synchsafe_int(repaired.len() as u32));
+    }
+
+    // The structural rules of validate have to pass on the repaired tag
+    let structural = [TAG_SIZE.id, SYNCHSAFE_SIZE.id, FRAME_SIZE.id];
+    let validation = validate_tag(&mut Cursor::new(&bytes))?;
+    if let Some(finding) = validation.findings.iter().find(|finding| structural.contains(&finding.rule) && !finding.finding.location.contains('/')) {
+        return Err(format!("the repaired tag still fails {}: {}", finding.rule, finding.finding.message).into());
+    }
+
+    Ok(Some(TagRepair { tag: format!("ID3v2.{}", version_major), bytes, fixes }))
+}
+
+/// Frame size as the version declares it and as the other encoding would read it
+fn frame_sizes(version_major: u8, size_bytes: &[u8; 4]) -> (usize, usize) {
+    let plain = u32::from_be_bytes(*size_bytes) as usize;
+    let synchsafe = decode_synchsafe_int(size_bytes) as usize;
+    if version_major == 4 {
+        (synchsafe, plain)
+    } else {
+        (plain, synchsafe)
+    }
+}
+
+fn encode_frame_size(version_major: u8, size: usize) -> [u8; 4] {
+    if version_major == 4 {
+        encode_synchsafe_int(size as u32)
+    } else {
+        (size as u32).to_be_bytes()
+    }
+}
+
+/// Whether a frame of `size` bytes starting at `start` ends at the end of the tag, at padding or
+/// at the header of another frame
+fn ends_at_boundary(body: &[u8], start: usize, size: usize) -> bool {
+    let Some(end) = start.checked_add(size).filter(|&end| end <= body.len()) else {
+        return false;
+    };
+    match body.get(end..end + 4) {
+        | None => body[end..].iter().all(|&byte| byte == 0),
+        | Some(id_bytes) => id_bytes[0] == 0 || id_bytes.iter().all(|&byte| byte.is_ascii_uppercase() || byte.is_ascii_digit()),
+    }
+}
+
+/// Position of the first header of a frame defined for the version after `from` whose size ends at a boundary
+fn next_frame(body: &[u8], from: usize, version_major: u8) -> Option<usize> {
+    (from..body.len().saturating_sub(9)).find(|&pos| {
+        let frame_id = frame_id_to_string(&body[pos..pos + 4]);
+        let size_bytes = [body[pos + 4], body[pos + 5], body[pos + 6], body[pos + 7]];
+        is_valid_frame_for_version(&frame_id, version_major) && ends_at_boundary(body, pos + 10, frame_sizes(version_major, &size_bytes).0)
+    })
+}
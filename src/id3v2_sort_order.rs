@@ -0,0 +1,92 @@
+/// Sort order frames (TSOT, TSOP, TSOA and the iTunes TSO2 and TSOC)
+///
+/// Players sort by these frames instead of the title, artist, album, album artist and composer
+/// they belong to, so "The Beatles" can be listed under B. An empty sort value files the track at
+/// the top of the list, and a sort value left over from an earlier tag (another artist, another
+/// album) files it under the wrong name. ID3v2.3 has no sort order frames; taggers write the
+/// de-facto XSOT, XSOP and XSOA there.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::Id3v2Frame;
+
+/// Sort order frames of ID3v2.4 with the frame they sort
+pub const SORT_FRAMES: &[(&str, &str)] = &[("TSOT", "TIT2"), ("TSOP", "TPE1"), ("TSOA", "TALB"), ("TSO2", "TPE2"), ("TSOC", "TCOM")];
+
+/// Leading articles that sort strings leave out
+pub const ARTICLES: &[&str] = &["The", "A", "An"];
+
+/// Frame ID that stores the sort order of `base_id` in tags of the given version
+pub fn sort_frame_id(base_id: &str, version_major: u8) -> Option<&'static str> {
+    let (sort_id, _) = SORT_FRAMES.iter().find(|(_, base)| *base == base_id)?;
+    Some(match *sort_id {
+        | "TSOT" if version_major < 4 => "XSOT",
+        | "TSOP" if version_major < 4 => "XSOP",
+        | "TSOA" if version_major < 4 => "XSOA",
+        | sort_id => sort_id,
+    })
+}
+
+/// Frame whose value a sort order frame (including XSOT, XSOP and XSOA) sorts
+pub fn base_frame_id(sort_id: &str) -> Option<&'static str> {
+    let sort_id = crate::id3v2_tools::de_facto_layout(sort_id).unwrap_or(sort_id);
+    SORT_FRAMES.iter().find(|(sort, _)| *sort == sort_id).map(|(_, base)| *base)
+}
+
+/// Sort string for a value: the value without a leading article ("The Beatles" sorts as "Beatles")
+pub fn sort_string(value: &str) -> String {
+    let value = value.trim();
+    for article in ARTICLES {
+        if let (Some(start), Some(rest)) = (value.get(..article.len()), value.get(article.len()..))
+            && start.eq_ignore_ascii_case(article)
+            && rest.starts_with(' ')
+            && !rest.trim().is_empty()
+        {
+            return rest.trim_start().to_string();
+        }
+    }
+    value.to_string()
+}
+
+/// Whether a sort value belongs to the value it sorts: they share at least one word
+///
+/// Reordered values ("Bowie, David"), values without articles and romanizations that keep a word
+/// all count as consistent. Phonetic readings in another script (kana for kanji) do not.
+pub fn is_consistent(sort_value: &str, value: &str) -> bool {
+    let words = |text: &str| text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).map(str::to_lowercase).collect::<Vec<_>>();
+    let base_words = words(value);
+    words(sort_value).iter().any(|word| base_words.contains(word))
+}
+
+/// Value of a sort order frame; empty for a frame that holds at most the encoding byte, `None` if
+/// it was not parsed as text (XSOT, XSOP and XSOA without `--lenient`)
+pub fn sort_value(frame: &Id3v2Frame) -> Option<String> {
+    match frame.get_text_values() {
+        | Some(values) => Some(values.join(" ")),
+        | None if frame.data.len() <= 1 => Some(String::new()),
+        | None => None,
+    }
+}
+
+/// Findings for sort order frames that are empty, sort a missing frame or do not match their frame
+pub fn sort_findings(frames: &[Id3v2Frame]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for frame in frames {
+        let Some(base_id) = base_frame_id(&frame.id) else {
+            continue;
+        };
+        let Some(sort_value) = sort_value(frame) else {
+            continue;
+        };
+        if sort_value.trim().is_empty() {
+            findings.push(Finding::new(Severity::Warning, &frame.id, format!("empty sort value, the {} sorts before every other", base_id)));
+            continue;
+        }
+        let Some(value) = frames.iter().find(|base| base.id == base_id).and_then(|base| base.get_text_values()).map(|values| values.join(" ")) else {
+            findings.push(Finding::new(Severity::Warning, &frame.id, format!("'{}' sorts a {} the tag does not have", sort_value, base_id)));
+            continue;
+        };
+        if !is_consistent(&sort_value, &value) {
+            findings.push(Finding::new(Severity::Warning, &frame.id, format!("'{}' does not sort {} '{}' (unless it is a phonetic reading)", sort_value, base_id, value)));
+        }
+    }
+    findings
+}
@@ -0,0 +1,127 @@
+/// Synchronised Lyrics/Text Frame (SYLT)
+///
+/// Structure: Text encoding + Language + Time stamp format + Content type + Content descriptor
+/// + (terminated text + 4-byte time stamp) pairs
+use crate::error::SupertoolError;
+use crate::id3v2_text_encoding::{TextEncoding, decode_text_with_encoding_simple, encode_text, get_terminator_length, is_null_terminator, terminator_bytes};
+use crate::text_truncate::truncate_text;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Time stamps count MPEG frames
+pub const TIMESTAMP_MPEG_FRAMES: u8 = 1;
+/// Time stamps count milliseconds
+pub const TIMESTAMP_MILLISECONDS: u8 = 2;
+
+/// Content type "lyrics"
+pub const CONTENT_TYPE_LYRICS: u8 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncedLyricsFrame {
+    pub encoding: TextEncoding,
+    pub language: String,
+    /// Unit of the time stamps (`TIMESTAMP_MPEG_FRAMES` or `TIMESTAMP_MILLISECONDS`)
+    pub timestamp_format: u8,
+    /// Content type (0 other, 1 lyrics, 2 text transcription, 3 movement, 4 events, 5 chord, 6 trivia, ...)
+    pub content_type: u8,
+    pub description: String,
+    /// Text syllables or lines with their time stamps, in file order
+    pub entries: Vec<(String, u32)>,
+}
+
+impl SyncedLyricsFrame {
+    /// Parse a SYLT frame from raw data
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
+        if data.len() < 6 {
+            return Err(SupertoolError::TruncatedFrame("Synchronised lyrics frame data too short".to_string()));
+        }
+
+        let encoding = TextEncoding::from_byte(data[0])?;
+        let language = String::from_utf8_lossy(&data[1..4]).to_string();
+        let timestamp_format = data[4];
+        let content_type = data[5];
+
+        let (description, mut pos) = read_terminated(data, 6, encoding)?;
+        let mut entries = Vec::new();
+        while pos < data.len() {
+            let (text, text_end) = read_terminated(data, pos, encoding)?;
+            if text_end + 4 > data.len() {
+                return Err(SupertoolError::TruncatedFrame("Synchronised lyrics entry without time stamp".to_string()));
+            }
+            let timestamp = u32::from_be_bytes([data[text_end], data[text_end + 1], data[text_end + 2], data[text_end + 3]]);
+            entries.push((text, timestamp));
+            pos = text_end + 4;
+        }
+
+        Ok(SyncedLyricsFrame { encoding, language, timestamp_format, content_type, description, entries })
+    }
+
+    /// Serialize the frame content
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.encoding as u8];
+        let mut language = self.language.as_bytes().to_vec();
+        language.resize(3, b' ');
+        bytes.extend_from_slice(&language[..3]);
+        bytes.push(self.timestamp_format);
+        bytes.push(self.content_type);
+        bytes.extend(encode_text(&self.description, self.encoding));
+        bytes.extend_from_slice(terminator_bytes(self.encoding));
+        for (text, timestamp) in &self.entries {
+            bytes.extend(encode_text(text, self.encoding));
+            bytes.extend_from_slice(terminator_bytes(self.encoding));
+            bytes.extend_from_slice(&timestamp.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Human-readable name of the content type
+    pub fn content_type_description(&self) -> &'static str {
+        match self.content_type {
+            | 0 => "Other",
+            | 1 => "Lyrics",
+            | 2 => "Text transcription",
+            | 3 => "Movement/part name",
+            | 4 => "Events",
+            | 5 => "Chord",
+            | 6 => "Trivia/pop-up information",
+            | 7 => "URLs to webpages",
+            | 8 => "URLs to images",
+            | _ => "Unknown",
+        }
+    }
+}
+
+/// Read a terminated string starting at `start`, returning it and the position after the terminator
+fn read_terminated(data: &[u8], start: usize, encoding: TextEncoding) -> Result<(String, usize), SupertoolError> {
+    let terminator_len = get_terminator_length(encoding);
+    let mut pos = start;
+    // Step by the terminator width so UTF-16 strings stay aligned
+    while pos + terminator_len <= data.len() && !is_null_terminator(&data[pos..pos + terminator_len], encoding) {
+        pos += terminator_len;
+    }
+    let end = pos.min(data.len());
+    let text = decode_text_with_encoding_simple(&data[start..end], encoding)?;
+    Ok((text, (end + terminator_len).min(data.len())))
+}
+
+impl fmt::Display for SyncedLyricsFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Encoding: {}", self.encoding)?;
+        writeln!(f, "Language: \"{}\"", self.language)?;
+        let unit = match self.timestamp_format {
+            | TIMESTAMP_MPEG_FRAMES => "MPEG frames",
+            | TIMESTAMP_MILLISECONDS => "milliseconds",
+            | _ => "unknown",
+        };
+        writeln!(f, "Time stamp format: {} ({})", self.timestamp_format, unit)?;
+        writeln!(f, "Content type: {} ({})", self.content_type, self.content_type_description())?;
+        if !self.description.is_empty() {
+            writeln!(f, "Description: \"{}\"", self.description)?;
+        }
+        writeln!(f, "Entries ({}):", self.entries.len())?;
+        for (text, timestamp) in &self.entries {
+            writeln!(f, "  [{}] \"{}\"", timestamp, truncate_text(text).escape_debug())?;
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,14 @@
+use crate::error::SupertoolError;
 use crate::id3v2_frame::Id3v2Frame;
 /// Table of Contents Frame (CTOC)
 ///
 /// Structure: Element ID + TOC flags + Entry count + Child element IDs + Sub-frames
 /// Part of ID3v2 Chapter Frame Addendum specification
 use crate::id3v2_text_encoding::decode_iso88591_string;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableOfContentsFrame {
     /// Element ID (null-terminated)
     pub element_id: String,
@@ -21,10 +23,10 @@ pub struct TableOfContentsFrame {
 }
 
 impl TableOfContentsFrame {
-    /// Parse a CTOC frame from raw data
-    pub fn parse(data: &[u8], version_major: u8) -> Result<Self, String> {
+    /// Parse a CTOC frame from raw data; `depth` is its nesting level (0 at the top level of the tag)
+    pub fn parse(data: &[u8], version_major: u8, depth: usize) -> Result<Self, SupertoolError> {
         if data.is_empty() {
-            return Err("Table of contents frame data is empty".to_string());
+            return Err(SupertoolError::TruncatedFrame("Table of contents frame data is empty".to_string()));
         }
 
         let mut pos = 0;
@@ -35,14 +37,14 @@ impl TableOfContentsFrame {
             pos += 1;
         }
         if pos >= data.len() {
-            return Err("TOC frame element ID not null-terminated".to_string());
+            return Err(SupertoolError::TruncatedFrame("TOC frame element ID not null-terminated".to_string()));
         }
         let element_id = decode_iso88591_string(&data[element_id_start..pos]);
         pos += 1; // Skip null terminator
 
         // TOC flags (1 byte)
         if pos >= data.len() {
-            return Err("TOC frame missing flags".to_string());
+            return Err(SupertoolError::TruncatedFrame("TOC frame missing flags".to_string()));
         }
         let flags = data[pos];
         pos += 1;
@@ -52,7 +54,7 @@ impl TableOfContentsFrame {
 
         // Entry count (1 byte)
         if pos >= data.len() {
-            return Err("TOC frame missing entry count".to_string());
+            return Err(SupertoolError::TruncatedFrame("TOC frame missing entry count".to_string()));
         }
         let entry_count = data[pos];
         pos += 1;
@@ -65,7 +67,7 @@ impl TableOfContentsFrame {
                 pos += 1;
             }
             if pos >= data.len() {
-                return Err("TOC frame child element ID not null-terminated".to_string());
+                return Err(SupertoolError::TruncatedFrame("TOC frame child element ID not null-terminated".to_string()));
             }
             let child_id = decode_iso88591_string(&data[id_start..pos]);
             child_element_ids.push(child_id);
@@ -74,7 +76,7 @@ impl TableOfContentsFrame {
 
         // Parse embedded sub-frames (rest of the data)
         let sub_frames = if pos < data.len() {
-            crate::id3v2_tools::parse_embedded_frames(&data[pos..], version_major)
+            crate::id3v2_tools::parse_embedded_frames(&data[pos..], version_major, depth + 1)
         } else {
             Vec::new()
         };
@@ -82,6 +84,32 @@ impl TableOfContentsFrame {
         Ok(TableOfContentsFrame { element_id, top_level, ordered, child_element_ids, sub_frames })
     }
 
+    /// Serialize the frame content including its sub-frames for the given ID3v2 version
+    pub fn to_bytes(&self, version_major: u8) -> Vec<u8> {
+        let mut bytes = self.element_id.as_bytes().to_vec();
+        bytes.push(0);
+        bytes.push(
+            (if self.top_level {
+                0x02
+            } else {
+                0
+            }) | (if self.ordered {
+                0x01
+            } else {
+                0
+            }),
+        );
+        bytes.push(self.child_element_ids.len() as u8);
+        for child_id in &self.child_element_ids {
+            bytes.extend_from_slice(child_id.as_bytes());
+            bytes.push(0);
+        }
+        for sub_frame in &self.sub_frames {
+            bytes.extend(sub_frame.to_bytes(version_major));
+        }
+        bytes
+    }
+
     /// Get number of child elements
     pub fn child_count(&self) -> usize {
         self.child_element_ids.len()
@@ -91,6 +119,11 @@ impl TableOfContentsFrame {
     pub fn has_sub_frames(&self) -> bool {
         !self.sub_frames.is_empty()
     }
+
+    /// Title of this table of contents (TIT2 sub-frame)
+    pub fn title(&self) -> Option<&str> {
+        self.sub_frames.iter().find(|frame| frame.id == "TIT2").and_then(|frame| frame.get_text())
+    }
 }
 
 impl fmt::Display for TableOfContentsFrame {
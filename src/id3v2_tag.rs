@@ -0,0 +1,355 @@
+/// Complete ID3v2 tag (header plus parsed frames)
+///
+/// Reads a tag without printing diagnostics, for consumers that need the parsed
+/// frames as data (summaries, sidecars, library tools) rather than dissection output.
+use crate::checked_size::data_range;
+use crate::error::SupertoolError;
+use crate::finding::{Finding, Severity};
+use crate::id3v2_chapter_frame::ChapterFrame;
+use crate::id3v2_chapter_tree::{ChapterTree, build_chapter_tree};
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_frame_order::order_findings;
+use crate::id3v2_general_object_frame::GeneralObjectFrame;
+use crate::id3v2_genre::parse_genres;
+use crate::id3v2_known_user_text::{KnownUserText, interpret_comment, interpret_user_text};
+use crate::id3v2_plausibility::content_findings;
+use crate::id3v2_sort_order::sort_findings;
+use crate::id3v2_tools::{Id3v2Header, decode_synchsafe_int, remove_unsynchronization};
+use crate::media_source::MediaSource;
+use crate::xmp::XmpPacket;
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, SeekFrom};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Id3v2Tag {
+    /// Major version (3 or 4)
+    pub version_major: u8,
+    /// Revision number
+    pub version_minor: u8,
+    /// Tag header flags
+    pub flags: u8,
+    /// Tag size from the header (excluding the 10-byte header and optional footer)
+    pub size: u32,
+    /// Parsed top-level frames in file order
+    pub frames: Vec<Id3v2Frame>,
+}
+
+impl Id3v2Tag {
+    /// Create an empty tag of the given major version
+    pub fn new(version_major: u8) -> Self {
+        Id3v2Tag { version_major, version_minor: 0, flags: 0, size: 0, frames: Vec::new() }
+    }
+
+    /// Read the ID3v2 tag at the beginning of the file, if present
+    ///
+    /// Fails with `UnsupportedVersion` for major versions other than 2, 3 and 4, and with
+    /// `InvalidHeader` when the file ends before the tag size from the header.
+    pub fn read(file: &mut dyn MediaSource) -> Result<Option<Self>, SupertoolError> {
+        file.seek(SeekFrom::Start(0))?;
+        let mut header = [0u8; 10];
+        if file.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+
+        let Some((version_major, version_minor, flags, size)) = parse_tag_header(&header) else {
+            return Ok(None);
+        };
+        if !(2..=4).contains(&version_major) {
+            return Err(SupertoolError::UnsupportedVersion(format!("ID3v2.{}.{}", version_major, version_minor)));
+        }
+
+        let mut body = vec![0u8; size as usize];
+        file.read_exact(&mut body).map_err(|error| match error.kind() {
+            | ErrorKind::UnexpectedEof => SupertoolError::InvalidHeader(format!("the tag header claims {} bytes, but the file ends before", size)),
+            | _ => SupertoolError::Io(error),
+        })?;
+
+        Ok(Some(Self::parse_body(version_major, version_minor, flags, size, &body)))
+    }
+
+    /// Parse a complete tag (header and body) embedded in another container, e.g. a WAVE id3 chunk
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (version_major, version_minor, flags, size) = parse_tag_header(data)?;
+        let body = &data[10..(10 + size as usize).min(data.len())];
+        Some(Self::parse_body(version_major, version_minor, flags, size, body))
+    }
+
+    /// Parse the frames of a tag body (the bytes following the 10-byte header)
+    pub fn parse_body(version_major: u8, version_minor: u8, flags: u8, size: u32, body: &[u8]) -> Self {
+        let mut tag = Id3v2Tag { version_major, version_minor, flags, size, frames: Vec::new() };
+        if version_major == 2 {
+            tag.frames = parse_id3v2_2_frames(flags, body);
+            return tag;
+        }
+        if version_major != 3 && version_major != 4 {
+            return tag;
+        }
+
+        let buffer = if flags & 0x80 != 0 {
+            remove_unsynchronization(body)
+        } else {
+            body.to_vec()
+        };
+
+        // Skip the extended header (size encoding differs between versions)
+        let mut pos = 0;
+        if flags & 0x40 != 0 && buffer.len() >= 4 {
+            let extended_size = if version_major == 4 {
+                decode_synchsafe_int(&buffer[0..4])
+            } else {
+                u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]])
+            };
+            // A size beyond the body leaves no frames
+            pos = data_range("extended header", 0, 4, extended_size as u64, buffer.len()).map_or(buffer.len(), |range| range.end);
+        }
+
+        while pos + 10 <= buffer.len() {
+            let frame_id = &buffer[pos..pos + 4];
+            if frame_id[0] == 0 {
+                break;
+            }
+
+            let frame_size = if version_major == 4 {
+                decode_synchsafe_int(&buffer[pos + 4..pos + 8])
+            } else {
+                u32::from_be_bytes([buffer[pos + 4], buffer[pos + 5], buffer[pos + 6], buffer[pos + 7]])
+            } as usize;
+
+            // An ID that is not alphanumeric only counts as a (corrupt) frame if its size fits
+            let Ok(range) = data_range("frame", pos, 10, frame_size as u64, buffer.len()) else {
+                break;
+            };
+            if frame_size == 0 && !frame_id.iter().all(|c| c.is_ascii_alphanumeric()) {
+                break;
+            }
+
+            let parsed = if version_major == 4 {
+                crate::id3v2_4_dissector::parse_id3v2_4_frame(&buffer, pos)
+            } else {
+                crate::id3v2_3_dissector::parse_id3v2_3_frame(&buffer, pos)
+            };
+
+            // Frames that could not be parsed are kept as raw data so a rewrite preserves them
+            match parsed {
+                | Some(frame) => tag.frames.push(frame),
+                | None if frame_size > 0 => {
+                    let frame_flags = u16::from_be_bytes([buffer[pos + 8], buffer[pos + 9]]);
+                    let data = buffer[range.clone()].to_vec();
+                    tag.frames.push(Id3v2Frame::from_id_bytes(frame_id, frame_size as u32, frame_flags, pos, data));
+                }
+                | None => {}
+            }
+
+            pos = range.end;
+        }
+
+        tag
+    }
+
+    /// Size of a frame header: 6 bytes in ID3v2.2 (3-byte ID and size), 10 bytes in later versions
+    pub fn frame_header_size(&self) -> u64 {
+        if self.version_major == 2 {
+            6
+        } else {
+            10
+        }
+    }
+
+    /// Human-readable tag type, e.g. "ID3v2.4"
+    pub fn tag_type(&self) -> String {
+        format!("ID3v2.{}", self.version_major)
+    }
+
+    /// Replace all frames matching `matches` with `frame`, keeping the position of the first match
+    ///
+    /// The frame is appended if no existing frame matches.
+    pub fn replace_frames(&mut self, frame: Id3v2Frame, matches: impl Fn(&Id3v2Frame) -> bool) {
+        match self.frames.iter().position(&matches) {
+            | Some(index) => {
+                self.frames[index] = frame;
+                let mut current = 0;
+                self.frames.retain(|existing| {
+                    let keep = current == index || !matches(existing);
+                    current += 1;
+                    keep
+                });
+            }
+            | None => self.frames.push(frame),
+        }
+    }
+
+    /// Identifier of the first UFID frame with the given owner (e.g. "http://musicbrainz.org")
+    pub fn unique_file_id(&self, owner: &str) -> Option<String> {
+        self.frames.iter().find_map(|frame| match &frame.content {
+            | Some(Id3v2FrameContent::UniqueFileId(ufid)) if ufid.owner_identifier == owner => Some(String::from_utf8_lossy(&ufid.identifier).to_string()),
+            | _ => None,
+        })
+    }
+
+    /// Normalized genre list from the TCON frame (empty if there is none)
+    pub fn genres(&self) -> Vec<String> {
+        self.frames
+            .iter()
+            .find(|frame| frame.id == "TCON")
+            .and_then(|frame| frame.get_text_values())
+            .map(parse_genres)
+            .unwrap_or_default()
+    }
+
+    /// Typed values of all TXXX frames with a well-known description (ReplayGain, MusicBrainz, ...)
+    /// and of the iTunes normalization and gapless comments
+    pub fn known_user_texts(&self) -> Vec<KnownUserText> {
+        self.frames
+            .iter()
+            .filter_map(|frame| match &frame.content {
+                | Some(Id3v2FrameContent::UserText(user_text)) => interpret_user_text(&user_text.description, &user_text.value),
+                | Some(Id3v2FrameContent::Comment(comment)) if frame.id == "COMM" => interpret_comment(&comment.description, &comment.text),
+                | _ => None,
+            })
+            .collect()
+    }
+
+    /// XMP packets of PRIV frames owned by "XMP" and of JPEG pictures (packets that fail to parse are skipped)
+    pub fn xmp_packets(&self) -> Vec<XmpPacket> {
+        self.frames
+            .iter()
+            .filter_map(|frame| match &frame.content {
+                | Some(Id3v2FrameContent::Private(private)) => private.xmp(),
+                | Some(Id3v2FrameContent::Picture(picture)) => picture.xmp(),
+                | _ => None,
+            })
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    /// Problems found in the frames (malformed URLs, including CHAP/CTOC sub-frames, and a broken table of contents)
+    pub fn findings(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        collect_findings(&self.frames, "", &mut findings);
+        findings.extend(self.chapter_tree().findings);
+        findings.extend(order_findings(&self.frames));
+        findings.extend(sort_findings(&self.frames));
+        findings
+    }
+
+    /// Table of contents resolved from the CTOC and CHAP frames
+    pub fn chapter_tree(&self) -> ChapterTree {
+        build_chapter_tree(&self.frames)
+    }
+
+    /// Top-level chapter frames in file order
+    pub fn chapters(&self) -> impl Iterator<Item = &ChapterFrame> {
+        self.frames.iter().filter_map(|frame| match &frame.content {
+            | Some(Id3v2FrameContent::Chapter(chapter)) => Some(chapter),
+            | _ => None,
+        })
+    }
+
+    /// URLs of all W*** and WXXX frames with their location, including CHAP/CTOC sub-frames
+    pub fn urls(&self) -> Vec<(String, String)> {
+        let mut urls = Vec::new();
+        collect_urls(&self.frames, "", &mut urls);
+        urls
+    }
+
+    /// GEOB frames with their location, including CHAP/CTOC sub-frames
+    pub fn general_objects(&self) -> Vec<(String, &GeneralObjectFrame)> {
+        let mut objects = Vec::new();
+        collect_general_objects(&self.frames, "", &mut objects);
+        objects
+    }
+
+    /// Full version string, e.g. "2.4.0"
+    pub fn version(&self) -> String {
+        format!("2.{}.{}", self.version_major, self.version_minor)
+    }
+
+    /// Total number of bytes the tag occupies in the file (header, body and footer)
+    pub fn total_size(&self) -> u64 {
+        let footer = if self.version_major == 4 && self.flags & 0x10 != 0 {
+            10
+        } else {
+            0
+        };
+        10 + self.size as u64 + footer
+    }
+}
+
+fn collect_findings(frames: &[Id3v2Frame], prefix: &str, findings: &mut Vec<Finding>) {
+    for frame in frames {
+        let location = format!("{}{}", prefix, frame.id);
+        findings.extend(frame.url_findings(&location));
+        findings.extend(content_findings(frame, &location));
+        match &frame.content {
+            | Some(Id3v2FrameContent::Picture(picture)) => findings.extend(picture.image_info().map(|image| image.findings(&location)).unwrap_or_default()),
+            | Some(Id3v2FrameContent::Chapter(chapter)) => collect_findings(&chapter.sub_frames, &format!("{}/", location), findings),
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => collect_findings(&toc.sub_frames, &format!("{}/", location), findings),
+            | Some(Id3v2FrameContent::TooDeep { depth }) => findings.push(Finding::new(
+                Severity::Error,
+                &location,
+                format!("nested {} levels deep, more than the maximum of {}; sub-frames not dissected", depth, crate::id3v2_tools::max_embedding_depth()),
+            )),
+            | _ => {}
+        }
+    }
+}
+
+fn collect_urls(frames: &[Id3v2Frame], prefix: &str, urls: &mut Vec<(String, String)>) {
+    for frame in frames {
+        let location = format!("{}{}", prefix, frame.id);
+        if let Some(url) = frame.get_url() {
+            urls.push((location.clone(), url.trim_end_matches('\0').to_string()));
+        }
+        match &frame.content {
+            | Some(Id3v2FrameContent::Chapter(chapter)) => collect_urls(&chapter.sub_frames, &format!("{}/", location), urls),
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => collect_urls(&toc.sub_frames, &format!("{}/", location), urls),
+            | _ => {}
+        }
+    }
+}
+
+fn collect_general_objects<'a>(frames: &'a [Id3v2Frame], prefix: &str, objects: &mut Vec<(String, &'a GeneralObjectFrame)>) {
+    for frame in frames {
+        let location = format!("{}{}", prefix, frame.id);
+        match &frame.content {
+            | Some(Id3v2FrameContent::GeneralObject(object)) => objects.push((location, object)),
+            | Some(Id3v2FrameContent::Chapter(chapter)) => collect_general_objects(&chapter.sub_frames, &format!("{}/", location), objects),
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => collect_general_objects(&toc.sub_frames, &format!("{}/", location), objects),
+            | _ => {}
+        }
+    }
+}
+
+/// Frames of an ID3v2.2 tag body, under the IDs of their ID3v2.3 counterparts
+///
+/// A set compression flag (never defined for ID3v2.2) leaves the frames unreadable.
+fn parse_id3v2_2_frames(flags: u8, body: &[u8]) -> Vec<Id3v2Frame> {
+    if flags & 0x40 != 0 {
+        return Vec::new();
+    }
+    let buffer = if flags & 0x80 != 0 {
+        remove_unsynchronization(body)
+    } else {
+        body.to_vec()
+    };
+
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while pos + 6 <= buffer.len() && buffer[pos] != 0 {
+        let frame_size = u32::from_be_bytes([0, buffer[pos + 3], buffer[pos + 4], buffer[pos + 5]]);
+        let Ok(range) = data_range("frame", pos, 6, frame_size as u64, buffer.len()) else {
+            break;
+        };
+        frames.extend(crate::id3v2_2_dissector::parse_id3v2_2_frame(&buffer, pos));
+        pos = range.end;
+    }
+    frames
+}
+
+/// Parse a 10-byte ID3v2 tag header without printing diagnostics
+pub fn parse_tag_header(header: &[u8]) -> Option<Id3v2Header> {
+    if header.len() < 10 || &header[0..3] != b"ID3" {
+        return None;
+    }
+    Some((header[3], header[4], header[5], decode_synchsafe_int(&header[6..10])))
+}
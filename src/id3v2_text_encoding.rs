@@ -2,10 +2,23 @@
 ///
 /// This module provides the `TextEncoding` enum and helper functions for decoding
 /// text in various encodings used by ID3v2 frames.
+use crate::error::SupertoolError;
+use crate::options::{AssumedEncoding, CommonOptions};
+use crate::text_normalization::normalize_decoded;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// Code page assumed for ISO-8859-1 text (`--assume-encoding`), if any
+///
+/// Many taggers wrote the system code page (Windows-1251, Shift_JIS, ...) into frames marked as
+/// ISO-8859-1, which makes the text unreadable when it is decoded as declared. Identifiers, URLs
+/// and MIME types are always ISO-8859-1 and not affected.
+pub fn assumed_encoding() -> Option<AssumedEncoding> {
+    CommonOptions::current().assume_encoding
+}
+
 /// Text encoding types used in ID3v2 frames
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TextEncoding {
     /// ISO-8859-1 (Latin-1)
     Iso88591 = 0,
@@ -19,13 +32,13 @@ pub enum TextEncoding {
 
 impl TextEncoding {
     /// Create TextEncoding from byte value
-    pub fn from_byte(byte: u8) -> Result<Self, String> {
+    pub fn from_byte(byte: u8) -> Result<Self, SupertoolError> {
         match byte {
             | 0 => Ok(TextEncoding::Iso88591),
             | 1 => Ok(TextEncoding::Utf16Bom),
             | 2 => Ok(TextEncoding::Utf16Be),
             | 3 => Ok(TextEncoding::Utf8),
-            | _ => Err(format!("Unknown text encoding: {}", byte)),
+            | _ => Err(SupertoolError::BadEncoding(format!("Unknown text encoding: {}", byte))),
         }
     }
 
@@ -36,6 +49,19 @@ impl TextEncoding {
             | TextEncoding::Utf16Be | TextEncoding::Utf8 => version_major >= 4,
         }
     }
+
+    /// Choose the encoding used when writing new text for the given ID3v2 version
+    ///
+    /// ID3v2.4 always gets UTF-8. ID3v2.3 uses ISO-8859-1 when the text fits, UTF-16 otherwise.
+    pub fn preferred_for_version(version_major: u8, text: &str) -> Self {
+        if version_major >= 4 {
+            TextEncoding::Utf8
+        } else if can_encode(text, TextEncoding::Iso88591) {
+            TextEncoding::Iso88591
+        } else {
+            TextEncoding::Utf16Bom
+        }
+    }
 }
 
 impl fmt::Display for TextEncoding {
@@ -53,7 +79,18 @@ impl fmt::Display for TextEncoding {
 // Helper functions for text decoding
 
 /// Decode text with specified encoding, handling multiple null-separated strings
-pub fn decode_text_with_encoding(data: &[u8], encoding: TextEncoding) -> Result<(String, Vec<String>), String> {
+pub fn decode_text_with_encoding(data: &[u8], encoding: TextEncoding) -> Result<(String, Vec<String>), SupertoolError> {
+    let strings = decode_strings(data, encoding, decode_text_with_encoding_simple)?;
+    let primary_text = strings.first().cloned().unwrap_or_default();
+    Ok((primary_text, strings))
+}
+
+/// Decode null-separated strings as stored, without the NFC normalization of `--nfc`
+pub fn decode_stored_strings(data: &[u8], encoding: TextEncoding) -> Result<Vec<String>, SupertoolError> {
+    decode_strings(data, encoding, decode_stored_text)
+}
+
+fn decode_strings(data: &[u8], encoding: TextEncoding, decode: fn(&[u8], TextEncoding) -> Result<String, SupertoolError>) -> Result<Vec<String>, SupertoolError> {
     let mut strings = Vec::new();
     let mut pos = 0;
     let terminator_len = get_terminator_length(encoding);
@@ -76,7 +113,7 @@ pub fn decode_text_with_encoding(data: &[u8], encoding: TextEncoding) -> Result<
         }
 
         if start < pos {
-            let text = decode_text_with_encoding_simple(&data[start..pos], encoding)?;
+            let text = decode(&data[start..pos], encoding)?;
             if !text.is_empty() {
                 strings.push(text);
             }
@@ -88,7 +125,7 @@ pub fn decode_text_with_encoding(data: &[u8], encoding: TextEncoding) -> Result<
         } else {
             // No terminator found, include remaining data if any
             if pos < data.len() {
-                let text = decode_text_with_encoding_simple(&data[pos..], encoding)?;
+                let text = decode(&data[pos..], encoding)?;
                 if !text.is_empty() {
                     strings.push(text);
                 }
@@ -97,26 +134,24 @@ pub fn decode_text_with_encoding(data: &[u8], encoding: TextEncoding) -> Result<
         }
     }
 
-    let primary_text = if strings.is_empty() {
-        String::new()
-    } else {
-        strings[0].clone()
-    };
+    Ok(strings)
+}
 
-    Ok((primary_text, strings))
+/// Decode single text string with specified encoding (normalized to NFC with `--nfc`)
+pub fn decode_text_with_encoding_simple(data: &[u8], encoding: TextEncoding) -> Result<String, SupertoolError> {
+    decode_stored_text(data, encoding).map(normalize_decoded)
 }
 
-/// Decode single text string with specified encoding
-pub fn decode_text_with_encoding_simple(data: &[u8], encoding: TextEncoding) -> Result<String, String> {
+fn decode_stored_text(data: &[u8], encoding: TextEncoding) -> Result<String, SupertoolError> {
     match encoding {
-        | TextEncoding::Iso88591 => Ok(decode_iso88591_string(data)),
+        | TextEncoding::Iso88591 => Ok(decode_legacy_string(data)),
         | TextEncoding::Utf8 => Ok(String::from_utf8_lossy(data).to_string()),
         | TextEncoding::Utf16Bom | TextEncoding::Utf16Be => decode_utf16_string(data, encoding),
     }
 }
 
 /// Split text data into two parts at first null terminator
-pub fn split_terminated_text(data: &[u8], encoding: TextEncoding) -> Result<(String, String), String> {
+pub fn split_terminated_text(data: &[u8], encoding: TextEncoding) -> Result<(String, String), SupertoolError> {
     let (first_bytes, second_bytes) = find_text_terminator(data, encoding)?;
     let first = decode_text_with_encoding_simple(first_bytes, encoding)?;
     let second = decode_text_with_encoding_simple(second_bytes, encoding)?;
@@ -124,7 +159,7 @@ pub fn split_terminated_text(data: &[u8], encoding: TextEncoding) -> Result<(Str
 }
 
 /// Find first null terminator and split data
-pub fn find_text_terminator(data: &[u8], encoding: TextEncoding) -> Result<(&[u8], &[u8]), String> {
+pub fn find_text_terminator(data: &[u8], encoding: TextEncoding) -> Result<(&[u8], &[u8]), SupertoolError> {
     let terminator_len = get_terminator_length(encoding);
     let mut pos = 0;
 
@@ -150,18 +185,68 @@ pub fn get_terminator_length(encoding: TextEncoding) -> usize {
 /// Check if bytes represent null terminator for encoding
 pub fn is_null_terminator(bytes: &[u8], encoding: TextEncoding) -> bool {
     match encoding {
-        | TextEncoding::Iso88591 | TextEncoding::Utf8 => bytes.len() >= 1 && bytes[0] == 0,
+        | TextEncoding::Iso88591 | TextEncoding::Utf8 => !bytes.is_empty() && bytes[0] == 0,
         | TextEncoding::Utf16Bom | TextEncoding::Utf16Be => bytes.len() >= 2 && bytes[0] == 0 && bytes[1] == 0,
     }
 }
 
+/// Encode a single string (without terminator) in the given encoding
+///
+/// UTF-16 with BOM is written little-endian with a leading BOM. Characters outside
+/// ISO-8859-1 are replaced by '?' when encoding to ISO-8859-1.
+pub fn encode_text(text: &str, encoding: TextEncoding) -> Vec<u8> {
+    match encoding {
+        | TextEncoding::Iso88591 => text
+            .chars()
+            .map(|c| {
+                if (c as u32) <= 0xFF {
+                    c as u8
+                } else {
+                    b'?'
+                }
+            })
+            .collect(),
+        | TextEncoding::Utf8 => text.as_bytes().to_vec(),
+        | TextEncoding::Utf16Bom => {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+            bytes
+        }
+        | TextEncoding::Utf16Be => text.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect(),
+    }
+}
+
+/// Check whether `text` can be encoded without loss (only ISO-8859-1 is restricted)
+pub fn can_encode(text: &str, encoding: TextEncoding) -> bool {
+    encoding != TextEncoding::Iso88591 || text.chars().all(|c| (c as u32) <= 0xFF)
+}
+
+/// Null terminator bytes for the given encoding
+pub fn terminator_bytes(encoding: TextEncoding) -> &'static [u8] {
+    match encoding {
+        | TextEncoding::Iso88591 | TextEncoding::Utf8 => &[0],
+        | TextEncoding::Utf16Bom | TextEncoding::Utf16Be => &[0, 0],
+    }
+}
+
 /// Decode ISO-8859-1 string
 pub fn decode_iso88591_string(data: &[u8]) -> String {
     data.iter().map(|&b| b as char).collect()
 }
 
+/// Decode text declared as ISO-8859-1, in the assumed code page if one was chosen
+pub fn decode_legacy_string(data: &[u8]) -> String {
+    let codepage = match assumed_encoding() {
+        | Some(AssumedEncoding::Cp1252) => encoding_rs::WINDOWS_1252,
+        | Some(AssumedEncoding::Cp1251) => encoding_rs::WINDOWS_1251,
+        | Some(AssumedEncoding::ShiftJis) => encoding_rs::SHIFT_JIS,
+        | None => return decode_iso88591_string(data),
+    };
+    codepage.decode_without_bom_handling(data).0.into_owned()
+}
+
 /// Decode UTF-16 string
-pub fn decode_utf16_string(data: &[u8], encoding: TextEncoding) -> Result<String, String> {
+pub fn decode_utf16_string(data: &[u8], encoding: TextEncoding) -> Result<String, SupertoolError> {
     if data.is_empty() {
         return Ok(String::new());
     }
@@ -181,12 +266,12 @@ pub fn decode_utf16_string(data: &[u8], encoding: TextEncoding) -> Result<String
             }
         }
         | TextEncoding::Utf16Be => (0, false), // Always big endian
-        | _ => return Err("Invalid UTF-16 encoding".to_string()),
+        | _ => return Err(SupertoolError::BadEncoding("Invalid UTF-16 encoding".to_string())),
     };
 
     let utf16_data = &data[start_pos..];
-    if utf16_data.len() % 2 != 0 {
-        return Err("UTF-16 data length must be even".to_string());
+    if !utf16_data.len().is_multiple_of(2) {
+        return Err(SupertoolError::BadEncoding("UTF-16 data length must be even".to_string()));
     }
 
     let mut utf16_chars = Vec::new();
@@ -199,5 +284,5 @@ pub fn decode_utf16_string(data: &[u8], encoding: TextEncoding) -> Result<String
         utf16_chars.push(code_unit);
     }
 
-    String::from_utf16(&utf16_chars).map_err(|_| "Invalid UTF-16 sequence".to_string())
+    String::from_utf16(&utf16_chars).map_err(|_| SupertoolError::BadEncoding("Invalid UTF-16 sequence".to_string()))
 }
@@ -2,10 +2,16 @@
 ///
 /// Structure: Text encoding + Information
 /// Examples: TIT2, TALB, TPE1, TPE2, TCON, TYER, etc.
-use crate::id3v2_text_encoding::{TextEncoding, decode_text_with_encoding};
+use crate::error::SupertoolError;
+use crate::id3v2_text_encoding::{TextEncoding, decode_text_with_encoding, encode_text, terminator_bytes};
+use crate::text_truncate::truncate_text;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+/// Separator for multiple values in ID3v2.3, which has no null-separated multi-value text frames
+pub const ID3V2_3_VALUE_SEPARATOR: &str = "/";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextFrame {
     pub encoding: TextEncoding,
     pub text: String,
@@ -14,15 +20,21 @@ pub struct TextFrame {
 }
 
 impl TextFrame {
+    /// Create a text frame with one or more values
+    pub fn new(encoding: TextEncoding, strings: Vec<String>) -> Self {
+        let text = strings.first().cloned().unwrap_or_default();
+        TextFrame { encoding, text, strings }
+    }
+
     /// Parse a text frame from raw data
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
         if data.is_empty() {
-            return Err("Text frame data is empty".to_string());
+            return Err(SupertoolError::TruncatedFrame("Text frame data is empty".to_string()));
         }
 
         let encoding = TextEncoding::from_byte(data[0])?;
         if data.len() < 2 {
-            return Err("Text frame data too short".to_string());
+            return Err(SupertoolError::TruncatedFrame("Text frame data too short".to_string()));
         }
 
         let text_data = &data[1..];
@@ -35,6 +47,30 @@ impl TextFrame {
     pub fn primary_text(&self) -> &str {
         &self.text
     }
+
+    /// Get all values (several for ID3v2.4 multi-value frames such as TPE1 or TCON)
+    pub fn values(&self) -> &[String] {
+        &self.strings
+    }
+
+    /// Serialize the frame content (encoding byte followed by the values) for the given ID3v2 version
+    ///
+    /// ID3v2.4 separates the values with null terminators. ID3v2.3 has no multi-value
+    /// text frames, so the values are joined with `ID3V2_3_VALUE_SEPARATOR` there.
+    pub fn to_bytes(&self, version_major: u8) -> Vec<u8> {
+        let mut bytes = vec![self.encoding as u8];
+        if version_major < 4 {
+            bytes.extend(encode_text(&self.strings.join(ID3V2_3_VALUE_SEPARATOR), self.encoding));
+            return bytes;
+        }
+        for (i, string) in self.strings.iter().enumerate() {
+            if i > 0 {
+                bytes.extend_from_slice(terminator_bytes(self.encoding));
+            }
+            bytes.extend(encode_text(string, self.encoding));
+        }
+        bytes
+    }
 }
 
 impl fmt::Display for TextFrame {
@@ -43,10 +79,10 @@ impl fmt::Display for TextFrame {
         if self.strings.len() > 1 {
             writeln!(f, "Values ({} strings):", self.strings.len())?;
             for (i, string) in self.strings.iter().enumerate() {
-                writeln!(f, "  [{}] \"{}\"", i + 1, string)?;
+                writeln!(f, "  [{}] \"{}\"", i + 1, truncate_text(string))?;
             }
         } else if !self.text.is_empty() {
-            writeln!(f, "Value: \"{}\"", self.text)?;
+            writeln!(f, "Value: \"{}\"", truncate_text(&self.text))?;
         }
         Ok(())
     }
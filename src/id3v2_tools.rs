@@ -1,10 +1,65 @@
-use std::fs::File;
+use crate::media_source::MediaSource;
+use crate::options::CommonOptions;
 use std::io::Write;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::SeekFrom;
 
 /// ID3v2 header information: (major_version, minor_version, flags, size)
 pub type Id3v2Header = (u8, u8, u8, u32);
 
+/// Nesting levels of CHAP/CTOC frames and CTOC references followed by default (`--max-frame-depth`)
+pub const DEFAULT_MAX_EMBEDDING_DEPTH: usize = 8;
+
+/// Deepest nesting level of CHAP/CTOC sub-frames and CTOC references that is still dissected
+///
+/// Crafted tags can nest CHAP/CTOC frames or chain CTOC references thousands of levels deep,
+/// which would overflow the stack of the recursive parser and renderer.
+pub fn max_embedding_depth() -> usize {
+    CommonOptions::current().max_frame_depth
+}
+
+/// Whether de-facto experimental frames are parsed (`--lenient`)
+pub fn lenient() -> bool {
+    CommonOptions::current().lenient
+}
+
+/// Whether the frame ID is in the experimental space (first character X, Y or Z)
+///
+/// The ID3v2 specifications leave these IDs free for everyone to use, so they are valid in
+/// every version even though their content is not defined.
+pub fn is_experimental_frame(frame_id: &str) -> bool {
+    frame_id.len() == 4 && frame_id.starts_with(['X', 'Y', 'Z']) && frame_id.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+}
+
+/// Whether the frame is one of the non-standard frames iTunes writes into podcast episodes
+///
+/// iTunes writes them into ID3v2.3 and ID3v2.4 tags alike, and other podcast tools followed,
+/// so they are accepted in both versions.
+pub fn is_podcast_frame(frame_id: &str) -> bool {
+    matches!(frame_id, "PCST" | "TGID" | "WFED" | "TDES" | "TCAT" | "TKWD")
+}
+
+/// Whether the frame is one of the non-standard text frames iTunes writes for compilations and sorting
+///
+/// Like the podcast frames, they appear in tags of every version (TCP, TS2 and TSC in ID3v2.2).
+pub fn is_itunes_frame(frame_id: &str) -> bool {
+    matches!(frame_id, "TCMP" | "TSO2" | "TSOC")
+}
+
+/// Standard frame whose layout a de-facto experimental frame uses
+///
+/// iTunes and other taggers wrote the ID3v2.4 sort order and original release frames into
+/// ID3v2.3 tags with an X prefix; XRVA is RVA2 for ID3v2.3 tags (normalize and others).
+pub fn de_facto_layout(frame_id: &str) -> Option<&'static str> {
+    match frame_id {
+        | "XSOA" => Some("TSOA"),
+        | "XSOP" => Some("TSOP"),
+        | "XSOT" => Some("TSOT"),
+        | "XDOR" => Some("TDOR"),
+        | "XRVA" => Some("RVA2"),
+        | _ => None,
+    }
+}
+
 /// Get a human-readable description for an ID3v2 frame ID (unified for v2.3 and v2.4)
 pub fn get_frame_description(frame_id: &str) -> &'static str {
     match frame_id {
@@ -113,6 +168,27 @@ pub fn get_frame_description(frame_id: &str) -> &'static str {
         | "CHAP" => "Chapter frame",
         | "CTOC" => "Table of contents frame",
 
+        // iTunes podcast frames (non-standard)
+        | "PCST" => "Podcast flag (iTunes)",
+        | "TGID" => "Podcast episode GUID (iTunes)",
+        | "WFED" => "Podcast feed URL (iTunes)",
+        | "TDES" => "Podcast description (iTunes)",
+        | "TCAT" => "Podcast category (iTunes)",
+        | "TKWD" => "Podcast keywords (iTunes)",
+
+        // iTunes compilation and sort order frames (non-standard)
+        | "TCMP" => "Part of a compilation (iTunes)",
+        | "TSO2" => "Album artist sort order (iTunes)",
+        | "TSOC" => "Composer sort order (iTunes)",
+
+        // De-facto experimental frames (parsed with --lenient)
+        | "XSOA" => "Album sort order (experimental, TSOA layout)",
+        | "XSOP" => "Performer sort order (experimental, TSOP layout)",
+        | "XSOT" => "Title sort order (experimental, TSOT layout)",
+        | "XDOR" => "Original release time (experimental, TDOR layout)",
+        | "XRVA" => "Relative volume adjustment (experimental, RVA2 layout)",
+        | id if is_experimental_frame(id) => "Experimental frame",
+
         | _ => "Unknown frame type",
     }
 }
@@ -138,7 +214,10 @@ pub fn detect_mpeg_sync(header: &[u8]) -> bool {
 }
 
 /// Read and parse ID3v2 header, returning version info and tag size
-pub fn read_id3v2_header(file: &mut File) -> Result<Option<Id3v2Header>, Box<dyn std::error::Error>> {
+///
+/// The raw header and size bytes are printed with `show_raw` (`--header`); violations of the
+/// synchsafe size format are reported either way.
+pub fn read_id3v2_header(file: &mut dyn MediaSource, show_raw: bool) -> Result<Option<Id3v2Header>, Box<dyn std::error::Error>> {
     // Seek to beginning and read ID3v2 header
     file.seek(SeekFrom::Start(0))?;
     let mut id3_header = [0u8; 10];
@@ -156,28 +235,32 @@ pub fn read_id3v2_header(file: &mut File) -> Result<Option<Id3v2Header>, Box<dyn
     let flags = id3_header[5];
 
     // Add diagnostic output for raw header bytes
-    println!(
-        "  Raw header bytes: [0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}]",
-        id3_header[0], id3_header[1], id3_header[2], id3_header[3], id3_header[4], id3_header[5], id3_header[6], id3_header[7], id3_header[8], id3_header[9]
-    );
+    if show_raw {
+        outln!(
+            "  Raw header bytes: [0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}]",
+            id3_header[0], id3_header[1], id3_header[2], id3_header[3], id3_header[4], id3_header[5], id3_header[6], id3_header[7], id3_header[8], id3_header[9]
+        );
+    }
 
     // Calculate tag size (synchsafe integer)
     let size = decode_synchsafe_int(&id3_header[6..10]);
 
     // Add diagnostic for size bytes
-    println!("  Size bytes: [0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}]", id3_header[6], id3_header[7], id3_header[8], id3_header[9]);
+    if show_raw {
+        outln!("  Size bytes: [0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}]", id3_header[6], id3_header[7], id3_header[8], id3_header[9]);
+    }
 
     // Validate synchsafe format (each byte should have MSB = 0)
     let mut synchsafe_violation = false;
     for (i, &byte) in id3_header[6..10].iter().enumerate() {
         if byte & 0x80 != 0 {
-            println!("  WARNING: Size byte {} (0x{:02X}) violates synchsafe format (MSB set)!", i, byte);
+            outln!("  WARNING: Size byte {} (0x{:02X}) violates synchsafe format (MSB set)!", i, byte);
             synchsafe_violation = true;
         }
     }
 
     if synchsafe_violation {
-        println!("  ERROR: Invalid synchsafe format detected in size field");
+        outln!("  ERROR: Invalid synchsafe format detected in size field");
     }
 
     Ok(Some((version_major, version_minor, flags, size)))
@@ -192,6 +275,19 @@ pub fn decode_synchsafe_int(bytes: &[u8]) -> u32 {
     }
 }
 
+/// Largest value a synchsafe integer holds (28 bits)
+pub const MAX_SYNCHSAFE: u32 = 0x0FFF_FFFF;
+
+/// Encode a value as a synchsafe integer (7 bits per byte) as used in ID3v2
+pub fn encode_synchsafe_int(value: u32) -> [u8; 4] {
+    [
+        ((value >> 21) & 0x7F) as u8,
+        ((value >> 14) & 0x7F) as u8,
+        ((value >> 7) & 0x7F) as u8,
+        (value & 0x7F) as u8,
+    ]
+}
+
 /// Remove unsynchronization bytes (0xFF 0x00 -> 0xFF) from ID3v2 data
 pub fn remove_unsynchronization(data: &[u8]) -> Vec<u8> {
     let mut result = Vec::new();
@@ -243,18 +339,28 @@ pub fn is_valid_id3v2_4_frame(frame_id: &str) -> bool {
     VALID_ID3V2_4_FRAME_IDS.contains(&frame_id)
 }
 
-/// Check if a frame ID is valid for a specific ID3v2 version
+/// Frame ID for display: the characters of a printable ASCII ID, otherwise the bytes in hex (e.g. "0x544954E9")
+pub fn frame_id_to_string(id_bytes: &[u8]) -> String {
+    if id_bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        String::from_utf8_lossy(id_bytes).to_string()
+    } else {
+        format!("0x{}", id_bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+    }
+}
+
+/// Check if a frame ID is valid for a specific ID3v2 version (experimental IDs and the iTunes frames are valid in both)
 pub fn is_valid_frame_for_version(frame_id: &str, version_major: u8) -> bool {
     match version_major {
-        | 3 => is_valid_id3v2_3_frame(frame_id),
-        | 4 => is_valid_id3v2_4_frame(frame_id),
+        | 3 => is_valid_id3v2_3_frame(frame_id) || is_experimental_frame(frame_id) || is_podcast_frame(frame_id) || is_itunes_frame(frame_id),
+        | 4 => is_valid_id3v2_4_frame(frame_id) || is_experimental_frame(frame_id) || is_podcast_frame(frame_id) || is_itunes_frame(frame_id),
         | _ => false, // Unsupported version
     }
 }
 
 /// Parse embedded frames from raw frame data
-/// Used by both CHAP and CTOC frames to parse their embedded sub-frames
-pub fn parse_embedded_frames(frame_data: &[u8], version_major: u8) -> Vec<crate::id3v2_frame::Id3v2Frame> {
+/// Used by both CHAP and CTOC frames to parse their embedded sub-frames; `depth` is the nesting
+/// level of the sub-frames (1 for the sub-frames of a top-level frame)
+pub fn parse_embedded_frames(frame_data: &[u8], version_major: u8, depth: usize) -> Vec<crate::id3v2_frame::Id3v2Frame> {
     let mut embedded_frames = Vec::new();
     let mut pos = 0;
 
@@ -284,25 +390,25 @@ pub fn parse_embedded_frames(frame_data: &[u8], version_major: u8) -> Vec<crate:
         let frame_flags = u16::from_be_bytes([frame_data[pos + 8], frame_data[pos + 9]]);
 
         // Ensure we have enough data for the complete frame
-        if pos + 10 + frame_size as usize > frame_data.len() {
+        let Ok(range) = crate::checked_size::data_range("sub-frame", pos, 10, frame_size as u64, frame_data.len()) else {
             break;
-        }
+        };
 
         // Extract frame data
-        let data = frame_data[pos + 10..pos + 10 + frame_size as usize].to_vec();
+        let data = frame_data[range.clone()].to_vec();
 
         // Create the embedded frame with relative offset within the parent frame
         let mut embedded_frame = crate::id3v2_frame::Id3v2Frame::new_with_offset(frame_id, frame_size, frame_flags, pos, data);
 
         // Parse the embedded frame content for rich display
-        if let Err(_e) = embedded_frame.parse_content(version_major) {
+        if let Err(_e) = embedded_frame.parse_content_at_depth(version_major, depth) {
             // If parsing fails, we still keep the frame with raw data
         }
 
         embedded_frames.push(embedded_frame);
 
         // Move to next frame
-        pos += 10 + frame_size as usize;
+        pos = range.end;
     }
 
     embedded_frames
@@ -312,7 +418,7 @@ pub fn parse_embedded_frames(frame_data: &[u8], version_major: u8) -> Vec<crate:
 /// This function provides unified frame header display for both top-level and embedded frames
 pub fn display_frame_header(output: &mut dyn Write, frame: &crate::id3v2_frame::Id3v2Frame, indentation: &str) -> std::io::Result<()> {
     // Extract the individual bytes from the frame ID for diagnostic display
-    let id_bytes = frame.id.as_bytes();
+    let id_bytes = frame.id_bytes();
     let size_bytes = frame.size.to_be_bytes();
 
     // Display frame header information in the same format as top-level frames
@@ -356,3 +462,30 @@ pub fn display_frame_header(output: &mut dyn Write, frame: &crate::id3v2_frame::
 
     Ok(())
 }
+
+/// Describe the frame header flags that are set, using the names from the ID3v2.3/2.4 specifications
+pub fn describe_frame_flags(flags: u16, version_major: u8) -> Vec<&'static str> {
+    let known_flags: &[(u16, &'static str)] = if version_major == 4 {
+        &[
+            (0x4000, "tag_alter_preservation"),
+            (0x2000, "file_alter_preservation"),
+            (0x1000, "read_only"),
+            (0x0040, "grouping_identity"),
+            (0x0008, "compression"),
+            (0x0004, "encryption"),
+            (0x0002, "unsynchronisation"),
+            (0x0001, "data_length_indicator"),
+        ]
+    } else {
+        &[
+            (0x8000, "tag_alter_preservation"),
+            (0x4000, "file_alter_preservation"),
+            (0x2000, "read_only"),
+            (0x0080, "compression"),
+            (0x0040, "encryption"),
+            (0x0020, "grouping_identity"),
+        ]
+    };
+
+    known_flags.iter().filter(|(mask, _)| flags & mask != 0).map(|(_, name)| *name).collect()
+}
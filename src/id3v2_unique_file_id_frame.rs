@@ -1,10 +1,12 @@
 /// Unique File Identifier Frame (UFID)
 ///
 /// Structure: Owner identifier + Identifier
+use crate::error::SupertoolError;
 use crate::id3v2_text_encoding::decode_iso88591_string;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UniqueFileIdFrame {
     pub owner_identifier: String,
     pub identifier: Vec<u8>,
@@ -12,9 +14,9 @@ pub struct UniqueFileIdFrame {
 
 impl UniqueFileIdFrame {
     /// Parse a UFID frame from raw data
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
         if data.is_empty() {
-            return Err("UFID frame data is empty".to_string());
+            return Err(SupertoolError::TruncatedFrame("UFID frame data is empty".to_string()));
         }
 
         // Find null terminator for owner identifier
@@ -23,7 +25,7 @@ impl UniqueFileIdFrame {
             pos += 1;
         }
         if pos >= data.len() {
-            return Err("UFID owner identifier not null-terminated".to_string());
+            return Err(SupertoolError::TruncatedFrame("UFID owner identifier not null-terminated".to_string()));
         }
 
         let owner_identifier = decode_iso88591_string(&data[0..pos]);
@@ -32,7 +34,7 @@ impl UniqueFileIdFrame {
         // Identifier is the rest of the data (up to 64 bytes)
         let identifier = data[pos..].to_vec();
         if identifier.len() > 64 {
-            return Err("UFID identifier too long (max 64 bytes)".to_string());
+            return Err(SupertoolError::InvalidFrame("UFID identifier too long (max 64 bytes)".to_string()));
         }
 
         Ok(UniqueFileIdFrame { owner_identifier, identifier })
@@ -2,17 +2,19 @@
 ///
 /// Structure: URL (text string)
 /// Examples: WCOM, WCOP, WOAF, WOAR, WOAS, WORS, WPAY, WPUB
+use crate::error::SupertoolError;
 use crate::id3v2_text_encoding::decode_iso88591_string;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UrlFrame {
     pub url: String,
 }
 
 impl UrlFrame {
     /// Parse a URL frame from raw data
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
         // URL frames are always encoded in ISO-8859-1
         let url = decode_iso88591_string(data);
         Ok(UrlFrame { url })
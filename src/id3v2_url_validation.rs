@@ -0,0 +1,94 @@
+/// Validation of URLs in W*** and WXXX frames
+///
+/// Checks the scheme against a whitelist, percent-encoding, characters outside ASCII
+/// (URL frames are ISO-8859-1, UTF-8 bytes show up as mojibake) and common typos such
+/// as spaces or a missing scheme.
+use crate::finding::{Finding, Severity};
+
+/// Schemes accepted in URL frames
+const ALLOWED_SCHEMES: [&str; 4] = ["http", "https", "ftp", "mailto"];
+
+/// Validate the URL of the frame at `location` and return all findings
+pub fn validate_url(url: &str, location: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let url = url.trim_end_matches('\0');
+
+    if url.trim().is_empty() {
+        findings.push(Finding::new(Severity::Error, location, "URL is empty"));
+        return findings;
+    }
+
+    if url.trim() != url {
+        findings.push(Finding::new(Severity::Warning, location, "URL has leading or trailing whitespace"));
+    }
+    if url.trim().contains(' ') {
+        findings.push(Finding::new(Severity::Warning, location, "URL contains spaces (should be encoded as %20)"));
+    }
+    if url.chars().any(|c| c.is_control()) {
+        findings.push(Finding::new(Severity::Error, location, "URL contains control characters"));
+    }
+
+    // The decoded ISO-8859-1 characters map 1:1 back to the stored bytes
+    let bytes: Vec<u8> = url.chars().map(|c| c as u32 as u8).collect();
+    if !bytes.is_ascii() {
+        let message = match std::str::from_utf8(&bytes) {
+            | Ok(decoded) => format!("URL contains UTF-8 bytes (\"{}\"), but URL frames are ISO-8859-1; non-ASCII characters must be percent-encoded", decoded),
+            | Err(_) => "URL contains non-ASCII characters that must be percent-encoded".to_string(),
+        };
+        findings.push(Finding::new(Severity::Warning, location, message));
+    }
+
+    if let Some(position) = invalid_percent_encoding(url) {
+        findings.push(Finding::new(Severity::Warning, location, format!("invalid percent-encoding at position {}", position)));
+    }
+
+    findings.extend(check_scheme(url.trim(), location));
+    findings
+}
+
+/// Check the scheme and the part right after it
+fn check_scheme(url: &str, location: &str) -> Option<Finding> {
+    if url.contains('\\') && !url.contains("://") {
+        return Some(Finding::new(Severity::Error, location, "URL uses backslashes instead of \"//\""));
+    }
+
+    let Some(colon) = url.find(':').filter(|&colon| colon > 0 && url[..colon].chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))) else {
+        let hint = if url.starts_with("www.") {
+            " (add https://)"
+        } else {
+            ""
+        };
+        return Some(Finding::new(Severity::Error, location, format!("URL has no scheme{}", hint)));
+    };
+
+    let scheme = url[..colon].to_ascii_lowercase();
+    let rest = &url[colon + 1..];
+
+    if !ALLOWED_SCHEMES.contains(&scheme.as_str()) {
+        let hint = match scheme.as_str() {
+            | "htp" | "htt" | "hhtp" | "http2" | "htps" | "htttp" | "htttps" | "hhtps" | "httsp" => " (typo?)",
+            | _ => "",
+        };
+        return Some(Finding::new(Severity::Error, location, format!("scheme '{}' is not allowed{} (expected http, https, ftp or mailto)", scheme, hint)));
+    }
+
+    if scheme == "mailto" {
+        return (!rest.contains('@')).then(|| Finding::new(Severity::Warning, location, "mailto URL has no e-mail address"));
+    }
+
+    match rest.strip_prefix("//") {
+        | None => Some(Finding::new(Severity::Error, location, format!("'{}:' must be followed by \"//\"", scheme))),
+        | Some(authority) if authority.is_empty() || authority.starts_with('/') => Some(Finding::new(Severity::Error, location, "URL has no host name")),
+        | Some(_) if scheme == "http" => Some(Finding::new(Severity::Info, location, "URL uses unencrypted http")),
+        | Some(_) => None,
+    }
+}
+
+/// Position of the first '%' that is not followed by two hex digits
+fn invalid_percent_encoding(url: &str) -> Option<usize> {
+    let chars: Vec<char> = url.chars().collect();
+    chars.iter().enumerate().find_map(|(i, &c)| {
+        let valid = chars.len() > i + 2 && chars[i + 1].is_ascii_hexdigit() && chars[i + 2].is_ascii_hexdigit();
+        (c == '%' && !valid).then_some(i)
+    })
+}
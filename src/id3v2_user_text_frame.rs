@@ -1,10 +1,13 @@
 /// User-Defined Text Information Frame (TXXX)
 ///
 /// Structure: Text encoding + Description + Value
-use crate::id3v2_text_encoding::{TextEncoding, split_terminated_text};
+use crate::error::SupertoolError;
+use crate::id3v2_text_encoding::{TextEncoding, encode_text, split_terminated_text, terminator_bytes};
+use crate::text_truncate::truncate_text;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserTextFrame {
     pub encoding: TextEncoding,
     pub description: String,
@@ -13,14 +16,14 @@ pub struct UserTextFrame {
 
 impl UserTextFrame {
     /// Parse a TXXX frame from raw data
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
         if data.is_empty() {
-            return Err("User text frame data is empty".to_string());
+            return Err(SupertoolError::TruncatedFrame("User text frame data is empty".to_string()));
         }
 
         let encoding = TextEncoding::from_byte(data[0])?;
         if data.len() < 2 {
-            return Err("User text frame data too short".to_string());
+            return Err(SupertoolError::TruncatedFrame("User text frame data too short".to_string()));
         }
 
         let text_data = &data[1..];
@@ -28,13 +31,22 @@ impl UserTextFrame {
 
         Ok(UserTextFrame { encoding, description, value })
     }
+
+    /// Serialize the frame content (encoding byte, terminated description, value)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.encoding as u8];
+        bytes.extend(encode_text(&self.description, self.encoding));
+        bytes.extend_from_slice(terminator_bytes(self.encoding));
+        bytes.extend(encode_text(&self.value, self.encoding));
+        bytes
+    }
 }
 
 impl fmt::Display for UserTextFrame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Encoding: {}", self.encoding)?;
-        writeln!(f, "Description: \"{}\"", self.description)?;
-        writeln!(f, "Value: \"{}\"", self.value)?;
+        writeln!(f, "Description: \"{}\"", truncate_text(&self.description))?;
+        writeln!(f, "Value: \"{}\"", truncate_text(&self.value))?;
         Ok(())
     }
 }
@@ -1,10 +1,12 @@
 /// User-Defined URL Link Frame (WXXX)
 ///
 /// Structure: Text encoding + Description + URL
-use crate::id3v2_text_encoding::{TextEncoding, decode_iso88591_string, decode_text_with_encoding_simple, find_text_terminator};
+use crate::error::SupertoolError;
+use crate::id3v2_text_encoding::{TextEncoding, decode_iso88591_string, decode_text_with_encoding_simple, encode_text, find_text_terminator, terminator_bytes};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserUrlFrame {
     pub encoding: TextEncoding,
     pub description: String,
@@ -13,14 +15,14 @@ pub struct UserUrlFrame {
 
 impl UserUrlFrame {
     /// Parse a WXXX frame from raw data
-    pub fn parse(data: &[u8]) -> Result<Self, String> {
+    pub fn parse(data: &[u8]) -> Result<Self, SupertoolError> {
         if data.is_empty() {
-            return Err("User URL frame data is empty".to_string());
+            return Err(SupertoolError::TruncatedFrame("User URL frame data is empty".to_string()));
         }
 
         let encoding = TextEncoding::from_byte(data[0])?;
         if data.len() < 2 {
-            return Err("User URL frame data too short".to_string());
+            return Err(SupertoolError::TruncatedFrame("User URL frame data too short".to_string()));
         }
 
         let text_data = &data[1..];
@@ -34,6 +36,15 @@ impl UserUrlFrame {
 
         Ok(UserUrlFrame { encoding, description, url })
     }
+
+    /// Serialize the frame content (encoding byte, terminated description, ISO-8859-1 URL)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.encoding as u8];
+        bytes.extend(encode_text(&self.description, self.encoding));
+        bytes.extend_from_slice(terminator_bytes(self.encoding));
+        bytes.extend(encode_text(&self.url, TextEncoding::Iso88591));
+        bytes
+    }
 }
 
 impl fmt::Display for UserUrlFrame {
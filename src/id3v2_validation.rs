@@ -0,0 +1,527 @@
+/// Spec-compliance checks of ID3v2 tags with stable rule IDs (`validate`)
+///
+/// The dissectors read tags leniently: a frame that breaks the specification is kept as raw data
+/// and players are assumed to cope. Publishing pipelines want the opposite, a verdict per file
+/// that fails the build when a tag is broken. These checks walk the raw tag once more and report
+/// every violation under the ID of the rule it breaks, with the severity of that rule, so a
+/// pipeline can ignore single rules and match on IDs instead of on messages.
+///
+/// ID3v2.2 tags are checked up to the tag header; their frames are not validated.
+use crate::batch_errors::{BatchErrors, ReadErrorKind};
+use crate::checked_size::data_range;
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_frame_format::FrameFormat;
+use crate::id3v2_text_encoding::TextEncoding;
+use crate::id3v2_tools::{decode_synchsafe_int, frame_id_to_string, is_valid_frame_for_version, remove_unsynchronization};
+use crate::media_source::MediaSource;
+use crate::path_display::display_path;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+
+/// A check with a stable ID and the severity of its violations
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+}
+
+pub const TAG_VERSION: Rule = Rule { id: "tag-version", severity: Severity::Error, description: "The tag has a major version other than 2, 3 or 4" };
+pub const TAG_FLAGS: Rule = Rule { id: "tag-flags", severity: Severity::Error, description: "The tag header sets flags the version does not define" };
+pub const TAG_SIZE: Rule = Rule { id: "tag-size", severity: Severity::Error, description: "The tag header claims more bytes than the file has" };
+pub const SYNCHSAFE_SIZE: Rule = Rule { id: "synchsafe-size", severity: Severity::Error, description: "A size that has to be synchsafe (tag, ID3v2.4 extended header and frames) has a byte with the high bit set" };
+pub const FRAME_SIZE: Rule = Rule { id: "frame-size", severity: Severity::Error, description: "A frame or extended header extends past the end of the tag" };
+pub const FRAME_ID_CHARACTERS: Rule = Rule { id: "frame-id-characters", severity: Severity::Error, description: "A frame ID has characters other than A-Z and 0-9" };
+pub const FRAME_ID_VERSION: Rule = Rule { id: "frame-id-version", severity: Severity::Error, description: "A frame ID belongs to the other ID3v2 version (TYER in ID3v2.4, TDRC in ID3v2.3)" };
+pub const FRAME_ID_UNKNOWN: Rule = Rule { id: "frame-id-unknown", severity: Severity::Warning, description: "A frame ID is defined by no ID3v2 version and is not in the experimental X, Y, Z space" };
+pub const ENCODING_BYTE: Rule = Rule { id: "encoding-byte", severity: Severity::Error, description: "A text encoding byte is unknown, or UTF-16BE/UTF-8 in an ID3v2.3 tag" };
+pub const ENCODING_DATA: Rule = Rule { id: "encoding-data", severity: Severity::Error, description: "Text is not valid in its declared encoding (invalid UTF-8, UTF-16 without byte order mark, odd length, unpaired surrogates)" };
+pub const UNTERMINATED_STRING: Rule = Rule { id: "unterminated-string", severity: Severity::Error, description: "A string that other fields follow has no terminator" };
+pub const FRAME_CONTENT: Rule = Rule { id: "frame-content", severity: Severity::Error, description: "Frame content that cannot be read (truncated fields, bad format flags)" };
+pub const CTOC_MISSING_CHILD: Rule = Rule { id: "ctoc-missing-child", severity: Severity::Error, description: "A CTOC frame lists a child element ID that no CHAP or CTOC frame has" };
+pub const LANGUAGE_CODE: Rule = Rule { id: "language-code", severity: Severity::Error, description: "A COMM, USLT, SYLT or USER language is not three letters (ISO 639-2)" };
+pub const LANGUAGE_CASE: Rule = Rule { id: "language-case", severity: Severity::Warning, description: "A language code has upper-case letters; ISO 639-2 codes are lower case" };
+
+/// All rules, in the order of the checks
+pub const RULES: &[Rule] = &[
+    TAG_VERSION,
+    TAG_FLAGS,
+    TAG_SIZE,
+    SYNCHSAFE_SIZE,
+    FRAME_SIZE,
+    FRAME_ID_CHARACTERS,
+    FRAME_ID_VERSION,
+    FRAME_ID_UNKNOWN,
+    ENCODING_BYTE,
+    ENCODING_DATA,
+    UNTERMINATED_STRING,
+    FRAME_CONTENT,
+    CTOC_MISSING_CHILD,
+    LANGUAGE_CODE,
+    LANGUAGE_CASE,
+];
+
+/// A violation of a rule
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleFinding {
+    pub rule: &'static str,
+    #[serde(flatten)]
+    pub finding: Finding,
+}
+
+impl fmt::Display for RuleFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} [{}]: {}", self.finding.severity, self.rule, self.finding.location, self.finding.message)
+    }
+}
+
+/// Result of validating the ID3v2 tag of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct TagValidation {
+    /// Tag type ("ID3v2.4"); `None` if the file has no ID3v2 tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    pub findings: Vec<RuleFinding>,
+}
+
+impl TagValidation {
+    /// Number of findings with the given severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|finding| finding.finding.severity == severity).count()
+    }
+}
+
+/// One field of a frame that holds strings, in storage order
+enum Field {
+    /// Terminated string in the frame's text encoding
+    Encoded(&'static str),
+    /// Terminated ISO-8859-1 string
+    Latin1(&'static str),
+    /// Bytes that are not checked
+    Skip(usize),
+    /// Text in the frame's encoding up to the end, values separated by terminators
+    Text,
+}
+
+/// Fields of the frames whose strings are checked, and whether they start with an encoding byte
+fn string_fields(frame_id: &str) -> Option<(bool, &'static [Field])> {
+    Some(match frame_id {
+        | "TXXX" => (true, &[Field::Encoded("description"), Field::Text]),
+        | id if id.starts_with('T') => (true, &[Field::Text]),
+        | "WXXX" => (true, &[Field::Encoded("description")]),
+        | "COMM" | "USLT" => (true, &[Field::Skip(3), Field::Encoded("description"), Field::Text]),
+        | "SYLT" => (true, &[Field::Skip(5), Field::Encoded("content descriptor")]),
+        | "USER" => (true, &[Field::Skip(3), Field::Text]),
+        | "APIC" => (true, &[Field::Latin1("MIME type"), Field::Skip(1), Field::Encoded("description")]),
+        | "GEOB" => (true, &[Field::Latin1("MIME type"), Field::Encoded("filename"), Field::Encoded("description")]),
+        | "UFID" | "PRIV" => (false, &[Field::Latin1("owner identifier")]),
+        | _ => return None,
+    })
+}
+
+/// Collects the findings of one tag
+struct Validator {
+    version_major: u8,
+    findings: Vec<RuleFinding>,
+    /// Element IDs of all CHAP and CTOC frames
+    element_ids: HashSet<String>,
+    /// Child element IDs listed by CTOC frames, with the location of the CTOC
+    children: Vec<(String, String)>,
+}
+
+impl Validator {
+    fn report(&mut self, rule: Rule, location: &str, message: impl Into<String>) {
+        self.findings.push(RuleFinding { rule: rule.id, finding: Finding::new(rule.severity, location, message) });
+    }
+
+    /// Check the frames in `buffer` (a tag body or the sub-frames of a CHAP/CTOC frame)
+    fn check_frames(&mut self, buffer: &[u8], prefix: &str, depth: usize) {
+        let mut pos = 0;
+        while pos + 10 <= buffer.len() {
+            let id_bytes = &buffer[pos..pos + 4];
+            if id_bytes[0] == 0 {
+                break;
+            }
+            let frame_id = frame_id_to_string(id_bytes);
+            let location = format!("{}{}", prefix, frame_id);
+
+            let size_bytes = &buffer[pos + 4..pos + 8];
+            let size = if self.version_major == 4 {
+                if size_bytes.iter().any(|&byte| byte & 0x80 != 0) {
+                    self.report(SYNCHSAFE_SIZE, &location, format!("frame size bytes {} are not synchsafe", hex(size_bytes)));
+                }
+                decode_synchsafe_int(size_bytes)
+            } else {
+                u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]])
+            } as usize;
+            let start = pos + 10;
+            if size > buffer.len() - start {
+                self.report(FRAME_SIZE, &location, format!("frame claims {} bytes, only {} are left", size, buffer.len() - start));
+                break;
+            }
+            let flags = u16::from_be_bytes([buffer[pos + 8], buffer[pos + 9]]);
+            self.check_frame(&frame_id, &location, flags, &buffer[start..start + size], depth);
+            pos = start + size;
+        }
+    }
+
+    fn check_frame(&mut self, frame_id: &str, location: &str, flags: u16, data: &[u8], depth: usize) {
+        if !id_has_valid_characters(frame_id) {
+            self.report(FRAME_ID_CHARACTERS, location, "frame ID is not four characters of A-Z and 0-9");
+            return;
+        }
+        if !is_valid_frame_for_version(frame_id, self.version_major) {
+            let other = if self.version_major == 4 {
+                3
+            } else {
+                4
+            };
+            if is_valid_frame_for_version(frame_id, other) {
+                self.report(FRAME_ID_VERSION, location, format!("{} is an ID3v2.{} frame, not defined in ID3v2.{}", frame_id, other, self.version_major));
+            } else {
+                self.report(FRAME_ID_UNKNOWN, location, format!("{} is not defined by any ID3v2 version", frame_id));
+            }
+            return;
+        }
+
+        let format = match FrameFormat::read(flags, data, self.version_major) {
+            | Ok(format) => format,
+            | Err(e) => return self.report(FRAME_CONTENT, location, e.to_string()),
+        };
+        if format.encryption_method.is_some() {
+            return;
+        }
+        let content = match format.content(data) {
+            | Ok(content) => content,
+            | Err(e) => return self.report(FRAME_CONTENT, location, e.to_string()),
+        };
+
+        let before = self.findings.len();
+        self.check_strings(frame_id, location, &content);
+        let mut frame = Id3v2Frame::new_with_offset(frame_id.to_string(), data.len() as u32, flags, 0, data.to_vec());
+        // Encoding and terminator problems also fail the parse; report those only once
+        if let Err(e) = frame.parse_content_at_depth(self.version_major, depth)
+            && self.findings.len() == before
+        {
+            self.report(FRAME_CONTENT, location, e.to_string());
+        }
+
+        if let Some(language) = frame.language().map(str::to_string).or_else(|| (frame_id == "USER").then(|| String::from_utf8_lossy(content.get(1..4).unwrap_or_default()).into_owned())) {
+            self.check_language(location, &language);
+        }
+        match &frame.content {
+            | Some(Id3v2FrameContent::Chapter(chapter)) => {
+                self.element_ids.insert(chapter.element_id.clone());
+                // Sub-frames follow the element ID and the four times and offsets
+                if let Some(start) = content.iter().position(|&byte| byte == 0).map(|end| end + 17) {
+                    self.check_frames(content.get(start..).unwrap_or_default(), &format!("{}/", location), depth + 1);
+                }
+            }
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => {
+                self.element_ids.insert(toc.element_id.clone());
+                self.children.extend(toc.child_element_ids.iter().map(|child| (location.to_string(), child.clone())));
+                if let Some(start) = toc_sub_frames_start(&content) {
+                    self.check_frames(&content[start..], &format!("{}/", location), depth + 1);
+                }
+            }
+            | _ => {}
+        }
+    }
+
+    /// Check the encoding byte, the terminators and the text of the string fields
+    fn check_strings(&mut self, frame_id: &str, location: &str, content: &[u8]) {
+        let Some((has_encoding, fields)) = string_fields(frame_id) else {
+            return;
+        };
+        let mut pos = 0;
+        let mut encoding = TextEncoding::Iso88591;
+        if has_encoding {
+            let Some(&byte) = content.first() else {
+                return;
+            };
+            match TextEncoding::from_byte(byte) {
+                | Ok(declared) if declared.is_valid_for_version(self.version_major) => encoding = declared,
+                | Ok(declared) => return self.report(ENCODING_BYTE, location, format!("text encoding {} is not defined in ID3v2.{}", declared, self.version_major)),
+                | Err(_) => return self.report(ENCODING_BYTE, location, format!("unknown text encoding {}", byte)),
+            }
+            pos = 1;
+        }
+
+        for field in fields {
+            match field {
+                | Field::Skip(count) => pos += count,
+                | Field::Encoded(name) | Field::Latin1(name) => {
+                    let field_encoding = match field {
+                        | Field::Latin1(_) => TextEncoding::Iso88591,
+                        | _ => encoding,
+                    };
+                    let Some(end) = find_terminator(content, pos, field_encoding) else {
+                        if pos < content.len() {
+                            self.report(UNTERMINATED_STRING, location, format!("the {} has no terminator", name));
+                        }
+                        return;
+                    };
+                    if let Some(problem) = text_problem(&content[pos..end], field_encoding) {
+                        self.report(ENCODING_DATA, location, format!("the {} {}", name, problem));
+                    }
+                    pos = end + terminator_length(field_encoding);
+                }
+                | Field::Text => {
+                    while pos < content.len() {
+                        let end = find_terminator(content, pos, encoding).unwrap_or(content.len());
+                        if let Some(problem) = text_problem(&content[pos..end], encoding) {
+                            return self.report(ENCODING_DATA, location, format!("the text {}", problem));
+                        }
+                        pos = end + terminator_length(encoding);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_language(&mut self, location: &str, language: &str) {
+        if language.len() != 3 || !language.bytes().all(|byte| byte.is_ascii_alphabetic()) {
+            self.report(LANGUAGE_CODE, location, format!("language '{}' is not an ISO 639-2 code", language.escape_default()));
+        } else if language.bytes().any(|byte| byte.is_ascii_uppercase()) && language != "XXX" {
+            self.report(LANGUAGE_CASE, location, format!("language '{}' should be '{}'", language, language.to_ascii_lowercase()));
+        }
+    }
+}
+
+/// Validate the ID3v2 tag at the beginning of the file
+pub fn validate_tag(file: &mut dyn MediaSource) -> Result<TagValidation, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut header = [0u8; 10];
+    file.seek(SeekFrom::Start(0))?;
+    if file_size < 10 || file.read_exact(&mut header).is_err() || &header[0..3] != b"ID3" {
+        return Ok(TagValidation { tag: None, findings: Vec::new() });
+    }
+
+    let version_major = header[3];
+    let flags = header[5];
+    let mut validator = Validator { version_major, findings: Vec::new(), element_ids: HashSet::new(), children: Vec::new() };
+    let validation = |validator: Validator| TagValidation { tag: Some(format!("ID3v2.{}", version_major)), findings: validator.findings };
+    if !(2..=4).contains(&version_major) {
+        validator.report(TAG_VERSION, "header", format!("version 2.{}.{} is not defined", version_major, header[4]));
+        return Ok(validation(validator));
+    }
+
+    let defined_flags = match version_major {
+        | 2 => 0xC0,
+        | 3 => 0xE0,
+        | _ => 0xF0,
+    };
+    if flags & !defined_flags != 0 {
+        validator.report(TAG_FLAGS, "header", format!("flags 0x{:02X} set, ID3v2.{} defines only 0x{:02X}", flags & !defined_flags, version_major, defined_flags));
+    }
+    if header[6..10].iter().any(|&byte| byte & 0x80 != 0) {
+        validator.report(SYNCHSAFE_SIZE, "header", format!("tag size bytes {} are not synchsafe", hex(&header[6..10])));
+    }
+    let size = decode_synchsafe_int(&header[6..10]) as u64;
+    let available = file_size - 10;
+    if size > available {
+        validator.report(TAG_SIZE, "header", format!("tag claims {} bytes, the file has {} after the header", size, available));
+    }
+    if version_major == 2 {
+        return Ok(validation(validator));
+    }
+
+    let mut body = vec![0u8; size.min(available) as usize];
+    file.read_exact(&mut body)?;
+    let buffer = if flags & 0x80 != 0 {
+        remove_unsynchronization(&body)
+    } else {
+        body
+    };
+
+    let mut pos = 0;
+    if flags & 0x40 != 0 && buffer.len() >= 4 {
+        let size_bytes = &buffer[0..4];
+        // ID3v2.4 counts the size field itself, ID3v2.3 does not
+        let (size_field, size) = if version_major == 4 {
+            if size_bytes.iter().any(|&byte| byte & 0x80 != 0) {
+                validator.report(SYNCHSAFE_SIZE, "extended header", format!("extended header size bytes {} are not synchsafe", hex(size_bytes)));
+            }
+            (0, decode_synchsafe_int(size_bytes))
+        } else {
+            (4, u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]))
+        };
+        match data_range("extended header", 0, size_field, size as u64, buffer.len()) {
+            | Ok(range) => pos = range.end,
+            | Err(error) => {
+                validator.report(FRAME_SIZE, "extended header", format!("extended header claims {} bytes, the tag has {}", error.claimed, error.available));
+                return Ok(validation(validator));
+            }
+        }
+    }
+
+    validator.check_frames(&buffer[pos..], "", 0);
+    for (location, child) in std::mem::take(&mut validator.children) {
+        if !validator.element_ids.contains(&child) {
+            validator.report(CTOC_MISSING_CHILD, &location, format!("child element '{}' has no CHAP or CTOC frame", child));
+        }
+    }
+    Ok(validation(validator))
+}
+
+/// Whether the ID is four characters of A-Z and 0-9
+fn id_has_valid_characters(frame_id: &str) -> bool {
+    frame_id.len() == 4 && frame_id.bytes().all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+}
+
+/// Start of the sub-frames of a CTOC frame: after the element ID, flags, entry count and child IDs
+fn toc_sub_frames_start(content: &[u8]) -> Option<usize> {
+    let mut pos = content.iter().position(|&byte| byte == 0)? + 1;
+    let entry_count = *content.get(pos + 1)?;
+    pos += 2;
+    for _ in 0..entry_count {
+        pos += content.get(pos..)?.iter().position(|&byte| byte == 0)? + 1;
+    }
+    (pos <= content.len()).then_some(pos)
+}
+
+fn terminator_length(encoding: TextEncoding) -> usize {
+    match encoding {
+        | TextEncoding::Iso88591 | TextEncoding::Utf8 => 1,
+        | TextEncoding::Utf16Bom | TextEncoding::Utf16Be => 2,
+    }
+}
+
+/// Position of the terminator of the string starting at `start` (UTF-16 terminators are aligned to code units)
+fn find_terminator(content: &[u8], start: usize, encoding: TextEncoding) -> Option<usize> {
+    let rest = content.get(start..)?;
+    match terminator_length(encoding) {
+        | 1 => rest.iter().position(|&byte| byte == 0),
+        | _ => rest.chunks_exact(2).position(|unit| unit == [0, 0]).map(|index| index * 2),
+    }
+    .map(|offset| start + offset)
+}
+
+/// Why a string is not valid in its encoding, if it is not
+fn text_problem(bytes: &[u8], encoding: TextEncoding) -> Option<String> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let units = |bytes: &[u8], little_endian: bool| -> Vec<u16> {
+        bytes
+            .chunks_exact(2)
+            .map(|unit| {
+                if little_endian {
+                    u16::from_le_bytes([unit[0], unit[1]])
+                } else {
+                    u16::from_be_bytes([unit[0], unit[1]])
+                }
+            })
+            .collect()
+    };
+    let utf16 = |bytes: &[u8], little_endian: bool| {
+        if !bytes.len().is_multiple_of(2) {
+            return Some("has an odd number of UTF-16 bytes".to_string());
+        }
+        char::decode_utf16(units(bytes, little_endian)).find_map(Result::err).map(|error| format!("has an unpaired UTF-16 surrogate 0x{:04X}", error.unpaired_surrogate()))
+    };
+    match encoding {
+        | TextEncoding::Iso88591 => None,
+        | TextEncoding::Utf8 => std::str::from_utf8(bytes).err().map(|error| format!("is not valid UTF-8 (at byte {})", error.valid_up_to())),
+        | TextEncoding::Utf16Be => utf16(bytes, false),
+        | TextEncoding::Utf16Bom => match bytes.get(0..2) {
+            | Some([0xFF, 0xFE]) => utf16(&bytes[2..], true),
+            | Some([0xFE, 0xFF]) => utf16(&bytes[2..], false),
+            | _ => Some("is UTF-16 without a byte order mark".to_string()),
+        },
+    }
+}
+
+/// Bytes as upper-case hex pairs separated by spaces ("00 00 08 80")
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+/// Validation result of one file for the JSON output
+#[derive(Serialize)]
+struct FileValidation<'a> {
+    path: String,
+    #[serde(flatten)]
+    validation: Option<&'a TagValidation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Cause of `error` if the file could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<ReadErrorKind>,
+}
+
+/// Validate the ID3v2 tags of the files and print the violations
+///
+/// Findings of the `ignore`d rules are dropped. Fails if a file has an error, or with `strict` a
+/// warning, so CI pipelines can use the exit status. Files that cannot be read are added to
+/// `errors` (which holds what the directory walk could not read) and listed after the others;
+/// they fail the run with the I/O exit status unless `--continue-on-error` is set.
+pub fn validate_files(files: &[PathBuf], mut errors: BatchErrors, ignore: &[String], strict: bool, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(unknown) = ignore.iter().find(|id| !RULES.iter().any(|rule| rule.id == id.as_str())) {
+        return Err(format!("unknown rule '{}' (see 'validate --rules')", unknown).into());
+    }
+    let fails = |validation: &TagValidation| validation.count(Severity::Error) > 0 || (strict && validation.count(Severity::Warning) > 0);
+
+    let mut results = Vec::new();
+    for path in files {
+        let result = File::open(path).map_err(Into::into).and_then(|mut file| validate_tag(&mut file)).map(|mut validation| {
+            validation.findings.retain(|finding| !ignore.iter().any(|id| id == finding.rule));
+            validation
+        });
+        results.push((path, result));
+    }
+
+    // Unreadable files are listed with the paths the walk could not read, not counted as failed
+    let walk_errors = errors.len();
+    results.retain(|(path, result)| !result.as_ref().is_err_and(|e| errors.record(path, &**e)));
+    let failed = results.iter().filter(|(_, result)| result.as_ref().map_or(true, fails)).count();
+    if json {
+        let mut list: Vec<FileValidation> = results
+            .iter()
+            .map(|(path, result)| FileValidation { path: path.to_string_lossy().into_owned(), validation: result.as_ref().ok(), error: result.as_ref().err().map(ToString::to_string), error_kind: None })
+            .collect();
+        list.extend(errors.entries.iter().map(|entry| FileValidation { path: entry.path.clone(), validation: None, error: Some(entry.error.clone()), error_kind: Some(entry.kind) }));
+        outln!("{}", serde_json::to_string_pretty(&list)?);
+    } else {
+        for (path, result) in &results {
+            match result {
+                | Ok(TagValidation { tag: None, .. }) => outln!("{}: no ID3v2 tag", display_path(path)),
+                | Ok(validation @ TagValidation { tag: Some(tag), .. }) if validation.findings.is_empty() => outln!("{}: {}, valid", display_path(path), tag),
+                | Ok(validation @ TagValidation { tag: Some(tag), .. }) => {
+                    let line = format!("{}: {}, {} error(s), {} warning(s)", display_path(path), tag, validation.count(Severity::Error), validation.count(Severity::Warning));
+                    if fails(validation) {
+                        outln!("{}", line.bright_red());
+                    } else {
+                        outln!("{}", line);
+                    }
+                    for finding in &validation.findings {
+                        outln!("  {}", finding);
+                    }
+                }
+                | Err(e) => outln!("{}", format!("{}: {}", display_path(path), e).bright_red()),
+            }
+        }
+        if files.len() + walk_errors > 1 {
+            outln!("\n{} file(s) validated, {} failed", results.len(), failed);
+        }
+        errors.print_summary("");
+    }
+
+    if failed > 0 {
+        return Err(format!("{} file(s) failed validation", failed).into());
+    }
+    errors.finish()
+}
+
+/// Print the rules with their IDs and severities
+pub fn print_rules() {
+    for rule in RULES {
+        outln!("{:<20} {:<8} {}", rule.id, rule.severity.to_string(), rule.description);
+    }
+}
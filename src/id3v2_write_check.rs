@@ -0,0 +1,86 @@
+/// Round-trip check of written ID3v2 tags
+///
+/// Every `tag` command writes through `id3v2_writer::write_tag`, which reads the written file
+/// back, dissects the tag again and compares it with the tag it meant to write. A mismatch keeps
+/// the original file and fails the command with the list of differences.
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_tools::frame_id_to_string;
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Differences listed in the error before the rest is summarized
+const MAX_LISTED_DIFFERENCES: usize = 10;
+
+/// Compare the file at `path` with the intended `tag` of `tag_size` bytes followed by `audio`
+///
+/// Returns the differences; an empty list means the round trip matched byte for byte.
+pub fn compare_written_tag(path: &Path, tag: &Id3v2Tag, tag_size: u64, audio: &[u8]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let Some(written) = Id3v2Tag::read(&mut file)? else {
+        return Ok(vec!["no ID3v2 tag at the start of the file".to_string()]);
+    };
+
+    let mut differences = Vec::new();
+    if written.version_major != tag.version_major {
+        differences.push(format!("version 2.{} intended, 2.{} read back", tag.version_major, written.version_major));
+    }
+    if written.total_size() != tag_size {
+        differences.push(format!("tag size {} bytes intended, {} bytes read back", tag_size, written.total_size()));
+    }
+    if written.frames.len() != tag.frames.len() {
+        differences.push(format!("{} frames intended, {} frames read back", tag.frames.len(), written.frames.len()));
+    }
+    for (index, (intended, actual)) in tag.frames.iter().zip(&written.frames).enumerate() {
+        let intended_id = frame_id_to_string(intended.id_bytes());
+        let actual_id = frame_id_to_string(actual.id_bytes());
+        if intended_id != actual_id {
+            differences.push(format!("frame {}: {} intended, {} read back", index + 1, intended_id, actual_id));
+            continue;
+        }
+        let intended_bytes = intended.to_bytes(tag.version_major);
+        let actual_bytes = actual.to_bytes(written.version_major);
+        if let Some(position) = first_difference(&intended_bytes, &actual_bytes) {
+            differences.push(format!(
+                "frame {} ({}): {} bytes intended, {} bytes read back, first difference at byte {}",
+                index + 1,
+                intended_id,
+                intended_bytes.len(),
+                actual_bytes.len(),
+                position
+            ));
+        }
+    }
+
+    let file_size = file.metadata()?.len();
+    let mut rest = Vec::new();
+    if file_size >= written.total_size() {
+        file.seek(SeekFrom::Start(written.total_size()))?;
+        file.read_to_end(&mut rest)?;
+    }
+    if let Some(position) = first_difference(audio, &rest) {
+        differences.push(format!("data after the tag changed ({} bytes intended, {} bytes read back, first difference at byte {})", audio.len(), rest.len(), position));
+    }
+    Ok(differences)
+}
+
+/// Error describing a failed round trip, listing up to `MAX_LISTED_DIFFERENCES` differences
+pub fn round_trip_error(path: &Path, differences: &[String]) -> String {
+    let mut message = format!("round-trip check failed for {} (the file was not changed):", display_path(path));
+    for difference in differences.iter().take(MAX_LISTED_DIFFERENCES) {
+        message.push_str(&format!("\n  - {}", difference));
+    }
+    if differences.len() > MAX_LISTED_DIFFERENCES {
+        message.push_str(&format!("\n  ... {} more differences", differences.len() - MAX_LISTED_DIFFERENCES));
+    }
+    message
+}
+
+/// Position of the first differing byte, or the length of the shorter slice if one is a prefix of the other
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    if a == b {
+        return None;
+    }
+    Some(a.iter().zip(b).position(|(x, y)| x != y).unwrap_or(a.len().min(b.len())))
+}
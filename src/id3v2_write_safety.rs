@@ -0,0 +1,86 @@
+/// Preconditions checked before an ID3v2 tag is written
+///
+/// The writer replaces the tag at the start of the file and copies everything after it
+/// unchanged. That is only safe if nothing else in the file depends on the old tag and
+/// nothing is left that the tool does not understand: an ID3v2 tag appended to the end (or a
+/// SEEK frame pointing to one), unknown bytes after the last MPEG audio frame, encrypted frames
+/// and an extended header (whose CRC and restrictions are not written again). Any of these
+/// makes `tag` commands refuse to write the file, with the reasons, unless `--force` is given.
+/// APEv2, Lyrics3 and ID3v1 blocks are copied along with the audio and are no reason to refuse.
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_tools::decode_synchsafe_int;
+use crate::mpeg_audio::{MpegFrameHeader, read_audio_data, scan_frames};
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::path::Path;
+
+/// Fail with the list of violated preconditions unless `force` (`--force`) is set
+pub fn ensure_safe_to_write(path: &Path, tag: &Id3v2Tag, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let problems = check_preconditions(path, tag)?;
+    if problems.is_empty() || force {
+        return Ok(());
+    }
+    let mut message = format!("refusing to write {} (the file was not changed):", display_path(path));
+    for problem in &problems {
+        message.push_str(&format!("\n  - {}", problem));
+    }
+    message.push_str("\nUse --force to write anyway");
+    Err(message.into())
+}
+
+/// Reasons why writing `tag` to the file at `path` could damage it; empty if it is safe
+pub fn check_preconditions(path: &Path, tag: &Id3v2Tag) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut problems = Vec::new();
+    let mut file = File::open(long_path(path))?;
+    let file_size = file.metadata()?.len();
+
+    if let Some(existing) = Id3v2Tag::read(&mut file).ok().flatten() {
+        if existing.total_size() > file_size {
+            problems.push(format!("the tag claims {} bytes but the file has only {}, so the start of the audio is unknown", existing.total_size(), file_size));
+        }
+        if existing.flags & 0x40 != 0 {
+            problems.push("the tag has an extended header, whose CRC and restrictions would be dropped".to_string());
+        }
+        if existing.frames.iter().any(|frame| frame.id == "SEEK") {
+            problems.push("a SEEK frame points to another tag by its distance from this one, which changes when the tag is rewritten".to_string());
+        }
+    }
+
+    for (index, frame) in tag.frames.iter().enumerate() {
+        if let Some(method) = frame.format.encryption_method {
+            problems.push(format!("frame {} ({}) is encrypted with method 0x{:02X}; its content cannot be checked or converted", index + 1, frame.id, method));
+        }
+    }
+
+    // The audio data ends before APEv2, Lyrics3 and ID3v1 blocks
+    if let Some((start, data)) = read_audio_data(&mut file)?
+        && let Some(&(offset, header)) = scan_frames(&data).last()
+    {
+        let mut end = data.len();
+        let mut audio_end = offset + header.frame_length().unwrap_or_default();
+        // A last frame cut off by the end of the file is audio, not unknown data
+        if MpegFrameHeader::parse(&data[audio_end..]).is_some() {
+            audio_end = end;
+        }
+        if let Some(size) = appended_tag_size(&data[audio_end..]) {
+            end -= size;
+            problems.push(format!("an ID3v2 tag of {} bytes is appended at 0x{:08X}; readers may prefer it to the rewritten tag", size, start + end as u64));
+        }
+        if end > audio_end {
+            problems.push(format!("{} bytes of unknown data follow the last MPEG audio frame at 0x{:08X}", end - audio_end, start + audio_end as u64));
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Size of an ID3v2 tag with footer ("3DI") at the end of `data`
+fn appended_tag_size(data: &[u8]) -> Option<usize> {
+    let footer = data.get(data.len().checked_sub(10)?..)?;
+    if &footer[0..3] != b"3DI" || footer[3] != 4 || footer[6..10].iter().any(|byte| byte & 0x80 != 0) {
+        return None;
+    }
+    // Header, body and footer
+    let total = decode_synchsafe_int(&footer[6..10]) as usize + 20;
+    (total <= data.len()).then_some(total)
+}
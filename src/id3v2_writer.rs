@@ -0,0 +1,132 @@
+/// ID3v2 tag serialization and file rewriting
+///
+/// Tags are always written without unsynchronisation, extended header or footer.
+/// Frames keep their original flags and data unless they were replaced.
+use crate::id3v2_frame_order::canonicalize;
+use crate::id3v2_tag::{Id3v2Tag, parse_tag_header};
+use crate::id3v2_tools::encode_synchsafe_int;
+use crate::id3v2_write_check::{compare_written_tag, round_trip_error};
+use crate::id3v2_write_safety::ensure_safe_to_write;
+use crate::options::WriteOptions;
+use crate::path_display::long_path;
+use crate::safe_save;
+use std::fs;
+use std::path::Path;
+
+/// Padding added after the frames when the tag has to grow
+pub const DEFAULT_PADDING: usize = 1024;
+
+/// Outcome of writing a tag to a file
+#[derive(Debug, Clone)]
+pub struct WriteResult {
+    /// Bytes occupied by the previous tag (0 if the file had none)
+    pub old_tag_size: u64,
+    /// Bytes occupied by the new tag including padding
+    pub new_tag_size: u64,
+}
+
+/// Serialize the frames of a tag for its version
+pub fn serialize_frames(tag: &Id3v2Tag) -> Vec<u8> {
+    tag.frames.iter().flat_map(|frame| frame.to_bytes(tag.version_major)).collect()
+}
+
+/// Serialize a complete tag (header, frames and `padding` null bytes)
+pub fn serialize_tag(tag: &Id3v2Tag, padding: usize) -> Vec<u8> {
+    let mut body = serialize_frames(tag);
+    body.resize(body.len() + padding, 0);
+
+    let mut bytes = Vec::with_capacity(10 + body.len());
+    bytes.extend_from_slice(b"ID3");
+    bytes.push(tag.version_major);
+    bytes.push(0);
+    bytes.push(0); // No unsynchronisation, extended header or footer
+    bytes.extend_from_slice(&encode_synchsafe_int(body.len() as u32));
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+/// Size of the ID3v2 tag at the start of `data` including header and footer (0 if none)
+pub fn existing_tag_size(data: &[u8]) -> u64 {
+    match parse_tag_header(data) {
+        | Some((major, _minor, flags, size)) => {
+            let footer = if major == 4 && flags & 0x10 != 0 {
+                10
+            } else {
+                0
+            };
+            (10 + size as u64 + footer).min(data.len() as u64)
+        }
+        | None => 0,
+    }
+}
+
+/// Refuse tags of versions that cannot be written; only ID3v2.3 and ID3v2.4 tags can
+pub fn ensure_writable_version(tag: &Id3v2Tag) -> Result<(), Box<dyn std::error::Error>> {
+    if tag.version_major != 3 && tag.version_major != 4 {
+        return Err(format!("ID3v2.{} tags cannot be written", tag.version_major).into());
+    }
+    Ok(())
+}
+
+/// Replace the ID3v2 tag of the file at `path` with `tag`
+///
+/// The existing tag size is kept when the new frames fit, so the audio data does not move and
+/// only the tag is overwritten in place; otherwise the tag grows, gets `DEFAULT_PADDING` bytes of
+/// padding and the file is replaced through a temporary file. The written tag is dissected again
+/// and compared with `tag`; if they differ the original file is kept. With `canonical_order`
+/// the frames are written in the recommended order, with `store_nfc` their text in NFC. Files the rewrite could damage (see
+/// `id3v2_write_safety`) are refused unless `force` is set.
+pub fn write_tag(path: &Path, tag: &Id3v2Tag, options: &WriteOptions) -> Result<WriteResult, Box<dyn std::error::Error>> {
+    ensure_writable_version(tag)?;
+    ensure_safe_to_write(path, tag, options.force)?;
+    let mut prepared;
+    let tag = if options.canonical_order || options.store_nfc {
+        prepared = tag.clone();
+        if options.canonical_order {
+            canonicalize(&mut prepared.frames);
+        }
+        if options.store_nfc {
+            for frame in &mut prepared.frames {
+                frame.normalize_text(prepared.version_major);
+            }
+        }
+        &prepared
+    } else {
+        tag
+    };
+    let data = fs::read(long_path(path))?;
+    let old_tag_size = existing_tag_size(&data);
+    let audio = &data[old_tag_size as usize..];
+
+    let frames_size = serialize_frames(tag).len() as u64;
+    let padding = if old_tag_size >= 10 && frames_size <= old_tag_size - 10 {
+        (old_tag_size - 10 - frames_size) as usize
+    } else {
+        DEFAULT_PADDING
+    };
+
+    let tag_bytes = serialize_tag(tag, padding);
+    let new_tag_size = tag_bytes.len() as u64;
+    let verify = |written: &Path| -> Result<(), Box<dyn std::error::Error>> {
+        let differences = compare_written_tag(written, tag, new_tag_size, audio)?;
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(round_trip_error(path, &differences).into())
+        }
+    };
+
+    if new_tag_size == old_tag_size {
+        safe_save::overwrite_start(path, &tag_bytes, options)?;
+        if let Err(error) = verify(path) {
+            safe_save::overwrite_start(path, &data[..old_tag_size as usize], options)?;
+            return Err(error);
+        }
+    } else {
+        let mut output = tag_bytes;
+        output.extend_from_slice(audio);
+        safe_save::replace_file(path, &output, options, verify)?;
+    }
+
+    Ok(WriteResult { old_tag_size, new_tag_size })
+}
@@ -0,0 +1,312 @@
+/// Color space and ICC profile of embedded pictures (APIC frames, MP4 `covr` items)
+///
+/// A cover that does not show up is often a JPEG in CMYK: print shops deliver artwork that way,
+/// and most players, phones and car stereos only decode YCbCr (RGB) and grayscale JPEGs. The
+/// JPEG and PNG headers are read up to the image data for the dimensions, the color space and an
+/// embedded ICC profile, whose own color space should match the image.
+use crate::finding::{Finding, Severity};
+use crate::isobmff_box::{find_path, read_boxes, read_children, read_payload};
+use crate::media_source::MediaSource;
+use flate2::read::ZlibDecoder;
+use serde::Serialize;
+use std::fmt;
+use std::io::Read;
+
+/// Largest decompressed PNG ICC profile that is read
+const MAX_ICC_PROFILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Color model of the pixel data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    Gray,
+    Rgb,
+    YCbCr,
+    Cmyk,
+    /// CMYK with the color channels stored as YCbCr (Adobe transform 2)
+    Ycck,
+    /// Indexed colors from a palette (PNG)
+    Palette,
+    Unknown,
+}
+
+impl ColorSpace {
+    /// Color spaces most players decode
+    fn is_widely_supported(self) -> bool {
+        !matches!(self, ColorSpace::Cmyk | ColorSpace::Ycck)
+    }
+
+    /// Whether an ICC profile of the given color space ('RGB ', 'CMYK', 'GRAY') fits the image
+    fn matches_profile(self, profile_space: &str) -> bool {
+        match self {
+            | ColorSpace::Gray => profile_space == "GRAY",
+            | ColorSpace::Rgb | ColorSpace::YCbCr | ColorSpace::Palette => profile_space == "RGB",
+            | ColorSpace::Cmyk | ColorSpace::Ycck => profile_space == "CMYK",
+            | ColorSpace::Unknown => true,
+        }
+    }
+}
+
+impl fmt::Display for ColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            | ColorSpace::Gray => "grayscale",
+            | ColorSpace::Rgb => "RGB",
+            | ColorSpace::YCbCr => "YCbCr",
+            | ColorSpace::Cmyk => "CMYK",
+            | ColorSpace::Ycck => "YCCK",
+            | ColorSpace::Palette => "palette",
+            | ColorSpace::Unknown => "unknown color space",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// ICC profile embedded in a picture
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IccProfile {
+    pub size: usize,
+    /// Data color space from the profile header ("RGB", "CMYK", "GRAY", ...)
+    pub color_space: String,
+    /// Profile description ('desc' tag), e.g. "sRGB IEC61966-2.1"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl IccProfile {
+    fn parse(data: &[u8]) -> Self {
+        let color_space = data.get(16..20).map(|space| String::from_utf8_lossy(space).trim().to_string()).unwrap_or_default();
+        IccProfile { size: data.len(), color_space, description: icc_description(data) }
+    }
+}
+
+/// Format, size and color properties of a JPEG or PNG picture
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageInfo {
+    /// "JPEG" or "PNG"
+    pub format: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub color_space: ColorSpace,
+    /// PNG images declared as sRGB (sRGB chunk)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub srgb: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icc_profile: Option<IccProfile>,
+}
+
+impl ImageInfo {
+    /// Read the header of a JPEG or PNG picture; `None` for other formats and truncated headers
+    pub fn inspect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xFF, 0xD8]) {
+            inspect_jpeg(data)
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            inspect_png(data)
+        } else {
+            None
+        }
+    }
+
+    /// Color problems that keep players from showing the picture
+    pub fn findings(&self, location: &str) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        if !self.color_space.is_widely_supported() {
+            findings.push(Finding::new(
+                Severity::Warning,
+                location,
+                format!("{} {} image; many players and car stereos show no cover for it, convert it to RGB", self.color_space, self.format),
+            ));
+        }
+        if let Some(profile) = &self.icc_profile
+            && !profile.color_space.is_empty()
+            && !self.color_space.matches_profile(&profile.color_space)
+        {
+            findings.push(Finding::new(
+                Severity::Warning,
+                location,
+                format!("ICC profile is for {}, but the image is {}; colors are shown wrong or not at all", profile.color_space, self.color_space),
+            ));
+        }
+        findings
+    }
+}
+
+impl fmt::Display for ImageInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}x{}, {}", self.format, self.width, self.height, self.color_space)?;
+        if self.srgb {
+            write!(f, ", sRGB")?;
+        }
+        match &self.icc_profile {
+            | Some(profile) => {
+                write!(f, ", ICC profile")?;
+                if let Some(description) = &profile.description {
+                    write!(f, " \"{}\"", description)?;
+                }
+                write!(f, " ({}, {} bytes)", profile.color_space, profile.size)
+            }
+            | None => write!(f, ", no ICC profile"),
+        }
+    }
+}
+
+/// Walk the JPEG segments up to the start of scan
+fn inspect_jpeg(data: &[u8]) -> Option<ImageInfo> {
+    let mut frame = None;
+    let mut adobe_transform = None;
+    let mut icc_chunks: Vec<(u8, &[u8])> = Vec::new();
+    let mut pos = 2;
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let marker = data[pos + 1];
+        // Fill bytes before a marker
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let Some(segment) = data.get(pos + 4..pos + 2 + length.max(2)) else {
+            break;
+        };
+        match marker {
+            // Start of frame (all except DHT, JPG and DAC, which share the range)
+            | 0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) && segment.len() >= 6 => {
+                let height = u16::from_be_bytes([segment[1], segment[2]]) as u32;
+                let width = u16::from_be_bytes([segment[3], segment[4]]) as u32;
+                let components: Vec<u8> = segment[6..].chunks_exact(3).take(segment[5] as usize).map(|component| component[0]).collect();
+                frame = Some((width, height, segment[5], components));
+            }
+            | 0xE2 if segment.starts_with(b"ICC_PROFILE\0") && segment.len() >= 14 => icc_chunks.push((segment[12], &segment[14..])),
+            | 0xEE if segment.starts_with(b"Adobe") && segment.len() >= 12 => adobe_transform = Some(segment[11]),
+            | _ => {}
+        }
+        pos += 2 + length;
+    }
+
+    let (width, height, component_count, component_ids) = frame?;
+    let color_space = match (component_count, adobe_transform) {
+        | (1, _) => ColorSpace::Gray,
+        | (3, Some(0)) => ColorSpace::Rgb,
+        | (3, None) if component_ids == b"RGB" => ColorSpace::Rgb,
+        | (3, _) => ColorSpace::YCbCr,
+        | (4, Some(2)) => ColorSpace::Ycck,
+        | (4, _) => ColorSpace::Cmyk,
+        | _ => ColorSpace::Unknown,
+    };
+    // Profiles larger than a segment are split into numbered chunks
+    icc_chunks.sort_by_key(|&(sequence, _)| sequence);
+    let icc_profile = (!icc_chunks.is_empty()).then(|| IccProfile::parse(&icc_chunks.iter().flat_map(|(_, chunk)| chunk.iter().copied()).collect::<Vec<u8>>()));
+    Some(ImageInfo { format: "JPEG", width, height, color_space, srgb: false, icc_profile })
+}
+
+/// Walk the PNG chunks up to the image data
+fn inspect_png(data: &[u8]) -> Option<ImageInfo> {
+    let mut header = None;
+    let mut srgb = false;
+    let mut icc_profile = None;
+    let mut pos = 8;
+    while pos + 8 <= data.len() {
+        let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let chunk_type = &data[pos + 4..pos + 8];
+        let Some(chunk) = data.get(pos + 8..(pos + 8).saturating_add(length)) else {
+            break;
+        };
+        match chunk_type {
+            | b"IHDR" if chunk.len() >= 13 => {
+                let width = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                let height = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+                let color_space = match chunk[9] {
+                    | 0 | 4 => ColorSpace::Gray,
+                    | 2 | 6 => ColorSpace::Rgb,
+                    | 3 => ColorSpace::Palette,
+                    | _ => ColorSpace::Unknown,
+                };
+                header = Some((width, height, color_space));
+            }
+            | b"sRGB" => srgb = true,
+            // Profile name, compression method and the zlib-compressed profile
+            | b"iCCP" => {
+                if let Some(name_end) = chunk.iter().position(|&byte| byte == 0)
+                    && let Some(compressed) = chunk.get(name_end + 2..)
+                {
+                    let mut profile = Vec::new();
+                    if ZlibDecoder::new(compressed).take(MAX_ICC_PROFILE_SIZE).read_to_end(&mut profile).is_ok() {
+                        let mut parsed = IccProfile::parse(&profile);
+                        parsed.description = parsed.description.or_else(|| Some(String::from_utf8_lossy(&chunk[..name_end]).into_owned()));
+                        icc_profile = Some(parsed);
+                    }
+                }
+            }
+            | b"IDAT" | b"IEND" => break,
+            | _ => {}
+        }
+        // Length, type, data and CRC
+        pos += 12 + length;
+    }
+
+    let (width, height, color_space) = header?;
+    Some(ImageInfo { format: "PNG", width, height, color_space, srgb, icc_profile })
+}
+
+/// Text of the 'desc' tag: ASCII in ICC v2 ('desc' type), UTF-16 in ICC v4 ('mluc' type)
+fn icc_description(profile: &[u8]) -> Option<String> {
+    let read_u32 = |offset: usize| profile.get(offset..offset + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize);
+    let tag_count = read_u32(128)?;
+    let (offset, size) = (0..tag_count.min(256)).find_map(|i| {
+        let entry = 132 + i * 12;
+        (profile.get(entry..entry + 4)? == b"desc").then(|| Some((read_u32(entry + 4)?, read_u32(entry + 8)?)))?
+    })?;
+    let tag = profile.get(offset..offset.checked_add(size)?)?;
+    let text = match tag.get(0..4)? {
+        | b"desc" => {
+            let length = u32::from_be_bytes(tag.get(8..12)?.try_into().ok()?) as usize;
+            String::from_utf8_lossy(tag.get(12..12 + length)?).trim_end_matches('\0').to_string()
+        }
+        | b"mluc" => {
+            // First record: language, country, length and offset from the start of the tag
+            let length = u32::from_be_bytes(tag.get(20..24)?.try_into().ok()?) as usize;
+            let start = u32::from_be_bytes(tag.get(24..28)?.try_into().ok()?) as usize;
+            let units: Vec<u16> = tag.get(start..start.checked_add(length)?)?.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+            String::from_utf16_lossy(&units).trim_end_matches('\0').to_string()
+        }
+        | _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// Pictures of the `covr` item in the `moov/udta/meta/ilst` box of an MP4 file (one per `data` box)
+pub fn isobmff_cover_art(file: &mut dyn MediaSource) -> Result<Vec<ImageInfo>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let Some(moov) = read_boxes(file, 0, file_size)?.into_iter().find(|box_header| box_header.box_type == "moov") else {
+        return Ok(Vec::new());
+    };
+    let Some(covr) = find_path(file, &moov, &["udta", "meta", "ilst", "covr"])? else {
+        return Ok(Vec::new());
+    };
+    let mut images = Vec::new();
+    for data in read_children(file, &covr)?.iter().filter(|child| child.box_type == "data") {
+        // Type indicator (13 JPEG, 14 PNG) and locale precede the picture
+        let payload = read_payload(file, data)?;
+        if let Some(image) = payload.get(8..).and_then(ImageInfo::inspect) {
+            images.push(image);
+        }
+    }
+    Ok(images)
+}
+
+/// Print the cover pictures of an MP4 file with their problems, if it has any
+pub fn print_cover_art(images: &[ImageInfo]) {
+    if images.is_empty() {
+        return;
+    }
+    outln!("\nCover Art:");
+    for (i, image) in images.iter().enumerate() {
+        let location = format!("covr[{}]", i);
+        outln!("  {}: {}", location, image);
+        for finding in image.findings(&location) {
+            outln!("  {}", finding);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+/// ISO BMFF boxes held in memory for editing
+///
+/// Writers that change the movie box read it into a tree of `Atom`s: the containers on the way
+/// to the edited boxes (moov, trak, mdia, minf, stbl, udta, tref, edts, dinf) are split into
+/// children, every other box is kept as its raw payload, and the tree is encoded again with the
+/// sizes recomputed. When the movie box changes size, the media data behind it moves, and
+/// `relocate_chunks` shifts the chunk offsets (stco, co64) that point there.
+use crate::checked_size::buffer_length;
+use crate::isobmff_box::BoxHeader;
+use crate::media_source::MediaSource;
+use crate::options::WriteOptions;
+use crate::safe_save;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, SeekFrom, Write};
+use std::path::Path;
+
+/// Boxes whose children are edited; all others are kept as opaque payload
+const CONTAINERS: [&[u8; 4]; 9] = [b"moov", b"trak", b"mdia", b"minf", b"stbl", b"udta", b"tref", b"edts", b"dinf"];
+
+/// A box held in memory, with its children parsed if it is one of the edited containers
+pub struct Atom {
+    pub box_type: [u8; 4],
+    /// Payload of a box kept whole (empty for parsed containers)
+    pub payload: Vec<u8>,
+    /// Child boxes of a parsed container
+    pub children: Option<Vec<Atom>>,
+}
+
+impl Atom {
+    pub fn leaf(box_type: &[u8; 4], payload: Vec<u8>) -> Self {
+        Atom { box_type: *box_type, payload, children: None }
+    }
+
+    pub fn container(box_type: &[u8; 4], children: Vec<Atom>) -> Self {
+        Atom { box_type: *box_type, payload: Vec::new(), children: Some(children) }
+    }
+
+    /// Full box with version 0, the given flags and `content`
+    pub fn full(box_type: &[u8; 4], flags: u32, content: &[u8]) -> Self {
+        let mut payload = (flags & 0x00FFFFFF).to_be_bytes().to_vec();
+        payload.extend_from_slice(content);
+        Atom::leaf(box_type, payload)
+    }
+
+    pub fn children(&self) -> &[Atom] {
+        self.children.as_deref().unwrap_or_default()
+    }
+
+    pub fn child(&self, box_type: &[u8; 4]) -> Option<&Atom> {
+        self.children().iter().find(|child| &child.box_type == box_type)
+    }
+
+    pub fn child_mut(&mut self, box_type: &[u8; 4]) -> Option<&mut Atom> {
+        self.children.as_mut()?.iter_mut().find(|child| &child.box_type == box_type)
+    }
+
+    pub fn path(&self, path: &[&[u8; 4]]) -> Option<&Atom> {
+        path.iter().try_fold(self, |atom, box_type| atom.child(box_type))
+    }
+
+    /// The box with its header, with a 64-bit size if it needs one
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let mut content = Vec::new();
+        match &self.children {
+            | Some(children) => children.iter().for_each(|child| child.encode(&mut content)),
+            | None => content.extend_from_slice(&self.payload),
+        }
+        match u32::try_from(content.len() + 8) {
+            | Ok(size) => out.extend_from_slice(&size.to_be_bytes()),
+            | Err(_) => {
+                out.extend_from_slice(&1u32.to_be_bytes());
+                out.extend_from_slice(&self.box_type);
+                out.extend_from_slice(&(content.len() as u64 + 16).to_be_bytes());
+                out.extend_from_slice(&content);
+                return;
+            }
+        }
+        out.extend_from_slice(&self.box_type);
+        out.extend_from_slice(&content);
+    }
+}
+
+/// Boxes in `data`; containers whose payload is not a sequence of boxes are kept whole
+pub fn parse_atoms(data: &[u8]) -> Result<Vec<Atom>, String> {
+    let mut atoms = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        // QuickTime ends some udta boxes with a 32-bit zero
+        if data.len() - pos < 8 && data[pos..].iter().all(|&byte| byte == 0) {
+            break;
+        }
+        let header = data.get(pos..pos + 8).ok_or("truncated box header")?;
+        let box_type: [u8; 4] = [header[4], header[5], header[6], header[7]];
+        let (header_size, size) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize {
+            | 0 => (8, data.len() - pos),
+            | 1 => {
+                let large = data.get(pos + 8..pos + 16).ok_or("truncated box header")?;
+                (16, usize::try_from(u64::from_be_bytes(large.try_into().unwrap_or_default())).map_err(|_| "box too large")?)
+            }
+            | size => (8, size),
+        };
+        if size < header_size || size > data.len() - pos {
+            return Err(format!("box '{}' at offset {} claims {} bytes, but only {} are left", String::from_utf8_lossy(&box_type), pos, size, data.len() - pos));
+        }
+        let payload = &data[pos + header_size..pos + size];
+        let children = if CONTAINERS.contains(&&box_type) {
+            parse_atoms(payload).ok()
+        } else {
+            None
+        };
+        let payload = if children.is_some() {
+            Vec::new()
+        } else {
+            payload.to_vec()
+        };
+        atoms.push(Atom { box_type, payload, children });
+        pos += size;
+    }
+    Ok(atoms)
+}
+
+/// Handler type of a trak box ("soun", "vide", "text", ...)
+pub fn handler(trak: &Atom) -> Option<&[u8]> {
+    trak.path(&[b"mdia", b"hdlr"]).and_then(|hdlr| hdlr.payload.get(8..12))
+}
+
+/// Track ID from the tkhd box of a trak box
+pub fn track_id(trak: &Atom) -> Option<u32> {
+    let tkhd = trak.child(b"tkhd")?;
+    let at = if tkhd.payload.first() == Some(&1) {
+        20
+    } else {
+        12
+    };
+    tkhd.payload.get(at..at + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Shift the chunk offsets of all tracks with `shift`; returns how many changed
+pub fn relocate_chunks(moov: &mut Atom, shift: &dyn Fn(u64) -> Option<u64>) -> Result<usize, String> {
+    let mut shifted = 0;
+    for (track, trak) in moov.children.iter_mut().flatten().filter(|child| &child.box_type == b"trak").enumerate() {
+        let Some(stbl) = trak.child_mut(b"mdia").and_then(|mdia| mdia.child_mut(b"minf")).and_then(|minf| minf.child_mut(b"stbl")) else {
+            continue;
+        };
+        for table in stbl.children.iter_mut().flatten().filter(|child| &child.box_type == b"stco" || &child.box_type == b"co64") {
+            let width = if &table.box_type == b"co64" {
+                8
+            } else {
+                4
+            };
+            let count = read_u32(&table.payload, 4) as usize;
+            if count.checked_mul(width).and_then(|size| size.checked_add(8)).is_none_or(|size| size > table.payload.len()) {
+                return Err(format!("the '{}' box of track {} is truncated", String::from_utf8_lossy(&table.box_type), track + 1));
+            }
+            for index in 0..count {
+                let at = 8 + index * width;
+                let entry = &mut table.payload[at..at + width];
+                let offset = if width == 8 {
+                    u64::from_be_bytes(entry.try_into().unwrap_or_default())
+                } else {
+                    read_u32(entry, 0) as u64
+                };
+                let moved = shift(offset).ok_or_else(|| format!("chunk offset {} of track {} cannot be shifted", offset, track + 1))?;
+                if moved == offset {
+                    continue;
+                }
+                if width == 8 {
+                    entry.copy_from_slice(&moved.to_be_bytes());
+                } else {
+                    let moved = u32::try_from(moved).map_err(|_| format!("chunk offset {} of track {} would need more than 32 bits after the move (the track needs a co64 box)", moved, track + 1))?;
+                    entry.copy_from_slice(&moved.to_be_bytes());
+                }
+                shifted += 1;
+            }
+        }
+    }
+    Ok(shifted)
+}
+
+pub fn read_u32(payload: &[u8], at: usize) -> u32 {
+    payload.get(at..at + 4).map_or(0, |bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The moov box at `moov` as a tree
+pub fn read_moov(file: &mut dyn MediaSource, moov: &BoxHeader) -> Result<Atom, Box<dyn std::error::Error>> {
+    let mut payload = vec![0u8; buffer_length("box 'moov'", moov.offset, moov.size - moov.header_size)?];
+    file.seek(SeekFrom::Start(moov.payload_start()))?;
+    file.read_exact(&mut payload)?;
+    Ok(Atom::container(b"moov", parse_atoms(&payload).map_err(|error| format!("in 'moov': {}", error))?))
+}
+
+/// The trak box with the track ID `track`
+pub fn track_mut(moov: &mut Atom, track: u32) -> Option<&mut Atom> {
+    moov.children.as_mut()?.iter_mut().find(|child| &child.box_type == b"trak" && track_id(child) == Some(track))
+}
+
+/// Copy the top-level `boxes` of `file` to `out`, with `replacement` written instead of the box at `moov`
+pub fn copy_with_moov(file: &mut dyn MediaSource, boxes: &[BoxHeader], moov: &BoxHeader, replacement: &[u8], out: &mut impl Write) -> Result<(), Box<dyn std::error::Error>> {
+    for box_header in boxes {
+        if box_header.offset == moov.offset {
+            out.write_all(replacement)?;
+        } else {
+            file.seek(SeekFrom::Start(box_header.offset))?;
+            io::copy(&mut file.take(box_header.size), out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace the file at `path` by a copy with `moov` instead of the box at `moov_header`
+///
+/// The chunk offsets (stco, co64) of the media data behind the movie box are shifted by the
+/// growth; returns how many changed. Files with data outside the boxes or with movie fragments,
+/// which address data by absolute offsets, are refused.
+pub fn replace_moov(path: &Path, file: &mut File, boxes: &[BoxHeader], moov_header: &BoxHeader, mut moov: Atom, options: &WriteOptions) -> Result<usize, Box<dyn std::error::Error>> {
+    let file_size = file.metadata()?.len();
+    let covered = boxes.last().map_or(0, BoxHeader::end);
+    if covered != file_size {
+        return Err(format!("the boxes end at offset {} but the file has {} bytes; data outside the boxes cannot be moved", covered, file_size).into());
+    }
+    if boxes.iter().any(|box_header| box_header.box_type == "moof") {
+        return Err("the 'moov' box has to grow, but fragmented files address data by absolute offsets".into());
+    }
+
+    let mut encoded_moov = Vec::new();
+    moov.encode(&mut encoded_moov);
+    let delta = encoded_moov.len() as i64 - moov_header.size as i64;
+    let moov_end = moov_header.end();
+    let shifted = relocate_chunks(&mut moov, &|offset| {
+        if offset >= moov_end {
+            offset.checked_add_signed(delta)
+        } else {
+            Some(offset)
+        }
+    })?;
+    encoded_moov.clear();
+    moov.encode(&mut encoded_moov);
+
+    let expected = file_size.checked_add_signed(delta).ok_or("the rewritten file would be too large")?;
+    safe_save::replace_file_with(
+        path,
+        |temp| {
+            let mut out = BufWriter::new(temp);
+            copy_with_moov(file, boxes, moov_header, &encoded_moov, &mut out)?;
+            out.flush()?;
+            Ok(())
+        },
+        options,
+        |temp_path| {
+            let written = std::fs::metadata(temp_path)?.len();
+            if written != expected {
+                return Err(format!("the rewritten file has {} bytes instead of {}", written, expected).into());
+            }
+            Ok(())
+        },
+    )?;
+    Ok(shifted)
+}
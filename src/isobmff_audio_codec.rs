@@ -0,0 +1,153 @@
+/// Audio codec of ISO BMFF tracks, from the first entry of the sample description (stsd)
+///
+/// The .m4a extension is used for AAC and Apple Lossless (ALAC) alike, so the extension says
+/// nothing about whether a file is lossy. The sample entry type tells them apart ('mp4a' with
+/// an esds box, 'alac' with an ALACSpecificConfig), and the codec configuration gives the real
+/// sample rate, channel count and, for lossless codecs, the bit depth; the fields of the sample
+/// entry itself are only used where the codec has no configuration of its own.
+use crate::audio_format_check::{aac_channels, mp4a_format, parse_audio_specific_config};
+use crate::isobmff_demux::{decoder_config, find_child, mp4a_children, sample_entry};
+use serde::Serialize;
+use std::fmt;
+
+/// Codec and format of an audio track
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioCodec {
+    /// Codec name (e.g. "ALAC", "AAC LC", "HE-AAC v2", "MP3")
+    pub codec: String,
+    /// Type of the sample entry ("mp4a", "alac", "fLaC", ...)
+    pub sample_entry: String,
+    /// Whether the codec is lossless
+    pub lossless: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u32>,
+    /// Bits per sample of lossless and PCM audio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bit_depth: Option<u32>,
+}
+
+impl fmt::Display for AudioCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.lossless {
+            "lossless"
+        } else {
+            "lossy"
+        };
+        write!(f, "{} ({}, '{}')", self.codec, kind, self.sample_entry)?;
+        if let Some(bit_depth) = self.bit_depth {
+            write!(f, ", {}-bit", bit_depth)?;
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            write!(f, ", {} Hz", sample_rate)?;
+        }
+        match self.channels {
+            | Some(1) => write!(f, ", mono")?,
+            | Some(2) => write!(f, ", stereo")?,
+            | Some(channels) => write!(f, ", {} channels", channels)?,
+            | None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Audio codec from the payload of a sample description (stsd), if its first entry is audio
+pub fn audio_codec(stsd: &[u8]) -> Option<AudioCodec> {
+    let (entry_type, entry) = sample_entry(stsd)?;
+    let (declared_rate, declared_channels) = mp4a_format(entry);
+    let mut codec = AudioCodec {
+        codec: String::new(),
+        sample_entry: entry_type.clone(),
+        lossless: false,
+        sample_rate: Some(declared_rate).filter(|&rate| rate > 0),
+        channels: Some(declared_channels as u32).filter(|&channels| channels > 0),
+        bit_depth: None,
+    };
+    let children = mp4a_children(entry).unwrap_or_default();
+
+    match entry_type.as_str() {
+        | "mp4a" => {
+            let Some((object_type, config)) = find_child(children, b"esds").and_then(|esds| decoder_config(esds.get(4..)?)) else {
+                codec.codec = "MPEG-4 audio (no esds box)".to_string();
+                return Some(codec);
+            };
+            match object_type {
+                | 0x40 | 0x66..=0x68 => {
+                    codec.codec = "AAC".to_string();
+                    if let Some(config) = config.and_then(parse_audio_specific_config) {
+                        codec.codec = aac_name(config.object_type);
+                        codec.sample_rate = Some(config.extension_rate.unwrap_or(config.sample_rate));
+                        if let Some(channels) = aac_channels(config.channel_config) {
+                            // Parametric stereo decodes a mono core to two channels
+                            let parametric_stereo = config.object_type == 29 && channels == 1;
+                            codec.channels = Some(channels + parametric_stereo as u32);
+                        }
+                    }
+                }
+                | 0x69 | 0x6B => codec.codec = "MP3".to_string(),
+                | 0xA5 => codec.codec = "AC-3".to_string(),
+                | 0xA6 => codec.codec = "E-AC-3".to_string(),
+                | 0xAD => codec.codec = "Opus".to_string(),
+                | _ => codec.codec = format!("MPEG-4 audio object type 0x{:02X}", object_type),
+            }
+        }
+        | "alac" => {
+            codec.codec = "ALAC".to_string();
+            codec.lossless = true;
+            // ALACSpecificConfig after the version and flags of the 'alac' child box
+            if let Some(config) = find_child(children, b"alac").and_then(|alac| alac.get(4..28)) {
+                codec.bit_depth = Some(config[5] as u32);
+                codec.channels = Some(config[9] as u32);
+                codec.sample_rate = Some(u32::from_be_bytes([config[20], config[21], config[22], config[23]]));
+            }
+        }
+        | "fLaC" => {
+            codec.codec = "FLAC".to_string();
+            codec.lossless = true;
+            // STREAMINFO block after the version and flags of the dfLa box and the block header
+            if let Some(info) = find_child(children, b"dfLa").and_then(|dfla| dfla.get(8..26)) {
+                codec.sample_rate = Some(((info[10] as u32) << 12) | ((info[11] as u32) << 4) | (info[12] as u32 >> 4));
+                codec.channels = Some(((info[12] as u32 >> 1) & 0x07) + 1);
+                codec.bit_depth = Some((((info[12] as u32 & 0x01) << 4) | (info[13] as u32 >> 4)) + 1);
+            }
+        }
+        | "Opus" => codec.codec = "Opus".to_string(),
+        | "ac-3" => codec.codec = "AC-3".to_string(),
+        | "ec-3" => codec.codec = "E-AC-3".to_string(),
+        | ".mp3" => codec.codec = "MP3".to_string(),
+        | "lpcm" | "sowt" | "twos" | "in24" | "in32" | "fl32" | "fl64" | "ipcm" | "fpcm" => {
+            codec.codec = "PCM".to_string();
+            codec.lossless = true;
+            codec.bit_depth = sample_size(entry);
+        }
+        | _ => return None,
+    }
+    Some(codec)
+}
+
+/// Name of an MPEG-4 audio object type of the AAC family
+fn aac_name(object_type: u32) -> String {
+    match object_type {
+        | 1 => "AAC Main",
+        | 2 => "AAC LC",
+        | 3 => "AAC SSR",
+        | 4 => "AAC LTP",
+        | 5 => "HE-AAC",
+        | 23 => "AAC LD",
+        | 29 => "HE-AAC v2",
+        | 39 => "AAC ELD",
+        | 42 => "xHE-AAC",
+        | _ => return format!("AAC (object type {})", object_type),
+    }
+    .to_string()
+}
+
+/// Bits per sample of a sound description (QuickTime version 2 keeps them after the fixed fields)
+fn sample_size(entry: &[u8]) -> Option<u32> {
+    let read_u16 = |pos: usize| entry.get(pos..pos + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as u32);
+    if read_u16(16)? == 2 {
+        return entry.get(56..60).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).filter(|&bits| bits > 0);
+    }
+    read_u16(26).filter(|&bits| bits > 0)
+}
@@ -0,0 +1,148 @@
+/// ISO BMFF box headers and traversal
+///
+/// Boxes start with a 32-bit size and a four-character type; a size of 1 means a 64-bit
+/// size follows, a size of 0 that the box extends to the end of its parent.
+use crate::checked_size::buffer_length;
+use crate::media_source::MediaSource;
+use std::io::SeekFrom;
+
+/// Position and size of a box within the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxHeader {
+    /// Four-character box type (e.g. "moov")
+    pub box_type: String,
+    /// File offset of the box header
+    pub offset: u64,
+    /// Size of the box header (8, or 16 with a 64-bit size)
+    pub header_size: u64,
+    /// Size of the whole box including its header
+    pub size: u64,
+    /// The size field is 0: the box extends to the end of its parent (the file for top-level boxes)
+    pub to_end: bool,
+}
+
+impl BoxHeader {
+    /// File offset of the first payload byte
+    pub fn payload_start(&self) -> u64 {
+        self.offset + self.header_size
+    }
+
+    /// File offset just after the box
+    pub fn end(&self) -> u64 {
+        self.offset + self.size
+    }
+}
+
+/// Headers of the boxes between `start` and `end` (a file or a parent box payload)
+///
+/// Stops at the first box whose size does not fit into the range.
+pub fn read_boxes(file: &mut dyn MediaSource, start: u64, end: u64) -> Result<Vec<BoxHeader>, Box<dyn std::error::Error>> {
+    let mut boxes = Vec::new();
+    let mut pos = start;
+
+    while pos + 8 <= end {
+        let mut header = [0u8; 16];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut header[..8])?;
+
+        let mut header_size = 8;
+        let to_end = header[0..4] == [0, 0, 0, 0];
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64 {
+            | 0 => end - pos,
+            | 1 => {
+                if pos + 16 > end {
+                    break;
+                }
+                file.read_exact(&mut header[8..16])?;
+                header_size = 16;
+                u64::from_be_bytes(header[8..16].try_into()?)
+            }
+            | size => size,
+        };
+
+        if size < header_size || size > end - pos {
+            break;
+        }
+
+        boxes.push(BoxHeader { box_type: String::from_utf8_lossy(&header[4..8]).to_string(), offset: pos, header_size, size, to_end });
+        pos += size;
+    }
+
+    Ok(boxes)
+}
+
+/// Child boxes of a container box
+pub fn read_children(file: &mut dyn MediaSource, parent: &BoxHeader) -> Result<Vec<BoxHeader>, Box<dyn std::error::Error>> {
+    read_boxes(file, children_start(parent).unwrap_or(parent.payload_start()), parent.end())
+}
+
+/// Descend from `parent` through the first boxes of the given types
+pub fn find_path(file: &mut dyn MediaSource, parent: &BoxHeader, path: &[&str]) -> Result<Option<BoxHeader>, Box<dyn std::error::Error>> {
+    let mut current = parent.clone();
+    for box_type in path {
+        let Some(child) = read_children(file, &current)?.into_iter().find(|child| child.box_type == *box_type) else {
+            return Ok(None);
+        };
+        current = child;
+    }
+    Ok(Some(current))
+}
+
+/// Payload of a box (everything after its header)
+pub fn read_payload(file: &mut dyn MediaSource, box_header: &BoxHeader) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let length = buffer_length(format!("box '{}'", box_header.box_type), box_header.offset, box_header.size - box_header.header_size)?;
+    let mut payload = vec![0u8; length];
+    file.seek(SeekFrom::Start(box_header.payload_start()))?;
+    file.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// File offset of the first child box of a container box, or `None` if the box has no children
+///
+/// `meta` is a full box: version and flags precede its children.
+pub fn children_start(box_header: &BoxHeader) -> Option<u64> {
+    match box_header.box_type.as_str() {
+        | "meta" => Some(box_header.payload_start() + 4),
+        | "moov" | "trak" | "mdia" | "minf" | "stbl" | "udta" | "edts" | "dinf" | "mvex" | "moof" | "traf" | "ilst" => Some(box_header.payload_start()),
+        | _ => None,
+    }
+}
+
+/// Flags of a full box (the 24 bits after the version)
+pub fn full_box_flags(payload: &[u8]) -> u32 {
+    payload.get(..4).map_or(0, |bytes| u32::from_be_bytes([0, bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Big-endian reader over a box payload that fails on truncated data
+pub struct PayloadReader<'a> {
+    payload: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    pub fn new(payload: &'a [u8], pos: usize) -> Self {
+        PayloadReader { payload, pos }
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self.payload.get(self.pos..self.pos + count).ok_or("truncated box")?;
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap_or_default()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap_or_default()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.bytes(8)?.try_into().unwrap_or_default()))
+    }
+}
@@ -0,0 +1,127 @@
+/// Export of raw ISO BMFF box payloads (`extract box`)
+///
+/// Boxes are addressed by a path of four-character types from the top level down, e.g.
+/// `moov/udta/meta/ilst`. A type alone means its first occurrence among the siblings; an index
+/// in brackets picks another one, counting from 0 (`moov/trak[1]/mdia` is the `mdia` box of the
+/// second track).
+use crate::isobmff_box::{BoxHeader, children_start, read_boxes};
+use crate::media_source::MediaSource;
+use crate::path_display::display_path;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// One step of a box path: a box type and the index among the siblings of that type
+#[derive(Debug, Clone, PartialEq)]
+struct PathStep {
+    box_type: String,
+    index: usize,
+}
+
+impl PathStep {
+    fn parse(step: &str) -> Result<Self, String> {
+        let (box_type, index) = match step.strip_suffix(']').and_then(|step| step.split_once('[')) {
+            | Some((box_type, index)) => (box_type, index.parse::<usize>().map_err(|_| format!("invalid box index in '{}'", step))?),
+            | None => (step, 0),
+        };
+        if box_type.chars().count() != 4 {
+            return Err(format!("'{}' is not a four-character box type", box_type));
+        }
+        Ok(PathStep { box_type: box_type.to_string(), index })
+    }
+
+    /// Whether a box type read from the file matches this step
+    ///
+    /// Types like '©nam' hold Latin-1 bytes that are not valid UTF-8 and are read as
+    /// replacement characters, so the step is compared in the same form.
+    fn matches(&self, box_type: &str) -> bool {
+        if box_type == self.box_type {
+            return true;
+        }
+        let latin1: Option<Vec<u8>> = self.box_type.chars().map(|c| u8::try_from(c as u32).ok()).collect();
+        latin1.is_some_and(|bytes| String::from_utf8_lossy(&bytes) == box_type)
+    }
+}
+
+impl std::fmt::Display for PathStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.index == 0 {
+            write!(f, "{}", self.box_type)
+        } else {
+            write!(f, "{}[{}]", self.box_type, self.index)
+        }
+    }
+}
+
+/// Find the box at `path` (e.g. `moov/trak[1]/mdia`)
+///
+/// Returns the box and the path step that found it, which shows the type as given rather than
+/// as read from the file.
+pub fn find_box(file: &mut dyn MediaSource, path: &str) -> Result<(BoxHeader, String), Box<dyn std::error::Error>> {
+    let steps = path.split('/').filter(|step| !step.is_empty()).map(PathStep::parse).collect::<Result<Vec<_>, _>>()?;
+    if steps.is_empty() {
+        return Err("empty box path".into());
+    }
+
+    let file_size = file.byte_len()?;
+    let mut siblings = read_boxes(file, 0, file_size)?;
+    let mut found: Option<BoxHeader> = None;
+    let mut walked: Vec<String> = Vec::new();
+
+    for step in &steps {
+        if let Some(parent) = &found {
+            // Metadata items ('©nam', 'covr', ...) have arbitrary types but always contain 'data' boxes
+            let in_ilst = walked.len() >= 2 && walked[walked.len() - 2] == "ilst";
+            let start = if in_ilst {
+                Some(parent.payload_start())
+            } else {
+                children_start(parent)
+            };
+            let Some(start) = start else {
+                return Err(format!("{} is not a container box", walked.join("/")).into());
+            };
+            siblings = read_boxes(file, start, parent.end())?;
+        }
+        let candidates: Vec<&BoxHeader> = siblings.iter().filter(|box_header| step.matches(&box_header.box_type)).collect();
+        let Some(&box_header) = candidates.get(step.index) else {
+            let location = if walked.is_empty() {
+                "at the top level".to_string()
+            } else {
+                format!("in {}", walked.join("/"))
+            };
+            let available: Vec<&str> = siblings.iter().map(|box_header| box_header.box_type.as_str()).collect();
+            let available = if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            };
+            return Err(format!("no box {} {} (available: {})", step, location, available).into());
+        };
+        found = Some(box_header.clone());
+        walked.push(step.to_string());
+    }
+    let box_header = found.expect("box path has at least one step");
+    Ok((box_header, walked.pop().unwrap_or_default()))
+}
+
+/// Write the payload of the box at `box_path` (everything after its header) to `output`
+pub fn extract_box(path: &Path, box_path: &str, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let (box_header, box_type) = find_box(&mut file, box_path)?;
+
+    // Payloads like 'mdat' can be larger than memory, so they are copied rather than read
+    file.seek(SeekFrom::Start(box_header.payload_start()))?;
+    let mut payload = (&mut file).take(box_header.size - box_header.header_size);
+    let mut out_file = File::create(output)?;
+    let written = io::copy(&mut payload, &mut out_file)?;
+
+    outln!(
+        "Box payload written: {} ('{}' box at offset {}, {} bytes without the {}-byte header)",
+        display_path(output),
+        box_type,
+        box_header.offset,
+        written,
+        box_header.header_size
+    );
+    Ok(())
+}
@@ -0,0 +1,343 @@
+/// Chapters written into MP4 files (`mp4-chapters`)
+///
+/// MP4 players read chapters from two places: the Nero chapter list (`moov/udta/chpl`, start
+/// times and titles) and a QuickTime chapter track, a disabled text track with one sample per
+/// chapter that the audio and video tracks reference through `tref/chap` (Apple players). The
+/// chapters come from the CHAP frames of an ID3v2 tag, for example of the MP3 edition of the same
+/// audiobook, sorted by start time with titles from TIT2 as in `chapters`.
+///
+/// A copy of the input is written with both (or one of them). Chapters the file already has, the
+/// chpl box and the tracks referenced by `tref/chap`, are replaced; the text samples of a replaced
+/// chapter track stay in the media data unreferenced. The samples of the new track go into an
+/// `mdat` box right behind the moov box, so files with fast start keep it. The media data behind
+/// the moov box moves by the growth, and its chunk offsets (stco, co64) are shifted like in
+/// `faststart`.
+use crate::id3v2_chapter_frame::ChapterFrame;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::isobmff_atom::{Atom, copy_with_moov, handler, read_moov, read_u32, relocate_chunks, track_id};
+use crate::isobmff_box::{BoxHeader, read_boxes};
+use crate::options::Mp4ChapterStyle;
+use crate::path_display::display_path;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Timescale of the chapter track (milliseconds, like CHAP times)
+const CHAPTER_TIMESCALE: u32 = 1000;
+
+/// ISO 639-2 "und" packed into 15 bits as the mdhd language
+const LANGUAGE_UNDETERMINED: u16 = 0x55C4;
+
+/// Unity matrix of tkhd and the gmhd text box
+const UNITY_MATRIX: [u32; 9] = [0x00010000, 0, 0, 0, 0x00010000, 0, 0, 0, 0x40000000];
+
+/// Text sample entry contents after the data reference index, as ffmpeg writes them for chapter tracks
+const TEXT_SAMPLE_ENTRY: [u8; 43] = [
+    0, 0, 0, 1, // display flags
+    0, 0, // justification
+    0, 0, 0, 0, // background color
+    0, 0, 0, 0, 0, 0, 0, 0, // default text box
+    0, 0, 0, 0, 0, 1, 0, 0, // style record: characters, font ID, face, size
+    0, 0, 0, 0, // foreground color
+    0, 0, 0, 13, b'f', b't', b'a', b'b', 0, 1, 0, 1, 0, // font table with one unnamed font
+];
+
+/// Atom after each chapter title that declares it UTF-8
+const ENCD_UTF8: [u8; 12] = [0, 0, 0, 12, b'e', b'n', b'c', b'd', 0, 0, 1, 0];
+
+/// Write a copy of `input` with the chapters of the ID3v2 tag of `from` to `output`
+pub fn write_mp4_chapters(input: &Path, output: &Path, from: &Path, style: Mp4ChapterStyle) -> Result<(), Box<dyn std::error::Error>> {
+    if let (Ok(input), Ok(output)) = (fs::canonicalize(input), fs::canonicalize(output))
+        && input == output
+    {
+        return Err("the output would overwrite the input file; write the copy to another path".into());
+    }
+    let tag = Id3v2Tag::read(&mut File::open(from)?)?.ok_or_else(|| format!("no ID3v2 tag in {}", display_path(from)))?;
+    let mut chapters: Vec<&ChapterFrame> = tag.chapters().collect();
+    if chapters.is_empty() {
+        return Err(format!("no chapters (CHAP) in {}", display_path(from)).into());
+    }
+    chapters.sort_by_key(|chapter| (chapter.start_time, chapter.end_time));
+    if let Some(pair) = chapters.windows(2).find(|pair| pair[0].start_time == pair[1].start_time) {
+        return Err(format!("chapters '{}' and '{}' start at the same time; MP4 chapters cannot overlap", pair[0].element_id, pair[1].element_id).into());
+    }
+    let titles: Vec<&str> = chapters.iter().map(|chapter| chapter.title().unwrap_or(&chapter.element_id)).collect();
+    let with_list = style != Mp4ChapterStyle::Track;
+    let with_track = style != Mp4ChapterStyle::Chpl;
+    if with_list && chapters.len() > 255 {
+        return Err(format!("{} chapters, but the chpl box holds at most 255; use --style track", chapters.len()).into());
+    }
+
+    let mut file = File::open(input)?;
+    let file_size = file.metadata()?.len();
+    let boxes = read_boxes(&mut file, 0, file_size)?;
+    let covered = boxes.last().map_or(0, BoxHeader::end);
+    if covered != file_size {
+        return Err(format!("the boxes end at offset {} but the file has {} bytes; data outside the boxes cannot be moved", covered, file_size).into());
+    }
+    let Some(moov_header) = boxes.iter().find(|box_header| box_header.box_type == "moov") else {
+        return Err("no movie ('moov' box)".into());
+    };
+    if boxes.iter().any(|box_header| box_header.box_type == "moof") {
+        return Err("fragmented files are not supported; their fragments address data by absolute offsets".into());
+    }
+    let mut moov = read_moov(&mut file, moov_header)?;
+
+    let mvhd = moov.child(b"mvhd").ok_or("no movie header ('mvhd' box)")?;
+    let read_u64 = |at: usize| mvhd.payload.get(at..at + 8).map_or(0, |bytes| u64::from_be_bytes(bytes.try_into().unwrap_or_default()));
+    let (times, movie_timescale, movie_duration) = if mvhd.payload.first() == Some(&1) {
+        ([read_u64(4), read_u64(12)], read_u32(&mvhd.payload, 20), read_u64(24))
+    } else {
+        ([read_u32(&mvhd.payload, 4) as u64, read_u32(&mvhd.payload, 8) as u64], read_u32(&mvhd.payload, 12), read_u32(&mvhd.payload, 16) as u64)
+    };
+    // The chapter track is written with 32-bit times; later ones are left unset
+    let times: Vec<u8> = times.iter().flat_map(|&time| u32::try_from(time).unwrap_or(0).to_be_bytes()).collect();
+    let next_track_id = mvhd.payload.len().checked_sub(4).map_or(0, |at| read_u32(&mvhd.payload, at));
+
+    let removed = remove_chapters(&mut moov);
+    if with_list {
+        let chpl = chapter_list(&chapters, &titles);
+        match moov.child_mut(b"udta").and_then(|udta| udta.children.as_mut()) {
+            | Some(children) => children.push(chpl),
+            | None => moov.children.get_or_insert_default().push(Atom::container(b"udta", vec![chpl])),
+        }
+    }
+
+    // Text samples of the chapter track: each chapter lasts until the next one starts; the first starts at 0
+    let mut samples = Vec::new();
+    let mut sizes = Vec::new();
+    let mut durations = Vec::new();
+    if with_track {
+        let movie_ms = if movie_timescale == 0 {
+            0
+        } else {
+            movie_duration.saturating_mul(1000) / movie_timescale as u64
+        };
+        for (index, (chapter, title)) in chapters.iter().zip(&titles).enumerate() {
+            let start = if index == 0 {
+                0
+            } else {
+                chapter.start_time
+            };
+            let end = match chapters.get(index + 1) {
+                | Some(next) => next.start_time,
+                | None if chapter.end_time > chapter.start_time => chapter.end_time,
+                | None => (movie_ms.min(u32::MAX as u64) as u32).max(chapter.start_time.saturating_add(1)),
+            };
+            let text = title.as_bytes();
+            let text = &text[..text.len().min(u16::MAX as usize)];
+            let sample_start = samples.len();
+            samples.extend_from_slice(&(text.len() as u16).to_be_bytes());
+            samples.extend_from_slice(text);
+            samples.extend_from_slice(&ENCD_UTF8);
+            sizes.push((samples.len() - sample_start) as u32);
+            durations.push(end - start);
+        }
+    }
+
+    let mut chapter_track_id = 0;
+    let mut referencing = 0;
+    if with_track {
+        let traks = || moov.children().iter().filter(|child| &child.box_type == b"trak");
+        chapter_track_id = traks().filter_map(track_id).max().map_or(1, |id| id + 1).max(next_track_id);
+        for trak in moov.children.iter_mut().flatten().filter(|child| &child.box_type == b"trak") {
+            if matches!(handler(trak), Some(b"soun") | Some(b"vide")) {
+                add_chapter_reference(trak, chapter_track_id);
+                referencing += 1;
+            }
+        }
+        if referencing == 0 {
+            return Err("no audio or video track to attach the chapter track to".into());
+        }
+        if let Some(mvhd) = moov.child_mut(b"mvhd")
+            && let Some(at) = mvhd.payload.len().checked_sub(4)
+        {
+            mvhd.payload[at..].copy_from_slice(&chapter_track_id.saturating_add(1).to_be_bytes());
+        }
+    }
+
+    // The chapter samples follow the moov box, so fast start files stay fast start; everything behind moves by the growth
+    let mut samples_box = Vec::new();
+    if with_track {
+        Atom::leaf(b"mdat", samples).encode(&mut samples_box);
+    }
+    let mut encoded = Vec::new();
+    moov.encode(&mut encoded);
+    let trak_size = |wide| {
+        let mut bytes = Vec::new();
+        chapter_track(chapter_track_id, &times, movie_timescale, &sizes, &durations, 0, wide).encode(&mut bytes);
+        bytes.len() as u64
+    };
+    let mut moov_size = encoded.len() as u64;
+    let mut wide = false;
+    if with_track {
+        moov_size += trak_size(false);
+        if moov_header.offset + moov_size + 8 > u32::MAX as u64 {
+            wide = true;
+            moov_size += 4;
+        }
+    }
+    let samples_offset = moov_header.offset + moov_size + 8;
+    let delta = (moov_size + samples_box.len() as u64) as i64 - moov_header.size as i64;
+    let old_moov_end = moov_header.end();
+    let shift = |offset: u64| {
+        if offset >= old_moov_end {
+            offset.checked_add_signed(delta)
+        } else {
+            Some(offset)
+        }
+    };
+    let shifted = relocate_chunks(&mut moov, &shift)?;
+    if with_track {
+        moov.children.get_or_insert_default().push(chapter_track(chapter_track_id, &times, movie_timescale, &sizes, &durations, samples_offset, wide));
+    }
+    let mut encoded = Vec::new();
+    moov.encode(&mut encoded);
+
+    let mut out = BufWriter::new(File::create(output)?);
+    encoded.extend_from_slice(&samples_box);
+    copy_with_moov(&mut file, &boxes, moov_header, &encoded, &mut out)?;
+    out.flush()?;
+
+    if removed > 0 {
+        outln!("Replaced the existing chapters ({} chpl/chapter track boxes removed)", removed);
+    }
+    if with_list {
+        outln!("Wrote {} chapters to the Nero chapter list ('chpl')", chapters.len());
+    }
+    if with_track {
+        outln!("Wrote {} chapters as text track {}, referenced by {} track(s)", chapters.len(), chapter_track_id, referencing);
+    }
+    if shifted > 0 {
+        outln!("Shifted {} chunk offsets behind the grown 'moov' box", shifted);
+    }
+    outln!("Copy with chapters written: {}", display_path(output));
+    Ok(())
+}
+
+/// Remove the chpl box, the chapter tracks and the references to them; returns how many boxes went
+fn remove_chapters(moov: &mut Atom) -> usize {
+    let mut removed = 0;
+    if let Some(udta) = moov.child_mut(b"udta")
+        && let Some(children) = udta.children.as_mut()
+    {
+        let before = children.len();
+        children.retain(|child| &child.box_type != b"chpl");
+        removed += before - children.len();
+    }
+
+    let mut chapter_tracks = Vec::new();
+    for trak in moov.children.iter_mut().flatten().filter(|child| &child.box_type == b"trak") {
+        let Some(tref) = trak.child_mut(b"tref") else {
+            continue;
+        };
+        for chap in tref.children().iter().filter(|child| &child.box_type == b"chap") {
+            chapter_tracks.extend(chap.payload.chunks_exact(4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])));
+        }
+        if let Some(children) = tref.children.as_mut() {
+            children.retain(|child| &child.box_type != b"chap");
+        }
+        if tref.children().is_empty()
+            && let Some(children) = trak.children.as_mut()
+        {
+            children.retain(|child| &child.box_type != b"tref");
+        }
+    }
+    if let Some(children) = moov.children.as_mut() {
+        let before = children.len();
+        children.retain(|child| &child.box_type != b"trak" || handler(child) != Some(&b"text"[..]) || !track_id(child).is_some_and(|id| chapter_tracks.contains(&id)));
+        removed += before - children.len();
+    }
+    removed
+}
+
+/// Add the chapter track to the `tref/chap` references of a trak box
+fn add_chapter_reference(trak: &mut Atom, chapter_track: u32) {
+    let chap = Atom::leaf(b"chap", chapter_track.to_be_bytes().to_vec());
+    match trak.child_mut(b"tref").and_then(|tref| tref.children.as_mut()) {
+        | Some(children) => children.push(chap),
+        | None => {
+            // tref follows tkhd (and edts) in the usual box order
+            let children = trak.children.get_or_insert_default();
+            let at = children.iter().position(|child| &child.box_type == b"mdia").unwrap_or(children.len());
+            children.insert(at, Atom::container(b"tref", vec![chap]));
+        }
+    }
+}
+
+/// Nero chapter list: start times in 100 ns units and titles of at most 255 bytes
+fn chapter_list(chapters: &[&ChapterFrame], titles: &[&str]) -> Atom {
+    let mut payload = vec![1, 0, 0, 0, 0, 0, 0, 0, chapters.len() as u8];
+    for (chapter, title) in chapters.iter().zip(titles) {
+        let mut length = title.len().min(255);
+        while !title.is_char_boundary(length) {
+            length -= 1;
+        }
+        payload.extend_from_slice(&(chapter.start_time as u64 * 10_000).to_be_bytes());
+        payload.push(length as u8);
+        payload.extend_from_slice(&title.as_bytes()[..length]);
+    }
+    Atom::leaf(b"chpl", payload)
+}
+
+/// Disabled text track with one sample per chapter, all in one chunk at `chunk_offset`
+///
+/// `times` are the creation and modification times as two 32-bit values, taken over from the movie.
+fn chapter_track(track_id: u32, times: &[u8], movie_timescale: u32, sizes: &[u32], durations: &[u32], chunk_offset: u64, wide: bool) -> Atom {
+    let total_ms: u64 = durations.iter().map(|&duration| duration as u64).sum();
+    let matrix: Vec<u8> = UNITY_MATRIX.iter().flat_map(|value| value.to_be_bytes()).collect();
+
+    // Flags 2: in movie, but not enabled, so players do not show the titles as subtitles
+    let mut tkhd = times.to_vec();
+    tkhd.extend_from_slice(&track_id.to_be_bytes());
+    tkhd.extend_from_slice(&[0; 4]);
+    tkhd.extend_from_slice(&((total_ms * movie_timescale as u64 / 1000).min(u32::MAX as u64) as u32).to_be_bytes());
+    tkhd.extend_from_slice(&[0; 16]);
+    tkhd.extend_from_slice(&matrix);
+    tkhd.extend_from_slice(&[0; 8]);
+
+    let mut mdhd = times.to_vec();
+    mdhd.extend_from_slice(&CHAPTER_TIMESCALE.to_be_bytes());
+    mdhd.extend_from_slice(&(total_ms.min(u32::MAX as u64) as u32).to_be_bytes());
+    mdhd.extend_from_slice(&LANGUAGE_UNDETERMINED.to_be_bytes());
+    mdhd.extend_from_slice(&[0; 2]);
+
+    let mut hdlr = vec![0; 4];
+    hdlr.extend_from_slice(b"text");
+    hdlr.extend_from_slice(&[0; 12]);
+    hdlr.extend_from_slice(b"Chapters\0");
+
+    let gmin = Atom::full(b"gmin", 0, &[0, 0x40, 0x80, 0, 0x80, 0, 0x80, 0, 0, 0, 0, 0]);
+    let gmhd = Atom::container(b"gmhd", vec![gmin, Atom::leaf(b"text", matrix.clone())]);
+    let dref = Atom::full(b"dref", 0, &[&1u32.to_be_bytes()[..], &[0, 0, 0, 12], b"url ", &[0, 0, 0, 1]].concat());
+
+    let mut entry = vec![0, 0, 0, 0, 0, 0, 0, 1];
+    entry.extend_from_slice(&TEXT_SAMPLE_ENTRY);
+    let mut stsd = 1u32.to_be_bytes().to_vec();
+    Atom::leaf(b"text", entry).encode(&mut stsd);
+
+    // Runs of equal durations
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &duration in durations {
+        match runs.last_mut() {
+            | Some((count, last)) if *last == duration => *count += 1,
+            | _ => runs.push((1, duration)),
+        }
+    }
+    let mut stts = (runs.len() as u32).to_be_bytes().to_vec();
+    stts.extend(runs.iter().flat_map(|&(count, duration)| [count.to_be_bytes(), duration.to_be_bytes()].concat()));
+    let mut stsz = vec![0; 4];
+    stsz.extend_from_slice(&(sizes.len() as u32).to_be_bytes());
+    stsz.extend(sizes.iter().flat_map(|size| size.to_be_bytes()));
+    let stsc = [1u32, 1, sizes.len() as u32, 1].iter().flat_map(|value| value.to_be_bytes()).collect::<Vec<u8>>();
+    let chunks = if wide {
+        Atom::full(b"co64", 0, &[&1u32.to_be_bytes()[..], &chunk_offset.to_be_bytes()].concat())
+    } else {
+        Atom::full(b"stco", 0, &[1u32.to_be_bytes(), (chunk_offset as u32).to_be_bytes()].concat())
+    };
+
+    let stbl = Atom::container(b"stbl", vec![Atom::full(b"stsd", 0, &stsd), Atom::full(b"stts", 0, &stts), Atom::full(b"stsc", 0, &stsc), Atom::full(b"stsz", 0, &stsz), chunks]);
+    let minf = Atom::container(b"minf", vec![gmhd, Atom::container(b"dinf", vec![dref]), stbl]);
+    let mdia = Atom::container(b"mdia", vec![Atom::full(b"mdhd", 0, &mdhd), Atom::full(b"hdlr", 0, &hdlr), minf]);
+    Atom::container(b"trak", vec![Atom::full(b"tkhd", 2, &tkhd), mdia])
+}
@@ -0,0 +1,124 @@
+/// Byte ranges of ISO BMFF files over media time (`debug --chunk-map`)
+///
+/// Progressive download plays a file while it is still arriving, front to back. That works if
+/// the movie box (moov) with the sample tables comes before the media data (mdat), "fast start",
+/// and if the samples of all tracks for the same moment lie close together. The map lists, for
+/// every interval of media time, the byte ranges of the samples decoded in it (from the sample
+/// tables of all tracks) and how far into the file a download must have got to play up to the
+/// end of the interval. Times are decoding times; edit lists are not applied.
+use crate::isobmff_box::{read_boxes, read_children};
+use crate::isobmff_samples::{sample_table, time_to_sample};
+use crate::isobmff_seek_points::{read_track_info, to_ms};
+use crate::media_source::MediaSource;
+use crate::time_format::format_duration;
+use std::collections::BTreeMap;
+
+/// Byte ranges of one interval spread over more than this (and 4 times their size) are flagged
+const MAX_SPREAD: u64 = 512 * 1024;
+
+/// Print the byte ranges needed for each interval of `interval_seconds` and the fast-start check
+pub fn print_chunk_map(file: &mut dyn MediaSource, interval_seconds: f64) -> Result<(), Box<dyn std::error::Error>> {
+    if interval_seconds.is_nan() || interval_seconds <= 0.0 {
+        return Err("the --chunk-map interval must be more than 0 seconds".into());
+    }
+    let interval_ms = ((interval_seconds * 1000.0).round() as u64).max(1);
+    outln!("\nChunk Map ({} intervals):", format_duration(interval_ms));
+    let file_size = file.byte_len()?;
+    let file_boxes = read_boxes(file, 0, file_size)?;
+    let Some(moov) = file_boxes.iter().find(|box_header| box_header.box_type == "moov") else {
+        outln!("    No movie ('moov' box)");
+        return Ok(());
+    };
+
+    let mut warnings = Vec::new();
+    match file_boxes.iter().find(|box_header| box_header.box_type == "mdat") {
+        | Some(mdat) if moov.offset < mdat.offset => outln!("  Fast start: 'moov' at offset {} comes before 'mdat' at offset {}", moov.offset, mdat.offset),
+        | Some(mdat) => warnings.push(format!("'moov' at offset {} follows 'mdat' at offset {}; playback cannot start before the end of the file is downloaded", moov.offset, mdat.offset)),
+        | None => outln!("  No media data ('mdat' box)"),
+    }
+    if file_boxes.iter().any(|box_header| box_header.box_type == "moof") {
+        outln!("  Fragmented file: samples in movie fragments are not mapped; each 'moof' and its 'mdat' are downloaded as a unit");
+    }
+
+    // Sample ranges of all tracks by interval
+    let mut intervals: BTreeMap<u64, Vec<(u64, u64)>> = BTreeMap::new();
+    for trak in read_children(file, moov)?.into_iter().filter(|child| child.box_type == "trak") {
+        let Some(track) = read_track_info(file, &trak)? else {
+            continue;
+        };
+        let Some(stbl) = &track.stbl else {
+            continue;
+        };
+        let samples = sample_table(file, stbl, file_size)?;
+        if samples.is_empty() {
+            continue;
+        }
+        if track.timescale == 0 {
+            warnings.push(format!("track {} has no media timescale; its samples are not mapped", track.track_id));
+            continue;
+        }
+        outln!("  Track {} ({}, timescale {}): {} samples", track.track_id, track.handler, track.timescale, samples.len());
+        let stts = time_to_sample(file, stbl)?;
+        let mut durations = stts.iter().flat_map(|&(count, duration)| std::iter::repeat_n(duration, count as usize));
+        let mut time = 0u64;
+        for sample in &samples {
+            let interval = to_ms(time, track.timescale) / interval_ms;
+            intervals.entry(interval).or_default().push((sample.offset, sample.offset.saturating_add(sample.size as u64)));
+            time = time.saturating_add(durations.next().unwrap_or(0) as u64);
+        }
+    }
+
+    // A player needs the sample tables before any sample
+    let mut needed = moov.end();
+    let mut startup = None;
+    let mut next_interval = 0;
+    for (&interval, ranges) in &mut intervals {
+        if interval > next_interval {
+            outln!("  {}  no samples until {}", format_duration(next_interval * interval_ms), format_duration(interval * interval_ms));
+        }
+        next_interval = interval + 1;
+
+        ranges.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::new();
+        for &(start, end) in ranges.iter() {
+            match merged.last_mut() {
+                | Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                | _ => merged.push((start, end)),
+            }
+        }
+        let bytes: u64 = merged.iter().map(|(start, end)| end - start).sum();
+        let first = merged.first().map_or(0, |range| range.0);
+        let last = merged.iter().map(|range| range.1).max().unwrap_or(0);
+        needed = needed.max(last);
+        startup.get_or_insert(needed);
+
+        let list = merged.iter().map(|(start, end)| format!("0x{:08X}-0x{:08X}", start, end)).collect::<Vec<_>>().join(", ");
+        outln!("  {}  {} ({} bytes)  needed: 0x{:08X} ({:.1}%)", format_duration(interval * interval_ms), list, bytes, needed, percent(needed, file_size));
+        let spread = last - first;
+        if spread > MAX_SPREAD && spread > bytes.saturating_mul(4) {
+            warnings.push(format!(
+                "the {} bytes of the interval at {} are spread over {} bytes of the file; the tracks are poorly interleaved",
+                bytes,
+                format_duration(interval * interval_ms),
+                spread
+            ));
+        }
+    }
+
+    match startup {
+        | Some(startup) => outln!("  Startup: {} bytes ({:.1}% of the file) before the first {} can play", startup, percent(startup, file_size), format_duration(interval_ms)),
+        | None => outln!("  No samples in the sample tables"),
+    }
+    for warning in warnings {
+        outln!("  WARNING: {}", warning);
+    }
+    Ok(())
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
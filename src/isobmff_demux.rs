@@ -0,0 +1,335 @@
+/// Demuxing of single ISO BMFF tracks into elementary streams (`extract track`)
+///
+/// The samples of the track are written in decoding order. AAC audio gets an ADTS header per
+/// frame, built from the AudioSpecificConfig in the esds box; H.264 and H.265 video is turned
+/// from length-prefixed NAL units into an Annex B byte stream, with the parameter sets of the
+/// avcC or hvcC box in front. Other codecs (MP3, which frames itself, or anything unknown) are
+/// written as the plain sample data.
+use crate::checked_size::{end_offset, slice_at};
+use crate::isobmff_box::{BoxHeader, find_path, read_boxes, read_children, read_payload};
+use crate::isobmff_samples::{SampleLocation, track_samples};
+use crate::isobmff_tracks::track_id;
+use crate::media_source::MediaSource;
+use crate::path_display::display_path;
+use std::fs::File;
+use std::io::{BufWriter, SeekFrom, Write};
+use std::path::Path;
+
+/// Annex B start code written before every NAL unit
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// Largest frame an ADTS header can describe (13-bit length including the header)
+const MAX_ADTS_FRAME: usize = 0x1FFF;
+
+/// How the samples of a track are framed in the output
+#[derive(Debug, Clone)]
+enum StreamFormat {
+    /// AAC with a 7-byte ADTS header per frame
+    Adts { profile: u8, frequency_index: u8, channels: u8 },
+    /// NAL units with `length_size`-byte length prefixes, converted to start codes
+    AnnexB { length_size: usize, parameter_sets: Vec<Vec<u8>> },
+    /// Sample data as it is
+    Raw,
+}
+
+/// Write the samples of track `track_id` to `output` as an elementary stream
+pub fn extract_track(path: &Path, track_id: u32, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let file_size = file.byte_len()?;
+    let file_boxes = read_boxes(&mut file, 0, file_size)?;
+    let moov = file_boxes.iter().find(|box_header| box_header.box_type == "moov").ok_or("no 'moov' box")?.clone();
+    let trak = find_track(&mut file, &moov, track_id)?;
+
+    let stsd = find_path(&mut file, &trak, &["mdia", "minf", "stbl", "stsd"])?.ok_or_else(|| format!("track {} has no sample description (stsd)", track_id))?;
+    let (description, format) = stream_format(&read_payload(&mut file, &stsd)?)?;
+    let samples = track_samples(&mut file, &file_boxes, &moov, &trak, track_id)?;
+    if samples.is_empty() {
+        return Err(format!("track {} has no samples", track_id).into());
+    }
+
+    let written = write_stream(&mut file, &samples, &format, output)?;
+    outln!("Track {} ({}) written: {} ({} samples, {} bytes)", track_id, description, display_path(output), samples.len(), written);
+    Ok(())
+}
+
+/// The `trak` box with the given track ID
+fn find_track(file: &mut dyn MediaSource, moov: &BoxHeader, wanted: u32) -> Result<BoxHeader, Box<dyn std::error::Error>> {
+    let mut available = Vec::new();
+    for trak in read_children(file, moov)?.into_iter().filter(|child| child.box_type == "trak") {
+        let Some(tkhd) = read_children(file, &trak)?.into_iter().find(|child| child.box_type == "tkhd") else {
+            continue;
+        };
+        match track_id(&read_payload(file, &tkhd)?) {
+            | Some(id) if id == wanted => return Ok(trak),
+            | Some(id) => available.push(id.to_string()),
+            | None => {}
+        }
+    }
+    let available = if available.is_empty() {
+        "none".to_string()
+    } else {
+        available.join(", ")
+    };
+    Err(format!("no track {} (tracks: {})", wanted, available).into())
+}
+
+/// Codec description and output framing from the first entry of a sample description (stsd)
+fn stream_format(stsd: &[u8]) -> Result<(String, StreamFormat), Box<dyn std::error::Error>> {
+    let (entry_type, entry) = sample_entry(stsd).ok_or("invalid sample description (stsd)")?;
+
+    match entry_type.as_str() {
+        | "mp4a" => {
+            let esds = mp4a_children(entry).and_then(|children| find_child(children, b"esds")).ok_or("mp4a sample entry without esds box")?;
+            let (object_type, config) = decoder_config(esds.get(4..).unwrap_or_default()).ok_or("esds box without decoder configuration")?;
+            match object_type {
+                | 0x69 | 0x6B => Ok(("MP3".to_string(), StreamFormat::Raw)),
+                | 0x40 | 0x66..=0x68 => adts_format(config.ok_or("AAC track without AudioSpecificConfig")?),
+                | _ => Ok((format!("mp4a object type 0x{:02X}, raw samples", object_type), StreamFormat::Raw)),
+            }
+        }
+        | "avc1" | "avc3" => {
+            let avcc = entry.get(86..).and_then(|children| find_child(children, b"avcC")).ok_or("avc1 sample entry without avcC box")?;
+            Ok(("H.264 as Annex B".to_string(), parse_avcc(avcc).ok_or("invalid avcC box")?))
+        }
+        | "hvc1" | "hev1" => {
+            let hvcc = entry.get(86..).and_then(|children| find_child(children, b"hvcC")).ok_or("hvc1 sample entry without hvcC box")?;
+            Ok(("H.265 as Annex B".to_string(), parse_hvcc(hvcc).ok_or("invalid hvcC box")?))
+        }
+        | _ => Ok((format!("{}, raw samples", entry_type), StreamFormat::Raw)),
+    }
+}
+
+/// Type and bytes (including the box header) of the first entry of a sample description (stsd)
+pub fn sample_entry(stsd: &[u8]) -> Option<(String, &[u8])> {
+    let entry_size = u32::from_be_bytes(stsd.get(8..12)?.try_into().ok()?) as usize;
+    let entry = stsd.get(8..8 + entry_size).filter(|entry| entry.len() >= 16)?;
+    Some((String::from_utf8_lossy(&entry[4..8]).to_string(), entry))
+}
+
+/// Child boxes of an mp4a sample entry
+pub fn mp4a_children(entry: &[u8]) -> Option<&[u8]> {
+    // QuickTime sound descriptions of version 1 and 2 have extra fields before the child boxes
+    let extra = match u16::from_be_bytes([*entry.get(16)?, *entry.get(17)?]) {
+        | 1 => 16,
+        | 2 => 36,
+        | _ => 0,
+    };
+    entry.get(36 + extra..)
+}
+
+/// Payload of the first child box of type `box_type` in `data`
+pub fn find_child<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().ok()?);
+        if size < 8 {
+            return None;
+        }
+        let end = end_offset("box", pos as u64, size as u64, data.len() as u64).ok()? as usize;
+        if &data[pos + 4..pos + 8] == box_type {
+            return Some(&data[pos + 8..end]);
+        }
+        pos = end;
+    }
+    None
+}
+
+/// Object type indication and decoder specific info from the descriptors of an esds box
+pub fn decoder_config(descriptors: &[u8]) -> Option<(u8, Option<&[u8]>)> {
+    let (tag, body) = read_descriptor(descriptors)?;
+    if tag != 0x03 {
+        return None;
+    }
+    // ES_Descriptor: ES_ID, flags and the optional fields they announce
+    let flags = *body.get(2)?;
+    let mut pos = 3;
+    if flags & 0x80 != 0 {
+        pos += 2;
+    }
+    if flags & 0x40 != 0 {
+        pos += 1 + *body.get(pos)? as usize;
+    }
+    if flags & 0x20 != 0 {
+        pos += 2;
+    }
+    let (tag, body) = read_descriptor(body.get(pos..)?)?;
+    if tag != 0x04 {
+        return None;
+    }
+    // DecoderConfigDescriptor: object type, stream type, buffer size and bit rates
+    let object_type = *body.first()?;
+    let config = body.get(13..).and_then(read_descriptor).filter(|(tag, _)| *tag == 0x05).map(|(_, config)| config);
+    Some((object_type, config))
+}
+
+/// Tag and body of an MPEG-4 descriptor (the length uses up to four 7-bit bytes)
+fn read_descriptor(data: &[u8]) -> Option<(u8, &[u8])> {
+    let tag = *data.first()?;
+    let mut length = 0usize;
+    let mut pos = 1;
+    for _ in 0..4 {
+        let byte = *data.get(pos)?;
+        pos += 1;
+        length = (length << 7) | (byte & 0x7F) as usize;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Some((tag, data.get(pos..pos + length)?))
+}
+
+/// ADTS parameters from an AudioSpecificConfig
+fn adts_format(config: &[u8]) -> Result<(String, StreamFormat), Box<dyn std::error::Error>> {
+    let mut bits = BitReader::new(config);
+    let truncated = "truncated AudioSpecificConfig";
+    let mut object_type = bits.object_type().ok_or(truncated)?;
+    let frequency_index = bits.read(4).ok_or(truncated)?;
+    if frequency_index == 15 {
+        return Err("AAC with an explicit sampling frequency cannot be written as ADTS".into());
+    }
+    let channels = bits.read(4).ok_or(truncated)?;
+    // HE-AAC (SBR) and HE-AAC v2 (PS) signal the core object type after the extension frequency
+    if object_type == 5 || object_type == 29 {
+        if bits.read(4).ok_or(truncated)? == 15 {
+            bits.read(24).ok_or(truncated)?;
+        }
+        object_type = bits.object_type().ok_or(truncated)?;
+    }
+
+    let name = match object_type {
+        | 1 => "AAC Main",
+        | 2 => "AAC LC",
+        | 3 => "AAC SSR",
+        | 4 => "AAC LTP",
+        | _ => return Err(format!("AAC object type {} cannot be written as ADTS", object_type).into()),
+    };
+    Ok((
+        format!("{} as ADTS", name),
+        StreamFormat::Adts { profile: object_type as u8 - 1, frequency_index: frequency_index as u8, channels: channels as u8 },
+    ))
+}
+
+/// Reader for the bit fields of an AudioSpecificConfig
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        BitReader { data, bit: 0 }
+    }
+
+    pub fn read(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = *self.data.get(self.bit / 8)?;
+            value = (value << 1) | ((byte >> (7 - self.bit % 8)) & 1) as u32;
+            self.bit += 1;
+        }
+        Some(value)
+    }
+
+    /// Audio object type, with 31 escaping to 32 and more
+    pub fn object_type(&mut self) -> Option<u32> {
+        match self.read(5)? {
+            | 31 => Some(32 + self.read(6)?),
+            | object_type => Some(object_type),
+        }
+    }
+}
+
+/// Length size and SPS/PPS of an AVC decoder configuration (avcC)
+fn parse_avcc(avcc: &[u8]) -> Option<StreamFormat> {
+    let length_size = (*avcc.get(4)? & 0x03) as usize + 1;
+    let mut parameter_sets = Vec::new();
+    let mut pos = 5;
+    for mask in [0x1F, 0xFF] {
+        let count = *avcc.get(pos)? & mask;
+        pos += 1;
+        for _ in 0..count {
+            let length = u16::from_be_bytes([*avcc.get(pos)?, *avcc.get(pos + 1)?]) as usize;
+            parameter_sets.push(avcc.get(pos + 2..pos + 2 + length)?.to_vec());
+            pos += 2 + length;
+        }
+    }
+    Some(StreamFormat::AnnexB { length_size, parameter_sets })
+}
+
+/// Length size and VPS/SPS/PPS of an HEVC decoder configuration (hvcC)
+fn parse_hvcc(hvcc: &[u8]) -> Option<StreamFormat> {
+    let length_size = (*hvcc.get(21)? & 0x03) as usize + 1;
+    let mut parameter_sets = Vec::new();
+    let mut pos = 23;
+    for _ in 0..*hvcc.get(22)? {
+        let count = u16::from_be_bytes([*hvcc.get(pos + 1)?, *hvcc.get(pos + 2)?]);
+        pos += 3;
+        for _ in 0..count {
+            let length = u16::from_be_bytes([*hvcc.get(pos)?, *hvcc.get(pos + 1)?]) as usize;
+            parameter_sets.push(hvcc.get(pos + 2..pos + 2 + length)?.to_vec());
+            pos += 2 + length;
+        }
+    }
+    Some(StreamFormat::AnnexB { length_size, parameter_sets })
+}
+
+/// Write the samples in `format` to `output`, returning the number of bytes written
+fn write_stream(file: &mut dyn MediaSource, samples: &[SampleLocation], format: &StreamFormat, output: &Path) -> Result<u64, Box<dyn std::error::Error>> {
+    let mut out = BufWriter::new(File::create(output)?);
+    let mut written = 0u64;
+    let mut sample = Vec::new();
+
+    if let StreamFormat::AnnexB { parameter_sets, .. } = format {
+        for parameter_set in parameter_sets {
+            out.write_all(&START_CODE)?;
+            out.write_all(parameter_set)?;
+            written += (START_CODE.len() + parameter_set.len()) as u64;
+        }
+    }
+
+    for (index, location) in samples.iter().enumerate() {
+        sample.resize(location.size as usize, 0);
+        file.seek(SeekFrom::Start(location.offset))?;
+        file.read_exact(&mut sample)?;
+
+        match format {
+            | StreamFormat::Adts { profile, frequency_index, channels } => {
+                let frame_length = sample.len() + 7;
+                if frame_length > MAX_ADTS_FRAME {
+                    return Err(format!("sample {} ({} bytes) is too large for an ADTS frame", index + 1, sample.len()).into());
+                }
+                let header = [
+                    0xFF,
+                    0xF1,
+                    (profile << 6) | (frequency_index << 2) | (channels >> 2),
+                    ((channels & 0x03) << 6) | (frame_length >> 11) as u8,
+                    (frame_length >> 3) as u8,
+                    (((frame_length & 0x07) as u8) << 5) | 0x1F,
+                    0xFC,
+                ];
+                out.write_all(&header)?;
+                out.write_all(&sample)?;
+                written += frame_length as u64;
+            }
+            | StreamFormat::AnnexB { length_size, .. } => {
+                let mut pos = 0;
+                while pos < sample.len() {
+                    let length = slice_at(&sample, pos, *length_size).ok_or_else(|| format!("sample {}: truncated NAL unit length", index + 1))?;
+                    let length = length.iter().fold(0usize, |value, &byte| (value << 8) | byte as usize);
+                    pos += length_size;
+                    let nal_unit = slice_at(&sample, pos, length).ok_or_else(|| format!("sample {}: NAL unit of {} bytes exceeds the sample", index + 1, length))?;
+                    out.write_all(&START_CODE)?;
+                    out.write_all(nal_unit)?;
+                    written += (START_CODE.len() + length) as u64;
+                    pos += length;
+                }
+            }
+            | StreamFormat::Raw => {
+                out.write_all(&sample)?;
+                written += sample.len() as u64;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(written)
+}
@@ -1,7 +1,23 @@
-use crate::cli::DebugOptions;
-use crate::media_dissector::MediaDissector;
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+use crate::audio_format_check::{isobmff_format_findings, print_format_findings};
+use crate::coverage::Coverage;
+use crate::disc_info::{DiscInfo, print_disc_info};
+use crate::dissection_report::{ReportNode, summary_report};
+use crate::finding::{Finding, Severity};
+use crate::image_info::{isobmff_cover_art, print_cover_art};
+use crate::isobmff_box::{BoxHeader, children_start, read_boxes, read_payload};
+use crate::isobmff_telemetry::{isobmff_telemetry, print_telemetry};
+use crate::isobmff_tracks::read_movie;
+use crate::media_dissector::{Capabilities, MediaDissector, Operation, Signature};
+use crate::media_source::MediaSource;
+use crate::media_summary::MediaSummary;
+use crate::options::DebugOptions;
+use crate::podcast_info::{PodcastInfo, print_podcast};
+use crate::release_codes::{isobmff_release_codes, print_release_codes};
+use crate::size_tree::SizeNode;
+use crate::xmp::{XMP_UUID, isobmff_packets};
+
+/// Nesting of container boxes listed by the dissection (unless `--box-depth` is given) and broken down by `--sizes`
+pub const MAX_BOX_DEPTH: usize = 8;
 
 /// ISO Base Media File Format dissector for MP4 files
 pub struct IsobmffDissector;
@@ -11,7 +27,7 @@ impl MediaDissector for IsobmffDissector {
         "ISO BMFF"
     }
 
-    fn dissect_with_options(&self, file: &mut File, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
         dissect_isobmff_with_options(file, options)
     }
 
@@ -27,50 +43,209 @@ impl MediaDissector for IsobmffDissector {
     fn name(&self) -> &'static str {
         "ISO BMFF Dissector"
     }
-}
 
-pub fn dissect_isobmff_with_options(file: &mut File, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
-    // Seek back to beginning
-    file.seek(SeekFrom::Start(0))?;
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            extensions: &["mp4", "m4a", "m4b", "mov"],
+            signatures: &[
+                Signature { offset: 4, magic: b"ftyp", description: "file type box" },
+            ],
+            operations: &[Operation::Read, Operation::Validate],
+        }
+    }
 
-    if options.show_header {
-        println!("\nISO BMFF Container:");
-        println!("  Format: ISO Base Media File Format");
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        let mut coverage = Coverage::new(file.byte_len()?);
+        for box_header in read_boxes(file, 0, coverage.file_size)? {
+            match box_header.box_type.as_str() {
+                | "ftyp" | "moov" | "mdat" => coverage.interpret(box_header.offset, box_header.size),
+                | "uuid" if read_payload(file, &box_header)?.starts_with(&XMP_UUID) => coverage.interpret(box_header.offset, box_header.size),
+                | "free" | "skip" | "wide" => coverage.skip(box_header.offset, box_header.size, format!("free space ('{}' box)", box_header.box_type)),
+                | box_type => coverage.skip(box_header.offset, box_header.size, format!("'{}' box not dissected", box_type)),
+            }
+        }
+        Ok(coverage.finish())
     }
 
-    if !options.show_frames {
-        return Ok(());
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let boxes = read_boxes(file, 0, file_size)?;
+        let children = box_nodes(file, boxes, 0)?;
+        Ok(SizeNode::with_children("file", file_size, children, "other"))
     }
 
-    println!("\nISO BMFF Boxes:");
+    fn summarize(&self, file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let mut summary = MediaSummary::new(self.media_type(), self.name());
+        if let Some((movie, tracks)) = read_movie(file)? {
+            summary.movie = movie;
+            summary.tracks = tracks;
+        }
+        summary.xmp = isobmff_packets(file)?.into_iter().filter_map(Result::ok).collect();
+        summary.podcast = PodcastInfo::from_isobmff(file)?;
+        summary.disc = DiscInfo::from_isobmff(file)?;
+        summary.cover_art = isobmff_cover_art(file)?;
+        summary.release_codes = isobmff_release_codes(file)?;
+        summary.telemetry = isobmff_telemetry(file)?;
+        summary.format_findings = isobmff_format_findings(file)?;
+        Ok(summary)
+    }
 
-    let mut pos = 0u64;
+    fn report(&self, file: &mut dyn MediaSource) -> Result<ReportNode, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let mut summary = self.summarize(file)?;
+        summary.coverage = Some(self.coverage(file)?);
+        let mut root = summary_report(&summary, file_size);
+        let boxes = read_boxes(file, 0, file_size)?;
+        let boxes_end = boxes.last().map_or(0, BoxHeader::end);
+        if boxes_end < file_size {
+            root.diagnostics.push(Finding::new(Severity::Warning, "file", format!("{} byte(s) at the end of the file are not a valid box (truncated file?)", file_size - boxes_end)));
+        }
+        // Boxes take the place of the skipped ranges, which name the same boxes
+        root.children.retain(|node| node.name != "skipped");
+        root.children.splice(0..0, box_report_nodes(file, &boxes, false, 0)?);
+        root.children.sort_by_key(|node| node.offset.unwrap_or(u64::MAX));
+        Ok(root)
+    }
+}
 
-    // Parse top-level boxes
-    while pos < file.metadata()?.len() {
-        file.seek(SeekFrom::Start(pos))?;
+pub fn dissect_isobmff_with_options(file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let boxes = read_boxes(file, 0, file_size)?;
 
-        let mut box_header = [0u8; 8];
-        if file.read_exact(&mut box_header).is_err() {
-            break;
+    if options.show_header {
+        outln!("\nISO BMFF Container:");
+        outln!("  Format: ISO Base Media File Format");
+        if let Some(ftyp) = boxes.iter().find(|box_header| box_header.box_type == "ftyp") {
+            print_file_type(&read_payload(file, ftyp)?);
         }
 
-        let box_size = u32::from_be_bytes([box_header[0], box_header[1], box_header[2], box_header[3]]) as u64;
-        let box_type = std::str::from_utf8(&box_header[4..8]).unwrap_or("????");
+        // Movie and track headers with their creation/modification times
+        crate::isobmff_tracks::print_movie(file)?;
+        print_format_findings(&isobmff_format_findings(file)?);
+        print_podcast(PodcastInfo::from_isobmff(file)?.as_ref());
+        print_disc_info(DiscInfo::from_isobmff(file)?.as_ref());
+        print_cover_art(&isobmff_cover_art(file)?);
+        print_release_codes(&isobmff_release_codes(file)?);
+        print_telemetry(isobmff_telemetry(file)?.as_ref());
+    }
 
-        if box_size < 8 {
-            break;
+    if !options.show_frames {
+        return Ok(());
+    }
+
+    outln!("\nISO BMFF Boxes:");
+    print_box_tree(file, &boxes, false, 0, options)?;
+    let boxes_end = boxes.last().map_or(0, BoxHeader::end);
+    if boxes_end < file_size {
+        outln!("  WARNING: {} byte(s) at the end of the file are not a valid box (truncated file?)", file_size - boxes_end);
+    }
+
+    for packet in isobmff_packets(file)? {
+        match packet {
+            | Ok(packet) => {
+                outln!("\n{}:", packet.source);
+                out!("{}", packet);
+            }
+            | Err(error) => outln!("\nWARNING: Invalid XMP packet: {}", error),
         }
+    }
 
-        println!("  Box: {} (size: {} bytes)", box_type, box_size);
+    Ok(())
+}
 
-        pos += box_size;
+/// Brands of the file type box (ftyp)
+fn print_file_type(payload: &[u8]) {
+    if payload.len() < 8 {
+        outln!("  WARNING: ftyp box too short ({} bytes)", payload.len());
+        return;
+    }
+    outln!("  Major Brand: \"{}\" (version {})", String::from_utf8_lossy(&payload[0..4]), u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]));
+    let compatible: Vec<String> = payload[8..].chunks_exact(4).map(|brand| format!("\"{}\"", String::from_utf8_lossy(brand))).collect();
+    if !compatible.is_empty() {
+        outln!("  Compatible Brands: {}", compatible.join(", "));
+    }
+}
+
+/// List boxes indented by their nesting, descending into container boxes down to `--box-depth`
+///
+/// The metadata items of an `ilst` box ('©nam', 'covr', ...) have arbitrary types but always
+/// contain `data` boxes, so they are descended into as well. With `--frame-id`, only the
+/// selected box types are listed, at the indentation of their nesting.
+fn print_box_tree(file: &mut dyn MediaSource, boxes: &[BoxHeader], in_ilst: bool, depth: usize, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let max_depth = options.box_depth.unwrap_or(MAX_BOX_DEPTH);
+    let indent = "  ".repeat(depth + 1);
+    for box_header in boxes {
+        let size_field = if box_header.to_end && depth == 0 {
+            ", size field 0: to the end of the file"
+        } else if box_header.to_end {
+            ", size field 0: to the end of the parent"
+        } else if box_header.header_size == 16 {
+            ", 64-bit size"
+        } else {
+            ""
+        };
+        if options.lists_id(&box_header.box_type) {
+            outln!("{}Box: {} (size: {} bytes{})", indent, box_header.box_type, box_header.size, size_field);
+        }
+        let start = if in_ilst {
+            Some(box_header.payload_start())
+        } else {
+            children_start(box_header)
+        };
+        let Some(start) = start else {
+            continue;
+        };
+        let children = read_boxes(file, start, box_header.end())?;
+        if depth < max_depth {
+            print_box_tree(file, &children, box_header.box_type == "ilst", depth + 1, options)?;
+        } else if !children.is_empty() {
+            outln!("{}  ... {} child box(es) below the depth limit of {}", indent, children.len(), max_depth);
+        }
+        // Bytes after the last child that does not fit into its parent
+        let children_end = children.last().map_or(start, BoxHeader::end);
+        if children_end < box_header.end() {
+            outln!("{}  WARNING: {} byte(s) at the end of '{}' are not a valid box", indent, box_header.end() - children_end, box_header.box_type);
+        }
+    }
+    Ok(())
+}
 
-        // Prevent infinite loop
-        if pos >= file.metadata()?.len() || box_size == 0 {
-            break;
+/// Report nodes of boxes and, for container boxes (and the items of `ilst`), their children
+fn box_report_nodes(file: &mut dyn MediaSource, boxes: &[BoxHeader], in_ilst: bool, depth: usize) -> Result<Vec<ReportNode>, Box<dyn std::error::Error>> {
+    let mut nodes = Vec::new();
+    for box_header in boxes {
+        let mut node = ReportNode::new(format!("box '{}'", box_header.box_type)).at(box_header.offset, box_header.size);
+        let start = if in_ilst {
+            Some(box_header.payload_start())
+        } else {
+            children_start(box_header)
+        };
+        if let Some(start) = start.filter(|_| depth < MAX_BOX_DEPTH) {
+            let children = read_boxes(file, start, box_header.end())?;
+            let children_end = children.last().map_or(start, BoxHeader::end);
+            if children_end < box_header.end() {
+                node.diagnostics.push(Finding::new(Severity::Warning, &box_header.box_type, format!("{} byte(s) at the end of '{}' are not a valid box", box_header.end() - children_end, box_header.box_type)));
+            }
+            node.children = box_report_nodes(file, &children, box_header.box_type == "ilst", depth + 1)?;
         }
+        nodes.push(node);
     }
+    Ok(nodes)
+}
 
-    Ok(())
+/// Size nodes of boxes and, for container boxes, their children
+fn box_nodes(file: &mut dyn MediaSource, boxes: Vec<BoxHeader>, depth: usize) -> Result<Vec<SizeNode>, Box<dyn std::error::Error>> {
+    let mut nodes = Vec::new();
+    for box_header in boxes {
+        let label = format!("'{}' box", box_header.box_type);
+        match children_start(&box_header) {
+            | Some(start) if depth < MAX_BOX_DEPTH => {
+                let boxes = read_boxes(file, start, box_header.end())?;
+                let children = box_nodes(file, boxes, depth + 1)?;
+                nodes.push(SizeNode::with_children(label, box_header.size, children, "box header and fields"));
+            }
+            | _ => nodes.push(SizeNode::new(label, box_header.size)),
+        }
+    }
+    Ok(nodes)
 }
@@ -0,0 +1,132 @@
+/// Fast start for MP4 files (`faststart`)
+///
+/// Players that stream a file need the movie box (moov) with the sample tables before the media
+/// data (mdat). Encoders that write the moov box last produce files that only play once they
+/// have been downloaded completely. `faststart` writes a copy with the moov box moved in front
+/// of the first mdat box and every chunk offset (stco, co64) shifted to where its chunk now
+/// lies. Files that are already laid out that way are copied unchanged.
+///
+/// Fragmented files are refused: their fragments address data by absolute offsets in places
+/// (tfhd base data offsets, tfra entries) that the move does not rewrite. So are stco boxes
+/// whose offsets would no longer fit into 32 bits, which would need a co64 box instead.
+use crate::checked_size::buffer_length;
+use crate::isobmff_box::{BoxHeader, find_path, read_boxes, read_children};
+use crate::path_display::display_path;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Write a copy of `input` with the moov box in front of the media data to `output`
+pub fn faststart(input: &Path, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if let (Ok(input), Ok(output)) = (fs::canonicalize(input), fs::canonicalize(output))
+        && input == output
+    {
+        return Err("the output would overwrite the input file; write the copy to another path".into());
+    }
+    let mut file = File::open(input)?;
+    let file_size = file.metadata()?.len();
+    let boxes = read_boxes(&mut file, 0, file_size)?;
+    let covered = boxes.last().map_or(0, BoxHeader::end);
+    if covered != file_size {
+        return Err(format!("the boxes end at offset {} but the file has {} bytes; data outside the boxes cannot be moved", covered, file_size).into());
+    }
+    let Some(moov_index) = boxes.iter().position(|box_header| box_header.box_type == "moov") else {
+        return Err("no movie ('moov' box)".into());
+    };
+    let Some(mdat_index) = boxes.iter().position(|box_header| box_header.box_type == "mdat") else {
+        return Err("no media data ('mdat' box)".into());
+    };
+    if boxes.iter().any(|box_header| box_header.box_type == "moof") {
+        return Err("fragmented files are not supported; their fragments address data by absolute offsets".into());
+    }
+    let moov = &boxes[moov_index];
+    if moov_index < mdat_index {
+        fs::copy(input, output)?;
+        outln!("Already optimized: 'moov' at offset {} comes before 'mdat' at offset {}", moov.offset, boxes[mdat_index].offset);
+        outln!("Copy written unchanged: {}", display_path(output));
+        return Ok(());
+    }
+
+    // Everything before the first mdat box, the moov box, then the rest in the old order
+    let mut order: Vec<&BoxHeader> = boxes[..mdat_index].iter().collect();
+    order.push(moov);
+    order.extend(boxes.iter().enumerate().skip(mdat_index).filter(|&(index, _)| index != moov_index).map(|(_, box_header)| box_header));
+    let mut new_offsets = Vec::with_capacity(order.len());
+    let mut pos = 0;
+    for box_header in &order {
+        new_offsets.push(pos);
+        pos += box_header.size;
+    }
+    let relocate = |offset: u64| {
+        order
+            .iter()
+            .zip(&new_offsets)
+            .find(|(box_header, _)| box_header.offset <= offset && offset < box_header.end())
+            .map(|(box_header, &new_offset)| offset - box_header.offset + new_offset)
+    };
+
+    let mut moov_bytes = vec![0u8; buffer_length("box 'moov'", moov.offset, moov.size)?];
+    file.seek(SeekFrom::Start(moov.offset))?;
+    file.read_exact(&mut moov_bytes)?;
+    // A moov box that extended to the end of the file needs its size written out
+    if moov.to_end {
+        let size = u32::try_from(moov.size).map_err(|_| "the 'moov' box extends to the end of the file and is too large for a 32-bit size")?;
+        moov_bytes[0..4].copy_from_slice(&size.to_be_bytes());
+    }
+
+    let mut tables = 0;
+    let mut entries = 0;
+    let traks: Vec<BoxHeader> = read_children(&mut file, moov)?.into_iter().filter(|child| child.box_type == "trak").collect();
+    for (track, trak) in traks.iter().enumerate() {
+        let Some(stbl) = find_path(&mut file, trak, &["mdia", "minf", "stbl"])? else {
+            continue;
+        };
+        for table in read_children(&mut file, &stbl)?.into_iter().filter(|child| child.box_type == "stco" || child.box_type == "co64") {
+            let wide = table.box_type == "co64";
+            let width = if wide {
+                8
+            } else {
+                4
+            };
+            let payload = &mut moov_bytes[(table.payload_start() - moov.offset) as usize..(table.end() - moov.offset) as usize];
+            let count = payload.get(4..8).map_or(0, |bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])) as usize;
+            if count.checked_mul(width).and_then(|size| size.checked_add(8)).is_none_or(|size| size > payload.len()) {
+                return Err(format!("the '{}' box of track {} is truncated", table.box_type, track + 1).into());
+            }
+            for index in 0..count {
+                let at = 8 + index * width;
+                let offset = if wide {
+                    u64::from_be_bytes(payload[at..at + 8].try_into()?)
+                } else {
+                    u32::from_be_bytes(payload[at..at + 4].try_into()?) as u64
+                };
+                let moved = relocate(offset).ok_or_else(|| format!("chunk offset {} of track {} lies outside the boxes of the file", offset, track + 1))?;
+                if wide {
+                    payload[at..at + 8].copy_from_slice(&moved.to_be_bytes());
+                } else {
+                    let moved = u32::try_from(moved).map_err(|_| format!("chunk offset {} of track {} would need more than 32 bits after the move (the track needs a co64 box)", moved, track + 1))?;
+                    payload[at..at + 4].copy_from_slice(&moved.to_be_bytes());
+                }
+            }
+            tables += 1;
+            entries += count;
+        }
+    }
+
+    let mut out = BufWriter::new(File::create(output)?);
+    for box_header in &order {
+        if box_header.offset == moov.offset {
+            out.write_all(&moov_bytes)?;
+        } else {
+            file.seek(SeekFrom::Start(box_header.offset))?;
+            io::copy(&mut (&mut file).take(box_header.size), &mut out)?;
+        }
+    }
+    out.flush()?;
+
+    let new_moov_offset = new_offsets[mdat_index];
+    outln!("Moved 'moov' ({} bytes) from offset {} to offset {}, in front of 'mdat'", moov.size, moov.offset, new_moov_offset);
+    outln!("Shifted {} chunk offsets in {} stco/co64 boxes", entries, tables);
+    outln!("Fast-start copy written: {}", display_path(output));
+    Ok(())
+}
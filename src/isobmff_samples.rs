@@ -0,0 +1,260 @@
+/// Sample locations of ISO BMFF tracks
+///
+/// Regular files describe the samples of a track in its sample table: sizes (stsz or stz2),
+/// chunk offsets (stco or co64) and how many samples each chunk holds (stsc). Fragmented files
+/// describe them per movie fragment instead, in the track runs (trun) of each `moof/traf`, with
+/// defaults from the fragment header (tfhd) and the track extends box (trex).
+use crate::checked_size::end_offset;
+use crate::isobmff_box::{BoxHeader, PayloadReader, find_path, full_box_flags, read_children, read_payload};
+use crate::media_source::MediaSource;
+
+/// Position and size of one sample in the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleLocation {
+    pub offset: u64,
+    pub size: u32,
+}
+
+/// Samples of the track in `trak`, in decoding order
+///
+/// Takes the sample table of the track and, in fragmented files, the track runs of every
+/// movie fragment (`moov` and `moof` boxes are looked up from `file_boxes`).
+pub fn track_samples(file: &mut dyn MediaSource, file_boxes: &[BoxHeader], moov: &BoxHeader, trak: &BoxHeader, track_id: u32) -> Result<Vec<SampleLocation>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut samples = match find_path(file, trak, &["mdia", "minf", "stbl"])? {
+        | Some(stbl) => sample_table(file, &stbl, file_size)?,
+        | None => Vec::new(),
+    };
+
+    let default_size = match find_path(file, moov, &["mvex"])? {
+        | Some(mvex) => trex_default_size(file, &mvex, track_id)?,
+        | None => None,
+    };
+    for moof in file_boxes.iter().filter(|box_header| box_header.box_type == "moof") {
+        for traf in read_children(file, moof)?.iter().filter(|child| child.box_type == "traf") {
+            samples.extend(fragment_samples(file, moof, traf, track_id, default_size, file_size)?);
+        }
+    }
+
+    if let Some(sample) = samples.iter().find(|sample| end_offset("sample", sample.offset, sample.size as u64, file_size).is_err()) {
+        return Err(format!("sample at offset {} ({} bytes) extends beyond the end of the file", sample.offset, sample.size).into());
+    }
+    Ok(samples)
+}
+
+/// Samples described by a sample table (stbl)
+pub fn sample_table(file: &mut dyn MediaSource, stbl: &BoxHeader, file_size: u64) -> Result<Vec<SampleLocation>, Box<dyn std::error::Error>> {
+    let mut sizes = None;
+    let mut chunk_offsets = None;
+    let mut sample_to_chunk = None;
+    for child in read_children(file, stbl)? {
+        match child.box_type.as_str() {
+            | "stsz" => sizes = Some(parse_stsz(&read_payload(file, &child)?, file_size)?),
+            | "stz2" => sizes = Some(parse_stz2(&read_payload(file, &child)?)?),
+            | "stco" | "co64" => chunk_offsets = Some(parse_chunk_offsets(&read_payload(file, &child)?, child.box_type == "co64")?),
+            | "stsc" => sample_to_chunk = Some(parse_stsc(&read_payload(file, &child)?)?),
+            | _ => {}
+        }
+    }
+    let (Some(sizes), Some(chunk_offsets), Some(sample_to_chunk)) = (sizes, chunk_offsets, sample_to_chunk) else {
+        return Ok(Vec::new());
+    };
+
+    let mut samples = Vec::with_capacity(sizes.len());
+    let mut sizes = sizes.into_iter();
+    for (index, &chunk_offset) in chunk_offsets.iter().enumerate() {
+        let chunk = index as u32 + 1;
+        // The last entry starting at or before the chunk applies to it
+        let samples_per_chunk = sample_to_chunk.iter().rev().find(|(first_chunk, _)| *first_chunk <= chunk).map_or(0, |(_, count)| *count);
+        let mut offset = chunk_offset;
+        for size in sizes.by_ref().take(samples_per_chunk as usize) {
+            samples.push(SampleLocation { offset, size });
+            offset += size as u64;
+        }
+    }
+    Ok(samples)
+}
+
+/// Time-to-sample entries (stts): number of samples and their duration in media time units
+pub fn time_to_sample(file: &mut dyn MediaSource, stbl: &BoxHeader) -> Result<Vec<(u32, u32)>, Box<dyn std::error::Error>> {
+    let Some(stts) = read_children(file, stbl)?.into_iter().find(|child| child.box_type == "stts") else {
+        return Ok(Vec::new());
+    };
+    let payload = read_payload(file, &stts)?;
+    let mut reader = PayloadReader::new(&payload, 4);
+    let entry_count = reader.u32()?;
+    Ok((0..entry_count).map(|_| Ok((reader.u32()?, reader.u32()?))).collect::<Result<_, String>>()?)
+}
+
+/// Decoding time of `sample` (counting from 1) in media time units
+pub fn decode_time(time_to_sample: &[(u32, u32)], sample: u32) -> u64 {
+    let mut remaining = sample.saturating_sub(1) as u64;
+    let mut time = 0;
+    for &(count, duration) in time_to_sample {
+        let step = remaining.min(count as u64);
+        time += step * duration as u64;
+        remaining -= step;
+        if remaining == 0 {
+            break;
+        }
+    }
+    time
+}
+
+/// Sync sample numbers (stss, counting from 1), or `None` if the track has no stss box and
+/// every sample is a sync sample
+pub fn sync_samples(file: &mut dyn MediaSource, stbl: &BoxHeader) -> Result<Option<Vec<u32>>, Box<dyn std::error::Error>> {
+    let Some(stss) = read_children(file, stbl)?.into_iter().find(|child| child.box_type == "stss") else {
+        return Ok(None);
+    };
+    let payload = read_payload(file, &stss)?;
+    let mut reader = PayloadReader::new(&payload, 4);
+    let entry_count = reader.u32()?;
+    Ok(Some((0..entry_count).map(|_| reader.u32()).collect::<Result<_, String>>()?))
+}
+
+/// Sample sizes (stsz): one size for all samples or a table
+fn parse_stsz(payload: &[u8], file_size: u64) -> Result<Vec<u32>, String> {
+    let mut reader = PayloadReader::new(payload, 4);
+    let sample_size = reader.u32()?;
+    let sample_count = reader.u32()?;
+    if sample_size != 0 {
+        // A corrupt count must not allocate more samples than the file can hold
+        if sample_count as u64 * sample_size as u64 > file_size {
+            return Err(format!("{} samples of {} bytes do not fit into the file", sample_count, sample_size));
+        }
+        return Ok(vec![sample_size; sample_count as usize]);
+    }
+    (0..sample_count).map(|_| reader.u32()).collect()
+}
+
+/// Compact sample sizes (stz2) with 4, 8 or 16 bits per sample
+fn parse_stz2(payload: &[u8]) -> Result<Vec<u32>, String> {
+    let mut reader = PayloadReader::new(payload, 7);
+    let field_size = reader.u8()?;
+    let sample_count = reader.u32()?;
+    match field_size {
+        | 4 => {
+            let bytes = reader.bytes((sample_count as usize).div_ceil(2))?;
+            Ok((0..sample_count as usize).map(|index| (bytes[index / 2] >> (4 - 4 * (index % 2)) & 0x0F) as u32).collect())
+        }
+        | 8 => (0..sample_count).map(|_| reader.u8().map(u32::from)).collect(),
+        | 16 => (0..sample_count).map(|_| reader.u16().map(u32::from)).collect(),
+        | _ => Err(format!("invalid stz2 field size {}", field_size)),
+    }
+}
+
+/// Chunk offsets (stco with 32-bit, co64 with 64-bit offsets)
+fn parse_chunk_offsets(payload: &[u8], wide: bool) -> Result<Vec<u64>, String> {
+    let mut reader = PayloadReader::new(payload, 4);
+    let entry_count = reader.u32()?;
+    (0..entry_count)
+        .map(|_| {
+            if wide {
+                reader.u64()
+            } else {
+                reader.u32().map(u64::from)
+            }
+        })
+        .collect()
+}
+
+/// Sample-to-chunk entries (stsc): first chunk (counting from 1) and samples per chunk
+fn parse_stsc(payload: &[u8]) -> Result<Vec<(u32, u32)>, String> {
+    let mut reader = PayloadReader::new(payload, 4);
+    let entry_count = reader.u32()?;
+    (0..entry_count)
+        .map(|_| {
+            let first_chunk = reader.u32()?;
+            let samples_per_chunk = reader.u32()?;
+            reader.u32()?;
+            Ok((first_chunk, samples_per_chunk))
+        })
+        .collect()
+}
+
+/// Default sample size of the track from its track extends box (trex)
+fn trex_default_size(file: &mut dyn MediaSource, mvex: &BoxHeader, track_id: u32) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+    for trex in read_children(file, mvex)?.iter().filter(|child| child.box_type == "trex") {
+        let payload = read_payload(file, trex)?;
+        let mut reader = PayloadReader::new(&payload, 4);
+        if reader.u32()? == track_id {
+            reader.bytes(8)?;
+            return Ok(Some(reader.u32()?));
+        }
+    }
+    Ok(None)
+}
+
+/// Samples of one track fragment (traf), if it belongs to the track
+fn fragment_samples(file: &mut dyn MediaSource, moof: &BoxHeader, traf: &BoxHeader, track_id: u32, trex_size: Option<u32>, file_size: u64) -> Result<Vec<SampleLocation>, Box<dyn std::error::Error>> {
+    let children = read_children(file, traf)?;
+    let Some(tfhd) = children.iter().find(|child| child.box_type == "tfhd") else {
+        return Ok(Vec::new());
+    };
+    let payload = read_payload(file, tfhd)?;
+    let flags = full_box_flags(&payload);
+    let mut reader = PayloadReader::new(&payload, 4);
+    if reader.u32()? != track_id {
+        return Ok(Vec::new());
+    }
+    // Without an explicit base, offsets count from the moof box (default-base-is-moof or the first traf)
+    let base = if flags & 0x01 != 0 {
+        reader.u64()?
+    } else {
+        moof.offset
+    };
+    if flags & 0x02 != 0 {
+        reader.u32()?;
+    }
+    if flags & 0x08 != 0 {
+        reader.u32()?;
+    }
+    let default_size = if flags & 0x10 != 0 {
+        Some(reader.u32()?)
+    } else {
+        trex_size
+    };
+
+    let mut samples = Vec::new();
+    let mut next_offset = base;
+    for trun in children.iter().filter(|child| child.box_type == "trun") {
+        let payload = read_payload(file, trun)?;
+        let flags = full_box_flags(&payload);
+        let mut reader = PayloadReader::new(&payload, 4);
+        let sample_count = reader.u32()?;
+        // A corrupt count must not allocate more samples than the file has bytes
+        if sample_count as u64 > file_size {
+            return Err(format!("track run with {} samples in a file of {} bytes", sample_count, file_size).into());
+        }
+        let mut offset = if flags & 0x01 != 0 {
+            base.checked_add_signed(reader.u32()? as i32 as i64).ok_or("invalid trun data offset")?
+        } else {
+            next_offset
+        };
+        if flags & 0x04 != 0 {
+            reader.u32()?;
+        }
+        for _ in 0..sample_count {
+            if flags & 0x100 != 0 {
+                reader.u32()?;
+            }
+            let size = if flags & 0x200 != 0 {
+                reader.u32()?
+            } else {
+                default_size.ok_or("track run without sample sizes and no default size")?
+            };
+            if flags & 0x400 != 0 {
+                reader.u32()?;
+            }
+            if flags & 0x800 != 0 {
+                reader.u32()?;
+            }
+            samples.push(SampleLocation { offset, size });
+            offset += size as u64;
+        }
+        next_offset = offset;
+    }
+    Ok(samples)
+}
+
@@ -0,0 +1,228 @@
+/// Seek points of ISO BMFF files (`debug --seek-points`)
+///
+/// Players seek to sync samples (key frames): the sample numbers in the stss box of a track, or
+/// every sample if the track has none. Fragmented files list the random access points of each
+/// track in the tfra boxes of the movie fragment random access box (mfra) instead. Long stretches
+/// without a seek point make seeking slow or inaccurate, and tfra entries that do not point at a
+/// movie fragment make it fail.
+use crate::isobmff_box::{BoxHeader, PayloadReader, find_path, read_boxes, read_children, read_payload};
+use crate::isobmff_samples::{decode_time, sample_table, sync_samples, time_to_sample};
+use crate::isobmff_tracks::{media_timescale, track_id};
+use crate::media_source::MediaSource;
+use crate::time_format::format_duration;
+use std::collections::HashMap;
+
+/// Longest stretch without a seek point before it is flagged, in milliseconds
+const MAX_SEEK_GAP_MS: u64 = 10_000;
+
+/// Track facts needed to place its samples in time
+pub struct TrackInfo {
+    pub track_id: u32,
+    pub handler: String,
+    pub timescale: u32,
+    /// Sample table, missing for tracks that only have samples in movie fragments
+    pub stbl: Option<BoxHeader>,
+}
+
+/// One entry of a track fragment random access box (tfra)
+struct RandomAccessPoint {
+    time: u64,
+    moof_offset: u64,
+    traf_number: u32,
+    trun_number: u32,
+    sample_number: u32,
+}
+
+/// Print the sync samples of every track and the fragment random access points, with warnings
+pub fn print_seek_points(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    outln!("\nSeek Points:");
+    let file_size = file.byte_len()?;
+    let file_boxes = read_boxes(file, 0, file_size)?;
+    let Some(moov) = file_boxes.iter().find(|box_header| box_header.box_type == "moov") else {
+        outln!("    No movie ('moov' box)");
+        return Ok(());
+    };
+
+    let mut tracks = Vec::new();
+    for trak in read_children(file, moov)?.into_iter().filter(|child| child.box_type == "trak") {
+        tracks.extend(read_track_info(file, &trak)?);
+    }
+    for track in &tracks {
+        print_track_sync_samples(file, track)?;
+    }
+
+    let fragmented = file_boxes.iter().any(|box_header| box_header.box_type == "moof");
+    match file_boxes.iter().find(|box_header| box_header.box_type == "mfra") {
+        | Some(mfra) => {
+            let moof_offsets: Vec<u64> = file_boxes.iter().filter(|box_header| box_header.box_type == "moof").map(|moof| moof.offset).collect();
+            let timescales: HashMap<u32, u32> = tracks.iter().map(|track| (track.track_id, track.timescale)).collect();
+            for tfra in read_children(file, mfra)?.into_iter().filter(|child| child.box_type == "tfra") {
+                print_random_access_points(&read_payload(file, &tfra)?, &timescales, &moof_offsets)?;
+            }
+        }
+        | None if fragmented => outln!("    WARNING: fragmented file without a movie fragment random access box (mfra); players must scan the fragments to seek"),
+        | None => {}
+    }
+    Ok(())
+}
+
+/// Track ID, handler, media timescale and sample table of a `trak` box
+pub fn read_track_info(file: &mut dyn MediaSource, trak: &BoxHeader) -> Result<Option<TrackInfo>, Box<dyn std::error::Error>> {
+    let Some(tkhd) = read_children(file, trak)?.into_iter().find(|child| child.box_type == "tkhd") else {
+        return Ok(None);
+    };
+    let Some(track_id) = track_id(&read_payload(file, &tkhd)?) else {
+        return Ok(None);
+    };
+    let mut timescale = 0;
+    let mut handler = String::from("unknown");
+    if let Some(mdia) = find_path(file, trak, &["mdia"])? {
+        for child in read_children(file, &mdia)? {
+            let payload = read_payload(file, &child)?;
+            match child.box_type.as_str() {
+                | "mdhd" => timescale = media_timescale(&payload).unwrap_or(0),
+                | "hdlr" if payload.len() >= 12 => handler = String::from_utf8_lossy(&payload[8..12]).to_string(),
+                | _ => {}
+            }
+        }
+    }
+    let stbl = find_path(file, trak, &["mdia", "minf", "stbl"])?;
+    Ok(Some(TrackInfo { track_id, handler, timescale, stbl }))
+}
+
+/// Milliseconds of a time in media units
+pub fn to_ms(time: u64, timescale: u32) -> u64 {
+    if timescale == 0 {
+        0
+    } else {
+        (time as u128 * 1000 / timescale as u128) as u64
+    }
+}
+
+fn print_track_sync_samples(file: &mut dyn MediaSource, track: &TrackInfo) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(stbl) = &track.stbl else {
+        return Ok(());
+    };
+    let file_size = file.byte_len()?;
+    let samples = sample_table(file, stbl, file_size)?;
+    if samples.is_empty() {
+        // Fragmented files keep their samples (and seek points) in the movie fragments
+        return Ok(());
+    }
+    let stts = time_to_sample(file, stbl)?;
+    let duration_ms = to_ms(decode_time(&stts, samples.len() as u32 + 1), track.timescale);
+
+    let Some(sync) = sync_samples(file, stbl)? else {
+        outln!("  Track {} ({}): every sample is a sync sample (no stss box), {} samples", track.track_id, track.handler, samples.len());
+        return Ok(());
+    };
+    outln!("  Track {} ({}, timescale {}): {} sync samples of {}", track.track_id, track.handler, track.timescale, sync.len(), samples.len());
+    if sync.is_empty() {
+        outln!("    WARNING: the stss box lists no sync samples; the track cannot be seeked");
+        return Ok(());
+    }
+
+    let mut warnings = Vec::new();
+    if sync[0] != 1 {
+        warnings.push(format!("the first sample is not a sync sample (first sync sample is {})", sync[0]));
+    }
+    let mut previous: Option<(u32, u64)> = None;
+    for &sample in &sync {
+        if sample == 0 || sample as usize > samples.len() {
+            warnings.push(format!("sync sample {} does not exist", sample));
+            continue;
+        }
+        let time_ms = to_ms(decode_time(&stts, sample), track.timescale);
+        outln!("    sample {} at {} (offset {})", sample, format_duration(time_ms), samples[sample as usize - 1].offset);
+        match previous {
+            | Some((previous_sample, _)) if sample <= previous_sample => {
+                warnings.push(format!("sync sample {} follows sync sample {} (not in increasing order)", sample, previous_sample));
+                continue;
+            }
+            | Some((_, previous_ms)) if time_ms - previous_ms > MAX_SEEK_GAP_MS => warnings.push(seek_gap(previous_ms, time_ms)),
+            | _ => {}
+        }
+        previous = Some((sample, time_ms));
+    }
+    if let Some((_, last_ms)) = previous
+        && duration_ms.saturating_sub(last_ms) > MAX_SEEK_GAP_MS
+    {
+        warnings.push(format!("{} (from the last sync sample to the end)", seek_gap(last_ms, duration_ms)));
+    }
+    for warning in warnings {
+        outln!("    WARNING: {}", warning);
+    }
+    Ok(())
+}
+
+/// Warning about a stretch without seek points
+fn seek_gap(from_ms: u64, to_ms: u64) -> String {
+    format!("no seek point for {} ({} to {})", format_duration(to_ms - from_ms), format_duration(from_ms), format_duration(to_ms))
+}
+
+/// Entries of a track fragment random access box (tfra)
+fn parse_tfra(payload: &[u8]) -> Result<(u32, Vec<RandomAccessPoint>), String> {
+    let wide = payload.first() == Some(&1);
+    let mut reader = PayloadReader::new(payload, 4);
+    let track_id = reader.u32()?;
+    let sizes = reader.u32()?;
+    let entry_count = reader.u32()?;
+    // Each entry takes at least 11 bytes; a corrupt count must not allocate more
+    if entry_count as usize > payload.len() / 11 {
+        return Err(format!("tfra box with {} entries in {} bytes", entry_count, payload.len()));
+    }
+    let read_number = |reader: &mut PayloadReader, size_field: u32| -> Result<u32, String> {
+        let bytes = reader.bytes((size_field & 0x03) as usize + 1)?;
+        Ok(bytes.iter().fold(0, |value, &byte| (value << 8) | byte as u32))
+    };
+
+    let mut points = Vec::new();
+    for _ in 0..entry_count {
+        let (time, moof_offset) = if wide {
+            (reader.u64()?, reader.u64()?)
+        } else {
+            (reader.u32()? as u64, reader.u32()? as u64)
+        };
+        let traf_number = read_number(&mut reader, sizes >> 4)?;
+        let trun_number = read_number(&mut reader, sizes >> 2)?;
+        let sample_number = read_number(&mut reader, sizes)?;
+        points.push(RandomAccessPoint { time, moof_offset, traf_number, trun_number, sample_number });
+    }
+    Ok((track_id, points))
+}
+
+fn print_random_access_points(payload: &[u8], timescales: &HashMap<u32, u32>, moof_offsets: &[u64]) -> Result<(), Box<dyn std::error::Error>> {
+    let (track_id, points) = parse_tfra(payload)?;
+    let timescale = timescales.get(&track_id).copied().unwrap_or(0);
+    outln!("  Fragment random access (tfra), track {}: {} entries", track_id, points.len());
+
+    let mut warnings = Vec::new();
+    if timescale == 0 {
+        warnings.push(format!("track {} has no media timescale; times are shown as 0", track_id));
+    }
+    let mut previous_ms: Option<u64> = None;
+    for point in &points {
+        let time_ms = to_ms(point.time, timescale);
+        outln!(
+            "    {} at moof offset {} (traf {}, trun {}, sample {})",
+            format_duration(time_ms),
+            point.moof_offset,
+            point.traf_number,
+            point.trun_number,
+            point.sample_number
+        );
+        if !moof_offsets.contains(&point.moof_offset) {
+            warnings.push(format!("entry at {} points to offset {}, which is not the start of a 'moof' box", format_duration(time_ms), point.moof_offset));
+        }
+        match previous_ms {
+            | Some(previous) if time_ms <= previous => warnings.push(format!("entry at {} does not follow the previous entry in time", format_duration(time_ms))),
+            | Some(previous) if time_ms - previous > MAX_SEEK_GAP_MS => warnings.push(seek_gap(previous, time_ms)),
+            | _ => {}
+        }
+        previous_ms = Some(time_ms);
+    }
+    for warning in warnings {
+        outln!("    WARNING: {}", warning);
+    }
+    Ok(())
+}
@@ -0,0 +1,275 @@
+/// Telemetry of action and security cameras in ISO BMFF files (GoPro GPMF, ONVIF, CAMM)
+///
+/// GoPro cameras record their sensors (accelerometer, gyroscope, GPS, ...) as GPMF in a timed
+/// metadata track with a 'gpmd' sample entry, and describe the camera in boxes of `moov/udta`
+/// (FIRM, LENS, CAME and a GPMF box with the settings). ONVIF exports carry XML metadata in
+/// 'metx' tracks, Google's Camera Motion Metadata uses 'camm' tracks. Transcoders and editors
+/// often drop these tracks, so listing them shows whether the telemetry survived.
+///
+/// GPMF is a tree of key-length-value entries: a four-character key, a type, the size of one
+/// sample and a repeat count, followed by the data padded to 32 bits; type 0 nests entries. A
+/// device (DEVC) holds streams (STRM), and the last entry of a stream is its sensor data.
+use crate::isobmff_box::{find_path, read_boxes, read_children, read_payload};
+use crate::isobmff_demux::{extract_track, sample_entry};
+use crate::isobmff_samples::track_samples;
+use crate::isobmff_tracks::track_id;
+use crate::media_source::MediaSource;
+use serde::Serialize;
+use std::fs::File;
+use std::io::SeekFrom;
+use std::path::Path;
+
+/// Nesting of GPMF entries that is followed
+const MAX_GPMF_DEPTH: usize = 8;
+
+/// Camera boxes of GoPro files in `moov/udta` that hold text
+const GOPRO_TEXT_BOXES: [(&str, &str); 2] = [("FIRM", "firmware"), ("LENS", "lens")];
+
+/// Telemetry tracks and camera details of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct Telemetry {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tracks: Vec<TelemetryTrack>,
+    /// Camera details from the vendor boxes of `moov/udta` (e.g. firmware, lens, model)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub camera: Vec<CameraField>,
+}
+
+/// Timed metadata track with telemetry
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryTrack {
+    pub track_id: u32,
+    /// Type of the sample entry ("gpmd", "metx", "camm")
+    pub sample_entry: String,
+    /// What the track carries (e.g. "GoPro GPMF", "ONVIF metadata")
+    pub format: String,
+    pub samples: usize,
+    pub bytes: u64,
+    /// Sensor streams of GPMF tracks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub streams: Vec<TelemetryStream>,
+}
+
+/// One sensor stream of a GPMF track, over all samples of the track
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryStream {
+    /// Device name (DVNM), e.g. "Camera"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Key of the sensor data, e.g. "ACCL", "GYRO", "GPS5"
+    pub key: String,
+    /// Stream name (STNM), e.g. "Accelerometer"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Units (SIUN or UNIT), one per axis
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+    /// Number of sensor readings
+    pub readings: u64,
+}
+
+/// Camera detail from a vendor box
+#[derive(Debug, Clone, Serialize)]
+pub struct CameraField {
+    pub name: String,
+    pub value: String,
+}
+
+/// Telemetry tracks and camera boxes of the file, if it has any
+pub fn isobmff_telemetry(file: &mut dyn MediaSource) -> Result<Option<Telemetry>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let file_boxes = read_boxes(file, 0, file_size)?;
+    let Some(moov) = file_boxes.iter().find(|box_header| box_header.box_type == "moov") else {
+        return Ok(None);
+    };
+
+    let mut tracks = Vec::new();
+    for trak in read_children(file, moov)?.into_iter().filter(|child| child.box_type == "trak") {
+        let Some(tkhd) = read_children(file, &trak)?.into_iter().find(|child| child.box_type == "tkhd") else {
+            continue;
+        };
+        let Some(id) = track_id(&read_payload(file, &tkhd)?) else {
+            continue;
+        };
+        let Some(stsd) = find_path(file, &trak, &["mdia", "minf", "stbl", "stsd"])? else {
+            continue;
+        };
+        let stsd = read_payload(file, &stsd)?;
+        let Some((entry_type, entry)) = sample_entry(&stsd) else {
+            continue;
+        };
+        let format = match entry_type.as_str() {
+            | "gpmd" => "GoPro GPMF".to_string(),
+            | "camm" => "Camera Motion Metadata".to_string(),
+            | "metx" => xml_format(entry),
+            | _ => continue,
+        };
+
+        let samples = track_samples(file, &file_boxes, moov, &trak, id)?;
+        let mut streams = Vec::new();
+        if entry_type == "gpmd" {
+            for sample in &samples {
+                let mut data = vec![0u8; sample.size as usize];
+                file.seek(SeekFrom::Start(sample.offset))?;
+                if file.read_exact(&mut data).is_err() {
+                    break;
+                }
+                collect_streams(&data, None, 0, &mut streams);
+            }
+        }
+        let bytes = samples.iter().map(|sample| sample.size as u64).sum();
+        tracks.push(TelemetryTrack { track_id: id, sample_entry: entry_type, format, samples: samples.len(), bytes, streams });
+    }
+
+    let mut camera = Vec::new();
+    if let Some(udta) = read_children(file, moov)?.into_iter().find(|child| child.box_type == "udta") {
+        for child in read_children(file, &udta)? {
+            let payload = read_payload(file, &child)?;
+            if let Some((_, name)) = GOPRO_TEXT_BOXES.iter().find(|(box_type, _)| *box_type == child.box_type) {
+                camera.push(CameraField { name: name.to_string(), value: text(&payload) });
+            } else if child.box_type == "CAME" {
+                camera.push(CameraField { name: "ID".to_string(), value: payload.iter().map(|byte| format!("{:02X}", byte)).collect() });
+            } else if child.box_type == "GPMF" {
+                camera.extend(gpmf_settings(&payload));
+            }
+        }
+    }
+
+    if tracks.is_empty() && camera.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Telemetry { tracks, camera }))
+}
+
+/// Print the telemetry tracks with their streams and the camera details, if there are any
+pub fn print_telemetry(telemetry: Option<&Telemetry>) {
+    let Some(telemetry) = telemetry else {
+        return;
+    };
+    outln!("\nTelemetry:");
+    for field in &telemetry.camera {
+        outln!("  Camera {}: {}", field.name, field.value);
+    }
+    for track in &telemetry.tracks {
+        outln!("  Track {}: {} ('{}'), {} samples, {} bytes", track.track_id, track.format, track.sample_entry, track.samples, track.bytes);
+        for stream in &track.streams {
+            let mut line = format!("    {}", stream.key);
+            if let Some(name) = &stream.name {
+                line.push_str(&format!(" ({})", name));
+            }
+            line.push_str(&format!(": {} readings", stream.readings));
+            if let Some(units) = &stream.units {
+                line.push_str(&format!(" [{}]", units));
+            }
+            if let Some(device) = &stream.device {
+                line.push_str(&format!(" from {}", device));
+            }
+            outln!("{}", line);
+        }
+    }
+}
+
+/// Write the raw GPMF samples of the GoPro telemetry track (the given one, or the first) to `output`
+pub fn extract_gpmf(path: &Path, track: Option<u32>, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let telemetry = isobmff_telemetry(&mut File::open(path)?)?;
+    let gpmf_tracks: Vec<u32> = telemetry.iter().flat_map(|telemetry| &telemetry.tracks).filter(|track| track.sample_entry == "gpmd").map(|track| track.track_id).collect();
+    let id = match track {
+        | Some(id) if gpmf_tracks.contains(&id) => id,
+        | Some(id) => return Err(format!("track {} is not a GPMF track ('gpmd' sample entry)", id).into()),
+        | None => *gpmf_tracks.first().ok_or("no GPMF telemetry track ('gpmd' sample entry)")?,
+    };
+    extract_track(path, id, output)
+}
+
+/// Describe a 'metx' track by the namespace of its XML
+fn xml_format(entry: &[u8]) -> String {
+    // Content encoding, namespace and schema location follow the data reference index as C strings
+    let mut fields = entry.get(16..).unwrap_or_default().split(|&byte| byte == 0);
+    let namespace = fields.nth(1).map(|bytes| String::from_utf8_lossy(bytes).into_owned()).unwrap_or_default();
+    if namespace.contains("onvif.org") {
+        "ONVIF metadata".to_string()
+    } else if namespace.is_empty() {
+        "XML metadata".to_string()
+    } else {
+        format!("XML metadata ({})", namespace)
+    }
+}
+
+/// One GPMF entry: key, type, sample size, repeat count and data (without the padding)
+struct GpmfEntry<'a> {
+    key: String,
+    value_type: u8,
+    size: usize,
+    repeat: u64,
+    data: &'a [u8],
+}
+
+fn gpmf_entries(data: &[u8]) -> Vec<GpmfEntry<'_>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = data[pos + 5] as usize;
+        let repeat = u16::from_be_bytes([data[pos + 6], data[pos + 7]]) as usize;
+        let length = size * repeat;
+        let Some(body) = data.get(pos + 8..pos + 8 + length) else {
+            break;
+        };
+        entries.push(GpmfEntry { key: String::from_utf8_lossy(&data[pos..pos + 4]).into_owned(), value_type: data[pos + 4], size, repeat: repeat as u64, data: body });
+        pos += 8 + length.div_ceil(4) * 4;
+    }
+    entries
+}
+
+/// Add the streams of the devices in `data` to `streams`, merging readings of the same stream
+fn collect_streams(data: &[u8], device: Option<&str>, depth: usize, streams: &mut Vec<TelemetryStream>) {
+    if depth > MAX_GPMF_DEPTH {
+        return;
+    }
+    let entries = gpmf_entries(data);
+    let device_name = entries.iter().find(|entry| entry.key == "DVNM").map(|entry| text(entry.data));
+    let device = device_name.as_deref().or(device);
+    for entry in entries.iter().filter(|entry| entry.value_type == 0) {
+        match entry.key.as_str() {
+            | "STRM" => add_stream(entry.data, device, streams),
+            | _ => collect_streams(entry.data, device, depth + 1, streams),
+        }
+    }
+}
+
+fn add_stream(data: &[u8], device: Option<&str>, streams: &mut Vec<TelemetryStream>) {
+    let entries = gpmf_entries(data);
+    // The sensor data is the last entry, after the stream's name, units and scale
+    let Some(sensor) = entries.iter().rev().find(|entry| entry.value_type != 0) else {
+        return;
+    };
+    let name = entries.iter().find(|entry| entry.key == "STNM").map(|entry| text(entry.data));
+    let units = entries.iter().find(|entry| (entry.key == "SIUN" || entry.key == "UNIT") && entry.value_type == b'c').map(|entry| {
+        let units: Vec<String> = entry.data.chunks(entry.size.max(1)).map(text).collect();
+        units.join(", ")
+    });
+    match streams.iter_mut().find(|stream| stream.key == sensor.key && stream.device.as_deref() == device) {
+        | Some(stream) => stream.readings += sensor.repeat,
+        | None => streams.push(TelemetryStream { device: device.map(str::to_string), key: sensor.key.clone(), name, units, readings: sensor.repeat }),
+    }
+}
+
+/// Camera model and other text settings of the GPMF box in `moov/udta`
+fn gpmf_settings(data: &[u8]) -> Vec<CameraField> {
+    let mut fields = Vec::new();
+    for device in gpmf_entries(data).into_iter().filter(|entry| entry.key == "DEVC") {
+        for entry in gpmf_entries(device.data) {
+            let name = match entry.key.as_str() {
+                | "MINF" => "model",
+                | "DVNM" => "device",
+                | _ => continue,
+            };
+            fields.push(CameraField { name: name.to_string(), value: text(entry.data) });
+        }
+    }
+    fields
+}
+
+/// GPMF text: Latin-1 characters (units use ° and ²) up to the first NUL
+fn text(data: &[u8]) -> String {
+    data.iter().take_while(|&&byte| byte != 0).map(|&byte| byte as char).collect::<String>().trim().to_string()
+}
@@ -0,0 +1,249 @@
+/// Language and name of MP4 tracks (`tag mp4 set-track-lang`, `tag mp4 set-track-name`)
+///
+/// The language of a track is the ISO 639-2/T code packed into its media header (mdhd), which can
+/// be overwritten in place. An extended language box (elng, a BCP 47 tag such as "de-CH") takes
+/// precedence over it: `--elng` writes one, and without it an existing elng box is turned into a
+/// free box so it no longer contradicts the new code. The name of a track is the `udta/name` box
+/// of the trak box.
+///
+/// A changed box is rewritten in place when it keeps its size, when it shrinks by at least 8 bytes
+/// (the rest becomes a free box), or when a free box right behind it has the room it grows by.
+/// Otherwise the movie box is rebuilt and the file rewritten, with the chunk offsets (stco, co64)
+/// of the media data behind the movie box shifted by the growth.
+use crate::isobmff_atom::{Atom, read_moov, replace_moov, track_mut};
+use crate::isobmff_box::{BoxHeader, read_boxes, read_children, read_payload};
+use crate::isobmff_seek_points::read_track_info;
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use crate::safe_save::{self, DRY_RUN_NOTICE};
+use std::fs::File;
+use std::path::Path;
+
+/// Change to one track
+#[derive(Debug, Clone)]
+pub enum TrackEdit {
+    /// ISO 639-2/T code for mdhd and, if given, a BCP 47 tag for elng
+    Language { code: String, extended: Option<String> },
+    /// Track name (udta/name)
+    Name(String),
+}
+
+/// Byte ranges to overwrite, with what goes there
+type Patches = Vec<(u64, Vec<u8>)>;
+
+/// Apply `edit` to the track with ID `track` of the file at `path`
+pub fn edit_track(path: &Path, track: u32, edit: &TrackEdit, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let packed = match edit {
+        | TrackEdit::Language { code, extended } => {
+            if let Some(tag) = extended
+                && (tag.is_empty() || tag.len() > 35 || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+            {
+                return Err(format!("'{}' is not a BCP 47 language tag (e.g. de-CH)", tag).into());
+            }
+            pack_language(code)?
+        }
+        | TrackEdit::Name(_) => 0,
+    };
+
+    let mut file = File::open(long_path(path))?;
+    let file_size = file.metadata()?.len();
+    let boxes = read_boxes(&mut file, 0, file_size)?;
+    let Some(moov) = boxes.iter().find(|box_header| box_header.box_type == "moov") else {
+        return Err("no movie ('moov' box)".into());
+    };
+    let mut track_ids = Vec::new();
+    let mut trak = None;
+    for child in read_children(&mut file, moov)?.into_iter().filter(|child| child.box_type == "trak") {
+        if let Some(info) = read_track_info(&mut file, &child)? {
+            track_ids.push(info.track_id.to_string());
+            if info.track_id == track {
+                trak = Some(child);
+            }
+        }
+    }
+    let Some(trak) = trak else {
+        return Err(format!("no track with ID {} (tracks: {})", track, track_ids.join(", ")).into());
+    };
+
+    // Patches for an edit in place, or None if a box has to grow
+    let mut changes = Vec::new();
+    let patches: Option<Patches> = match edit {
+        | TrackEdit::Language { code, extended } => {
+            let mdia = find_child(&mut file, &trak, "mdia")?;
+            let mdia_children = read_children(&mut file, &mdia)?;
+            let mdhd = mdia_children.iter().find(|child| child.box_type == "mdhd").ok_or("the track has no media header ('mdhd' box)")?;
+            let mdhd_payload = read_payload(&mut file, mdhd)?;
+            let at = language_position(&mdhd_payload);
+            let old = mdhd_payload.get(at..at + 2).map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+            changes.push(format!("language {} -> {}", unpack_language(old), code.to_ascii_lowercase()));
+            let mut patches = vec![(mdhd.payload_start() + at as u64, packed.to_be_bytes().to_vec())];
+            let elng = mdia_children.iter().position(|child| child.box_type == "elng");
+            match (extended, elng) {
+                | (Some(tag), Some(index)) => {
+                    changes.push(format!("extended language {} -> {}", extended_language(&read_payload(&mut file, &mdia_children[index])?), tag));
+                    place(&mdia_children, index, &encoded(&elng_box(tag))).map(|elng_patches| {
+                        patches.extend(elng_patches);
+                        patches
+                    })
+                }
+                | (Some(tag), None) => {
+                    changes.push(format!("extended language {} added", tag));
+                    None
+                }
+                | (None, Some(index)) => {
+                    changes.push(format!("extended language {} removed (it would override the new code)", extended_language(&read_payload(&mut file, &mdia_children[index])?)));
+                    patches.push((mdia_children[index].offset + 4, b"free".to_vec()));
+                    Some(patches)
+                }
+                | (None, None) => Some(patches),
+            }
+        }
+        | TrackEdit::Name(name) => {
+            let udta = read_children(&mut file, &trak)?.into_iter().find(|child| child.box_type == "udta");
+            let udta_children = match &udta {
+                | Some(udta) => read_children(&mut file, udta)?,
+                | None => Vec::new(),
+            };
+            match udta_children.iter().position(|child| child.box_type == "name") {
+                | Some(index) => {
+                    let old = read_payload(&mut file, &udta_children[index])?;
+                    changes.push(format!("name \"{}\" -> \"{}\"", String::from_utf8_lossy(&old), name));
+                    place(&udta_children, index, &encoded(&Atom::leaf(b"name", name.as_bytes().to_vec())))
+                }
+                | None => {
+                    changes.push(format!("name \"{}\" added", name));
+                    None
+                }
+            }
+        }
+    };
+
+    let how = if patches.is_some() {
+        "in place"
+    } else {
+        "'moov' box rebuilt"
+    };
+    outln!("{}: track {}: {} ({})", display_path(path), track, changes.join(", "), how);
+    if dry_run {
+        outln!("{}", DRY_RUN_NOTICE);
+        return Ok(());
+    }
+    match patches {
+        | Some(patches) => {
+            drop(file);
+            safe_save::overwrite_ranges(path, &patches, options)?;
+        }
+        | None => rewrite(path, &mut file, &boxes, moov, track, edit, options)?,
+    }
+    Ok(())
+}
+
+/// Rebuild the movie box with the edit and rewrite the file
+fn rewrite(path: &Path, file: &mut File, boxes: &[BoxHeader], moov_header: &BoxHeader, track: u32, edit: &TrackEdit, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut moov = read_moov(file, moov_header)?;
+    let trak = track_mut(&mut moov, track).ok_or_else(|| format!("no track with ID {}", track))?;
+    match edit {
+        | TrackEdit::Language { code, extended } => {
+            let packed = pack_language(code)?;
+            let mdia = trak.child_mut(b"mdia").and_then(|mdia| mdia.children.as_mut()).ok_or("the track's 'mdia' box cannot be edited")?;
+            if let Some(mdhd) = mdia.iter_mut().find(|child| &child.box_type == b"mdhd") {
+                let at = language_position(&mdhd.payload);
+                if let Some(bytes) = mdhd.payload.get_mut(at..at + 2) {
+                    bytes.copy_from_slice(&packed.to_be_bytes());
+                }
+            }
+            mdia.retain(|child| &child.box_type != b"elng");
+            if let Some(tag) = extended {
+                let at = mdia.iter().position(|child| &child.box_type == b"mdhd").map_or(0, |index| index + 1);
+                mdia.insert(at, elng_box(tag));
+            }
+        }
+        | TrackEdit::Name(name) => {
+            let name_box = Atom::leaf(b"name", name.as_bytes().to_vec());
+            if trak.child(b"udta").is_none() {
+                trak.children.get_or_insert_default().push(Atom::container(b"udta", Vec::new()));
+            }
+            let udta = trak.child_mut(b"udta").and_then(|udta| udta.children.as_mut()).ok_or("the track's 'udta' box cannot be edited")?;
+            match udta.iter_mut().find(|child| &child.box_type == b"name") {
+                | Some(existing) => *existing = name_box,
+                | None => udta.push(name_box),
+            }
+        }
+    }
+
+    let shifted = replace_moov(path, file, boxes, moov_header, moov, options)?;
+    if shifted > 0 {
+        outln!("Shifted {} chunk offsets behind the grown 'moov' box", shifted);
+    }
+    Ok(())
+}
+
+fn find_child(file: &mut File, parent: &BoxHeader, box_type: &str) -> Result<BoxHeader, Box<dyn std::error::Error>> {
+    read_children(file, parent)?.into_iter().find(|child| child.box_type == box_type).ok_or_else(|| format!("the track has no '{}' box", box_type).into())
+}
+
+/// Position of the language in an mdhd payload (after the 32- or 64-bit times and duration)
+fn language_position(mdhd: &[u8]) -> usize {
+    if mdhd.first() == Some(&1) {
+        32
+    } else {
+        20
+    }
+}
+
+/// ISO 639-2/T code as three 5-bit letters
+pub fn pack_language(code: &str) -> Result<u16, String> {
+    let letters = code.to_ascii_lowercase();
+    if letters.len() != 3 || !letters.bytes().all(|letter| letter.is_ascii_lowercase()) {
+        return Err(format!("'{}' is not an ISO 639-2/T language code (three letters, e.g. deu)", code));
+    }
+    Ok(letters.bytes().fold(0u16, |packed, letter| (packed << 5) | (letter - 0x60) as u16))
+}
+
+fn unpack_language(packed: u16) -> String {
+    // Values below 0x400 are Macintosh language codes of old QuickTime files
+    if packed < 0x400 {
+        return format!("Macintosh code {}", packed);
+    }
+    [10, 5, 0].iter().map(|shift| (((packed >> shift) & 0x1F) as u8 + 0x60) as char).collect()
+}
+
+fn elng_box(tag: &str) -> Atom {
+    let mut content = tag.as_bytes().to_vec();
+    content.push(0);
+    Atom::full(b"elng", 0, &content)
+}
+
+fn extended_language(payload: &[u8]) -> String {
+    let tag = payload.get(4..).unwrap_or_default();
+    String::from_utf8_lossy(tag.split(|&byte| byte == 0).next().unwrap_or_default()).into_owned()
+}
+
+fn encoded(atom: &Atom) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    atom.encode(&mut bytes);
+    bytes
+}
+
+/// Patches that put `new` where `siblings[index]` is, if it fits there or into a free box behind it
+fn place(siblings: &[BoxHeader], index: usize, new: &[u8]) -> Option<Patches> {
+    let old = &siblings[index];
+    let mut spaces = vec![old.size];
+    if let Some(next) = siblings.get(index + 1)
+        && (next.box_type == "free" || next.box_type == "skip")
+    {
+        spaces.push(old.size + next.size);
+    }
+    spaces.into_iter().find_map(|space| {
+        let new_size = new.len() as u64;
+        let mut bytes = new.to_vec();
+        if space == new_size {
+            return Some(vec![(old.offset, bytes)]);
+        }
+        // The rest becomes a free box; its old contents are left as its payload
+        let rest = u32::try_from(space.checked_sub(new_size)?).ok().filter(|&rest| rest >= 8)?;
+        bytes.extend_from_slice(&rest.to_be_bytes());
+        bytes.extend_from_slice(b"free");
+        Some(vec![(old.offset, bytes)])
+    })
+}
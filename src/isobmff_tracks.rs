@@ -0,0 +1,287 @@
+/// Movie and track headers of ISO BMFF files (mvhd, tkhd, mdhd, hdlr, stsd)
+///
+/// Creation and modification times count seconds since 1904-01-01 UTC. They are converted
+/// to dates and checked for the values broken writers leave behind: zero, dates in the
+/// future and Unix timestamps stored without the 1904 offset.
+use crate::isobmff_audio_codec::{AudioCodec, audio_codec};
+use crate::isobmff_box::{BoxHeader, find_path, read_boxes, read_children, read_payload};
+use crate::media_source::MediaSource;
+use crate::time_format::{format_date, format_duration, iso8601_date};
+use serde::Serialize;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds between 1904-01-01 and 1970-01-01
+const MP4_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Unix time of 2000-01-01, the earliest plausible date for a misplaced Unix timestamp
+const YEAR_2000: i64 = 946_684_800;
+
+/// Creation or modification time of a movie, track or media header
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaTime {
+    /// Seconds since 1904-01-01 as stored in the box
+    pub raw: u64,
+    /// UTC date in ISO 8601 format (not set for zero)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// Why the value is suspicious
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+}
+
+impl MediaTime {
+    fn new(raw: u64, now: i64) -> Self {
+        if raw == 0 {
+            return MediaTime { raw, date: None, problem: Some("zero (never set by the writing software)".to_string()) };
+        }
+        let unix = raw as i64 - MP4_EPOCH_OFFSET;
+        let problem = if unix > now + 86400 {
+            Some("in the future".to_string())
+        } else if unix < 0 && (YEAR_2000..=now).contains(&(raw as i64)) {
+            Some(format!("before 1970, probably a Unix timestamp without the 1904 offset ({})", iso8601_date(raw as i64)))
+        } else {
+            None
+        };
+        MediaTime { raw, date: Some(iso8601_date(unix)), problem }
+    }
+
+    fn unix(&self) -> i64 {
+        self.raw as i64 - MP4_EPOCH_OFFSET
+    }
+}
+
+impl fmt::Display for MediaTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.raw == 0 {
+            write!(f, "0")?;
+        } else {
+            write!(f, "{}", format_date(self.unix()))?;
+        }
+        if let Some(problem) = &self.problem {
+            write!(f, " WARNING: {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Movie header (mvhd)
+#[derive(Debug, Clone, Serialize)]
+pub struct MovieSummary {
+    pub creation_time: MediaTime,
+    pub modification_time: MediaTime,
+    /// Time units per second of the movie
+    pub timescale: u32,
+    /// Duration in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Track with its header (tkhd), media header (mdhd) and handler (hdlr)
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackSummary {
+    pub track_id: u32,
+    /// Handler type ("soun", "vide", "text", ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handler: Option<String>,
+    /// ISO 639-2 language of the media
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Media duration in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Codec, sample rate, channels and bit depth of audio tracks (stsd)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioCodec>,
+    /// Track creation time (tkhd)
+    pub creation_time: MediaTime,
+    /// Track modification time (tkhd)
+    pub modification_time: MediaTime,
+    /// Media creation time (mdhd)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_creation_time: Option<MediaTime>,
+    /// Media modification time (mdhd)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_modification_time: Option<MediaTime>,
+}
+
+/// Movie header (if present) and tracks of a `moov` box
+pub type MovieHeaders = (Option<MovieSummary>, Vec<TrackSummary>);
+
+/// Movie header and tracks of the `moov` box, if the file has one
+pub fn read_movie(file: &mut dyn MediaSource) -> Result<Option<MovieHeaders>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let Some(moov) = read_boxes(file, 0, file_size)?.into_iter().find(|box_header| box_header.box_type == "moov") else {
+        return Ok(None);
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64);
+
+    let mut movie = None;
+    let mut tracks = Vec::new();
+    for child in read_children(file, &moov)? {
+        match child.box_type.as_str() {
+            | "mvhd" => movie = parse_mvhd(&read_payload(file, &child)?, now),
+            | "trak" => tracks.extend(read_track(file, &child, now)?),
+            | _ => {}
+        }
+    }
+    Ok(Some((movie, tracks)))
+}
+
+/// Print the movie header and the tracks with their decoded times
+pub fn print_movie(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((movie, tracks)) = read_movie(file)? else {
+        return Ok(());
+    };
+
+    if let Some(movie) = movie {
+        outln!("\nMovie Header (mvhd):");
+        outln!("  Duration: {} (timescale {})", format_duration(movie.duration_ms), movie.timescale);
+        outln!("  Created: {}", movie.creation_time);
+        outln!("  Modified: {}", movie.modification_time);
+    }
+    for track in tracks {
+        outln!("\nTrack {}:", track.track_id);
+        if let Some(handler) = &track.handler {
+            outln!("  Handler: {}", handler);
+        }
+        if let Some(language) = &track.language {
+            outln!("  Language: {}", language);
+        }
+        if let Some(duration_ms) = track.duration_ms {
+            outln!("  Duration: {}", format_duration(duration_ms));
+        }
+        if let Some(audio) = &track.audio {
+            outln!("  Codec: {}", audio);
+        }
+        outln!("  Created: {}", track.creation_time);
+        outln!("  Modified: {}", track.modification_time);
+        if let Some(time) = &track.media_creation_time {
+            outln!("  Media created: {}", time);
+        }
+        if let Some(time) = &track.media_modification_time {
+            outln!("  Media modified: {}", time);
+        }
+    }
+    Ok(())
+}
+
+fn read_track(file: &mut dyn MediaSource, trak: &BoxHeader, now: i64) -> Result<Option<TrackSummary>, Box<dyn std::error::Error>> {
+    let children = read_children(file, trak)?;
+    let Some(tkhd) = children.iter().find(|child| child.box_type == "tkhd") else {
+        return Ok(None);
+    };
+    let Some(mut track) = parse_tkhd(&read_payload(file, tkhd)?, now) else {
+        return Ok(None);
+    };
+
+    if let Some(mdia) = children.iter().find(|child| child.box_type == "mdia") {
+        for child in read_children(file, mdia)? {
+            let payload = read_payload(file, &child)?;
+            match child.box_type.as_str() {
+                | "mdhd" => apply_mdhd(&mut track, &payload, now),
+                | "hdlr" if payload.len() >= 12 => track.handler = Some(String::from_utf8_lossy(&payload[8..12]).to_string()),
+                | _ => {}
+            }
+        }
+        // Sample entries other than the audio ones leave `audio` unset
+        if let Some(stsd) = find_path(file, mdia, &["minf", "stbl", "stsd"])? {
+            track.audio = audio_codec(&read_payload(file, &stsd)?);
+        }
+    }
+    Ok(Some(track))
+}
+
+/// Track ID from the payload of a track header (tkhd)
+pub fn track_id(tkhd_payload: &[u8]) -> Option<u32> {
+    read_times(tkhd_payload).map(|(_, _, track_id, _)| track_id)
+}
+
+/// Time units per second from the payload of a media header (mdhd)
+pub fn media_timescale(mdhd_payload: &[u8]) -> Option<u32> {
+    read_times(mdhd_payload).map(|(_, _, timescale, _)| timescale)
+}
+
+/// Creation time, modification time and the following 32-bit field of a version 0 or 1 full box
+fn read_times(payload: &[u8]) -> Option<(u64, u64, u32, usize)> {
+    let read_u32 = |pos: usize| payload.get(pos..pos + 4).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap_or_default()));
+    let read_u64 = |pos: usize| payload.get(pos..pos + 8).map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap_or_default()));
+    match *payload.first()? {
+        | 1 => Some((read_u64(4)?, read_u64(12)?, read_u32(20)?, 24)),
+        | _ => Some((read_u32(4)? as u64, read_u32(8)? as u64, read_u32(12)?, 16)),
+    }
+}
+
+/// Duration field after `pos`, 64-bit in version 1 boxes
+fn read_duration(payload: &[u8], pos: usize) -> Option<u64> {
+    if payload[0] == 1 {
+        payload.get(pos..pos + 8).map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap_or_default()))
+    } else {
+        payload.get(pos..pos + 4).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap_or_default()) as u64)
+    }
+}
+
+fn parse_mvhd(payload: &[u8], now: i64) -> Option<MovieSummary> {
+    let (creation, modification, timescale, pos) = read_times(payload)?;
+    let duration = read_duration(payload, pos)?;
+    Some(MovieSummary {
+        creation_time: MediaTime::new(creation, now),
+        modification_time: modification_time(creation, modification, now),
+        timescale,
+        duration_ms: to_ms(duration, timescale),
+    })
+}
+
+fn parse_tkhd(payload: &[u8], now: i64) -> Option<TrackSummary> {
+    let (creation, modification, track_id, _) = read_times(payload)?;
+    Some(TrackSummary {
+        track_id,
+        handler: None,
+        language: None,
+        duration_ms: None,
+        audio: None,
+        creation_time: MediaTime::new(creation, now),
+        modification_time: modification_time(creation, modification, now),
+        media_creation_time: None,
+        media_modification_time: None,
+    })
+}
+
+fn apply_mdhd(track: &mut TrackSummary, payload: &[u8], now: i64) {
+    let Some((creation, modification, timescale, pos)) = read_times(payload) else {
+        return;
+    };
+    let duration = read_duration(payload, pos);
+    let duration_size = if payload[0] == 1 {
+        8
+    } else {
+        4
+    };
+    track.media_creation_time = Some(MediaTime::new(creation, now));
+    track.media_modification_time = Some(modification_time(creation, modification, now));
+    track.duration_ms = duration.map(|duration| to_ms(duration, timescale));
+    // Packed ISO 639-2 code: three 5-bit letters offset by 0x60
+    if let Some(bytes) = payload.get(pos + duration_size..pos + duration_size + 2) {
+        let packed = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let language: String = [10, 5, 0].iter().map(|shift| (((packed >> shift) & 0x1F) as u8 + 0x60) as char).collect();
+        if language.chars().all(|c| c.is_ascii_lowercase()) {
+            track.language = Some(language);
+        }
+    }
+}
+
+/// Modification time, flagged if it lies before the creation time
+fn modification_time(creation: u64, modification: u64, now: i64) -> MediaTime {
+    let mut time = MediaTime::new(modification, now);
+    if time.problem.is_none() && creation != 0 && modification < creation {
+        time.problem = Some("earlier than the creation time".to_string());
+    }
+    time
+}
+
+fn to_ms(duration: u64, timescale: u32) -> u64 {
+    if timescale == 0 {
+        0
+    } else {
+        duration * 1000 / timescale as u64
+    }
+}
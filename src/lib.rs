@@ -0,0 +1,152 @@
+//! Media file dissection as a library
+//!
+//! The `supertool` command line is a thin layer over this crate. Other programs can use the
+//! parsers directly (`Id3v2Tag::read`, `ChapterFrame`, `MediaDissector::summarize`) or
+//! `analyze_file` for the serializable summary of any supported file. Functions that print
+//! write through `output`; wrap them in `output::captured` to get the text instead of stdout.
+//! The global options of the command line are a `CommonOptions` per call, those of the `tag`
+//! commands a `WriteOptions` argument.
+
+#[macro_use]
+pub mod output;
+pub mod path_display;
+
+pub mod analysis;
+pub mod artwork_export;
+pub mod audio_format_check;
+pub mod audio_hash;
+pub mod batch_errors;
+pub mod baseline;
+pub mod chapter_edit;
+pub mod chapter_export;
+pub mod chapter_timeline;
+pub mod checked_size;
+pub mod coverage;
+pub mod crawler;
+pub mod csv_reader;
+pub mod daemon;
+pub mod disc_info;
+pub mod dissection_report;
+pub mod dissector_builder;
+pub mod dj_info;
+pub mod dsf_dissector;
+pub mod duplicate_finder;
+pub mod encoding_census;
+pub mod error;
+pub mod file_collector;
+pub mod file_links;
+pub mod finding;
+pub mod forensic;
+pub mod format_list;
+pub mod health_score;
+pub mod icy_metadata;
+pub mod id3v1_tag;
+pub mod id3v2_2_dissector;
+pub mod id3v2_3_dissector;
+pub mod id3v2_4_dissector;
+pub mod id3v2_attached_picture_frame;
+pub mod id3v2_chapter_frame;
+pub mod id3v2_chapter_tree;
+pub mod id3v2_convert;
+pub mod id3v2_comment_frame;
+pub mod id3v2_embedded;
+pub mod id3v2_frame;
+pub mod id3v2_frame_explainer;
+pub mod id3v2_frame_format;
+pub mod id3v2_frame_order;
+pub mod id3v2_general_object_frame;
+pub mod id3v2_genre;
+pub mod id3v2_known_user_text;
+pub mod id3v2_languages;
+pub mod id3v2_plausibility;
+pub mod id3v2_private_frame;
+pub mod id3v2_repair;
+pub mod id3v2_sort_order;
+pub mod id3v2_synced_lyrics_frame;
+pub mod id3v2_table_of_contents_frame;
+pub mod id3v2_tag;
+pub mod id3v2_text_encoding;
+pub mod id3v2_text_frame;
+pub mod id3v2_tools;
+pub mod id3v2_unique_file_id_frame;
+pub mod id3v2_url_frame;
+pub mod id3v2_url_validation;
+pub mod id3v2_user_text_frame;
+pub mod id3v2_user_url_frame;
+pub mod id3v2_validation;
+pub mod id3v2_write_check;
+pub mod id3v2_write_safety;
+pub mod id3v2_writer;
+pub mod image_info;
+pub mod isobmff_atom;
+pub mod isobmff_audio_codec;
+pub mod isobmff_box;
+pub mod isobmff_box_export;
+pub mod isobmff_chapter_writer;
+pub mod isobmff_chunk_map;
+pub mod isobmff_demux;
+pub mod isobmff_dissector;
+pub mod isobmff_faststart;
+pub mod isobmff_samples;
+pub mod isobmff_seek_points;
+pub mod isobmff_telemetry;
+pub mod isobmff_track_edit;
+pub mod isobmff_tracks;
+pub mod lrc;
+pub mod lyrics;
+pub mod media_dissector;
+pub mod media_source;
+pub mod media_summary;
+pub mod metadata_fingerprint;
+pub mod mpeg_audio;
+pub mod mpeg_bitrate;
+pub mod mpeg_crc;
+pub mod ogg_dissector;
+pub mod ogg_page;
+pub mod options;
+pub mod podcast_info;
+pub mod progress_events;
+pub mod release_codes;
+pub mod riff_chunk;
+pub mod safe_save;
+pub mod sandbox;
+pub mod serato;
+pub mod sidecar_writer;
+pub mod size_tree;
+pub mod stream_tags;
+pub mod summary_formats;
+pub mod tag_boundary;
+pub mod tag_compare;
+pub mod tag_csv_import;
+pub mod tag_disc;
+pub mod tag_field;
+pub mod tag_genres;
+pub mod tag_reencode;
+pub mod tag_reorder;
+pub mod tag_sort;
+pub mod tag_strip;
+pub mod tag_write;
+pub mod text_normalization;
+pub mod text_truncate;
+pub mod time_format;
+pub mod trailer_tags;
+pub mod unknown_dissector;
+pub mod url_checker;
+pub mod vorbis_comment;
+pub mod wave_dissector;
+pub mod xmp;
+pub mod zip_archive;
+pub mod zip_dissector;
+
+pub use analysis::{analyze_file, dissect_batch, dissect_bytes, dissect_file, dissect_files, report_file};
+pub use dissection_report::{DissectionReport, ReportNode};
+pub use dissector_builder::DissectorBuilder;
+pub use error::SupertoolError;
+pub use finding::{Finding, Severity};
+pub use id3v2_chapter_frame::ChapterFrame;
+pub use id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+pub use id3v2_table_of_contents_frame::TableOfContentsFrame;
+pub use id3v2_tag::Id3v2Tag;
+pub use media_dissector::MediaDissector;
+pub use media_summary::MediaSummary;
+pub use options::{CommonOptions, DebugOptions, WriteOptions};
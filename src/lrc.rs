@@ -0,0 +1,76 @@
+/// Parse LRC lyrics into lines with their time in milliseconds, sorted by time
+///
+/// Lines look like `[01:23.45]text`; a line may carry several time stamps and enhanced LRC
+/// adds `<mm:ss.xx>` word stamps inside the text, which are dropped. Tags such as `[ar:...]`
+/// are ignored except for `[offset:±ms]`, which shifts all time stamps (positive values
+/// make the lyrics appear earlier).
+pub fn parse_lrc(text: &str) -> Vec<(String, u32)> {
+    let mut offset: i64 = 0;
+    let mut lines = Vec::new();
+
+    for line in text.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some(tag_end) = rest.strip_prefix('[').and_then(|tag| tag.find(']')) {
+            let tag = &rest[1..tag_end + 1];
+            if let Some(ms) = parse_time(tag) {
+                times.push(ms);
+            } else if let Some(value) = tag.strip_prefix("offset:") {
+                offset = value.trim().parse().unwrap_or(0);
+            }
+            rest = &rest[tag_end + 2..];
+        }
+
+        let text = strip_word_stamps(rest.trim());
+        for time in times {
+            lines.push((text.clone(), (time - offset).clamp(0, u32::MAX as i64) as u32));
+        }
+    }
+
+    lines.sort_by_key(|(_, time)| *time);
+    lines
+}
+
+/// Whether the text contains at least one LRC time stamp line
+pub fn has_timestamps(text: &str) -> bool {
+    text.lines().any(|line| line.trim().strip_prefix('[').and_then(|tag| tag.split_once(']')).is_some_and(|(tag, _)| parse_time(tag).is_some()))
+}
+
+/// Format lines with their time in milliseconds as LRC
+pub fn format_lrc(lines: &[(String, u32)]) -> String {
+    lines.iter().map(|(text, ms)| format!("[{:02}:{:02}.{:02}]{}\n", ms / 60000, ms / 1000 % 60, ms % 1000 / 10, text)).collect()
+}
+
+/// Parse `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` into milliseconds
+fn parse_time(tag: &str) -> Option<i64> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: i64 = minutes.parse().ok()?;
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let seconds: i64 = seconds.parse().ok()?;
+    if !fraction.chars().all(|c| c.is_ascii_digit()) || fraction.len() > 3 {
+        return None;
+    }
+    // ".5" means 500 ms, ".45" 450 ms
+    let fraction_ms = format!("{:0<3}", fraction).parse::<i64>().ok()?;
+    Some(minutes * 60000 + seconds * 1000 + fraction_ms)
+}
+
+/// Remove enhanced LRC word stamps (`<01:23.45>`)
+fn strip_word_stamps(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            | Some(end) if parse_time(&rest[start + 1..start + end]).is_some() => {
+                result.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            }
+            | _ => {
+                result.push_str(&rest[..start + 1]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result.trim().to_string()
+}
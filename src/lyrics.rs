@@ -0,0 +1,181 @@
+/// Lyrics conversion between SYLT, USLT and LRC files (`extract lyrics`, `tag set-lyrics`)
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_languages::{language_inventory, language_matches};
+use crate::id3v2_synced_lyrics_frame::{CONTENT_TYPE_LYRICS, SyncedLyricsFrame, TIMESTAMP_MILLISECONDS, TIMESTAMP_MPEG_FRAMES};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_writer::{ensure_writable_version, write_tag};
+use crate::lrc::{format_lrc, has_timestamps, parse_lrc};
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::first_frame_header;
+use crate::options::{LyricsFormat, LyricsFrameName, WriteOptions};
+use crate::path_display::{display_path, long_path};
+use crate::safe_save::DRY_RUN_NOTICE;
+use std::fs::File;
+use std::path::Path;
+
+/// Where `tag set-lyrics` takes the lyrics from
+pub enum LyricsSource<'a> {
+    /// LRC or plain text file
+    File(&'a Path),
+    /// Another lyrics frame of the same tag
+    Frame(LyricsFrameName),
+}
+
+/// Write the lyrics of a file as LRC or plain text to `output` (stdout if not set)
+///
+/// LRC output is built from the SYLT frame, or taken from a USLT frame that already contains
+/// LRC time stamps. Plain text comes from the USLT frame, or from the SYLT frame without
+/// its time stamps. With `language` only frames in that language are considered.
+pub fn extract_lyrics(path: &Path, format: LyricsFormat, language: Option<&str>, output: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    let synced = match find_synced_lyrics(&tag, language) {
+        | Some(sylt) => Some(synced_lines(sylt, &mut file)?),
+        | None => None,
+    };
+    let unsynced = find_unsynced_lyrics(&tag, language);
+
+    if let Some(language) = language
+        && synced.is_none()
+        && unsynced.is_none()
+    {
+        let available: Vec<String> = language_inventory(&tag.frames).into_iter().filter(|usage| usage.frames.iter().any(|id| id != "COMM")).map(|usage| usage.language).collect();
+        let available = if available.is_empty() {
+            "none".to_string()
+        } else {
+            available.join(", ")
+        };
+        return Err(format!("no lyrics in language '{}' (available: {})", language, available).into());
+    }
+
+    let lyrics = match (format, synced, unsynced) {
+        | (LyricsFormat::Lrc, Some(lines), _) => format_lrc(&lines),
+        | (LyricsFormat::Lrc, None, Some(text)) if has_timestamps(text) => format!("{}\n", text.trim_end()),
+        | (LyricsFormat::Lrc, None, _) => return Err("no synchronised lyrics (SYLT) to export as LRC (use --format text)".into()),
+        | (LyricsFormat::Text, _, Some(text)) => format!("{}\n", text.trim_end()),
+        | (LyricsFormat::Text, Some(lines), None) => plain_text(&lines),
+        | (LyricsFormat::Text, None, None) => return Err("no lyrics (SYLT or USLT)".into()),
+    };
+
+    match output {
+        | Some(output) => {
+            std::fs::write(output, lyrics)?;
+            outln!("Lyrics written: {}", display_path(output));
+        }
+        | None => out!("{}", lyrics),
+    }
+    Ok(())
+}
+
+/// Set the SYLT and/or USLT frame of a file for `language` from an LRC/text file or the other lyrics frame
+///
+/// An LRC file sets both frames, a plain text file only USLT. `--from-frame sylt` derives
+/// USLT from SYLT, `--from-frame uslt` derives SYLT from a USLT frame containing LRC; the
+/// source frame in `language` is preferred.
+pub fn set_lyrics(path: &Path, source: LyricsSource, language: &str, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if language.len() != 3 || !language.is_ascii() {
+        return Err(format!("language must be a three-letter ISO 639-2 code, got '{}'", language).into());
+    }
+
+    let mut file = File::open(long_path(path))?;
+    let mut tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    ensure_writable_version(&tag)?;
+
+    let (synced, unsynced) = match source {
+        | LyricsSource::File(lyrics_path) => {
+            let text = std::fs::read_to_string(lyrics_path)?;
+            if has_timestamps(&text) {
+                let lines = parse_lrc(&text);
+                let plain = plain_text(&lines);
+                (Some(lines), Some(plain))
+            } else {
+                (None, Some(text))
+            }
+        }
+        | LyricsSource::Frame(LyricsFrameName::Sylt) => {
+            let sylt = find_synced_lyrics(&tag, Some(language)).or_else(|| find_synced_lyrics(&tag, None)).ok_or("no SYLT frame to convert")?;
+            (None, Some(plain_text(&synced_lines(sylt, &mut file)?)))
+        }
+        | LyricsSource::Frame(LyricsFrameName::Uslt) => {
+            let text = find_unsynced_lyrics(&tag, Some(language)).or_else(|| find_unsynced_lyrics(&tag, None)).ok_or("no USLT frame to convert")?;
+            if !has_timestamps(text) {
+                return Err("the USLT frame has no LRC time stamps to build SYLT from".into());
+            }
+            (Some(parse_lrc(text)), None)
+        }
+    };
+    drop(file);
+
+    let same_language = |id: &'static str| move |frame: &Id3v2Frame| frame.id == id && frame.language().is_none_or(|existing| language_matches(existing, language));
+    if let Some(lines) = &synced {
+        let frame = Id3v2Frame::new_synced_lyrics(language, "", lines.clone(), tag.version_major);
+        tag.replace_frames(frame, same_language("SYLT"));
+        outln!("{}: SYLT ({}) with {} line(s)", display_path(path), language, lines.len());
+    }
+    if let Some(text) = &unsynced {
+        let text = text.trim_end();
+        let frame = Id3v2Frame::new_lyrics(language, "", text, tag.version_major);
+        tag.replace_frames(frame, same_language("USLT"));
+        outln!("{}: USLT ({}) with {} line(s)", display_path(path), language, text.lines().count());
+    }
+
+    if dry_run {
+        outln!("{}", DRY_RUN_NOTICE);
+    } else {
+        write_tag(path, &tag, options)?;
+    }
+    Ok(())
+}
+
+/// First SYLT frame with lyrics, or any SYLT frame if none is marked as lyrics (in `language` if given)
+fn find_synced_lyrics<'a>(tag: &'a Id3v2Tag, language: Option<&str>) -> Option<&'a SyncedLyricsFrame> {
+    let mut frames = tag.frames.iter().filter_map(|frame| match &frame.content {
+        | Some(Id3v2FrameContent::SyncedLyrics(sylt)) if language.is_none_or(|language| language_matches(&sylt.language, language)) => Some(sylt),
+        | _ => None,
+    });
+    let first = frames.clone().next();
+    frames.find(|sylt| sylt.content_type == CONTENT_TYPE_LYRICS).or(first)
+}
+
+/// Text of the first USLT frame (in `language` if given)
+fn find_unsynced_lyrics<'a>(tag: &'a Id3v2Tag, language: Option<&str>) -> Option<&'a str> {
+    tag.frames
+        .iter()
+        .filter(|frame| frame.id == "USLT")
+        .find(|frame| language.is_none_or(|language| frame.language().is_some_and(|existing| language_matches(existing, language))))
+        .and_then(|frame| frame.get_text())
+}
+
+/// Lines of a SYLT frame with their time in milliseconds
+///
+/// Entries starting with a newline begin a new line and the others are syllables appended
+/// to the current line; if no entry starts with a newline every entry is a line of its own.
+fn synced_lines(sylt: &SyncedLyricsFrame, file: &mut dyn MediaSource) -> Result<Vec<(String, u32)>, Box<dyn std::error::Error>> {
+    let to_ms: Box<dyn Fn(u32) -> u32> = match sylt.timestamp_format {
+        | TIMESTAMP_MILLISECONDS => Box::new(|timestamp| timestamp),
+        | TIMESTAMP_MPEG_FRAMES => {
+            let header = first_frame_header(file)?.ok_or("SYLT time stamps count MPEG frames but the file has no MPEG audio")?;
+            Box::new(move |timestamp| header.frames_to_ms(timestamp as u64).min(u32::MAX as u64) as u32)
+        }
+        | format => return Err(format!("unknown SYLT time stamp format {}", format).into()),
+    };
+
+    let syllables = sylt.entries.iter().any(|(text, _)| text.starts_with('\n') || text.starts_with("\r\n"));
+    let mut lines: Vec<(String, u32)> = Vec::new();
+    for (text, timestamp) in &sylt.entries {
+        let starts_line = text.starts_with('\n') || text.starts_with("\r\n");
+        match lines.last_mut() {
+            | Some((line, _)) if syllables && !starts_line => line.push_str(text),
+            | _ => lines.push((text.trim_start_matches(['\r', '\n']).to_string(), to_ms(*timestamp))),
+        }
+    }
+    for (line, _) in &mut lines {
+        *line = line.trim_end().to_string();
+    }
+    Ok(lines)
+}
+
+/// Lines without their time stamps, one per line
+fn plain_text(lines: &[(String, u32)]) -> String {
+    lines.iter().map(|(text, _)| format!("{}\n", text)).collect()
+}
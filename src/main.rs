@@ -1,57 +1,135 @@
-use crate::cli::{Cli, Commands, DebugOptions};
+use crate::cli::{BaselineCommands, ChapterCommands, Cli, Commands, ExtractCommands, Mp4Commands, TagCommands};
 use clap::Parser;
-use std::fs::File;
-use std::path::PathBuf;
+use supertool::file_collector::FileSelection;
+use supertool::options::{CommonOptions, DebugOptions, ForensicOptions, SidecarOptions, WriteOptions};
+use supertool::tag_strip::StripSelection;
+use supertool::{
+    artwork_export, baseline, batch_errors, chapter_edit, chapter_export, crawler, daemon, dissect_batch, duplicate_finder, encoding_census, error, file_collector, format_list, health_score, icy_metadata, id3v2_convert, id3v2_repair, id3v2_validation, isobmff_box_export,
+    isobmff_chapter_writer, isobmff_demux, isobmff_faststart, isobmff_telemetry, isobmff_track_edit, lyrics, tag_compare, tag_csv_import, tag_disc, tag_genres, tag_reencode, tag_reorder, tag_sort, tag_strip, tag_write,
+};
 
 mod cli;
-mod dissector_builder;
-mod id3v2_3_dissector;
-mod id3v2_4_dissector;
-mod id3v2_attached_picture_frame;
-mod id3v2_chapter_frame;
-mod id3v2_comment_frame;
-mod id3v2_frame;
-mod id3v2_table_of_contents_frame;
-mod id3v2_text_encoding;
-mod id3v2_text_frame;
-mod id3v2_tools;
-mod id3v2_unique_file_id_frame;
-mod id3v2_url_frame;
-mod id3v2_user_text_frame;
-mod id3v2_user_url_frame;
-mod isobmff_dissector;
-mod media_dissector;
-mod unknown_dissector;
 
-use dissector_builder::DissectorBuilder;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
     let cli = Cli::parse();
-
-    match cli.command {
-        | Commands::Debug { file, header, frames, all } => {
-            let options = DebugOptions::from_flags(header, frames, all);
-            dissect_file(&file, &options)?;
-        }
+    if let Err(error) = run(cli) {
+        eprintln!("Error: {}", error);
+        std::process::exit(error::exit_code(&*error));
     }
-
-    Ok(())
 }
 
-fn dissect_file(file_path: &PathBuf, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
-    // Open file
-    let mut file = File::open(file_path)?;
-
-    // Build appropriate dissector based on file content
-    let builder = DissectorBuilder::new();
-    let dissector = builder.build_for_file(&mut file)?;
-
-    // Print file info
-    println!("Analyzing file: {}", file_path.display());
-    println!("Detected format: {} ({})", dissector.media_type(), dissector.name());
+/// Run the command; errors map to the exit status through `error::exit_code`
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let common = CommonOptions {
+        time_format: cli.time_format,
+        max_text_width: cli.max_text_width,
+        ellipsis: cli.ellipsis,
+        max_frame_depth: cli.max_frame_depth,
+        lenient: cli.lenient,
+        assume_encoding: cli.assume_encoding,
+        nfc: cli.nfc,
+        events: cli.events,
+        continue_on_error: cli.continue_on_error,
+        sandbox: cli.sandbox,
+    };
+    common.apply(|| run_command(cli.command, common))
+}
 
-    // Perform dissection with options
-    dissector.dissect_with_options(&mut file, options)?;
+/// Run a subcommand with the options of the global flags
+fn run_command(command: Commands, common: CommonOptions) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        | Commands::Debug { files, recursive, ext, exclude_ext, follow_links, jobs, header, frames, all, frame_id, explain_frame, sidecar, sidecar_dir, check_urls, lang, verify_crc, bitrates, timeline, sizes, seek_points, chunk_map, box_depth, extract_geob, inner, report, forensic, forensic_key } => {
+            let options = DebugOptions::from_flags(header, frames, all)
+                .with_frame_ids(frame_id)
+                .with_explain_frame(explain_frame)
+                .with_language(lang)
+                .with_inner(inner)
+                .with_sidecar(sidecar.map(|format| SidecarOptions { format, dir: sidecar_dir }))
+                .with_checks(check_urls, verify_crc, bitrates)
+                .with_timeline(timeline)
+                .with_sizes(sizes)
+                .with_seek_points(seek_points)
+                .with_chunk_map(chunk_map)
+                .with_box_depth(box_depth)
+                .with_extract_geob(extract_geob)
+                .with_report(report)
+                .with_forensic(forensic.zip(forensic_key).map(|(report, key)| ForensicOptions { report, key }))
+                .with_jobs(jobs)
+                .with_common(common);
+            let mut unreadable = batch_errors::BatchErrors::default();
+            let files = file_collector::expand_paths(&files, &FileSelection { recursive, include: ext, exclude: exclude_ext, follow_links }, &mut unreadable)?;
+            dissect_batch(&files, unreadable, &options)?;
+        }
+        | Commands::Chapters { file, format, output } => chapter_export::export_chapters(&file, format, output.as_deref())?,
+        | Commands::Compare { first, second } => tag_compare::compare_files(&first, &second)?,
+        | Commands::Crawl { dir, jobs, list, follow_links } => crawler::crawl(&dir, jobs, list, follow_links)?,
+        | Commands::Daemon { socket } => daemon::run_daemon(&socket, common)?,
+        | Commands::Dupes { dir } => duplicate_finder::find_duplicates(&dir)?,
+        | Commands::Encodings { path } => encoding_census::encoding_census(&path)?,
+        | Commands::Faststart { input, output } => isobmff_faststart::faststart(&input, &output)?,
+        | Commands::Formats { json } => format_list::list_formats(json)?,
+        | Commands::Icy { file, metaint, bitrate } => icy_metadata::analyze_icy(&file, metaint, bitrate)?,
+        | Commands::Mp4Chapters { input, output, from, style } => isobmff_chapter_writer::write_mp4_chapters(&input, &output, &from, style)?,
+        | Commands::Repair { files, output, dry_run } => id3v2_repair::repair_files(&files, output.as_deref(), dry_run)?,
+        | Commands::Strip { files, id3v1, ape, output, dry_run } => tag_strip::strip_files(&files, StripSelection { id3v1, ape }, output.as_deref(), dry_run)?,
+        | Commands::Triage { path, weight, details, all } => health_score::triage(&path, &health_score::HealthWeights::default().with(&weight), details, all)?,
+        | Commands::Validate { files, recursive, ignore, strict, json, rules } => {
+            if rules {
+                id3v2_validation::print_rules();
+            } else {
+                let mut unreadable = batch_errors::BatchErrors::default();
+                let files = file_collector::expand_paths(&files, &FileSelection { recursive, ..FileSelection::default() }, &mut unreadable)?;
+                id3v2_validation::validate_files(&files, unreadable, &ignore, strict, json)?;
+            }
+        }
+        | Commands::Baseline { action } => match action {
+            | BaselineCommands::Save { dir, output } => baseline::save_baseline(&dir, &output)?,
+            | BaselineCommands::Check { dir, against } => baseline::check_baseline(&dir, &against)?,
+        },
+        | Commands::Extract { action } => match action {
+            | ExtractCommands::Lyrics { file, format, lang, output } => lyrics::extract_lyrics(&file, format, lang.as_deref(), output.as_deref())?,
+            | ExtractCommands::Artwork { file, out } => artwork_export::extract_artwork(&file, &out)?,
+            | ExtractCommands::Box { file, path, out } => isobmff_box_export::extract_box(&file, &path, &out)?,
+            | ExtractCommands::Track { file, track, out } => isobmff_demux::extract_track(&file, track, &out)?,
+            | ExtractCommands::Gpmf { file, track, out } => isobmff_telemetry::extract_gpmf(&file, track, &out)?,
+        },
+        | Commands::Tag { preserve_mtime, canonical_order, store_nfc, force, action } => {
+            let options = WriteOptions { preserve_mtime, canonical_order, store_nfc, force };
+            match action {
+                | TagCommands::Write { files, set, artwork, picture_type, dry_run } => tag_write::write_files(&files, &set, artwork.as_deref(), picture_type, dry_run, &options)?,
+                | TagCommands::ApplyCsv { csv, dry_run } => tag_csv_import::apply_csv(&csv, dry_run, &options)?,
+                | TagCommands::Genres { files, style, dry_run } => tag_genres::rewrite_genres(&files, style.genre_style(), dry_run, &options)?,
+                | TagCommands::SetLyrics { file, from, from_frame, lang, dry_run } => {
+                    let source = match (&from, from_frame) {
+                        | (Some(from), _) => lyrics::LyricsSource::File(from),
+                        | (None, Some(frame)) => lyrics::LyricsSource::Frame(frame),
+                        | (None, None) => unreachable!("clap requires --from or --from-frame"),
+                    };
+                    lyrics::set_lyrics(&file, source, &lang, dry_run, &options)?
+                }
+                | TagCommands::Convert { files, to, dry_run } => id3v2_convert::convert_files(&files, to.version_major(), dry_run, &options)?,
+                | TagCommands::Reencode { files, encoding, dry_run } => tag_reencode::reencode_files(&files, encoding.text_encoding(), dry_run, &options)?,
+                | TagCommands::Chapters { action } => match action {
+                    | ChapterCommands::Merge { file, at, dry_run } => chapter_edit::edit_chapters(&file, chapter_edit::ChapterEdit::Merge { at: chapter_edit::parse_time(&at)? }, dry_run, &options)?,
+                    | ChapterCommands::Split { file, chapter, at, dry_run } => {
+                        chapter_edit::edit_chapters(&file, chapter_edit::ChapterEdit::Split { chapter, at: chapter_edit::parse_time(&at)? }, dry_run, &options)?
+                    }
+                    | ChapterCommands::Shift { file, offset, dry_run } => chapter_edit::edit_chapters(&file, chapter_edit::ChapterEdit::Shift { offset: chapter_edit::parse_offset(&offset)? }, dry_run, &options)?,
+                    | ChapterCommands::Normalize { files, dry_run } => chapter_edit::normalize_files(&files, dry_run, &options)?,
+                },
+                | TagCommands::Mp4 { action } => match action {
+                    | Mp4Commands::SetTrackLang { file, track, lang, elng, dry_run } => isobmff_track_edit::edit_track(&file, track, &isobmff_track_edit::TrackEdit::Language { code: lang, extended: elng }, dry_run, &options)?,
+                    | Mp4Commands::SetTrackName { file, track, name, dry_run } => isobmff_track_edit::edit_track(&file, track, &isobmff_track_edit::TrackEdit::Name(name), dry_run, &options)?,
+                },
+                | TagCommands::FixSort { files, dry_run } => tag_sort::fix_sort_files(&files, dry_run, &options)?,
+                | TagCommands::SetDisc { files, disc, compilation, no_compilation, dry_run } => {
+                    let compilation = (compilation || no_compilation).then_some(compilation);
+                    tag_disc::set_disc_files(&files, disc.as_deref(), compilation, dry_run, &options)?
+                }
+                | TagCommands::Reorder { files, dry_run } => tag_reorder::reorder_files(&files, dry_run, &options)?,
+            }
+        }
+    }
 
     Ok(())
 }
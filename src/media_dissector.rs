@@ -1,5 +1,11 @@
-use crate::cli::DebugOptions;
-use std::fs::File;
+use crate::coverage::Coverage;
+use crate::dissection_report::ReportNode;
+use crate::media_source::MediaSource;
+use crate::media_summary::MediaSummary;
+use crate::options::DebugOptions;
+use crate::size_tree::SizeNode;
+use serde::{Serialize, Serializer};
+use std::fmt;
 
 /// Common trait for all media file dissectors
 pub trait MediaDissector {
@@ -7,11 +13,104 @@ pub trait MediaDissector {
     fn media_type(&self) -> &'static str;
 
     /// Dissect the media file with specific output options
-    fn dissect_with_options(&self, file: &mut File, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>>;
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>>;
 
     /// Check if this dissector can handle the given file header
     fn can_handle(&self, header: &[u8]) -> bool;
 
     /// Get a descriptive name for this dissector
     fn name(&self) -> &'static str;
+
+    /// Extensions, signatures and operations of the format (listed by `supertool formats`)
+    fn capabilities(&self) -> Capabilities;
+
+    /// Byte ranges of the file the dissector interprets and skips
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        crate::coverage::not_dissected(file)
+    }
+
+    /// Sizes of the parts of the file, as a tree (`debug --sizes`)
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        crate::size_tree::flat(file)
+    }
+
+    /// Build a structured summary of the file without printing anything
+    fn summarize(&self, _file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        Ok(MediaSummary::new(self.media_type(), self.name()))
+    }
+
+    /// Structures of the file as a tree with offsets, values and diagnostics (`debug --report`)
+    ///
+    /// Built after the fact from `summarize` and `coverage`, not by the printing dissection
+    fn report(&self, file: &mut dyn MediaSource) -> Result<ReportNode, Box<dyn std::error::Error>> {
+        let mut summary = self.summarize(file)?;
+        summary.coverage = Some(self.coverage(file)?);
+        Ok(crate::dissection_report::summary_report(&summary, file.byte_len()?))
+    }
+}
+
+/// What a dissector recognizes and what the tool can do with the format
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    /// Usual file name extensions (without the dot)
+    pub extensions: &'static [&'static str],
+    /// Byte sequences the format is detected by
+    pub signatures: &'static [Signature],
+    /// Operations supported for the format
+    pub operations: &'static [Operation],
+}
+
+/// Magic bytes at a fixed offset from the start of the file
+#[derive(Debug, Clone, Serialize)]
+pub struct Signature {
+    pub offset: usize,
+    /// The bytes as hex, e.g. "49443303"
+    #[serde(serialize_with = "serialize_hex")]
+    pub magic: &'static [u8],
+    /// What the signature identifies
+    pub description: &'static str,
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let magic: String = self
+            .magic
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    (byte as char).to_string()
+                } else {
+                    format!("\\x{:02X}", byte)
+                }
+            })
+            .collect();
+        write!(f, "\"{}\" at offset {} ({})", magic, self.offset, self.description)
+    }
+}
+
+fn serialize_hex<S: Serializer>(bytes: &&'static [u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<String>())
+}
+
+/// Operation the tool supports for a format
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    /// Dissect and summarize (`debug`, sidecar files)
+    Read,
+    /// Rewrite the metadata (`tag` commands)
+    Write,
+    /// Report structural problems and checksums
+    Validate,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            | Operation::Read => "read",
+            | Operation::Write => "write",
+            | Operation::Validate => "validate",
+        };
+        write!(f, "{}", name)
+    }
 }
@@ -0,0 +1,19 @@
+/// Input of the dissectors: anything that can be read and seeked
+///
+/// The dissectors and the parsers they share take `&mut dyn MediaSource` instead of a `File`, so
+/// files, memory buffers (`Cursor`) and other seekable streams can be dissected alike. Every
+/// `Read + Seek` type is a `MediaSource`; the trait adds the length, found by seeking to the end.
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// A seekable byte source with a known length
+pub trait MediaSource: Read + Seek {
+    /// Length in bytes; the read position is left where it was
+    fn byte_len(&mut self) -> io::Result<u64> {
+        let position = self.stream_position()?;
+        let length = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(position))?;
+        Ok(length)
+    }
+}
+
+impl<T: Read + Seek> MediaSource for T {}
@@ -0,0 +1,286 @@
+/// Format-independent summary of a dissected media file
+///
+/// This is the serializable model written to sidecar files and consumed by
+/// library-level tooling. Dissectors fill in what they know about their format.
+use crate::coverage::Coverage;
+use crate::disc_info::DiscInfo;
+use crate::dj_info::DjInfo;
+use crate::finding::Finding;
+use crate::id3v1_tag::Id3v1Tag;
+use crate::id3v2_chapter_frame::ChapterFrame;
+use crate::id3v2_chapter_tree::TocEntry;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_known_user_text::KnownUserText;
+use crate::id3v2_languages::{LanguageUsage, language_inventory};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_tools::get_frame_description;
+use crate::image_info::ImageInfo;
+use crate::isobmff_telemetry::Telemetry;
+use crate::isobmff_tracks::{MovieSummary, TrackSummary};
+use crate::metadata_fingerprint::metadata_fingerprint;
+use crate::ogg_page::OggFile;
+use crate::podcast_info::PodcastInfo;
+use crate::riff_chunk::RiffFile;
+use crate::size_tree::SizeNode;
+use crate::stream_tags::InlineTag;
+use crate::trailer_tags::TrailerBlock;
+use crate::xmp::XmpPacket;
+use crate::zip_archive::ZipArchive;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaSummary {
+    /// Path of the analyzed file as given on the command line
+    pub path: String,
+    /// File size in bytes
+    pub file_size: u64,
+    /// Last modification time (seconds since the Unix epoch), if available
+    pub modified: Option<u64>,
+    /// Detected media type (e.g. "ID3v2.4", "ISO BMFF")
+    pub format: String,
+    /// Name of the dissector that handled the file
+    pub dissector: String,
+    /// Metadata tag found in the file, if any
+    pub tag: Option<TagSummary>,
+    /// Movie header of ISO BMFF files (mvhd)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movie: Option<MovieSummary>,
+    /// Tracks of ISO BMFF files with their header times
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tracks: Vec<TrackSummary>,
+    /// Container structure of WAVE files (RIFF/RF64/BW64 chunks and ds64 sizes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub riff: Option<RiffFile>,
+    /// Logical streams of Ogg files with their codec and Vorbis comment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ogg: Option<OggFile>,
+    /// Layout and members of ZIP bundles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<ZipArchive>,
+    /// XMP packets of ISO BMFF files (uuid box) and WAVE files (_PMX chunk)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub xmp: Vec<XmpPacket>,
+    /// Tag blocks after the audio (APEv2, Lyrics3, ID3v1), in file order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trailers: Vec<TrailerBlock>,
+    /// ID3v1 tag at the end of MP3 files, with its differences to the ID3v2 tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id3v1: Option<Id3v1Tag>,
+    /// ID3v2 tags and ICY metadata blocks inside the audio data of stream rips
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inline_tags: Vec<InlineTag>,
+    /// Podcast flag, episode GUID and feed URL (ID3v2 PCST/TGID/WFED, MP4 pcst/egid/purl)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub podcast: Option<PodcastInfo>,
+    /// Disc and track position and compilation flag (ID3v2 TPOS/TRCK/TCMP, MP4 disk/trkn/cpil)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc: Option<DiscInfo>,
+    /// Key, tempo, energy and DJ software data of ID3v2 tags (TKEY, TBPM, Traktor PRIV, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dj: Option<DjInfo>,
+    /// Cover pictures of MP4 files (`covr` item) with their color space and ICC profile
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cover_art: Vec<ImageInfo>,
+    /// ISRCs and barcodes of MP4 freeform items (`----:com.apple.iTunes:ISRC`, BARCODE, UPC, EAN)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub release_codes: Vec<KnownUserText>,
+    /// Telemetry tracks (GoPro GPMF, ONVIF, CAMM) and camera details of MP4 files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<Telemetry>,
+    /// Sample rate and channel count mismatches between the container and the codec
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub format_findings: Vec<Finding>,
+    /// Share of the file the dissector interpreted and the byte ranges it skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<Coverage>,
+    /// Size breakdown of the file (`--sizes`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sizes: Option<SizeNode>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagSummary {
+    /// Tag type (e.g. "ID3v2.3")
+    pub tag_type: String,
+    /// Full tag version (e.g. "2.3.0")
+    pub version: String,
+    /// Number of bytes the tag occupies in the file
+    pub size: u64,
+    /// Tag header flags
+    pub flags: u8,
+    /// Normalized genre list (ID3v1 references resolved, multi-genre values split)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<String>,
+    /// Well-known TXXX frames and iTunes data comments as typed, validated values
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub known_fields: Vec<KnownUserText>,
+    /// Problems found in the frames (e.g. malformed URLs)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<Finding>,
+    /// Chapters (CHAP frames) with their conventional sub-frame roles resolved
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chapters: Vec<ChapterSummary>,
+    /// Table of contents tree, starting at the top-level CTOC
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub toc: Vec<TocEntry>,
+    /// Languages of the COMM, USLT and SYLT frames with the frames using them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<LanguageUsage>,
+    /// XMP packets of PRIV frames and attached JPEG pictures
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub xmp: Vec<XmpPacket>,
+    /// SHA-256 over the canonical frames, independent of frame order, padding and text encoding
+    pub fingerprint: String,
+    /// Top-level frames in file order
+    pub frames: Vec<FrameSummary>,
+}
+
+/// A chapter with the roles of its sub-frames as named properties (Chapter Frame Addendum)
+#[derive(Debug, Clone, Serialize)]
+pub struct ChapterSummary {
+    /// Element ID referenced by CTOC frames
+    pub element_id: String,
+    /// Start time in milliseconds
+    pub start_ms: u32,
+    /// End time in milliseconds
+    pub end_ms: u32,
+    /// Title (TIT2 sub-frame)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Subtitle or description (TIT3 sub-frame)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    /// Link (WXXX sub-frame)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Image (APIC sub-frame)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<ChapterImage>,
+}
+
+/// Picture attached to a chapter, without the image data
+#[derive(Debug, Clone, Serialize)]
+pub struct ChapterImage {
+    pub mime_type: String,
+    pub picture_type: String,
+    pub description: String,
+    /// Size of the image data in bytes
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameSummary {
+    /// Frame identifier (e.g. "TIT2")
+    pub id: String,
+    /// Human-readable frame description
+    pub description: String,
+    /// Frame offset within the tag (or within the parent frame for embedded frames)
+    pub offset: Option<usize>,
+    /// Size of the frame data (excluding header)
+    pub size: u32,
+    /// Frame header flags
+    pub flags: u16,
+    /// Text or URL value, if the frame carries one (the first value of multi-value text frames)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// All values of a text frame (ID3v2.4 frames such as TPE1 or TCON may carry several)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    /// Embedded sub-frames (CHAP/CTOC)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sub_frames: Vec<FrameSummary>,
+}
+
+impl MediaSummary {
+    /// Create a summary without tag information
+    pub fn new(format: &str, dissector: &str) -> Self {
+        MediaSummary {
+            path: String::new(),
+            file_size: 0,
+            modified: None,
+            format: format.to_string(),
+            dissector: dissector.to_string(),
+            tag: None,
+            movie: None,
+            tracks: Vec::new(),
+            riff: None,
+            ogg: None,
+            bundle: None,
+            xmp: Vec::new(),
+            trailers: Vec::new(),
+            id3v1: None,
+            inline_tags: Vec::new(),
+            podcast: None,
+            disc: None,
+            dj: None,
+            cover_art: Vec::new(),
+            release_codes: Vec::new(),
+            telemetry: None,
+            format_findings: Vec::new(),
+            coverage: None,
+            sizes: None,
+        }
+    }
+}
+
+impl From<&Id3v2Tag> for TagSummary {
+    fn from(tag: &Id3v2Tag) -> Self {
+        TagSummary {
+            tag_type: tag.tag_type(),
+            version: tag.version(),
+            size: tag.total_size(),
+            flags: tag.flags,
+            genres: tag.genres(),
+            known_fields: tag.known_user_texts(),
+            findings: tag.findings(),
+            chapters: tag.chapters().map(ChapterSummary::from).collect(),
+            toc: tag.chapter_tree().roots,
+            languages: language_inventory(&tag.frames),
+            xmp: tag.xmp_packets(),
+            fingerprint: metadata_fingerprint(&tag.frames),
+            frames: tag.frames.iter().map(FrameSummary::from).collect(),
+        }
+    }
+}
+
+impl From<&ChapterFrame> for ChapterSummary {
+    fn from(chapter: &ChapterFrame) -> Self {
+        ChapterSummary {
+            element_id: chapter.element_id.clone(),
+            start_ms: chapter.start_time,
+            end_ms: chapter.end_time,
+            title: chapter.title().map(str::to_string),
+            subtitle: chapter.subtitle().map(str::to_string),
+            url: chapter.link().map(|link| link.url.clone()),
+            image: chapter.image().map(|picture| ChapterImage {
+                mime_type: picture.mime_type.clone(),
+                picture_type: picture.picture_type_description().to_string(),
+                description: picture.description.clone(),
+                size: picture.picture_data.len(),
+            }),
+        }
+    }
+}
+
+impl From<&Id3v2Frame> for FrameSummary {
+    fn from(frame: &Id3v2Frame) -> Self {
+        let value = frame.get_text().or_else(|| frame.get_url()).map(str::to_string);
+        let values = frame.get_text_values().map(<[String]>::to_vec).unwrap_or_default();
+        let sub_frames = match &frame.content {
+            | Some(Id3v2FrameContent::Chapter(chapter)) => chapter.sub_frames.iter().map(FrameSummary::from).collect(),
+            | Some(Id3v2FrameContent::TableOfContents(toc)) => toc.sub_frames.iter().map(FrameSummary::from).collect(),
+            | _ => Vec::new(),
+        };
+
+        FrameSummary {
+            id: frame.id.clone(),
+            description: get_frame_description(&frame.id).to_string(),
+            offset: frame.offset,
+            size: frame.size,
+            flags: frame.flags,
+            value,
+            values,
+            sub_frames,
+        }
+    }
+}
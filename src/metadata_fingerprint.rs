@@ -0,0 +1,70 @@
+/// Content fingerprint of the metadata of an ID3v2 tag
+///
+/// Dedupe pipelines need to tell whether two files carry the same tag without comparing every
+/// frame. The fingerprint is a SHA-256 over a canonical form of the frames, so it only depends on
+/// what the tag says: frame order, padding, tag size, frame flags and the text encoding of a value
+/// do not change it. Each frame is reduced to its key (as in `baseline`: ID plus description,
+/// language, picture type or owner) and its content; text, URL and comment frames contribute their
+/// value, pictures their MIME type, description and data, CHAP and CTOC frames their times or
+/// children and the canonical form of their sub-frames, and other frames their raw data. The
+/// reduced frames are sorted and hashed, each prefixed with its length.
+use crate::baseline::{frame_key, frame_value};
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use sha2::{Digest, Sha256};
+
+/// Fingerprint of a tag's frames as 64 lowercase hex digits
+pub fn metadata_fingerprint(frames: &[Id3v2Frame]) -> String {
+    Sha256::digest(canonical_frames(frames)).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Canonical forms of the frames, sorted and length-prefixed
+fn canonical_frames(frames: &[Id3v2Frame]) -> Vec<u8> {
+    let mut entries: Vec<Vec<u8>> = frames.iter().map(canonical_frame).collect();
+    entries.sort();
+    let mut bytes = Vec::new();
+    for entry in entries {
+        bytes.extend_from_slice(&(entry.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&entry);
+    }
+    bytes
+}
+
+fn canonical_frame(frame: &Id3v2Frame) -> Vec<u8> {
+    let mut bytes = frame_key(frame).into_bytes();
+    bytes.push(0);
+    match &frame.content {
+        | Some(Id3v2FrameContent::Picture(picture)) => {
+            bytes.extend_from_slice(picture.mime_type.as_bytes());
+            bytes.push(0);
+            bytes.extend_from_slice(picture.description.as_bytes());
+            bytes.push(0);
+            bytes.extend_from_slice(&picture.picture_data);
+        }
+        | Some(Id3v2FrameContent::Chapter(chapter)) => {
+            for value in [chapter.start_time, chapter.end_time, chapter.start_offset, chapter.end_offset] {
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+            bytes.extend_from_slice(&canonical_frames(&chapter.sub_frames));
+        }
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => {
+            bytes.push(u8::from(toc.top_level) << 1 | u8::from(toc.ordered));
+            // The order of the children is content (chapter order), unlike the order of the sub-frames
+            for child in &toc.child_element_ids {
+                bytes.extend_from_slice(child.as_bytes());
+                bytes.push(0);
+            }
+            bytes.push(0);
+            bytes.extend_from_slice(&canonical_frames(&toc.sub_frames));
+        }
+        | _ => match frame_value(frame) {
+            | Some(value) => bytes.extend_from_slice(value.as_bytes()),
+            | None => bytes.extend_from_slice(&frame.data),
+        },
+    }
+    bytes
+}
+
+/// Print the metadata fingerprint of a tag's frames
+pub fn print_fingerprint(frames: &[Id3v2Frame]) {
+    outln!("\nMetadata Fingerprint: {}", metadata_fingerprint(frames));
+}
@@ -0,0 +1,274 @@
+/// MPEG audio frame headers and duration estimation
+///
+/// Parses the 4-byte header of MPEG-1/2/2.5 Layer I-III frames and estimates the playing
+/// time of an MP3 file from the Xing/Info or VBRI header of the first frame, falling back
+/// to the bitrate of the first frame for CBR files without such a header.
+use crate::audio_hash::audio_ranges;
+use crate::media_source::MediaSource;
+use std::io::SeekFrom;
+
+/// Bytes searched for the first frame after the ID3v2 tag (skips padding and junk)
+const SYNC_SEARCH_LIMIT: usize = 64 * 1024;
+
+/// Bitrates in kbit/s by bitrate index: MPEG-1 Layer I, II, III, MPEG-2/2.5 Layer I, Layer II/III
+const BITRATES: [[u16; 15]; 5] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+/// Sample rates in Hz by sample rate index for MPEG-1 (MPEG-2 halves, MPEG-2.5 quarters them)
+const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// MPEG audio version
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MpegVersion {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+/// Decoded 4-byte MPEG audio frame header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MpegFrameHeader {
+    pub version: MpegVersion,
+    /// Layer 1, 2 or 3
+    pub layer: u8,
+    /// A 16-bit CRC follows the header
+    pub protected: bool,
+    /// Bitrate in kbit/s (0 for free format)
+    pub bitrate: u16,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    pub padding: bool,
+    /// Channel mode (0 stereo, 1 joint stereo, 2 dual channel, 3 mono)
+    pub channel_mode: u8,
+    /// Joint stereo mode extension
+    pub mode_extension: u8,
+}
+
+impl MpegFrameHeader {
+    /// Decode a frame header; `None` if the bytes are not a valid header
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 || bytes[0] != 0xFF || bytes[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = match (bytes[1] >> 3) & 0x03 {
+            | 0 => MpegVersion::Mpeg25,
+            | 2 => MpegVersion::Mpeg2,
+            | 3 => MpegVersion::Mpeg1,
+            | _ => return None,
+        };
+        let layer = match (bytes[1] >> 1) & 0x03 {
+            | 1 => 3,
+            | 2 => 2,
+            | 3 => 1,
+            | _ => return None,
+        };
+        let bitrate_index = (bytes[2] >> 4) as usize;
+        let sample_rate_index = ((bytes[2] >> 2) & 0x03) as usize;
+        if bitrate_index == 15 || sample_rate_index == 3 {
+            return None;
+        }
+
+        let table = match (version, layer) {
+            | (MpegVersion::Mpeg1, layer) => layer as usize - 1,
+            | (_, 1) => 3,
+            | _ => 4,
+        };
+        let sample_rate = match version {
+            | MpegVersion::Mpeg1 => SAMPLE_RATES[sample_rate_index],
+            | MpegVersion::Mpeg2 => SAMPLE_RATES[sample_rate_index] / 2,
+            | MpegVersion::Mpeg25 => SAMPLE_RATES[sample_rate_index] / 4,
+        };
+
+        Some(MpegFrameHeader {
+            version,
+            layer,
+            protected: bytes[1] & 0x01 == 0,
+            bitrate: BITRATES[table][bitrate_index],
+            sample_rate,
+            padding: bytes[2] & 0x02 != 0,
+            channel_mode: bytes[3] >> 6,
+            mode_extension: (bytes[3] >> 4) & 0x03,
+        })
+    }
+
+    /// Number of PCM samples per channel in one frame
+    pub fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.version) {
+            | (1, _) => 384,
+            | (2, _) | (3, MpegVersion::Mpeg1) => 1152,
+            | _ => 576,
+        }
+    }
+
+    /// Frame length in bytes including the header (`None` for free-format frames)
+    pub fn frame_length(&self) -> Option<usize> {
+        if self.bitrate == 0 {
+            return None;
+        }
+        let bitrate = self.bitrate as u32 * 1000;
+        Some(if self.layer == 1 {
+            // Layer I frames consist of 4-byte slots
+            ((12 * bitrate / self.sample_rate + self.padding as u32) * 4) as usize
+        } else {
+            (self.samples_per_frame() / 8 * bitrate / self.sample_rate + self.padding as u32) as usize
+        })
+    }
+
+    /// Playing time of `frames` frames in milliseconds
+    pub fn frames_to_ms(&self, frames: u64) -> u64 {
+        frames * self.samples_per_frame() as u64 * 1000 / self.sample_rate as u64
+    }
+
+    /// Size of the Layer III side information following the header (and CRC)
+    pub fn side_info_size(&self) -> usize {
+        match (self.version, self.channel_mode == 3) {
+            | (MpegVersion::Mpeg1, false) => 32,
+            | (MpegVersion::Mpeg1, true) => 17,
+            | (_, false) => 17,
+            | (_, true) => 9,
+        }
+    }
+}
+
+/// Offset and header of the first MPEG audio frame in `data`
+///
+/// A candidate only counts if the next frame header follows right after it, which rules
+/// out most false syncs inside junk data.
+pub fn find_first_frame(data: &[u8]) -> Option<(usize, MpegFrameHeader)> {
+    (0..data.len().saturating_sub(4)).find_map(|pos| {
+        let header = MpegFrameHeader::parse(&data[pos..])?;
+        let next = pos + header.frame_length()?;
+        let confirmed = next + 4 > data.len() || MpegFrameHeader::parse(&data[next..]).is_some();
+        confirmed.then_some((pos, header))
+    })
+}
+
+/// Offsets and headers of all frames in `data`, walking from frame to frame
+///
+/// After a frame that is not followed by another header the scan searches for the next
+/// confirmed frame, so junk between frames is skipped. It stops at free-format frames
+/// and at a frame that is cut off by the end of the data.
+pub fn scan_frames(data: &[u8]) -> Vec<(usize, MpegFrameHeader)> {
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while let Some((offset, header)) = find_first_frame(&data[pos..]) {
+        let mut current = (pos + offset, header);
+        loop {
+            let Some(length) = current.1.frame_length() else {
+                return frames;
+            };
+            if current.0 + length > data.len() {
+                return frames;
+            }
+            frames.push(current);
+            pos = current.0 + length;
+            match MpegFrameHeader::parse(&data[pos..]) {
+                | Some(next) => current = (pos, next),
+                | None => break,
+            }
+        }
+    }
+    frames
+}
+
+/// File offset of the audio data and its content
+pub type AudioData = (u64, Vec<u8>);
+
+/// Start offset and content of the audio data of a file (after the ID3v2 tag, before ID3v1)
+pub fn read_audio_data(file: &mut dyn MediaSource) -> Result<Option<AudioData>, Box<dyn std::error::Error>> {
+    let Some(&(start, end)) = audio_ranges(file)?.first() else {
+        return Ok(None);
+    };
+    let mut data = vec![0u8; (end - start) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut data)?;
+    Ok(Some((start, data)))
+}
+
+/// Header of the first MPEG audio frame of a file (`None` if there is no MPEG audio)
+pub fn first_frame_header(file: &mut dyn MediaSource) -> Result<Option<MpegFrameHeader>, Box<dyn std::error::Error>> {
+    Ok(read_first_frame(file)?.map(|(_, _, _, header)| header))
+}
+
+/// Estimate the playing time of an MPEG audio file in milliseconds
+///
+/// Returns `None` if no MPEG audio frame is found.
+pub fn estimate_duration_ms(file: &mut dyn MediaSource) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let Some((data, offset, audio_size, header)) = read_first_frame(file)? else {
+        return Ok(None);
+    };
+
+    if let Some(frame_count) = VbrHeader::parse(&data[offset..], &header).and_then(|vbr| vbr.frames) {
+        return Ok(Some(header.frames_to_ms(frame_count as u64)));
+    }
+    if header.bitrate == 0 {
+        return Ok(None);
+    }
+
+    // CBR: every frame has the bitrate of the first one
+    let audio_bytes = audio_size - offset as u64;
+    Ok(Some(audio_bytes * 8 / header.bitrate as u64))
+}
+
+/// Start of the audio data, offset of the first frame in it, size of the audio data and the first frame header
+type FirstFrame = (Vec<u8>, usize, u64, MpegFrameHeader);
+
+/// Read the start of the audio data and locate the first frame in it
+fn read_first_frame(file: &mut dyn MediaSource) -> Result<Option<FirstFrame>, Box<dyn std::error::Error>> {
+    let Some(&(start, end)) = audio_ranges(file)?.first() else {
+        return Ok(None);
+    };
+
+    let mut data = vec![0u8; (end - start).min(SYNC_SEARCH_LIMIT as u64) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut data)?;
+
+    Ok(find_first_frame(&data).map(|(offset, header)| (data, offset, end - start, header)))
+}
+
+/// Xing/Info (LAME) or VBRI header stored in the first frame of an MP3 file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VbrHeader {
+    /// "Xing", "Info" (written by LAME for CBR files) or "VBRI"
+    pub kind: &'static str,
+    /// Number of audio frames, not counting the frame holding this header
+    pub frames: Option<u32>,
+    /// Size of the audio data in bytes, including the frame holding this header
+    pub bytes: Option<u32>,
+}
+
+impl VbrHeader {
+    /// Find the header in the first frame of the stream
+    pub fn parse(frame: &[u8], header: &MpegFrameHeader) -> Option<Self> {
+        let read_u32 = |pos: usize| frame.get(pos..pos + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+
+        let xing = 4 + header.protected as usize * 2 + header.side_info_size();
+        if let Some(tag) = frame.get(xing..xing + 4)
+            && (tag == b"Xing" || tag == b"Info")
+        {
+            let kind = if tag == b"Xing" {
+                "Xing"
+            } else {
+                "Info"
+            };
+            let flags = read_u32(xing + 4)?;
+            // The optional fields follow the flags in order: frames, bytes
+            let frames = (flags & 0x01 != 0).then(|| read_u32(xing + 8)).flatten();
+            let bytes_pos = xing + 8 + (flags & 0x01 != 0) as usize * 4;
+            let bytes = (flags & 0x02 != 0).then(|| read_u32(bytes_pos)).flatten();
+            return Some(VbrHeader { kind, frames, bytes });
+        }
+
+        // The VBRI header (Fraunhofer encoders) always sits 32 bytes after the frame header
+        if frame.get(36..40) == Some(&b"VBRI"[..]) {
+            return Some(VbrHeader { kind: "VBRI", frames: read_u32(36 + 14), bytes: read_u32(36 + 10) });
+        }
+        None
+    }
+}
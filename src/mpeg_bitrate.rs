@@ -0,0 +1,84 @@
+/// Bitrate analysis of MPEG audio streams (`debug --bitrates`)
+///
+/// Scans all frames to decide whether the stream is CBR or VBR, prints a histogram of the
+/// frame bitrates and compares the Xing/Info/VBRI header with the frames actually present.
+/// A header with a wrong frame or byte count makes players compute wrong durations and
+/// seek to wrong positions.
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::{VbrHeader, read_audio_data, scan_frames};
+use std::collections::BTreeMap;
+
+/// Length of the longest histogram bar in characters
+const BAR_WIDTH: usize = 40;
+
+/// Print the bitrate mode, histogram and VBR header consistency of the MPEG audio stream
+pub fn print_bitrate_report(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    outln!("\nMPEG Bitrates:");
+    let Some((_, data)) = read_audio_data(file)? else {
+        outln!("    No audio data");
+        return Ok(());
+    };
+    let mut frames = scan_frames(&data);
+    let Some(&(first_pos, first_header)) = frames.first() else {
+        outln!("    No MPEG audio frames found");
+        return Ok(());
+    };
+
+    let scanned_bytes: usize = frames.iter().filter_map(|(_, header)| header.frame_length()).sum();
+    // The frame holding the VBR header carries no audio
+    let vbr_header = VbrHeader::parse(&data[first_pos..], &first_header);
+    if vbr_header.is_some() {
+        frames.remove(0);
+    }
+    if frames.is_empty() {
+        outln!("    No audio frames after the VBR header frame");
+        return Ok(());
+    }
+
+    let mut histogram: BTreeMap<u16, usize> = BTreeMap::new();
+    for (_, header) in &frames {
+        *histogram.entry(header.bitrate).or_default() += 1;
+    }
+    let vbr = histogram.len() > 1;
+    let average = frames.iter().map(|(_, header)| header.bitrate as u64).sum::<u64>() / frames.len() as u64;
+    let mode = if vbr {
+        format!("VBR, average {} kbit/s", average)
+    } else {
+        format!("CBR, {} kbit/s", average)
+    };
+    outln!("    {} audio frame(s), {}", frames.len(), mode);
+
+    let most = histogram.values().copied().max().unwrap_or(1);
+    for (bitrate, count) in &histogram {
+        let bar = "#".repeat((count * BAR_WIDTH).div_ceil(most));
+        outln!("    {:>3} kbit/s {:>7} {:>6.2}% {}", bitrate, count, *count as f64 * 100.0 / frames.len() as f64, bar);
+    }
+
+    let Some(vbr_header) = vbr_header else {
+        if vbr {
+            outln!("    WARNING: VBR stream without Xing/VBRI header (players estimate duration and seek positions from the first frame)");
+        }
+        return Ok(());
+    };
+    outln!("    {} header: {} frame(s), {} byte(s)", vbr_header.kind, describe(vbr_header.frames), describe(vbr_header.bytes));
+    match (vbr_header.kind, vbr) {
+        | ("Info", true) => outln!("    WARNING: Info header marks the stream as CBR but the frame bitrates vary"),
+        | ("Xing", false) => outln!("    Note: Xing header on a CBR stream"),
+        | _ => {}
+    }
+    if let Some(header_frames) = vbr_header.frames
+        && header_frames as usize != frames.len()
+    {
+        outln!("    WARNING: {} header counts {} frame(s) but the stream has {}", vbr_header.kind, header_frames, frames.len());
+    }
+    if let Some(header_bytes) = vbr_header.bytes
+        && header_bytes as usize != scanned_bytes
+    {
+        outln!("    WARNING: {} header counts {} byte(s) but the frames occupy {}", vbr_header.kind, header_bytes, scanned_bytes);
+    }
+    Ok(())
+}
+
+fn describe(value: Option<u32>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_else(|| "unknown".to_string())
+}
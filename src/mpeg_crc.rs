@@ -0,0 +1,143 @@
+/// CRC verification of protected MPEG audio frames (`debug --verify-crc`)
+///
+/// Frames with the protection bit set carry a CRC-16 over the last two header bytes and the
+/// side information (Layer III) or bit allocation (Layer I). Every such frame is checked and
+/// the corrupt ones are reported as a map over the stream, a list of positions and a health
+/// score. Layer II frames are counted but not verified, as their protected bits depend on
+/// the allocation tables.
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::{MpegFrameHeader, read_audio_data, scan_frames};
+use crate::options::CrcReport;
+
+/// Width of the corruption map in characters
+const MAP_WIDTH: usize = 72;
+
+/// Number of corrupt frame positions listed
+const MAX_LISTED: usize = 20;
+
+/// Result of checking one frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameCheck {
+    Valid,
+    Corrupt,
+    /// The frame has no CRC
+    Unprotected,
+    /// The frame has a CRC this tool cannot verify (Layer II)
+    Unverified,
+}
+
+/// Verify the CRCs of all MPEG audio frames and print the result
+pub fn verify_crc(file: &mut dyn MediaSource, report: CrcReport) -> Result<(), Box<dyn std::error::Error>> {
+    outln!("\nMPEG CRC Check:");
+    let Some((audio_start, data)) = read_audio_data(file)? else {
+        outln!("    No audio data");
+        return Ok(());
+    };
+    let frames = scan_frames(&data);
+    if frames.is_empty() {
+        outln!("    No MPEG audio frames found");
+        return Ok(());
+    }
+
+    let checks: Vec<FrameCheck> = frames.iter().map(|&(pos, header)| check_frame(&data[pos..], &header)).collect();
+    let count = |kind: FrameCheck| checks.iter().filter(|&&check| check == kind).count();
+    let (corrupt, unprotected, unverified) = (count(FrameCheck::Corrupt), count(FrameCheck::Unprotected), count(FrameCheck::Unverified));
+    let verified = count(FrameCheck::Valid) + corrupt;
+
+    if verified == 0 {
+        outln!("    {} frame(s), {} without CRC, {} with an unverifiable (Layer II) CRC", frames.len(), unprotected, unverified);
+        return Ok(());
+    }
+    let health = (verified - corrupt) as f64 * 100.0 / verified as f64;
+    if report == CrcReport::Score {
+        outln!("    Health: {:.2}% ({} of {} verified frame(s) corrupt)", health, corrupt, verified);
+        return Ok(());
+    }
+
+    outln!("    {} frame(s): {} verified, {} corrupt, {} without CRC, {} unverifiable", frames.len(), verified, corrupt, unprotected, unverified);
+    outln!("    |{}|", corruption_map(&checks));
+    outln!("    ('.' = valid, 'X' = corrupt, ' ' = not verified)");
+    let corrupt_frames: Vec<String> = frames
+        .iter()
+        .zip(&checks)
+        .enumerate()
+        .filter(|(_, (_, check))| **check == FrameCheck::Corrupt)
+        .map(|(index, ((pos, _), _))| format!("#{} at 0x{:08X}", index, audio_start + *pos as u64))
+        .collect();
+    for position in corrupt_frames.iter().take(MAX_LISTED) {
+        outln!("    Corrupt frame {}", position);
+    }
+    if corrupt_frames.len() > MAX_LISTED {
+        outln!("    ... and {} more", corrupt_frames.len() - MAX_LISTED);
+    }
+    outln!("    Health: {:.2}%", health);
+    Ok(())
+}
+
+/// Check the CRC of the frame starting at the beginning of `frame`
+fn check_frame(frame: &[u8], header: &MpegFrameHeader) -> FrameCheck {
+    if !header.protected {
+        return FrameCheck::Unprotected;
+    }
+    let protected_bits = match header.layer {
+        | 3 => header.side_info_size() * 8,
+        | 1 => layer1_allocation_bits(header),
+        | _ => return FrameCheck::Unverified,
+    };
+    if frame.len() < 6 + protected_bits.div_ceil(8) {
+        return FrameCheck::Corrupt;
+    }
+
+    let mut crc = crc16(0xFFFF, &frame[2..4], 16);
+    crc = crc16(crc, &frame[6..], protected_bits);
+    if crc == u16::from_be_bytes([frame[4], frame[5]]) {
+        FrameCheck::Valid
+    } else {
+        FrameCheck::Corrupt
+    }
+}
+
+/// Bits of the Layer I bit allocation: 4 per subband and channel, shared above the joint stereo bound
+fn layer1_allocation_bits(header: &MpegFrameHeader) -> usize {
+    let channels = if header.channel_mode == 3 {
+        1
+    } else {
+        2
+    };
+    let bound = if header.channel_mode == 1 {
+        (header.mode_extension as usize + 1) * 4
+    } else {
+        32
+    };
+    4 * (bound * channels + (32 - bound))
+}
+
+/// CRC-16 (polynomial 0x8005, MSB first) over the first `bits` bits of `bytes`
+fn crc16(mut crc: u16, bytes: &[u8], bits: usize) -> u16 {
+    for bit in 0..bits {
+        let data_bit = (bytes[bit / 8] >> (7 - bit % 8)) & 1 == 1;
+        let top_bit = crc & 0x8000 != 0;
+        crc <<= 1;
+        if data_bit != top_bit {
+            crc ^= 0x8005;
+        }
+    }
+    crc
+}
+
+/// One character per slice of frames: 'X' if any frame in it is corrupt, '.' if all verified ones are valid
+fn corruption_map(checks: &[FrameCheck]) -> String {
+    let width = MAP_WIDTH.min(checks.len());
+    (0..width)
+        .map(|column| {
+            let slice = &checks[column * checks.len() / width..(column + 1) * checks.len() / width];
+            if slice.contains(&FrameCheck::Corrupt) {
+                'X'
+            } else if slice.contains(&FrameCheck::Valid) {
+                '.'
+            } else {
+                ' '
+            }
+        })
+        .collect()
+}
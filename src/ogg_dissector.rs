@@ -0,0 +1,124 @@
+use crate::coverage::Coverage;
+use crate::media_dissector::{Capabilities, MediaDissector, Operation, Signature};
+use crate::media_source::MediaSource;
+use crate::media_summary::MediaSummary;
+use crate::ogg_page::OggFile;
+use crate::options::DebugOptions;
+use crate::size_tree::SizeNode;
+use crate::time_format::format_duration;
+
+/// Dissector for Ogg files (Vorbis, Opus, FLAC, Speex)
+///
+/// Walks all pages with their checksums, identifies the codec of each logical stream and shows
+/// its Vorbis comment with the embedded pictures.
+pub struct OggDissector;
+
+impl MediaDissector for OggDissector {
+    fn media_type(&self) -> &'static str {
+        "Ogg"
+    }
+
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+        dissect_ogg_with_options(file, options)
+    }
+
+    fn can_handle(&self, header: &[u8]) -> bool {
+        header.len() >= 5 && &header[0..4] == b"OggS" && header[4] == 0
+    }
+
+    fn name(&self) -> &'static str {
+        "Ogg Dissector"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            extensions: &["ogg", "oga", "opus", "spx", "ogv"],
+            signatures: &[
+                Signature { offset: 0, magic: b"OggS", description: "Ogg page, version 0 at offset 4" },
+            ],
+            operations: &[Operation::Read, Operation::Validate],
+        }
+    }
+
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        let mut coverage = Coverage::new(file.byte_len()?);
+        let ogg = OggFile::read(file)?;
+        // Everything between the gaps is pages
+        let mut pos = 0;
+        for &(offset, size, reason) in &ogg.gaps {
+            coverage.interpret(pos, offset - pos);
+            coverage.skip(offset, size, reason);
+            pos = offset + size;
+        }
+        coverage.interpret(pos, coverage.file_size - pos);
+        Ok(coverage.finish())
+    }
+
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let ogg = OggFile::read(file)?;
+        let mut children = vec![SizeNode::new("page headers and segment tables", ogg.header_bytes)];
+        for stream in &ogg.streams {
+            let label = format!("stream 0x{:08X} ({})", stream.serial, stream.codec);
+            match stream.comment_size {
+                | Some(comment_size) => {
+                    children.push(SizeNode::with_children(label, stream.data_size, vec![SizeNode::new("comment header", comment_size as u64)], "other headers and audio"));
+                }
+                | None => children.push(SizeNode::new(label, stream.data_size)),
+            }
+        }
+        Ok(SizeNode::with_children("file", file_size, children, "outside pages"))
+    }
+
+    fn summarize(&self, file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let mut summary = MediaSummary::new(self.media_type(), self.name());
+        summary.ogg = Some(OggFile::read(file)?);
+        Ok(summary)
+    }
+}
+
+pub fn dissect_ogg_with_options(file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let ogg = OggFile::read(file)?;
+
+    if options.show_header {
+        outln!("\nOgg Container:");
+        outln!("  Pages: {}", ogg.pages);
+        outln!("  Streams: {}", ogg.streams.len());
+        for stream in &ogg.streams {
+            let mut line = format!("  Stream 0x{:08X}: {}", stream.serial, stream.codec);
+            if let Some(channels) = stream.channels {
+                line.push_str(&format!(", {} channel(s)", channels));
+            }
+            if let Some(sample_rate) = stream.sample_rate {
+                line.push_str(&format!(", {} Hz", sample_rate));
+            }
+            if let Some(duration_ms) = stream.duration_ms {
+                line.push_str(&format!(", duration {}", format_duration(duration_ms)));
+            }
+            outln!("{} ({} pages, {} bytes)", line, stream.pages, stream.size);
+        }
+        for (offset, size, reason) in &ogg.gaps {
+            outln!("  WARNING: {} bytes at 0x{:08X}: {}", size, offset, reason);
+        }
+        for problem in &ogg.problems {
+            outln!("  WARNING: {}", problem);
+        }
+    }
+
+    if !options.show_frames {
+        return Ok(());
+    }
+
+    let mut found = false;
+    for stream in &ogg.streams {
+        if let Some(comment) = &stream.comment {
+            outln!("\nVorbis Comment ({} stream 0x{:08X}, {} bytes):", stream.codec, stream.serial, stream.comment_size.unwrap_or_default());
+            out!("{}", comment);
+            found = true;
+        }
+    }
+    if !found {
+        outln!("\nNo metadata (Vorbis comment) present");
+    }
+    Ok(())
+}
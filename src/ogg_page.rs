@@ -0,0 +1,384 @@
+/// Ogg page traversal (RFC 3533)
+///
+/// An Ogg file is a sequence of pages, each with a 27-byte header ("OggS"), a segment table and
+/// up to 255 segments of at most 255 bytes. Packets are split into segments, a segment shorter
+/// than 255 bytes ends a packet, so packets may continue over several pages. Logical streams
+/// are multiplexed by serial number; each starts on a page flagged "beginning of stream" whose
+/// first packet identifies the codec, and the following header packets carry the comments.
+use crate::media_source::MediaSource;
+use crate::vorbis_comment::VorbisComment;
+use serde::Serialize;
+use std::fmt;
+use std::io::SeekFrom;
+
+/// Size of the fixed part of a page header
+const PAGE_HEADER_SIZE: u64 = 27;
+
+/// Header type flags
+const FLAG_CONTINUED: u8 = 0x01;
+const FLAG_BEGINNING_OF_STREAM: u8 = 0x02;
+const FLAG_END_OF_STREAM: u8 = 0x04;
+
+/// Granule position of pages on which no packet ends
+const NO_GRANULE_POSITION: u64 = u64::MAX;
+
+/// Coverage reasons of the bytes outside complete pages
+pub const OUTSIDE_PAGES: &str = "data outside Ogg pages (resync)";
+pub const TRUNCATED_PAGE: &str = "truncated Ogg page";
+
+/// Bytes read at a time when searching for the next page after garbage
+const RESYNC_BUFFER_SIZE: usize = 64 * 1024;
+
+/// CRC-32 with polynomial 0x04C11DB7, not reflected, as used for the page checksums
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &byte| (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize])
+}
+
+/// Codec of a logical stream, identified by its first packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OggCodec {
+    Vorbis,
+    Opus,
+    Flac,
+    Speex,
+    Theora,
+    /// Ogg Skeleton (stream index of the other streams)
+    Skeleton,
+    Unknown,
+}
+
+impl fmt::Display for OggCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            | OggCodec::Vorbis => "Vorbis",
+            | OggCodec::Opus => "Opus",
+            | OggCodec::Flac => "FLAC",
+            | OggCodec::Speex => "Speex",
+            | OggCodec::Theora => "Theora",
+            | OggCodec::Skeleton => "Skeleton",
+            | OggCodec::Unknown => "unknown codec",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Logical stream of an Ogg file
+#[derive(Debug, Clone, Serialize)]
+pub struct OggStream {
+    pub serial: u32,
+    pub codec: OggCodec,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u32>,
+    /// Sample rate of the decoded audio (always 48000 for Opus)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// Duration from the granule position of the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    pub pages: u64,
+    /// Bytes of all pages of the stream, including the page headers
+    pub size: u64,
+    /// Bytes of packet data (without page headers and segment tables)
+    #[serde(skip)]
+    pub data_size: u64,
+    /// Size of the packet holding the comments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<VorbisComment>,
+    /// Whether the stream ends on a page flagged "end of stream"
+    pub complete: bool,
+    /// Header packets, collected until the codec's count is reached
+    #[serde(skip)]
+    headers: Vec<Vec<u8>>,
+    #[serde(skip)]
+    header_count: usize,
+    /// Packet continued on the next page
+    #[serde(skip)]
+    partial: Vec<u8>,
+    #[serde(skip)]
+    last_granule: Option<u64>,
+}
+
+impl OggStream {
+    fn new(serial: u32) -> Self {
+        OggStream {
+            serial,
+            codec: OggCodec::Unknown,
+            channels: None,
+            sample_rate: None,
+            duration_ms: None,
+            pages: 0,
+            size: 0,
+            data_size: 0,
+            comment_size: None,
+            comment: None,
+            complete: false,
+            headers: Vec::new(),
+            header_count: 1,
+            partial: Vec::new(),
+            last_granule: None,
+        }
+    }
+
+    /// Collect the packets of a page until all header packets are complete
+    fn add_packets(&mut self, segment_table: &[u8], data: &[u8], continued: bool) {
+        if !continued {
+            self.partial.clear();
+        }
+        let mut pos = 0;
+        for &lacing in segment_table {
+            if self.headers.len() >= self.header_count {
+                return;
+            }
+            self.partial.extend_from_slice(&data[pos..pos + lacing as usize]);
+            pos += lacing as usize;
+            if lacing < 255 {
+                let packet = std::mem::take(&mut self.partial);
+                if self.headers.is_empty() {
+                    self.identify(&packet);
+                }
+                self.headers.push(packet);
+            }
+        }
+    }
+
+    /// Codec, channels, sample rate and number of header packets from the first packet
+    fn identify(&mut self, packet: &[u8]) {
+        let read_u32_le = |pos: usize| packet.get(pos..pos + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        if packet.starts_with(b"\x01vorbis") {
+            self.codec = OggCodec::Vorbis;
+            self.channels = packet.get(11).map(|&channels| channels as u32);
+            self.sample_rate = read_u32_le(12);
+            self.header_count = 3;
+        } else if packet.starts_with(b"OpusHead") {
+            self.codec = OggCodec::Opus;
+            self.channels = packet.get(9).map(|&channels| channels as u32);
+            self.sample_rate = Some(48000);
+            self.header_count = 2;
+        } else if packet.starts_with(b"\x7FFLAC") {
+            // Mapping header, "fLaC" and the STREAMINFO block; the number of header packets that
+            // follow is 0 if unknown
+            self.codec = OggCodec::Flac;
+            if let Some(info) = packet.get(27..30) {
+                self.sample_rate = Some(((info[0] as u32) << 12) | ((info[1] as u32) << 4) | (info[2] as u32 >> 4));
+                self.channels = Some(((info[2] as u32 >> 1) & 0x07) + 1);
+            }
+            let following = packet.get(7..9).map_or(0, |count| u16::from_be_bytes([count[0], count[1]]) as usize);
+            self.header_count = 1 + following.max(1);
+        } else if packet.starts_with(b"Speex   ") {
+            self.codec = OggCodec::Speex;
+            self.sample_rate = read_u32_le(36);
+            self.channels = read_u32_le(48);
+            self.header_count = 2 + read_u32_le(68).unwrap_or(0).min(16) as usize;
+        } else if packet.starts_with(b"\x80theora") {
+            self.codec = OggCodec::Theora;
+            self.header_count = 3;
+        } else if packet.starts_with(b"fishead\0") {
+            self.codec = OggCodec::Skeleton;
+        }
+    }
+
+    /// Parse the comment header and compute the duration once all pages are read
+    fn finish(&mut self) -> Option<String> {
+        let comment_packet = match self.codec {
+            | OggCodec::Vorbis => self.headers.get(1).and_then(|packet| packet.strip_prefix(b"\x03vorbis")),
+            | OggCodec::Opus => self.headers.get(1).and_then(|packet| packet.strip_prefix(b"OpusTags")),
+            | OggCodec::Speex => self.headers.get(1).map(Vec::as_slice),
+            | OggCodec::Theora => self.headers.get(1).and_then(|packet| packet.strip_prefix(b"\x81theora")),
+            // FLAC metadata blocks: type (7 bits) and 24-bit length; type 4 is the comment
+            | OggCodec::Flac => self.headers.iter().skip(1).find(|packet| packet.first().is_some_and(|&header| header & 0x7F == 4)).and_then(|packet| packet.get(4..)),
+            | OggCodec::Skeleton | OggCodec::Unknown => None,
+        };
+        let mut problem = None;
+        if let Some(packet) = comment_packet {
+            self.comment_size = Some(packet.len());
+            match VorbisComment::parse(packet) {
+                | Ok(comment) => self.comment = Some(comment),
+                | Err(error) => problem = Some(format!("stream 0x{:08X}: {}", self.serial, error)),
+            }
+        }
+        // Pictures of Ogg FLAC are metadata blocks of type 6 rather than comment fields
+        if self.codec == OggCodec::Flac
+            && let Some(comment) = &mut self.comment
+        {
+            for block in self.headers.iter().skip(1).filter(|packet| packet.first().is_some_and(|&header| header & 0x7F == 6)) {
+                match crate::vorbis_comment::FlacPicture::parse(block.get(4..).unwrap_or_default()) {
+                    | Ok(picture) => comment.pictures.push(picture),
+                    | Err(error) => comment.problems.push(format!("picture block: {}", error)),
+                }
+            }
+        }
+
+        self.duration_ms = match (self.codec, self.last_granule, self.sample_rate) {
+            | (OggCodec::Theora | OggCodec::Skeleton | OggCodec::Unknown, _, _) => None,
+            // Opus granules count 48 kHz samples including the pre-skip
+            | (OggCodec::Opus, Some(granule), _) => {
+                let pre_skip = self.headers.first().and_then(|packet| packet.get(10..12)).map_or(0, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as u64);
+                Some(granule.saturating_sub(pre_skip) * 1000 / 48000)
+            }
+            | (_, Some(granule), Some(rate)) if rate > 0 => Some(granule * 1000 / rate as u64),
+            | _ => None,
+        };
+        self.headers.clear();
+        self.partial.clear();
+        problem
+    }
+}
+
+/// Pages and logical streams of an Ogg file
+#[derive(Debug, Clone, Serialize)]
+pub struct OggFile {
+    pub pages: u64,
+    /// Logical streams in the order of their first page
+    pub streams: Vec<OggStream>,
+    /// Checksum errors and streams without their first or last page
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+    /// Byte ranges outside of complete pages (offset, size, reason)
+    #[serde(skip)]
+    pub gaps: Vec<(u64, u64, &'static str)>,
+    /// Total size of the page headers and segment tables
+    #[serde(skip)]
+    pub header_bytes: u64,
+}
+
+impl OggFile {
+    /// Walk all pages, verify their checksums and parse the header packets of each stream
+    pub fn read(file: &mut dyn MediaSource) -> Result<Self, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let mut ogg = OggFile { pages: 0, streams: Vec::new(), problems: Vec::new(), gaps: Vec::new(), header_bytes: 0 };
+        let mut pos = 0;
+        let mut crc_errors = 0;
+        while pos + PAGE_HEADER_SIZE <= file_size {
+            let mut header = [0u8; PAGE_HEADER_SIZE as usize];
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut header)?;
+            if &header[0..4] != b"OggS" || header[4] != 0 {
+                // Resynchronize at the next capture pattern
+                let next = find_capture_pattern(file, pos + 1, file_size)?.unwrap_or(file_size);
+                ogg.gaps.push((pos, next - pos, OUTSIDE_PAGES));
+                pos = next;
+                continue;
+            }
+
+            let segment_count = header[26] as usize;
+            let mut segment_table = vec![0u8; segment_count];
+            let page_size = match file.read_exact(&mut segment_table) {
+                | Ok(()) => PAGE_HEADER_SIZE + segment_count as u64 + segment_table.iter().map(|&lacing| lacing as u64).sum::<u64>(),
+                | Err(_) => PAGE_HEADER_SIZE + segment_count as u64,
+            };
+            if pos + page_size > file_size {
+                ogg.gaps.push((pos, file_size - pos, TRUNCATED_PAGE));
+                pos = file_size;
+                break;
+            }
+            let mut data = vec![0u8; (page_size - PAGE_HEADER_SIZE) as usize - segment_count];
+            file.read_exact(&mut data)?;
+
+            // The checksum covers the whole page with the checksum field set to zero
+            let stored_crc = u32::from_le_bytes([header[22], header[23], header[24], header[25]]);
+            header[22..26].fill(0);
+            let crc = crc32(crc32(crc32(0, &header), &segment_table), &data);
+            if crc != stored_crc {
+                crc_errors += 1;
+                ogg.problems.push(format!("page {} at 0x{:08X}: checksum 0x{:08X}, computed 0x{:08X}", ogg.pages, pos, stored_crc, crc));
+            }
+
+            let flags = header[5];
+            let granule = u64::from_le_bytes(header[6..14].try_into().unwrap_or_default());
+            let serial = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+            let index = match ogg.streams.iter().position(|stream| stream.serial == serial) {
+                | Some(index) => {
+                    if flags & FLAG_BEGINNING_OF_STREAM != 0 {
+                        ogg.problems.push(format!("page at 0x{:08X} starts stream 0x{:08X} a second time", pos, serial));
+                    }
+                    index
+                }
+                | None => {
+                    if flags & FLAG_BEGINNING_OF_STREAM == 0 {
+                        ogg.problems.push(format!("stream 0x{:08X} has no beginning-of-stream page (first page at 0x{:08X})", serial, pos));
+                    }
+                    ogg.streams.push(OggStream::new(serial));
+                    ogg.streams.len() - 1
+                }
+            };
+            let stream = &mut ogg.streams[index];
+            stream.pages += 1;
+            stream.size += page_size;
+            stream.data_size += data.len() as u64;
+            if granule != NO_GRANULE_POSITION {
+                stream.last_granule = Some(granule);
+            }
+            stream.complete |= flags & FLAG_END_OF_STREAM != 0;
+            stream.add_packets(&segment_table, &data, flags & FLAG_CONTINUED != 0);
+
+            ogg.pages += 1;
+            ogg.header_bytes += PAGE_HEADER_SIZE + segment_count as u64;
+            pos += page_size;
+        }
+        if pos < file_size {
+            ogg.gaps.push((pos, file_size - pos, OUTSIDE_PAGES));
+        }
+        if crc_errors > 0 {
+            ogg.problems.push(format!("{} of {} pages have a wrong checksum", crc_errors, ogg.pages));
+        }
+        for stream in &mut ogg.streams {
+            if let Some(problem) = stream.finish() {
+                ogg.problems.push(problem);
+            }
+            if !stream.complete {
+                ogg.problems.push(format!("stream 0x{:08X} ({}) has no end-of-stream page; the file may be truncated", stream.serial, stream.codec));
+            }
+        }
+        Ok(ogg)
+    }
+
+    /// The first audio stream with a comment header
+    pub fn comment(&self) -> Option<(&OggStream, &VorbisComment)> {
+        self.streams.iter().find_map(|stream| stream.comment.as_ref().map(|comment| (stream, comment)))
+    }
+}
+
+/// Offset of the next "OggS" at or after `start`
+fn find_capture_pattern(file: &mut dyn MediaSource, start: u64, file_size: u64) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let mut buffer = vec![0u8; RESYNC_BUFFER_SIZE];
+    let mut pos = start;
+    while pos < file_size {
+        file.seek(SeekFrom::Start(pos))?;
+        let length = (file_size - pos).min(RESYNC_BUFFER_SIZE as u64) as usize;
+        file.read_exact(&mut buffer[..length])?;
+        if let Some(found) = buffer[..length].windows(4).position(|window| window == b"OggS") {
+            return Ok(Some(pos + found as u64));
+        }
+        if length < 4 {
+            break;
+        }
+        // Keep three bytes so a pattern across the buffer boundary is found
+        pos += length as u64 - 3;
+    }
+    Ok(None)
+}
@@ -0,0 +1,447 @@
+/// Options of analyses and exports that callers of the library pass in
+///
+/// The command line maps its flags onto these types; library users build them directly, e.g.
+/// `DebugOptions::from_flags(true, false, false).with_sizes(true)`.
+use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_languages::language_matches;
+use crate::id3v2_tools::DEFAULT_MAX_EMBEDDING_DEPTH;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::cell::Cell;
+use std::path::PathBuf;
+
+/// Lyrics export formats
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LyricsFormat {
+    /// LRC lines with [mm:ss.xx] time stamps
+    Lrc,
+    /// Plain text without time stamps
+    Text,
+}
+
+/// Lyrics frames that can be converted into each other
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LyricsFrameName {
+    /// Synchronised lyrics
+    Sylt,
+    /// Unsynchronised lyrics
+    Uslt,
+}
+
+/// Which symbolic links directory walks follow
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum FollowLinks {
+    /// Leave symbolic links out
+    #[default]
+    Never,
+    /// Follow symbolic links to files, not to directories
+    Files,
+    /// Follow symbolic links to files and directories (each directory is walked once)
+    All,
+}
+
+/// Options for controlling debug output
+#[derive(Debug, Clone, Default)]
+pub struct DebugOptions {
+    pub show_header: bool,
+    pub show_frames: bool,
+    /// Frame IDs (ID3v2) or box types (ISO BMFF) to list; all if empty
+    pub frame_ids: Vec<String>,
+    /// Frame ID whose raw bytes should be explained field by field
+    pub explain_frame: Option<String>,
+    /// Language selected for COMM, USLT and SYLT frames (all languages if not set)
+    pub language: Option<String>,
+    /// Member of a ZIP bundle to dissect instead of the archive itself
+    pub inner: Option<String>,
+    /// Sidecar file to write after the dissection
+    pub sidecar: Option<SidecarOptions>,
+    /// Check the links of URL frames over the network
+    pub check_urls: bool,
+    /// Verify the CRCs of the MPEG audio frames
+    pub verify_crc: Option<CrcReport>,
+    /// Analyze the bitrates of the MPEG audio frames
+    pub bitrates: bool,
+    /// Draw the chapter timeline
+    pub timeline: Option<TimelineFormat>,
+    /// Print the size breakdown of the file
+    pub sizes: bool,
+    /// List the seek points of MP4 tracks
+    pub seek_points: bool,
+    /// Map the byte ranges of MP4 samples over media time in intervals of this many seconds
+    pub chunk_map: Option<f64>,
+    /// Deepest nesting of ISO BMFF container boxes that is listed (8 if not set)
+    pub box_depth: Option<usize>,
+    /// Directory to write the objects of GEOB frames into
+    pub extract_geob: Option<PathBuf>,
+    /// Evidence report to write in forensic mode
+    pub forensic: Option<ForensicOptions>,
+    /// Print the structured report in this format instead of the dissection
+    pub report: Option<ReportFormat>,
+    /// Files dissected at the same time when several are given (one per CPU if not set)
+    pub jobs: Option<usize>,
+    /// Decoding, display and batch options the dissection runs with
+    pub common: CommonOptions,
+}
+
+impl DebugOptions {
+    pub fn from_flags(header: bool, frames: bool, all: bool) -> Self {
+        // If no flags specified, default to showing everything; --all shows everything regardless of other flags
+        if (!header && !frames) || all {
+            return DebugOptions { show_header: true, show_frames: true, ..Default::default() };
+        }
+
+        // Otherwise, use the specific flags
+        DebugOptions { show_header: header, show_frames: frames, ..Default::default() }
+    }
+
+    /// Only list the frames or boxes with one of the given IDs
+    pub fn with_frame_ids(mut self, frame_ids: Vec<String>) -> Self {
+        self.frame_ids = frame_ids;
+        self
+    }
+
+    /// Explain the raw bytes of frames with the given ID
+    pub fn with_explain_frame(mut self, frame_id: Option<String>) -> Self {
+        self.explain_frame = frame_id;
+        self
+    }
+
+    /// Only show language-dependent frames in the given language
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Dissect a member of a ZIP bundle instead of the archive
+    pub fn with_inner(mut self, inner: Option<String>) -> Self {
+        self.inner = inner;
+        self
+    }
+
+    /// Write a sidecar file with the summary of the dissected file
+    pub fn with_sidecar(mut self, sidecar: Option<SidecarOptions>) -> Self {
+        self.sidecar = sidecar;
+        self
+    }
+
+    /// Run the network, CRC and bitrate checks after the dissection
+    pub fn with_checks(mut self, check_urls: bool, verify_crc: Option<CrcReport>, bitrates: bool) -> Self {
+        self.check_urls = check_urls;
+        self.verify_crc = verify_crc;
+        self.bitrates = bitrates;
+        self
+    }
+
+    /// Draw the chapter timeline after the dissection
+    pub fn with_timeline(mut self, timeline: Option<TimelineFormat>) -> Self {
+        self.timeline = timeline;
+        self
+    }
+
+    /// Print the size breakdown of the file
+    pub fn with_sizes(mut self, sizes: bool) -> Self {
+        self.sizes = sizes;
+        self
+    }
+
+    /// List the seek points of MP4 tracks after the dissection
+    pub fn with_seek_points(mut self, seek_points: bool) -> Self {
+        self.seek_points = seek_points;
+        self
+    }
+
+    /// Map the byte ranges of MP4 samples over media time after the dissection
+    pub fn with_chunk_map(mut self, interval_seconds: Option<f64>) -> Self {
+        self.chunk_map = interval_seconds;
+        self
+    }
+
+    /// Limit the nesting of the listed ISO BMFF boxes
+    pub fn with_box_depth(mut self, box_depth: Option<usize>) -> Self {
+        self.box_depth = box_depth;
+        self
+    }
+
+    /// Write the objects of GEOB frames into a directory after the dissection
+    pub fn with_extract_geob(mut self, dir: Option<PathBuf>) -> Self {
+        self.extract_geob = dir;
+        self
+    }
+
+    /// Hash the file before and after the dissection and write a signed evidence report
+    pub fn with_forensic(mut self, forensic: Option<ForensicOptions>) -> Self {
+        self.forensic = forensic;
+        self
+    }
+
+    /// Print the structured report instead of the dissection
+    pub fn with_report(mut self, report: Option<ReportFormat>) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// Dissect up to `jobs` files at the same time
+    pub fn with_jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Decode, show and count the files with these options
+    pub fn with_common(mut self, common: CommonOptions) -> Self {
+        self.common = common;
+        self
+    }
+
+    /// Whether frames or boxes with this ID are listed (`--frame-id`)
+    pub fn lists_id(&self, id: &str) -> bool {
+        self.frame_ids.is_empty() || self.frame_ids.iter().any(|selected| selected.eq_ignore_ascii_case(id.trim_end()))
+    }
+
+    /// Whether a frame is shown, i.e. it has no language or the selected one
+    pub fn shows_frame(&self, frame: &Id3v2Frame) -> bool {
+        match (&self.language, frame.language()) {
+            | (Some(selected), Some(language)) => language_matches(language, selected),
+            | _ => true,
+        }
+    }
+}
+
+/// Options every command takes (the global flags of the command line)
+///
+/// They are used deep inside the parsers and formatters, so a call does not pass them down but
+/// makes them current for its thread with `apply`; threads the call starts apply them again.
+/// The functions that take `DebugOptions` apply `DebugOptions::common` themselves; around other
+/// calls (e.g. `analyze_file`), callers apply the options they want, e.g.
+/// `CommonOptions { lenient: true, ..Default::default() }.apply(|| analyze_file(path))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommonOptions {
+    /// Format of all times and durations
+    pub time_format: TimeFormat,
+    /// Shorten displayed text values to this many characters (not shortened if not set or 0)
+    pub max_text_width: Option<usize>,
+    /// Which part of a shortened value is replaced by the ellipsis
+    pub ellipsis: EllipsisStyle,
+    /// Deepest nesting of CHAP/CTOC frames and CTOC references that is dissected
+    pub max_frame_depth: usize,
+    /// Parse de-facto experimental frames like their standard counterparts
+    pub lenient: bool,
+    /// Legacy code page for text that frames declare as ISO-8859-1
+    pub assume_encoding: Option<AssumedEncoding>,
+    /// Normalize all decoded text to NFC
+    pub nfc: bool,
+    /// Progress event stream on stderr
+    pub events: Option<EventsFormat>,
+    /// Let batch runs succeed although files could not be read
+    pub continue_on_error: bool,
+    /// Parse files in worker processes
+    pub sandbox: bool,
+}
+
+impl Default for CommonOptions {
+    fn default() -> Self {
+        CommonOptions {
+            time_format: TimeFormat::Hms,
+            max_text_width: None,
+            ellipsis: EllipsisStyle::End,
+            max_frame_depth: DEFAULT_MAX_EMBEDDING_DEPTH,
+            lenient: false,
+            assume_encoding: None,
+            nfc: false,
+            events: None,
+            continue_on_error: false,
+            sandbox: false,
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_OPTIONS: Cell<CommonOptions> = Cell::new(CommonOptions::default());
+}
+
+impl CommonOptions {
+    /// Options of the call running on this thread (the defaults outside of `apply`)
+    pub fn current() -> Self {
+        CURRENT_OPTIONS.with(Cell::get)
+    }
+
+    /// Run `f` with these options, the previous ones are current again afterwards
+    pub fn apply<T>(self, f: impl FnOnce() -> T) -> T {
+        // Restored on drop, so a panic in `f` does not leave the options behind
+        struct Restore(CommonOptions);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_OPTIONS.with(|current| current.set(self.0));
+            }
+        }
+        let _restore = Restore(CURRENT_OPTIONS.with(|current| current.replace(self)));
+        f()
+    }
+}
+
+/// Options of the commands that rewrite tags
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Keep the modification time of rewritten files
+    pub preserve_mtime: bool,
+    /// Write the frames in the recommended order
+    pub canonical_order: bool,
+    /// Store all text NFC-normalized
+    pub store_nfc: bool,
+    /// Write even if the checks of `id3v2_write_safety` fail
+    pub force: bool,
+}
+
+/// Serialization format for sidecar files
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SidecarFormat {
+    Json,
+    Yaml,
+    Xml,
+}
+
+impl SidecarFormat {
+    /// File extension used for sidecar files of this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            | SidecarFormat::Json => "json",
+            | SidecarFormat::Yaml => "yaml",
+            | SidecarFormat::Xml => "xml",
+        }
+    }
+}
+
+/// Rendering of the structured dissection report
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    /// Indented tree with colored names and diagnostics
+    Text,
+    Json,
+}
+
+/// Options for writing sidecar files alongside analyzed media files
+#[derive(Debug, Clone)]
+pub struct SidecarOptions {
+    pub format: SidecarFormat,
+    /// Mirror directory for sidecar files (next to the media file if not set)
+    pub dir: Option<PathBuf>,
+}
+
+/// Evidence report of forensic mode
+#[derive(Debug, Clone)]
+pub struct ForensicOptions {
+    /// File to write the signed JSON report to
+    pub report: PathBuf,
+    /// File whose bytes are the HMAC key
+    pub key: PathBuf,
+}
+
+/// Output format of the chapter timeline
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TimelineFormat {
+    /// Bar of characters in the terminal
+    Ascii,
+    /// HTML page next to the file (<file>.timeline.html)
+    Html,
+}
+
+/// Chapter export formats
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ChapterFormat {
+    /// CUE sheet with one track per chapter
+    Cue,
+    /// ffmpeg metadata file (;FFMETADATA1) with [CHAPTER] sections
+    Ffmetadata,
+    /// WebVTT chapter track
+    Webvtt,
+    /// Podlove Simple Chapters as JSON
+    Podlove,
+}
+
+/// Where `mp4-chapters` writes the chapters
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Mp4ChapterStyle {
+    /// Nero chapter list (moov/udta/chpl)
+    Chpl,
+    /// QuickTime chapter text track referenced through tref/chap
+    Track,
+    /// Both, for the widest player support
+    Both,
+}
+
+/// Output of the MPEG CRC check
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum CrcReport {
+    /// Counts, a map of corrupt frames, their positions and the health score
+    Map,
+    /// Health score only (percentage of verified frames with a valid CRC)
+    Score,
+}
+
+/// Formats for times and durations
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// Milliseconds (3723450)
+    Ms,
+    /// Hours, minutes, seconds and milliseconds (01:02:03.450)
+    Hms,
+    /// ISO 8601 duration (PT1H2M3.45S)
+    Iso8601,
+    /// Seconds with millisecond precision (3723.450)
+    Seconds,
+}
+
+/// Where shortened text values are cut (`--ellipsis`)
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EllipsisStyle {
+    /// Keep the start of the value ("A very long comm…")
+    #[default]
+    End,
+    /// Keep the start and the end ("A very lo…comment")
+    Middle,
+}
+
+/// Legacy code pages for text that ID3v2 frames declare as ISO-8859-1 (`--assume-encoding`)
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssumedEncoding {
+    /// Windows-1252 (Western European, with curly quotes, dashes and the euro sign)
+    Cp1252,
+    /// Windows-1251 (Cyrillic)
+    Cp1251,
+    /// Shift_JIS (Japanese)
+    #[value(name = "shift_jis", alias = "shift-jis")]
+    #[serde(rename = "shift_jis", alias = "shift-jis")]
+    ShiftJis,
+}
+
+/// Formats of the progress event stream (`--events`)
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum EventsFormat {
+    /// One JSON object per line on stderr
+    Jsonl,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id3v2_tools::lenient;
+
+    #[test]
+    fn apply_makes_the_options_current_for_the_call_only() {
+        assert!(!lenient());
+        let (inside, nested) = CommonOptions { lenient: true, ..Default::default() }.apply(|| (lenient(), CommonOptions::default().apply(lenient)));
+        assert!(inside);
+        assert!(!nested);
+        assert!(!lenient());
+        assert_eq!(CommonOptions::current(), CommonOptions::default());
+    }
+
+    #[test]
+    fn apply_restores_the_options_after_a_panic() {
+        let result = std::panic::catch_unwind(|| CommonOptions { max_frame_depth: 1, ..Default::default() }.apply(|| panic!("parser bug")));
+        assert!(result.is_err());
+        assert_eq!(CommonOptions::current().max_frame_depth, DEFAULT_MAX_EMBEDDING_DEPTH);
+    }
+}
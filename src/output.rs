@@ -0,0 +1,143 @@
+/// Output of an analysis, collected per file and written in one piece
+///
+/// Dissectors and helpers print with `out!`/`outln!` and pass `output::writer()` where a
+/// `Write` is needed. Inside `buffered` everything goes into a buffer of the current thread
+/// that is written to stdout under a single lock when the analysis ends, so the output of
+/// files analyzed in parallel never interleaves. Outside of it output goes to stdout directly.
+/// Write errors (e.g. a closed pipe) end the output silently instead of panicking. Library users
+/// that want the text instead of stdout run the analysis inside `captured`.
+use std::cell::RefCell;
+use std::fmt;
+use std::io::Write;
+
+thread_local! {
+    static BUFFER: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+}
+
+/// Like `print!`, but into the output of the current analysis
+#[macro_export]
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::output::write_fmt(format_args!($($arg)*))
+    };
+}
+
+/// Like `println!`, but into the output of the current analysis
+#[macro_export]
+macro_rules! outln {
+    () => {
+        $crate::output::write_fmt(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::write_fmt(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// Write formatted text to the buffer of the current analysis, or to stdout outside of one
+pub fn write_fmt(args: fmt::Arguments) {
+    let _ = writer().write_fmt(args);
+}
+
+/// Writer for the output of the current analysis
+pub fn writer() -> Output {
+    Output
+}
+
+/// `Write` handle for the output of the current analysis
+pub struct Output;
+
+impl Write for Output {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        BUFFER.with_borrow_mut(|buffer| match buffer {
+            | Some(buffer) => buffer.write(data),
+            | None => std::io::stdout().lock().write(data),
+        })
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        BUFFER.with_borrow_mut(|buffer| match buffer {
+            | Some(_) => Ok(()),
+            | None => std::io::stdout().lock().flush(),
+        })
+    }
+}
+
+/// Run an analysis with its output collected and write it to stdout atomically afterwards
+///
+/// The output is written even if the analysis fails or panics, so everything up to the error is
+/// kept. Nested calls add to the outer buffer.
+pub fn buffered<T>(analysis: impl FnOnce() -> T) -> T {
+    if BUFFER.with_borrow(Option::is_some) {
+        return analysis();
+    }
+
+    BUFFER.set(Some(Vec::new()));
+    let _flush = FlushBuffer;
+    analysis()
+}
+
+/// Run an analysis with its output collected and return the output instead of writing it to stdout
+pub fn captured<T>(analysis: impl FnOnce() -> T) -> (T, String) {
+    let mut restore = RestoreBuffer(Some(BUFFER.replace(Some(Vec::new()))));
+    let result = analysis();
+    let buffer = restore.restore();
+    (result, String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Writes the buffer of `buffered` to stdout and ends it when dropped, also when the analysis panics
+struct FlushBuffer;
+
+impl Drop for FlushBuffer {
+    fn drop(&mut self) {
+        let buffer = BUFFER.take().unwrap_or_default();
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(&buffer);
+        let _ = stdout.flush();
+    }
+}
+
+/// Puts back the buffer that was current before `captured`, at the latest when dropped
+struct RestoreBuffer(Option<Option<Vec<u8>>>);
+
+impl RestoreBuffer {
+    /// Restore the outer buffer and return the captured output
+    fn restore(&mut self) -> Vec<u8> {
+        match self.0.take() {
+            | Some(outer) => BUFFER.replace(outer).unwrap_or_default(),
+            | None => Vec::new(),
+        }
+    }
+}
+
+impl Drop for RestoreBuffer {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_panic_inside_captured_restores_the_outer_capture() {
+        let ((), text) = captured(|| {
+            outln!("before");
+            let result = std::panic::catch_unwind(|| captured(|| {
+                outln!("lost");
+                panic!("parser bug")
+            }));
+            assert!(result.is_err());
+            outln!("after");
+        });
+        assert_eq!(text, "before\nafter\n");
+        assert!(BUFFER.with_borrow(Option::is_none));
+    }
+
+    #[test]
+    fn a_panic_inside_buffered_ends_the_buffer() {
+        let result = std::panic::catch_unwind(|| buffered(|| panic!("parser bug")));
+        assert!(result.is_err());
+        assert!(BUFFER.with_borrow(Option::is_none));
+    }
+}
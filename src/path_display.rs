@@ -0,0 +1,106 @@
+/// Robust handling of odd file names
+///
+/// Paths stay `Path`/`OsStr` until they are printed. Printing escapes what would garble the
+/// terminal or a report: control characters (a newline or an escape sequence in a file name)
+/// and bytes that are not valid UTF-8 are shown as `\n`, `\t` or `\xNN`. On Windows, long paths
+/// get the `\\?\` prefix for file system calls, which is hidden again when they are printed.
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Prefix of Windows extended-length paths
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+/// Printable form of a path, see `display_path`
+pub struct PathDisplay<'a>(&'a Path);
+
+/// Printable form of `path` with control characters and invalid UTF-8 escaped
+pub fn display_path(path: &Path) -> PathDisplay<'_> {
+    PathDisplay(path)
+}
+
+impl fmt::Display for PathDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = os_str_escaped(self.0.as_os_str());
+        f.write_str(text.strip_prefix(VERBATIM_PREFIX).unwrap_or(&text))
+    }
+}
+
+/// Escape control characters and invalid UTF-8 of a file name or path
+fn os_str_escaped(name: &OsStr) -> String {
+    let mut escaped = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                | '\n' => escaped.push_str("\\n"),
+                | '\r' => escaped.push_str("\\r"),
+                | '\t' => escaped.push_str("\\t"),
+                | c if c.is_control() => escaped.push_str(&format!("\\u{{{:04X}}}", c as u32)),
+                | c => escaped.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    escaped
+}
+
+/// Form of `path` that file system calls accept regardless of its length
+///
+/// Windows limits paths to 260 characters unless they are absolute and carry the `\\?\`
+/// prefix; other platforms have no such limit and get the path unchanged.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    if cfg!(windows)
+        && path.as_os_str().len() >= 260
+        && !path.as_os_str().as_encoded_bytes().starts_with(VERBATIM_PREFIX.as_bytes())
+        && let Ok(absolute) = std::path::absolute(path)
+    {
+        let mut verbatim = OsStr::new(VERBATIM_PREFIX).to_os_string();
+        verbatim.push(absolute.as_os_str());
+        return Cow::Owned(verbatim.into());
+    }
+    Cow::Borrowed(path)
+}
+
+/// File name for a name stored in a media file: its last path component, without characters that
+/// are invalid on common file systems; `None` if nothing usable is left
+pub fn safe_filename(filename: &str) -> Option<String> {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || "<>:\"|?*".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name.trim().trim_end_matches('.');
+    if name.is_empty() {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// `dir/name`, or `dir/name-2.ext`, `dir/name-3.ext`, ... if that file already exists
+pub fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        | Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        | _ => (name, String::new()),
+    };
+    let mut number = 2;
+    loop {
+        let path = dir.join(format!("{}-{}{}", stem, number, extension));
+        if !path.exists() {
+            return path;
+        }
+        number += 1;
+    }
+}
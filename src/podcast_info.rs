@@ -0,0 +1,144 @@
+/// Podcast fields of ID3v2 tags and MP4 metadata
+///
+/// iTunes marks podcast episodes with a podcast flag, the GUID of the episode and the URL of its
+/// feed: in ID3v2 tags with the non-standard PCST, TGID and WFED frames, in MP4 files with the
+/// `pcst`, `egid` and `purl` items of the `ilst` box. Both are read into the same model and
+/// checked the same way, so the fields a podcast host wrote can be verified in either format.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_url_validation::validate_url;
+use crate::isobmff_box::{find_path, read_boxes, read_children, read_payload};
+use crate::media_source::MediaSource;
+use serde::Serialize;
+
+/// Podcast flag, episode GUID and feed URL of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct PodcastInfo {
+    /// The file is marked as a podcast episode (PCST frame, `pcst` item)
+    pub podcast: bool,
+    /// Episode GUID as in the feed (TGID frame, `egid` item)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episode_guid: Option<String>,
+    /// URL of the podcast feed (WFED frame, `purl` item)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<String>,
+    /// Missing or inconsistent podcast fields
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<Finding>,
+}
+
+/// Names of the podcast fields in a format, for the findings
+struct FieldNames {
+    flag: &'static str,
+    guid: &'static str,
+    feed: &'static str,
+}
+
+impl PodcastInfo {
+    /// Podcast fields of the top-level frames of an ID3v2 tag; `None` if the tag has none of them
+    pub fn from_frames(frames: &[Id3v2Frame]) -> Option<Self> {
+        let text = |id: &str| frames.iter().find(|frame| frame.id == id).and_then(|frame| frame.get_text().or(frame.get_url())).map(clean);
+        let podcast = frames.iter().any(|frame| frame.id == "PCST");
+        Self::checked(podcast, text("TGID"), text("WFED"), FieldNames { flag: "PCST", guid: "TGID", feed: "WFED" })
+    }
+
+    /// Podcast items of the `moov/udta/meta/ilst` box of an MP4 file; `None` if it has none of them
+    pub fn from_isobmff(file: &mut dyn MediaSource) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let Some(moov) = read_boxes(file, 0, file_size)?.into_iter().find(|box_header| box_header.box_type == "moov") else {
+            return Ok(None);
+        };
+        let Some(ilst) = find_path(file, &moov, &["udta", "meta", "ilst"])? else {
+            return Ok(None);
+        };
+
+        let mut podcast = false;
+        let mut guid = None;
+        let mut feed = None;
+        for item in read_children(file, &ilst)? {
+            if !matches!(item.box_type.as_str(), "pcst" | "egid" | "purl") {
+                continue;
+            }
+            let Some(data) = read_children(file, &item)?.into_iter().find(|child| child.box_type == "data") else {
+                continue;
+            };
+            // Type indicator and locale precede the value
+            let payload = read_payload(file, &data)?;
+            let value = payload.get(8..).unwrap_or_default();
+            match item.box_type.as_str() {
+                | "pcst" => podcast = value.iter().any(|&byte| byte != 0),
+                | "egid" => guid = Some(clean(&String::from_utf8_lossy(value))),
+                | _ => feed = Some(clean(&String::from_utf8_lossy(value))),
+            }
+        }
+        Ok(Self::checked(podcast, guid, feed, FieldNames { flag: "pcst", guid: "egid", feed: "purl" }))
+    }
+
+    fn checked(podcast: bool, episode_guid: Option<String>, feed_url: Option<String>, names: FieldNames) -> Option<Self> {
+        if !podcast && episode_guid.is_none() && feed_url.is_none() {
+            return None;
+        }
+        let mut findings = Vec::new();
+        if !podcast {
+            findings.push(Finding::new(
+                Severity::Warning,
+                names.flag,
+                format!("{} without the podcast flag; apps do not list the file as a podcast episode", present_fields(&episode_guid, &feed_url, &names)),
+            ));
+        }
+        match &episode_guid {
+            | Some(guid) if guid.is_empty() => findings.push(Finding::new(Severity::Warning, names.guid, "empty episode GUID")),
+            | Some(_) => {}
+            | None if podcast => findings.push(Finding::new(Severity::Info, names.guid, "podcast episode without an episode GUID")),
+            | None => {}
+        }
+        match &feed_url {
+            | Some(url) => findings.extend(validate_url(url, names.feed)),
+            | None if podcast => findings.push(Finding::new(Severity::Info, names.feed, "podcast episode without a feed URL")),
+            | None => {}
+        }
+        Some(PodcastInfo { podcast, episode_guid, feed_url, findings })
+    }
+}
+
+/// "episode GUID (TGID) and feed URL (WFED)" or one of them
+fn present_fields(episode_guid: &Option<String>, feed_url: &Option<String>, names: &FieldNames) -> String {
+    let mut fields = Vec::new();
+    if episode_guid.is_some() {
+        fields.push(format!("episode GUID ({})", names.guid));
+    }
+    if feed_url.is_some() {
+        fields.push(format!("feed URL ({})", names.feed));
+    }
+    fields.join(" and ")
+}
+
+/// Value without the terminating zero bytes some writers leave
+fn clean(value: &str) -> String {
+    value.trim_end_matches('\0').to_string()
+}
+
+/// Print the podcast fields and their findings, if the file has any
+pub fn print_podcast(info: Option<&PodcastInfo>) {
+    let Some(info) = info else {
+        return;
+    };
+    outln!("\nPodcast:");
+    outln!(
+        "  Podcast flag: {}",
+        if info.podcast {
+            "set"
+        } else {
+            "not set"
+        }
+    );
+    if let Some(guid) = &info.episode_guid {
+        outln!("  Episode GUID: {}", guid);
+    }
+    if let Some(url) = &info.feed_url {
+        outln!("  Feed URL: {}", url);
+    }
+    for finding in &info.findings {
+        outln!("    {}", finding);
+    }
+}
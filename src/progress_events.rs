@@ -0,0 +1,103 @@
+/// Machine-readable progress events on stderr (`--events jsonl`)
+///
+/// Programs that wrap the tool follow its progress from these events instead of scraping the
+/// colored text on stdout. Every event is one JSON object on a line of its own, with the event
+/// name in `event`:
+///
+/// `file-start` (with the position in the batch), `tag-found`, `frame-count`, `warning` (for
+/// every warning or error found) and `file-done` (with the result and the time taken).
+use crate::finding::{Finding, Severity};
+use crate::health_score::{IssueCategory, collect_issues};
+use crate::media_summary::MediaSummary;
+use crate::options::{CommonOptions, EventsFormat};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+
+/// Whether events are written
+pub fn enabled() -> bool {
+    CommonOptions::current().events.is_some()
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+enum Event<'a> {
+    FileStart {
+        path: &'a str,
+        /// Position of the file in the batch, starting at 1
+        index: usize,
+        total: usize,
+    },
+    TagFound {
+        path: &'a str,
+        tag_type: &'a str,
+        version: &'a str,
+        size: u64,
+    },
+    FrameCount {
+        path: &'a str,
+        /// Top-level frames, not counting the sub-frames of CHAP/CTOC
+        count: usize,
+    },
+    Warning {
+        path: &'a str,
+        category: IssueCategory,
+        #[serde(flatten)]
+        finding: &'a Finding,
+    },
+    FileDone {
+        path: &'a str,
+        ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        elapsed_ms: u64,
+    },
+}
+
+fn emit(event: &Event) {
+    match CommonOptions::current().events {
+        | Some(EventsFormat::Jsonl) => {
+            if let Ok(line) = serde_json::to_string(event) {
+                eprintln!("{}", line);
+            }
+        }
+        | None => {}
+    }
+}
+
+/// A file being processed, from `file-start` to `file-done`
+pub struct FileProgress {
+    path: String,
+    started: Instant,
+}
+
+impl FileProgress {
+    /// Emit `file-start` for file `index` (starting at 1) of `total`
+    pub fn start(path: &Path, index: usize, total: usize) -> Self {
+        let path = path.to_string_lossy().into_owned();
+        emit(&Event::FileStart { path: &path, index, total });
+        FileProgress { path, started: Instant::now() }
+    }
+
+    /// Emit `tag-found`, `frame-count` and the `warning` events of a summary
+    pub fn summary(&self, summary: &MediaSummary) {
+        if !enabled() {
+            return;
+        }
+        let path = self.path.as_str();
+        if let Some(tag) = &summary.tag {
+            emit(&Event::TagFound { path, tag_type: &tag.tag_type, version: &tag.version, size: tag.size });
+            emit(&Event::FrameCount { path, count: tag.frames.len() });
+        }
+        for issue in collect_issues(summary).iter().filter(|issue| issue.finding.severity > Severity::Info) {
+            emit(&Event::Warning { path, category: issue.category, finding: &issue.finding });
+        }
+    }
+
+    /// Emit `file-done` with the outcome of the file
+    pub fn done<T>(self, result: &Result<T, Box<dyn std::error::Error>>) {
+        let error = result.as_ref().err().map(|e| e.to_string());
+        let elapsed_ms = self.started.elapsed().as_millis() as u64;
+        emit(&Event::FileDone { path: &self.path, ok: error.is_none(), error, elapsed_ms });
+    }
+}
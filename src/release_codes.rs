@@ -0,0 +1,132 @@
+/// Structural checks of recording and release codes
+///
+/// Labels deliver ISRCs (International Standard Recording Code, TSRC frame) and UPC/EAN
+/// barcodes of the release (BARCODE fields) with every file, and stores reject deliveries whose
+/// codes are malformed. The registries themselves are not online, but their structure is fixed:
+/// an ISRC starts with the country code of the issuing agency and a three-character registrant
+/// code, a barcode ends with a GS1 check digit.
+///
+/// ID3v2 tags hold the codes in TSRC and in TXXX frames (ISRC, BARCODE, UPC, EAN), MP4 files in
+/// freeform items with the same names (`----:com.apple.iTunes:BARCODE`).
+use crate::id3v2_known_user_text::{KnownUserText, interpret_user_text};
+use crate::isobmff_box::{find_path, read_boxes, read_children, read_payload};
+use crate::media_source::MediaSource;
+
+/// Names of the freeform items that hold release codes
+const CODE_ITEM_NAMES: &[&str] = &["ISRC", "BARCODE", "UPC", "EAN"];
+
+/// Country prefixes that ISRC agencies issue besides the ISO 3166-1 codes: UK for the United
+/// Kingdom, QM to QZ for the United States, ZZ for the international ISRC agency and XK for Kosovo
+const EXTRA_ISRC_PREFIXES: &[&str] = &["UK", "QM", "QN", "QO", "QP", "QQ", "QR", "QS", "QT", "QU", "QV", "QW", "QX", "QY", "QZ", "ZZ", "XK"];
+
+/// ISO 3166-1 alpha-2 country codes
+const COUNTRY_CODES: &str = "AD AE AF AG AI AL AM AO AQ AR AS AT AU AW AX AZ BA BB BD BE BF BG BH BI BJ BL BM BN BO BQ BR BS BT BV BW BY BZ \
+    CA CC CD CF CG CH CI CK CL CM CN CO CR CU CV CW CX CY CZ DE DJ DK DM DO DZ EC EE EG EH ER ES ET FI FJ FK FM FO FR \
+    GA GB GD GE GF GG GH GI GL GM GN GP GQ GR GS GT GU GW GY HK HM HN HR HT HU ID IE IL IM IN IO IQ IR IS IT JE JM JO JP \
+    KE KG KH KI KM KN KP KR KW KY KZ LA LB LC LI LK LR LS LT LU LV LY MA MC MD ME MF MG MH MK ML MM MN MO MP MQ MR MS MT \
+    MU MV MW MX MY MZ NA NC NE NF NG NI NL NO NP NR NU NZ OM PA PE PF PG PH PK PL PM PN PR PS PT PW PY QA RE RO RS RU RW \
+    SA SB SC SD SE SG SH SI SJ SK SL SM SN SO SR SS ST SV SX SY SZ TC TD TF TG TH TJ TK TL TM TN TO TR TT TV TW TZ UA UG \
+    UM US UY UZ VA VC VE VG VI VN VU WF WS YE YT ZA ZM ZW";
+
+/// Check an ISRC: country code, registrant code, year of reference and designation code
+///
+/// Returns the problem, or `None` for a well-formed code.
+pub fn check_isrc(value: &str) -> Option<String> {
+    let compact: String = value.chars().filter(|&c| c != '-').collect();
+    let bytes = compact.as_bytes();
+    let valid = bytes.len() == 12
+        && bytes[0..2].iter().all(u8::is_ascii_uppercase)
+        && bytes[2..5].iter().all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+        && bytes[5..12].iter().all(u8::is_ascii_digit);
+    if !valid {
+        return Some(format!("'{}' is not an ISRC (two letters, three letters or digits, seven digits, e.g. USRC17607839)", value));
+    }
+    let country = &compact[0..2];
+    if !EXTRA_ISRC_PREFIXES.contains(&country) && !COUNTRY_CODES.split_whitespace().any(|code| code == country) {
+        return Some(format!("'{}' starts with '{}', which is not a country code issued by an ISRC agency", value, country));
+    }
+    if compact != value {
+        return Some(format!("'{}' contains hyphens, the frame holds the 12 characters {} without them", value, compact));
+    }
+    None
+}
+
+/// Validate a UPC-A/EAN-8/EAN-13/GTIN-14 barcode including its GS1 check digit
+pub fn validate_barcode(barcode: &str) -> Result<(), String> {
+    if !barcode.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("barcode '{}' contains non-digit characters", barcode));
+    }
+    if ![8, 12, 13, 14].contains(&barcode.len()) {
+        return Err(format!("barcode '{}' has {} digits (expected 8, 12, 13 or 14)", barcode, barcode.len()));
+    }
+
+    let digits: Vec<u32> = barcode.chars().filter_map(|c| c.to_digit(10)).collect();
+    let (payload, check) = digits.split_at(digits.len() - 1);
+    // Weights alternate 3, 1, 3, ... starting from the digit next to the check digit
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| {
+            if i % 2 == 0 {
+                digit * 3
+            } else {
+                *digit
+            }
+        })
+        .sum();
+    let expected = (10 - sum % 10) % 10;
+    if check[0] != expected {
+        return Err(format!("barcode '{}' has check digit {} (expected {})", barcode, check[0], expected));
+    }
+    Ok(())
+}
+
+/// Release codes in the freeform items of the `moov/udta/meta/ilst` box of an MP4 file
+pub fn isobmff_release_codes(file: &mut dyn MediaSource) -> Result<Vec<KnownUserText>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let Some(moov) = read_boxes(file, 0, file_size)?.into_iter().find(|box_header| box_header.box_type == "moov") else {
+        return Ok(Vec::new());
+    };
+    let Some(ilst) = find_path(file, &moov, &["udta", "meta", "ilst"])? else {
+        return Ok(Vec::new());
+    };
+
+    let mut codes = Vec::new();
+    for item in read_children(file, &ilst)?.iter().filter(|item| item.box_type == "----") {
+        let mut name = None;
+        let mut value = None;
+        for child in read_children(file, item)? {
+            let payload = read_payload(file, &child)?;
+            match child.box_type.as_str() {
+                // Version and flags precede the name
+                | "name" => name = Some(String::from_utf8_lossy(payload.get(4..).unwrap_or_default()).into_owned()),
+                // Type indicator and locale precede the value; only the first value is used
+                | "data" if value.is_none() => value = Some(String::from_utf8_lossy(payload.get(8..).unwrap_or_default()).trim_end_matches('\0').to_string()),
+                | _ => {}
+            }
+        }
+        if let (Some(name), Some(value)) = (name, value)
+            && CODE_ITEM_NAMES.iter().any(|code| code.eq_ignore_ascii_case(&name))
+            && let Some(code) = interpret_user_text(&name, &value)
+        {
+            codes.push(code);
+        }
+    }
+    Ok(codes)
+}
+
+/// Print the release codes of an MP4 file with their problems, if it has any
+pub fn print_release_codes(codes: &[KnownUserText]) {
+    if codes.is_empty() {
+        return;
+    }
+    outln!("\nRelease Codes:");
+    for code in codes {
+        match (&code.value, &code.problem) {
+            | (_, Some(problem)) => outln!("  WARNING: ----:{}: {}", code.description, problem),
+            | (Some(value), None) => outln!("  ----:{}: {}", code.description, value),
+            | (None, None) => {}
+        }
+    }
+}
@@ -0,0 +1,171 @@
+/// RIFF/RF64/BW64 chunk traversal for WAVE files
+///
+/// RIFF sizes are 32 bits, which limits a file to 4 GB. RF64 (EBU Tech 3306) and BW64
+/// (ITU-R BS.2088) write 0xFFFFFFFF into the size fields instead and put the real 64-bit
+/// sizes of the file, the data chunk and any other oversized chunk into a `ds64` chunk
+/// right after the header.
+use crate::checked_size::{buffer_length, end_offset};
+use crate::media_source::MediaSource;
+use serde::Serialize;
+use std::io::SeekFrom;
+
+/// 32-bit size value meaning "see the ds64 chunk"
+const SIZE_IN_DS64: u32 = 0xFFFFFFFF;
+
+/// Position and size of a chunk within the file
+#[derive(Debug, Clone, Serialize)]
+pub struct RiffChunk {
+    /// Four-character chunk ID (e.g. "fmt ", "data", "id3 ")
+    pub id: String,
+    /// File offset of the chunk header
+    pub offset: u64,
+    /// Size of the chunk payload (from ds64 for oversized chunks)
+    pub size: u64,
+    /// Whether the size comes from the ds64 chunk
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub size_from_ds64: bool,
+}
+
+impl RiffChunk {
+    /// File offset of the first payload byte
+    pub fn payload_start(&self) -> u64 {
+        self.offset + 8
+    }
+
+    /// File offset of the next chunk (payloads are padded to an even size)
+    pub fn end(&self) -> u64 {
+        self.payload_start() + self.size + (self.size & 1)
+    }
+}
+
+/// 64-bit sizes of an RF64/BW64 file
+#[derive(Debug, Clone, Serialize)]
+pub struct Ds64 {
+    /// Size of the file minus 8 bytes
+    pub riff_size: u64,
+    /// Size of the data chunk payload
+    pub data_size: u64,
+    /// Number of samples per channel
+    pub sample_count: u64,
+    /// Sizes of other chunks larger than 4 GB
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub table: Vec<(String, u64)>,
+}
+
+/// Header and chunk list of a RIFF WAVE, RF64 or BW64 file
+#[derive(Debug, Clone, Serialize)]
+pub struct RiffFile {
+    /// Container ID: "RIFF", "RF64" or "BW64"
+    pub form: String,
+    /// Size from the header (from ds64 for RF64/BW64)
+    pub riff_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ds64: Option<Ds64>,
+    /// Top-level chunks in file order
+    pub chunks: Vec<RiffChunk>,
+    /// Problems with the chunk structure
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+impl RiffFile {
+    /// Read the header and chunk list of a WAVE file; `None` for other files
+    pub fn read(file: &mut dyn MediaSource) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let mut header = [0u8; 12];
+        file.seek(SeekFrom::Start(0))?;
+        if file.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+        let form = String::from_utf8_lossy(&header[0..4]).to_string();
+        if !matches!(form.as_str(), "RIFF" | "RF64" | "BW64") || &header[8..12] != b"WAVE" {
+            return Ok(None);
+        }
+
+        let mut riff = RiffFile { form, riff_size: u32::from_le_bytes(header[4..8].try_into()?) as u64, ds64: None, chunks: Vec::new(), problems: Vec::new() };
+        let mut pos = 12u64;
+        while pos + 8 <= file_size {
+            let mut chunk_header = [0u8; 8];
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut chunk_header)?;
+            let id = String::from_utf8_lossy(&chunk_header[0..4]).to_string();
+            let size32 = u32::from_le_bytes(chunk_header[4..8].try_into()?);
+
+            let mut chunk = RiffChunk { id, offset: pos, size: size32 as u64, size_from_ds64: false };
+            if chunk.id == "ds64" && riff.chunks.is_empty() {
+                let mut payload = vec![0u8; chunk.size.min(file_size - chunk.payload_start()) as usize];
+                file.read_exact(&mut payload)?;
+                riff.ds64 = parse_ds64(&payload);
+                if let Some(ds64) = &riff.ds64 {
+                    riff.riff_size = ds64.riff_size;
+                }
+            } else if size32 == SIZE_IN_DS64 && riff.form != "RIFF" {
+                let size = match &riff.ds64 {
+                    | Some(ds64) if chunk.id == "data" => Some(ds64.data_size),
+                    | Some(ds64) => ds64.table.iter().find(|(id, _)| *id == chunk.id).map(|(_, size)| *size),
+                    | None => None,
+                };
+                match size {
+                    | Some(size) => {
+                        chunk.size = size;
+                        chunk.size_from_ds64 = true;
+                    }
+                    | None => {
+                        riff.problems.push(format!("'{}' chunk at offset {} has no size in the ds64 chunk, assuming it extends to the end of the file", chunk.id, pos));
+                        chunk.size = file_size - chunk.payload_start();
+                    }
+                }
+            }
+
+            if end_offset("chunk", chunk.payload_start(), chunk.size, file_size).is_err() {
+                riff.problems.push(format!(
+                    "'{}' chunk at offset {} claims {} bytes but only {} remain (truncated file, or a >4 GB file written with 32-bit sizes)",
+                    chunk.id,
+                    pos,
+                    chunk.size,
+                    file_size - chunk.payload_start()
+                ));
+                chunk.size = file_size - chunk.payload_start();
+                riff.chunks.push(chunk);
+                break;
+            }
+            pos = chunk.end();
+            riff.chunks.push(chunk);
+        }
+
+        if riff.form != "RIFF" && riff.ds64.is_none() {
+            riff.problems.push(format!("{} file without a ds64 chunk", riff.form));
+        }
+        if riff.riff_size.checked_add(8) != Some(file_size) {
+            riff.problems.push(format!("header size {} does not match the file size {} (expected {})", riff.riff_size, file_size, file_size.saturating_sub(8)));
+        }
+        Ok(Some(riff))
+    }
+
+    /// First chunk with the given ID
+    pub fn chunk(&self, id: &str) -> Option<&RiffChunk> {
+        self.chunks.iter().find(|chunk| chunk.id == id)
+    }
+}
+
+/// Payload of a chunk
+pub fn read_chunk_payload(file: &mut dyn MediaSource, chunk: &RiffChunk) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut payload = vec![0u8; buffer_length(format!("chunk '{}'", chunk.id), chunk.offset, chunk.size)?];
+    file.seek(SeekFrom::Start(chunk.payload_start()))?;
+    file.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Parse the ds64 chunk: three 64-bit sizes and a table of (chunk ID, 64-bit size) entries
+fn parse_ds64(payload: &[u8]) -> Option<Ds64> {
+    let read_u64 = |pos: usize| payload.get(pos..pos + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap_or_default()));
+    let table_length = payload.get(24..28).map_or(0, |bytes| u32::from_le_bytes(bytes.try_into().unwrap_or_default())) as usize;
+    let table = (0..table_length)
+        .map_while(|i| {
+            let pos = 28 + i * 12;
+            let id = payload.get(pos..pos + 4)?;
+            Some((String::from_utf8_lossy(id).to_string(), read_u64(pos + 4)?))
+        })
+        .collect();
+    Some(Ds64 { riff_size: read_u64(0)?, data_size: read_u64(8)?, sample_count: read_u64(16)?, table })
+}
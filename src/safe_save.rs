@@ -0,0 +1,92 @@
+/// Crash-safe file rewriting
+///
+/// A rewritten file is written to a temporary file next to it, flushed to disk and renamed over
+/// the original, so a crash leaves either the old or the new file but never a mix of both. When
+/// only the first bytes change and the length stays the same (a tag that fits its padding), they
+/// are overwritten in place; the audio data is not touched. Permissions are kept, the
+/// modification time only with `WriteOptions::preserve_mtime` (`--preserve-mtime`).
+use crate::options::WriteOptions;
+use crate::path_display::long_path;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Last line of every command that changes files when it ran with `--dry-run`
+pub const DRY_RUN_NOTICE: &str = "Dry run: no files were written";
+
+/// Overwrite the first bytes of the file at `path` with `bytes` and flush them to disk
+pub fn overwrite_start(path: &Path, bytes: &[u8], options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    overwrite_ranges(path, &[(0, bytes.to_vec())], options)
+}
+
+/// Overwrite the bytes at each offset of `patches` and flush them to disk; the length stays the same
+pub fn overwrite_ranges(path: &Path, patches: &[(u64, Vec<u8>)], options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let path = &*long_path(path);
+    let modified = fs::metadata(path)?.modified()?;
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    for (offset, bytes) in patches {
+        file.seek(SeekFrom::Start(*offset))?;
+        file.write_all(bytes)?;
+    }
+    restore_mtime(&file, modified, options)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Replace the file at `path` with `contents`
+///
+/// `check` gets the path of the finished temporary file before it replaces the original; an
+/// error from it removes the temporary file and leaves the original untouched.
+pub fn replace_file(path: &Path, contents: &[u8], options: &WriteOptions, check: impl FnOnce(&Path) -> Result<(), Box<dyn std::error::Error>>) -> Result<(), Box<dyn std::error::Error>> {
+    replace_file_with(path, |temp| Ok(temp.write_all(contents)?), options, check)
+}
+
+/// Replace the file at `path` with what `write` writes, for contents too large to hold in memory
+pub fn replace_file_with(path: &Path, write: impl FnOnce(&mut File) -> Result<(), Box<dyn std::error::Error>>, options: &WriteOptions, check: impl FnOnce(&Path) -> Result<(), Box<dyn std::error::Error>>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = &*long_path(path);
+    let metadata = fs::metadata(path)?;
+    let temp_path = temp_path(path);
+    // A temporary file left by an interrupted run is incomplete
+    let _ = fs::remove_file(&temp_path);
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let mut temp = OpenOptions::new().write(true).create_new(true).open(&temp_path)?;
+        write(&mut temp)?;
+        temp.set_permissions(metadata.permissions())?;
+        restore_mtime(&temp, metadata.modified()?, options)?;
+        temp.sync_all()?;
+        drop(temp);
+        check(&temp_path)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+
+    // Persist the rename; directories cannot be opened for syncing on every platform
+    if let Some(directory) = path.parent().filter(|parent| !parent.as_os_str().is_empty())
+        && let Ok(directory) = File::open(directory)
+    {
+        let _ = directory.sync_all();
+    }
+    Ok(())
+}
+
+/// Hidden temporary file in the directory of `path` (renames only stay atomic on one file system)
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".supertool-tmp");
+    path.with_file_name(name)
+}
+
+fn restore_mtime(file: &File, modified: SystemTime, options: &WriteOptions) -> std::io::Result<()> {
+    if options.preserve_mtime {
+        file.set_modified(modified)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,362 @@
+/// Parsing in resource-limited worker processes (`--sandbox`)
+///
+/// The parsers are aimed at malformed and hostile files. With `--sandbox`, each file is parsed in
+/// a child process forked for it, so a parser bug that crashes, loops or exhausts memory ends that
+/// process only: the file is reported as failed and the batch run or the daemon goes on. The
+/// parent opens the file and the child inherits it; the child then limits its CPU time, address
+/// space, open files, file writes and processes, and on Linux (x86-64 and AArch64) installs a
+/// seccomp filter that refuses every system call except reading the open file, memory management
+/// and writing to the pipe its answer goes through. The answer is the printed output and the
+/// result as JSON; the parent prints the output and returns the result. A child that does not
+/// answer within the wall time limit is killed. Options that open files or the network while
+/// parsing (`--sidecar`, `--extract-geob`, `--timeline html`, `--check-urls`) are refused.
+use crate::batch_errors::{ReadErrorKind, read_error_kind};
+use crate::error::SupertoolError;
+use crate::options::{CommonOptions, DebugOptions, TimelineFormat};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io;
+
+/// CPU time a worker process may use
+pub const CPU_SECONDS: u64 = 120;
+
+/// Time a worker process may take before it is killed (it may wait for a slow disk)
+pub const WALL_SECONDS: u64 = 300;
+
+/// Memory a worker process may allocate on top of what the parent had mapped
+pub const MEMORY_LIMIT: u64 = 2 << 30;
+
+/// Whether files are parsed in worker processes (`--sandbox`)
+pub fn enabled() -> bool {
+    CommonOptions::current().sandbox
+}
+
+/// Refuse the options that open files or the network while parsing, which the sandbox forbids
+pub fn check_options(options: &DebugOptions) -> Result<(), Box<dyn Error>> {
+    let refused = [
+        (options.sidecar.is_some(), "--sidecar"),
+        (options.extract_geob.is_some(), "--extract-geob"),
+        (options.timeline == Some(TimelineFormat::Html), "--timeline html"),
+        (options.check_urls, "--check-urls"),
+    ];
+    match refused.iter().find(|(set, _)| *set) {
+        | Some((_, flag)) => Err(format!("{} writes files or uses the network, which --sandbox does not allow", flag).into()),
+        | None => Ok(()),
+    }
+}
+
+/// Error of a parse in the worker process, as passed to the parent
+#[derive(Serialize, Deserialize)]
+struct Failure {
+    message: String,
+    /// Set for I/O errors, so the parent counts the file as unreadable
+    read_error: Option<ReadErrorKind>,
+}
+
+/// What the worker process writes to the pipe
+#[derive(Serialize, Deserialize)]
+struct Answer<T> {
+    output: String,
+    result: Result<T, Failure>,
+}
+
+impl Failure {
+    fn of(error: &(dyn Error + 'static)) -> Self {
+        Failure { message: error.to_string(), read_error: read_error_kind(error) }
+    }
+
+    fn into_error(self) -> Box<dyn Error> {
+        let kind = match self.read_error {
+            | Some(ReadErrorKind::PermissionDenied) => io::ErrorKind::PermissionDenied,
+            | Some(ReadErrorKind::NotFound) => io::ErrorKind::NotFound,
+            | Some(ReadErrorKind::Io) => io::ErrorKind::Other,
+            | None => return self.message.into(),
+        };
+        SupertoolError::Io(io::Error::new(kind, self.message)).into()
+    }
+}
+
+/// Run `parse` on `file` in a worker process and return its result; its output is printed here
+///
+/// The worker is a fork of this process, so `parse` sees the options and settings of the run. It
+/// must not open files: the sandbox refuses that. Crashes, panics and exceeded limits of the
+/// worker become errors.
+#[cfg(unix)]
+pub fn isolated<T: Serialize + DeserializeOwned>(file: &mut File, parse: impl FnOnce(&mut File) -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+    use std::io::Write;
+    use std::os::fd::FromRawFd;
+
+    let memory_limit = unix::mapped_memory().unwrap_or(0).saturating_add(MEMORY_LIMIT);
+    let (reader, writer) = unix::pipe()?;
+    // Hold the stream locks while forking: a lock another thread held at that moment would stay
+    // locked forever in the child, which has no copy of that thread
+    let streams = (io::stdout().lock(), io::stderr().lock());
+    // SAFETY: the child only runs the parse and leaves through `_exit`, without returning here
+    let pid = unsafe { libc::fork() };
+    drop(streams);
+    match pid {
+        | -1 => Err(io::Error::last_os_error().into()),
+        | 0 => {
+            // SAFETY: the descriptors come from `pipe` and are owned by nobody else in this process
+            unsafe { libc::close(reader) };
+            let mut pipe = unsafe { File::from_raw_fd(writer) };
+            let answer = unix::work(file, parse, memory_limit);
+            let status = if pipe.write_all(&answer).is_ok() { 0 } else { 1 };
+            // SAFETY: leaves the child without running the destructors and exit handlers of the parent
+            unsafe { libc::_exit(status) }
+        }
+        | pid => {
+            // SAFETY: the write end belongs to the child now; the read end is owned by the file
+            unsafe { libc::close(writer) };
+            let pipe = unsafe { File::from_raw_fd(reader) };
+            let answer = unix::collect(pid, pipe)?;
+            let answer: Answer<T> = serde_json::from_slice(&answer).map_err(|e| format!("the parser process sent an unreadable answer ({})", e))?;
+            out!("{}", answer.output);
+            answer.result.map_err(Failure::into_error)
+        }
+    }
+}
+
+/// Run `parse` on `file` in a worker process (not available: it needs Unix processes)
+#[cfg(not(unix))]
+pub fn isolated<T: Serialize + DeserializeOwned>(_file: &mut File, _parse: impl FnOnce(&mut File) -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+    Err("--sandbox needs Unix processes, which this platform does not provide".into())
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::{Answer, CPU_SECONDS, Failure, WALL_SECONDS};
+    use crate::output;
+    use serde::Serialize;
+    use std::error::Error;
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::os::fd::{AsRawFd, RawFd};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::time::{Duration, Instant};
+
+    /// A pipe whose descriptors are not inherited by programs other threads start
+    pub fn pipe() -> io::Result<(RawFd, RawFd)> {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` has room for the two descriptors
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        for fd in fds {
+            // SAFETY: `fd` was just opened
+            unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+        }
+        Ok((fds[0], fds[1]))
+    }
+
+    /// Bytes of address space this process has mapped (Linux only)
+    pub fn mapped_memory() -> Option<u64> {
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().next()?.parse().ok()?;
+        // SAFETY: sysconf has no preconditions
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Some(pages * u64::try_from(page_size).ok()?)
+    }
+
+    /// Restrict this (child) process, run the parse and return the answer as JSON
+    pub fn work<T: Serialize>(file: &mut File, parse: impl FnOnce(&mut File) -> Result<T, Box<dyn Error>>, memory_limit: u64) -> Vec<u8> {
+        let answer = match limit_resources(memory_limit).and_then(|()| filter_system_calls()) {
+            | Err(e) => Answer { output: String::new(), result: Err(Failure { message: format!("could not set up the sandbox: {}", e), read_error: None }) },
+            | Ok(()) => {
+                let (result, output) = output::captured(|| panic::catch_unwind(AssertUnwindSafe(|| parse(file))));
+                let result = match result {
+                    | Ok(Ok(value)) => Ok(value),
+                    | Ok(Err(e)) => Err(Failure::of(&*e)),
+                    | Err(panic) => {
+                        let message = panic.downcast_ref::<&str>().map(|s| s.to_string()).or_else(|| panic.downcast_ref::<String>().cloned()).unwrap_or_default();
+                        Err(Failure { message: format!("the parser panicked: {}", message), read_error: None })
+                    }
+                };
+                Answer { output, result }
+            }
+        };
+        serde_json::to_vec(&answer).unwrap_or_default()
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    type Resource = libc::c_int;
+
+    fn set_limit(resource: Resource, soft: u64, hard: u64) -> io::Result<()> {
+        let limit = libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t };
+        // SAFETY: `limit` is a valid rlimit
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// CPU time (SIGXCPU, then SIGKILL a second later), address space, no new files, no writes
+    /// to files, no core dumps and no new processes
+    fn limit_resources(memory_limit: u64) -> io::Result<()> {
+        set_limit(libc::RLIMIT_CPU, CPU_SECONDS, CPU_SECONDS + 1)?;
+        set_limit(libc::RLIMIT_AS, memory_limit, memory_limit)?;
+        set_limit(libc::RLIMIT_NOFILE, 0, 0)?;
+        set_limit(libc::RLIMIT_FSIZE, 0, 0)?;
+        set_limit(libc::RLIMIT_CORE, 0, 0)?;
+        set_limit(libc::RLIMIT_NPROC, 0, 0)
+    }
+
+    /// Allow only the system calls parsing an open file needs; others fail with EPERM
+    #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn filter_system_calls() -> io::Result<()> {
+        #[cfg(target_arch = "x86_64")]
+        const AUDIT_ARCH: u32 = 0xC000_003E;
+        #[cfg(target_arch = "aarch64")]
+        const AUDIT_ARCH: u32 = 0xC000_00B7;
+        const ALLOWED: &[libc::c_long] = &[
+            libc::SYS_read,
+            libc::SYS_pread64,
+            libc::SYS_readv,
+            libc::SYS_write,
+            libc::SYS_writev,
+            libc::SYS_lseek,
+            libc::SYS_close,
+            libc::SYS_fstat,
+            libc::SYS_newfstatat,
+            libc::SYS_statx,
+            libc::SYS_mmap,
+            libc::SYS_munmap,
+            libc::SYS_mremap,
+            libc::SYS_mprotect,
+            libc::SYS_madvise,
+            libc::SYS_brk,
+            libc::SYS_futex,
+            libc::SYS_sched_yield,
+            libc::SYS_rt_sigaction,
+            libc::SYS_rt_sigprocmask,
+            libc::SYS_rt_sigreturn,
+            libc::SYS_sigaltstack,
+            libc::SYS_getrandom,
+            libc::SYS_clock_gettime,
+            libc::SYS_getpid,
+            libc::SYS_gettid,
+            libc::SYS_tgkill,
+            libc::SYS_exit,
+            libc::SYS_exit_group,
+        ];
+        // Offsets of the architecture and the call number in `seccomp_data`
+        const ARCH_OFFSET: u32 = 4;
+        const NR_OFFSET: u32 = 0;
+        let statement = |code: u32, k: u32| libc::sock_filter { code: code as u16, jt: 0, jf: 0, k };
+        let jump_if = |k: u32, jt: u8, jf: u8| libc::sock_filter { code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16, jt, jf, k };
+        let load = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+        let ret = libc::BPF_RET | libc::BPF_K;
+
+        // Calls of another architecture (e.g. the 32-bit ABI) could bypass the numbers below
+        let mut program = vec![statement(load, ARCH_OFFSET), jump_if(AUDIT_ARCH, 1, 0), statement(ret, libc::SECCOMP_RET_KILL_PROCESS), statement(load, NR_OFFSET)];
+        for &call in ALLOWED {
+            program.push(jump_if(call as u32, 0, 1));
+            program.push(statement(ret, libc::SECCOMP_RET_ALLOW));
+        }
+        program.push(statement(ret, libc::SECCOMP_RET_ERRNO | libc::EPERM as u32));
+        let filter = libc::sock_fprog { len: program.len() as u16, filter: program.as_mut_ptr() };
+
+        // SAFETY: `filter` points at `program`, which lives until the kernel has copied it
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 || libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &filter as *const libc::sock_fprog) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// No system call filter where seccomp is not available; the resource limits still apply
+    #[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    fn filter_system_calls() -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Read the answer of the worker process `pid` and wait for it to end
+    ///
+    /// Pipes of workers forked at the same time by other threads are inherited by each other, so
+    /// the end of the answer is not always seen as the end of the pipe; the exit of the worker is
+    /// watched as well.
+    pub fn collect(pid: libc::pid_t, mut pipe: File) -> Result<Vec<u8>, Box<dyn Error>> {
+        let started = Instant::now();
+        let mut answer = Vec::new();
+        let mut chunk = vec![0u8; 64 * 1024];
+        let mut exited = None;
+        loop {
+            let mut poll_fd = libc::pollfd { fd: pipe.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            // Once the worker has exited, only the data left in the pipe is read
+            let timeout = if exited.is_some() { 0 } else { 100 };
+            // SAFETY: `poll_fd` is one valid pollfd
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout) };
+            if ready > 0 {
+                match pipe.read(&mut chunk) {
+                    | Ok(0) => break,
+                    | Ok(length) => answer.extend_from_slice(&chunk[..length]),
+                    | Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    | Err(e) => return Err(e.into()),
+                }
+                continue;
+            }
+            if exited.is_some() {
+                break;
+            }
+            exited = try_wait(pid)?;
+            if exited.is_none() && started.elapsed() > Duration::from_secs(WALL_SECONDS) {
+                // SAFETY: `pid` is the worker, which has not been waited for yet
+                unsafe { libc::kill(pid, libc::SIGKILL) };
+                wait(pid)?;
+                return Err(format!("the parser process did not finish within {} s and was killed", WALL_SECONDS).into());
+            }
+        }
+        let status = match exited {
+            | Some(status) => status,
+            | None => wait(pid)?,
+        };
+        if libc::WIFSIGNALED(status) {
+            return Err(signal_error(libc::WTERMSIG(status)).into());
+        }
+        if libc::WEXITSTATUS(status) != 0 || answer.is_empty() {
+            return Err(format!("the parser process ended without an answer (exit status {})", libc::WEXITSTATUS(status)).into());
+        }
+        Ok(answer)
+    }
+
+    /// Exit status of `pid` if it has ended
+    fn try_wait(pid: libc::pid_t) -> io::Result<Option<libc::c_int>> {
+        let mut status = 0;
+        // SAFETY: `status` is a valid out pointer
+        match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+            | -1 => Err(io::Error::last_os_error()),
+            | 0 => Ok(None),
+            | _ => Ok(Some(status)),
+        }
+    }
+
+    fn wait(pid: libc::pid_t) -> io::Result<libc::c_int> {
+        let mut status = 0;
+        loop {
+            // SAFETY: `status` is a valid out pointer
+            if unsafe { libc::waitpid(pid, &mut status, 0) } != -1 {
+                return Ok(status);
+            }
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+    }
+
+    fn signal_error(signal: libc::c_int) -> String {
+        let cause = match signal {
+            | libc::SIGXCPU | libc::SIGKILL => format!("exceeded the CPU time limit of {} s", CPU_SECONDS),
+            | libc::SIGSEGV | libc::SIGBUS => "crashed".to_string(),
+            | libc::SIGABRT => "aborted (e.g. out of memory under the sandbox limit)".to_string(),
+            | libc::SIGSYS => "made a system call the sandbox forbids".to_string(),
+            | _ => "was killed".to_string(),
+        };
+        format!("the parser process {} (signal {})", cause, signal)
+    }
+}
@@ -0,0 +1,230 @@
+/// Serato DJ data in GEOB frames ("Serato Markers_", "Serato BeatGrid")
+///
+/// Serato stores the hot cues, saved loops and beatgrid of a track in GEOB frames of the file
+/// itself, in a binary format of its own. Positions are milliseconds in the markers and seconds
+/// in the beatgrid; numbers in the markers use "serato32", four bytes of seven bits each, which
+/// keeps the data free of bytes that look like MPEG sync.
+use crate::time_format::format_duration;
+use std::fmt;
+
+/// GEOB description of the hot cues and loops
+pub const MARKERS_DESCRIPTION: &str = "Serato Markers_";
+
+/// GEOB description of the beatgrid
+pub const BEATGRID_DESCRIPTION: &str = "Serato BeatGrid";
+
+/// Size of an entry in "Serato Markers_"
+const MARKER_ENTRY_SIZE: usize = 22;
+
+/// Entries before the first loop slot in "Serato Markers_"
+const CUE_SLOTS: usize = 5;
+
+/// Decoded Serato data of a GEOB frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeratoData {
+    Markers(SeratoMarkers),
+    BeatGrid(SeratoBeatGrid),
+}
+
+impl SeratoData {
+    /// Decode the object of a GEOB frame with a Serato description; `None` for other frames
+    pub fn parse(description: &str, data: &[u8]) -> Option<Result<Self, String>> {
+        match description {
+            | MARKERS_DESCRIPTION => Some(SeratoMarkers::parse(data).map(SeratoData::Markers)),
+            | BEATGRID_DESCRIPTION => Some(SeratoBeatGrid::parse(data).map(SeratoData::BeatGrid)),
+            | _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SeratoData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | SeratoData::Markers(markers) => write!(f, "{}", markers),
+            | SeratoData::BeatGrid(beat_grid) => write!(f, "{}", beat_grid),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    Cue,
+    Loop,
+}
+
+/// Hot cue or saved loop
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeratoMarker {
+    pub kind: MarkerKind,
+    /// Slot number as shown in Serato (1-based, counted separately for cues and loops)
+    pub slot: usize,
+    pub start_ms: u32,
+    /// End of a loop
+    pub end_ms: Option<u32>,
+    pub color: [u8; 3],
+    pub locked: bool,
+}
+
+/// Hot cues, saved loops and track color ("Serato Markers_")
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeratoMarkers {
+    pub markers: Vec<SeratoMarker>,
+    pub track_color: Option<[u8; 3]>,
+}
+
+impl SeratoMarkers {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 6 {
+            return Err("Serato Markers_ data too short".to_string());
+        }
+        if data[0..2] != [0x02, 0x05] {
+            return Err(format!("unsupported Serato Markers_ version {}.{}", data[0], data[1]));
+        }
+        let count = u32::from_be_bytes([data[2], data[3], data[4], data[5]]) as usize;
+        let entries_end = count.checked_mul(MARKER_ENTRY_SIZE).and_then(|size| size.checked_add(6)).filter(|&end| end <= data.len());
+        let Some(entries_end) = entries_end else {
+            return Err(format!("Serato Markers_ data too short for {} entries", count));
+        };
+
+        let mut markers = Vec::new();
+        for (i, entry) in data[6..entries_end].chunks_exact(MARKER_ENTRY_SIZE).enumerate() {
+            // Position flags are 0x00 for a set position and 0x7F for an empty slot
+            let kind = match entry[20] {
+                | 0x01 => MarkerKind::Cue,
+                | 0x03 => MarkerKind::Loop,
+                | _ => continue,
+            };
+            if entry[0] != 0x00 {
+                continue;
+            }
+            let slot = match kind {
+                | MarkerKind::Cue => i + 1,
+                | MarkerKind::Loop => i.checked_sub(CUE_SLOTS).unwrap_or(i) + 1,
+            };
+            markers.push(SeratoMarker {
+                kind,
+                slot,
+                start_ms: serato32(&entry[1..5]),
+                end_ms: (kind == MarkerKind::Loop && entry[5] == 0x00).then(|| serato32(&entry[6..10])),
+                color: rgb(serato32(&entry[16..20])),
+                locked: entry[21] != 0,
+            });
+        }
+        let track_color = data.get(entries_end..entries_end + 4).map(|color| rgb(serato32(color)));
+        Ok(SeratoMarkers { markers, track_color })
+    }
+}
+
+impl fmt::Display for SeratoMarkers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Serato markers: {} cue(s) and loop(s)", self.markers.len())?;
+        for marker in &self.markers {
+            match (marker.kind, marker.end_ms) {
+                | (MarkerKind::Loop, Some(end_ms)) => {
+                    write!(f, "  Loop {}: {} - {}", marker.slot, format_duration(marker.start_ms as u64), format_duration(end_ms as u64))?;
+                }
+                | (MarkerKind::Loop, None) => write!(f, "  Loop {}: {}", marker.slot, format_duration(marker.start_ms as u64))?,
+                | (MarkerKind::Cue, _) => write!(f, "  Cue {}: {}", marker.slot, format_duration(marker.start_ms as u64))?,
+            }
+            write!(f, ", color {}", hex_color(marker.color))?;
+            if marker.locked {
+                write!(f, ", locked")?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(color) = self.track_color {
+            writeln!(f, "  Track color: {}", hex_color(color))?;
+        }
+        Ok(())
+    }
+}
+
+/// Beatgrid anchor
+///
+/// All markers but the last give the number of beats to the next marker, the last one the
+/// tempo from there on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatGridMarker {
+    /// Position in seconds, negative if the grid starts before the track
+    pub position: f32,
+    pub beats_to_next: Option<u32>,
+    pub bpm: f32,
+}
+
+/// Beatgrid ("Serato BeatGrid")
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeratoBeatGrid {
+    pub markers: Vec<BeatGridMarker>,
+}
+
+impl SeratoBeatGrid {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 6 {
+            return Err("Serato BeatGrid data too short".to_string());
+        }
+        if data[0..2] != [0x01, 0x00] {
+            return Err(format!("unsupported Serato BeatGrid version {}.{}", data[0], data[1]));
+        }
+        let count = u32::from_be_bytes([data[2], data[3], data[4], data[5]]) as usize;
+        if count == 0 {
+            return Ok(SeratoBeatGrid { markers: Vec::new() });
+        }
+        let Some(entries) = count.checked_mul(8).and_then(|size| data.get(6..6 + size)) else {
+            return Err(format!("Serato BeatGrid data too short for {} markers", count));
+        };
+
+        let positions: Vec<f32> = entries.chunks_exact(8).map(|entry| f32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]])).collect();
+        let mut markers = Vec::new();
+        for (i, entry) in entries.chunks_exact(8).enumerate() {
+            let value = [entry[4], entry[5], entry[6], entry[7]];
+            let marker = match positions.get(i + 1) {
+                // Tempo between two markers from their distance and the beats in between
+                | Some(next) => {
+                    let beats = u32::from_be_bytes(value);
+                    let seconds = next - positions[i];
+                    let bpm = if seconds > 0.0 {
+                        beats as f32 * 60.0 / seconds
+                    } else {
+                        0.0
+                    };
+                    BeatGridMarker { position: positions[i], beats_to_next: Some(beats), bpm }
+                }
+                | None => BeatGridMarker { position: positions[i], beats_to_next: None, bpm: f32::from_be_bytes(value) },
+            };
+            markers.push(marker);
+        }
+        Ok(SeratoBeatGrid { markers })
+    }
+}
+
+impl fmt::Display for SeratoBeatGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Serato beatgrid: {} marker(s)", self.markers.len())?;
+        for (i, marker) in self.markers.iter().enumerate() {
+            let position = format_duration((marker.position.abs() * 1000.0).round() as u64);
+            let sign = if marker.position < 0.0 {
+                "-"
+            } else {
+                ""
+            };
+            match marker.beats_to_next {
+                | Some(beats) => writeln!(f, "  Marker {}: {}{}, {} beats to the next marker ({:.2} BPM)", i + 1, sign, position, beats, marker.bpm)?,
+                | None => writeln!(f, "  Marker {}: {}{}, {:.2} BPM", i + 1, sign, position, marker.bpm)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decode a serato32 number: the low seven bits of four bytes, of which 24 bits are used
+fn serato32(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |value, &byte| (value << 7) | (byte & 0x7F) as u32) & 0x00FF_FFFF
+}
+
+fn rgb(value: u32) -> [u8; 3] {
+    [(value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn hex_color(color: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
@@ -0,0 +1,55 @@
+use crate::media_summary::MediaSummary;
+use crate::options::{SidecarFormat, SidecarOptions};
+use crate::summary_formats::{to_xml, to_yaml};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Suffix appended to the analyzed file name to form the sidecar file name
+const SIDECAR_SUFFIX: &str = ".supertool";
+
+/// Write the summary next to the analyzed file (or into the mirror directory) and return the sidecar path
+pub fn write_sidecar(file_path: &Path, summary: &MediaSummary, options: &SidecarOptions) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let sidecar_path = sidecar_path(file_path, options);
+
+    let contents = match options.format {
+        | SidecarFormat::Json => serde_json::to_string_pretty(summary)?,
+        | SidecarFormat::Yaml => to_yaml(&serde_json::to_value(summary)?).trim_end().to_string(),
+        | SidecarFormat::Xml => to_xml(&serde_json::to_value(summary)?).trim_end().to_string(),
+    };
+
+    if let Some(parent) = sidecar_path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&sidecar_path, contents + "\n")?;
+
+    Ok(sidecar_path)
+}
+
+/// Determine where the sidecar for `file_path` goes
+///
+/// Without a sidecar directory the file is placed next to the media file. With one,
+/// the media file's directory structure is mirrored below it.
+pub fn sidecar_path(file_path: &Path, options: &SidecarOptions) -> PathBuf {
+    let mut file_name = file_path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    file_name.push(SIDECAR_SUFFIX);
+    file_name.push(".");
+    file_name.push(options.format.extension());
+
+    match &options.dir {
+        | Some(dir) => {
+            // Keep only the normal components so absolute paths and ".." cannot escape the mirror directory
+            let mut mirrored = dir.clone();
+            if let Some(parent) = file_path.parent() {
+                for component in parent.components() {
+                    if let Component::Normal(part) = component {
+                        mirrored.push(part);
+                    }
+                }
+            }
+            mirrored.join(file_name)
+        }
+        | None => file_path.with_file_name(file_name),
+    }
+}
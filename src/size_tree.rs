@@ -0,0 +1,126 @@
+/// Hierarchical size breakdown of a file (`debug --sizes`)
+///
+/// Splits the file into its tags, audio and container structures, tags into the bytes per frame
+/// type (CHAP and CTOC into their sub-frames), and ISO BMFF files into boxes. Siblings of the
+/// same type are merged and counted, and every level is sorted by size, so the biggest
+/// contributors come first.
+use crate::audio_hash::audio_ranges;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::media_source::MediaSource;
+use crate::trailer_tags::trailers;
+use serde::Serialize;
+use std::cmp::Reverse;
+
+/// Part of a file and its size
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeNode {
+    /// What the bytes are (e.g. "ID3v2.4 tag", "APIC", "'moov' box")
+    pub label: String,
+    pub size: u64,
+    /// Number of merged siblings of the same type
+    pub count: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SizeNode>,
+}
+
+impl SizeNode {
+    pub fn new(label: impl Into<String>, size: u64) -> Self {
+        SizeNode { label: label.into(), size, count: 1, children: Vec::new() }
+    }
+
+    /// Node with `children`, merged by label and sorted by size
+    ///
+    /// Bytes of `size` the children do not cover are added as a child labeled `rest` (if any).
+    pub fn with_children(label: impl Into<String>, size: u64, children: Vec<SizeNode>, rest: &str) -> Self {
+        let mut children = merge(children);
+        let covered: u64 = children.iter().map(|child| child.size).sum();
+        if size > covered {
+            children.push(SizeNode::new(rest, size - covered));
+        }
+        children.sort_by_key(|node| Reverse(node.size));
+        SizeNode { label: label.into(), size, count: 1, children }
+    }
+}
+
+/// Merge siblings with the same label, summing sizes and merging their children
+fn merge(nodes: Vec<SizeNode>) -> Vec<SizeNode> {
+    let mut merged: Vec<SizeNode> = Vec::new();
+    for node in nodes {
+        match merged.iter_mut().find(|existing| existing.label == node.label) {
+            | Some(existing) => {
+                existing.size += node.size;
+                existing.count += node.count;
+                existing.children.extend(node.children);
+            }
+            | None => merged.push(node),
+        }
+    }
+    for node in &mut merged {
+        node.children = merge(std::mem::take(&mut node.children));
+        node.children.sort_by_key(|node| Reverse(node.size));
+    }
+    merged
+}
+
+/// Size tree of a file no dissector understands
+pub fn flat(file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+    Ok(SizeNode::new("file", file.byte_len()?))
+}
+
+/// Tag header, frames by type and padding of an ID3v2 tag
+pub fn tag_node(tag: &Id3v2Tag) -> SizeNode {
+    let mut children = vec![SizeNode::new("tag header", 10)];
+    children.extend(tag.frames.iter().map(|frame| frame_node(frame, tag.frame_header_size())));
+    SizeNode::with_children(format!("{} tag", tag.tag_type()), tag.total_size(), children, "padding")
+}
+
+/// A frame (header and data), with the sub-frames of CHAP and CTOC frames
+fn frame_node(frame: &Id3v2Frame, header_size: u64) -> SizeNode {
+    let size = header_size + frame.size as u64;
+    let sub_frames = match &frame.content {
+        | Some(Id3v2FrameContent::Chapter(chapter)) => &chapter.sub_frames,
+        | Some(Id3v2FrameContent::TableOfContents(toc)) => &toc.sub_frames,
+        | _ => return SizeNode::new(&frame.id, size),
+    };
+    SizeNode::with_children(&frame.id, size, sub_frames.iter().map(|sub_frame| frame_node(sub_frame, header_size)).collect(), &format!("{} header and fields", frame.id))
+}
+
+/// Size tree of an MP3 file: ID3v2 tag, MPEG audio and the tag blocks after the audio
+pub fn mp3_size_tree(file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut children = Vec::new();
+    if let Some(tag) = Id3v2Tag::read(file)? {
+        children.push(tag_node(&tag));
+    }
+    for (start, end) in audio_ranges(file)? {
+        children.push(SizeNode::new("MPEG audio", end - start));
+    }
+    for block in trailers(file)? {
+        children.push(SizeNode::new(format!("{} tag", block.kind), block.size));
+    }
+    Ok(SizeNode::with_children("file", file_size, children, "other"))
+}
+
+/// Print the tree with the share of the file every node takes
+pub fn print_size_tree(root: &SizeNode) {
+    outln!("\nSize Breakdown:");
+    print_node(root, root.size, 1);
+}
+
+fn print_node(node: &SizeNode, total: u64, depth: usize) {
+    let share = if total > 0 {
+        node.size as f64 * 100.0 / total as f64
+    } else {
+        0.0
+    };
+    let count = if node.count > 1 {
+        format!(" ({}x)", node.count)
+    } else {
+        String::new()
+    };
+    outln!("{}{}{}: {} bytes ({:.1}%)", "  ".repeat(depth), node.label, count, node.size, share);
+    for child in &node.children {
+        print_node(child, total, depth + 1);
+    }
+}
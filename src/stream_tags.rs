@@ -0,0 +1,145 @@
+/// Tags inside the audio of stream rips
+///
+/// Recordings of internet radio streams often carry a new ID3v2 tag before every song, or the
+/// ICY metadata blocks of a SHOUTcast/Icecast stream (a length byte counting 16-byte units,
+/// then `StreamTitle='...';` padded with zero bytes) between the MPEG frames. Both are found
+/// by scanning the audio data after the leading tag, and listed with their now-playing data.
+use crate::id3v2_tag::{Id3v2Tag, parse_tag_header};
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::read_audio_data;
+use crate::text_truncate::truncate_text;
+use serde::Serialize;
+
+/// Start of the text of an ICY metadata block
+const ICY_MARKER: &[u8] = b"StreamTitle='";
+
+/// Inline tags listed in the text output before the rest is summarized
+const MAX_LISTED_TAGS: usize = 50;
+
+/// Tag found inside the audio data
+#[derive(Debug, Clone, Serialize)]
+pub struct InlineTag {
+    pub offset: u64,
+    pub size: u64,
+    /// "ID3v2.3 tag", "ID3v2.4 tag" or "ICY metadata"
+    pub kind: String,
+    /// Artist and title of the song that starts here, if the tag names them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub now_playing: Option<String>,
+    /// Text frames of ID3v2 tags or the fields of ICY metadata (StreamTitle, StreamUrl, ...)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<(String, String)>,
+}
+
+/// Inline tags in the audio data of the file
+pub fn inline_tags(file: &mut dyn MediaSource) -> Result<Vec<InlineTag>, Box<dyn std::error::Error>> {
+    let Some((audio_start, data)) = read_audio_data(file)? else {
+        return Ok(Vec::new());
+    };
+    Ok(find_inline_tags(&data, audio_start))
+}
+
+/// Inline tags in `data`, which starts at file offset `base`
+pub fn find_inline_tags(data: &[u8], base: u64) -> Vec<InlineTag> {
+    let mut tags = Vec::new();
+    let mut pos = 0;
+    while pos + 4 <= data.len() {
+        // ICY blocks are found at their text and start one byte earlier at the length byte
+        let found = match data[pos] {
+            | b'I' => inline_id3v2(&data[pos..]).map(|tag| (tag, 0)),
+            | b'S' if pos > 0 => icy_block(&data[pos - 1..]).map(|tag| (tag, 1)),
+            | _ => None,
+        };
+        match found {
+            | Some((mut tag, back)) => {
+                tag.offset = base + (pos - back) as u64;
+                pos += tag.size as usize - back;
+                tags.push(tag);
+            }
+            | None => pos += 1,
+        }
+    }
+    tags
+}
+
+/// ID3v2 tag with at least one frame at the start of `data`
+fn inline_id3v2(data: &[u8]) -> Option<InlineTag> {
+    let (major, _minor, flags, size) = parse_tag_header(data.get(..10)?)?;
+    let footer = if major == 4 && flags & 0x10 != 0 {
+        10
+    } else {
+        0
+    };
+    let total = 10 + size as usize + footer;
+    let tag = Id3v2Tag::parse(data.get(..total)?)?;
+    if tag.frames.is_empty() {
+        return None;
+    }
+
+    let fields: Vec<(String, String)> = tag.frames.iter().filter_map(|frame| Some((frame.id.clone(), frame.get_text()?.to_string()))).collect();
+    let field = |id: &str| fields.iter().find(|(frame_id, _)| frame_id == id).map(|(_, value)| value.as_str());
+    let now_playing = match (field("TPE1"), field("TIT2")) {
+        | (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+        | (None, Some(title)) => Some(title.to_string()),
+        | _ => None,
+    };
+    Some(InlineTag { offset: 0, size: total as u64, kind: format!("{} tag", tag.tag_type()), now_playing, fields })
+}
+
+/// ICY metadata block whose length byte is the first byte of `data`
+///
+/// The text must fill the block up to less than 16 bytes of zero padding, which rules out
+/// `StreamTitle='` appearing by chance in the audio.
+fn icy_block(data: &[u8]) -> Option<InlineTag> {
+    if !data[1..].starts_with(ICY_MARKER) {
+        return None;
+    }
+    let length = data[0] as usize * 16;
+    let block = data.get(1..1 + length)?;
+    let text_length = block.iter().position(|&byte| byte == 0).unwrap_or(length);
+    if text_length < ICY_MARKER.len() || text_length + 16 <= length || block[text_length..].iter().any(|&byte| byte != 0) {
+        return None;
+    }
+
+    let fields = icy_fields(&icy_text(&block[..text_length]));
+    let now_playing = fields.iter().find(|(key, _)| key == "StreamTitle").map(|(_, value)| value.clone()).filter(|title| !title.is_empty());
+    Some(InlineTag { offset: 0, size: 1 + length as u64, kind: "ICY metadata".to_string(), now_playing, fields })
+}
+
+/// Text of ICY metadata, which stations send as UTF-8 or Latin-1
+pub fn icy_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        | Ok(text) => text.to_string(),
+        | Err(_) => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+/// Fields of ICY metadata text (`Key='value';Key='value';`)
+pub fn icy_fields(text: &str) -> Vec<(String, String)> {
+    text.split("';")
+        .filter_map(|field| {
+            let (key, value) = field.split_once("='")?;
+            Some((key.trim_start_matches(';').to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Print the inline tags of the file, if there are any
+pub fn print_inline_tags(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    let tags = inline_tags(file)?;
+    if tags.is_empty() {
+        return Ok(());
+    }
+    outln!("\nInline Tags ({} in the audio data):", tags.len());
+    for tag in tags.iter().take(MAX_LISTED_TAGS) {
+        let now_playing = tag.now_playing.as_deref().map_or(String::new(), |now_playing| format!(": {}", now_playing));
+        outln!("  0x{:08X}: {} ({} bytes){}", tag.offset, tag.kind, tag.size, now_playing);
+        for (key, value) in &tag.fields {
+            outln!("    {}: {}", key, truncate_text(value));
+        }
+    }
+    if tags.len() > MAX_LISTED_TAGS {
+        outln!("  ... {} more (all of them are in the sidecar file)", tags.len() - MAX_LISTED_TAGS);
+    }
+    Ok(())
+}
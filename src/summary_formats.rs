@@ -0,0 +1,186 @@
+/// YAML and XML serializations of the analysis summary (`--sidecar yaml`, `--sidecar xml`)
+///
+/// Both are written from the same `serde_json::Value` as the JSON sidecar, so every format
+/// carries the same fields in the same order. YAML strings are plain where that cannot be read
+/// as another type and double-quoted (with JSON escapes, which YAML shares) otherwise.
+///
+/// XML has no arrays or untyped maps: every field becomes an element named after it, array
+/// items become `<item>` elements, and map keys that are no valid element names (frame IDs like
+/// "©nam", keys with spaces) become `<entry key="...">`. Characters XML 1.0 cannot carry at
+/// all (control characters other than tab and line breaks) are replaced by U+FFFD.
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Root element of XML summaries
+const XML_ROOT: &str = "supertool";
+
+/// Width of one indentation level
+const INDENT: usize = 2;
+
+/// `value` as a YAML document
+pub fn to_yaml(value: &Value) -> String {
+    let mut out = String::from("---\n");
+    match value {
+        | Value::Object(map) if !map.is_empty() => write_yaml_map(&mut out, map, 0),
+        | Value::Array(items) if !items.is_empty() => write_yaml_list(&mut out, items, 0),
+        | _ => {
+            out.push_str(&yaml_scalar(value));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_yaml_map(out: &mut String, map: &serde_json::Map<String, Value>, indent: usize) {
+    for (index, (key, value)) in map.iter().enumerate() {
+        // The first key of a list item follows its "- "
+        if index > 0 || !out.ends_with("- ") {
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str(&yaml_string(key));
+        out.push(':');
+        write_yaml_value(out, value, indent);
+    }
+}
+
+fn write_yaml_list(out: &mut String, items: &[Value], indent: usize) {
+    for item in items {
+        if !out.ends_with("- ") {
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str("- ");
+        match item {
+            | Value::Object(map) if !map.is_empty() => write_yaml_map(out, map, indent + INDENT),
+            | Value::Array(nested) if !nested.is_empty() => write_yaml_list(out, nested, indent + INDENT),
+            | _ => {
+                out.push_str(&yaml_scalar(item));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// Value of a map entry, after its "key:"
+fn write_yaml_value(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        | Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_yaml_map(out, map, indent + INDENT);
+        }
+        | Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_yaml_list(out, items, indent + INDENT);
+        }
+        | _ => {
+            out.push(' ');
+            out.push_str(&yaml_scalar(value));
+            out.push('\n');
+        }
+    }
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        | Value::Null => "null".to_string(),
+        | Value::Bool(flag) => flag.to_string(),
+        | Value::Number(number) => number.to_string(),
+        | Value::String(text) => yaml_string(text),
+        | Value::Array(_) => "[]".to_string(),
+        | Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// A string plain if YAML reads it back as that string, double-quoted otherwise
+fn yaml_string(text: &str) -> String {
+    const RESERVED: [&str; 11] = ["true", "false", "null", "yes", "no", "on", "off", "y", "n", "~", "nan"];
+    let plain = text.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !text.ends_with(' ')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || " _.,/()+-".contains(c))
+        && !RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(text));
+    if plain {
+        return text.to_string();
+    }
+    // JSON escapes the C0 controls only; YAML does not allow C1 controls or noncharacters either
+    let mut quoted = String::with_capacity(text.len() + 2);
+    for c in serde_json::to_string(text).unwrap_or_default().chars() {
+        match c {
+            | '\u{80}'..='\u{9F}' | '\u{FEFF}' | '\u{FFFE}' | '\u{FFFF}' => {
+                let _ = write!(quoted, "\\u{:04X}", c as u32);
+            }
+            | c => quoted.push(c),
+        }
+    }
+    quoted
+}
+
+/// `value` as an XML document with a `<supertool>` root element
+pub fn to_xml(value: &Value) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    write_xml_element(&mut out, XML_ROOT, value, 0);
+    out
+}
+
+fn write_xml_element(out: &mut String, name: &str, value: &Value, indent: usize) {
+    let padding = " ".repeat(indent);
+    let (open, close) = if is_xml_name(name) {
+        (name.to_string(), name.to_string())
+    } else {
+        (format!("entry key=\"{}\"", xml_escape(name)), "entry".to_string())
+    };
+    match value {
+        | Value::Null => {
+            let _ = writeln!(out, "{}<{}/>", padding, open);
+        }
+        | Value::Object(map) if map.is_empty() => {
+            let _ = writeln!(out, "{}<{}/>", padding, open);
+        }
+        | Value::Array(items) if items.is_empty() => {
+            let _ = writeln!(out, "{}<{}/>", padding, open);
+        }
+        | Value::Object(map) => {
+            let _ = writeln!(out, "{}<{}>", padding, open);
+            for (key, child) in map {
+                write_xml_element(out, key, child, indent + INDENT);
+            }
+            let _ = writeln!(out, "{}</{}>", padding, close);
+        }
+        | Value::Array(items) => {
+            let _ = writeln!(out, "{}<{}>", padding, open);
+            for item in items {
+                write_xml_element(out, "item", item, indent + INDENT);
+            }
+            let _ = writeln!(out, "{}</{}>", padding, close);
+        }
+        | Value::String(text) => {
+            let _ = writeln!(out, "{}<{}>{}</{}>", padding, open, xml_escape(text), close);
+        }
+        | Value::Bool(_) | Value::Number(_) => {
+            let _ = writeln!(out, "{}<{}>{}</{}>", padding, open, value, close);
+        }
+    }
+}
+
+/// Whether `name` can be used as element name as it is
+fn is_xml_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+        && !name.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("xml"))
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            | '&' => escaped.push_str("&amp;"),
+            | '<' => escaped.push_str("&lt;"),
+            | '>' => escaped.push_str("&gt;"),
+            | '"' => escaped.push_str("&quot;"),
+            // Parsers turn a literal carriage return into a line feed
+            | '\r' => escaped.push_str("&#13;"),
+            | '\t' | '\n' => escaped.push(c),
+            | c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => escaped.push(char::REPLACEMENT_CHARACTER),
+            | c => escaped.push(c),
+        }
+    }
+    escaped
+}
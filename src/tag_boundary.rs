@@ -0,0 +1,119 @@
+/// Consistency of the ID3v2 tag size with the start of the MPEG audio
+///
+/// The audio should start right where the tag ends according to its size field. Bytes in
+/// between (junk left by broken taggers) and audio frames that start inside the declared
+/// tag area (a tag size that is too large) both make players skip or misread audio.
+use crate::finding::{Finding, Severity};
+use crate::id3v2_tag::parse_tag_header;
+use crate::media_source::MediaSource;
+use crate::mpeg_audio::{MpegFrameHeader, find_first_frame};
+use std::io::SeekFrom;
+
+/// Bytes searched for the first frame on either side of the tag end
+const SEARCH_WINDOW: u64 = 64 * 1024;
+
+/// Consecutive frames required before a sync inside the tag area counts as audio
+const MIN_CHAIN: usize = 4;
+
+/// Check the bytes around the end of the ID3v2 tag; no findings if the audio starts right after it
+pub fn check_tag_boundary(file: &mut dyn MediaSource) -> Result<Vec<Finding>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut header = [0u8; 10];
+    file.seek(SeekFrom::Start(0))?;
+    if file_size < header.len() as u64 {
+        return Ok(Vec::new());
+    }
+    file.read_exact(&mut header)?;
+    let Some((major, _minor, flags, size)) = parse_tag_header(&header) else {
+        return Ok(Vec::new());
+    };
+    let footer = if major == 4 && flags & 0x10 != 0 {
+        10
+    } else {
+        0
+    };
+    let tag_end = 10 + size as u64 + footer;
+    if tag_end >= file_size {
+        return Ok(Vec::new());
+    }
+
+    let window_start = tag_end.saturating_sub(SEARCH_WINDOW).max(10);
+    let window_end = (tag_end + SEARCH_WINDOW).min(file_size);
+    let mut data = vec![0u8; (window_end - window_start) as usize];
+    file.seek(SeekFrom::Start(window_start))?;
+    file.read_exact(&mut data)?;
+    let boundary = (tag_end - window_start) as usize;
+
+    if chain_length(&data, boundary) > 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut findings = Vec::new();
+    // A frame chain that starts inside the tag and runs across its end means the size field is too large
+    if let Some(start) = (0..boundary).find(|&pos| crosses(&data, pos, boundary)) {
+        findings.push(Finding::new(
+            Severity::Error,
+            "ID3v2",
+            format!(
+                "MPEG audio starts at 0x{:08X}, inside the declared tag area ending at 0x{:08X} (tag size {} bytes too large)",
+                window_start + start as u64,
+                tag_end,
+                boundary - start
+            ),
+        ));
+        return Ok(findings);
+    }
+
+    match find_first_frame(&data[boundary..]) {
+        | Some((gap, _)) => {
+            let garbage = &data[boundary..boundary + gap];
+            let (severity, kind) = if garbage.iter().all(|&byte| byte == 0) {
+                (Severity::Warning, "zero bytes")
+            } else {
+                (Severity::Error, "bytes of non-audio data")
+            };
+            findings.push(Finding::new(
+                severity,
+                "ID3v2",
+                format!("{} {} between the end of the tag (0x{:08X}) and the first MPEG frame (0x{:08X})", gap, kind, tag_end, tag_end + gap as u64),
+            ));
+        }
+        | None => findings.push(Finding::new(Severity::Warning, "ID3v2", format!("no MPEG audio frame within {} KiB after the end of the tag", SEARCH_WINDOW / 1024))),
+    }
+    Ok(findings)
+}
+
+/// Print the boundary findings, if there are any
+pub fn print_tag_boundary(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    let findings = check_tag_boundary(file)?;
+    if !findings.is_empty() {
+        outln!("\nTag Boundary:");
+        for finding in findings {
+            outln!("    {}: {}", finding.severity, finding.message);
+        }
+    }
+    Ok(())
+}
+
+/// Number of consecutive valid frames starting at `pos`
+fn chain_length(data: &[u8], mut pos: usize) -> usize {
+    let mut count = 0;
+    while let Some(length) = MpegFrameHeader::parse(&data[pos.min(data.len())..]).and_then(|header| header.frame_length()) {
+        count += 1;
+        pos += length;
+    }
+    count
+}
+
+/// Whether a chain of at least `MIN_CHAIN` frames starts at `pos` and continues past `boundary`
+fn crosses(data: &[u8], mut pos: usize, boundary: usize) -> bool {
+    let mut count = 0;
+    while let Some(length) = MpegFrameHeader::parse(&data[pos.min(data.len())..]).and_then(|header| header.frame_length()) {
+        count += 1;
+        pos += length;
+        if pos > boundary && count >= MIN_CHAIN {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,107 @@
+/// Structural diff of the ID3v2 tags of two files (`compare`)
+///
+/// Meant for checking that a rewrite by another tool kept everything: frames are matched like in
+/// `baseline check` (by ID and description, language, picture type or owner), so reordered frames
+/// are no difference. Frames that exist in one tag only, differing values, differing picture
+/// sizes and differing binary data are reported, as is a different tag version; frames renamed by
+/// a version conversion (TYER to TDRC) show up as removed and added.
+use crate::baseline::{frame_key, frame_value};
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::path_display::display_path;
+use owo_colors::OwoColorize;
+use std::fs::File;
+use std::path::Path;
+
+/// Print the differences between the tags of `first` and `second`; fails if there are any
+pub fn compare_files(first: &Path, second: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let first_tag = Id3v2Tag::read(&mut File::open(first)?)?;
+    let second_tag = Id3v2Tag::read(&mut File::open(second)?)?;
+    outln!("--- {} ({})", display_path(first), describe_tag(first_tag.as_ref()));
+    outln!("+++ {} ({})", display_path(second), describe_tag(second_tag.as_ref()));
+
+    let mut differences = 0;
+    match (&first_tag, &second_tag) {
+        | (Some(a), Some(b)) if a.version_major != b.version_major => {
+            outln!("{}", format!("~ version: ID3v2.{} -> ID3v2.{}", a.version_major, b.version_major).bright_yellow());
+            differences += 1;
+        }
+        | (None, None) => return Err("neither file has an ID3v2 tag".into()),
+        | _ => {}
+    }
+
+    let first_frames = keyed_frames(first_tag.as_ref());
+    let second_frames = keyed_frames(second_tag.as_ref());
+    for (key, frame) in &first_frames {
+        let difference = match second_frames.iter().find(|(other_key, _)| other_key == key) {
+            | None => Some(format!("- {}{}", key, quoted(frame_value(frame))).bright_red().to_string()),
+            | Some((_, other)) => describe_change(key, frame, other).map(|change| change.bright_yellow().to_string()),
+        };
+        if let Some(difference) = difference {
+            outln!("{}", difference);
+            differences += 1;
+        }
+    }
+    for (key, frame) in &second_frames {
+        if !first_frames.iter().any(|(other_key, _)| other_key == key) {
+            outln!("{}", format!("+ {}{}", key, quoted(frame_value(frame))).bright_green());
+            differences += 1;
+        }
+    }
+
+    if differences == 0 {
+        outln!("\nThe tags are equal");
+        return Ok(());
+    }
+    outln!("\n{} difference(s)", differences);
+    Err(format!("the tags differ in {} place(s)", differences).into())
+}
+
+fn describe_tag(tag: Option<&Id3v2Tag>) -> String {
+    match tag {
+        | Some(tag) => format!("ID3v2.{}, {} frame(s)", tag.version_major, tag.frames.len()),
+        | None => "no ID3v2 tag".to_string(),
+    }
+}
+
+/// Frames of a tag in file order with their keys; frames with the same key get "#2", "#3", ...
+fn keyed_frames(tag: Option<&Id3v2Tag>) -> Vec<(String, &Id3v2Frame)> {
+    let mut keyed: Vec<(String, &Id3v2Frame)> = Vec::new();
+    for frame in tag.map(|tag| tag.frames.as_slice()).unwrap_or_default() {
+        let key = frame_key(frame);
+        let count = keyed.iter().filter(|(_, other)| frame_key(other) == key).count();
+        let key = if count == 0 {
+            key
+        } else {
+            format!("{}#{}", key, count + 1)
+        };
+        keyed.push((key, frame));
+    }
+    keyed
+}
+
+/// Difference between two frames with the same key, if any
+fn describe_change(key: &str, first: &Id3v2Frame, second: &Id3v2Frame) -> Option<String> {
+    if let (Some(Id3v2FrameContent::Picture(a)), Some(Id3v2FrameContent::Picture(b))) = (&first.content, &second.content) {
+        if a.picture_data.len() != b.picture_data.len() || a.mime_type != b.mime_type {
+            return Some(format!("~ {}: {}, {} bytes -> {}, {} bytes", key, a.mime_type, a.picture_data.len(), b.mime_type, b.picture_data.len()));
+        }
+        if a.picture_data != b.picture_data {
+            return Some(format!("~ {}: picture data differs ({} bytes)", key, a.picture_data.len()));
+        }
+        return None;
+    }
+    let (first_value, second_value) = (frame_value(first), frame_value(second));
+    if first_value != second_value {
+        return Some(format!("~ {}:{} ->{}", key, quoted(first_value), quoted(second_value)));
+    }
+    // Text is compared by value: a different encoding of the same text is no loss
+    if first_value.is_none() && first.data != second.data {
+        return Some(format!("~ {}: data differs ({} bytes -> {} bytes)", key, first.data.len(), second.data.len()));
+    }
+    None
+}
+
+fn quoted(value: Option<String>) -> String {
+    value.map(|value| format!(" \"{}\"", value)).unwrap_or_default()
+}
@@ -0,0 +1,134 @@
+/// Bulk retagging from CSV spreadsheets (`tag apply-csv`)
+///
+/// Each row names a file in the `path` column; every other column sets one field.
+/// Columns are frame IDs (TIT2), `TXXX:<description>` or friendly aliases (title, artist).
+/// Empty cells leave the field unchanged.
+use crate::batch_errors::BatchErrors;
+use crate::csv_reader::parse_csv;
+use crate::id3v2_writer::write_tag;
+use crate::options::WriteOptions;
+use crate::path_display::display_path;
+use crate::safe_save::DRY_RUN_NOTICE;
+use crate::tag_field::{TagField, read_or_create_tag, set_field};
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Counters for the final summary
+#[derive(Debug, Default)]
+struct ImportSummary {
+    rows: usize,
+    files_changed: usize,
+    files_unchanged: usize,
+    fields_changed: usize,
+    errors: usize,
+}
+
+/// Apply the tag values from `csv_path` to the listed files
+///
+/// Relative paths in the CSV are resolved against the directory containing the CSV file.
+pub fn apply_csv(csv_path: &Path, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(csv_path)?;
+    let rows = parse_csv(&text)?;
+    let Some((header, records)) = rows.split_first() else {
+        return Err("CSV file is empty".into());
+    };
+
+    let path_column = header
+        .iter()
+        .position(|name| name.trim().eq_ignore_ascii_case("path") || name.trim().eq_ignore_ascii_case("file"))
+        .ok_or("CSV header has no 'path' column")?;
+
+    // Resolve all columns up front so a typo fails before any file is touched
+    let mut fields = Vec::new();
+    for (index, name) in header.iter().enumerate() {
+        if index != path_column {
+            fields.push((index, TagField::from_name(name)?));
+        }
+    }
+
+    let base_dir = csv_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut summary = ImportSummary::default();
+    let mut unreadable = BatchErrors::default();
+
+    for (row_index, record) in records.iter().enumerate() {
+        summary.rows += 1;
+        let line = row_index + 2;
+
+        let Some(file) = record.get(path_column).map(|path| path.trim()).filter(|path| !path.is_empty()) else {
+            outln!("{}", format!("Row {}: missing path, skipped", line).bright_red());
+            summary.errors += 1;
+            continue;
+        };
+        let file_path = resolve_path(&base_dir, file);
+
+        // The changes of one file are printed as one block
+        match crate::output::buffered(|| apply_row(&file_path, record, &fields, dry_run, options)) {
+            | Ok(0) => summary.files_unchanged += 1,
+            | Ok(changed) => {
+                summary.files_changed += 1;
+                summary.fields_changed += changed;
+            }
+            | Err(e) if unreadable.record(&file_path, &*e) => {}
+            | Err(e) => {
+                outln!("{}", format!("Row {}: {}: {}", line, display_path(&file_path), e).bright_red());
+                summary.errors += 1;
+            }
+        }
+    }
+
+    outln!(
+        "\nSummary: {} row(s), {} file(s) changed, {} field(s) changed, {} file(s) unchanged, {} error(s)",
+        summary.rows, summary.files_changed, summary.fields_changed, summary.files_unchanged, summary.errors
+    );
+    if dry_run {
+        outln!("{}", DRY_RUN_NOTICE);
+    }
+    unreadable.print_summary("");
+
+    if summary.errors > 0 {
+        return Err(format!("{} row(s) could not be applied", summary.errors).into());
+    }
+    unreadable.finish()
+}
+
+/// Apply one CSV row to a file and return the number of changed fields
+fn apply_row(file_path: &Path, record: &[String], fields: &[(usize, TagField)], dry_run: bool, options: &WriteOptions) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut tag = read_or_create_tag(file_path)?;
+    let mut changes = Vec::new();
+
+    for (index, field) in fields {
+        let Some(value) = record.get(*index).map(|value| value.trim()).filter(|value| !value.is_empty()) else {
+            continue;
+        };
+        if let Some(change) = set_field(&mut tag, field, value)? {
+            changes.push(format!("  {}", change));
+        }
+    }
+
+    if changes.is_empty() {
+        return Ok(0);
+    }
+
+    outln!("{}", display_path(file_path));
+    for change in &changes {
+        outln!("{}", change);
+    }
+
+    if !dry_run {
+        let result = write_tag(file_path, &tag, options)?;
+        outln!("  Written: tag size {} -> {} bytes", result.old_tag_size, result.new_tag_size);
+    }
+
+    Ok(changes.len())
+}
+
+/// Resolve a CSV path relative to the CSV file's directory
+fn resolve_path(base_dir: &Path, file: &str) -> PathBuf {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    }
+}
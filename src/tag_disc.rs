@@ -0,0 +1,154 @@
+/// Disc position and compilation flag written into ID3v2 tags and MP4 files (`tag set-disc`)
+///
+/// ID3v2 tags get a TPOS frame ("2/3") and the iTunes TCMP frame ("1"; a file that is no
+/// compilation loses the frame, since players read a missing flag as "no"). MP4 files get the
+/// `disk` and `cpil` items of `moov/udta/meta/ilst`, which are created if the file has no
+/// metadata yet. The movie box is rebuilt for that, and the chunk offsets (stco, co64) of the
+/// media data behind it are shifted like in `tag mp4 set-track-name`.
+use crate::batch_errors::BatchErrors;
+use crate::disc_info::Position;
+use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_writer::{ensure_writable_version, write_tag};
+use crate::isobmff_atom::{Atom, parse_atoms, read_moov, replace_moov};
+use crate::isobmff_box::read_boxes;
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Type indicator of `data` boxes holding an integer (iTunes "BE signed integer")
+const DATA_TYPE_INTEGER: u32 = 21;
+
+/// Set the disc position and compilation flag of every file
+pub fn set_disc_files(files: &[PathBuf], disc: Option<&str>, compilation: Option<bool>, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let disc = disc.map(|text| Position::parse(text).filter(|position| position.number > 0).ok_or_else(|| format!("'{}' is not a disc position like 2 or 2/3", text))).transpose()?;
+    if let Some(Position { number, total: Some(total) }) = disc
+        && number > total
+    {
+        return Err(format!("disc {} is past the set of {}", number, total).into());
+    }
+
+    let mut changes = Vec::new();
+    if let Some(disc) = disc {
+        changes.push(format!("disc {}", disc));
+    }
+    match compilation {
+        | Some(true) => changes.push("compilation".to_string()),
+        | Some(false) => changes.push("no compilation".to_string()),
+        | None => {}
+    }
+
+    let mut unreadable = BatchErrors::default();
+    let failed = unreadable.change_files(files, |path| {
+        let format = set_disc_file(path, disc, compilation, dry_run, options)?;
+        outln!("{}: {} set in the {}", display_path(path), changes.join(", "), format);
+        Ok(())
+    });
+    unreadable.finish_files(failed, "changed", dry_run)
+}
+
+/// Set the fields in one file and return where they were stored
+fn set_disc_file(path: &Path, disc: Option<Position>, compilation: Option<bool>, dry_run: bool, options: &WriteOptions) -> Result<&'static str, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let mut header = [0u8; 8];
+    let is_isobmff = file.read_exact(&mut header).is_ok() && &header[4..8] == b"ftyp";
+    file.seek(SeekFrom::Start(0))?;
+    if is_isobmff {
+        set_isobmff(path, &mut file, disc, compilation, dry_run, options)?;
+        return Ok("MP4 metadata");
+    }
+
+    let mut tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    drop(file);
+    ensure_writable_version(&tag)?;
+    if let Some(disc) = disc {
+        let value = match disc.total {
+            | Some(total) => format!("{}/{}", disc.number, total),
+            | None => disc.number.to_string(),
+        };
+        tag.replace_frames(Id3v2Frame::new_text("TPOS", vec![value], tag.version_major), |frame| frame.id == "TPOS");
+    }
+    match compilation {
+        | Some(true) => tag.replace_frames(Id3v2Frame::new_text("TCMP", vec!["1".to_string()], tag.version_major), |frame| frame.id == "TCMP"),
+        | Some(false) => tag.frames.retain(|frame| frame.id != "TCMP"),
+        | None => {}
+    }
+    if !dry_run {
+        write_tag(path, &tag, options)?;
+    }
+    Ok("ID3v2 tag")
+}
+
+/// Replace the `disk` and `cpil` items of the movie box and rewrite the file
+fn set_isobmff(path: &Path, file: &mut File, disc: Option<Position>, compilation: Option<bool>, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let file_size = file.metadata()?.len();
+    let boxes = read_boxes(file, 0, file_size)?;
+    let moov_header = boxes.iter().find(|box_header| box_header.box_type == "moov").ok_or("no 'moov' box")?;
+    let mut moov = read_moov(file, moov_header)?;
+
+    if moov.child(b"udta").is_none() {
+        moov.children.get_or_insert_default().push(Atom::container(b"udta", Vec::new()));
+    }
+    let udta = moov.child_mut(b"udta").and_then(|udta| udta.children.as_mut()).ok_or("the 'udta' box cannot be edited")?;
+    if !udta.iter().any(|child| &child.box_type == b"meta") {
+        udta.push(Atom::full(b"meta", 0, &[]));
+    }
+    let meta = udta.iter_mut().find(|child| &child.box_type == b"meta").ok_or("the 'meta' box cannot be edited")?;
+
+    // meta is a full box; its children follow version and flags
+    let mut meta_children = parse_atoms(meta.payload.get(4..).ok_or("the 'meta' box is truncated")?)?;
+    if !meta_children.iter().any(|child| &child.box_type == b"hdlr") {
+        let mut hdlr = vec![0; 4];
+        hdlr.extend_from_slice(b"mdir");
+        hdlr.extend_from_slice(b"appl");
+        hdlr.extend_from_slice(&[0; 9]);
+        meta_children.insert(0, Atom::full(b"hdlr", 0, &hdlr));
+    }
+    if !meta_children.iter().any(|child| &child.box_type == b"ilst") {
+        meta_children.push(Atom::leaf(b"ilst", Vec::new()));
+    }
+    let ilst = meta_children.iter_mut().find(|child| &child.box_type == b"ilst").ok_or("the 'ilst' box cannot be edited")?;
+    let mut items = parse_atoms(&ilst.payload)?;
+
+    if let Some(disc) = disc {
+        let number = u16::try_from(disc.number).map_err(|_| "the disc number does not fit into the 'disk' item")?;
+        let total = u16::try_from(disc.total.unwrap_or(0)).map_err(|_| "the number of discs does not fit into the 'disk' item")?;
+        let mut value = vec![0, 0];
+        value.extend_from_slice(&number.to_be_bytes());
+        value.extend_from_slice(&total.to_be_bytes());
+        set_item(&mut items, b"disk", 0, &value);
+    }
+    if let Some(compilation) = compilation {
+        set_item(&mut items, b"cpil", DATA_TYPE_INTEGER, &[compilation as u8]);
+    }
+
+    ilst.payload.clear();
+    items.iter().for_each(|item| item.encode(&mut ilst.payload));
+    meta.payload.truncate(4);
+    meta_children.iter().for_each(|child| child.encode(&mut meta.payload));
+
+    if !dry_run {
+        let shifted = replace_moov(path, file, &boxes, moov_header, moov, options)?;
+        if shifted > 0 {
+            outln!("Shifted {} chunk offsets behind the grown 'moov' box", shifted);
+        }
+    }
+    Ok(())
+}
+
+/// Replace the item `item_type` of an `ilst` box (or append it) by one with a single `data` box
+fn set_item(items: &mut Vec<Atom>, item_type: &[u8; 4], data_type: u32, value: &[u8]) {
+    // Type indicator and locale precede the value
+    let mut data = data_type.to_be_bytes().to_vec();
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(value);
+    let mut payload = Vec::new();
+    Atom::leaf(b"data", data).encode(&mut payload);
+    let item = Atom::leaf(item_type, payload);
+    match items.iter_mut().find(|existing| &existing.box_type == item_type) {
+        | Some(existing) => *existing = item,
+        | None => items.push(item),
+    }
+}
@@ -0,0 +1,164 @@
+/// Text fields of ID3v2 tags as named by users (`tag apply-csv` columns, `tag write --set` keys)
+///
+/// A field is a text frame ID (TIT2), `TXXX:<description>` or a friendly alias (title, artist);
+/// "year" and "date" map to TYER in ID3v2.3 and TDRC in ID3v2.4.
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_text_frame::ID3V2_3_VALUE_SEPARATOR;
+use crate::id3v2_tools::{detect_mpeg_sync, is_valid_frame_for_version, is_valid_id3v2_3_frame, is_valid_id3v2_4_frame};
+use crate::id3v2_writer::ensure_writable_version;
+use crate::path_display::long_path;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Friendly column names and the text frames they map to
+const FIELD_ALIASES: &[(&str, &str)] = &[
+    ("title", "TIT2"),
+    ("subtitle", "TIT3"),
+    ("grouping", "TIT1"),
+    ("artist", "TPE1"),
+    ("album_artist", "TPE2"),
+    ("albumartist", "TPE2"),
+    ("conductor", "TPE3"),
+    ("album", "TALB"),
+    ("genre", "TCON"),
+    ("track", "TRCK"),
+    ("tracknumber", "TRCK"),
+    ("disc", "TPOS"),
+    ("discnumber", "TPOS"),
+    ("composer", "TCOM"),
+    ("lyricist", "TEXT"),
+    ("publisher", "TPUB"),
+    ("label", "TPUB"),
+    ("copyright", "TCOP"),
+    ("bpm", "TBPM"),
+    ("isrc", "TSRC"),
+    ("encoded_by", "TENC"),
+    ("language", "TLAN"),
+    ("key", "TKEY"),
+];
+
+/// Text field of an ID3v2 tag, named by frame ID, `TXXX:<description>` or a friendly alias
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagField {
+    /// Text information frame (T*** except TXXX)
+    Text(String),
+    /// User-defined text frame (TXXX) with this description
+    UserText(String),
+    /// Recording year/date (TYER in ID3v2.3, TDRC in ID3v2.4)
+    Year,
+}
+
+impl TagField {
+    /// Resolve a field name (CSV column, `--set` key) to a field
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let trimmed = name.trim();
+
+        if let Some(description) = trimmed.strip_prefix("TXXX:").or_else(|| trimmed.strip_prefix("txxx:")) {
+            return Ok(TagField::UserText(description.to_string()));
+        }
+
+        if trimmed.len() == 4 && trimmed.starts_with('T') && trimmed != "TXXX" && (is_valid_id3v2_3_frame(trimmed) || is_valid_id3v2_4_frame(trimmed)) {
+            return Ok(TagField::Text(trimmed.to_string()));
+        }
+
+        let alias = trimmed.to_lowercase().replace([' ', '-'], "_");
+        if alias == "year" || alias == "date" {
+            return Ok(TagField::Year);
+        }
+        FIELD_ALIASES
+            .iter()
+            .find(|(name, _)| *name == alias)
+            .map(|(_, frame_id)| TagField::Text(frame_id.to_string()))
+            .ok_or_else(|| format!("Unknown field '{}' (use a text frame ID, TXXX:<description> or a field name like title/artist/album)", trimmed))
+    }
+
+    /// Frame ID for this field in the given ID3v2 version
+    pub fn frame_id(&self, version_major: u8) -> String {
+        match self {
+            | TagField::Text(id) => id.clone(),
+            | TagField::UserText(_) => "TXXX".to_string(),
+            | TagField::Year if version_major == 4 => "TDRC".to_string(),
+            | TagField::Year => "TYER".to_string(),
+        }
+    }
+
+    /// Label used in the change report
+    pub fn label(&self, version_major: u8) -> String {
+        match self {
+            | TagField::UserText(description) => format!("TXXX:{}", description),
+            | _ => self.frame_id(version_major),
+        }
+    }
+}
+
+/// Set a field to `value`; returns the change ("TIT2: (not set) -> \"Title\""), or `None` if it already had the value
+pub fn set_field(tag: &mut Id3v2Tag, field: &TagField, value: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let frame_id = field.frame_id(tag.version_major);
+    if !is_valid_frame_for_version(&frame_id, tag.version_major) {
+        return Err(format!("{} is not a valid ID3v2.{} frame", frame_id, tag.version_major).into());
+    }
+
+    let current = current_value(tag, field);
+    if current.as_deref() == Some(value) {
+        return Ok(None);
+    }
+
+    match field {
+        | TagField::UserText(description) => {
+            let frame = Id3v2Frame::new_user_text(description, value, tag.version_major);
+            tag.replace_frames(frame, |existing| user_text_description(existing).is_some_and(|d| d.eq_ignore_ascii_case(description)));
+        }
+        | _ => {
+            let frame = Id3v2Frame::new_text(&frame_id, vec![value.to_string()], tag.version_major);
+            tag.replace_frames(frame, |existing| existing.id == frame_id);
+        }
+    }
+
+    let old = current.map(|text| format!("\"{}\"", text)).unwrap_or_else(|| "(not set)".to_string());
+    Ok(Some(format!("{}: {} -> \"{}\"", field.label(tag.version_major), old, value)))
+}
+
+/// Read the existing ID3v2 tag, or start a new ID3v2.4 tag for untagged MPEG audio
+pub fn read_or_create_tag(file_path: &Path) -> Result<Id3v2Tag, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(file_path))?;
+
+    if let Some(tag) = Id3v2Tag::read(&mut file)? {
+        ensure_writable_version(&tag)?;
+        return Ok(tag);
+    }
+
+    let mut header = [0u8; 4];
+    file.seek(SeekFrom::Start(0))?;
+    if file.read_exact(&mut header).is_err() || !detect_mpeg_sync(&header) {
+        return Err("not an MPEG audio file with or without ID3v2 tag".into());
+    }
+    Ok(Id3v2Tag::new(4))
+}
+
+/// Current value of a field in the tag, if set
+pub fn current_value(tag: &Id3v2Tag, field: &TagField) -> Option<String> {
+    match field {
+        | TagField::UserText(description) => tag.frames.iter().find_map(|frame| match &frame.content {
+            | Some(Id3v2FrameContent::UserText(user_text)) if user_text.description.eq_ignore_ascii_case(description) => Some(user_text.value.clone()),
+            | _ => None,
+        }),
+        | _ => {
+            let frame_id = field.frame_id(tag.version_major);
+            tag.frames
+                .iter()
+                .find(|frame| frame.id == frame_id)
+                .and_then(|frame| frame.get_text_values())
+                .map(|values| values.join(ID3V2_3_VALUE_SEPARATOR))
+        }
+    }
+}
+
+/// Description of a TXXX frame
+fn user_text_description(frame: &Id3v2Frame) -> Option<&str> {
+    match &frame.content {
+        | Some(Id3v2FrameContent::UserText(user_text)) => Some(&user_text.description),
+        | _ => None,
+    }
+}
@@ -0,0 +1,65 @@
+/// Rewriting of TCON genre frames in a chosen style (`tag genres`)
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_genre::{GenreStyle, format_genres};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_writer::{ensure_writable_version, write_tag};
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Old and new TCON values of a file
+type GenreChange = (Vec<String>, Vec<String>);
+
+/// Rewrite the genres of every file in `style`
+pub fn rewrite_genres(files: &[PathBuf], style: GenreStyle, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unreadable = BatchErrors::default();
+    let failed = unreadable.change_files(files, |path| {
+        match rewrite_file(path, style, dry_run, options)? {
+            | None => outln!("{}: no genre", display_path(path)),
+            | Some((old, new)) if old == new => outln!("{}: unchanged ({})", display_path(path), describe(&new)),
+            | Some((old, new)) => outln!("{}: {} -> {}", display_path(path), describe(&old), describe(&new)),
+        }
+        Ok(())
+    });
+    unreadable.finish_files(failed, "rewritten", dry_run)
+}
+
+/// Rewrite one file and return the old and new TCON values
+fn rewrite_file(path: &Path, style: GenreStyle, dry_run: bool, options: &WriteOptions) -> Result<Option<GenreChange>, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let mut tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    drop(file);
+
+    ensure_writable_version(&tag)?;
+    if style == GenreStyle::MultiValue && tag.version_major < 4 {
+        return Err("ID3v2.3 has no multi-value text frames (use --style id3v23 or slash)".into());
+    }
+
+    let Some(old) = tag
+        .frames
+        .iter()
+        .find(|frame| frame.id == "TCON")
+        .and_then(|frame| frame.get_text_values())
+        .map(<[String]>::to_vec)
+    else {
+        return Ok(None);
+    };
+    let genres = tag.genres();
+    if genres.is_empty() {
+        return Ok(None);
+    }
+
+    let new = format_genres(&genres, style);
+    if new != old && !dry_run {
+        let frame = Id3v2Frame::new_text("TCON", new.clone(), tag.version_major);
+        tag.replace_frames(frame, |existing| existing.id == "TCON");
+        write_tag(path, &tag, options)?;
+    }
+    Ok(Some((old, new)))
+}
+
+fn describe(values: &[String]) -> String {
+    values.iter().map(|value| format!("\"{}\"", value)).collect::<Vec<_>>().join(" + ")
+}
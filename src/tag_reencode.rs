@@ -0,0 +1,44 @@
+/// Re-encoding of all text frames of ID3v2 tags (`tag reencode`)
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_text_encoding::TextEncoding;
+use crate::id3v2_writer::{ensure_writable_version, write_tag};
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Re-encode the text frames of every file in `encoding`
+pub fn reencode_files(files: &[PathBuf], encoding: TextEncoding, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unreadable = BatchErrors::default();
+    let failed = unreadable.change_files(files, |path| {
+        match reencode_file(path, encoding, dry_run, options)? {
+            | 0 => outln!("{}: already {}", display_path(path), encoding),
+            | changed => outln!("{}: {} frame(s) re-encoded to {}", display_path(path), changed, encoding),
+        }
+        Ok(())
+    });
+    unreadable.finish_files(failed, "re-encoded", dry_run)
+}
+
+/// Re-encode one file and return the number of frames changed
+fn reencode_file(path: &Path, encoding: TextEncoding, dry_run: bool, options: &WriteOptions) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let mut tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    drop(file);
+
+    ensure_writable_version(&tag)?;
+    if !encoding.is_valid_for_version(tag.version_major) {
+        return Err(format!("{} is not allowed in ID3v2.{} tags", encoding, tag.version_major).into());
+    }
+
+    let mut changed = 0;
+    for frame in &mut tag.frames {
+        changed += frame.reencode(encoding, tag.version_major)?;
+    }
+
+    if changed > 0 && !dry_run {
+        write_tag(path, &tag, options)?;
+    }
+    Ok(changed)
+}
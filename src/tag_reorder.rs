@@ -0,0 +1,37 @@
+/// Rewriting of ID3v2 tags with their frames in the recommended order (`tag reorder`)
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_frame_order::canonicalize;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_writer::{ensure_writable_version, write_tag};
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Put the frames of every file into the recommended order
+pub fn reorder_files(files: &[PathBuf], dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unreadable = BatchErrors::default();
+    let failed = unreadable.change_files(files, |path| {
+        match reorder_file(path, dry_run, options)? {
+            | 0 => outln!("{}: already in the recommended order", display_path(path)),
+            | moved => outln!("{}: {} frame(s) moved", display_path(path), moved),
+        }
+        Ok(())
+    });
+    unreadable.finish_files(failed, "reordered", dry_run)
+}
+
+/// Reorder one file and return the number of frames that changed position
+fn reorder_file(path: &Path, dry_run: bool, options: &WriteOptions) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let mut tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    drop(file);
+
+    ensure_writable_version(&tag)?;
+
+    let moved = canonicalize(&mut tag.frames);
+    if moved > 0 && !dry_run {
+        write_tag(path, &tag, options)?;
+    }
+    Ok(moved)
+}
@@ -0,0 +1,93 @@
+/// Repair of sort order frames (`tag fix-sort`)
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_frame::Id3v2Frame;
+use crate::id3v2_sort_order::{SORT_FRAMES, base_frame_id, is_consistent, sort_frame_id, sort_string, sort_value};
+use crate::id3v2_tag::Id3v2Tag;
+use crate::id3v2_writer::{ensure_writable_version, write_tag};
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// A sort order frame that is set (with its new values) or removed
+enum SortChange {
+    Set(&'static str, Vec<String>),
+    Removed(String),
+}
+
+/// Fix the sort order frames of every file
+///
+/// Sort values that are non-empty and share a word with their frame are kept, so deliberate
+/// values like "Bowie, David" survive. Empty and mismatching sort values, and missing ones for
+/// values with a leading article, are replaced by the value without the article; where that is
+/// the value itself, the sort frame is removed. Sort frames for missing frames are removed too.
+pub fn fix_sort_files(files: &[PathBuf], dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut unreadable = BatchErrors::default();
+    let failed = unreadable.change_files(files, |path| {
+        let changes = fix_sort_file(path, dry_run, options)?;
+        if changes.is_empty() {
+            outln!("{}: sort order frames are consistent", display_path(path));
+        }
+        for change in changes {
+            match change {
+                | SortChange::Set(id, values) => outln!("{}: {} -> {}", display_path(path), id, describe(&values)),
+                | SortChange::Removed(id) => outln!("{}: {} removed", display_path(path), id),
+            }
+        }
+        Ok(())
+    });
+    unreadable.finish_files(failed, "fixed", dry_run)
+}
+
+/// Fix one file and return the sort order frames set or removed
+fn fix_sort_file(path: &Path, dry_run: bool, options: &WriteOptions) -> Result<Vec<SortChange>, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let mut tag = Id3v2Tag::read(&mut file)?.ok_or("no ID3v2 tag")?;
+    drop(file);
+
+    ensure_writable_version(&tag)?;
+
+    let mut changes = Vec::new();
+    for (_, base_id) in SORT_FRAMES {
+        let sorts_base = |frame: &Id3v2Frame| base_frame_id(&frame.id) == Some(*base_id);
+        let values = tag.frames.iter().find(|frame| frame.id == *base_id).and_then(|frame| frame.get_text_values()).map(<[String]>::to_vec);
+        let existing = tag.frames.iter().find(|frame| sorts_base(frame));
+
+        // Keep sort values that fit, and those that were not parsed (XSO* without --lenient)
+        if let Some(existing) = existing {
+            let keep = match (sort_value(existing), &values) {
+                | (None, _) => true,
+                | (Some(sort_value), Some(values)) => !sort_value.trim().is_empty() && is_consistent(&sort_value, &values.join(" ")),
+                | (Some(_), None) => false,
+            };
+            if keep {
+                continue;
+            }
+        }
+
+        let sort_values: Option<Vec<String>> = values.as_ref().map(|values| values.iter().map(|value| sort_string(value)).collect()).filter(|sort_values| Some(sort_values) != values.as_ref());
+        match (sort_values, existing) {
+            | (Some(sort_values), _) => {
+                let Some(sort_id) = sort_frame_id(base_id, tag.version_major) else {
+                    continue;
+                };
+                tag.replace_frames(Id3v2Frame::new_text(sort_id, sort_values.clone(), tag.version_major), sorts_base);
+                changes.push(SortChange::Set(sort_id, sort_values));
+            }
+            | (None, Some(existing)) => {
+                changes.push(SortChange::Removed(existing.id.clone()));
+                tag.frames.retain(|frame| !sorts_base(frame));
+            }
+            | (None, None) => {}
+        }
+    }
+
+    if !changes.is_empty() && !dry_run {
+        write_tag(path, &tag, options)?;
+    }
+    Ok(changes)
+}
+
+fn describe(values: &[String]) -> String {
+    values.iter().map(|value| format!("\"{}\"", value)).collect::<Vec<_>>().join(" + ")
+}
@@ -0,0 +1,153 @@
+/// Removal of tags from MP3 files (`strip`)
+///
+/// The ID3v2 tags at the start of the file are always removed, the blocks after the audio only on
+/// request: ID3v1 (with its Enhanced "TAG+" block) with `--id3v1`, APEv1/APEv2 with `--ape`.
+/// Other blocks, such as Lyrics3, stay where they are. The remaining bytes are copied unchanged,
+/// in place through a temporary file (see `safe_save`) or to `--output`.
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_tag::parse_tag_header;
+use crate::media_source::MediaSource;
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use crate::safe_save::replace_file_with;
+use crate::trailer_tags::find_trailers;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Which tags to remove besides the ID3v2 tags
+#[derive(Debug, Clone, Copy)]
+pub struct StripSelection {
+    pub id3v1: bool,
+    pub ape: bool,
+}
+
+/// A tag found for removal
+struct StrippedTag {
+    kind: String,
+    offset: u64,
+    size: u64,
+}
+
+/// Remove the tags from every file, or write the stripped copy of a single file to `output`
+pub fn strip_files(files: &[PathBuf], selection: StripSelection, output: Option<&Path>, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(output) = output {
+        let [input] = files else {
+            return Err("--output takes a single input file".into());
+        };
+        if let (Ok(input), Ok(output)) = (fs::canonicalize(input), fs::canonicalize(output))
+            && input == output
+        {
+            return Err("the output would overwrite the input file; leave out --output to strip in place".into());
+        }
+    }
+
+    let mut unreadable = BatchErrors::default();
+    let mut reclaimed = 0;
+    let failed = unreadable.change_files(files, |path| {
+        let tags = strip_file(path, selection, output, dry_run)?;
+        if tags.is_empty() {
+            outln!("{}: no tags to strip", display_path(path));
+        } else {
+            let size: u64 = tags.iter().map(|tag| tag.size).sum();
+            let removed: Vec<String> = tags.iter().map(|tag| format!("{} at 0x{:08X} ({} bytes)", tag.kind, tag.offset, tag.size)).collect();
+            outln!("{}: {} removed, {} bytes reclaimed", display_path(path), removed.join(", "), size);
+            reclaimed += size;
+        }
+        Ok(())
+    });
+
+    if let Some(output) = output.filter(|_| !dry_run && failed == 0 && unreadable.is_empty()) {
+        outln!("Stripped copy written: {}", display_path(output));
+    }
+    if files.len() > 1 {
+        outln!("{} bytes reclaimed in {} file(s)", reclaimed, files.len() - failed - unreadable.len());
+    }
+    unreadable.finish_files(failed, "stripped", dry_run)
+}
+
+/// Strip one file and return the tags removed
+fn strip_file(path: &Path, selection: StripSelection, output: Option<&Path>, dry_run: bool) -> Result<Vec<StrippedTag>, Box<dyn std::error::Error>> {
+    let mut file = File::open(long_path(path))?;
+    let mut tags = leading_id3v2_tags(&mut file)?;
+    let audio_start = tags.last().map_or(0, |tag| tag.offset + tag.size);
+    for block in find_trailers(&mut file, audio_start)? {
+        let selected = match block.kind {
+            | "ID3v1" | "ID3v1 Enhanced" => selection.id3v1,
+            | "APEv1" | "APEv2" => selection.ape,
+            | _ => false,
+        };
+        if selected {
+            tags.push(StrippedTag { kind: block.kind.to_string(), offset: block.offset, size: block.size });
+        }
+    }
+    if dry_run || (tags.is_empty() && output.is_none()) {
+        return Ok(tags);
+    }
+
+    // The bytes between the removed tags, in file order
+    let file_size = file.byte_len()?;
+    let mut kept = Vec::new();
+    let mut position = 0;
+    for tag in &tags {
+        if tag.offset > position {
+            kept.push((position, tag.offset - position));
+        }
+        position = tag.offset + tag.size;
+    }
+    if file_size > position {
+        kept.push((position, file_size - position));
+    }
+    let expected_size: u64 = kept.iter().map(|(_, size)| size).sum();
+
+    let mut write = |target: &mut File| -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = BufWriter::new(target);
+        for &(offset, size) in &kept {
+            file.seek(SeekFrom::Start(offset))?;
+            io::copy(&mut (&mut file).take(size), &mut writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    };
+    let check = |written: &Path| -> Result<(), Box<dyn std::error::Error>> {
+        let written_size = fs::metadata(written)?.len();
+        if written_size != expected_size {
+            return Err(format!("the stripped file has {} bytes instead of {}", written_size, expected_size).into());
+        }
+        Ok(())
+    };
+    match output {
+        | Some(output) => {
+            write(&mut File::create(output)?)?;
+            check(output)?;
+        }
+        | None => replace_file_with(path, write, &WriteOptions::default(), check)?,
+    }
+    Ok(tags)
+}
+
+/// The ID3v2 tags at the start of the file (taggers sometimes prepend a new tag to an old one)
+fn leading_id3v2_tags(file: &mut File) -> Result<Vec<StrippedTag>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut tags = Vec::new();
+    let mut offset = 0;
+    loop {
+        let mut header = [0u8; 10];
+        file.seek(SeekFrom::Start(offset))?;
+        if file.read_exact(&mut header).is_err() {
+            break;
+        }
+        let Some((major, _minor, flags, size)) = parse_tag_header(&header) else {
+            break;
+        };
+        let footer = if major == 4 && flags & 0x10 != 0 {
+            10
+        } else {
+            0
+        };
+        let tag_size = (10 + size as u64 + footer).min(file_size - offset);
+        tags.push(StrippedTag { kind: format!("ID3v2.{}", major), offset, size: tag_size });
+        offset += tag_size;
+    }
+    Ok(tags)
+}
@@ -0,0 +1,104 @@
+/// Setting text frames and artwork from the command line (`tag write`)
+///
+/// `--set FIELD=VALUE` takes the fields of `tag apply-csv` (frame IDs, `TXXX:<description>`,
+/// aliases like title) and replaces the frame or adds it. `--artwork` replaces the pictures of the
+/// same picture type by a JPEG or PNG file. Untagged MPEG audio gets a new ID3v2.4 tag; ID3v2.3
+/// and ID3v2.4 tags keep their version. The tag is written by `id3v2_writer`, which encodes the
+/// sizes for the version (plain or synchsafe) and reuses or adds padding.
+use crate::batch_errors::BatchErrors;
+use crate::id3v2_attached_picture_frame::picture_type_description;
+use crate::id3v2_frame::{Id3v2Frame, Id3v2FrameContent};
+use crate::id3v2_writer::write_tag;
+use crate::options::WriteOptions;
+use crate::path_display::{display_path, long_path};
+use crate::tag_field::{TagField, read_or_create_tag, set_field};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Picture to attach to every file
+struct Artwork {
+    mime_type: &'static str,
+    picture_type: u8,
+    data: Vec<u8>,
+}
+
+/// Set the fields and artwork in every file
+pub fn write_files(files: &[PathBuf], set: &[String], artwork: Option<&Path>, picture_type: u8, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    // Resolve all fields and read the picture up front so a typo fails before any file is touched
+    let mut fields = Vec::new();
+    for assignment in set {
+        let (name, value) = assignment.split_once('=').ok_or_else(|| format!("'{}' is not FIELD=VALUE", assignment))?;
+        fields.push((TagField::from_name(name)?, value.to_string()));
+    }
+    let artwork = match artwork {
+        | Some(path) => Some(read_artwork(path, picture_type)?),
+        | None => None,
+    };
+    if fields.is_empty() && artwork.is_none() {
+        return Err("nothing to write (use --set or --artwork)".into());
+    }
+
+    let mut unreadable = BatchErrors::default();
+    // The changes of one file are printed as one block
+    let failed = unreadable.change_files(files, |path| crate::output::buffered(|| write_file(path, &fields, artwork.as_ref(), dry_run, options)));
+    unreadable.finish_files(failed, "written", dry_run)
+}
+
+/// Apply the fields and artwork to one file
+fn write_file(path: &Path, fields: &[(TagField, String)], artwork: Option<&Artwork>, dry_run: bool, options: &WriteOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tag = read_or_create_tag(path)?;
+    let mut changes = Vec::new();
+
+    for (field, value) in fields {
+        if let Some(change) = set_field(&mut tag, field, value)? {
+            changes.push(change);
+        }
+    }
+    if let Some(artwork) = artwork {
+        let frame = Id3v2Frame::new_picture(artwork.mime_type, artwork.picture_type, "", artwork.data.clone(), tag.version_major);
+        let same_type = |existing: &Id3v2Frame| matches!(&existing.content, Some(Id3v2FrameContent::Picture(picture)) if picture.picture_type == artwork.picture_type);
+        let replaced = tag.frames.iter().filter(|existing| same_type(existing)).count();
+        tag.replace_frames(frame, same_type);
+        changes.push(format!(
+            "APIC: {} {}, {} bytes{}",
+            picture_type_description(artwork.picture_type),
+            artwork.mime_type,
+            artwork.data.len(),
+            if replaced > 0 {
+                format!(" (replaces {} picture(s))", replaced)
+            } else {
+                String::new()
+            }
+        ));
+    }
+
+    outln!("{} (ID3v2.{})", display_path(path), tag.version_major);
+    if changes.is_empty() {
+        outln!("  Unchanged");
+        return Ok(());
+    }
+    for change in &changes {
+        outln!("  {}", change);
+    }
+    if !dry_run {
+        let result = write_tag(path, &tag, options)?;
+        outln!("  Written: tag size {} -> {} bytes", result.old_tag_size, result.new_tag_size);
+    }
+    Ok(())
+}
+
+/// Read a JPEG or PNG picture
+fn read_artwork(path: &Path, picture_type: u8) -> Result<Artwork, Box<dyn std::error::Error>> {
+    if picture_type > 20 {
+        return Err(format!("picture type {} is not defined (0 to 20, 3 is the front cover)", picture_type).into());
+    }
+    let data = fs::read(long_path(path))?;
+    let mime_type = if data.starts_with(&[0xFF, 0xD8]) {
+        "image/jpeg"
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else {
+        return Err(format!("{} is neither a JPEG nor a PNG picture", display_path(path)).into());
+    };
+    Ok(Artwork { mime_type, picture_type, data })
+}
@@ -0,0 +1,37 @@
+/// Unicode NFC normalization of decoded text (`--nfc`, `tag --nfc`)
+///
+/// The same text can be stored precomposed ("é" as U+00E9, NFC) or decomposed (e + U+0301,
+/// NFD); macOS file names and some taggers produce the latter. Both look the same, but compare
+/// and sort differently, so a library with mixed forms shows duplicate artists and albums. With
+/// `--nfc` all decoded text is normalized to NFC; `tag --nfc` also stores it that way. Values
+/// that change under normalization are reported by the frame content checks either way.
+use crate::options::CommonOptions;
+use unicode_normalization::{UnicodeNormalization, is_nfc};
+
+/// Whether decoded text is normalized to NFC
+pub fn nfc_decoding() -> bool {
+    CommonOptions::current().nfc
+}
+
+/// Apply the normalization chosen with `--nfc` to a decoded string
+pub fn normalize_decoded(text: String) -> String {
+    if nfc_decoding() {
+        to_nfc(text)
+    } else {
+        text
+    }
+}
+
+/// `text` in NFC, unchanged (without copying) if it already is
+pub fn to_nfc(text: String) -> String {
+    if is_nfc(&text) {
+        text
+    } else {
+        text.nfc().collect()
+    }
+}
+
+/// Whether `text` changes under NFC normalization
+pub fn changes_under_nfc(text: &str) -> bool {
+    !is_nfc(text)
+}
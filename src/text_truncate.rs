@@ -0,0 +1,46 @@
+/// Shortening of long text values for display (`--max-text-width`, `--ellipsis`)
+///
+/// Comments, lyrics and user-defined fields can be kilobytes long. With `--max-text-width` every
+/// display path shortens text values through `truncate_text`, so all of them cut at the same
+/// width in the same style. The width counts grapheme clusters (what a reader sees as one
+/// character), not bytes or code points: "é" written as e + combining accent, flags and emoji
+/// with skin tones or ZWJ sequences are never cut apart. The ellipsis counts towards the width.
+///
+/// Sidecar files, exports and written tags always keep the full text.
+use crate::options::{CommonOptions, EllipsisStyle};
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Marker for the removed part of a shortened value
+pub const ELLIPSIS: &str = "…";
+
+/// Shorten a value for display to the width chosen with `--max-text-width`
+pub fn truncate_text(text: &str) -> Cow<'_, str> {
+    let options = CommonOptions::current();
+    match options.max_text_width.filter(|&width| width > 0) {
+        | Some(width) => truncate_to(text, width, options.ellipsis),
+        | None => Cow::Borrowed(text),
+    }
+}
+
+/// Shorten `text` to at most `width` grapheme clusters, the ellipsis included
+///
+/// `End` keeps the start of the text, `Middle` its start and end (for paths and URLs, whose
+/// end tells them apart).
+pub fn truncate_to(text: &str, width: usize, style: EllipsisStyle) -> Cow<'_, str> {
+    let graphemes: Vec<(usize, &str)> = text.grapheme_indices(true).collect();
+    if graphemes.len() <= width {
+        return Cow::Borrowed(text);
+    }
+    // Room for the text next to the ellipsis
+    let kept = width.saturating_sub(1);
+    let offset = |index: usize| graphemes.get(index).map_or(text.len(), |&(offset, _)| offset);
+    match style {
+        | EllipsisStyle::End => Cow::Owned(format!("{}{}", text[..offset(kept)].trim_end(), ELLIPSIS)),
+        | EllipsisStyle::Middle => {
+            let head = kept.div_ceil(2);
+            let tail = kept - head;
+            Cow::Owned(format!("{}{}{}", &text[..offset(head)], ELLIPSIS, &text[offset(graphemes.len() - tail)..]))
+        }
+    }
+}
@@ -0,0 +1,80 @@
+/// Formatting of times and durations (`--time-format`)
+///
+/// All positions and durations go through `format_duration`, so human readers and scripts
+/// get the same values in the style they chose. The style is set once from the command line.
+use crate::options::{CommonOptions, TimeFormat};
+
+fn time_format() -> TimeFormat {
+    CommonOptions::current().time_format
+}
+
+/// Format a position or duration given in milliseconds
+///
+/// `hms` gives "01:02:03.450", `ms` "3723450", `seconds` "3723.450" and `iso8601` an
+/// ISO 8601 duration ("PT1H2M3.45S").
+pub fn format_duration(ms: u64) -> String {
+    let total_seconds = ms / 1000;
+    let milliseconds = ms % 1000;
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let seconds = total_seconds % 60;
+
+    match time_format() {
+        | TimeFormat::Hms => format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, milliseconds),
+        | TimeFormat::Ms => ms.to_string(),
+        | TimeFormat::Seconds => format!("{}.{:03}", total_seconds, milliseconds),
+        | TimeFormat::Iso8601 => {
+            let mut duration = String::from("PT");
+            if hours > 0 {
+                duration.push_str(&format!("{}H", hours));
+            }
+            if minutes > 0 {
+                duration.push_str(&format!("{}M", minutes));
+            }
+            if seconds > 0 || milliseconds > 0 || duration.len() == 2 {
+                duration.push_str(&seconds.to_string());
+                if milliseconds > 0 {
+                    duration.push_str(format!(".{:03}", milliseconds).trim_end_matches('0'));
+                }
+                duration.push('S');
+            }
+            duration
+        }
+    }
+}
+
+/// Format a point in time given as seconds since the Unix epoch (UTC)
+///
+/// `hms` gives "2024-03-01 12:30:00 UTC", `iso8601` "2024-03-01T12:30:00Z", `seconds` and
+/// `ms` the Unix time.
+pub fn format_date(unix_seconds: i64) -> String {
+    match time_format() {
+        | TimeFormat::Hms => iso8601_date(unix_seconds).replace('T', " ").replace('Z', " UTC"),
+        | TimeFormat::Iso8601 => iso8601_date(unix_seconds),
+        | TimeFormat::Seconds => unix_seconds.to_string(),
+        | TimeFormat::Ms => (unix_seconds * 1000).to_string(),
+    }
+}
+
+/// ISO 8601 UTC date and time ("2024-03-01T12:30:00Z"), independent of `--time-format`
+pub fn iso8601_date(unix_seconds: i64) -> String {
+    let days = unix_seconds.div_euclid(86400);
+    let seconds_of_day = unix_seconds.rem_euclid(86400);
+
+    // Civil date from days since 1970-01-01 (proleptic Gregorian calendar)
+    let shifted = days + 719468;
+    let era = shifted.div_euclid(146097);
+    let day_of_era = shifted.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, seconds_of_day / 3600, seconds_of_day % 3600 / 60, seconds_of_day % 60)
+}
@@ -0,0 +1,122 @@
+/// Tag blocks at the end of MP3 files (APEv2, Lyrics3, ID3v1)
+///
+/// Taggers append their blocks after the audio, so a file can end in a stack such as
+/// audio, APEv2, Lyrics3v2, ID3v1, in any order and with repeated blocks. The stack is
+/// walked backwards from the end of the file, one recognized block at a time.
+use crate::audio_hash::audio_ranges;
+use crate::media_source::MediaSource;
+use serde::Serialize;
+use std::io::SeekFrom;
+
+/// Size of an ID3v1 tag
+const ID3V1_SIZE: u64 = 128;
+/// Size of the Enhanced ID3v1 block ("TAG+") in front of an ID3v1 tag
+const ID3V1_ENHANCED_SIZE: u64 = 227;
+/// Size of an APE tag header and footer
+const APE_FOOTER_SIZE: u64 = 32;
+/// Maximum size of a Lyrics3v1 block after "LYRICSBEGIN"
+const LYRICS3V1_MAX_SIZE: u64 = 5100;
+
+/// A tag block found after the audio
+#[derive(Debug, Clone, Serialize)]
+pub struct TrailerBlock {
+    /// Block type ("ID3v1", "ID3v1 Enhanced", "APEv1", "APEv2", "Lyrics3v1", "Lyrics3v2")
+    pub kind: &'static str,
+    /// File offset of the first byte of the block
+    pub offset: u64,
+    /// Size of the block in bytes
+    pub size: u64,
+}
+
+/// Tag blocks between `audio_start` and the end of the file, in file order
+pub fn find_trailers(file: &mut dyn MediaSource, audio_start: u64) -> Result<Vec<TrailerBlock>, Box<dyn std::error::Error>> {
+    let mut end = file.byte_len()?;
+    let mut blocks = Vec::new();
+
+    while let Some((kind, size)) = block_before(file, audio_start, end)? {
+        end -= size;
+        blocks.push(TrailerBlock { kind, offset: end, size });
+    }
+
+    blocks.reverse();
+    Ok(blocks)
+}
+
+/// Print the tag blocks after the audio, if there are any
+pub fn print_trailers(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    let blocks = trailers(file)?;
+    if !blocks.is_empty() {
+        outln!("\nTrailing Tags:");
+        for block in blocks {
+            outln!("    {} at 0x{:08X} ({} bytes)", block.kind, block.offset, block.size);
+        }
+    }
+    Ok(())
+}
+
+/// Tag blocks after the audio of a file with or without ID3v2 tag
+pub fn trailers(file: &mut dyn MediaSource) -> Result<Vec<TrailerBlock>, Box<dyn std::error::Error>> {
+    let audio_start = audio_ranges(file)?.first().map_or(0, |&(start, _)| start);
+    find_trailers(file, audio_start)
+}
+
+/// Type and size of the block that ends at `end`, if it is a recognized tag block
+fn block_before(file: &mut dyn MediaSource, audio_start: u64, end: u64) -> Result<Option<(&'static str, u64)>, Box<dyn std::error::Error>> {
+    let available = end - audio_start;
+
+    if available >= ID3V1_SIZE && read_at(file, end - ID3V1_SIZE, 3)? == b"TAG" {
+        return Ok(Some(("ID3v1", ID3V1_SIZE)));
+    }
+    if available >= ID3V1_ENHANCED_SIZE && read_at(file, end - ID3V1_ENHANCED_SIZE, 4)? == b"TAG+" {
+        return Ok(Some(("ID3v1 Enhanced", ID3V1_ENHANCED_SIZE)));
+    }
+
+    if available >= APE_FOOTER_SIZE {
+        let footer = read_at(file, end - APE_FOOTER_SIZE, APE_FOOTER_SIZE as usize)?;
+        if &footer[0..8] == b"APETAGEX" {
+            let read_u32 = |pos: usize| u32::from_le_bytes([footer[pos], footer[pos + 1], footer[pos + 2], footer[pos + 3]]);
+            let (version, tag_size, flags) = (read_u32(8), read_u32(12) as u64, read_u32(20));
+            // The size covers the items and the footer; APEv2 tags may also have a header
+            let size = tag_size + if version >= 2000 && flags & 0x8000_0000 != 0 {
+                APE_FOOTER_SIZE
+            } else {
+                0
+            };
+            let kind = if version >= 2000 {
+                "APEv2"
+            } else {
+                "APEv1"
+            };
+            if tag_size >= APE_FOOTER_SIZE && size <= available {
+                return Ok(Some((kind, size)));
+            }
+        }
+    }
+
+    if available >= 15 {
+        let tail = read_at(file, end - 15, 15)?;
+        if &tail[6..] == b"LYRICS200"
+            && let Some(size) = std::str::from_utf8(&tail[..6]).ok().and_then(|digits| digits.parse::<u64>().ok())
+            && size + 15 <= available
+            && read_at(file, end - 15 - size, 11)? == b"LYRICSBEGIN"
+        {
+            return Ok(Some(("Lyrics3v2", size + 15)));
+        }
+        if &tail[6..] == b"LYRICSEND" {
+            let search = (LYRICS3V1_MAX_SIZE + 11 + 9).min(available);
+            let block = read_at(file, end - search, search as usize)?;
+            if let Some(begin) = block.windows(11).position(|window| window == b"LYRICSBEGIN") {
+                return Ok(Some(("Lyrics3v1", search - begin as u64)));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn read_at(file: &mut dyn MediaSource, offset: u64, length: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut buffer = vec![0u8; length];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
@@ -1,6 +1,6 @@
-use crate::cli::DebugOptions;
-use crate::media_dissector::MediaDissector;
-use std::fs::File;
+use crate::media_dissector::{Capabilities, MediaDissector};
+use crate::media_source::MediaSource;
+use crate::options::DebugOptions;
 
 /// Fallback dissector for unknown file formats
 pub struct UnknownDissector;
@@ -10,8 +10,8 @@ impl MediaDissector for UnknownDissector {
         "Unknown"
     }
 
-    fn dissect_with_options(&self, _file: &mut File, _options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
-        println!("Unknown format - no suitable dissector available");
+    fn dissect_with_options(&self, _file: &mut dyn MediaSource, _options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+        outln!("Unknown format - no suitable dissector available");
         Ok(())
     }
 
@@ -22,4 +22,8 @@ impl MediaDissector for UnknownDissector {
     fn name(&self) -> &'static str {
         "Unknown Format Dissector"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { extensions: &[], signatures: &[], operations: &[] }
+    }
 }
@@ -0,0 +1,150 @@
+/// Link checking for URL frames (`debug --check-urls`)
+///
+/// Sends HEAD requests to the URLs of W*** and WXXX frames, including chapter sub-frames,
+/// and reports the HTTP status and every redirect hop. Dead sponsor links in old podcast
+/// episodes are the typical catch. Requires the `network` feature.
+use crate::id3v2_tag::Id3v2Tag;
+use crate::media_source::MediaSource;
+#[cfg(feature = "network")]
+use owo_colors::OwoColorize;
+
+/// Redirect hops followed before giving up
+#[cfg(feature = "network")]
+const MAX_REDIRECTS: usize = 10;
+
+/// Per-request timeout
+#[cfg(feature = "network")]
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Outcome of checking one URL
+#[cfg(feature = "network")]
+#[derive(Debug, Clone)]
+enum LinkStatus {
+    /// Final response after following redirects (each hop as status and target URL)
+    Response { redirects: Vec<(u16, String)>, status: u16 },
+    /// Scheme other than http/https
+    Skipped(String),
+    /// Connection, TLS or redirect failure
+    Failed(String),
+}
+
+/// Check all URLs in the file's ID3v2 tag and print the results
+pub fn check_urls(file: &mut dyn MediaSource) -> Result<(), Box<dyn std::error::Error>> {
+    let urls = Id3v2Tag::read(file)?.map(|tag| tag.urls()).unwrap_or_default();
+    report_links(&urls)
+}
+
+/// Request every URL (given with its frame location) and print status and redirects
+#[cfg(feature = "network")]
+fn report_links(urls: &[(String, String)]) -> Result<(), Box<dyn std::error::Error>> {
+    outln!("\n{}", "URL Check:".bright_cyan());
+    if urls.is_empty() {
+        outln!("    No URL frames found");
+        return Ok(());
+    }
+
+    let agent = ureq::AgentBuilder::new().redirects(0).timeout(REQUEST_TIMEOUT).build();
+    let mut dead = 0;
+    for (location, url) in urls {
+        outln!("    {}: {}", location, url);
+        match check_url(&agent, url) {
+            | LinkStatus::Response { redirects, status } => {
+                for (hop_status, target) in &redirects {
+                    outln!("        {} -> {}", hop_status.bright_yellow(), target);
+                }
+                let line = format!("{} {}", status, status_text(status));
+                if status < 400 {
+                    outln!("        {}", line.bright_green());
+                } else {
+                    dead += 1;
+                    outln!("        {}", line.bright_red());
+                }
+            }
+            | LinkStatus::Skipped(reason) => outln!("        Skipped: {}", reason),
+            | LinkStatus::Failed(reason) => {
+                dead += 1;
+                outln!("        {}", format!("Failed: {}", reason).bright_red());
+            }
+        }
+    }
+
+    outln!("    {} URL(s), {} unreachable", urls.len(), dead);
+    Ok(())
+}
+
+/// Without the `network` feature there is no HTTP client to check URLs with
+#[cfg(not(feature = "network"))]
+fn report_links(_urls: &[(String, String)]) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--check-urls requires supertool to be built with the network feature (cargo build --features network)".into())
+}
+
+/// Send HEAD requests, following redirects by hand so every hop can be reported
+#[cfg(feature = "network")]
+fn check_url(agent: &ureq::Agent, url: &str) -> LinkStatus {
+    let mut current = match url::Url::parse(url.trim()) {
+        | Ok(parsed) => parsed,
+        | Err(e) => return LinkStatus::Failed(format!("not a valid URL ({})", e)),
+    };
+    if current.scheme() != "http" && current.scheme() != "https" {
+        return LinkStatus::Skipped(format!("'{}' URLs are not checked", current.scheme()));
+    }
+
+    let mut redirects = Vec::new();
+    loop {
+        let response = match agent.head(current.as_str()).call() {
+            | Ok(response) => response,
+            // Some servers refuse HEAD; ask again with GET without reading the body
+            | Err(ureq::Error::Status(405 | 501, _)) => match agent.get(current.as_str()).call() {
+                | Ok(response) => response,
+                | Err(ureq::Error::Status(status, _)) => return LinkStatus::Response { redirects, status },
+                | Err(e) => return LinkStatus::Failed(e.to_string()),
+            },
+            // 4xx/5xx responses are results, not failures
+            | Err(ureq::Error::Status(status, _)) => return LinkStatus::Response { redirects, status },
+            | Err(e) => return LinkStatus::Failed(e.to_string()),
+        };
+
+        let status = response.status();
+        if !(300..400).contains(&status) {
+            return LinkStatus::Response { redirects, status };
+        }
+        if redirects.len() == MAX_REDIRECTS {
+            return LinkStatus::Failed(format!("more than {} redirects", MAX_REDIRECTS));
+        }
+        let Some(location) = response.header("Location") else {
+            return LinkStatus::Failed(format!("{} redirect without Location header", status));
+        };
+        current = match current.join(location) {
+            | Ok(target) => target,
+            | Err(e) => return LinkStatus::Failed(format!("invalid redirect target '{}' ({})", location, e)),
+        };
+        redirects.push((status, current.to_string()));
+    }
+}
+
+/// Reason phrase for common HTTP status codes
+#[cfg(feature = "network")]
+fn status_text(status: u16) -> &'static str {
+    match status {
+        | 200 => "OK",
+        | 204 => "No Content",
+        | 301 => "Moved Permanently",
+        | 302 => "Found",
+        | 303 => "See Other",
+        | 304 => "Not Modified",
+        | 307 => "Temporary Redirect",
+        | 308 => "Permanent Redirect",
+        | 400 => "Bad Request",
+        | 401 => "Unauthorized",
+        | 403 => "Forbidden",
+        | 404 => "Not Found",
+        | 405 => "Method Not Allowed",
+        | 410 => "Gone",
+        | 429 => "Too Many Requests",
+        | 500 => "Internal Server Error",
+        | 502 => "Bad Gateway",
+        | 503 => "Service Unavailable",
+        | 504 => "Gateway Timeout",
+        | _ => "",
+    }
+}
@@ -0,0 +1,225 @@
+/// Vorbis comments (metadata of Ogg Vorbis, Opus, Speex, Theora and FLAC)
+///
+/// A vendor string followed by a list of "NAME=value" fields, each prefixed with its length as
+/// a 32-bit little-endian number. Names are case-insensitive ASCII, values UTF-8, and a name
+/// may repeat (one ARTIST field per artist). Cover pictures are stored as
+/// METADATA_BLOCK_PICTURE fields holding a base64-encoded FLAC picture block.
+use crate::finding::Finding;
+use crate::id3v2_attached_picture_frame::picture_type_description;
+use crate::image_info::ImageInfo;
+use crate::text_truncate::truncate_text;
+use serde::Serialize;
+use std::fmt;
+
+/// Field holding a base64-encoded FLAC picture block
+pub const PICTURE_FIELD: &str = "METADATA_BLOCK_PICTURE";
+
+/// Vendor string and fields of a comment header
+#[derive(Debug, Clone, Serialize)]
+pub struct VorbisComment {
+    /// Encoder that wrote the header (e.g. "Lavf60.16.100")
+    pub vendor: String,
+    /// Fields in header order, without the pictures
+    pub fields: Vec<VorbisField>,
+    /// Pictures of METADATA_BLOCK_PICTURE fields (and of FLAC picture blocks)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pictures: Vec<FlacPicture>,
+    /// Malformed fields and pictures
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VorbisField {
+    pub name: String,
+    pub value: String,
+}
+
+impl VorbisComment {
+    /// Parse a comment header without its codec-specific prefix ("\x03vorbis", "OpusTags")
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = LengthPrefixed { data, pos: 0 };
+        let vendor = reader.bytes().ok_or("comment header too short for the vendor string")?;
+        let vendor = String::from_utf8_lossy(vendor).into_owned();
+        let count = reader.u32().ok_or("comment header too short for the field count")?;
+
+        let mut comment = VorbisComment { vendor, fields: Vec::new(), pictures: Vec::new(), problems: Vec::new() };
+        for i in 0..count {
+            let Some(field) = reader.bytes() else {
+                comment.problems.push(format!("comment header ends after {} of {} fields", i, count));
+                break;
+            };
+            let Some(separator) = field.iter().position(|&byte| byte == b'=') else {
+                comment.problems.push(format!("field {} has no '=': \"{}\"", i + 1, String::from_utf8_lossy(field)));
+                continue;
+            };
+            let name = String::from_utf8_lossy(&field[..separator]).into_owned();
+            // Printable ASCII except '=' (0x20 to 0x7D)
+            if name.is_empty() || !field[..separator].iter().all(|&byte| (0x20..=0x7D).contains(&byte)) {
+                comment.problems.push(format!("field name \"{}\" contains characters other than printable ASCII", name));
+            }
+            let value = match std::str::from_utf8(&field[separator + 1..]) {
+                | Ok(value) => value.to_string(),
+                | Err(_) => {
+                    comment.problems.push(format!("{} is not valid UTF-8", name));
+                    String::from_utf8_lossy(&field[separator + 1..]).into_owned()
+                }
+            };
+            if name.eq_ignore_ascii_case(PICTURE_FIELD) {
+                match decode_base64(&value).ok_or_else(|| "not valid base64".to_string()).and_then(|block| FlacPicture::parse(&block)) {
+                    | Ok(picture) => comment.pictures.push(picture),
+                    | Err(error) => comment.problems.push(format!("{} {}: {}", PICTURE_FIELD, comment.pictures.len() + 1, error)),
+                }
+                continue;
+            }
+            comment.fields.push(VorbisField { name, value });
+        }
+        Ok(comment)
+    }
+
+    /// Values of a field (names compare case-insensitively)
+    pub fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.fields.iter().filter(move |field| field.name.eq_ignore_ascii_case(name)).map(|field| field.value.as_str())
+    }
+
+    /// Color problems of the pictures, located as "PICTURE[i]"
+    pub fn picture_findings(&self) -> Vec<Finding> {
+        self.pictures
+            .iter()
+            .enumerate()
+            .filter_map(|(i, picture)| picture.image.as_ref().map(|image| image.findings(&format!("PICTURE[{}]", i))))
+            .flatten()
+            .collect()
+    }
+}
+
+impl fmt::Display for VorbisComment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  Vendor: \"{}\"", self.vendor)?;
+        for field in &self.fields {
+            writeln!(f, "  {}: \"{}\"", field.name, truncate_text(&field.value))?;
+        }
+        for (i, picture) in self.pictures.iter().enumerate() {
+            writeln!(f, "  Picture {}: {}", i + 1, picture)?;
+            if let Some(image) = &picture.image {
+                writeln!(f, "    Image: {}", image)?;
+                for finding in image.findings("PICTURE") {
+                    writeln!(f, "    {}: {}", finding.severity, finding.message)?;
+                }
+            }
+        }
+        for problem in &self.problems {
+            writeln!(f, "  WARNING: {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// FLAC picture block (METADATA_BLOCK_PICTURE), with the same picture types as APIC
+#[derive(Debug, Clone, Serialize)]
+pub struct FlacPicture {
+    pub picture_type: u32,
+    pub mime_type: String,
+    pub description: String,
+    /// Dimensions as declared in the block (0 if not set)
+    pub width: u32,
+    pub height: u32,
+    pub size: usize,
+    /// Header of the image data itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageInfo>,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+impl FlacPicture {
+    /// Parse a picture block: big-endian fields, MIME type and description with 32-bit lengths
+    pub fn parse(block: &[u8]) -> Result<Self, String> {
+        let read_u32 = |pos: usize| block.get(pos..pos + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        let read_bytes = |pos: usize| -> Option<(&[u8], usize)> {
+            let length = read_u32(pos)? as usize;
+            let end = (pos + 4).checked_add(length)?;
+            Some((block.get(pos + 4..end)?, end))
+        };
+        let picture_type = read_u32(0).ok_or("picture block too short")?;
+        let (mime_type, pos) = read_bytes(4).ok_or("picture block too short for the MIME type")?;
+        let (description, pos) = read_bytes(pos).ok_or("picture block too short for the description")?;
+        let width = read_u32(pos).ok_or("picture block too short for the dimensions")?;
+        let height = read_u32(pos + 4).ok_or("picture block too short for the dimensions")?;
+        // Color depth and palette size (pos + 8, pos + 12) are not shown
+        let (data, _) = read_bytes(pos + 16).ok_or("picture block too short for the picture data")?;
+        Ok(FlacPicture {
+            picture_type,
+            mime_type: String::from_utf8_lossy(mime_type).into_owned(),
+            description: String::from_utf8_lossy(description).into_owned(),
+            width,
+            height,
+            size: data.len(),
+            image: ImageInfo::inspect(data),
+            data: data.to_vec(),
+        })
+    }
+
+    pub fn picture_type_description(&self) -> &'static str {
+        u8::try_from(self.picture_type).map_or("Unknown", picture_type_description)
+    }
+}
+
+impl fmt::Display for FlacPicture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}), {}", self.picture_type, self.picture_type_description(), self.mime_type)?;
+        if self.width > 0 && self.height > 0 {
+            write!(f, ", {}x{}", self.width, self.height)?;
+        }
+        if !self.description.is_empty() {
+            write!(f, ", \"{}\"", self.description)?;
+        }
+        write!(f, ", {} bytes", self.size)
+    }
+}
+
+/// Reader for the little-endian length-prefixed strings of a comment header
+struct LengthPrefixed<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LengthPrefixed<'a> {
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.data.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let length = self.u32()? as usize;
+        let bytes = self.data.get(self.pos..self.pos.checked_add(length)?)?;
+        self.pos += length;
+        Some(bytes)
+    }
+}
+
+/// Decode standard base64 (padding optional, whitespace ignored); `None` on other characters
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+        let value = match c {
+            | b'A'..=b'Z' => c - b'A',
+            | b'a'..=b'z' => c - b'a' + 26,
+            | b'0'..=b'9' => c - b'0' + 52,
+            | b'+' => 62,
+            | b'/' => 63,
+            | b'=' => break,
+            | _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
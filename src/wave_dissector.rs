@@ -0,0 +1,376 @@
+use crate::audio_format_check::{print_format_findings, wave_format_findings};
+use crate::coverage::Coverage;
+use crate::id3v2_tag::Id3v2Tag;
+use crate::media_dissector::{Capabilities, MediaDissector, Operation, Signature};
+use crate::media_source::MediaSource;
+use crate::media_summary::{MediaSummary, TagSummary};
+use crate::options::DebugOptions;
+use crate::riff_chunk::{RiffChunk, RiffFile, read_chunk_payload};
+use crate::size_tree::{SizeNode, tag_node};
+use crate::text_truncate::truncate_text;
+use crate::time_format::format_duration;
+use crate::xmp::XmpPacket;
+
+/// Lines of an axml chunk printed before the rest is elided
+const AXML_PREVIEW_LINES: usize = 20;
+
+/// Dissector for WAVE files in RIFF, RF64 and BW64 containers
+pub struct WaveDissector;
+
+impl MediaDissector for WaveDissector {
+    fn media_type(&self) -> &'static str {
+        "WAVE"
+    }
+
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+        dissect_wave_with_options(file, options)
+    }
+
+    fn can_handle(&self, header: &[u8]) -> bool {
+        header.len() >= 12 && matches!(&header[0..4], b"RIFF" | b"RF64" | b"BW64") && &header[8..12] == b"WAVE"
+    }
+
+    fn name(&self) -> &'static str {
+        "WAVE Dissector"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            extensions: &["wav", "rf64", "bw64"],
+            signatures: &[
+                Signature { offset: 0, magic: b"RIFF", description: "RIFF container, \"WAVE\" at offset 8" },
+                Signature { offset: 0, magic: b"RF64", description: "RF64 container with 64-bit sizes" },
+                Signature { offset: 0, magic: b"BW64", description: "BW64 container with 64-bit sizes" },
+            ],
+            operations: &[Operation::Read, Operation::Validate],
+        }
+    }
+
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        let mut coverage = Coverage::new(file.byte_len()?);
+        let Some(riff) = RiffFile::read(file)? else {
+            return Ok(coverage.finish());
+        };
+        coverage.interpret(0, 12);
+        for chunk in &riff.chunks {
+            let size = chunk.end() - chunk.offset;
+            match chunk.id.as_str() {
+                | "ds64" | "fmt " | "data" | "bext" | "LIST" | "cue " | "axml" | "_PMX" => coverage.interpret(chunk.offset, size),
+                | id if id.eq_ignore_ascii_case("id3 ") => coverage.interpret(chunk.offset, size),
+                | "JUNK" | "junk" | "PAD " | "FLLR" => coverage.skip(chunk.offset, size, format!("padding ('{}' chunk)", chunk.id)),
+                | id => coverage.skip(chunk.offset, size, format!("'{}' chunk not dissected", id)),
+            }
+        }
+        Ok(coverage.finish())
+    }
+
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let Some(riff) = RiffFile::read(file)? else {
+            return crate::size_tree::flat(file);
+        };
+        let mut children = vec![SizeNode::new(format!("{} header", riff.form), 12)];
+        for chunk in &riff.chunks {
+            let size = chunk.end() - chunk.offset;
+            let tag = if chunk.id.eq_ignore_ascii_case("id3 ") {
+                Id3v2Tag::parse(&read_chunk_payload(file, chunk)?)
+            } else {
+                None
+            };
+            match tag {
+                | Some(tag) => children.push(SizeNode::with_children(format!("'{}' chunk", chunk.id), size, vec![tag_node(&tag)], "chunk header")),
+                | None => children.push(SizeNode::new(format!("'{}' chunk", chunk.id), size)),
+            }
+        }
+        Ok(SizeNode::with_children("file", file_size, children, "other"))
+    }
+
+    fn summarize(&self, file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let mut summary = MediaSummary::new(self.media_type(), self.name());
+        let Some(riff) = RiffFile::read(file)? else {
+            return Ok(summary);
+        };
+        if let Some(chunk) = id3_chunk(&riff) {
+            summary.tag = Id3v2Tag::parse(&read_chunk_payload(file, chunk)?).map(|tag| TagSummary::from(&tag));
+        }
+        if let Some(chunk) = riff.chunk("_PMX") {
+            summary.xmp = XmpPacket::parse("_PMX chunk", &read_chunk_payload(file, chunk)?).into_iter().collect();
+        }
+        summary.format_findings = wave_format_findings(file, &riff)?;
+        summary.riff = Some(riff);
+        Ok(summary)
+    }
+}
+
+pub fn dissect_wave_with_options(file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let riff = RiffFile::read(file)?.ok_or("not a WAVE file")?;
+
+    if options.show_header {
+        outln!("\nWAVE Container:");
+        match riff.form.as_str() {
+            | "RIFF" => outln!("  Format: RIFF (32-bit sizes)"),
+            | form => outln!("  Format: {} (64-bit sizes in the ds64 chunk)", form),
+        }
+        outln!("  RIFF Size: {} bytes", riff.riff_size);
+        if let Some(ds64) = &riff.ds64 {
+            outln!("  ds64 Data Size: {} bytes", ds64.data_size);
+            outln!("  ds64 Sample Count: {}", ds64.sample_count);
+            for (id, size) in &ds64.table {
+                outln!("  ds64 Size of '{}': {} bytes", id, size);
+            }
+        }
+        for problem in &riff.problems {
+            outln!("  WARNING: {}", problem);
+        }
+    }
+
+    if !options.show_frames {
+        return Ok(());
+    }
+
+    outln!("\nWAVE Chunks:");
+    for chunk in &riff.chunks {
+        let source = if chunk.size_from_ds64 {
+            ", from ds64"
+        } else {
+            ""
+        };
+        outln!("  Chunk: {} at 0x{:08X} (size: {} bytes{})", chunk.id, chunk.offset, chunk.size, source);
+    }
+
+    if let Some(chunk) = riff.chunk("fmt ") {
+        print_format(&read_chunk_payload(file, chunk)?, &riff);
+        print_format_findings(&wave_format_findings(file, &riff)?);
+    }
+    if let Some(chunk) = riff.chunk("bext") {
+        print_broadcast_extension(&read_chunk_payload(file, chunk)?);
+    }
+    let mut labels = Vec::new();
+    for chunk in riff.chunks.iter().filter(|chunk| chunk.id == "LIST") {
+        let payload = read_chunk_payload(file, chunk)?;
+        print_info_list(&payload);
+        labels.extend(cue_labels(&payload));
+    }
+    if let Some(chunk) = riff.chunk("cue ") {
+        let sample_rate = match riff.chunk("fmt ") {
+            | Some(format) => read_chunk_payload(file, format)?.get(4..8).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            | None => None,
+        };
+        print_cue_points(&read_chunk_payload(file, chunk)?, &labels, sample_rate.unwrap_or_default());
+    }
+    if let Some(chunk) = riff.chunk("axml") {
+        print_axml(&read_chunk_payload(file, chunk)?, chunk);
+    }
+    if let Some(chunk) = riff.chunk("_PMX") {
+        match XmpPacket::parse("_PMX chunk", &read_chunk_payload(file, chunk)?) {
+            | Ok(packet) => {
+                outln!("\n_PMX chunk:");
+                out!("{}", packet);
+            }
+            | Err(error) => outln!("\nWARNING: Invalid XMP packet in the _PMX chunk: {}", error),
+        }
+    }
+    if let Some(chunk) = id3_chunk(&riff) {
+        match Id3v2Tag::parse(&read_chunk_payload(file, chunk)?) {
+            | Some(tag) => crate::id3v2_embedded::print_embedded_tag(&tag, &format!("'{}' chunk at 0x{:08X}", chunk.id, chunk.offset), options)?,
+            | None => outln!("\nWARNING: '{}' chunk does not start with an ID3v2 header", chunk.id),
+        }
+    }
+
+    Ok(())
+}
+
+/// The chunk holding an ID3v2 tag ("id3 ", or "ID3 " as written by some tools)
+fn id3_chunk(riff: &RiffFile) -> Option<&RiffChunk> {
+    riff.chunks.iter().find(|chunk| chunk.id.eq_ignore_ascii_case("id3 "))
+}
+
+fn print_format(payload: &[u8], riff: &RiffFile) {
+    if payload.len() < 16 {
+        outln!("\nWARNING: fmt chunk too short ({} bytes)", payload.len());
+        return;
+    }
+    let read_u16 = |pos: usize| u16::from_le_bytes([payload[pos], payload[pos + 1]]);
+    let read_u32 = |pos: usize| u32::from_le_bytes([payload[pos], payload[pos + 1], payload[pos + 2], payload[pos + 3]]);
+    let format_tag = read_u16(0);
+    let channels = read_u16(2);
+    let sample_rate = read_u32(4);
+    let block_align = read_u16(12);
+    let bits_per_sample = read_u16(14);
+    // WAVE_FORMAT_EXTENSIBLE carries the actual format in the first two bytes of the sub-format GUID
+    let effective_tag = if format_tag == 0xFFFE && payload.len() >= 26 {
+        read_u16(24)
+    } else {
+        format_tag
+    };
+    let format_name = match effective_tag {
+        | 0x0001 => "PCM",
+        | 0x0003 => "IEEE float",
+        | 0x0006 => "A-law",
+        | 0x0007 => "mu-law",
+        | 0x0050 => "MPEG",
+        | 0x0055 => "MPEG Layer III",
+        | _ => "other",
+    };
+
+    outln!("\nAudio Format:");
+    if format_tag == 0xFFFE {
+        outln!("  Format: 0x{:04X} ({}, extensible)", effective_tag, format_name);
+    } else {
+        outln!("  Format: 0x{:04X} ({})", format_tag, format_name);
+    }
+    outln!("  Channels: {}", channels);
+    outln!("  Sample Rate: {} Hz", sample_rate);
+    outln!("  Bits per Sample: {}", bits_per_sample);
+
+    let data_size = riff.chunk("data").map(|chunk| chunk.size);
+    let samples = match (&riff.ds64, data_size) {
+        | (Some(ds64), _) if ds64.sample_count > 0 => Some(ds64.sample_count),
+        | (_, Some(size)) if block_align > 0 => Some(size / block_align as u64),
+        | _ => None,
+    };
+    if let Some(samples) = samples
+        && sample_rate > 0
+    {
+        outln!("  Duration: {} ({} samples)", format_duration(samples * 1000 / sample_rate as u64), samples);
+    }
+}
+
+/// Broadcast Wave Format extension (EBU Tech 3285)
+fn print_broadcast_extension(payload: &[u8]) {
+    if payload.len() < 346 {
+        outln!("\nWARNING: bext chunk too short ({} bytes)", payload.len());
+        return;
+    }
+    let text = |range: std::ops::Range<usize>| String::from_utf8_lossy(&payload[range]).trim_end_matches('\0').trim().to_string();
+    let time_reference = u64::from_le_bytes(payload[338..346].try_into().unwrap_or_default());
+
+    outln!("\nBroadcast Extension (bext):");
+    outln!("  Description: \"{}\"", truncate_text(&text(0..256)));
+    outln!("  Originator: \"{}\"", text(256..288));
+    outln!("  Originator Reference: \"{}\"", text(288..320));
+    outln!("  Origination: {} {}", text(320..330), text(330..338));
+    outln!("  Time Reference: {} samples", time_reference);
+
+    let Some(version) = payload.get(346..348).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]])) else {
+        return;
+    };
+    outln!("  Version: {}", version);
+    // Version 2 adds loudness values (EBU R 128) in hundredths of LU/LUFS/dBTP, 0x7FFF if not set
+    if version >= 2 && payload.len() >= 422 {
+        let loudness = |pos: usize| i16::from_le_bytes([payload[pos], payload[pos + 1]]);
+        for (name, pos, unit) in [("Integrated Loudness", 412, "LUFS"), ("Loudness Range", 414, "LU"), ("Max True Peak", 416, "dBTP"), ("Max Momentary Loudness", 418, "LUFS"), ("Max Short-Term Loudness", 420, "LUFS")] {
+            let value = loudness(pos);
+            if value != 0x7FFF {
+                outln!("  {}: {:.2} {}", name, value as f32 / 100.0, unit);
+            }
+        }
+    }
+    if payload.len() > 602 {
+        let history = String::from_utf8_lossy(&payload[602..]).trim_end_matches('\0').trim().to_string();
+        if !history.is_empty() {
+            outln!("  Coding History:");
+            for line in history.lines() {
+                outln!("    {}", line.trim_end());
+            }
+        }
+    }
+}
+
+/// LIST chunk of type INFO with its text entries
+fn print_info_list(payload: &[u8]) {
+    if !payload.starts_with(b"INFO") {
+        return;
+    }
+    outln!("\nINFO List:");
+    let mut pos = 4;
+    while pos + 8 <= payload.len() {
+        let id = String::from_utf8_lossy(&payload[pos..pos + 4]).to_string();
+        let size = u32::from_le_bytes(payload[pos + 4..pos + 8].try_into().unwrap_or_default()) as usize;
+        let end = (pos + 8).saturating_add(size).min(payload.len());
+        let value = String::from_utf8_lossy(&payload[pos + 8..end]).trim_end_matches('\0').to_string();
+        outln!("  {}: \"{}\"", id, truncate_text(&value));
+        pos = end + (size & 1);
+    }
+}
+
+/// Text attached to a cue point by the associated data list (LIST adtl)
+struct CueLabel {
+    /// "labl" (label), "note" or "ltxt" (text for a region)
+    kind: String,
+    cue_id: u32,
+    text: String,
+    /// Region length of an ltxt entry
+    sample_length: Option<u32>,
+}
+
+/// Labels, notes and region texts of a LIST chunk of type adtl
+fn cue_labels(payload: &[u8]) -> Vec<CueLabel> {
+    let mut labels = Vec::new();
+    if !payload.starts_with(b"adtl") {
+        return labels;
+    }
+    let mut pos = 4;
+    while pos + 12 <= payload.len() {
+        let kind = String::from_utf8_lossy(&payload[pos..pos + 4]).to_string();
+        let size = u32::from_le_bytes(payload[pos + 4..pos + 8].try_into().unwrap_or_default()) as usize;
+        let end = (pos + 8).saturating_add(size).min(payload.len());
+        let cue_id = u32::from_le_bytes(payload[pos + 8..pos + 12].try_into().unwrap_or_default());
+        // ltxt: sample length, purpose, country, language, dialect and code page before the text
+        let (text_start, sample_length) = match kind.as_str() {
+            | "ltxt" if end >= pos + 28 => (pos + 28, Some(u32::from_le_bytes(payload[pos + 12..pos + 16].try_into().unwrap_or_default()))),
+            | _ => (pos + 12, None),
+        };
+        let text = String::from_utf8_lossy(&payload[text_start.min(end)..end]).trim_end_matches('\0').to_string();
+        labels.push(CueLabel { kind, cue_id, text, sample_length });
+        pos = end + (size & 1);
+    }
+    labels
+}
+
+/// Cue points (cue chunk) with their labels and region lengths from LIST adtl
+fn print_cue_points(payload: &[u8], labels: &[CueLabel], sample_rate: u32) {
+    let Some(count) = payload.get(0..4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])) else {
+        outln!("\nWARNING: cue chunk too short ({} bytes)", payload.len());
+        return;
+    };
+    let time = |samples: u32| {
+        if sample_rate > 0 {
+            format!(" ({})", format_duration(samples as u64 * 1000 / sample_rate as u64))
+        } else {
+            String::new()
+        }
+    };
+
+    outln!("\nCue Points ({}):", count);
+    // Each cue point: ID, play order position, data chunk ID, chunk start, block start, sample offset
+    for (i, point) in payload[4..].chunks_exact(24).take(count as usize).enumerate() {
+        let read_u32 = |pos: usize| u32::from_le_bytes([point[pos], point[pos + 1], point[pos + 2], point[pos + 3]]);
+        let cue_id = read_u32(0);
+        let sample_offset = read_u32(20);
+        outln!("  Cue {} (ID {}): sample {}{}", i + 1, cue_id, sample_offset, time(sample_offset));
+        for label in labels.iter().filter(|label| label.cue_id == cue_id) {
+            match label.sample_length {
+                | Some(length) => outln!("    {}: \"{}\", region of {} samples{}", label.kind, truncate_text(&label.text), length, time(length)),
+                | None => outln!("    {}: \"{}\"", label.kind, truncate_text(&label.text)),
+            }
+        }
+    }
+    let stored = (payload.len() - 4) / 24;
+    if stored < count as usize {
+        outln!("  WARNING: cue chunk declares {} cue points but holds {}", count, stored);
+    }
+}
+
+/// XML metadata chunk (axml, e.g. EBUCore or ADM), shown up to `AXML_PREVIEW_LINES` lines
+fn print_axml(payload: &[u8], chunk: &RiffChunk) {
+    let text = String::from_utf8_lossy(payload);
+    let lines: Vec<&str> = text.trim_end_matches('\0').lines().filter(|line| !line.trim().is_empty()).collect();
+    outln!("\naxml Chunk ({} bytes of XML at 0x{:08X}):", chunk.size, chunk.offset);
+    for line in lines.iter().take(AXML_PREVIEW_LINES) {
+        outln!("  {}", line);
+    }
+    if lines.len() > AXML_PREVIEW_LINES {
+        outln!("  ... ({} more lines)", lines.len() - AXML_PREVIEW_LINES);
+    }
+}
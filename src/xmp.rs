@@ -0,0 +1,435 @@
+/// XMP metadata packets (Extensible Metadata Platform)
+///
+/// Packets are found in ID3v2 PRIV frames owned by "XMP", in the APP1 segment of JPEG images
+/// (attached pictures) and in the MP4 uuid box BE7ACFCB-97A9-42E8-9C71-999491E3AFAC. The
+/// RDF/XML is reduced to properties keyed by namespace URI, so a property reads the same
+/// whatever prefix the writing software chose.
+use crate::isobmff_box::{read_boxes, read_children, read_payload};
+use crate::media_source::MediaSource;
+use crate::text_truncate::truncate_text;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// User type of the MP4 uuid box carrying XMP
+pub const XMP_UUID: [u8; 16] = [0xBE, 0x7A, 0xCF, 0xCB, 0x97, 0xA9, 0x42, 0xE8, 0x9C, 0x71, 0x99, 0x94, 0x91, 0xE3, 0xAF, 0xAC];
+
+/// Owner identifier of PRIV frames carrying XMP
+pub const PRIV_OWNER: &str = "XMP";
+
+/// Signature at the start of a JPEG APP1 segment carrying XMP
+const JPEG_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+const RDF_NAMESPACE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Conventional prefixes of well-known namespaces
+const KNOWN_NAMESPACES: &[(&str, &str)] = &[
+    ("http://purl.org/dc/elements/1.1/", "dc"),
+    ("http://ns.adobe.com/xap/1.0/", "xmp"),
+    ("http://ns.adobe.com/xap/1.0/mm/", "xmpMM"),
+    ("http://ns.adobe.com/xap/1.0/rights/", "xmpRights"),
+    ("http://ns.adobe.com/xap/1.0/sType/ResourceEvent#", "stEvt"),
+    ("http://ns.adobe.com/xap/1.0/sType/ResourceRef#", "stRef"),
+    ("http://ns.adobe.com/xmp/1.0/DynamicMedia/", "xmpDM"),
+    ("http://ns.adobe.com/photoshop/1.0/", "photoshop"),
+    ("http://ns.adobe.com/tiff/1.0/", "tiff"),
+    ("http://ns.adobe.com/exif/1.0/", "exif"),
+    ("http://ns.adobe.com/exif/1.0/aux/", "aux"),
+    ("http://ns.adobe.com/camera-raw-settings/1.0/", "crs"),
+    ("http://ns.adobe.com/pdf/1.3/", "pdf"),
+    ("http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/", "Iptc4xmpCore"),
+    ("http://iptc.org/std/Iptc4xmpExt/2008-02-29/", "Iptc4xmpExt"),
+    ("http://ns.useplus.org/ldf/xmp/1.0/", "plus"),
+];
+
+/// Top-level property of an XMP packet
+#[derive(Debug, Clone, Serialize)]
+pub struct XmpProperty {
+    /// Namespace URI
+    pub namespace: String,
+    /// Conventional prefix of the namespace (the packet's own prefix for unknown namespaces)
+    pub prefix: String,
+    pub name: String,
+    /// Simple value, or the items of an array (structures as "field=value; ..." strings)
+    pub values: Vec<String>,
+}
+
+/// Parsed XMP packet
+#[derive(Debug, Clone, Serialize)]
+pub struct XmpPacket {
+    /// Where the packet was found (e.g. "PRIV frame", "uuid box at offset 1234")
+    pub source: String,
+    /// Size of the packet in bytes
+    pub size: usize,
+    pub properties: Vec<XmpProperty>,
+}
+
+impl XmpPacket {
+    /// Parse the packet contained in `data` (surrounding padding or binary data is skipped)
+    pub fn parse(source: &str, data: &[u8]) -> Result<Self, String> {
+        let packet = find_packet(data).ok_or("no XMP packet found")?;
+        let text = String::from_utf8_lossy(packet);
+        let nodes = parse_xml(&text)?;
+        let mut properties = Vec::new();
+        for node in &nodes {
+            if let Node::Element(element) = node {
+                collect_properties(element, &HashMap::new(), &mut properties);
+            }
+        }
+        Ok(XmpPacket { source: source.to_string(), size: packet.len(), properties })
+    }
+}
+
+impl fmt::Display for XmpPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "XMP packet ({} bytes, {} properties):", self.size, self.properties.len())?;
+        let mut namespaces: Vec<&str> = Vec::new();
+        for property in &self.properties {
+            if !namespaces.contains(&property.namespace.as_str()) {
+                namespaces.push(&property.namespace);
+            }
+        }
+        for namespace in namespaces {
+            let mut properties = self.properties.iter().filter(|property| property.namespace == namespace).peekable();
+            let prefix = properties.peek().map_or("", |property| property.prefix.as_str());
+            writeln!(f, "  {} ({})", prefix, namespace)?;
+            for property in properties {
+                match property.values.as_slice() {
+                    | [value] => writeln!(f, "    {}: {}", property.name, truncate_text(value))?,
+                    | values => {
+                        writeln!(f, "    {}:", property.name)?;
+                        for value in values {
+                            writeln!(f, "      - {}", truncate_text(value))?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// XMP packets of the uuid boxes at the top level and in the `moov` box of an ISO BMFF file
+pub fn isobmff_packets(file: &mut dyn MediaSource) -> Result<Vec<Result<XmpPacket, String>>, Box<dyn std::error::Error>> {
+    let file_size = file.byte_len()?;
+    let mut boxes = read_boxes(file, 0, file_size)?;
+    if let Some(moov) = boxes.iter().find(|box_header| box_header.box_type == "moov").cloned() {
+        boxes.extend(read_children(file, &moov)?);
+    }
+
+    let mut packets = Vec::new();
+    for box_header in boxes.iter().filter(|box_header| box_header.box_type == "uuid") {
+        let payload = read_payload(file, box_header)?;
+        if let Some(data) = payload.strip_prefix(&XMP_UUID[..]) {
+            packets.push(XmpPacket::parse(&format!("uuid box at offset {}", box_header.offset), data));
+        }
+    }
+    Ok(packets)
+}
+
+/// XMP data of a JPEG image (APP1 segment), if it has any
+pub fn jpeg_xmp(data: &[u8]) -> Option<&[u8]> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let marker = data[pos + 1];
+        // Start of scan: the entropy-coded image data follows, no more metadata segments
+        if marker == 0xDA || marker == 0xD9 {
+            return None;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + length)?;
+        if marker == 0xE1
+            && let Some(xmp) = segment.strip_prefix(JPEG_SIGNATURE)
+        {
+            return Some(xmp);
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+/// The XMP packet within `data`: from the xpacket wrapper, x:xmpmeta or rdf:RDF element to its end
+fn find_packet(data: &[u8]) -> Option<&[u8]> {
+    let find = |needle: &[u8]| data.windows(needle.len()).position(|window| window == needle);
+    let start = [&b"<?xpacket begin"[..], b"<x:xmpmeta", b"<rdf:RDF"].iter().find_map(|needle| find(needle))?;
+    let end = match find(b"<?xpacket end") {
+        | Some(end) => end + data[end..].windows(2).position(|window| window == b"?>")? + 2,
+        | None => [&b"</x:xmpmeta>"[..], b"</rdf:RDF>"].iter().find_map(|needle| find(needle).map(|end| end + needle.len()))?,
+    };
+    (start < end).then(|| &data[start..end])
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn child_elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            | Node::Element(element) => Some(element),
+            | Node::Text(_) => None,
+        })
+    }
+
+    fn text(&self) -> String {
+        let text: String = self
+            .children
+            .iter()
+            .map(|node| match node {
+                | Node::Text(text) => text.as_str(),
+                | Node::Element(_) => "",
+            })
+            .collect();
+        text.trim().to_string()
+    }
+
+    /// Namespace declarations of this element added to the inherited ones
+    fn scope(&self, inherited: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut scope = inherited.clone();
+        for (name, value) in &self.attributes {
+            if name == "xmlns" {
+                scope.insert(String::new(), value.clone());
+            } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+                scope.insert(prefix.to_string(), value.clone());
+            }
+        }
+        scope
+    }
+}
+
+/// Namespace URI, prefix and local part of a qualified name
+fn resolve<'a>(name: &'a str, scope: &HashMap<String, String>) -> (String, &'a str, &'a str) {
+    let (prefix, local) = name.split_once(':').unwrap_or(("", name));
+    let namespace = match prefix {
+        | "xml" => XML_NAMESPACE.to_string(),
+        | _ => scope.get(prefix).cloned().unwrap_or_default(),
+    };
+    (namespace, prefix, local)
+}
+
+fn is_rdf(name: &str, scope: &HashMap<String, String>, local_name: &str) -> bool {
+    let (namespace, _, local) = resolve(name, scope);
+    namespace == RDF_NAMESPACE && local == local_name
+}
+
+/// Conventional prefix of a namespace, or the one used in the packet
+fn display_prefix(namespace: &str, prefix: &str) -> String {
+    KNOWN_NAMESPACES.iter().find(|(uri, _)| *uri == namespace).map_or(prefix, |(_, known)| known).to_string()
+}
+
+/// Whether an attribute is a namespace declaration or belongs to RDF/XML itself
+fn is_syntax_attribute(name: &str, scope: &HashMap<String, String>) -> bool {
+    let (namespace, _, _) = resolve(name, scope);
+    name == "xmlns" || name.starts_with("xmlns:") || namespace == RDF_NAMESPACE || namespace == XML_NAMESPACE
+}
+
+/// Collect the properties of every rdf:Description below `element`
+fn collect_properties(element: &Element, inherited: &HashMap<String, String>, properties: &mut Vec<XmpProperty>) {
+    let scope = element.scope(inherited);
+    if !is_rdf(&element.name, &scope, "Description") {
+        for child in element.child_elements() {
+            collect_properties(child, &scope, properties);
+        }
+        return;
+    }
+
+    // Simple properties may be written as attributes of rdf:Description
+    for (name, value) in element.attributes.iter().filter(|(name, _)| !is_syntax_attribute(name, &scope)) {
+        let (namespace, prefix, local) = resolve(name, &scope);
+        properties.push(XmpProperty { prefix: display_prefix(&namespace, prefix), namespace, name: local.to_string(), values: vec![value.clone()] });
+    }
+    for child in element.child_elements() {
+        let child_scope = child.scope(&scope);
+        let (namespace, prefix, local) = resolve(&child.name, &child_scope);
+        properties.push(XmpProperty { prefix: display_prefix(&namespace, prefix), namespace, name: local.to_string(), values: property_values(child, &child_scope) });
+    }
+}
+
+/// Value of a property element: the items of an rdf:Seq/Bag/Alt or a single value
+fn property_values(element: &Element, scope: &HashMap<String, String>) -> Vec<String> {
+    let array = element.child_elements().find(|child| ["Seq", "Bag", "Alt"].iter().any(|kind| is_rdf(&child.name, scope, kind)));
+    match array {
+        | Some(array) => {
+            let array_scope = array.scope(scope);
+            array
+                .child_elements()
+                .filter(|item| is_rdf(&item.name, &array_scope, "li"))
+                .map(|item| {
+                    let item_scope = item.scope(&array_scope);
+                    let value = single_value(item, &item_scope);
+                    match item.attributes.iter().find(|(name, _)| name == "xml:lang") {
+                        | Some((_, language)) if language != "x-default" => format!("{} [{}]", value, language),
+                        | _ => value,
+                    }
+                })
+                .collect()
+        }
+        | None => vec![single_value(element, scope)],
+    }
+}
+
+/// Text, resource reference or structure ("field=value; ...") of an element
+fn single_value(element: &Element, scope: &HashMap<String, String>) -> String {
+    if let Some((_, resource)) = element.attributes.iter().find(|(name, _)| is_rdf(name, scope, "resource")) {
+        return resource.clone();
+    }
+    if element.child_elements().next().is_none() && !element.attributes.iter().any(|(name, _)| !is_syntax_attribute(name, scope)) {
+        return element.text();
+    }
+
+    // Structure: fields as attributes or child elements, optionally wrapped in rdf:Description
+    let mut fields = Vec::new();
+    let mut add_fields = |element: &Element, scope: &HashMap<String, String>| {
+        for (name, value) in element.attributes.iter().filter(|(name, _)| !is_syntax_attribute(name, scope)) {
+            let (namespace, prefix, local) = resolve(name, scope);
+            fields.push(format!("{}:{}={}", display_prefix(&namespace, prefix), local, value));
+        }
+        for child in element.child_elements().filter(|child| !is_rdf(&child.name, scope, "Description")) {
+            let child_scope = child.scope(scope);
+            let (namespace, prefix, local) = resolve(&child.name, &child_scope);
+            fields.push(format!("{}:{}={}", display_prefix(&namespace, prefix), local, property_values(child, &child_scope).join(", ")));
+        }
+    };
+    add_fields(element, scope);
+    for description in element.child_elements().filter(|child| is_rdf(&child.name, scope, "Description")) {
+        add_fields(description, &description.scope(scope));
+    }
+    fields.join("; ")
+}
+
+/// Parse XML into a tree; comments, processing instructions and declarations are dropped
+fn parse_xml(text: &str) -> Result<Vec<Node>, String> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<Element> = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        if start > 0 {
+            let text = Node::Text(decode_entities(&rest[..start]));
+            match stack.last_mut() {
+                | Some(parent) => parent.children.push(text),
+                | None => roots.push(text),
+            }
+        }
+        rest = &rest[start..];
+
+        let (skip_to, node) = if rest.starts_with("<!--") {
+            (rest.find("-->").map(|end| end + 3), None)
+        } else if rest.starts_with("<?") {
+            (rest.find("?>").map(|end| end + 2), None)
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            (cdata.find("]]>").map(|end| end + 12), cdata.find("]]>").map(|end| Node::Text(cdata[..end].to_string())))
+        } else if rest.starts_with("<!") {
+            (rest.find('>').map(|end| end + 1), None)
+        } else if let Some(closing) = rest.strip_prefix("</") {
+            let end = closing.find('>').ok_or("unterminated closing tag")?;
+            let name = closing[..end].trim();
+            let element = stack.pop().ok_or_else(|| format!("unexpected closing tag </{}>", name))?;
+            if element.name != name {
+                return Err(format!("<{}> closed by </{}>", element.name, name));
+            }
+            (Some(end + 3), Some(Node::Element(element)))
+        } else {
+            let end = tag_end(rest).ok_or("unterminated tag")?;
+            let self_closing = rest[..end].ends_with('/');
+            let content = rest[1..end].trim_end_matches('/');
+            let name_end = content.find(|c: char| c.is_whitespace()).unwrap_or(content.len());
+            let element = Element { name: content[..name_end].to_string(), attributes: parse_attributes(&content[name_end..])?, children: Vec::new() };
+            if self_closing {
+                (Some(end + 1), Some(Node::Element(element)))
+            } else {
+                stack.push(element);
+                (Some(end + 1), None)
+            }
+        };
+
+        rest = &rest[skip_to.ok_or("unterminated markup")?..];
+        if let Some(node) = node {
+            match stack.last_mut() {
+                | Some(parent) => parent.children.push(node),
+                | None => roots.push(node),
+            }
+        }
+    }
+
+    match stack.last() {
+        | Some(element) => Err(format!("<{}> is not closed", element.name)),
+        | None => Ok(roots),
+    }
+}
+
+/// Position of the '>' ending a start tag, ignoring '>' inside quoted attribute values
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (c, quote) {
+            | ('"' | '\'', None) => quote = Some(c),
+            | (c, Some(open)) if c == open => quote = None,
+            | ('>', None) => return Some(i),
+            | _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attributes(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut attributes = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let (name, value) = rest.split_once('=').ok_or_else(|| format!("attribute without value: {}", rest))?;
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'').ok_or("unquoted attribute value")?;
+        let end = value[1..].find(quote).ok_or("unterminated attribute value")?;
+        attributes.push((name.trim().to_string(), decode_entities(&value[1..end + 1])));
+        rest = value[end + 2..].trim_start();
+    }
+    Ok(attributes)
+}
+
+/// Replace the predefined and numeric character references
+fn decode_entities(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            | "amp" => Some('&'),
+            | "lt" => Some('<'),
+            | "gt" => Some('>'),
+            | "quot" => Some('"'),
+            | "apos" => Some('\''),
+            | _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                | Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                | None => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()).and_then(char::from_u32),
+            },
+        };
+        match decoded {
+            | Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            | None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
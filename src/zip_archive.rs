@@ -0,0 +1,352 @@
+/// ZIP archives holding audio bundles (EPUB, Readium audiobooks, album deliveries)
+///
+/// Only the central directory is read to list the members. A single member is made available
+/// as a file of its own by streaming it (stored or deflated) into a temporary file that is
+/// removed again when it is dropped, so the dissectors can read it like any other input.
+use crate::checked_size::{buffer_length, end_offset};
+use crate::coverage::Coverage;
+use crate::media_source::MediaSource;
+use flate2::Crc;
+use flate2::read::DeflateDecoder;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, SeekFrom, Write};
+use std::path::PathBuf;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034B50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014B50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054B50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064B50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064B50;
+/// Extra field holding the 64-bit sizes and offset of a ZIP64 entry
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+
+/// Size of the end of central directory record without its comment
+const END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 22;
+/// Largest archive comment, limiting the search for the end of central directory record
+const MAX_COMMENT_SIZE: u64 = 0xFFFF;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// File extensions treated as audio members
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "m4b", "mp4", "aac", "wav", "flac", "ogg", "opus", "dsf", "aif", "aiff"];
+
+/// Member of a ZIP archive as listed in the central directory
+#[derive(Debug, Clone, Serialize)]
+pub struct ZipEntry {
+    /// Path within the archive
+    pub name: String,
+    /// Compression method (0 stored, 8 deflated)
+    pub method: u16,
+    pub compressed_size: u64,
+    /// Uncompressed size
+    pub size: u64,
+    #[serde(skip)]
+    pub crc32: u32,
+    #[serde(skip)]
+    pub local_header_offset: u64,
+}
+
+impl ZipEntry {
+    /// Whether the member looks like an audio file (by extension)
+    pub fn is_audio(&self) -> bool {
+        self.name.rsplit_once('.').is_some_and(|(_, extension)| AUDIO_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+    }
+
+    /// Human-readable compression method
+    pub fn method_name(&self) -> &'static str {
+        match self.method {
+            | METHOD_STORED => "stored",
+            | METHOD_DEFLATED => "deflated",
+            | _ => "unsupported",
+        }
+    }
+}
+
+/// Known bundle layouts, recognized by their marker files
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum BundleLayout {
+    /// EPUB publication ("mimetype" containing application/epub+zip)
+    Epub,
+    /// Readium audiobook (".audiobook", manifest.json)
+    ReadiumAudiobook,
+    /// W3C Lightweight Packaging Format (".lpf", publication.json)
+    Lpf,
+    /// Any other archive with audio members (e.g. an album delivery)
+    AudioArchive,
+    /// Archive without audio members
+    Other,
+}
+
+impl BundleLayout {
+    pub fn description(&self) -> &'static str {
+        match self {
+            | BundleLayout::Epub => "EPUB publication",
+            | BundleLayout::ReadiumAudiobook => "Readium audiobook (manifest.json)",
+            | BundleLayout::Lpf => "W3C Lightweight Packaging Format (publication.json)",
+            | BundleLayout::AudioArchive => "ZIP archive with audio files",
+            | BundleLayout::Other => "ZIP archive without audio files",
+        }
+    }
+}
+
+/// Central directory of a ZIP archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ZipArchive {
+    pub layout: BundleLayout,
+    /// Whether the archive uses ZIP64 records
+    pub zip64: bool,
+    pub entries: Vec<ZipEntry>,
+    /// Audio members, in reading order if the bundle manifest defines one
+    pub audio: Vec<String>,
+    /// File offset of the central directory
+    #[serde(skip)]
+    pub directory_offset: u64,
+}
+
+/// A member streamed into a temporary file, removed when dropped
+pub struct ZipMember {
+    pub file: File,
+    path: PathBuf,
+}
+
+impl Drop for ZipMember {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl ZipArchive {
+    /// Read the central directory of a ZIP archive
+    pub fn read(file: &mut dyn MediaSource) -> Result<Self, Box<dyn std::error::Error>> {
+        let file_size = file.byte_len()?;
+        let tail_size = file_size.min(END_OF_CENTRAL_DIRECTORY_SIZE + MAX_COMMENT_SIZE);
+        let mut tail = vec![0u8; tail_size as usize];
+        file.seek(SeekFrom::Start(file_size - tail_size))?;
+        file.read_exact(&mut tail)?;
+
+        let eocd = (0..=tail.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE as usize))
+            .rev()
+            .find(|&pos| read_u32(&tail, pos) == END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+            .ok_or("no ZIP end of central directory record")?;
+        let mut entry_count = read_u16(&tail, eocd + 10) as u64;
+        let mut directory_size = read_u32(&tail, eocd + 12) as u64;
+        let mut directory_offset = read_u32(&tail, eocd + 16) as u64;
+
+        // ZIP64: the locator right before the record points to the 64-bit end of central directory
+        let mut zip64 = false;
+        if eocd >= 20 && read_u32(&tail, eocd - 20) == ZIP64_LOCATOR_SIGNATURE {
+            let record_offset = read_u64(&tail, eocd - 20 + 8);
+            let mut record = [0u8; 56];
+            file.seek(SeekFrom::Start(record_offset))?;
+            file.read_exact(&mut record)?;
+            if read_u32(&record, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+                return Err("ZIP64 locator does not point to a ZIP64 end of central directory record".into());
+            }
+            entry_count = read_u64(&record, 32);
+            directory_size = read_u64(&record, 40);
+            directory_offset = read_u64(&record, 48);
+            zip64 = true;
+        }
+        end_offset("ZIP central directory", directory_offset, directory_size, file_size)?;
+
+        let mut directory = vec![0u8; buffer_length("ZIP central directory", directory_offset, directory_size)?];
+        file.seek(SeekFrom::Start(directory_offset))?;
+        file.read_exact(&mut directory)?;
+
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while entries.len() < entry_count as usize && pos + 46 <= directory.len() {
+            if read_u32(&directory, pos) != CENTRAL_HEADER_SIGNATURE {
+                return Err(format!("broken ZIP central directory entry at offset {}", directory_offset + pos as u64).into());
+            }
+            let name_length = read_u16(&directory, pos + 28) as usize;
+            let extra_length = read_u16(&directory, pos + 30) as usize;
+            let comment_length = read_u16(&directory, pos + 32) as usize;
+            let name_end = (pos + 46 + name_length).min(directory.len());
+            let extra = &directory[name_end..(name_end + extra_length).min(directory.len())];
+
+            let mut entry = ZipEntry {
+                name: String::from_utf8_lossy(&directory[pos + 46..name_end]).to_string(),
+                method: read_u16(&directory, pos + 10),
+                crc32: read_u32(&directory, pos + 16),
+                compressed_size: read_u32(&directory, pos + 20) as u64,
+                size: read_u32(&directory, pos + 24) as u64,
+                local_header_offset: read_u32(&directory, pos + 42) as u64,
+            };
+            apply_zip64_extra(&mut entry, extra);
+            zip64 |= entry.size > u32::MAX as u64 || entry.local_header_offset > u32::MAX as u64;
+            entries.push(entry);
+            pos = name_end + extra_length + comment_length;
+        }
+
+        let mut archive = ZipArchive { layout: BundleLayout::Other, zip64, entries, audio: Vec::new(), directory_offset };
+        archive.layout = archive.detect_layout(file)?;
+        archive.audio = archive.reading_order(file)?;
+        Ok(archive)
+    }
+
+    /// Member with the given path
+    pub fn entry(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Stream a member into a temporary file, verifying its CRC-32
+    pub fn open_member(&self, file: &mut dyn MediaSource, name: &str) -> Result<ZipMember, Box<dyn std::error::Error>> {
+        let Some(entry) = self.entry(name).or_else(|| self.entries.iter().find(|entry| entry.name.rsplit('/').next() == Some(name))) else {
+            let audio = if self.audio.is_empty() {
+                "none".to_string()
+            } else {
+                self.audio.join(", ")
+            };
+            return Err(format!("no member '{}' in the archive (audio files: {})", name, audio).into());
+        };
+
+        let file_name = entry.name.rsplit('/').next().unwrap_or("member");
+        let path = std::env::temp_dir().join(format!("supertool-{}-{}", std::process::id(), file_name));
+        let mut member = ZipMember { file: File::create(&path)?, path };
+        let mut reader = self.member_reader(file, entry)?;
+        let mut crc = Crc::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let count = reader.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            crc.update(&buffer[..count]);
+            member.file.write_all(&buffer[..count])?;
+        }
+        if crc.sum() != entry.crc32 {
+            outln!("WARNING: CRC-32 of '{}' is 0x{:08X}, the archive says 0x{:08X}", entry.name, crc.sum(), entry.crc32);
+        }
+
+        member.file = File::open(&member.path)?;
+        Ok(member)
+    }
+
+    /// Uncompressed contents of a small member (manifests)
+    fn read_member(&self, file: &mut dyn MediaSource, entry: &ZipEntry) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut data = Vec::new();
+        self.member_reader(file, entry)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Members with their local headers, the central directory and the end records
+    ///
+    /// Data descriptors after streamed members are not interpreted.
+    pub fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        let mut coverage = Coverage::new(file.byte_len()?);
+        for entry in &self.entries {
+            match self.data_start(file, entry) {
+                | Ok(data_start) => coverage.interpret(entry.local_header_offset, (data_start - entry.local_header_offset).saturating_add(entry.compressed_size)),
+                | Err(error) => coverage.skip(entry.local_header_offset, 30, error.to_string()),
+            }
+        }
+        coverage.interpret(self.directory_offset, coverage.file_size.saturating_sub(self.directory_offset));
+        Ok(coverage.finish())
+    }
+
+    /// File offset of the data of a member, after its local header
+    pub fn data_start(&self, file: &mut dyn MediaSource, entry: &ZipEntry) -> Result<u64, Box<dyn std::error::Error>> {
+        let mut header = [0u8; 30];
+        file.seek(SeekFrom::Start(entry.local_header_offset))?;
+        file.read_exact(&mut header)?;
+        if read_u32(&header, 0) != LOCAL_HEADER_SIGNATURE {
+            return Err(format!("no local header for '{}' at offset {}", entry.name, entry.local_header_offset).into());
+        }
+        // The local name and extra field may differ in length from the central directory copies
+        Ok(entry.local_header_offset + 30 + read_u16(&header, 26) as u64 + read_u16(&header, 28) as u64)
+    }
+
+    /// Reader for the uncompressed data of a member
+    fn member_reader<'a>(&self, file: &'a mut dyn MediaSource, entry: &ZipEntry) -> Result<Box<dyn Read + 'a>, Box<dyn std::error::Error>> {
+        let data_start = self.data_start(file, entry)?;
+        end_offset(format!("ZIP member '{}'", entry.name), data_start, entry.compressed_size, file.byte_len()?)?;
+        file.seek(SeekFrom::Start(data_start))?;
+
+        let compressed = file.take(entry.compressed_size);
+        match entry.method {
+            | METHOD_STORED => Ok(Box::new(compressed)),
+            | METHOD_DEFLATED => Ok(Box::new(DeflateDecoder::new(compressed))),
+            | method => Err(format!("'{}' uses the unsupported compression method {}", entry.name, method).into()),
+        }
+    }
+
+    fn detect_layout(&self, file: &mut dyn MediaSource) -> Result<BundleLayout, Box<dyn std::error::Error>> {
+        if let Some(mimetype) = self.entry("mimetype")
+            && self.read_member(file, mimetype)?.trim_ascii() == b"application/epub+zip"
+        {
+            return Ok(BundleLayout::Epub);
+        }
+        let layout = if self.entry("manifest.json").is_some() {
+            BundleLayout::ReadiumAudiobook
+        } else if self.entry("publication.json").is_some() {
+            BundleLayout::Lpf
+        } else if self.entries.iter().any(ZipEntry::is_audio) {
+            BundleLayout::AudioArchive
+        } else {
+            BundleLayout::Other
+        };
+        Ok(layout)
+    }
+
+    /// Audio members in the reading order of the Readium/LPF manifest, then the rest in archive order
+    fn reading_order(&self, file: &mut dyn MediaSource) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let manifest = match self.layout {
+            | BundleLayout::ReadiumAudiobook => self.entry("manifest.json"),
+            | BundleLayout::Lpf => self.entry("publication.json"),
+            | _ => None,
+        };
+        let mut order: Vec<String> = Vec::new();
+        if let Some(manifest) = manifest
+            && let Ok(json) = serde_json::from_slice::<serde_json::Value>(&self.read_member(file, manifest)?)
+            && let Some(items) = json.get("readingOrder").and_then(|items| items.as_array())
+        {
+            for href in items.iter().filter_map(|item| item.get("href").and_then(|href| href.as_str()).or_else(|| item.as_str())) {
+                let href = href.split('#').next().unwrap_or(href).trim_start_matches("./");
+                if self.entry(href).is_some_and(ZipEntry::is_audio) && !order.iter().any(|name| name == href) {
+                    order.push(href.to_string());
+                }
+            }
+        }
+        for entry in self.entries.iter().filter(|entry| entry.is_audio()) {
+            if !order.contains(&entry.name) {
+                order.push(entry.name.clone());
+            }
+        }
+        Ok(order)
+    }
+}
+
+/// Take the 64-bit values of a ZIP64 extra field for the fields set to 0xFFFFFFFF
+fn apply_zip64_extra(entry: &mut ZipEntry, extra: &[u8]) {
+    let mut pos = 0;
+    while pos + 4 <= extra.len() {
+        let id = read_u16(extra, pos);
+        let size = read_u16(extra, pos + 2) as usize;
+        let data = &extra[pos + 4..(pos + 4 + size).min(extra.len())];
+        if id == ZIP64_EXTRA_FIELD {
+            let mut field = 0;
+            for value in [&mut entry.size, &mut entry.compressed_size, &mut entry.local_header_offset] {
+                if *value == u32::MAX as u64 && field + 8 <= data.len() {
+                    *value = read_u64(data, field);
+                    field += 8;
+                }
+            }
+        }
+        pos += 4 + size;
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap_or_default())
+}
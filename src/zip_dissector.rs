@@ -0,0 +1,94 @@
+use crate::coverage::Coverage;
+use crate::media_dissector::{Capabilities, MediaDissector, Operation, Signature};
+use crate::media_source::MediaSource;
+use crate::media_summary::MediaSummary;
+use crate::options::DebugOptions;
+use crate::size_tree::SizeNode;
+use crate::zip_archive::ZipArchive;
+
+/// Dissector for ZIP-based bundles (EPUB, Readium audiobooks, album deliveries)
+///
+/// Lists the audio members; `--inner <NAME>` dissects one of them instead of the archive.
+pub struct ZipDissector;
+
+impl MediaDissector for ZipDissector {
+    fn media_type(&self) -> &'static str {
+        "ZIP"
+    }
+
+    fn dissect_with_options(&self, file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+        dissect_zip_with_options(file, options)
+    }
+
+    fn can_handle(&self, header: &[u8]) -> bool {
+        // Local file header, or the end of central directory record of an empty archive
+        header.len() >= 4 && (header[0..4] == [0x50, 0x4B, 0x03, 0x04] || header[0..4] == [0x50, 0x4B, 0x05, 0x06])
+    }
+
+    fn name(&self) -> &'static str {
+        "ZIP Bundle Dissector"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            extensions: &["zip", "epub", "audiobook", "lpf"],
+            signatures: &[
+                Signature { offset: 0, magic: b"PK\x03\x04", description: "local file header" },
+                Signature { offset: 0, magic: b"PK\x05\x06", description: "end of central directory of an empty archive" },
+            ],
+            operations: &[Operation::Read, Operation::Validate],
+        }
+    }
+
+    fn coverage(&self, file: &mut dyn MediaSource) -> Result<Coverage, Box<dyn std::error::Error>> {
+        ZipArchive::read(file)?.coverage(file)
+    }
+
+    fn size_tree(&self, file: &mut dyn MediaSource) -> Result<SizeNode, Box<dyn std::error::Error>> {
+        let archive = ZipArchive::read(file)?;
+        let file_size = file.byte_len()?;
+        let mut children: Vec<SizeNode> = archive.entries.iter().map(|entry| SizeNode::new(format!("{} (compressed)", entry.name), entry.compressed_size)).collect();
+        children.push(SizeNode::new("central directory", file_size.saturating_sub(archive.directory_offset)));
+        Ok(SizeNode::with_children("file", file_size, children, "local headers and other"))
+    }
+
+    fn summarize(&self, file: &mut dyn MediaSource) -> Result<MediaSummary, Box<dyn std::error::Error>> {
+        let mut summary = MediaSummary::new(self.media_type(), self.name());
+        summary.bundle = Some(ZipArchive::read(file)?);
+        Ok(summary)
+    }
+}
+
+pub fn dissect_zip_with_options(file: &mut dyn MediaSource, options: &DebugOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let archive = ZipArchive::read(file)?;
+
+    if options.show_header {
+        outln!("\nZIP Archive:");
+        outln!("  Layout: {}", archive.layout.description());
+        outln!("  Members: {}", archive.entries.len());
+        if archive.zip64 {
+            outln!("  ZIP64: yes");
+        }
+    }
+
+    if !options.show_frames {
+        return Ok(());
+    }
+
+    outln!("\nAudio Files:");
+    if archive.audio.is_empty() {
+        outln!("  None");
+        return Ok(());
+    }
+    for name in &archive.audio {
+        if let Some(entry) = archive.entry(name) {
+            outln!("  {} ({} bytes, {})", entry.name, entry.size, entry.method_name());
+        }
+    }
+    let other = archive.entries.len() - archive.audio.len();
+    if other > 0 {
+        outln!("  ({} other member(s))", other);
+    }
+    outln!("\nUse --inner <NAME> to dissect one of the audio files");
+    Ok(())
+}